@@ -0,0 +1,180 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use clap::parser::ValueSource;
+use clap::{Arg, ArgMatches, Command, CommandFactory};
+
+use crate::error::Report;
+use crate::Cli;
+
+/// The environment variable with default flags, e.g., `--no-color --log-level info`.
+const FLAGS_VAR: &str = "RYAN_FLAGS";
+
+/// The file with default flags, looked up in the current directory and then in each of
+/// its parents. It is Ryan code evaluating to a dictionary of option names to values,
+/// e.g., `{ no_color: true, log_level: "info" }`.
+const RC_FILE: &str = ".ryanrc";
+
+/// The command line, with the default flags in front of the ones given explicitly. An
+/// option given explicitly overrides its default from `RYAN_FLAGS`, which overrides its
+/// default from `.ryanrc`. Also returns the warnings found while reading the defaults.
+pub fn args() -> anyhow::Result<(Vec<OsString>, Vec<String>)> {
+    let explicit = std::env::args_os().collect::<Vec<_>>();
+    let flags = std::env::var(FLAGS_VAR).ok();
+    let rc_file = find_rc_file(&std::env::current_dir()?);
+
+    with_defaults(explicit, flags.as_deref(), rc_file.as_deref())
+}
+
+fn with_defaults(
+    explicit: Vec<OsString>,
+    flags: Option<&str>,
+    rc_file: Option<&Path>,
+) -> anyhow::Result<(Vec<OsString>, Vec<String>)> {
+    let mut warnings = vec![];
+    let mut layers = vec![];
+
+    if let Some(flags) = flags {
+        let tokens = flags.split_whitespace().map(str::to_owned).collect();
+        layers.push(parse_layer(tokens, FLAGS_VAR, &mut warnings));
+    }
+
+    if let Some(path) = rc_file {
+        let source = path.display().to_string();
+        let tokens = read_rc_file(path, &source, &mut warnings)?;
+        layers.push(parse_layer(tokens, &source, &mut warnings));
+    }
+
+    let explicit_matches = Cli::command()
+        .disable_help_flag(true)
+        .disable_version_flag(true)
+        .ignore_errors(true)
+        .get_matches_from(&explicit);
+    let mut given = options()
+        .into_iter()
+        .filter(|arg| {
+            explicit_matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+        })
+        .map(|arg| arg.get_id().to_string())
+        .collect::<Vec<_>>();
+
+    let mut args = explicit[..1].to_vec();
+    for matches in layers {
+        for arg in options() {
+            let id = arg.get_id().as_str();
+            if matches.value_source(id) != Some(ValueSource::CommandLine)
+                || given.iter().any(|given| given == id)
+            {
+                continue;
+            }
+
+            let long = arg.get_long().expect("every option has a long name");
+            if arg.get_action().takes_values() {
+                for value in matches.get_raw(id).into_iter().flatten() {
+                    args.push(format!("--{long}={}", value.to_string_lossy()).into());
+                }
+            } else {
+                args.push(format!("--{long}").into());
+            }
+
+            given.push(id.to_owned());
+        }
+    }
+    args.extend_from_slice(&explicit[1..]);
+
+    Ok((args, warnings))
+}
+
+/// The options of the CLI, i.e., every argument but the file.
+fn options() -> Vec<Arg> {
+    Cli::command()
+        .get_arguments()
+        .filter(|arg| !arg.is_positional())
+        .cloned()
+        .collect()
+}
+
+/// Parses the flags of a source of defaults. Flags that are not understood are reported
+/// as warnings and left out.
+fn parse_layer(tokens: Vec<String>, source: &str, warnings: &mut Vec<String>) -> ArgMatches {
+    // The file is not a default, but other options refer to it:
+    let command = Command::new("ryan")
+        .no_binary_name(true)
+        .disable_help_flag(true)
+        .disable_version_flag(true)
+        .args(options())
+        .arg(Arg::new("file"));
+
+    if let Err(err) = command.clone().try_get_matches_from(&tokens) {
+        let message = err.to_string();
+        let message = message.lines().next().unwrap_or_default();
+        warnings.push(format!(
+            "Ignoring some default flags in {source}: {}",
+            message.trim_start_matches("error: ")
+        ));
+    }
+
+    command.ignore_errors(true).get_matches_from(tokens)
+}
+
+/// Finds the closest `.ryanrc`, starting from `dir` and going up.
+fn find_rc_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(RC_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Evaluates a `.ryanrc` hermetically and turns it into flags, e.g., `log_level: "info"`
+/// into `--log-level=info`. Options that do not exist and values that make no sense for
+/// an option are reported as warnings and left out.
+fn read_rc_file(
+    path: &Path,
+    source: &str,
+    warnings: &mut Vec<String>,
+) -> anyhow::Result<Vec<String>> {
+    let bytes = std::fs::read(path)
+        .map_err(|err| Report::new(ryan::Error::Io(err), Some(source), ""))
+        .with_context(|| format!("Failed to read the default flags in {source}"))?;
+    let code = ryan::decode_text(&bytes)
+        .map_err(|err| Report::new(ryan::Error::Encoding(err), Some(source), ""))
+        .with_context(|| format!("Failed to read the default flags in {source}"))?;
+    let defaults: serde_json::Map<String, serde_json::Value> = ryan::from_str_hermetic(&code)
+        .map_err(|err| Report::new(err, Some(source), &code))
+        .with_context(|| format!("Failed to read the default flags in {source}"))?;
+
+    let mut tokens = vec![];
+    for (key, value) in defaults {
+        let long = key.replace('_', "-");
+        let Some(arg) = options()
+            .into_iter()
+            .find(|arg| arg.get_long() == Some(long.as_str()))
+        else {
+            warnings.push(format!("Unknown option `{key}` in {source}"));
+            continue;
+        };
+
+        let values = match value {
+            serde_json::Value::Array(values) => values,
+            value => vec![value],
+        };
+
+        for value in values {
+            match (value, arg.get_action().takes_values()) {
+                (serde_json::Value::Bool(true), false) => tokens.push(format!("--{long}")),
+                (serde_json::Value::Bool(false) | serde_json::Value::Null, _) => {}
+                (serde_json::Value::String(value), true) => {
+                    tokens.push(format!("--{long}={value}"))
+                }
+                (serde_json::Value::Number(value), true) => {
+                    tokens.push(format!("--{long}={value}"))
+                }
+                (value, _) => warnings.push(format!(
+                    "Option `{key}` in {source} cannot be set to {value}"
+                )),
+            }
+        }
+    }
+
+    Ok(tokens)
+}
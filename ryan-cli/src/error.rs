@@ -0,0 +1,96 @@
+use std::fmt::{self, Display};
+
+use clap::ValueEnum;
+
+/// How errors are reported to `stderr`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// Human-readable text, with the offending code underlined.
+    Text,
+    /// A JSON array of error objects.
+    Json,
+}
+
+/// A Ryan error, rendered while the code it refers to is still at hand. Unlike
+/// [`ryan::Error`], this can be carried around by [`anyhow::Error`].
+#[derive(Debug)]
+pub struct Report {
+    text: String,
+    json: serde_json::Value,
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl std::error::Error for Report {}
+
+impl Report {
+    /// Renders an error that happened while running some code, read from a file, if any.
+    pub fn new(err: ryan::Error, file: Option<&str>, code: &str) -> Report {
+        let entries = match &err {
+            ryan::Error::Parse(err) => err
+                .entries()
+                .iter()
+                .map(|error| {
+                    let (line, column) = error.position(code);
+                    let mut entry = entry("parse", error.error.clone(), file);
+                    entry["line"] = line.into();
+                    entry["column"] = column.into();
+                    entry["span"] = serde_json::json!([error.span.0, error.span.1]);
+                    entry
+                })
+                .collect(),
+            ryan::Error::Eval(err) => {
+                let mut entry = entry(err.kind().name(), err.kind().to_string(), file);
+                entry["context"] = err
+                    .context()
+                    .iter()
+                    .map(|context| context.to_string())
+                    .collect();
+                vec![entry]
+            }
+            ryan::Error::Io(err) => vec![entry("io", err.to_string(), file)],
+            ryan::Error::Encoding(err) => {
+                let mut entry = entry("encoding", err.to_string(), file);
+                entry["line"] = err.line.into();
+                entry["column"] = err.column.into();
+                vec![entry]
+            }
+            ryan::Error::DecodeError(err) => vec![entry("decode", err.to_string(), file)],
+            ryan::Error::NotRepresentable(err) => {
+                vec![entry("not_representable", err.to_string(), file)]
+            }
+        };
+
+        Report {
+            text: err.to_string(),
+            json: serde_json::Value::Array(entries),
+        }
+    }
+}
+
+/// An error object with the fields `kind`, `message`, `file`, `line`, `column`, `span`
+/// and `context`. Lines and columns start at one and spans are byte offsets into the
+/// code. Fields that do not apply are `null` (or empty, for the context).
+fn entry(kind: &str, message: String, file: Option<&str>) -> serde_json::Value {
+    serde_json::json!({
+        "kind": kind,
+        "message": message,
+        "file": file,
+        "line": null,
+        "column": null,
+        "span": null,
+        "context": [],
+    })
+}
+
+/// Converts any error into a JSON array of error objects.
+pub fn to_json(err: &anyhow::Error) -> serde_json::Value {
+    match err.downcast_ref::<Report>() {
+        Some(report) => report.json.clone(),
+        None => serde_json::Value::Array(vec![entry("other", err.to_string(), None)]),
+    }
+}
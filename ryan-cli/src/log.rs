@@ -0,0 +1,184 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::io::Write;
+use std::rc::Rc;
+use std::time::Instant;
+
+use clap::ValueEnum;
+use ryan::environment::EvalEvent;
+
+/// How much is logged to `stderr`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum LogLevel {
+    /// Log nothing.
+    Off,
+    /// Log only warnings.
+    Warn,
+    /// Log warnings and imports being loaded.
+    Info,
+    /// Log everything, including cache hits.
+    Debug,
+    /// Log everything, and also each step of the evaluation: variables bound, rules of
+    /// pattern matches tried and branches taken. Same as `--trace`.
+    Trace,
+}
+
+/// How each log line is formatted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines.
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+/// Logs [`EvalEvent`]s to `stderr`.
+pub struct Logger {
+    level: LogLevel,
+    format: LogFormat,
+    started: RefCell<HashMap<Rc<str>, Instant>>,
+    warnings: Cell<usize>,
+}
+
+impl Logger {
+    pub fn new(level: LogLevel, format: LogFormat) -> Logger {
+        Logger {
+            level,
+            format,
+            started: RefCell::default(),
+            warnings: Cell::new(0),
+        }
+    }
+
+    /// How many warnings were logged so far, including the ones not printed because of
+    /// the log level.
+    pub fn warnings(&self) -> usize {
+        self.warnings.get()
+    }
+
+    /// Whether this logger will ever print anything.
+    pub fn is_off(&self) -> bool {
+        self.level == LogLevel::Off
+    }
+
+    pub fn log(&self, event: EvalEvent) {
+        if let EvalEvent::Warning { .. } = &event {
+            self.warnings.set(self.warnings.get() + 1);
+        }
+
+        let (level, name, mut fields) = match &event {
+            EvalEvent::ImportStarted { path, format } => {
+                self.started
+                    .borrow_mut()
+                    .insert(path.clone(), Instant::now());
+                (
+                    LogLevel::Debug,
+                    "import_started",
+                    serde_json::json!({ "path": &**path, "format": format.to_string() }),
+                )
+            }
+            EvalEvent::ImportLoaded {
+                path,
+                format,
+                cache_hit,
+            } => {
+                let level = if *cache_hit {
+                    LogLevel::Debug
+                } else {
+                    LogLevel::Info
+                };
+                let mut fields = serde_json::json!({
+                    "path": &**path,
+                    "format": format.to_string(),
+                    "cache_hit": cache_hit,
+                });
+                self.time_import(path, &mut fields);
+                (level, "import_loaded", fields)
+            }
+            EvalEvent::ImportFailed {
+                path,
+                format,
+                error,
+            } => {
+                let mut fields = serde_json::json!({
+                    "path": &**path,
+                    "format": format.to_string(),
+                    "error": &**error,
+                });
+                self.time_import(path, &mut fields);
+                (LogLevel::Debug, "import_failed", fields)
+            }
+            EvalEvent::Warning { message } => (
+                LogLevel::Warn,
+                "warning",
+                serde_json::json!({ "message": &**message }),
+            ),
+            EvalEvent::BindingEvaluated {
+                name,
+                value_summary,
+            } => (
+                LogLevel::Trace,
+                "binding_evaluated",
+                serde_json::json!({ "name": &**name, "value_summary": &**value_summary }),
+            ),
+            EvalEvent::PatternRuleTried {
+                name,
+                rule_index,
+                matched,
+            } => (
+                LogLevel::Trace,
+                "pattern_rule_tried",
+                serde_json::json!({
+                    "name": &**name,
+                    "rule_index": rule_index,
+                    "matched": matched,
+                }),
+            ),
+            EvalEvent::ConditionalTaken { branch } => (
+                LogLevel::Trace,
+                "conditional_taken",
+                serde_json::json!({ "branch": branch.to_string() }),
+            ),
+        };
+
+        if level > self.level {
+            return;
+        }
+
+        let line = match self.format {
+            LogFormat::Text => {
+                let mut line = format!("[{}] {event}", level_name(level));
+                if let Some(duration) = fields.get("duration_ms").and_then(|d| d.as_f64()) {
+                    line += &format!(" in {duration:.3}ms");
+                }
+                line
+            }
+            LogFormat::Json => {
+                fields["level"] = level_name(level).into();
+                fields["event"] = name.into();
+                fields.to_string()
+            }
+        };
+
+        // Logging is best-effort: there is nothing sensible to do if stderr is gone.
+        let _ = writeln!(std::io::stderr().lock(), "{line}");
+    }
+
+    /// Adds how long an import took to the fields of the event ending it, successfully or
+    /// not. The import is forgotten either way, so that a retry is timed on its own.
+    fn time_import(&self, path: &str, fields: &mut serde_json::Value) {
+        if let Some(started) = self.started.borrow_mut().remove(path) {
+            fields["duration_ms"] = (started.elapsed().as_secs_f64() * 1e3).into();
+        }
+    }
+}
+
+fn level_name(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Off => "off",
+        LogLevel::Warn => "warn",
+        LogLevel::Info => "info",
+        LogLevel::Debug => "debug",
+        LogLevel::Trace => "trace",
+    }
+}
@@ -1,45 +1,453 @@
-use std::io::Write;
+mod defaults;
+mod error;
+mod log;
+mod repl;
+mod testing;
 
-use clap::Parser;
+use std::io::{Read, Write};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use clap::{CommandFactory, Parser, Subcommand};
+use ryan::environment::{DefaultImporter, EvalEvent, ImportLoader};
+use ryan::parser::{PathSeg, Value};
 use termcolor::{ColorChoice, StandardStream};
 
+use crate::error::{ErrorFormat, Report};
+use crate::log::{LogFormat, LogLevel, Logger};
+
 /// The Ryan configuration language CLI.
+///
+/// Default flags are read from the `RYAN_FLAGS` environment variable, e.g.,
+/// `--no-color --log-level info`, and from the closest `.ryanrc` file, e.g.,
+/// `{ no_color: true, log_level: "info" }`, which is evaluated hermetically. Flags given
+/// explicitly override `RYAN_FLAGS`, which overrides `.ryanrc`.
 #[derive(Debug, Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 pub struct Cli {
+    #[command(subcommand)]
+    subcommand: Option<Commands>,
     /// If set, will interpret the FILE not as a filename, but as actual Ryan code.
     #[clap(long, short)]
     command: bool,
     /// The name of the file to be executed. Pass `-` to read from standard input.
-    file: String,
-    /// Hermetic mode: disables all imports.
+    #[clap(required_unless_present_any = ["repl", "eval_many", "test"])]
+    file: Option<String>,
+    /// Hermetic mode: disables all imports and effectful built-ins.
     #[clap(long)]
     hermetic: bool,
+    /// Strict mode: a program or module without a final expression is an error instead
+    /// of evaluating to `null`.
+    #[clap(long)]
+    strict: bool,
+    /// Warns about every `env:` import that used its default because the environment
+    /// variable is not set. A misspelled variable is never set, after all.
+    #[clap(long)]
+    warn_missing_env: bool,
+    /// Makes the `env:` imports of these environment variables fail if they are not set,
+    /// even if the imports have a default, e.g., `--require-env DATABASE_URL,API_KEY`.
+    #[clap(long, value_delimiter = ',', value_name = "VARS")]
+    require_env: Vec<String>,
     /// Disables fancy color output. This app detects `tty`s, so you don't need to
     /// worry about setting this option when piping.
     #[clap(long)]
     no_color: bool,
+    /// How much to log to standard error while evaluating. Warnings are shown at `warn`
+    /// and above.
+    #[clap(long, value_enum, default_value_t = LogLevel::Warn)]
+    log_level: LogLevel,
+    /// The format of the lines logged to standard error.
+    #[clap(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+    /// The format of the errors reported to standard error. In `json`, errors are
+    /// reported as an array of objects with the fields `kind`, `message`, `file`, `line`,
+    /// `column`, `span` and `context`.
+    #[clap(long, value_enum, default_value_t = ErrorFormat::Text)]
+    error_format: ErrorFormat,
+    /// Silences all logging to standard error. Same as `--log-level off`.
+    #[clap(long, short)]
+    quiet: bool,
+    /// Logs each step of the evaluation to standard error, in order: the variables bound,
+    /// the rules of pattern matches tried, the branches of `if`s taken and the modules
+    /// imported. Same as `--log-level trace`.
+    #[clap(long, conflicts_with = "quiet")]
+    trace: bool,
+    /// Sorts the keys of every dictionary in the output, byte by byte.
+    #[clap(long)]
+    sort_keys: bool,
+    /// Sorts every list of dictionaries in the output by the value under this key. Use
+    /// dots for nested keys, e.g., `metadata.name`. Dictionaries without the key are kept
+    /// at the end of the list and reported as a warning.
+    #[clap(long, value_name = "KEY")]
+    sort_lists_by: Option<String>,
+    /// Fails if any warnings are found, e.g., unused variables or duplicate keys.
+    #[clap(long)]
+    deny_warnings: bool,
+    /// Only reports the warnings found in the code, e.g., unused variables or imports,
+    /// without evaluating it.
+    #[clap(long, conflicts_with_all = ["digest", "fmt", "fmt_check", "repl"])]
+    lint: bool,
+    /// Prints the size and shape of the evaluated value to standard error, e.g., the
+    /// number of values and how deeply they are nested.
+    #[clap(long)]
+    stats: bool,
+    /// Prints to standard error how long parsing and evaluating took, how many modules
+    /// were imported (and how many came from the cache) and how many values were
+    /// computed.
+    #[clap(long)]
+    timings: bool,
+    /// Prints to standard error where the value under this key of the output comes from:
+    /// the module and the binding that produced it. Use dots for nested keys and indices,
+    /// e.g., `servers.0.host`.
+    #[clap(long, value_name = "KEY")]
+    explain: Option<String>,
+    /// Prints the digest of the file, to be pinned in the `integrity` clause of its
+    /// imports, instead of evaluating it.
+    #[clap(long, conflicts_with_all = ["command", "fmt", "fmt_check", "repl"])]
+    digest: bool,
+    /// Prints the code formatted in the canonical style instead of evaluating it.
+    #[clap(long, conflicts_with = "fmt_check")]
+    fmt: bool,
+    /// Fails if the code is not formatted in the canonical style, without evaluating it.
+    #[clap(long)]
+    fmt_check: bool,
+    /// Reads many Ryan documents from standard input, separated by lines containing only
+    /// `---`, and evaluates each of them in order, printing each result as it comes.
+    /// Imported modules are loaded only once for all documents.
+    #[clap(long, conflicts_with_all = ["file", "digest", "fmt", "fmt_check", "lint", "repl"])]
+    eval_many: bool,
+    /// Prints each element of the resulting list as compact JSON in its own line, also
+    /// known as NDJSON. The result must be a list.
+    #[clap(long)]
+    ndjson: bool,
+    /// Opens an interactive prompt, where the bindings of each input are available to
+    /// the next ones.
+    #[clap(long, conflicts_with_all = ["file", "fmt", "fmt_check"])]
+    repl: bool,
+    /// Runs the tests in every `*.test.ryan` file under this directory (or in this
+    /// single file) and prints a summary. A test file evaluates to
+    /// `{ tests: { name: bool, ... } }` and each `false` is a failed test, as is each file
+    /// that fails to run.
+    #[clap(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["file", "digest", "fmt", "fmt_check", "lint", "repl", "eval_many"]
+    )]
+    test: Option<String>,
+    /// Lets test files import environment variables and the standard input. By default,
+    /// they can only import files, so that tests give the same results everywhere.
+    #[clap(long, requires = "test")]
+    allow_imports: bool,
+    /// The warnings found while reading the default flags.
+    #[clap(skip)]
+    default_warnings: Vec<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// Prints the script that completes the arguments of `ryan` in a shell, e.g.,
+    /// `ryan completions bash > /etc/bash_completion.d/ryan`.
+    Completions {
+        /// The shell to complete in.
+        shell: clap_complete::Shell,
+    },
 }
 
 fn main() -> Result<(), anyhow::Error> {
-    let cli = Cli::parse();
+    let (args, default_warnings) = defaults::args()?;
+    let cli = Cli {
+        default_warnings,
+        ..Cli::parse_from(args)
+    };
+    let error_format = cli.error_format;
+
+    match run(cli) {
+        Err(err) if error_format == ErrorFormat::Json => {
+            eprintln!("{}", error::to_json(&err));
+            std::process::exit(1);
+        }
+        result => result,
+    }
+}
+
+fn run(cli: Cli) -> Result<(), anyhow::Error> {
+    if let Some(Commands::Completions { shell }) = cli.subcommand {
+        clap_complete::generate(shell, &mut Cli::command(), "ryan", &mut std::io::stdout());
+        return Ok(());
+    }
 
     // Config:
-    let env = if cli.hermetic {
-        ryan::Environment::builder()
-            .import_loader(ryan::environment::NoImport)
-            .build()
+    let mut builder = ryan::Environment::builder();
+
+    if cli.hermetic {
+        builder = builder.hermetic();
+    }
+
+    if cli.strict {
+        builder = builder.strict(true);
+    }
+
+    if cli.warn_missing_env {
+        builder = builder.warn_missing_env(true);
+    }
+
+    builder = builder.require_env(&cli.require_env);
+
+    if cli.explain.is_some() {
+        builder = builder.track_provenance(true);
+    }
+
+    let log_level = if cli.quiet {
+        LogLevel::Off
+    } else if cli.trace {
+        LogLevel::Trace
     } else {
-        ryan::Environment::builder().build()
+        cli.log_level
     };
+    builder = builder.trace(log_level == LogLevel::Trace);
+    let logger = Rc::new(Logger::new(log_level, cli.log_format));
+    for message in &cli.default_warnings {
+        logger.log(EvalEvent::Warning {
+            message: message.as_str().into(),
+        });
+    }
+    // Warnings are counted even when they are not printed, for `--deny-warnings`:
+    if !logger.is_off() || cli.deny_warnings {
+        let logger = logger.clone();
+        builder = builder.on_event(move |event| logger.log(event));
+    }
 
-    // Eval:
-    let output: serde_json::Value = match (cli.command, cli.file.as_str()) {
-        (false, "-") => ryan::from_reader_with_env(&env, std::io::stdin().lock())?,
-        (false, path) => ryan::from_path_with_env(&env, path)?,
-        (true, code) => ryan::from_str_with_env(&env, code)?,
+    if cli.repl {
+        return repl::run(builder.build());
+    }
+
+    if let Some(path) = &cli.test {
+        return testing::run(builder, path, cli.allow_imports);
+    }
+
+    if cli.eval_many {
+        builder = builder.import_loader(stdin_taken());
+        let mut bytes = vec![];
+        std::io::stdin()
+            .lock()
+            .read_to_end(&mut bytes)
+            .map_err(|err| Report::new(ryan::Error::Io(err), None, ""))?;
+        let stream = decode(None, &bytes)?;
+
+        let env = builder.build();
+        let mut parse_time = Duration::ZERO;
+        let evaluated = ryan::documents(&stream)
+            .enumerate()
+            .try_for_each(|(idx, document)| {
+                let name = format!("document {}", idx + 1);
+                evaluate(&cli, &env, &logger, Some(&name), document, &mut parse_time)
+                    .and_then(|value| print(&cli, Some(&name), document, value))
+                    .with_context(|| format!("Failed to run {name} from the standard input"))
+            });
+
+        if cli.timings {
+            let mut metrics = env.metrics();
+            metrics.parse_time += parse_time;
+            eprintln!("{metrics}");
+        }
+
+        return evaluated;
+    }
+
+    let file = cli
+        .file
+        .clone()
+        .expect("clap requires a file unless in the REPL, testing or evaluating many documents");
+    let name = (!cli.command && file != "-").then_some(file.as_str());
+
+    // Digest:
+    if cli.digest {
+        let bytes = if file == "-" {
+            let mut bytes = vec![];
+            std::io::stdin()
+                .lock()
+                .read_to_end(&mut bytes)
+                .map_err(|err| Report::new(ryan::Error::Io(err), name, ""))?;
+            bytes
+        } else {
+            std::fs::read(&file).map_err(|err| Report::new(ryan::Error::Io(err), name, ""))?
+        };
+        println!("{}", ryan::environment::digest(&bytes));
+        return Ok(());
+    }
+
+    let code = match (cli.command, file.as_str()) {
+        (false, "-") => {
+            builder = builder.import_loader(stdin_taken());
+            let mut bytes = vec![];
+            std::io::stdin()
+                .lock()
+                .read_to_end(&mut bytes)
+                .map_err(|err| Report::new(ryan::Error::Io(err), name, ""))?;
+            decode(name, &bytes)?
+        }
+        (false, path) => {
+            builder = builder.module(path);
+            let bytes =
+                std::fs::read(path).map_err(|err| Report::new(ryan::Error::Io(err), name, ""))?;
+            decode(name, &bytes)?
+        }
+        (true, code) => code.to_owned(),
     };
 
+    // Format:
+    if cli.fmt {
+        let formatted = ryan::fmt::format(&code)
+            .map_err(|err| Report::new(ryan::Error::Parse(err), name, &code))?;
+        std::io::stdout().lock().write_all(formatted.as_bytes())?;
+        return Ok(());
+    }
+
+    if cli.fmt_check {
+        if !ryan::fmt::is_formatted(&code)
+            .map_err(|err| Report::new(ryan::Error::Parse(err), name, &code))?
+        {
+            anyhow::bail!("{file} is not formatted; run `ryan --fmt` on it");
+        }
+        return Ok(());
+    }
+
+    // Lint:
+    if cli.lint {
+        let warnings = ryan::lint(&code).map_err(|err| Report::new(err, name, &code))?;
+        for warning in &warnings {
+            logger.log(EvalEvent::Warning {
+                message: warning.to_string_with(&code).into(),
+            });
+        }
+
+        if cli.deny_warnings && !warnings.is_empty() {
+            anyhow::bail!(
+                "Found {} warning(s) and `--deny-warnings` is set",
+                warnings.len()
+            );
+        }
+        return Ok(());
+    }
+
+    let env = builder.build();
+    let mut parse_time = Duration::ZERO;
+    let evaluated = evaluate(&cli, &env, &logger, name, &code, &mut parse_time);
+
+    if cli.timings {
+        let mut metrics = env.metrics();
+        metrics.parse_time += parse_time;
+        eprintln!("{metrics}");
+    }
+
+    print(&cli, name, &code, evaluated?)
+}
+
+/// The default import loader, except for `stdin:`, for when the standard input holds the
+/// program itself.
+fn stdin_taken() -> impl ImportLoader {
+    DefaultImporter.r#block("stdin:".to_owned())
+}
+
+/// Decodes the code of a Ryan file, which is UTF-8 or, with a byte order mark, UTF-16.
+fn decode(name: Option<&str>, bytes: &[u8]) -> Result<String, Report> {
+    ryan::decode_text(bytes)
+        .map(|code| code.into_owned())
+        .map_err(|mut err| {
+            err.path = name.map(str::to_owned);
+            Report::new(ryan::Error::Encoding(err), name, "")
+        })
+}
+
+/// Evaluates a program, logging its warnings, and returns the canonicalized result. The
+/// time spent parsing is added to `parse_time`, since it is not counted in the metrics of
+/// the environment.
+fn evaluate(
+    cli: &Cli,
+    env: &ryan::Environment,
+    logger: &Logger,
+    name: Option<&str>,
+    code: &str,
+    parse_time: &mut Duration,
+) -> Result<Value, anyhow::Error> {
+    // Eval:
+    let warnings_before = logger.warnings();
+    let parse_start = Instant::now();
+    let (parsed, warnings) = ryan::parser::parse_with_warnings(code)
+        .map_err(|err| Report::new(ryan::Error::Parse(err), name, code))?;
+    *parse_time += parse_start.elapsed();
+    for warning in warnings {
+        logger.log(EvalEvent::Warning {
+            message: warning.to_string_with(code).into(),
+        });
+    }
+
+    let value = ryan::parser::eval(env.clone(), &parsed)
+        .map_err(|err| Report::new(ryan::Error::Eval(err), name, code))?;
+
+    if cli.stats {
+        eprintln!("{}", value.stats());
+    }
+
+    if let Some(key_path) = &cli.explain {
+        eprintln!("{}", explain(env, &value, key_path)?);
+    }
+
+    // Canonicalize:
+    let canonicalized = value.canonicalize(ryan::CanonicalizeOptions {
+        sort_keys: cli.sort_keys,
+        sort_lists_by: cli
+            .sort_lists_by
+            .as_ref()
+            .map(|key_path| key_path.split('.').map(Into::into).collect()),
+    });
+    for message in canonicalized.warnings {
+        logger.log(EvalEvent::Warning {
+            message: message.into(),
+        });
+    }
+
+    // Warnings raised while evaluating reach the logger through the environment:
+    let warning_count = logger.warnings() - warnings_before;
+    if cli.deny_warnings && warning_count > 0 {
+        anyhow::bail!("Found {warning_count} warning(s) and `--deny-warnings` is set");
+    }
+
+    Ok(canonicalized.value)
+}
+
+/// Prints the result of a program to standard output, as colored JSON or as NDJSON.
+fn print(cli: &Cli, name: Option<&str>, code: &str, value: Value) -> Result<(), anyhow::Error> {
+    value
+        .check_representable()
+        .map_err(|err| Report::new(ryan::Error::NotRepresentable(err), name, code))?;
+
+    if cli.ndjson {
+        let Value::List(list) = &value else {
+            anyhow::bail!(
+                "The result must be a list to be printed with `--ndjson`, got a value of type \
+                {}",
+                value.canonical_type()
+            );
+        };
+
+        let mut stdout = std::io::stdout().lock();
+        for item in list.iter() {
+            let output: serde_json::Value = item
+                .decode()
+                .map_err(|err| Report::new(ryan::Error::DecodeError(err), name, code))?;
+            serde_json::to_writer(&mut stdout, &output)?;
+            stdout.write_all(b"\n")?;
+        }
+
+        return Ok(());
+    }
+
+    let output: serde_json::Value = value
+        .decode()
+        .map_err(|err| Report::new(ryan::Error::DecodeError(err), name, code))?;
+
     // Print:
     let stdout = StandardStream::stdout(if cli.no_color || atty::isnt(atty::Stream::Stdout) {
         ColorChoice::Never
@@ -51,3 +459,27 @@ fn main() -> Result<(), anyhow::Error> {
 
     Ok(())
 }
+
+/// Describes where the value under a dotted key path comes from.
+fn explain(env: &ryan::Environment, value: &Value, key_path: &str) -> anyhow::Result<String> {
+    let mut path = vec![];
+    let mut current = value.clone();
+
+    for segment in key_path.split('.') {
+        let (seg, next) = match (&current, segment.parse::<usize>()) {
+            (Value::List(list), Ok(idx)) => (PathSeg::Index(idx), list.get(idx).cloned()),
+            (Value::Map(map), _) => (PathSeg::Key(segment.into()), map.get(segment).cloned()),
+            _ => (PathSeg::Key(segment.into()), None),
+        };
+        match next {
+            Some(next) => current = next,
+            None => anyhow::bail!("Key `{key_path}` not found in the output"),
+        }
+        path.push(seg);
+    }
+
+    match env.provenance(value).get(&path) {
+        Some(origin) => Ok(format!("{key_path}: {origin}")),
+        None => anyhow::bail!("Could not find out where `{key_path}` comes from"),
+    }
+}
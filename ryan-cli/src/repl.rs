@@ -0,0 +1,98 @@
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+use ryan::parser::Session;
+use ryan::Environment;
+
+const PROMPT: &str = "ryan> ";
+const CONTINUATION_PROMPT: &str = "  ... ";
+
+/// Runs an interactive prompt, where each input is evaluated as a Ryan block and the
+/// bindings defined so far are available to the next inputs.
+pub fn run(env: Environment) -> Result<(), anyhow::Error> {
+    let mut editor = DefaultEditor::new()?;
+    let mut session = Session::new(env);
+    let mut input = String::new();
+
+    println!("Evaluate Ryan code, see its type with `:type <code>` or leave with `:quit`.");
+
+    loop {
+        let prompt = if input.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+
+        match editor.readline(prompt) {
+            Ok(line) => {
+                input += &line;
+                input += "\n";
+            }
+            // Cancels the current input, but not the session:
+            Err(ReadlineError::Interrupted) => {
+                input.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err.into()),
+        }
+
+        if is_incomplete(&input) {
+            continue;
+        }
+
+        let code = std::mem::take(&mut input);
+        let code = code.trim();
+        if code.is_empty() {
+            continue;
+        }
+
+        editor.add_history_entry(code)?;
+
+        if code == ":quit" || code == ":q" {
+            break;
+        } else if let Some(expression) = code.strip_prefix(":type ") {
+            match session.peek(expression) {
+                Ok(value) => println!("{}", value.canonical_type()),
+                Err(err) => eprintln!("{err}"),
+            }
+        } else {
+            match session.eval_line(code) {
+                // Only bindings, e.g., `let x = 1;`:
+                Ok(_) if code.ends_with(';') => {}
+                Ok(value) => println!("{value}"),
+                Err(err) => eprintln!("{err}"),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the input still has open brackets or an open template string, in which case
+/// more lines are needed to evaluate it.
+fn is_incomplete(code: &str) -> bool {
+    let mut depth = 0i64;
+    let mut quote = None;
+    let mut chars = code.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match (quote, ch) {
+            (Some(_), '\\') => {
+                chars.next();
+            }
+            (Some(open), ch) if ch == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '`') => quote = Some(ch),
+            (None, '/') if chars.peek() == Some(&'/') => {
+                chars.by_ref().find(|&ch| ch == '\n');
+            }
+            (None, '(' | '[' | '{') => depth += 1,
+            (None, ')' | ']' | '}') => depth -= 1,
+            _ => {}
+        }
+    }
+
+    // Only templates may span many lines:
+    depth > 0 || quote == Some('`')
+}
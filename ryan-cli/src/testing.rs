@@ -0,0 +1,32 @@
+use ryan::environment::{DefaultImporter, EnvironmentBuilder, ImportLoader};
+
+/// Runs the tests in the `*.test.ryan` files under a directory, printing a summary to
+/// standard output. Unless `allow_imports` is set, test files can import other files,
+/// such as the modules under test, but nothing else that may change from one run to the
+/// next, like environment variables or the standard input.
+pub fn run(
+    mut builder: EnvironmentBuilder,
+    path: &str,
+    allow_imports: bool,
+) -> Result<(), anyhow::Error> {
+    if !allow_imports {
+        builder = builder.import_loader(
+            DefaultImporter.filter(|path| !path.starts_with("env:") && path != "stdin:"),
+        );
+    }
+
+    let files = ryan::discover_tests(path)
+        .map_err(|err| anyhow::anyhow!("Failed to look for tests in {path}: {err}"))?;
+    let report = ryan::run_tests(&files, &builder.build());
+    println!("{report}");
+
+    if !report.is_success() {
+        anyhow::bail!(
+            "{} of {} tests failed",
+            report.failed(),
+            report.results.len()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,712 @@
+//! Runs the `ryan` binary as it is run in a shell.
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use predicates::str::contains;
+
+fn ryan() -> Command {
+    Command::cargo_bin("ryan").unwrap()
+}
+
+#[test]
+fn ndjson_prints_one_element_per_line() {
+    ryan()
+        .args(["--ndjson", "-c", r#"[{ a: 1 }, [1.0, "x"], null]"#])
+        .assert()
+        .success()
+        .stdout("{\"a\":1}\n[1.0,\"x\"]\nnull\n");
+}
+
+#[test]
+fn ndjson_prints_nothing_for_an_empty_list() {
+    ryan()
+        .args(["--ndjson", "-c", "[]"])
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn ndjson_needs_a_list() {
+    ryan()
+        .args(["--ndjson", "-c", "{ a: 1 }"])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(contains(
+            "The result must be a list to be printed with `--ndjson`, got a value of type {int}",
+        ));
+}
+
+#[test]
+fn eval_many_evaluates_documents_in_order() {
+    ryan()
+        .args(["--eval-many", "--ndjson"])
+        .write_stdin("---\n[1, 2]\n---\nlet x = 3;\n[x]\n---\n[]\n")
+        .assert()
+        .success()
+        .stdout("1\n2\n3\n");
+}
+
+#[test]
+fn eval_many_prints_each_result() {
+    ryan()
+        .arg("--eval-many")
+        .write_stdin("1 + 1\n---\n\"two\"\n")
+        .assert()
+        .success()
+        .stdout("2\n\"two\"\n");
+}
+
+#[test]
+fn eval_many_stops_at_the_first_error() {
+    ryan()
+        .arg("--eval-many")
+        .write_stdin("1\n---\nnot_defined\n---\n3\n")
+        .assert()
+        .failure()
+        .stdout("1\n")
+        .stderr(contains("Failed to run document 2 from the standard input"))
+        .stderr(contains("Variable `not_defined` is undefined"));
+}
+
+#[test]
+fn eval_many_reports_the_document_in_json_errors() {
+    ryan()
+        .args(["--eval-many", "--error-format", "json"])
+        .write_stdin("1\n---\n1 +\n")
+        .assert()
+        .failure()
+        .stderr(contains(r#""file":"document 2""#))
+        .stderr(contains(r#""kind":"parse""#));
+}
+
+#[test]
+fn eval_many_does_not_take_a_file() {
+    ryan()
+        .args(["--eval-many", "config.ryan"])
+        .assert()
+        .failure()
+        .stderr(contains("cannot be used with"));
+}
+
+#[test]
+fn imports_stdin_when_the_program_is_a_command() {
+    ryan()
+        .args(["-c", r#"import "stdin:" as text"#])
+        .write_stdin("piped")
+        .assert()
+        .success()
+        .stdout("\"piped\"\n");
+}
+
+#[test]
+fn does_not_import_stdin_when_it_holds_the_program() {
+    ryan()
+        .arg("-")
+        .write_stdin(r#"import "stdin:" as text"#)
+        .assert()
+        .failure()
+        .stderr(contains(r#"Import path "stdin:" is blocked"#));
+}
+
+#[test]
+fn reads_utf16_and_locates_invalid_utf8() {
+    let utf16 = [b"\xff\xfe".as_slice(), b"1\0 \0+\0 \x002\0"].concat();
+    ryan()
+        .arg("-")
+        .write_stdin(utf16)
+        .assert()
+        .success()
+        .stdout("3\n");
+
+    ryan()
+        .args(["-", "--error-format", "json"])
+        .write_stdin(b"[\n  \"ok\",\n  \"caf\xe9\"\n]".as_slice())
+        .assert()
+        .failure()
+        .stderr(contains(r#""kind":"encoding""#))
+        .stderr(contains(r#""line":3"#))
+        .stderr(contains(r#""column":7"#));
+}
+
+/// Writes files under a fresh directory in the temporary directory.
+fn fixture(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("ryan-cli-{name}"));
+    let _ = std::fs::remove_dir_all(&dir);
+    for (path, content) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+const NETWORKING: &str = "{ port: 443, timeout_secs: 30 }";
+
+#[test]
+fn test_mode_runs_test_files_recursively() {
+    let dir = fixture(
+        "test-pass",
+        &[
+            ("networking.ryan", NETWORKING),
+            (
+                "networking.test.ryan",
+                r#"let net = import "networking.ryan"; { tests: { https: net.port == 443 } }"#,
+            ),
+            (
+                "nested/timeouts.test.ryan",
+                r#"{ tests: { positive: (import "../networking.ryan").timeout_secs > 0 } }"#,
+            ),
+        ],
+    );
+
+    ryan()
+        .arg("--test")
+        .arg(&dir)
+        .assert()
+        .success()
+        .stdout(contains("running 2 tests"))
+        .stdout(contains("networking.test.ryan::https ... ok"))
+        .stdout(contains("timeouts.test.ryan::positive ... ok"))
+        .stdout(contains("test result: ok. 2 passed; 0 failed"));
+}
+
+#[test]
+fn test_mode_fails_on_false_tests() {
+    let dir = fixture(
+        "test-fail",
+        &[(
+            "math.test.ryan",
+            "{ tests: { sums: 1 + 1 == 2, lies: 1 + 1 == 3 } }",
+        )],
+    );
+
+    ryan()
+        .arg("--test")
+        .arg(&dir)
+        .assert()
+        .failure()
+        .stdout(contains("math.test.ryan::sums ... ok"))
+        .stdout(contains("math.test.ryan::lies ... FAILED"))
+        .stdout(contains("test result: FAILED. 1 passed; 1 failed"))
+        .stderr(contains("1 of 2 tests failed"));
+}
+
+#[test]
+fn test_mode_counts_broken_test_files_as_failures() {
+    let dir = fixture(
+        "test-broken",
+        &[
+            ("broken.test.ryan", "{ tests: { oops: 1 + } }"),
+            ("not_bool.test.ryan", r#"{ tests: { "text": "yes" } }"#),
+            ("ok.test.ryan", "{ tests: { fine: true } }"),
+        ],
+    );
+
+    ryan()
+        .arg("--test")
+        .arg(&dir)
+        .assert()
+        .failure()
+        .stdout(contains("broken.test.ryan ... FAILED"))
+        .stdout(contains("not_bool.test.ryan::text ... FAILED"))
+        .stdout(contains(
+            "Test `text` should evaluate to a bool, got a value of type text",
+        ))
+        .stdout(contains("test result: FAILED. 1 passed; 2 failed"));
+}
+
+#[test]
+fn test_mode_blocks_environment_variables_unless_allowed() {
+    let dir = fixture(
+        "test-env",
+        &[(
+            "env.test.ryan",
+            r#"{ tests: { home: (import "env:RYAN_CLI_TEST_VAR" as text) == "set" } }"#,
+        )],
+    );
+
+    ryan()
+        .arg("--test")
+        .arg(&dir)
+        .env("RYAN_CLI_TEST_VAR", "set")
+        .assert()
+        .failure()
+        .stdout(contains(
+            r#"Import path "env:RYAN_CLI_TEST_VAR" is blocked"#,
+        ));
+
+    ryan()
+        .args(["--allow-imports", "--test"])
+        .arg(&dir)
+        .env("RYAN_CLI_TEST_VAR", "set")
+        .assert()
+        .success()
+        .stdout(contains("test result: ok. 1 passed; 0 failed"));
+}
+
+#[test]
+fn warns_about_missing_environment_variables_only_if_asked() {
+    let program = r#"import "env:RYAN_CLI_UNSET_VAR" as text or "default""#;
+
+    ryan()
+        .args(["-c", program])
+        .env_remove("RYAN_CLI_UNSET_VAR")
+        .assert()
+        .success()
+        .stdout("\"default\"\n")
+        .stderr(contains("Is the name right?").not());
+
+    ryan()
+        .args(["--warn-missing-env", "-c", program])
+        .env_remove("RYAN_CLI_UNSET_VAR")
+        .assert()
+        .success()
+        .stdout("\"default\"\n")
+        .stderr(contains(
+            "Environment variable RYAN_CLI_UNSET_VAR is not set, so <main> used the default",
+        ));
+}
+
+#[test]
+fn required_environment_variables_ignore_defaults() {
+    let program = r#"[
+        import "env:RYAN_CLI_REQUIRED_VAR" as text or "default",
+        import "env:RYAN_CLI_OTHER_VAR" as text or "default",
+    ]"#;
+
+    ryan()
+        .args([
+            "--require-env",
+            "RYAN_CLI_OTHER_VAR,RYAN_CLI_REQUIRED_VAR",
+            "-c",
+        ])
+        .arg(program)
+        .env_remove("RYAN_CLI_REQUIRED_VAR")
+        .env_remove("RYAN_CLI_OTHER_VAR")
+        .assert()
+        .failure()
+        .stderr(contains(
+            "Environment variable RYAN_CLI_REQUIRED_VAR is not set",
+        ));
+
+    ryan()
+        .args([
+            "--require-env",
+            "RYAN_CLI_OTHER_VAR,RYAN_CLI_REQUIRED_VAR",
+            "-c",
+        ])
+        .arg(program)
+        .env("RYAN_CLI_REQUIRED_VAR", "a")
+        .env("RYAN_CLI_OTHER_VAR", "b")
+        .assert()
+        .success()
+        .stdout(contains("\"a\""));
+}
+
+#[test]
+fn completions_are_generated_for_each_shell() {
+    ryan()
+        .args(["completions", "bash"])
+        .assert()
+        .success()
+        .stdout(contains("_ryan()"))
+        .stdout(contains("--sort-keys"));
+
+    ryan()
+        .args(["completions", "fish"])
+        .assert()
+        .success()
+        .stdout(contains("complete -c ryan"));
+}
+
+#[test]
+fn explicit_flags_override_ryan_flags_which_override_ryanrc() {
+    let dir = fixture(
+        "rc-precedence",
+        &[(".ryanrc", r#"{ error_format: "json", sort_keys: true }"#)],
+    );
+    let program = "{ b: 1, a: 1 + }";
+
+    ryan()
+        .current_dir(&dir)
+        .env_remove("RYAN_FLAGS")
+        .args(["-c", program])
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with("[{"));
+
+    ryan()
+        .current_dir(&dir)
+        .env("RYAN_FLAGS", "--error-format text")
+        .args(["-c", program])
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with("Error:"));
+
+    ryan()
+        .current_dir(&dir)
+        .env("RYAN_FLAGS", "--error-format text")
+        .args(["--error-format", "json", "-c", program])
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with("[{"));
+
+    // Options not set anywhere else are still taken from `.ryanrc`:
+    ryan()
+        .current_dir(&dir)
+        .env("RYAN_FLAGS", "--error-format text")
+        .args(["-c", "{ b: 1, a: 2 }"])
+        .assert()
+        .success()
+        .stdout("{\n  \"a\": 2,\n  \"b\": 1\n}\n");
+}
+
+#[test]
+fn ryanrc_is_looked_up_in_parent_directories() {
+    let dir = fixture(
+        "rc-parent",
+        &[(".ryanrc", "{ sort_keys: 1 > 0 }"), ("nested/deeper/x", "")],
+    );
+
+    ryan()
+        .current_dir(dir.join("nested/deeper"))
+        .env_remove("RYAN_FLAGS")
+        .args(["-c", "{ b: 1, a: 2 }"])
+        .assert()
+        .success()
+        .stdout("{\n  \"a\": 2,\n  \"b\": 1\n}\n");
+}
+
+#[test]
+fn unknown_default_options_are_warnings() {
+    let dir = fixture(
+        "rc-unknown",
+        &[(
+            ".ryanrc",
+            r#"{ colour: false, log_level: true, sort_keys: true }"#,
+        )],
+    );
+
+    ryan()
+        .current_dir(&dir)
+        .env("RYAN_FLAGS", "--no-such-flag")
+        .args(["-c", "{ b: 1, a: 2 }"])
+        .assert()
+        .success()
+        .stdout("{\n  \"a\": 2,\n  \"b\": 1\n}\n")
+        .stderr(contains("Unknown option `colour`"))
+        .stderr(contains("Option `log_level`"))
+        .stderr(contains("Ignoring some default flags in RYAN_FLAGS"));
+}
+
+#[test]
+fn malformed_ryanrc_is_an_error() {
+    let dir = fixture("rc-malformed", &[(".ryanrc", "{ no_color: ")]);
+
+    ryan()
+        .current_dir(&dir)
+        .env_remove("RYAN_FLAGS")
+        .args(["-c", "1"])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(contains("Failed to read the default flags in"))
+        .stderr(contains(".ryanrc"));
+
+    let dir = fixture("rc-not-hermetic", &[(".ryanrc", r#"import "env:HOME""#)]);
+
+    ryan()
+        .current_dir(&dir)
+        .env_remove("RYAN_FLAGS")
+        .args(["-c", "1"])
+        .assert()
+        .failure()
+        .stderr(contains("Failed to read the default flags in"));
+}
+
+#[test]
+fn trace_logs_each_step_of_the_evaluation() {
+    ryan()
+        .args([
+            "--trace",
+            "-c",
+            "let sign 0 = 0; let sign x = if x < 0 then -1 else 1; let s = sign 5; s",
+        ])
+        .assert()
+        .success()
+        .stdout("1\n")
+        .stderr(
+            "[trace] sign: rule #0 did not match\n\
+            [trace] sign: rule #1 matched\n\
+            [trace] if: took `else`\n\
+            [trace] let s = 1\n",
+        );
+
+    ryan()
+        .args(["-c", "let s = 1; s"])
+        .assert()
+        .success()
+        .stderr("");
+}
+
+#[test]
+fn logs_imports_with_their_durations_and_warnings() {
+    let dir = fixture(
+        "logging",
+        &[
+            (
+                "main.ryan",
+                r#"let lib = import "lib.ryan"; let x = import "missing.ryan" or 0; lib + x"#,
+            ),
+            ("lib.ryan", "1"),
+        ],
+    );
+
+    let text = ryan()
+        .args(["--log-level", "info"])
+        .arg(dir.join("main.ryan"))
+        .assert()
+        .success()
+        .stdout("1\n")
+        .get_output()
+        .stderr
+        .clone();
+    let text = String::from_utf8(text).unwrap();
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "{text}");
+    assert!(
+        lines[0].starts_with("[info] Imported ") && lines[0].contains("lib.ryan\" as ryan in ")
+    );
+    assert!(lines[0].ends_with("ms"), "{text}");
+    assert!(lines[1].starts_with("[warn] Import \"missing.ryan\" failed, using default"));
+
+    let json = ryan()
+        .args(["--log-level", "info", "--log-format", "json"])
+        .arg(dir.join("main.ryan"))
+        .assert()
+        .success()
+        .get_output()
+        .stderr
+        .clone();
+    let events = String::from_utf8(json)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(events.len(), 2, "{events:?}");
+    assert_eq!(events[0]["event"], "import_loaded");
+    assert_eq!(events[0]["level"], "info");
+    assert_eq!(events[0]["cache_hit"], false);
+    assert!(events[0]["path"].as_str().unwrap().ends_with("lib.ryan"));
+    assert!(events[0]["duration_ms"].as_f64().unwrap() >= 0.0);
+    assert_eq!(events[1]["event"], "warning");
+    assert_eq!(events[1]["level"], "warn");
+    assert!(events[1]["message"]
+        .as_str()
+        .unwrap()
+        .contains("missing.ryan"));
+
+    ryan()
+        .args(["--log-level", "info", "--quiet"])
+        .arg(dir.join("main.ryan"))
+        .assert()
+        .success()
+        .stderr("");
+}
+
+#[test]
+fn times_each_attempt_at_a_failed_import() {
+    let dir = fixture(
+        "logging-retries",
+        &[
+            (
+                "main.ryan",
+                r#"let a = import "lib" or 1; let b = import "lib" or 2; a + b"#,
+            ),
+            // A directory resolves, but cannot be read:
+            ("lib/main.ryan", "0"),
+        ],
+    );
+
+    let json = ryan()
+        .args(["--log-level", "debug", "--log-format", "json"])
+        .arg(dir.join("main.ryan"))
+        .assert()
+        .success()
+        .stdout("3\n")
+        .get_output()
+        .stderr
+        .clone();
+    let events = String::from_utf8(json)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    let names = events
+        .iter()
+        .map(|event| event["event"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "import_started",
+            "import_failed",
+            "warning",
+            "import_started",
+            "import_failed",
+            "warning"
+        ],
+        "{events:?}"
+    );
+
+    for failed in [&events[1], &events[4]] {
+        assert_eq!(failed["level"], "debug");
+        assert!(failed["path"].as_str().unwrap().ends_with("lib"));
+        assert!(!failed["error"].as_str().unwrap().is_empty());
+        assert!(failed["duration_ms"].as_f64().unwrap() >= 0.0);
+    }
+}
+
+#[test]
+fn warns_about_shadowed_unused_and_duplicate_definitions_where_they_are() {
+    let code = "let x = 1;\nlet x = 2;\nlet unused = 3;\nlet _ignored = 4;\n{ a: x, a: 2 }";
+    let output = ryan()
+        .args(["-c", code])
+        .assert()
+        .success()
+        .stdout("{\n  \"a\": 2\n}\n")
+        .get_output()
+        .stderr
+        .clone();
+    let warnings = String::from_utf8(output).unwrap();
+    let warnings = warnings
+        .split("[warn] ")
+        .filter(|warning| !warning.is_empty())
+        .collect::<Vec<_>>();
+
+    assert_eq!(warnings.len(), 4, "{warnings:?}");
+    for (warning, position, message) in [
+        (
+            warnings[0],
+            "line 2, col 1",
+            "Variable `x` is already defined in this block",
+        ),
+        (
+            warnings[1],
+            "line 5, col 9",
+            "Key \"a\" is defined more than once in this dictionary",
+        ),
+        (warnings[2], "line 1, col 5", "Variable `x` is never used"),
+        (
+            warnings[3],
+            "line 3, col 5",
+            "Variable `unused` is never used",
+        ),
+    ] {
+        assert!(
+            warning.contains(&format!("Starting at {position}:")),
+            "{warning}"
+        );
+        assert!(warning.contains(message), "{warning}");
+    }
+}
+
+#[test]
+fn quiet_silences_warnings() {
+    ryan()
+        .args(["--quiet", "-c", "let unused = 1; 2"])
+        .assert()
+        .success()
+        .stdout("2\n")
+        .stderr("");
+}
+
+#[test]
+fn deny_warnings_fails_on_parse_warnings_even_if_quiet() {
+    for args in [&["--deny-warnings"][..], &["--deny-warnings", "--quiet"]] {
+        ryan()
+            .args(args)
+            .args(["-c", "let unused = 1; 2"])
+            .assert()
+            .failure()
+            .stdout("")
+            .stderr(contains("Found 1 warning(s) and `--deny-warnings` is set"));
+    }
+
+    ryan()
+        .args(["--deny-warnings", "-c", "let _unused = 1; 2"])
+        .assert()
+        .success()
+        .stdout("2\n");
+}
+
+#[test]
+fn deny_warnings_counts_warnings_raised_while_evaluating() {
+    let code = r#"import "/__ryan_missing__.ryan" or 1"#;
+
+    ryan().args(["-c", code]).assert().success().stdout("1\n");
+
+    for args in [&["--deny-warnings"][..], &["--deny-warnings", "--quiet"]] {
+        ryan()
+            .args(args)
+            .args(["-c", code])
+            .assert()
+            .failure()
+            .stdout("")
+            .stderr(contains("Found 1 warning(s) and `--deny-warnings` is set"));
+    }
+}
+
+#[test]
+fn json_errors_locate_parse_errors_in_files() {
+    let dir = fixture("json-errors", &[("bad.ryan", "let x = 1;\nlet y = ;\nx")]);
+    let file = dir.join("bad.ryan");
+
+    let output = ryan()
+        .args(["--error-format", "json"])
+        .arg(&file)
+        .assert()
+        .failure()
+        .get_output()
+        .stderr
+        .clone();
+    let errors: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(
+        errors,
+        serde_json::json!([{
+            "kind": "parse",
+            "message": "Expected a code block.",
+            "file": file.to_string_lossy(),
+            "line": 2,
+            "column": 9,
+            "span": [19, 20],
+            "context": [],
+        }])
+    );
+}
+
+#[test]
+fn json_errors_carry_the_kind_and_context_of_eval_errors() {
+    let output = ryan()
+        .args(["--error-format", "json", "-c", "let a = 1; a + b"])
+        .assert()
+        .failure()
+        .get_output()
+        .stderr
+        .clone();
+    let errors: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(
+        errors,
+        serde_json::json!([{
+            "kind": "undefined_variable",
+            "message": "Variable `b` is undefined",
+            "file": null,
+            "line": null,
+            "column": null,
+            "span": null,
+            "context": ["Running <main>"],
+        }])
+    );
+}
@@ -3,7 +3,9 @@
 
 mod utils;
 
-use js_sys::{Array, Object};
+use js_sys::{Array, Object, Uint8Array};
+use ryan::environment::{DefaultImporter, ImportLoader};
+use std::collections::BTreeMap;
 use wasm_bindgen::prelude::*;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
@@ -27,10 +29,9 @@ fn ryan_to_js(value: &Value) -> Result<JsValue, JsValue> {
         Value::Integer(int) => Ok(JsValue::from_f64(*int as f64)),
         Value::Float(float) => Ok(JsValue::from_f64(*float)),
         Value::Text(text) => Ok(JsValue::from_str(text)),
+        Value::Bytes(bytes) => Ok(Uint8Array::from(&bytes[..]).into()),
         Value::List(list) => Ok(JsValue::from(
-            list.iter()
-                .map(|item| ryan_to_js(item))
-                .collect::<Result<Array, _>>()?,
+            list.iter().map(ryan_to_js).collect::<Result<Array, _>>()?,
         )),
         Value::Map(dict) => Ok({
             let object = Object::new();
@@ -49,10 +50,20 @@ fn ryan_to_js(value: &Value) -> Result<JsValue, JsValue> {
     }
 }
 
+/// Converts the result of a Ryan program to JavaScript, failing early with where the
+/// culprit is if the result is not just data, usually because of a pattern match that
+/// was not applied.
+fn to_js(value: &Value) -> Result<JsValue, JsValue> {
+    value
+        .check_data()
+        .map_err(|err| JsError::new(&err.to_string()))?;
+    ryan_to_js(value)
+}
+
 /// This is a patch for a function missing in Ryan as of `0.1.0`.
 fn value_from_str(s: &str) -> Result<Value, ryan::Error> {
     let env = ryan::Environment::new(None);
-    let parsed = ryan::parser::parse(&s).map_err(ryan::Error::Parse)?;
+    let parsed = ryan::parser::parse(s).map_err(ryan::Error::Parse)?;
     let value = ryan::parser::eval(env, &parsed).map_err(ryan::Error::Eval)?;
 
     Ok(value)
@@ -61,7 +72,7 @@ fn value_from_str(s: &str) -> Result<Value, ryan::Error> {
 /// This is a patch for a function missing in Ryan as of `0.1.0`.
 fn value_from_str_with_filename(filename: &str, s: &str) -> Result<Value, ryan::Error> {
     let env = ryan::Environment::new(Some(filename));
-    let parsed = ryan::parser::parse(&s).map_err(ryan::Error::Parse)?;
+    let parsed = ryan::parser::parse(s).map_err(ryan::Error::Parse)?;
     let value = ryan::parser::eval(env, &parsed).map_err(ryan::Error::Eval)?;
 
     Ok(value)
@@ -69,7 +80,7 @@ fn value_from_str_with_filename(filename: &str, s: &str) -> Result<Value, ryan::
 
 /// This is a patch for a function missing in Ryan as of `0.1.0`.
 pub fn value_from_str_with_env(env: &ryan::Environment, s: &str) -> Result<Value, ryan::Error> {
-    let parsed = ryan::parser::parse(&s).map_err(ryan::Error::Parse)?;
+    let parsed = ryan::parser::parse(s).map_err(ryan::Error::Parse)?;
     let value = ryan::parser::eval(env.clone(), &parsed).map_err(ryan::Error::Eval)?;
 
     Ok(value)
@@ -86,8 +97,8 @@ pub fn value_from_str_with_env(env: &ryan::Environment, s: &str) -> Result<Value
 /// `current_module` will be set to `None` while executing in this mode.
 #[wasm_bindgen]
 pub fn fromStr(s: &str) -> Result<JsValue, JsValue> {
-    let value = value_from_str(s.into()).map_err(|err| JsError::new(&err.to_string()))?;
-    ryan_to_js(&value)
+    let value = value_from_str(s).map_err(|err| JsError::new(&err.to_string()))?;
+    to_js(&value)
 }
 
 /// Loads a Ryan file from a supplied reader and executes it, building a JavaScript object
@@ -95,9 +106,9 @@ pub fn fromStr(s: &str) -> Result<JsValue, JsValue> {
 /// will be set to `filename` while executing in this mode.
 #[wasm_bindgen]
 pub fn fromStrWithFilename(filename: &str, s: &str) -> Result<JsValue, JsValue> {
-    let value = value_from_str_with_filename(filename, s.into())
-        .map_err(|err| JsError::new(&err.to_string()))?;
-    ryan_to_js(&value)
+    let value =
+        value_from_str_with_filename(filename, s).map_err(|err| JsError::new(&err.to_string()))?;
+    to_js(&value)
 }
 
 /// Loads a Ryan file from a supplied string and executes it, finally building an instance
@@ -106,9 +117,15 @@ pub fn fromStrWithFilename(filename: &str, s: &str) -> Result<JsValue, JsValue>
 /// the `current_module` name.
 #[wasm_bindgen]
 pub fn fromStrWithEnv(env: &Environment, s: &str) -> Result<JsValue, JsValue> {
-    let value =
-        value_from_str_with_env(&env.0, s.into()).map_err(|err| JsError::new(&err.to_string()))?;
-    ryan_to_js(&value)
+    let value = value_from_str_with_env(&env.0, s).map_err(|err| JsError::new(&err.to_string()))?;
+    to_js(&value)
+}
+
+#[wasm_bindgen]
+extern "C" {
+    /// An array of strings, typed as such in the TypeScript definitions.
+    #[wasm_bindgen(typescript_type = "string[]")]
+    pub type StringArray;
 }
 
 /// The environment on which a Ryan program operates.
@@ -120,7 +137,22 @@ impl Environment {
     /// Creates an environment builder. Use this to tweak Ryan.
     #[wasm_bindgen]
     pub fn builder() -> EnvironmentBuilder {
-        EnvironmentBuilder(ryan::Environment::builder())
+        EnvironmentBuilder {
+            builder: ryan::Environment::builder(),
+            loader: None,
+            overrides: BTreeMap::new(),
+        }
+    }
+
+    /// The names of the built-ins available to programs evaluated in this environment,
+    /// sorted. Namespaces, like `str`, are listed by their own name.
+    #[wasm_bindgen]
+    pub fn builtins(&self) -> StringArray {
+        self.0
+            .builtins()
+            .map(|(id, _)| JsValue::from_str(id))
+            .collect::<Array>()
+            .unchecked_into()
     }
 
     #[wasm_bindgen(getter)]
@@ -136,26 +168,69 @@ impl Environment {
 
 /// A builder for `Environment`s. Use `Environment.builder` to create a new builder.
 #[wasm_bindgen]
-pub struct EnvironmentBuilder(ryan::environment::EnvironmentBuilder);
+pub struct EnvironmentBuilder {
+    builder: ryan::environment::EnvironmentBuilder,
+    loader: Option<JsLoader>,
+    /// The imports overridden by `overrideImport` (`Some`) and `blockImport` (`None`),
+    /// which go around the loader when the environment is built.
+    overrides: BTreeMap<String, Option<String>>,
+}
 
 #[wasm_bindgen]
 impl EnvironmentBuilder {
     /// Buils the environment with the supplied configurations.
     #[wasm_bindgen]
     pub fn build(self) -> Environment {
-        Environment(self.0.build())
+        let builder = match self.loader {
+            Some(loader) => self
+                .builder
+                .import_loader(loader.override_many(self.overrides)),
+            None if self.overrides.is_empty() => self.builder,
+            None => self
+                .builder
+                .import_loader(DefaultImporter.override_many(self.overrides)),
+        };
+
+        Environment(builder.build())
     }
 
     /// Sets the current module name for the environment.
     #[wasm_bindgen]
-    pub fn module(self, module: &str) -> Self {
-        Self(self.0.module(module))
+    pub fn module(mut self, module: &str) -> Self {
+        self.builder = self.builder.module(module);
+        self
     }
 
     /// The the import loader for the environment.
     #[wasm_bindgen]
-    pub fn importLoader(self, loader: JsLoader) -> Self {
-        Self(self.0.import_loader(loader))
+    pub fn importLoader(mut self, loader: JsLoader) -> Self {
+        self.loader = Some(loader);
+        self
+    }
+
+    /// Makes the environment hermetic: programs evaluated with it cannot import anything,
+    /// whatever the import loader and the overridden imports, and the built-ins that
+    /// depend on the outside world are removed.
+    #[wasm_bindgen]
+    pub fn hermetic(mut self) -> Self {
+        self.builder = self.builder.hermetic();
+        self
+    }
+
+    /// Makes importing `path` give `content`, instead of asking the import loader. The
+    /// path is matched exactly, as written in the `import`.
+    #[wasm_bindgen]
+    pub fn overrideImport(mut self, path: String, content: String) -> Self {
+        self.overrides.insert(path, Some(content));
+        self
+    }
+
+    /// Makes importing `path` an error, instead of asking the import loader. The path is
+    /// matched exactly, as written in the `import`.
+    #[wasm_bindgen]
+    pub fn blockImport(mut self, path: String) -> Self {
+        self.overrides.insert(path, None);
+        self
     }
 }
 
@@ -266,7 +341,7 @@ impl ryan::environment::ImportLoader for JsLoader {
         Ok(Box::new(std::io::Cursor::new(
             current.as_string().ok_or_else(|| {
                 Box::new(ImportError {
-                    error: format!("Resolved module cannot be represented in UTF-8"),
+                    error: "Resolved module cannot be represented in UTF-8".to_string(),
                 })
             })?,
         )))
@@ -11,3 +11,76 @@ wasm_bindgen_test_configure!(run_in_browser);
 fn pass() {
     assert_eq!(1 + 1, 2);
 }
+
+/// The message of the error thrown by a call to the library.
+fn message(err: wasm_bindgen::JsValue) -> String {
+    use wasm_bindgen::JsCast;
+    String::from(err.dyn_into::<js_sys::Error>().unwrap().message())
+}
+
+#[wasm_bindgen_test]
+fn unapplied_patterns_are_reported() {
+    let code = "let make_service name = { name }; { services: { api: make_service } }";
+    let err = message(ryan_lang_node::fromStr(code).unwrap_err());
+
+    assert!(
+        err.contains("unapplied pattern `make_service` at .services.api"),
+        "{err}"
+    );
+    assert!(err.contains("Did you forget to apply it?"), "{err}");
+}
+
+#[wasm_bindgen_test]
+fn types_are_reported() {
+    let err =
+        message(ryan_lang_node::fromStr("type Port = int; { ports: [80, Port] }").unwrap_err());
+    assert!(err.contains("the type `int` at .ports[1]"), "{err}");
+}
+
+#[wasm_bindgen_test]
+fn hermetic_environments_reject_imports() {
+    use ryan_lang_node::{Environment, JsLoader};
+
+    let modules = js_sys::Object::new();
+    js_sys::Reflect::set(&modules, &"a.ryan".into(), &"[1, 2, 3]".into()).unwrap();
+    let env = Environment::builder()
+        .importLoader(JsLoader::new(modules.into()))
+        .overrideImport("b.ryan".to_owned(), "1".to_owned())
+        .hermetic()
+        .build();
+
+    for code in [r#"import "/a.ryan""#, r#"import "b.ryan""#] {
+        let err = message(ryan_lang_node::fromStrWithEnv(&env, code).unwrap_err());
+        assert!(err.contains("Imports are disabled"), "{code}: {err}");
+    }
+}
+
+#[wasm_bindgen_test]
+fn overrides_supply_and_block_imports() {
+    use ryan_lang_node::Environment;
+
+    let env = Environment::builder()
+        .overrideImport("lib.ryan".to_owned(), "{ a: 1 }".to_owned())
+        .blockImport("secret.ryan".to_owned())
+        .build();
+
+    let value = ryan_lang_node::fromStrWithEnv(&env, r#"(import "lib.ryan").a + 1"#).unwrap();
+    assert_eq!(value.as_f64(), Some(2.0));
+
+    let err = message(ryan_lang_node::fromStrWithEnv(&env, r#"import "secret.ryan""#).unwrap_err());
+    assert!(
+        err.contains(r#"Import path "secret.ryan" is blocked"#),
+        "{err}"
+    );
+}
+
+#[wasm_bindgen_test]
+fn builtins_are_listed() {
+    use wasm_bindgen::JsCast;
+
+    let builtins = ryan_lang_node::Environment::builder().build().builtins();
+    let builtins = builtins.unchecked_into::<js_sys::Array>();
+
+    assert!(builtins.includes(&"len".into(), 0));
+    assert!(builtins.includes(&"str".into(), 0));
+}
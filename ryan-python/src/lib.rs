@@ -1,41 +1,183 @@
+use std::fmt::{self, Display};
+
 use pyo3::exceptions::{PyException, PyValueError};
 use pyo3::prelude::*;
 
 use ::ryan::parser::Value;
-use pyo3::types::{PyDict, PyList};
+use pyo3::types::{PyBytes, PyDict, PyList, PyTuple};
+
+/// The keyword arguments controlling how a Ryan value is converted to Python.
+struct Options<'py> {
+    /// Called with the text of every float, e.g., `decimal.Decimal`.
+    parse_float: Option<&'py PyAny>,
+    /// Called with every dictionary, from the innermost to the outermost.
+    object_hook: Option<&'py PyAny>,
+    /// `types.MappingProxyType`, if the result is to be immutable.
+    mapping_proxy: Option<&'py PyAny>,
+}
+
+impl<'py> Options<'py> {
+    fn new(
+        py: Python<'py>,
+        parse_float: Option<&'py PyAny>,
+        object_hook: Option<&'py PyAny>,
+        frozen: bool,
+    ) -> PyResult<Options<'py>> {
+        let mapping_proxy = if frozen {
+            Some(py.import("types")?.getattr("MappingProxyType")?)
+        } else {
+            None
+        };
+
+        Ok(Options {
+            parse_float,
+            object_hook,
+            mapping_proxy,
+        })
+    }
+}
+
+/// A step from a value into one of its children.
+enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+/// Where a value is within the result of a Ryan program, e.g., `$.servers[0].port`.
+struct ValuePath<'a, 'b>(&'b [Segment<'a>]);
+
+impl Display for ValuePath<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+
+        for segment in self.0 {
+            match segment {
+                Segment::Key(key)
+                    if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+                {
+                    write!(f, ".{key}")?
+                }
+                Segment::Key(key) => write!(f, "[{key:?}]")?,
+                Segment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
 
-fn ryan_to_python(py: Python, value: &Value) -> PyResult<PyObject> {
+        Ok(())
+    }
+}
+
+/// Calls a hook supplied by the user. If the hook raises, the exception is passed on as
+/// is, but annotated with where the value was: the path is set as the `ryan_path`
+/// attribute of the exception and, in Python 3.11 onwards, added as a note.
+fn call_hook(
+    py: Python,
+    name: &str,
+    hook: &PyAny,
+    arg: impl IntoPy<Py<PyTuple>>,
+    path: &[Segment],
+) -> PyResult<PyObject> {
+    hook.call1(arg).map(Into::into).inspect_err(|err| {
+        let path = ValuePath(path).to_string();
+        let exception = err.value(py);
+
+        // Best effort: some exceptions do not take attributes.
+        let _ = exception.setattr("ryan_path", &path);
+        if let Ok(add_note) = exception.getattr("add_note") {
+            let _ = add_note.call1((format!("raised by `{name}` at {path}"),));
+        }
+    })
+}
+
+fn ryan_to_python<'a>(
+    py: Python,
+    value: &'a Value,
+    options: &Options,
+    path: &mut Vec<Segment<'a>>,
+) -> PyResult<PyObject> {
     match value {
         Value::Null => Ok(().into_py(py)),
         Value::Bool(b) => Ok(b.into_py(py)),
         Value::Integer(int) => Ok(int.into_py(py)),
-        Value::Float(float) => Ok(float.into_py(py)),
+        Value::Float(float) => match options.parse_float {
+            // Debug is the shortest text that reads back as the same float, like `repr`:
+            Some(parse_float) => call_hook(
+                py,
+                "parse_float",
+                parse_float,
+                (format!("{float:?}"),),
+                path,
+            ),
+            None => Ok(float.into_py(py)),
+        },
         Value::Text(text) => Ok(text.into_py(py)),
-        Value::List(list) => Ok(PyList::new(
-            py,
-            list.iter()
-                .map(|v| ryan_to_python(py, v))
-                .collect::<Result<Vec<_>, _>>()?,
-        )
-        .into()),
-        Value::Map(dict) => Ok(PyDict::from_sequence(
-            py,
-            dict.iter()
-                .map(|(k, v)| Ok((k.to_object(py), ryan_to_python(py, v)?)))
-                .collect::<Result<Vec<_>, PyErr>>()?
-                .to_object(py),
-        )?
-        .into()),
+        Value::Bytes(bytes) => Ok(PyBytes::new(py, bytes).into()),
+        Value::List(list) => {
+            let items = list
+                .iter()
+                .enumerate()
+                .map(|(index, v)| {
+                    path.push(Segment::Index(index));
+                    let item = ryan_to_python(py, v, options, path);
+                    path.pop();
+                    item
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if options.mapping_proxy.is_some() {
+                Ok(PyTuple::new(py, items).into())
+            } else {
+                Ok(PyList::new(py, items).into())
+            }
+        }
+        Value::Map(dict) => {
+            let dict: PyObject = PyDict::from_sequence(
+                py,
+                dict.iter()
+                    .map(|(k, v)| {
+                        path.push(Segment::Key(k));
+                        let item = ryan_to_python(py, v, options, path);
+                        path.pop();
+                        Ok((k.to_object(py), item?))
+                    })
+                    .collect::<Result<Vec<_>, PyErr>>()?
+                    .to_object(py),
+            )?
+            .into();
+
+            // The hook gets a plain dictionary and what it returns is kept as is:
+            match (options.object_hook, options.mapping_proxy) {
+                (Some(object_hook), _) => call_hook(py, "object_hook", object_hook, (dict,), path),
+                (None, Some(mapping_proxy)) => Ok(mapping_proxy.call1((dict,))?.into()),
+                (None, None) => Ok(dict),
+            }
+        }
         val => Err(PyValueError::new_err(format!(
             "Unrepresentable value: {val}"
         ))),
     }
 }
 
+/// Converts the result of a Ryan program to Python, according to the supplied options.
+fn convert<'py>(
+    py: Python<'py>,
+    value: &Value,
+    parse_float: Option<&'py PyAny>,
+    object_hook: Option<&'py PyAny>,
+    frozen: bool,
+) -> PyResult<PyObject> {
+    // Fail early with where the culprit is, usually a pattern match not applied:
+    value
+        .check_data()
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+
+    let options = Options::new(py, parse_float, object_hook, frozen)?;
+    ryan_to_python(py, value, &options, &mut vec![])
+}
+
 /// This is a patch for a function missing in Ryan as of `0.1.0`.
 pub fn value_from_str(s: &str) -> Result<Value, ::ryan::Error> {
     let env = ::ryan::Environment::new(None);
-    let parsed = ::ryan::parser::parse(&s).map_err(::ryan::Error::Parse)?;
+    let parsed = ::ryan::parser::parse(s).map_err(::ryan::Error::Parse)?;
     let value = ::ryan::parser::eval(env, &parsed).map_err(::ryan::Error::Eval)?;
 
     Ok(value)
@@ -44,7 +186,7 @@ pub fn value_from_str(s: &str) -> Result<Value, ::ryan::Error> {
 /// This is a patch for a function missing in Ryan as of `0.1.0`.
 pub fn value_from_str_with_filename(filename: &str, s: &str) -> Result<Value, ::ryan::Error> {
     let env = ::ryan::Environment::new(Some(filename));
-    let parsed = ::ryan::parser::parse(&s).map_err(::ryan::Error::Parse)?;
+    let parsed = ::ryan::parser::parse(s).map_err(::ryan::Error::Parse)?;
     let value = ::ryan::parser::eval(env, &parsed).map_err(::ryan::Error::Eval)?;
 
     Ok(value)
@@ -67,29 +209,59 @@ pub fn ryan(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     /// Loads a Ryan file from a supplied string and executes it, building a python
     /// object equivalent to the JSON value resulting from this computation. The
     /// `current_module` will be set to `None` while executing in this mode.
+    ///
+    /// Like in the `json` module, `parse_float` is called with the text of every float
+    /// (e.g., `decimal.Decimal`) and `object_hook` is called with every dictionary, from
+    /// the innermost to the outermost, and its return value is used instead. Exceptions
+    /// raised by these hooks are passed on, with the path of the value where they
+    /// happened in the `ryan_path` attribute. If `frozen` is set, dictionaries are
+    /// returned as `types.MappingProxyType` and lists as tuples.
     #[pyfn(m)]
-    fn from_str(py: Python, s: &str) -> PyResult<PyObject> {
-        let value =
-            value_from_str(s.into()).map_err(|err| PyException::new_err(err.to_string()))?;
-        ryan_to_python(py, &value)
+    #[pyo3(signature = (s, *, parse_float = None, object_hook = None, frozen = false))]
+    fn from_str<'py>(
+        py: Python<'py>,
+        s: &str,
+        parse_float: Option<&'py PyAny>,
+        object_hook: Option<&'py PyAny>,
+        frozen: bool,
+    ) -> PyResult<PyObject> {
+        let value = value_from_str(s).map_err(|err| PyException::new_err(err.to_string()))?;
+        convert(py, &value, parse_float, object_hook, frozen)
     }
 
     /// Loads a Ryan file from a supplied reader and executes it, building a python object
     /// equivalent to the JSON value resulting from this computation. The `current_module`
-    /// will be set to `filename` while executing in this mode.
+    /// will be set to `filename` while executing in this mode. The keyword arguments are
+    /// the same as in `from_str`.
     #[pyfn(m)]
-    fn from_str_with_filename(py: Python, filename: &str, s: &str) -> PyResult<PyObject> {
-        let value = value_from_str_with_filename(filename, s.into())
+    #[pyo3(signature = (filename, s, *, parse_float = None, object_hook = None, frozen = false))]
+    fn from_str_with_filename<'py>(
+        py: Python<'py>,
+        filename: &str,
+        s: &str,
+        parse_float: Option<&'py PyAny>,
+        object_hook: Option<&'py PyAny>,
+        frozen: bool,
+    ) -> PyResult<PyObject> {
+        let value = value_from_str_with_filename(filename, s)
             .map_err(|err| PyException::new_err(err.to_string()))?;
-        ryan_to_python(py, &value)
+        convert(py, &value, parse_float, object_hook, frozen)
     }
 
     /// Loads a Ryan file from disk and executes it, building a python object equivalent
-    /// to the JSON value resulting from this computation.
+    /// to the JSON value resulting from this computation. The keyword arguments are the
+    /// same as in `from_str`.
     #[pyfn(m)]
-    fn from_path(py: Python, path: &str) -> PyResult<PyObject> {
+    #[pyo3(signature = (path, *, parse_float = None, object_hook = None, frozen = false))]
+    fn from_path<'py>(
+        py: Python<'py>,
+        path: &str,
+        parse_float: Option<&'py PyAny>,
+        object_hook: Option<&'py PyAny>,
+        frozen: bool,
+    ) -> PyResult<PyObject> {
         let value = value_from_path(path).map_err(|err| PyException::new_err(err.to_string()))?;
-        ryan_to_python(py, &value)
+        convert(py, &value, parse_float, object_hook, frozen)
     }
 
     m.add("__version__", env!("CARGO_PKG_VERSION"))?;
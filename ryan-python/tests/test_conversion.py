@@ -0,0 +1,138 @@
+import dataclasses
+import decimal
+import types
+
+import pytest
+
+import ryan
+
+
+def test_defaults():
+    assert ryan.from_str("{ a: [1, 2.5, null], b: true }") == {
+        "a": [1, 2.5, None],
+        "b": True,
+    }
+
+
+def test_parse_float():
+    value = ryan.from_str("{ a: 0.1, b: [1.0, 2] }", parse_float=decimal.Decimal)
+    assert value == {"a": decimal.Decimal("0.1"), "b": [decimal.Decimal("1.0"), 2]}
+    assert isinstance(value["b"][1], int)
+
+
+def test_object_hook_is_bottom_up():
+    seen = []
+
+    def hook(obj):
+        seen.append(dict(obj))
+        return tuple(sorted(obj))
+
+    value = ryan.from_str("{ outer: { inner: 1 }, other: 2 }", object_hook=hook)
+    assert value == ("other", "outer")
+    assert seen == [{"inner": 1}, {"outer": ("inner",), "other": 2}]
+
+
+def test_object_hook_builds_dataclasses():
+    @dataclasses.dataclass
+    class Server:
+        host: str
+        port: int
+
+    def hook(obj):
+        return Server(**obj) if "host" in obj else obj
+
+    value = ryan.from_str(
+        '{ servers: [{ host: "a", port: 1 }, { host: "b", port: 2 }] }',
+        object_hook=hook,
+    )
+    assert value == {"servers": [Server("a", 1), Server("b", 2)]}
+
+
+def test_frozen():
+    value = ryan.from_str("{ a: { b: [1, { c: 2 }] } }", frozen=True)
+    assert isinstance(value, types.MappingProxyType)
+    assert isinstance(value["a"], types.MappingProxyType)
+    assert value["a"]["b"] == (1, {"c": 2})
+    assert isinstance(value["a"]["b"][1], types.MappingProxyType)
+
+    with pytest.raises(TypeError):
+        value["a"] = 1
+
+
+def test_frozen_leaves_object_hook_results_alone():
+    value = ryan.from_str("{ a: { b: 1 } }", object_hook=dict, frozen=True)
+    assert type(value) is dict
+    assert type(value["a"]) is dict
+
+
+def test_options_are_keyword_only():
+    with pytest.raises(TypeError):
+        ryan.from_str("1.0", decimal.Decimal)
+
+
+def test_from_path(tmp_path):
+    path = tmp_path / "config.ryan"
+    path.write_text("{ ratio: 0.5 }")
+
+    value = ryan.from_path(str(path), parse_float=decimal.Decimal, frozen=True)
+    assert value == {"ratio": decimal.Decimal("0.5")}
+    assert isinstance(value, types.MappingProxyType)
+
+
+def test_parse_float_exception():
+    def parse_float(text):
+        raise ValueError(f"no floats here: {text}")
+
+    with pytest.raises(ValueError, match="no floats here: 2.5") as info:
+        ryan.from_str('{ a: [1, 2.5] }', parse_float=parse_float)
+
+    assert info.value.ryan_path == "$.a[1]"
+
+
+def test_object_hook_exception():
+    def hook(obj):
+        if "port" in obj:
+            raise KeyError("bad server")
+        return obj
+
+    with pytest.raises(KeyError) as info:
+        ryan.from_str(
+            '{ servers: [{ host: "a" }, { port: 1 }], "the db": { port: 2 } }',
+            object_hook=hook,
+        )
+
+    assert info.value.ryan_path == "$.servers[1]"
+    if hasattr(info.value, "add_note"):
+        assert info.value.__notes__ == ["raised by `object_hook` at $.servers[1]"]
+
+
+def test_object_hook_exception_path_with_odd_keys():
+    def hook(obj):
+        if "port" in obj:
+            raise RuntimeError("bad")
+        return obj
+
+    with pytest.raises(RuntimeError) as info:
+        ryan.from_str('{ "the db": { port: 2 } }', object_hook=hook)
+
+    assert info.value.ryan_path == '$["the db"]'
+
+
+def test_unapplied_patterns_are_reported():
+    code = "let make_service name = { name }; { services: { api: make_service } }"
+
+    with pytest.raises(ValueError) as info:
+        ryan.from_str(code)
+
+    message = str(info.value)
+    assert "unapplied pattern `make_service` at .services.api" in message
+    assert "Did you forget to apply it?" in message
+
+
+def test_types_are_reported():
+    with pytest.raises(ValueError, match=r"the type `int` at \.ports\[1\]"):
+        ryan.from_str("type Port = int; { ports: [80, Port] }")
+
+
+def test_bytes_are_data():
+    assert ryan.from_str('{ logo: import "data:text,abc" as bytes }') == {"logo": b"abc"}
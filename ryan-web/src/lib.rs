@@ -3,7 +3,7 @@
 
 mod utils;
 
-use js_sys::{Array, Object};
+use js_sys::{Array, Object, Uint8Array};
 use wasm_bindgen::prelude::*;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
@@ -27,10 +27,9 @@ fn ryan_to_js(value: &Value) -> Result<JsValue, JsValue> {
         Value::Integer(int) => Ok(JsValue::from_f64(*int as f64)),
         Value::Float(float) => Ok(JsValue::from_f64(*float)),
         Value::Text(text) => Ok(JsValue::from_str(text)),
+        Value::Bytes(bytes) => Ok(Uint8Array::from(&bytes[..]).into()),
         Value::List(list) => Ok(JsValue::from(
-            list.iter()
-                .map(|item| ryan_to_js(item))
-                .collect::<Result<Array, _>>()?,
+            list.iter().map(ryan_to_js).collect::<Result<Array, _>>()?,
         )),
         Value::Map(dict) => Ok({
             let object = Object::new();
@@ -52,7 +51,7 @@ fn ryan_to_js(value: &Value) -> Result<JsValue, JsValue> {
 /// This is a patch for a function missing in Ryan as of `0.1.0`.
 fn value_from_str(s: &str) -> Result<Value, ryan::Error> {
     let env = ryan::Environment::new(None);
-    let parsed = ryan::parser::parse(&s).map_err(ryan::Error::Parse)?;
+    let parsed = ryan::parser::parse(s).map_err(ryan::Error::Parse)?;
     let value = ryan::parser::eval(env, &parsed).map_err(ryan::Error::Eval)?;
 
     Ok(value)
@@ -61,7 +60,7 @@ fn value_from_str(s: &str) -> Result<Value, ryan::Error> {
 /// This is a patch for a function missing in Ryan as of `0.1.0`.
 fn value_from_str_with_filename(filename: &str, s: &str) -> Result<Value, ryan::Error> {
     let env = ryan::Environment::new(Some(filename));
-    let parsed = ryan::parser::parse(&s).map_err(ryan::Error::Parse)?;
+    let parsed = ryan::parser::parse(s).map_err(ryan::Error::Parse)?;
     let value = ryan::parser::eval(env, &parsed).map_err(ryan::Error::Eval)?;
 
     Ok(value)
@@ -69,7 +68,7 @@ fn value_from_str_with_filename(filename: &str, s: &str) -> Result<Value, ryan::
 
 /// This is a patch for a function missing in Ryan as of `0.1.0`.
 pub fn value_from_str_with_env(env: &ryan::Environment, s: &str) -> Result<Value, ryan::Error> {
-    let parsed = ryan::parser::parse(&s).map_err(ryan::Error::Parse)?;
+    let parsed = ryan::parser::parse(s).map_err(ryan::Error::Parse)?;
     let value = ryan::parser::eval(env.clone(), &parsed).map_err(ryan::Error::Eval)?;
 
     Ok(value)
@@ -86,7 +85,7 @@ pub fn value_from_str_with_env(env: &ryan::Environment, s: &str) -> Result<Value
 /// `current_module` will be set to `None` while executing in this mode.
 #[wasm_bindgen]
 pub fn fromStr(s: &str) -> Result<JsValue, JsValue> {
-    let value = value_from_str(s.into()).map_err(|err| JsError::new(&err.to_string()))?;
+    let value = value_from_str(s).map_err(|err| JsError::new(&err.to_string()))?;
     ryan_to_js(&value)
 }
 
@@ -95,8 +94,8 @@ pub fn fromStr(s: &str) -> Result<JsValue, JsValue> {
 /// will be set to `filename` while executing in this mode.
 #[wasm_bindgen]
 pub fn fromStrWithFilename(filename: &str, s: &str) -> Result<JsValue, JsValue> {
-    let value = value_from_str_with_filename(filename, s.into())
-        .map_err(|err| JsError::new(&err.to_string()))?;
+    let value =
+        value_from_str_with_filename(filename, s).map_err(|err| JsError::new(&err.to_string()))?;
     ryan_to_js(&value)
 }
 
@@ -106,8 +105,7 @@ pub fn fromStrWithFilename(filename: &str, s: &str) -> Result<JsValue, JsValue>
 /// the `current_module` name.
 #[wasm_bindgen]
 pub fn fromStrWithEnv(env: &Environment, s: &str) -> Result<JsValue, JsValue> {
-    let value =
-        value_from_str_with_env(&env.0, s.into()).map_err(|err| JsError::new(&err.to_string()))?;
+    let value = value_from_str_with_env(&env.0, s).map_err(|err| JsError::new(&err.to_string()))?;
     ryan_to_js(&value)
 }
 
@@ -266,7 +264,7 @@ impl ryan::environment::ImportLoader for JsLoader {
         Ok(Box::new(std::io::Cursor::new(
             current.as_string().ok_or_else(|| {
                 Box::new(ImportError {
-                    error: format!("Resolved module cannot be represented in UTF-8"),
+                    error: "Resolved module cannot be represented in UTF-8".to_string(),
                 })
             })?,
         )))
@@ -0,0 +1,41 @@
+//! Applying pattern matches that capture many variables. Applying a pattern match should
+//! cost the same, however many variables it captures.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ryan::{environment::Environment, parser};
+
+/// A pattern match that captures `captures` variables, applied 100k times in a
+/// comprehension.
+fn source(captures: usize) -> String {
+    let mut source = String::new();
+
+    for i in 0..captures {
+        source.push_str(&format!("let c{i} = {i};\n"));
+    }
+
+    let sum = (0..captures)
+        .map(|i| format!("c{i}"))
+        .collect::<Vec<_>>()
+        .join(" + ");
+    source.push_str(&format!("let f x = if x < 0 then {sum} else x;\n"));
+    source.push_str("[f x for x in range [0, 100000]]\n");
+
+    source
+}
+
+fn apply_captured_pattern(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply_captured_pattern");
+    group.sample_size(10);
+
+    for captures in [1, 10, 100] {
+        let block = parser::parse(&source(captures)).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(captures), &block, |b, block| {
+            b.iter(|| parser::eval(Environment::new(None), block).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, apply_captured_pattern);
+criterion_main!(benches);
@@ -0,0 +1,15 @@
+//! Escapes arbitrary strings and reads them back, and unescapes arbitrary input. Run with
+//! `cargo +nightly fuzz run escape_unescape` from the `ryan` directory.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    // Every string reads back as itself:
+    let escaped = ryan::text::escape(input);
+    assert_eq!(ryan::text::unescape(&escaped).as_deref(), Ok(input));
+
+    // Anything else is either a string or an error, never a panic:
+    let _ = ryan::text::unescape(input);
+});
@@ -0,0 +1,34 @@
+//! Parses and evaluates arbitrary input. Whatever the input, Ryan must return an error
+//! instead of panicking. Run with `cargo +nightly fuzz run parse_eval` from the `ryan`
+//! directory.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ryan::environment::NoImport;
+use ryan::Environment;
+
+fuzz_target!(|source: &str| {
+    let block = match ryan::parser::parse(source) {
+        Ok(block) => block,
+        Err(err) => {
+            // Rendering the error points into the source, which must not panic either.
+            let _ = err.to_string();
+            return;
+        }
+    };
+
+    // Printing the syntax tree back must not panic either:
+    let _ = block.to_string();
+    let _ = ryan::fmt::format(source);
+
+    let environment = Environment::builder().import_loader(NoImport).build();
+    match ryan::parser::eval(environment, &block) {
+        Ok(value) => {
+            let _ = value.to_string();
+        }
+        Err(err) => {
+            let _ = err.to_string();
+        }
+    }
+});
@@ -0,0 +1,119 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use crate::parser::{PathSeg, Value};
+
+/// How to canonicalize a Ryan value with [`Value::canonicalize`]. By default, nothing is
+/// changed.
+#[derive(Debug, Clone, Default)]
+pub struct CanonicalizeOptions {
+    /// Whether to sort the keys of every dictionary, recursively. Keys are compared
+    /// byte by byte.
+    pub sort_keys: bool,
+    /// The path of keys by which to sort every list of dictionaries, e.g., `["name"]` or
+    /// `["metadata", "name"]`. Elements without this path are kept at the end of the list,
+    /// in their original order, and reported as a warning.
+    pub sort_lists_by: Option<Vec<Rc<str>>>,
+}
+
+/// The outcome of [`Value::canonicalize`].
+#[derive(Debug, Clone)]
+pub struct Canonicalized {
+    /// The canonicalized value.
+    pub value: Value,
+    /// Human-readable descriptions of what could not be canonicalized.
+    pub warnings: Vec<String>,
+}
+
+impl Value {
+    /// Rewrites this value in a stable form, so that the same configuration always
+    /// serializes to the same output, e.g., regardless of the order in which files were
+    /// listed by the operating system. All sorts are stable.
+    pub fn canonicalize(&self, options: CanonicalizeOptions) -> Canonicalized {
+        let warnings = RefCell::new(vec![]);
+
+        let value = self.clone().map(|path, value| match value {
+            Value::Map(map) if options.sort_keys => {
+                let mut sorted = (*map).clone();
+                sorted.sort_keys();
+                Value::Map(Rc::new(sorted))
+            }
+            Value::List(list) => match &options.sort_lists_by {
+                Some(key_path) => sort_list(path, list, key_path, &mut warnings.borrow_mut()),
+                None => Value::List(list),
+            },
+            value => value,
+        });
+
+        Canonicalized {
+            value,
+            warnings: warnings.into_inner(),
+        }
+    }
+}
+
+/// Sorts a list of dictionaries by the value found at `key_path` in each of them. Lists
+/// with anything other than dictionaries are left untouched.
+fn sort_list(
+    path: &[PathSeg],
+    list: Rc<[Value]>,
+    key_path: &[Rc<str>],
+    warnings: &mut Vec<String>,
+) -> Value {
+    if list.is_empty() || !list.iter().all(|item| matches!(item, Value::Map(_))) {
+        return Value::List(list);
+    }
+
+    let mut keyed = vec![];
+    let mut missing = vec![];
+
+    for (idx, item) in list.iter().enumerate() {
+        match sort_key(item, key_path) {
+            Some(key) => keyed.push((key, item)),
+            None => missing.push(idx),
+        }
+    }
+
+    if !missing.is_empty() {
+        warnings.push(format!(
+            "List at {} has elements without the key {} at indices {}: these were kept at \
+            the end of the list",
+            fmt_path(path),
+            key_path.join("."),
+            missing
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+        ));
+    }
+
+    keyed.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+    keyed
+        .into_iter()
+        .map(|(_, item)| item.clone())
+        .chain(missing.iter().map(|&idx| list[idx].clone()))
+        .collect()
+}
+
+fn sort_key<'a>(item: &'a Value, key_path: &[Rc<str>]) -> Option<&'a Value> {
+    key_path.iter().try_fold(item, |value, key| match value {
+        Value::Map(map) => map.get(key),
+        _ => None,
+    })
+}
+
+fn fmt_path(path: &[PathSeg]) -> String {
+    if path.is_empty() {
+        return "the root".to_string();
+    }
+
+    path.iter()
+        .map(|seg| match seg {
+            PathSeg::Key(key) => format!("[{key:?}]"),
+            PathSeg::Index(idx) => format!("[{idx}]"),
+        })
+        .collect()
+}
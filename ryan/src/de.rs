@@ -1,5 +1,7 @@
 use std::borrow::Cow;
 use std::fmt::Display;
+use std::rc::Rc;
+use std::str::FromStr;
 
 use serde::de::value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer, StrDeserializer};
 use serde::de::{IntoDeserializer, Visitor};
@@ -46,7 +48,7 @@ impl Display for MaterializedType {
             MaterializedType::F64 => "a double precision float",
             MaterializedType::Char => "a single character",
             MaterializedType::String => "text",
-            MaterializedType::Bytes => "a list of 8-bit unsigned integers",
+            MaterializedType::Bytes => "bytes or a list of 8-bit unsigned integers",
             MaterializedType::List => "a list of values",
             MaterializedType::Map => "a key-value map",
             MaterializedType::Enum => "an enumeration",
@@ -56,6 +58,22 @@ impl Display for MaterializedType {
     }
 }
 
+impl MaterializedType {
+    /// The smallest and the largest integers of this type that a Ryan integer can hold.
+    fn integer_range(self) -> (i64, i64) {
+        match self {
+            MaterializedType::I8 => (i8::MIN.into(), i8::MAX.into()),
+            MaterializedType::I16 => (i16::MIN.into(), i16::MAX.into()),
+            MaterializedType::I32 => (i32::MIN.into(), i32::MAX.into()),
+            MaterializedType::U8 => (0, u8::MAX.into()),
+            MaterializedType::U16 => (0, u16::MAX.into()),
+            MaterializedType::U32 => (0, u32::MAX.into()),
+            MaterializedType::U64 => (0, i64::MAX),
+            _ => (i64::MIN, i64::MAX),
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum DecodeError {
     #[error("{0}")]
@@ -72,8 +90,23 @@ pub enum DecodeError {
         expected: MaterializedType,
         got: i64,
     },
+    #[error("expected {expected} but got the number {got}, which is not a whole number")]
+    NotWholeNumber {
+        expected: MaterializedType,
+        got: f64,
+    },
+    #[error("cannot fit the number {got} into {expected}")]
+    FloatRangeError {
+        expected: MaterializedType,
+        got: f64,
+    },
     #[error("expected list of length {expected} but got list of length {got}")]
     LengthMismatch { expected: usize, got: usize },
+    #[error("expected {expected} as the key of a map but got the key {key:?}")]
+    KeyError {
+        expected: MaterializedType,
+        key: Rc<str>,
+    },
 }
 
 impl serde::de::Error for DecodeError {
@@ -84,6 +117,47 @@ impl serde::de::Error for DecodeError {
 
 pub struct RyanDeserializer<'de> {
     pub(crate) value: Cow<'de, Value>,
+    /// Whether floats with no fractional part can be deserialized as integers.
+    pub(crate) lenient: bool,
+}
+
+impl<'de> RyanDeserializer<'de> {
+    fn child(&self, value: Value) -> Self {
+        RyanDeserializer {
+            value: Cow::Owned(value),
+            lenient: self.lenient,
+        }
+    }
+
+    /// The value to be deserialized as an integer of a given type. In lenient mode,
+    /// floats with no fractional part become the integer they are equal to, if it fits
+    /// the type.
+    fn integral(&self, expected: MaterializedType) -> Result<Cow<'_, Value>, DecodeError> {
+        match &*self.value {
+            &Value::Float(float) if self.lenient => {
+                if float.fract() != 0.0 {
+                    // This includes `NaN` and the infinities, whose `fract` is `NaN`.
+                    return Err(DecodeError::NotWholeNumber {
+                        expected,
+                        got: float,
+                    });
+                }
+
+                // Adding one is exact for all ranges but the 64-bit ones, where the maximum
+                // is rounded up to 2^63, the first float out of range, anyway.
+                let (min, max) = expected.integer_range();
+                if float < min as f64 || float >= max as f64 + 1.0 {
+                    return Err(DecodeError::FloatRangeError {
+                        expected,
+                        got: float,
+                    });
+                }
+
+                Ok(Cow::Owned(Value::Integer(float as i64)))
+            }
+            value => Ok(Cow::Borrowed(value)),
+        }
+    }
 }
 
 impl<'de> IntoDeserializer<'de, DecodeError> for RyanDeserializer<'de> {
@@ -106,6 +180,7 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
             Value::Integer(_) => self.deserialize_i64(visitor),
             Value::Float(_) => self.deserialize_f64(visitor),
             Value::Text(_) => self.deserialize_str(visitor),
+            Value::Bytes(_) => self.deserialize_bytes(visitor),
             Value::List(_) => self.deserialize_seq(visitor),
             Value::Map(_) => self.deserialize_map(visitor),
             v => Err(DecodeError::DeserializeAnyError {
@@ -131,7 +206,8 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        match &*self.value {
+        let value = self.integral(MaterializedType::I8)?;
+        match &*value {
             &Value::Integer(int) if int as i8 as i64 == int => visitor.visit_i8(int as i8),
             &Value::Integer(int) => Err(DecodeError::RangeError {
                 expected: MaterializedType::I8,
@@ -148,7 +224,8 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        match &*self.value {
+        let value = self.integral(MaterializedType::I16)?;
+        match &*value {
             &Value::Integer(int) if int as i16 as i64 == int => visitor.visit_i16(int as i16),
             &Value::Integer(int) => Err(DecodeError::RangeError {
                 expected: MaterializedType::I16,
@@ -165,7 +242,8 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        match &*self.value {
+        let value = self.integral(MaterializedType::I32)?;
+        match &*value {
             &Value::Integer(int) if int as i32 as i64 == int => visitor.visit_i32(int as i32),
             &Value::Integer(int) => Err(DecodeError::RangeError {
                 expected: MaterializedType::I32,
@@ -182,7 +260,8 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        match &*self.value {
+        let value = self.integral(MaterializedType::I64)?;
+        match &*value {
             &Value::Integer(int) => visitor.visit_i64(int),
             v => Err(DecodeError::TypeError {
                 expected: MaterializedType::I64,
@@ -195,7 +274,8 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        match &*self.value {
+        let value = self.integral(MaterializedType::U8)?;
+        match &*value {
             &Value::Integer(int) if int as u8 as i64 == int => visitor.visit_u8(int as u8),
             &Value::Integer(int) => Err(DecodeError::RangeError {
                 expected: MaterializedType::U8,
@@ -212,7 +292,8 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        match &*self.value {
+        let value = self.integral(MaterializedType::U16)?;
+        match &*value {
             &Value::Integer(int) if int as u16 as i64 == int => visitor.visit_u16(int as u16),
             &Value::Integer(int) => Err(DecodeError::RangeError {
                 expected: MaterializedType::U16,
@@ -229,7 +310,8 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        match &*self.value {
+        let value = self.integral(MaterializedType::U32)?;
+        match &*value {
             &Value::Integer(int) if int as u32 as i64 == int => visitor.visit_u32(int as u32),
             &Value::Integer(int) => Err(DecodeError::RangeError {
                 expected: MaterializedType::U32,
@@ -246,7 +328,8 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        match &*self.value {
+        let value = self.integral(MaterializedType::U64)?;
+        match &*value {
             &Value::Integer(int) if int as u64 as i64 == int => visitor.visit_u64(int as u64),
             &Value::Integer(int) => Err(DecodeError::RangeError {
                 expected: MaterializedType::U64,
@@ -264,7 +347,9 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
         V: Visitor<'de>,
     {
         match &*self.value {
-            &Value::Integer(int) if int as f32 as i64 == int => visitor.visit_f32(int as f32),
+            &Value::Integer(int) if int as f32 as i128 == int as i128 => {
+                visitor.visit_f32(int as f32)
+            }
             &Value::Integer(int) => Err(DecodeError::RangeError {
                 expected: MaterializedType::F32,
                 got: int,
@@ -282,7 +367,9 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
         V: Visitor<'de>,
     {
         match &*self.value {
-            &Value::Integer(int) if int as f64 as i64 == int => visitor.visit_f64(int as f64),
+            &Value::Integer(int) if int as f64 as i128 == int as i128 => {
+                visitor.visit_f64(int as f64)
+            }
             &Value::Integer(int) => Err(DecodeError::RangeError {
                 expected: MaterializedType::F64,
                 got: int,
@@ -335,6 +422,7 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
         V: Visitor<'de>,
     {
         match &*self.value {
+            Value::Bytes(bytes) => visitor.visit_bytes(bytes),
             Value::List(list) => {
                 let bytes = list
                     .iter()
@@ -373,7 +461,7 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
     {
         match &*self.value {
             Value::Null => visitor.visit_none(),
-            _ => visitor.visit_some(Self { value: self.value }),
+            _ => visitor.visit_some(self),
         }
     }
 
@@ -418,11 +506,13 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
     {
         match &*self.value {
             Value::List(list) => {
-                let values = list.iter().map(|item| Self {
-                    value: Cow::Owned(item.clone()),
-                });
+                let values = list.iter().map(|item| self.child(item.clone()));
                 visitor.visit_seq(SeqDeserializer::new(values))
             }
+            // So that bytes can be deserialized as, e.g., `Vec<u8>`:
+            Value::Bytes(bytes) => visitor.visit_seq(SeqDeserializer::<_, DecodeError>::new(
+                bytes.iter().copied(),
+            )),
             v => Err(DecodeError::TypeError {
                 expected: MaterializedType::List,
                 got: v.canonical_type(),
@@ -436,6 +526,7 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
     {
         match &*self.value {
             Value::List(list) if list.len() == len => self.deserialize_seq(visitor),
+            Value::Bytes(bytes) if bytes.len() == len => self.deserialize_seq(visitor),
             Value::List(list) => Err(DecodeError::LengthMismatch {
                 expected: len,
                 got: list.len(),
@@ -465,16 +556,9 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
     {
         match &*self.value {
             Value::Map(dict) => {
-                let values = dict.iter().map(|(key, item)| {
-                    (
-                        Self {
-                            value: Cow::Owned(Value::Text(key.clone())),
-                        },
-                        Self {
-                            value: Cow::Owned(item.clone()),
-                        },
-                    )
-                });
+                let values = dict
+                    .iter()
+                    .map(|(key, item)| (KeyDeserializer(key.clone()), self.child(item.clone())));
                 visitor.visit_map(MapDeserializer::new(values))
             }
             v => Err(DecodeError::TypeError {
@@ -510,12 +594,8 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
             Value::Map(dict) => {
                 let values = dict.iter().map(|(key, item)| {
                     (
-                        Self {
-                            value: Cow::Owned(Value::Text(key.clone())),
-                        },
-                        Self {
-                            value: Cow::Owned(item.clone()),
-                        },
+                        self.child(Value::Text(key.clone())),
+                        self.child(item.clone()),
                     )
                 });
                 visitor.visit_enum(MapAccessDeserializer::new(MapDeserializer::new(values)))
@@ -541,3 +621,162 @@ impl<'de> Deserializer<'de> for RyanDeserializer<'de> {
         self.deserialize_any(visitor)
     }
 }
+
+/// Deserializes the keys of a map, which are always text in Ryan, into whatever the map
+/// is keyed by. Keys of integers, booleans and characters are parsed from the text, e.g.,
+/// `"8080"` is a valid key of a `HashMap<u16, _>`.
+struct KeyDeserializer(Rc<str>);
+
+impl KeyDeserializer {
+    fn parse<T: FromStr>(&self, expected: MaterializedType) -> Result<T, DecodeError> {
+        self.0.parse().map_err(|_| DecodeError::KeyError {
+            expected,
+            key: self.0.clone(),
+        })
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DecodeError> for KeyDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for KeyDeserializer {
+    type Error = DecodeError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_str(&self.0)
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_bool(self.parse(MaterializedType::Bool)?)
+    }
+
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i8(self.parse(MaterializedType::I8)?)
+    }
+
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i16(self.parse(MaterializedType::I16)?)
+    }
+
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i32(self.parse(MaterializedType::I32)?)
+    }
+
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i64(self.parse(MaterializedType::I64)?)
+    }
+
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u8(self.parse(MaterializedType::U8)?)
+    }
+
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u16(self.parse(MaterializedType::U16)?)
+    }
+
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u32(self.parse(MaterializedType::U32)?)
+    }
+
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u64(self.parse(MaterializedType::U64)?)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_char(self.parse(MaterializedType::Char)?)
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_str(&self.0)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(StrDeserializer::new(&self.0))
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        f32 f64 bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        ignored_any
+    }
+}
@@ -0,0 +1,131 @@
+use indexmap::IndexMap;
+use std::rc::Rc;
+
+use crate::parser::{PathSeg, Value};
+use crate::rc_world;
+
+/// A single difference between two Ryan values, as returned by [`diff`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffEntry {
+    /// Where the difference is, starting from the root of the compared values.
+    pub path: Vec<PathSeg>,
+    /// What the difference is.
+    pub kind: DiffKind,
+}
+
+/// The kinds of difference between two Ryan values.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffKind {
+    /// A value exists only in the new value.
+    Added(Value),
+    /// A value exists only in the old value.
+    Removed(Value),
+    /// A value exists in both, but is different (possibly even of a different type).
+    Changed {
+        /// The value in the old value.
+        from: Value,
+        /// The value in the new value.
+        to: Value,
+    },
+}
+
+impl DiffEntry {
+    /// Represents this entry as a Ryan dictionary, with the path as a list of keys and
+    /// indices, e.g., `{ path: ["a", 0], kind: "changed", from: 1, to: 2 }`.
+    pub fn to_value(&self) -> Value {
+        let path: Value = self
+            .path
+            .iter()
+            .map(|seg| match seg {
+                PathSeg::Key(key) => Value::Text(key.clone()),
+                PathSeg::Index(idx) => Value::Integer(*idx as i64),
+            })
+            .collect();
+
+        let mut map = IndexMap::new();
+        map.insert(rc_world::str_to_rc("path"), path);
+
+        let mut insert = |key: &str, value: Value| map.insert(rc_world::str_to_rc(key), value);
+        match &self.kind {
+            DiffKind::Added(value) => {
+                insert("kind", Value::Text(rc_world::str_to_rc("added")));
+                insert("value", value.clone());
+            }
+            DiffKind::Removed(value) => {
+                insert("kind", Value::Text(rc_world::str_to_rc("removed")));
+                insert("value", value.clone());
+            }
+            DiffKind::Changed { from, to } => {
+                insert("kind", Value::Text(rc_world::str_to_rc("changed")));
+                insert("from", from.clone());
+                insert("to", to.clone());
+            }
+        }
+
+        Value::Map(Rc::new(map))
+    }
+}
+
+/// Lists the differences between an old value `a` and a new value `b`. Dictionaries are
+/// compared key by key, disregarding the order of the keys, and lists are compared
+/// position by position. Anything else is compared using Ryan's equality, so `1` and
+/// `1.0` are not reported as different.
+pub fn diff(a: &Value, b: &Value) -> Vec<DiffEntry> {
+    let mut entries = vec![];
+    diff_at(&mut vec![], a, b, &mut entries);
+    entries
+}
+
+fn diff_at(path: &mut Vec<PathSeg>, a: &Value, b: &Value, entries: &mut Vec<DiffEntry>) {
+    match (a, b) {
+        (Value::Map(a), Value::Map(b)) => {
+            for (key, old) in a.iter() {
+                path.push(PathSeg::Key(key.clone()));
+                if let Some(new) = b.get(key) {
+                    diff_at(path, old, new, entries);
+                } else {
+                    entries.push(DiffEntry {
+                        path: path.clone(),
+                        kind: DiffKind::Removed(old.clone()),
+                    });
+                }
+                path.pop();
+            }
+
+            for (key, new) in b.iter().filter(|(key, _)| !a.contains_key(*key)) {
+                path.push(PathSeg::Key(key.clone()));
+                entries.push(DiffEntry {
+                    path: path.clone(),
+                    kind: DiffKind::Added(new.clone()),
+                });
+                path.pop();
+            }
+        }
+        (Value::List(a), Value::List(b)) => {
+            for idx in 0..a.len().max(b.len()) {
+                path.push(PathSeg::Index(idx));
+                match (a.get(idx), b.get(idx)) {
+                    (Some(old), Some(new)) => diff_at(path, old, new, entries),
+                    (Some(old), None) => entries.push(DiffEntry {
+                        path: path.clone(),
+                        kind: DiffKind::Removed(old.clone()),
+                    }),
+                    (None, Some(new)) => entries.push(DiffEntry {
+                        path: path.clone(),
+                        kind: DiffKind::Added(new.clone()),
+                    }),
+                    (None, None) => unreachable!(),
+                }
+                path.pop();
+            }
+        }
+        (a, b) if a != b => entries.push(DiffEntry {
+            path: path.clone(),
+            kind: DiffKind::Changed {
+                from: a.clone(),
+                to: b.clone(),
+            },
+        }),
+        _ => {}
+    }
+}
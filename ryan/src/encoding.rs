@@ -0,0 +1,182 @@
+use std::borrow::Cow;
+use std::fmt::{self, Display};
+
+/// The content of a file or of a module is not valid text. This tells where the first
+/// invalid sequence is and, if it can be told, what the content looks like instead.
+///
+/// ```
+/// let err = ryan::decode_text(b"{\n    name: \"caf\xe9\"\n}").unwrap_err();
+/// assert_eq!((err.offset, err.line, err.column), (16, 2, 15));
+/// assert_eq!(
+///     err.to_string(),
+///     "Invalid UTF-8 at byte 16 (line 2, column 15); it looks like Latin-1 or Windows-1252",
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodingError {
+    /// The file or module with the content, if known.
+    pub path: Option<String>,
+    /// The encoding in which the content was read, e.g., `UTF-8`.
+    pub encoding: &'static str,
+    /// The offset of the first invalid byte, counting any byte order mark.
+    pub offset: usize,
+    /// The line of the first invalid byte, starting at one.
+    pub line: usize,
+    /// The column of the first invalid byte, in characters, starting at one.
+    pub column: usize,
+    /// What is wrong with the content, if it can be told.
+    pub hint: Option<&'static str>,
+}
+
+impl Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid {} at byte {} (line {}, column {})",
+            self.encoding, self.offset, self.line, self.column
+        )?;
+
+        if let Some(path) = &self.path {
+            write!(f, " in {path}")?;
+        }
+
+        if let Some(hint) = self.hint {
+            write!(f, "; {hint}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for EncodingError {}
+
+impl EncodingError {
+    /// Builds the error for the first invalid byte at `offset`, after the already decoded
+    /// `text`.
+    fn new(text: &str, encoding: &'static str, offset: usize, hint: Option<&'static str>) -> Self {
+        let line_start = text.rfind('\n').map_or(0, |idx| idx + 1);
+
+        EncodingError {
+            path: None,
+            encoding,
+            offset,
+            line: text.matches('\n').count() + 1,
+            column: text[line_start..].chars().count() + 1,
+            hint,
+        }
+    }
+
+    /// Names the file or module where the error happened.
+    pub(crate) fn in_module(mut self, path: Option<&str>) -> Self {
+        self.path = path.map(str::to_owned);
+        self
+    }
+}
+
+/// Decodes the content of a Ryan file. The content is UTF-8, unless it starts with a
+/// byte order mark for UTF-16, either little or big endian. A byte order mark for UTF-8
+/// is also accepted and removed.
+///
+/// ```
+/// let utf16 = b"\xff\xfe1\x002\x00";
+/// assert_eq!(ryan::decode_text(utf16).unwrap(), "12");
+/// assert_eq!(ryan::decode_text(b"\xef\xbb\xbf12").unwrap(), "12");
+/// ```
+pub fn decode_text(bytes: &[u8]) -> Result<Cow<'_, str>, EncodingError> {
+    if let Some(rest) = bytes.strip_prefix(b"\xef\xbb\xbf") {
+        decode_utf8(rest, 3)
+    } else if let Some(rest) = bytes.strip_prefix(b"\xff\xfe") {
+        decode_utf16(rest, "UTF-16LE", u16::from_le_bytes).map(Cow::Owned)
+    } else if let Some(rest) = bytes.strip_prefix(b"\xfe\xff") {
+        decode_utf16(rest, "UTF-16BE", u16::from_be_bytes).map(Cow::Owned)
+    } else {
+        decode_utf8(bytes, 0)
+    }
+}
+
+/// Decodes UTF-8 coming after a byte order mark of `bom_len` bytes.
+fn decode_utf8(bytes: &[u8], bom_len: usize) -> Result<Cow<'_, str>, EncodingError> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Ok(Cow::Borrowed(text)),
+        Err(err) => {
+            let valid_up_to = err.valid_up_to();
+            let text = std::str::from_utf8(&bytes[..valid_up_to])
+                .expect("the content is valid up to this point");
+            let hint = if err.error_len().is_none() {
+                Some("it ends in the middle of a character")
+            } else {
+                Some(guess_encoding(bytes))
+            };
+
+            Err(EncodingError::new(
+                text,
+                "UTF-8",
+                bom_len + valid_up_to,
+                hint,
+            ))
+        }
+    }
+}
+
+/// Decodes UTF-16 coming after its byte order mark.
+fn decode_utf16(
+    bytes: &[u8],
+    encoding: &'static str,
+    from_bytes: fn([u8; 2]) -> u16,
+) -> Result<String, EncodingError> {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+    let mut text = String::with_capacity(bytes.len() / 2);
+    let mut offset = 2;
+
+    for ch in char::decode_utf16(units) {
+        match ch {
+            Ok(ch) => {
+                text.push(ch);
+                offset += 2 * ch.len_utf16();
+            }
+            Err(_) => {
+                return Err(EncodingError::new(
+                    &text,
+                    encoding,
+                    offset,
+                    Some("it has a lone surrogate"),
+                ))
+            }
+        }
+    }
+
+    if !bytes.len().is_multiple_of(2) {
+        return Err(EncodingError::new(
+            &text,
+            encoding,
+            offset,
+            Some("it ends in the middle of a character"),
+        ));
+    }
+
+    Ok(text)
+}
+
+/// Tells what invalid UTF-8 looks like. Text in UTF-16 saved without a byte order mark
+/// has a zero byte for every ASCII character, while anything else is most likely in one
+/// of the usual single byte encodings.
+fn guess_encoding(bytes: &[u8]) -> &'static str {
+    let zeros_at = |parity| {
+        bytes
+            .iter()
+            .skip(parity)
+            .step_by(2)
+            .filter(|&&byte| byte == 0)
+            .count()
+    };
+
+    if zeros_at(1) * 4 > bytes.len() {
+        "it looks like UTF-16LE without a byte order mark"
+    } else if zeros_at(0) * 4 > bytes.len() {
+        "it looks like UTF-16BE without a byte order mark"
+    } else {
+        "it looks like Latin-1 or Windows-1252"
+    }
+}
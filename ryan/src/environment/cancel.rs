@@ -0,0 +1,42 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A flag to stop an evaluation from another thread, as set with
+/// [`crate::environment::EnvironmentBuilder::cancel_token`]. Clones share the same flag,
+/// so keep a clone and call [`CancellationToken::cancel`] on it to stop evaluating.
+///
+/// Cancellation is cooperative: it is checked before each element of lists, dictionaries
+/// and comprehensions, before each binding and before each import, and the evaluation
+/// fails with [`crate::parser::EvalErrorKind::Cancelled`] at the first check after the
+/// token is cancelled. A single built-in call that takes long, e.g., a huge `range`, is
+/// not interrupted.
+///
+/// ```
+/// use ryan::environment::{CancellationToken, Environment};
+///
+/// let token = CancellationToken::new();
+/// let env = Environment::builder().cancel_token(token.clone()).build();
+///
+/// token.cancel();
+/// assert!(ryan::from_str_with_env::<Vec<i64>>(&env, "[1, 2, 3]").is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Cancels every evaluation using this token or any of its clones. This cannot be
+    /// undone: create a new token for the next evaluation.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether this token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
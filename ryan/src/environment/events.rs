@@ -0,0 +1,156 @@
+use std::{
+    fmt::{self, Debug, Display},
+    rc::Rc,
+};
+
+use crate::parser::{Format, Value};
+use crate::rc_world;
+
+/// Something that happened while a Ryan program was being evaluated. Hosts can listen to
+/// these events by installing a hook with [`super::EnvironmentBuilder::on_event`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalEvent {
+    /// A module is about to be read by the import loader.
+    ImportStarted {
+        /// The resolved path of the module.
+        path: Rc<str>,
+        /// The format in which the module is being imported.
+        format: Format,
+    },
+    /// A module was imported successfully.
+    ImportLoaded {
+        /// The resolved path of the module, even if the value came from the cache.
+        path: Rc<str>,
+        /// The format in which the module was imported.
+        format: Format,
+        /// Whether the value was served from the cache of already loaded modules.
+        cache_hit: bool,
+    },
+    /// A module could not be imported after [`EvalEvent::ImportStarted`], e.g., because it
+    /// does not parse. The program may still carry on if the import has a default, as in
+    /// `import "x.ryan" or {}`.
+    ImportFailed {
+        /// The resolved path of the module.
+        path: Rc<str>,
+        /// The format in which the module was being imported.
+        format: Format,
+        /// Why the module could not be imported.
+        error: Rc<str>,
+    },
+    /// A non-fatal issue was found. Evaluation carries on as usual.
+    Warning {
+        /// A human-readable description of the issue.
+        message: Rc<str>,
+    },
+    /// A variable was bound by a `let`, e.g., `let x = 1;` or each of the variables of
+    /// `let [a, b] = [1, 2];`. This is only sent when tracing, as per
+    /// [`super::EnvironmentBuilder::trace`].
+    BindingEvaluated {
+        /// The name of the variable.
+        name: Rc<str>,
+        /// The value bound to the variable, shortened if too long.
+        value_summary: Rc<str>,
+    },
+    /// A rule of a pattern match was tried on an argument. Rules are tried in the order
+    /// in which they were defined, until one matches. This is only sent when tracing, as
+    /// per [`super::EnvironmentBuilder::trace`].
+    PatternRuleTried {
+        /// The name of the pattern match.
+        name: Rc<str>,
+        /// The position of the rule among the rules of the pattern match, starting at
+        /// zero.
+        rule_index: usize,
+        /// Whether the pattern (and the guard, if any) matched the argument.
+        matched: bool,
+    },
+    /// The condition of an `if ... then ... else ...` was evaluated and one of the
+    /// branches is about to be evaluated. This is only sent when tracing, as per
+    /// [`super::EnvironmentBuilder::trace`].
+    ConditionalTaken {
+        /// The branch being evaluated.
+        branch: Branch,
+    },
+}
+
+/// A branch of an `if ... then ... else ...`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Branch {
+    /// The expression after `then`, taken when the condition is `true`.
+    Then,
+    /// The expression after `else`, taken when the condition is `false`.
+    Else,
+}
+
+impl Display for Branch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Then => write!(f, "then"),
+            Self::Else => write!(f, "else"),
+        }
+    }
+}
+
+impl Display for EvalEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ImportStarted { path, format } => {
+                write!(f, "Importing {path:?} as {format}")
+            }
+            Self::ImportLoaded {
+                path,
+                format,
+                cache_hit: false,
+            } => write!(f, "Imported {path:?} as {format}"),
+            Self::ImportLoaded {
+                path,
+                format,
+                cache_hit: true,
+            } => write!(f, "Imported {path:?} as {format} (cached)"),
+            Self::ImportFailed {
+                path,
+                format,
+                error,
+            } => write!(f, "Failed to import {path:?} as {format}: {error}"),
+            Self::Warning { message } => write!(f, "{message}"),
+            Self::BindingEvaluated {
+                name,
+                value_summary,
+            } => write!(f, "let {name} = {value_summary}"),
+            Self::PatternRuleTried {
+                name,
+                rule_index,
+                matched: true,
+            } => write!(f, "{name}: rule #{rule_index} matched"),
+            Self::PatternRuleTried {
+                name,
+                rule_index,
+                matched: false,
+            } => write!(f, "{name}: rule #{rule_index} did not match"),
+            Self::ConditionalTaken { branch } => write!(f, "if: took `{branch}`"),
+        }
+    }
+}
+
+/// A callback receiving every [`EvalEvent`] emitted during evaluation.
+#[derive(Clone)]
+pub(crate) struct EventHook(pub(crate) Rc<dyn Fn(EvalEvent)>);
+
+impl Debug for EventHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EventHook")
+    }
+}
+
+/// The longest value summary in a [`EvalEvent::BindingEvaluated`], in characters.
+const MAX_SUMMARY_LEN: usize = 80;
+
+/// Writes a value for a tracing event, cutting it short if it is too long.
+pub(crate) fn summarize(value: &Value) -> Rc<str> {
+    let mut summary = value.to_string();
+    if let Some((idx, _)) = summary.char_indices().nth(MAX_SUMMARY_LEN) {
+        summary.truncate(idx);
+        summary.push_str("...");
+    }
+
+    rc_world::string_to_rc(summary)
+}
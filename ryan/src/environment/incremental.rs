@@ -0,0 +1,147 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fmt::{self, Display},
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+use super::loader::ModuleKey;
+use crate::parser::Value;
+
+/// How many modules the last incremental evaluation of a program reused and how many it
+/// had to evaluate again, as returned by [`crate::parser::Program::incremental_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IncrementalStats {
+    /// The number of imports served from a previous evaluation, since nothing they read
+    /// changed in the meantime.
+    pub reused: usize,
+    /// The number of imports evaluated again, because they were never evaluated before,
+    /// something they read changed or they cannot be safely reused.
+    pub recomputed: usize,
+}
+
+impl Display for IncrementalStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "reused: {}, recomputed: {}",
+            self.reused, self.recomputed
+        )
+    }
+}
+
+/// What a module read while being evaluated, directly or through its own imports: each
+/// resolved path with the hash of its content, or `None` if it could not be read.
+type Inputs = Vec<(Rc<str>, Option<u64>)>;
+
+/// A module being evaluated.
+#[derive(Debug, Default)]
+struct Frame {
+    inputs: Inputs,
+    /// Whether the value of the module might depend on something other than its inputs,
+    /// in which case it is never reused.
+    uncertain: bool,
+}
+
+/// The values of the modules imported by a program, kept across evaluations together with
+/// everything they read, so that modules whose inputs did not change are not evaluated
+/// again.
+#[derive(Debug, Default)]
+pub(crate) struct Memo {
+    modules: HashMap<ModuleKey, (Inputs, Value)>,
+    /// The content of each module as of the current evaluation, read at most once.
+    fingerprints: HashMap<Rc<str>, Option<u64>>,
+    /// The modules being evaluated, starting with the program itself.
+    frames: Vec<Frame>,
+    stats: IncrementalStats,
+}
+
+impl Memo {
+    /// Gets ready for a new evaluation of the program.
+    pub(crate) fn start(&mut self) {
+        self.fingerprints.clear();
+        self.frames = vec![Frame::default()];
+        self.stats = IncrementalStats::default();
+    }
+
+    pub(crate) fn stats(&self) -> IncrementalStats {
+        self.stats
+    }
+
+    fn top(&mut self) -> &mut Frame {
+        self.frames
+            .last_mut()
+            .expect("program frame is never popped")
+    }
+
+    /// Gets the value of a module from a previous evaluation, if nothing it read changed
+    /// since. The current content of each input is read with `read`.
+    pub(super) fn get(
+        &mut self,
+        key: &ModuleKey,
+        mut read: impl FnMut(&str) -> Option<Vec<u8>>,
+    ) -> Option<Value> {
+        let (inputs, value) = self.modules.get(key)?;
+        for (path, fingerprint) in inputs {
+            let current = *self
+                .fingerprints
+                .entry(path.clone())
+                .or_insert_with(|| read(path).as_deref().map(hash_bytes));
+            if current != *fingerprint {
+                return None;
+            }
+        }
+
+        let (inputs, value) = (inputs.clone(), value.clone());
+        self.top().inputs.extend(inputs);
+        self.stats.reused += 1;
+
+        Some(value)
+    }
+
+    /// Starts evaluating a module, which is never reused if it is `uncertain`.
+    pub(super) fn enter(&mut self, uncertain: bool) {
+        self.frames.push(Frame {
+            inputs: vec![],
+            uncertain,
+        });
+    }
+
+    /// Finishes evaluating a module, keeping its value for the next evaluations, unless
+    /// it failed or it is uncertain. Either way, whatever it read was also read by the
+    /// module importing it.
+    pub(super) fn leave(&mut self, key: ModuleKey, value: Option<&Value>) {
+        let frame = self.frames.pop().expect("module frame was pushed");
+
+        if let Some(value) = value {
+            self.stats.recomputed += 1;
+            if !frame.uncertain {
+                self.modules
+                    .insert(key, (frame.inputs.clone(), value.clone()));
+            }
+        }
+
+        let parent = self.top();
+        parent.inputs.extend(frame.inputs);
+        parent.uncertain |= frame.uncertain;
+    }
+
+    /// Records the content of a module, as just read by the module being evaluated.
+    pub(super) fn record_read(&mut self, resolved: &Rc<str>, bytes: Option<&[u8]>) {
+        let fingerprint = bytes.map(hash_bytes);
+        self.fingerprints.insert(resolved.clone(), fingerprint);
+        self.top().inputs.push((resolved.clone(), fingerprint));
+    }
+
+    /// Marks the module being evaluated, and everything importing it, as never to be
+    /// reused, e.g., because it called an effectful native pattern match.
+    pub(super) fn taint(&mut self) {
+        self.top().uncertain = true;
+    }
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
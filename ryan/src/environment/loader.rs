@@ -1,14 +1,20 @@
 use std::{
-    collections::HashMap,
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
     error::Error,
-    fmt::{self, Debug},
+    fmt::{self, Debug, Display},
     io::{Cursor, Read},
     path::PathBuf,
     rc::Rc,
 };
+use indexmap::IndexMap;
 use thiserror::Error;
 
-use crate::{parser::Value, rc_world};
+use crate::{
+    parser::{Block, Format, Type, Value},
+    rc_world,
+    utils::{self, QuotedStr},
+};
 
 /// The loader trait for Ryan.
 pub trait ImportLoader: fmt::Debug {
@@ -24,13 +30,65 @@ pub trait ImportLoader: fmt::Debug {
     /// Resolves an _absolute_ path into a reader, where a Ryan module can be read from.
     fn load(&self, path: &str) -> Result<Box<dyn Read>, Box<dyn Error + 'static>>;
 
+    /// Returns the current version of the module at an _absolute_ path, e.g., an ETag or
+    /// a modification time. Modules are read and parsed only once and reused for as long
+    /// as their version stays the same. The default is `None`, meaning that the module
+    /// never changes, so it is cached for as long as the environment lives (or until
+    /// [`crate::Environment::invalidate`] is called).
+    ///
+    /// If imports are not cached (see [`crate::environment::EnvironmentBuilder::import_cache`]),
+    /// only modules with a version are reused.
+    fn version(&self, _path: &str) -> Option<String> {
+        None
+    }
+
+    /// Returns whether the module at an _absolute_ path, as returned by
+    /// [`ImportLoader::resolve`], may be kept in the cache of loaded modules at all. The default is `true`. Return `false` for paths whose
+    /// content changes between evaluations without having a version, so that they are
+    /// read again on every import. `env:` paths are only cached if
+    /// [`crate::environment::EnvironmentBuilder::cache_env_imports`] is set, whatever this
+    /// returns.
+    ///
+    /// ```
+    /// use std::io::{Cursor, Read};
+    /// use std::error::Error;
+    /// use ryan::environment::{Environment, ImportLoader, NoImport};
+    ///
+    /// /// Serves the time of the day at `now:`, which is never cached.
+    /// #[derive(Debug)]
+    /// struct Clock;
+    ///
+    /// impl ImportLoader for Clock {
+    ///     fn resolve(&self, _: Option<&str>, path: &str) -> Result<String, Box<dyn Error>> {
+    ///         Ok(path.to_owned())
+    ///     }
+    ///
+    ///     fn load(&self, _: &str) -> Result<Box<dyn Read>, Box<dyn Error>> {
+    ///         let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?;
+    ///         Ok(Box::new(Cursor::new(now.as_nanos().to_string())))
+    ///     }
+    ///
+    ///     fn cacheable(&self, path: &str) -> bool {
+    ///         path != "now:"
+    ///     }
+    /// }
+    ///
+    /// let env = Environment::builder().import_loader(Clock).build();
+    /// let first: u64 = ryan::from_str_with_env(&env, r#"import "now:""#).unwrap();
+    /// let second: u64 = ryan::from_str_with_env(&env, r#"import "now:""#).unwrap();
+    /// assert!(second > first);
+    /// ```
+    fn cacheable(&self, _path: &str) -> bool {
+        true
+    }
+
     /// Overrides a single path to be represented by a different model than would be
     /// represented by this loader.
     fn r#override(self, path: String, value: String) -> Override<Self>
     where
         Self: Sized,
     {
-        let mut overrides = HashMap::new();
+        let mut overrides = BTreeMap::new();
         overrides.insert(path, Some(value));
         Override {
             loader: self,
@@ -43,7 +101,7 @@ pub trait ImportLoader: fmt::Debug {
     where
         Self: Sized,
     {
-        let mut overrides = HashMap::new();
+        let mut overrides = BTreeMap::new();
         overrides.insert(path, None);
         Override {
             loader: self,
@@ -51,15 +109,17 @@ pub trait ImportLoader: fmt::Debug {
         }
     }
 
-    /// Overrides the value imported by multiple paths. Pass `None` as the value associated
-    /// to a key in the hashmap to deny access to a given path.
-    fn r#override_many(self, overrides: HashMap<String, Option<String>>) -> Override<Self>
+    /// Overrides the value imported by multiple paths, given as pairs of paths and
+    /// values, e.g., a `HashMap`. Pass `None` as the value associated to a path to deny
+    /// access to it.
+    fn r#override_many<I>(self, overrides: I) -> Override<Self>
     where
         Self: Sized,
+        I: IntoIterator<Item = (String, Option<String>)>,
     {
         Override {
             loader: self,
-            overrides,
+            overrides: overrides.into_iter().collect(),
         }
     }
 
@@ -131,6 +191,33 @@ impl ImportLoader for NoImport {
 /// the one added restriction that `env:` modules don't have access to load regular files.
 /// This happens because the working directory for an environment variable is
 /// ill-defined.
+///
+/// A module ending with `*`, e.g., `env:APP_*`, is a dictionary of all the environment
+/// variables starting with what comes before the `*`, as text. The keys are the names
+/// of the variables without the prefix and in lowercase, sorted. Use `env:*` to get all
+/// environment variables.
+///
+/// Two more pseudo-modules help tools composing Ryan programs on the fly. Like `env:`
+/// modules, they cannot import regular files:
+///
+/// * A `data:` URL holds the content of the module itself, e.g.,
+///   `import "data:text,hello%20world" as text` or `import "data:ryan,1%20%2B%201"`. What
+///   comes before the `,` is only a label, except for a trailing `;base64`, which means
+///   that the content is in base64 instead of percent-encoded. Since the content is in
+///   the path, caching these modules by path is caching them by content.
+/// * `stdin:` is the standard input. Since it can only be read once, importing it twice
+///   in the same environment is an error, and it is never cached.
+///
+/// Use [`ImportLoader::filter`] to block any of these, e.g.,
+/// `DefaultImporter.filter(|path| !path.starts_with("data:"))`.
+///
+/// ```
+/// let text = ryan::from_str::<String>(r#"import "data:text,hello%20world" as text"#);
+/// assert_eq!(text.unwrap(), "hello world");
+///
+/// let sum = ryan::from_str::<i64>(r#"import "data:ryan;base64,MSArIDE=""#);
+/// assert_eq!(sum.unwrap(), 2);
+/// ```
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct DefaultImporter;
 
@@ -140,12 +227,16 @@ impl ImportLoader for DefaultImporter {
         current: Option<&str>,
         path: &str,
     ) -> Result<String, Box<dyn Error + 'static>> {
-        if path.starts_with("env:") {
+        if is_pseudo_module(path) {
             Ok(path.to_owned())
         } else {
             let resolved = if let Some(current) = current {
                 if current.starts_with("env:") {
                     return Err(Box::new(ImportError::CannotAccessFileSystemFromEnv));
+                } else if is_pseudo_module(current) {
+                    return Err(Box::new(
+                        ImportError::CannotAccessFileSystemFromPseudoModule,
+                    ));
                 } else {
                     let mut resolved = PathBuf::from(current);
                     resolved.pop();
@@ -163,63 +254,590 @@ impl ImportLoader for DefaultImporter {
     }
 
     fn load(&self, path: &str) -> Result<Box<dyn Read>, Box<dyn Error + 'static>> {
-        if path.starts_with("env:") {
-            let var = &path["env:".len()..];
-            Ok(Box::new(std::io::Cursor::new(std::env::var(var)?)))
+        if let Some(var) = path.strip_prefix("env:") {
+            if let Some(prefix) = var.strip_suffix('*') {
+                Ok(Box::new(Cursor::new(env_vars_with_prefix(prefix))))
+            } else {
+                match std::env::var(var) {
+                    Ok(value) => Ok(Box::new(Cursor::new(value))),
+                    Err(std::env::VarError::NotPresent) => {
+                        Err(Box::new(ImportError::EnvVarNotFound {
+                            name: rc_world::str_to_rc(var),
+                        }))
+                    }
+                    Err(err) => Err(Box::new(err)),
+                }
+            }
+        } else if path.starts_with("data:") {
+            Ok(Box::new(Cursor::new(decode_data_url(path)?)))
+        } else if path == STDIN {
+            Ok(Box::new(std::io::stdin()))
         } else {
             Ok(Box::new(std::fs::File::open(path)?))
         }
     }
 }
 
+/// The module standing for the standard input.
+pub(super) const STDIN: &str = "stdin:";
+
+/// Whether a module is not a file, i.e., an environment variable, a `data:` URL or the
+/// standard input.
+pub(super) fn is_pseudo_module(module: &str) -> bool {
+    module.starts_with("env:") || module.starts_with("data:") || module == STDIN
+}
+
+/// Decodes the content of a `data:` URL, e.g., `data:text,hello%20world`.
+fn decode_data_url(url: &str) -> Result<Vec<u8>, ImportError> {
+    let invalid = |reason: String| ImportError::InvalidDataUrl { reason };
+    let (header, content) = url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(','))
+        .ok_or_else(|| invalid("expected a `,` before the content".to_string()))?;
+    let decoded = utils::percent_decode(content).map_err(invalid)?;
+
+    if header.ends_with(";base64") {
+        let text = String::from_utf8(decoded)
+            .map_err(|_| invalid("base64 content is not valid text".to_string()))?;
+        utils::base64_decode(&text).map_err(invalid)
+    } else {
+        Ok(decoded)
+    }
+}
+
+/// Writes the environment variables starting with a given prefix as a Ryan dictionary of
+/// text, with the prefix stripped from the keys and the keys in lowercase. Variables
+/// whose names or values are not valid unicode are skipped.
+fn env_vars_with_prefix(prefix: &str) -> String {
+    // Sorting by the original name first makes clashes, like `A_x` and `A_X`, resolve
+    // the same way every time:
+    let vars = std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(name, _)| name.starts_with(prefix))
+        .collect::<BTreeMap<_, _>>();
+    let lowercased = vars
+        .into_iter()
+        .map(|(name, value)| (name[prefix.len()..].to_lowercase(), value))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut code = "{".to_string();
+    for (name, value) in lowercased {
+        code += &format!("{}: {},", QuotedStr(&name), QuotedStr(&value));
+    }
+    code + "}"
+}
+
 /// Errors that can happen while importing a module.
 #[derive(Error, Debug)]
 pub enum ImportError {
-    /// A module tried to, directly or indirectly, import itself.
-    #[error("Circular import detected at {0:?}")]
-    CircularImportDetected(Rc<str>),
+    /// A module tried to, directly or indirectly, import itself. The chain of imports
+    /// starts and ends at the same module.
+    #[error("Circular import detected: {}", fmt_import_chain(.chain))]
+    CircularImportDetected {
+        /// The modules involved in the cycle, in import order.
+        chain: Vec<Rc<str>>,
+    },
+    /// The environment variable of an `env:` module is not set. Unlike other failures to
+    /// read a variable, e.g., one that is not valid unicode, this one can be told apart
+    /// by hosts, e.g., to find out which variables fell back to their defaults.
+    #[error("Environment variable {name} is not set")]
+    EnvVarNotFound {
+        /// The name of the variable, without the `env:` prefix.
+        name: Rc<str>,
+    },
+    /// The evaluation was cancelled before the module was loaded. See
+    /// [`crate::environment::CancellationToken`].
+    #[error("Evaluation was cancelled")]
+    Cancelled,
     /// An environment variable module tried to access the filesystem.
     #[error("Cannot access the filesystem from the environment variable")]
     CannotAccessFileSystemFromEnv,
+    /// A `data:` or `stdin:` module tried to access the filesystem.
+    #[error("Cannot access the filesystem from a `data:` or `stdin:` module")]
+    CannotAccessFileSystemFromPseudoModule,
     /// There is an override for this module and it cannot be accessed.
-    #[error("Cannot access the filesystem from the environment variable")]
+    #[error("Import path {0:?} is blocked")]
     ImportPathIsOverridden(Rc<str>),
+    /// A `data:` URL is malformed, e.g., it has an invalid `%` escape. Like
+    /// [`ImportError::ModuleFailed`], the `or` default of an import does not recover from
+    /// this one.
+    #[error("Invalid data URL: {reason}")]
+    InvalidDataUrl {
+        /// What is wrong with the URL.
+        reason: String,
+    },
+    /// The standard input was imported a second time, after it was read to the end.
+    /// Like [`ImportError::ModuleFailed`], the `or` default of an import does not recover
+    /// from this one.
+    #[error("The standard input can only be imported once")]
+    StdinAlreadyImported,
+    /// The module was found, but its content is broken, e.g., it has a syntax error or
+    /// it fails to evaluate. Unlike the other errors, the `or` default of an import does
+    /// not recover from this one, since it is a bug in the module and not a module that
+    /// is missing.
+    #[error("{source}")]
+    ModuleFailed {
+        /// The resolved path of the module.
+        path: Rc<str>,
+        /// What is wrong with the module.
+        source: Box<dyn Error + 'static>,
+    },
+    /// The module is larger than the maximum set with
+    /// [`super::EnvironmentBuilder::max_import_bytes`]. Like [`ImportError::ModuleFailed`],
+    /// the `or` default of an import does not recover from this one.
+    #[error("Module {path} is larger than the maximum of {max_bytes} bytes for imports")]
+    TooLarge {
+        /// The resolved path of the module.
+        path: Rc<str>,
+        /// The maximum size allowed for modules.
+        max_bytes: usize,
+    },
+    /// The content of the module does not match the digest pinned by the `integrity`
+    /// clause of the import. Like [`ImportError::ModuleFailed`], the `or` default of an
+    /// import does not recover from this one.
+    #[error("Integrity check failed for module {path}: expected {expected}, got {found}")]
+    IntegrityMismatch {
+        /// The resolved path of the module.
+        path: Rc<str>,
+        /// The digest pinned by the import.
+        expected: Rc<str>,
+        /// The digest of the content actually read.
+        found: String,
+    },
+    /// The module of an import with `only` does not have some of the keys to import. Like
+    /// [`ImportError::ModuleFailed`], the `or` default of an import does not recover from
+    /// this one.
+    #[error(
+        "Module {path:?} has no {} {}; its keys are {}",
+        if .missing.len() == 1 { "key" } else { "keys" },
+        fmt_keys(.missing),
+        fmt_keys(.available)
+    )]
+    MissingKeys {
+        /// The import path, as written.
+        path: Rc<str>,
+        /// The keys to import that the module does not have.
+        missing: Vec<Rc<str>>,
+        /// The keys the module has.
+        available: Vec<Rc<str>>,
+    },
+    /// The module of an import with `only` does not evaluate to a dictionary. Like
+    /// [`ImportError::ModuleFailed`], the `or` default of an import does not recover from
+    /// this one.
+    #[error("Module {path:?} must be a dictionary to import only some of its keys, got {found}")]
+    NotADictionary {
+        /// The import path, as written.
+        path: Rc<str>,
+        /// The type of the value of the module.
+        found: Type,
+    },
+    /// An environment variable referenced as `${NAME}` in an import path could not be
+    /// read through the loader, e.g., because it is not set or imports are disabled.
+    #[error("Cannot expand `${{{variable}}}` in import path {path:?}: {source}")]
+    PathVariableUnavailable {
+        /// The name of the environment variable.
+        variable: Rc<str>,
+        /// The import path, as written.
+        path: Rc<str>,
+        /// Why the variable could not be read.
+        source: Box<dyn Error + 'static>,
+    },
+}
+
+impl ImportError {
+    /// Whether the `or` default of an import recovers from this error. Only a missing
+    /// module can be replaced by a default, not one that is broken or untrusted.
+    pub(crate) fn is_recoverable(&self) -> bool {
+        !matches!(
+            self,
+            Self::ModuleFailed { .. }
+                | Self::TooLarge { .. }
+                | Self::IntegrityMismatch { .. }
+                | Self::MissingKeys { .. }
+                | Self::NotADictionary { .. }
+                | Self::InvalidDataUrl { .. }
+                | Self::StdinAlreadyImported
+                | Self::Cancelled
+        )
+    }
+}
+
+/// Computes the digest of the content of a module, as used by the `integrity` clause of
+/// imports, e.g., `import "lib.ryan" integrity "sha256-..."`. The digest is the SHA-256
+/// hash of the raw bytes, in hexadecimal, prefixed by `sha256-`.
+///
+/// ```
+/// assert_eq!(
+///     ryan::environment::digest(b"abc"),
+///     "sha256-ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+/// );
+/// ```
+pub fn digest(bytes: &[u8]) -> String {
+    format!("sha256-{}", utils::hex_encode(&utils::sha256(bytes)))
+}
+
+/// Expands every `${NAME}` in an import path to the value of the environment variable
+/// `NAME`, read through the loader as the module `env:NAME`. Names are made of ASCII
+/// letters, digits and underscores; anything else is kept as written.
+pub(super) fn expand_path<'a>(
+    loader: &dyn ImportLoader,
+    path: &'a str,
+) -> Result<Cow<'a, str>, ImportError> {
+    if !path.contains("${") {
+        return Ok(Cow::Borrowed(path));
+    }
+
+    let mut expanded = String::new();
+    let mut rest = path;
+
+    while let Some(start) = rest.find("${") {
+        let after = &rest[start + 2..];
+        let name_len = after
+            .find(|ch: char| !ch.is_ascii_alphanumeric() && ch != '_')
+            .unwrap_or(after.len());
+
+        if name_len == 0 || !after[name_len..].starts_with('}') {
+            expanded.push_str(&rest[..start + 2]);
+            rest = after;
+            continue;
+        }
+
+        let variable = &after[..name_len];
+        let value = loader
+            .load(&format!("env:{variable}"))
+            .and_then(|mut read| {
+                let mut value = String::new();
+                read.read_to_string(&mut value)?;
+                Ok(value)
+            })
+            .map_err(|source| ImportError::PathVariableUnavailable {
+                variable: rc_world::str_to_rc(variable),
+                path: rc_world::str_to_rc(path),
+                source,
+            })?;
+
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&value);
+        rest = &after[name_len + 1..];
+    }
+
+    expanded.push_str(rest);
+    Ok(Cow::Owned(expanded))
+}
+
+/// Reads the whole content of a module, failing if it is larger than `max_bytes`. At
+/// most one byte past the limit is ever read, so that an unbounded reader cannot exhaust
+/// the memory.
+pub(super) fn read_module(
+    path: &Rc<str>,
+    mut reader: Box<dyn Read>,
+    max_bytes: Option<usize>,
+) -> Result<Vec<u8>, Box<dyn Error + 'static>> {
+    let mut bytes = Vec::new();
+
+    match max_bytes {
+        Some(max_bytes) => {
+            reader.take(max_bytes as u64 + 1).read_to_end(&mut bytes)?;
+            if bytes.len() > max_bytes {
+                return Err(Box::new(ImportError::TooLarge {
+                    path: path.clone(),
+                    max_bytes,
+                }));
+            }
+        }
+        None => {
+            reader.read_to_end(&mut bytes)?;
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Formats a chain of imports as `a.ryan -> b.ryan -> c.ryan`.
+pub(crate) fn fmt_import_chain(chain: &[Rc<str>]) -> String {
+    chain
+        .iter()
+        .map(|path| path.as_ref())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+/// Lists keys in backticks, e.g., `` `a`, `b` ``, or says there are none.
+fn fmt_keys(keys: &[Rc<str>]) -> String {
+    if keys.is_empty() {
+        return "none".to_owned();
+    }
+
+    keys.iter()
+        .map(|key| format!("`{key}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Keeps only some of the top-level keys of the value of a module, in the given order,
+/// for an import with `only`.
+pub(super) fn select_keys(
+    path: &str,
+    value: &Value,
+    only: &[Rc<str>],
+) -> Result<Value, ImportError> {
+    let Value::Map(dict) = value else {
+        return Err(ImportError::NotADictionary {
+            path: rc_world::str_to_rc(path),
+            found: value.canonical_type(),
+        });
+    };
+
+    let missing = only
+        .iter()
+        .filter(|key| !dict.contains_key(&***key))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        return Err(ImportError::MissingKeys {
+            path: rc_world::str_to_rc(path),
+            missing,
+            available: dict.keys().cloned().collect(),
+        });
+    }
+
+    let selected = only
+        .iter()
+        .map(|key| (key.clone(), dict[&**key].clone()))
+        .collect();
+
+    Ok(Value::Map(Rc::new(selected)))
+}
+
+/// An edge in the import graph: a module importing another module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportEdge {
+    /// The module containing the `import` statement. This is `None` when the code being
+    /// evaluated has no module name, e.g., when it is supplied as a string.
+    pub importer: Option<Rc<str>>,
+    /// The resolved path of the imported module.
+    pub imported: Rc<str>,
+    /// The format in which the module was imported.
+    pub format: Format,
+}
+
+/// The key under which a loaded module is cached. The same path imported in a different
+/// format or with different arguments yields a different value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct ModuleKey {
+    /// The resolved path of the module, so that the same relative path imported from
+    /// different directories yields different modules.
+    pub(super) path: Rc<str>,
+    pub(super) format: Format,
+    /// A hash of the arguments passed with `with`, if any.
+    pub(super) args_hash: Option<u64>,
+    /// The digest pinned with `integrity`, if any, so that a pinned import is always
+    /// checked, even if the same module was imported before without a pin.
+    pub(super) integrity: Option<Rc<str>>,
+}
+
+/// The content of a module, as read by the import loader, and its code, once parsed.
+#[derive(Debug, Clone)]
+pub(crate) struct ModuleSource {
+    /// The version of the module when it was read, as per [`ImportLoader::version`].
+    pub(crate) version: Option<String>,
+    /// The raw content of the module.
+    pub(crate) bytes: Rc<[u8]>,
+    /// The parsed code of the module, once it has been imported as Ryan.
+    pub(crate) block: Option<Rc<Block>>,
+}
+
+/// How many times the content of a module was reused instead of being read and parsed
+/// again, as returned by [`crate::Environment::parse_cache_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseCacheStats {
+    /// The number of modules reused from the cache.
+    pub hits: usize,
+    /// The number of modules read from the import loader.
+    pub misses: usize,
+}
+
+impl Display for ParseCacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "hits: {}, misses: {}", self.hits, self.misses)
+    }
 }
 
 /// The internal state of the import system.
 #[derive(Debug)]
 pub(super) struct ImportState {
     pub(super) import_loader: Box<dyn ImportLoader>,
-    pub(super) loaded: HashMap<Rc<str>, Value>,
+    /// The modules already loaded, from the least to the most recently used.
+    pub(super) loaded: IndexMap<ModuleKey, Value>,
+    /// The content of the modules already read, by resolved path.
+    pub(super) sources: HashMap<Rc<str>, ModuleSource>,
+    pub(super) parse_cache_stats: ParseCacheStats,
     pub(super) import_stack: Vec<Rc<str>>,
+    /// Every import attempted so far, in order, without repetitions.
+    pub(super) import_graph: Vec<ImportEdge>,
+    /// Whether modules are kept in `loaded` at all.
+    pub(super) import_cache: bool,
+    /// Whether `env:` modules are kept in `loaded`.
+    pub(super) cache_env_imports: bool,
+    /// The maximum number of modules kept in `loaded`, if any.
+    pub(super) max_cached_modules: Option<usize>,
+    /// The maximum size of a module, in bytes, if any.
+    pub(super) max_import_bytes: Option<usize>,
+    /// Whether the standard input was already imported.
+    pub(super) stdin_imported: bool,
+    /// The environment variables that were not set, but whose imports fell back to their
+    /// defaults, with the importing modules, since the end of the last evaluation.
+    pub(super) missing_env: Vec<(Rc<str>, Option<Rc<str>>)>,
 }
 
 impl Default for ImportState {
     fn default() -> Self {
         ImportState {
             import_loader: Box::new(DefaultImporter),
-            loaded: HashMap::default(),
+            loaded: IndexMap::default(),
+            sources: HashMap::default(),
+            parse_cache_stats: ParseCacheStats::default(),
             import_stack: vec![],
+            import_graph: vec![],
+            import_cache: true,
+            cache_env_imports: false,
+            max_cached_modules: None,
+            max_import_bytes: None,
+            stdin_imported: false,
+            missing_env: vec![],
         }
     }
 }
 
 impl ImportState {
-    pub(super) fn try_push_import(
+    /// Whether a module at a resolved path may be kept in the caches at all, whatever
+    /// its version. Environment variables are only cached if asked to, since the host
+    /// may change them between evaluations, and the standard input is never cached.
+    fn is_cacheable_path(&self, resolved: &str) -> bool {
+        (self.cache_env_imports || !resolved.starts_with("env:"))
+            && resolved != STDIN
+            && self.import_loader.cacheable(resolved)
+    }
+
+    /// Whether a module with the given key, which holds its resolved path, should be kept
+    /// in the cache at all.
+    fn is_cacheable(&self, key: &ModuleKey) -> bool {
+        self.import_cache && self.is_cacheable_path(&key.path)
+    }
+
+    /// Adds an edge to the import graph, if it is not already there.
+    pub(super) fn record_import(&mut self, edge: ImportEdge) {
+        if !self.import_graph.contains(&edge) {
+            self.import_graph.push(edge);
+        }
+    }
+
+    /// Gets a module from the cache, marking it as the most recently used. A module
+    /// whose version changed since it was read is forgotten instead.
+    pub(super) fn get_cached(&mut self, key: &ModuleKey) -> Option<Value> {
+        if !self.is_cacheable(key) {
+            return None;
+        }
+
+        if let Some(source) = self.sources.get(&key.path) {
+            if source.version != self.import_loader.version(&key.path) {
+                self.invalidate(&key.path.clone());
+                return None;
+            }
+        }
+
+        let (key, value) = self.loaded.shift_remove_entry(key)?;
+        self.loaded.insert(key, value.clone());
+
+        Some(value)
+    }
+
+    /// Puts a module in the cache, evicting the least recently used modules, and their
+    /// content, if the cache is full.
+    pub(super) fn cache(&mut self, key: ModuleKey, value: Value) {
+        if !self.is_cacheable(&key) {
+            return;
+        }
+
+        self.loaded.insert(key, value);
+
+        if let Some(max) = self.max_cached_modules {
+            while self.loaded.len() > max {
+                let Some((evicted, _)) = self.loaded.shift_remove_index(0) else {
+                    break;
+                };
+                // Keep the content only while it is still needed by another key:
+                if !self.loaded.keys().any(|key| key.path == evicted.path) {
+                    self.sources.remove(&evicted.path);
+                }
+            }
+        }
+    }
+
+    /// Gets the content of a module, unless it was never read or its version changed
+    /// since. Modules whose version changed are forgotten altogether.
+    pub(super) fn get_source(
         &mut self,
+        resolved: &str,
+        version: &Option<String>,
+    ) -> Option<ModuleSource> {
+        match self.sources.get(resolved) {
+            Some(source) if source.version == *version => {
+                self.parse_cache_stats.hits += 1;
+                Some(source.clone())
+            }
+            Some(_) => {
+                self.invalidate(resolved);
+                self.parse_cache_stats.misses += 1;
+                None
+            }
+            None => {
+                self.parse_cache_stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Keeps the content of a module for the next time it is imported. Only modules with
+    /// a version are kept if imports are not cached.
+    pub(super) fn cache_source(&mut self, resolved: Rc<str>, source: ModuleSource) {
+        let is_cacheable =
+            (self.import_cache || source.version.is_some()) && self.is_cacheable_path(&resolved);
+
+        if is_cacheable {
+            self.sources.insert(resolved, source);
+        }
+    }
+
+    /// Forgets everything known about a module, given its resolved path.
+    pub(super) fn invalidate(&mut self, resolved: &str) {
+        self.sources.remove(resolved);
+        self.loaded.retain(|key, _| &*key.path != resolved);
+    }
+
+    /// Resolves the path of a module imported from the `current` module.
+    pub(super) fn resolve(
+        &self,
         current: Option<&str>,
         path: &str,
     ) -> Result<Rc<str>, Box<dyn Error + 'static>> {
         let path = self.import_loader.resolve(current, path)?;
-        let resolved = rc_world::string_to_rc(path);
+        Ok(rc_world::string_to_rc(path))
+    }
 
-        if self.import_stack.iter().any(|p| p == &resolved) {
-            return Err(Box::new(ImportError::CircularImportDetected(resolved)));
+    pub(super) fn try_push_import(
+        &mut self,
+        resolved: Rc<str>,
+    ) -> Result<(), Box<dyn Error + 'static>> {
+        if let Some(start) = self.import_stack.iter().position(|p| p == &resolved) {
+            let mut chain = self.import_stack[start..].to_vec();
+            chain.push(resolved);
+            return Err(Box::new(ImportError::CircularImportDetected { chain }));
         }
 
-        self.import_stack.push(resolved.clone());
+        self.import_stack.push(resolved);
 
-        Ok(resolved)
+        Ok(())
     }
 }
 
@@ -228,7 +846,7 @@ impl ImportState {
 #[derive(Debug)]
 pub struct Override<L> {
     loader: L,
-    overrides: HashMap<String, Option<String>>,
+    overrides: BTreeMap<String, Option<String>>,
 }
 
 impl<L: ImportLoader> ImportLoader for Override<L> {
@@ -250,9 +868,21 @@ impl<L: ImportLoader> ImportLoader for Override<L> {
             Some(None) => Err(Box::new(ImportError::ImportPathIsOverridden(
                 rc_world::str_to_rc(path),
             ))),
-            None => self.load(path),
+            None => self.loader.load(path),
+        }
+    }
+
+    fn version(&self, path: &str) -> Option<String> {
+        if self.overrides.contains_key(path) {
+            None
+        } else {
+            self.loader.version(path)
         }
     }
+
+    fn cacheable(&self, path: &str) -> bool {
+        self.overrides.contains_key(path) || self.loader.cacheable(path)
+    }
 }
 
 /// The resulting loader for the [`ImportLoader::filter`] method.
@@ -281,13 +911,21 @@ where
 
     fn load(&self, path: &str) -> Result<Box<dyn Read>, Box<dyn Error + 'static>> {
         if (self.filter)(path) {
-            self.load(path)
+            self.loader.load(path)
         } else {
-            return Err(Box::new(ImportError::ImportPathIsOverridden(
+            Err(Box::new(ImportError::ImportPathIsOverridden(
                 rc_world::str_to_rc(path),
-            )));
+            )))
         }
     }
+
+    fn version(&self, path: &str) -> Option<String> {
+        self.loader.version(path)
+    }
+
+    fn cacheable(&self, path: &str) -> bool {
+        self.loader.cacheable(path)
+    }
 }
 
 /// The resulting loader for the [`ImportLoader::with_resolver`] method.
@@ -318,6 +956,14 @@ where
     fn load(&self, path: &str) -> Result<Box<dyn Read>, Box<dyn Error + 'static>> {
         self.loader.load(path)
     }
+
+    fn version(&self, path: &str) -> Option<String> {
+        self.loader.version(path)
+    }
+
+    fn cacheable(&self, path: &str) -> bool {
+        self.loader.cacheable(path)
+    }
 }
 
 /// The resulting loader for the [`ImportLoader::with_loader`] method.
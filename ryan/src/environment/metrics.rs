@@ -0,0 +1,127 @@
+use std::cell::Cell;
+use std::fmt::{self, Display};
+use std::time::{Duration, Instant};
+
+use crate::rc_world;
+
+/// Numbers about the evaluations done with an environment, as returned by
+/// [`crate::Environment::metrics`] and [`crate::from_str_with_env_metrics`]. Use these to
+/// find out which programs are slow and why.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EvalMetrics {
+    /// The time spent parsing code, including the code of imported modules.
+    pub parse_time: Duration,
+    /// The time spent evaluating code, not counting the time spent parsing imported
+    /// modules.
+    pub eval_time: Duration,
+    /// The number of modules imported, including the ones served from the cache.
+    pub imports: usize,
+    /// The number of imports served from the cache of already loaded modules.
+    pub cache_hits: usize,
+    /// The number of distinct strings interned in this thread. Interned strings are never
+    /// freed, so this is also the peak.
+    pub interned_strings: usize,
+    /// The number of values produced by expressions, e.g., `[1, 2]` produces three: the
+    /// list and each of its elements.
+    pub values: usize,
+}
+
+impl Display for EvalMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "parse: {:?}, eval: {:?}, imports: {} ({} from cache), interned strings: {}, \
+            values: {}",
+            self.parse_time,
+            self.eval_time,
+            self.imports,
+            self.cache_hits,
+            self.interned_strings,
+            self.values
+        )
+    }
+}
+
+impl EvalMetrics {
+    /// The metrics accumulated since an earlier snapshot of the same environment. The
+    /// number of interned strings is kept as is, since it is a peak and not a sum.
+    pub fn since(&self, earlier: &EvalMetrics) -> EvalMetrics {
+        EvalMetrics {
+            parse_time: self.parse_time.saturating_sub(earlier.parse_time),
+            eval_time: self.eval_time.saturating_sub(earlier.eval_time),
+            imports: self.imports - earlier.imports,
+            cache_hits: self.cache_hits - earlier.cache_hits,
+            interned_strings: self.interned_strings,
+            values: self.values - earlier.values,
+        }
+    }
+}
+
+/// The running counters behind [`EvalMetrics`], shared by an environment and its clones.
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    parse_time: Cell<Duration>,
+    eval_time: Cell<Duration>,
+    imports: Cell<usize>,
+    cache_hits: Cell<usize>,
+    values: Cell<usize>,
+    /// How many evaluations are running. Imported modules are evaluated within the
+    /// evaluation that imports them, so only the outermost one is timed.
+    depth: Cell<usize>,
+}
+
+impl Counters {
+    /// Runs a parse, adding its duration to the parse time.
+    #[cfg_attr(not(feature = "parse"), allow(dead_code))]
+    pub(crate) fn time_parse<T>(&self, parse: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let parsed = parse();
+        self.parse_time.set(self.parse_time.get() + start.elapsed());
+
+        parsed
+    }
+
+    /// Runs an evaluation, adding its duration to the evaluation time, unless it runs
+    /// within another evaluation.
+    pub(crate) fn time_eval<T>(&self, eval: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let parse_time = self.parse_time.get();
+
+        self.depth.set(self.depth.get() + 1);
+        let evaluated = eval();
+        self.depth.set(self.depth.get() - 1);
+
+        if self.depth.get() == 0 {
+            let parsing = self.parse_time.get().saturating_sub(parse_time);
+            let elapsed = start.elapsed().saturating_sub(parsing);
+            self.eval_time.set(self.eval_time.get() + elapsed);
+        }
+
+        evaluated
+    }
+
+    /// Counts a module imported successfully.
+    pub(crate) fn count_import(&self, cache_hit: bool) {
+        self.imports.set(self.imports.get() + 1);
+        if cache_hit {
+            self.cache_hits.set(self.cache_hits.get() + 1);
+        }
+    }
+
+    /// Counts a value produced by an expression.
+    pub(crate) fn count_value(&self) {
+        self.values.set(self.values.get() + 1);
+    }
+
+    /// The current value of the counters.
+    pub(crate) fn snapshot(&self) -> EvalMetrics {
+        EvalMetrics {
+            parse_time: self.parse_time.get(),
+            eval_time: self.eval_time.get(),
+            imports: self.imports.get(),
+            cache_hits: self.cache_hits.get(),
+            interned_strings: rc_world::interned_count(),
+            values: self.values.get(),
+        }
+    }
+}
@@ -1,18 +1,53 @@
+/// Stopping evaluations from other threads.
+pub mod cancel;
+/// Events emitted during evaluation, for hosts that want to know what is going on.
+pub mod events;
+/// Reusing the values of imported modules across evaluations of the same program.
+#[cfg_attr(not(feature = "parse"), allow(dead_code))]
+pub mod incremental;
 /// The Ryan import system.
 pub mod loader;
+/// Counters about evaluations, for finding slow programs.
+#[cfg(feature = "metrics")]
+pub mod metrics;
 /// Ryan native extensions.
 pub mod native;
+/// Tracking where each part of an evaluated value comes from.
+pub mod provenance;
 
-pub use loader::{DefaultImporter, ImportLoader, NoImport};
+pub use cancel::CancellationToken;
+pub use events::{Branch, EvalEvent};
+pub use incremental::IncrementalStats;
+pub use loader::{
+    digest, DefaultImporter, ImportEdge, ImportError, ImportLoader, NoImport, ParseCacheStats,
+};
+#[cfg(feature = "metrics")]
+pub use metrics::EvalMetrics;
 pub use native::{NativePatternMatch, BUILT_INS};
-use std::{cell::RefCell, collections::HashMap, error::Error, fmt::Debug, rc::Rc};
+pub use provenance::{Origin, Provenance};
+use std::{
+    cell::RefCell,
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    error::Error,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    path::Path,
+    rc::Rc,
+};
 
-use self::loader::ImportState;
+use self::events::EventHook;
+use self::incremental::Memo;
+use self::loader::{ImportState, ModuleKey, ModuleSource};
+use self::provenance::ProvenanceTracker;
 use crate::{
-    parser::{Format, Value},
+    parser::{parse_module, Block, Format, Value},
     rc_world,
 };
 
+/// How deep pattern matches may be nested, by default, before evaluation is aborted. See
+/// [`EnvironmentBuilder::max_call_depth`].
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 64;
+
 /// The environment on which a Ryan program operates.
 #[derive(Debug, Clone)]
 pub struct Environment {
@@ -20,7 +55,25 @@ pub struct Environment {
     /// The name of the current model. It can be `None` if no module is set. This happens
     /// when, e.g., executing Ryan from a supplied string without any extra configuration.
     pub current_module: Option<Rc<str>>,
-    built_ins: Rc<HashMap<Rc<str>, Value>>,
+    built_ins: Rc<BTreeMap<Rc<str>, Value>>,
+    event_hook: Option<EventHook>,
+    /// The event hook, only if tracing is on.
+    trace_hook: Option<EventHook>,
+    duplicate_keys: DuplicateKeyPolicy,
+    precision_loss: PrecisionLossPolicy,
+    max_call_depth: usize,
+    import_args: Option<Value>,
+    strict: bool,
+    hermetic: bool,
+    warn_missing_env: bool,
+    required_env: Rc<[Rc<str>]>,
+    cancel_token: Option<CancellationToken>,
+    provenance: Option<Rc<RefCell<ProvenanceTracker>>>,
+    /// The values of imported modules kept across evaluations, when evaluating
+    /// incrementally.
+    incremental: Option<Rc<RefCell<Memo>>>,
+    #[cfg(feature = "metrics")]
+    metrics: Rc<metrics::Counters>,
 }
 
 impl Environment {
@@ -40,73 +93,842 @@ impl Environment {
             import_loader: Box::new(DefaultImporter),
             current_module: None,
             built_ins: None,
+            event_hook: None,
+            trace: false,
+            duplicate_keys: DuplicateKeyPolicy::default(),
+            precision_loss: PrecisionLossPolicy::default(),
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            import_cache: true,
+            cache_env_imports: false,
+            max_cached_modules: None,
+            max_import_bytes: None,
+            #[cfg(feature = "builtins")]
+            max_range_size: None,
+            strict: false,
+            hermetic: false,
+            warn_missing_env: false,
+            required_env: vec![],
+            cancel_token: None,
+            track_provenance: false,
         }
     }
 
     /// Returns the value associated with a given builtin name.
     pub fn builtin(&self, id: &str) -> Option<Value> {
-        self.built_ins.get(id).map(Clone::clone)
+        self.built_ins.get(id).cloned()
+    }
+
+    /// Lists all built-ins available in this environment, sorted by name, including the
+    /// ones registered with [`EnvironmentBuilder::built_ins`]. Native pattern matches
+    /// may carry a signature and some documentation; see [`NativePatternMatch::with_doc`].
+    /// Namespaces, like `str`, are listed as dictionaries, next to the deprecated bare
+    /// names of their members, like `trim`.
+    ///
+    /// ```rust
+    /// use std::{convert::Infallible, rc::Rc};
+    /// use ryan::environment::{Environment, NativePatternMatch, BUILT_INS};
+    /// use ryan::parser::{Pattern, Value};
+    ///
+    /// let env = Environment::new(None);
+    /// let Some((_, Value::NativePatternMatch(len))) = env.builtins().find(|(id, _)| *id == "len")
+    /// else {
+    ///     panic!("len is a built-in")
+    /// };
+    /// assert_eq!(len.doc.as_deref(), Some("Gets the length of a list, a dictionary, a text or some bytes."));
+    ///
+    /// let mut built_ins = BUILT_INS.with(|built_ins| (**built_ins).clone());
+    /// let double = NativePatternMatch::new("double", Pattern::Identifier("x".into(), None), |x| {
+    ///     Ok::<_, Infallible>(Value::List(vec![x.clone(), x].into()))
+    /// })
+    /// .with_doc("double x: any", "Makes a list with two copies of a value.");
+    /// built_ins.insert("double".into(), Value::NativePatternMatch(Rc::new(double)));
+    ///
+    /// let env = Environment::builder().built_ins(Rc::new(built_ins)).build();
+    /// assert!(env.builtins().any(|(id, _)| id == "double"));
+    /// assert_eq!(
+    ///     ryan::from_str_with_env::<String>(&env, r#"(help "double").doc"#).unwrap(),
+    ///     "Makes a list with two copies of a value.",
+    /// );
+    /// assert_eq!(
+    ///     ryan::from_str_with_env::<String>(&env, "(help null).double").unwrap(),
+    ///     "double x: any",
+    /// );
+    /// ```
+    pub fn builtins(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.built_ins.iter().map(|(id, value)| (&**id, value))
+    }
+
+    /// Returns what happens when a dictionary defines the same key twice.
+    pub fn duplicate_key_policy(&self) -> DuplicateKeyPolicy {
+        self.duplicate_keys
+    }
+
+    /// Returns what happens when an operation mixes a float with an integer too big to
+    /// be turned into a float exactly.
+    pub fn precision_loss_policy(&self) -> PrecisionLossPolicy {
+        self.precision_loss
+    }
+
+    /// Returns how deep pattern matches may be nested. See
+    /// [`EnvironmentBuilder::max_call_depth`].
+    pub fn max_call_depth(&self) -> usize {
+        self.max_call_depth
+    }
+
+    /// Returns whether programs evaluated with this environment run in strict mode. See
+    /// [`EnvironmentBuilder::strict`].
+    pub fn is_strict(&self) -> bool {
+        self.strict
     }
 
-    /// Tries to push an import to the import stack.
-    fn try_push_import(&self, path: &str) -> Result<Environment, Box<dyn Error + 'static>> {
-        let resolved = self
-            .import_state
+    /// Returns whether this environment was built with [`EnvironmentBuilder::hermetic`],
+    /// i.e., whether programs evaluated with it can neither import anything nor call
+    /// effectful native pattern matches. Check this at startup if your service relies on
+    /// hermetic configuration.
+    pub fn is_hermetic(&self) -> bool {
+        self.hermetic
+    }
+
+    /// Returns whether the [`CancellationToken`] of this environment, if any, was
+    /// cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_token
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Returns a clone of this environment stopped by a different cancellation token.
+    #[cfg(all(feature = "parse", feature = "std-thread"))]
+    pub(crate) fn with_cancel_token(&self, token: CancellationToken) -> Environment {
+        Environment {
+            cancel_token: Some(token),
+            ..self.clone()
+        }
+    }
+
+    /// Returns where each part of a value evaluated with this environment (or any of its
+    /// clones) came from. The provenance is empty unless the environment was built with
+    /// [`EnvironmentBuilder::track_provenance`].
+    pub fn provenance(&self, value: &Value) -> Provenance {
+        self.provenance
+            .as_ref()
+            .map(|tracker| tracker.borrow().provenance(value))
+            .unwrap_or_default()
+    }
+
+    /// Returns this environment if it tracks provenance or else a clone of it that does.
+    #[cfg(feature = "parse")]
+    pub(crate) fn tracking_provenance(&self) -> Environment {
+        if self.provenance.is_some() {
+            self.clone()
+        } else {
+            Environment {
+                provenance: Some(Rc::default()),
+                ..self.clone()
+            }
+        }
+    }
+
+    /// Returns the provenance tracker, if provenance is being tracked.
+    pub(crate) fn provenance_tracker(&self) -> Option<&Rc<RefCell<ProvenanceTracker>>> {
+        self.provenance.as_ref()
+    }
+
+    /// Records that a module evaluated to a given value, as the origin of the value and
+    /// of the import that asked for it.
+    fn record_import_origin(&self, resolved: Rc<str>, value: &Value) {
+        if let Some(tracker) = &self.provenance {
+            let origin = Origin {
+                module: resolved,
+                binding: None,
+            };
+            let mut tracker = tracker.borrow_mut();
+            tracker.record(value, origin.clone());
+            tracker.last_import = Some(origin);
+        }
+    }
+
+    /// Returns a clone of this environment reusing the values of imported modules kept in
+    /// `memo`, unless something they read changed.
+    #[cfg(feature = "parse")]
+    pub(crate) fn incremental(&self, memo: Rc<RefCell<Memo>>) -> Environment {
+        Environment {
+            incremental: Some(memo),
+            ..self.clone()
+        }
+    }
+
+    /// Marks the module being evaluated as depending on something other than what it
+    /// imports, so that it is never reused by an incremental evaluation.
+    pub(crate) fn taint_incremental(&self) {
+        if let Some(memo) = &self.incremental {
+            memo.borrow_mut().taint();
+        }
+    }
+
+    /// Returns the value of the `args` variable: the arguments passed with `with` to the
+    /// import that loaded the current module or an empty dictionary if there were none.
+    pub(crate) fn import_args(&self) -> Value {
+        self.import_args
+            .clone()
+            .unwrap_or_else(|| Value::Map(Rc::default()))
+    }
+
+    /// Returns the value of the `__module__` variable: the current module, as resolved by
+    /// the import loader, or `null` if there is none.
+    pub(crate) fn module_name(&self) -> Value {
+        self.current_module.clone().map_or(Value::Null, Value::Text)
+    }
+
+    /// Returns the value of the `__dir__` variable: the directory of the current module,
+    /// `.` if the module has no directory in it, or `null` if there is no current module
+    /// or if it is not a file, e.g., an `env:` import.
+    pub(crate) fn module_dir(&self) -> Value {
+        match self.current_module.as_deref() {
+            Some(module) if !loader::is_pseudo_module(module) => {
+                let dir = match Path::new(module).parent() {
+                    Some(dir) if !dir.as_os_str().is_empty() => dir.to_string_lossy(),
+                    _ => ".".into(),
+                };
+                Value::Text(rc_world::str_to_rc(&dir))
+            }
+            _ => Value::Null,
+        }
+    }
+
+    /// Returns whether an `env:` import of a variable that is not set must fail, even if
+    /// it has a default. See [`EnvironmentBuilder::require_env`].
+    pub(crate) fn is_env_required(&self, name: &str) -> bool {
+        self.required_env.iter().any(|required| &**required == name)
+    }
+
+    /// Records that the current module fell back to the default of an `env:` import,
+    /// since the variable is not set, if these are to be warned about.
+    pub(crate) fn record_missing_env(&self, name: Rc<str>) {
+        if self.warn_missing_env {
+            let importer = self.current_module.clone();
+            let mut import_state = self.import_state.borrow_mut();
+            if !import_state
+                .missing_env
+                .contains(&(name.clone(), importer.clone()))
+            {
+                import_state.missing_env.push((name, importer));
+            }
+        }
+    }
+
+    /// Warns about every `env:` import that fell back to its default since the last
+    /// evaluation, once the program itself, and not an imported module, is evaluated. See
+    /// [`EnvironmentBuilder::warn_missing_env`].
+    pub(crate) fn warn_about_missing_env(&self) {
+        let missing = {
+            let mut import_state = self.import_state.borrow_mut();
+            if !import_state.import_stack.is_empty() {
+                return;
+            }
+            std::mem::take(&mut import_state.missing_env)
+        };
+
+        for (name, importer) in missing {
+            self.emit(EvalEvent::Warning {
+                message: rc_world::string_to_rc(format!(
+                    "Environment variable {name} is not set, so {} used the default of its \
+                    import. Is the name right?",
+                    importer.as_deref().unwrap_or("<main>")
+                )),
+            });
+        }
+    }
+
+    /// Sends an event to the event hook, if one is installed.
+    pub(crate) fn emit(&self, event: EvalEvent) {
+        if let Some(hook) = &self.event_hook {
+            (hook.0)(event);
+        }
+    }
+
+    /// Sends a tracing event to the event hook, if one is installed and tracing is on.
+    /// The event is only built if it is going to be sent.
+    pub(crate) fn trace(&self, event: impl FnOnce() -> EvalEvent) {
+        if let Some(hook) = &self.trace_hook {
+            (hook.0)(event());
+        }
+    }
+
+    /// Returns every import attempted so far with this environment (or any of its clones),
+    /// in the order they first happened. Imports served from the cache are also included,
+    /// so this is the full dependency graph of the evaluated code. Failed imports are
+    /// included as well, since they are an input to the evaluation all the same.
+    pub fn import_graph(&self) -> Vec<ImportEdge> {
+        self.import_state.borrow().import_graph.clone()
+    }
+
+    /// Forgets all modules loaded so far, so that the next imports are read again from
+    /// the import loader. Use this when reusing an environment across evaluations and the
+    /// underlying modules might have changed in the meantime. Clones of this environment
+    /// share the same cache.
+    pub fn clear_import_cache(&self) {
+        let mut import_state = self.import_state.borrow_mut();
+        import_state.loaded.clear();
+        import_state.sources.clear();
+    }
+
+    /// Forgets a single module, so that it is read and parsed again the next time it is
+    /// imported. The path is resolved as an import in the current module would be, e.g.,
+    /// the path of a file that just changed. If your import loader knows when modules
+    /// change, implement [`ImportLoader::version`] instead.
+    pub fn invalidate(&self, path: &str) {
+        let mut import_state = self.import_state.borrow_mut();
+        let resolved = import_state
+            .import_loader
+            .resolve(self.current_module.as_deref(), path)
+            .unwrap_or_else(|_| path.to_owned());
+        import_state.invalidate(&resolved);
+    }
+
+    /// Returns how many times modules were reused instead of being read and parsed again
+    /// from the import loader, with this environment or any of its clones.
+    pub fn parse_cache_stats(&self) -> ParseCacheStats {
+        self.import_state.borrow().parse_cache_stats
+    }
+
+    /// Returns the metrics of all evaluations with this environment or any of its clones
+    /// so far. Use [`EvalMetrics::since`] to get the metrics of a single evaluation.
+    ///
+    /// ```rust
+    /// use ryan::environment::{Environment, ImportLoader, NoImport};
+    ///
+    /// let loader = NoImport.r#override("lib.ryan".to_owned(), "{ port: 8080 }".to_owned());
+    /// let env = Environment::builder().import_loader(loader).build();
+    /// let code = r#"[(import "lib.ryan").port, (import "lib.ryan").port]"#;
+    /// ryan::from_str_with_env::<Vec<i64>>(&env, code).unwrap();
+    ///
+    /// let metrics = env.metrics();
+    /// assert_eq!((metrics.imports, metrics.cache_hits), (2, 1));
+    /// assert!(metrics.values > 0);
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> EvalMetrics {
+        self.metrics.snapshot()
+    }
+
+    /// The counters behind [`Environment::metrics`].
+    #[cfg(feature = "metrics")]
+    pub(crate) fn counters(&self) -> &Rc<metrics::Counters> {
+        &self.metrics
+    }
+
+    /// Returns the chain of modules that led to the current module, from the outermost to
+    /// the innermost, including the current module.
+    pub(crate) fn import_chain(&self) -> Vec<Rc<str>> {
+        let import_stack = &self.import_state.borrow().import_stack;
+        if import_stack.is_empty() {
+            self.current_module.iter().cloned().collect()
+        } else {
+            import_stack.clone()
+        }
+    }
+
+    /// Resolves the path of a module imported from the current module.
+    fn resolve_import(&self, path: &str) -> Result<Rc<str>, Box<dyn Error + 'static>> {
+        self.import_state
+            .borrow()
+            .resolve(self.current_module.as_deref(), path)
+    }
+
+    /// Tries to push an import, with its resolved path, to the import stack.
+    fn try_push_import(&self, resolved: Rc<str>) -> Result<Environment, Box<dyn Error + 'static>> {
+        self.import_state
             .borrow_mut()
-            .try_push_import(self.current_module.as_deref(), path)?;
+            .try_push_import(resolved.clone())?;
         Ok(Environment {
-            import_state: self.import_state.clone(),
             current_module: Some(resolved),
-            built_ins: self.built_ins.clone(),
+            import_args: None,
+            ..self.clone()
         })
     }
 
     /// Loads a module as a given [`Format`] from a supplied path using the currently
     /// configured loader.
+    ///
+    /// Errors in the content of the module, as opposed to errors finding or reading it,
+    /// are returned as [`ImportError::ModuleFailed`].
+    ///
+    /// Every `${NAME}` in the path is first replaced by the value of the environment
+    /// variable `NAME`, read through the loader as the module `env:NAME`. A variable that
+    /// cannot be read is returned as [`ImportError::PathVariableUnavailable`].
+    ///
+    /// ```rust
+    /// use ryan::environment::{Environment, ImportLoader, NoImport};
+    ///
+    /// let loader = NoImport
+    ///     .r#override("env:CONFIG_DIR".to_owned(), "/etc/app".to_owned())
+    ///     .r#override("/etc/app/base.ryan".to_owned(), "{ port: 8080 }".to_owned());
+    /// let env = Environment::builder().import_loader(loader).build();
+    /// let code = r#"(import "${CONFIG_DIR}/base.ryan").port"#;
+    /// assert_eq!(ryan::from_str_with_env::<i64>(&env, code).unwrap(), 8080);
+    ///
+    /// let err = ryan::from_str_with_env::<i64>(&env, r#"import "${NOPE}/base.ryan""#);
+    /// assert!(err.unwrap_err().to_string().contains("`${NOPE}`"));
+    ///
+    /// let env = Environment::builder().hermetic().build();
+    /// assert!(ryan::from_str_with_env::<i64>(&env, r#"import "${HOME}/base.ryan""#).is_err());
+    /// ```
     pub fn load(&self, format: Format, path: &str) -> Result<Value, Box<dyn Error + 'static>> {
-        if let Some(value) = self.import_state.borrow().loaded.get(path) {
-            return Ok(value.clone());
-        }
-
-        let sub_environment = self.try_push_import(path)?;
-        let read = self.import_state.borrow().import_loader.load(
-            sub_environment
-                .current_module
-                .as_deref()
-                .expect("import stack not empty"),
-        )?;
-        let value = format.load(sub_environment, read)?;
+        self.load_with_args(format, path, None)
+    }
+
+    /// Loads a module as a given [`Format`] from a supplied path using the currently
+    /// configured loader, making `args` available to the module as the `args` variable.
+    /// Each distinct value of `args` loads the module anew.
+    pub fn load_with_args(
+        &self,
+        format: Format,
+        path: &str,
+        args: Option<&Value>,
+    ) -> Result<Value, Box<dyn Error + 'static>> {
+        self.load_with_integrity(format, path, args, None)
+    }
+
+    /// Loads a module like [`Environment::load_with_integrity`], keeping only the
+    /// top-level keys in `only`, if supplied, in that order. A module that does not
+    /// evaluate to a dictionary with all of these keys is an error, listing the keys it
+    /// does have.
+    ///
+    /// ```rust
+    /// use ryan::environment::{Environment, ImportLoader, NoImport};
+    /// use ryan::parser::Format;
+    ///
+    /// let loader = NoImport.r#override("lib.ryan".to_owned(), "{ a: 1, b: 2 }".to_owned());
+    /// let env = Environment::builder().import_loader(loader).build();
+    ///
+    /// let value = env.load_only(Format::Ryan, "lib.ryan", None, None, Some(&["b".into()]));
+    /// assert_eq!(value.unwrap().to_string(), "{b: 2}");
+    ///
+    /// let err = env
+    ///     .load_only(Format::Ryan, "lib.ryan", None, None, Some(&["c".into()]))
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "Module \"lib.ryan\" has no key `c`; its keys are `a`, `b`",
+    /// );
+    /// ```
+    pub fn load_only(
+        &self,
+        format: Format,
+        path: &str,
+        args: Option<&Value>,
+        integrity: Option<&str>,
+        only: Option<&[Rc<str>]>,
+    ) -> Result<Value, Box<dyn Error + 'static>> {
+        let value = self.load_with_integrity(format, path, args, integrity)?;
+
+        match only {
+            Some(only) => Ok(loader::select_keys(path, &value, only)?),
+            None => Ok(value),
+        }
+    }
+
+    /// Loads a module like [`Environment::load_with_args`], also checking that the digest
+    /// of its raw content, as computed by [`digest`], is `integrity`, if supplied. A
+    /// mismatch is returned as [`ImportError::IntegrityMismatch`].
+    pub fn load_with_integrity(
+        &self,
+        format: Format,
+        path: &str,
+        args: Option<&Value>,
+        integrity: Option<&str>,
+    ) -> Result<Value, Box<dyn Error + 'static>> {
+        if self.is_cancelled() {
+            return Err(Box::new(ImportError::Cancelled));
+        }
+
+        // The variables in the path and the arguments are computed by the importing
+        // module, which is simpler to evaluate again than to keep track of:
+        if path.contains("${") || args.is_some() {
+            self.taint_incremental();
+        }
+        let expanded = loader::expand_path(&*self.import_state.borrow().import_loader, path)?;
+        let path = &*expanded;
+        let resolved = self.resolve_import(path).inspect_err(|_| {
+            // Whether a path resolves might change, e.g., if a missing file shows up:
+            self.taint_incremental();
+        })?;
+        let key = ModuleKey {
+            path: resolved,
+            format,
+            args_hash: args.map(hash_args),
+            integrity: integrity.map(rc_world::str_to_rc),
+        };
+        let cached = match &self.incremental {
+            Some(memo) => self.get_memoized(memo, &key),
+            None => self.import_state.borrow_mut().get_cached(&key),
+        };
+        if let Some(value) = cached {
+            self.import_state.borrow_mut().record_import(ImportEdge {
+                importer: self.current_module.clone(),
+                imported: key.path.clone(),
+                format,
+            });
+            self.record_import_origin(key.path.clone(), &value);
+
+            #[cfg(feature = "metrics")]
+            self.metrics.count_import(true);
+            self.emit(EvalEvent::ImportLoaded {
+                path: key.path,
+                format,
+                cache_hit: true,
+            });
+            return Ok(value);
+        }
+
+        let mut sub_environment = self.try_push_import(key.path.clone())?;
+        sub_environment.import_args = args.cloned();
+        let resolved = sub_environment
+            .current_module
+            .clone()
+            .expect("import stack not empty");
+        self.import_state.borrow_mut().record_import(ImportEdge {
+            importer: self.current_module.clone(),
+            imported: resolved.clone(),
+            format,
+        });
+        self.emit(EvalEvent::ImportStarted {
+            path: resolved.clone(),
+            format,
+        });
+        if let Some(memo) = &self.incremental {
+            // Arguments are only hashed, so a module imported with them is never reused:
+            memo.borrow_mut().enter(args.is_some());
+        }
+        // Pop the import stack even if loading fails, so that a failed import does not
+        // look like a circular import the next time it is attempted.
+        let loaded = self.read_source(&resolved).and_then(|mut source| {
+            if let Some(expected) = integrity {
+                let found = digest(&source.bytes);
+                if found != expected {
+                    return Err(Box::new(ImportError::IntegrityMismatch {
+                        path: resolved.clone(),
+                        expected: rc_world::str_to_rc(expected),
+                        found,
+                    }) as Box<dyn Error>);
+                }
+            }
+
+            let value = format.load(sub_environment, &mut source).map_err(|err| {
+                Box::new(ImportError::ModuleFailed {
+                    path: resolved.clone(),
+                    source: err,
+                }) as Box<dyn Error>
+            })?;
+            self.import_state
+                .borrow_mut()
+                .cache_source(resolved.clone(), source);
+
+            Ok(value)
+        });
         self.import_state.borrow_mut().import_stack.pop();
+        if let Some(memo) = &self.incremental {
+            memo.borrow_mut().leave(key.clone(), loaded.as_ref().ok());
+        }
+        if let Err(err) = &loaded {
+            self.emit(EvalEvent::ImportFailed {
+                path: resolved.clone(),
+                format,
+                error: rc_world::string_to_rc(err.to_string()),
+            });
+        }
+        let value = loaded?;
 
-        self.import_state
-            .borrow_mut()
-            .loaded
-            .insert(rc_world::str_to_rc(path), value.clone());
+        if self.incremental.is_none() {
+            self.import_state.borrow_mut().cache(key, value.clone());
+        }
+        self.record_import_origin(resolved.clone(), &value);
+
+        #[cfg(feature = "metrics")]
+        self.metrics.count_import(false);
+        self.emit(EvalEvent::ImportLoaded {
+            path: resolved,
+            format,
+            cache_hit: false,
+        });
 
         Ok(value)
     }
+
+    /// Reads and parses a module for a `use` statement, calling `f` with its code and the
+    /// environment in which to evaluate it. The module stays on the import stack while
+    /// `f` runs, so that it cannot use or import itself, directly or not.
+    pub(crate) fn with_used_module<T>(
+        &self,
+        path: &str,
+        f: impl FnOnce(&Block, Environment) -> T,
+    ) -> Result<T, Box<dyn Error + 'static>> {
+        if self.is_cancelled() {
+            return Err(Box::new(ImportError::Cancelled));
+        }
+
+        // The bindings of a module are not memoized, only its value:
+        self.taint_incremental();
+        let expanded = loader::expand_path(&*self.import_state.borrow().import_loader, path)?;
+        let sub_environment = self.try_push_import(self.resolve_import(&expanded)?)?;
+        let resolved = sub_environment
+            .current_module
+            .clone()
+            .expect("import stack not empty");
+        self.import_state.borrow_mut().record_import(ImportEdge {
+            importer: self.current_module.clone(),
+            imported: resolved.clone(),
+            format: Format::Ryan,
+        });
+
+        let parsed = self.read_source(&resolved).and_then(|mut source| {
+            let block = parse_module(&sub_environment, &mut source).map_err(|err| {
+                Box::new(ImportError::ModuleFailed {
+                    path: resolved.clone(),
+                    source: err,
+                }) as Box<dyn Error>
+            })?;
+            self.import_state
+                .borrow_mut()
+                .cache_source(resolved.clone(), source);
+
+            Ok(block)
+        });
+        let used = parsed.map(|block| f(&block, sub_environment));
+        self.import_state.borrow_mut().import_stack.pop();
+
+        used
+    }
+
+    /// Gets the value of a module from a previous evaluation of the same program, if
+    /// nothing it read changed since.
+    fn get_memoized(&self, memo: &RefCell<Memo>, key: &ModuleKey) -> Option<Value> {
+        let import_state = self.import_state.borrow();
+
+        memo.borrow_mut().get(key, |path| {
+            let read = import_state.import_loader.load(path).ok()?;
+            let path = rc_world::str_to_rc(path);
+            loader::read_module(&path, read, import_state.max_import_bytes).ok()
+        })
+    }
+
+    /// Reads the content of a module from the import loader, unless it was read before
+    /// and its version did not change since.
+    fn read_source(&self, resolved: &Rc<str>) -> Result<ModuleSource, Box<dyn Error + 'static>> {
+        let mut import_state = self.import_state.borrow_mut();
+        let version = import_state.import_loader.version(resolved);
+        if let Some(memo) = &self.incremental {
+            // The content is needed to tell whether it changed, so always read it:
+            return self.read_source_incremental(&mut import_state, memo, resolved, version);
+        }
+        if let Some(source) = import_state.get_source(resolved, &version) {
+            return Ok(source);
+        }
+
+        // The standard input is gone once read, so a second import would silently be empty:
+        if &**resolved == loader::STDIN {
+            if import_state.stdin_imported {
+                return Err(Box::new(ImportError::StdinAlreadyImported));
+            }
+            import_state.stdin_imported = true;
+        }
+
+        let read = import_state.import_loader.load(resolved)?;
+        let bytes = loader::read_module(resolved, read, import_state.max_import_bytes)?;
+
+        Ok(ModuleSource {
+            version,
+            bytes: bytes.into(),
+            block: None,
+        })
+    }
+
+    /// Reads the content of a module from the import loader, recording it as an input of
+    /// the module being evaluated. The parsed code is still reused if the content is the
+    /// same as the last time it was read.
+    fn read_source_incremental(
+        &self,
+        import_state: &mut ImportState,
+        memo: &RefCell<Memo>,
+        resolved: &Rc<str>,
+        version: Option<String>,
+    ) -> Result<ModuleSource, Box<dyn Error + 'static>> {
+        if &**resolved == loader::STDIN {
+            memo.borrow_mut().taint();
+            if import_state.stdin_imported {
+                return Err(Box::new(ImportError::StdinAlreadyImported));
+            }
+            import_state.stdin_imported = true;
+        }
+
+        let read = import_state
+            .import_loader
+            .load(resolved)
+            .and_then(|read| loader::read_module(resolved, read, import_state.max_import_bytes));
+        memo.borrow_mut()
+            .record_read(resolved, read.as_deref().ok());
+        let bytes = read?;
+
+        match import_state.sources.get(resolved) {
+            Some(source) if *source.bytes == *bytes => {
+                import_state.parse_cache_stats.hits += 1;
+                Ok(source.clone())
+            }
+            _ => {
+                import_state.parse_cache_stats.misses += 1;
+                Ok(ModuleSource {
+                    version,
+                    bytes: bytes.into(),
+                    block: None,
+                })
+            }
+        }
+    }
+}
+
+/// Hashes the arguments of an import, so that modules imported with arguments are cached
+/// separately for each distinct value of the arguments.
+fn hash_args(args: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    args.to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// What to do when a dictionary ends up with the same key defined more than once, be it
+/// by literal keys, flatten expressions (`...`) or dictionary comprehensions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateKeyPolicy {
+    /// The last value wins. The key keeps the position where it was first defined. This
+    /// is the default.
+    #[default]
+    LastWins,
+    /// The first value wins. Later definitions of the same key are ignored.
+    FirstWins,
+    /// Defining the same key twice raises an error.
+    Error,
+}
+
+/// What to do when an arithmetic operation mixes a float with an integer whose magnitude
+/// is bigger than 2^53. The integer is turned into a float for the operation, and floats
+/// cannot represent all integers that big, so the result may be silently off. This is
+/// the same limit JSON parsers that read every number as a float (JavaScript's, for one)
+/// have.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PrecisionLossPolicy {
+    /// The operation goes on, but a warning is sent as an [`EvalEvent::Warning`]. This
+    /// is the default.
+    #[default]
+    Warn,
+    /// The operation raises an error.
+    Error,
+    /// The operation goes on silently.
+    Allow,
 }
 
 /// A builder for [`Environment`]s. Use [`Environment::builder`] to create a new builder.
 pub struct EnvironmentBuilder {
     import_loader: Box<dyn ImportLoader>,
     current_module: Option<Rc<str>>,
-    built_ins: Option<Rc<HashMap<Rc<str>, Value>>>,
+    built_ins: Option<Rc<BTreeMap<Rc<str>, Value>>>,
+    event_hook: Option<EventHook>,
+    trace: bool,
+    duplicate_keys: DuplicateKeyPolicy,
+    precision_loss: PrecisionLossPolicy,
+    max_call_depth: usize,
+    import_cache: bool,
+    cache_env_imports: bool,
+    max_cached_modules: Option<usize>,
+    max_import_bytes: Option<usize>,
+    #[cfg(feature = "builtins")]
+    max_range_size: Option<usize>,
+    strict: bool,
+    hermetic: bool,
+    warn_missing_env: bool,
+    required_env: Vec<Rc<str>>,
+    cancel_token: Option<CancellationToken>,
+    track_provenance: bool,
 }
 
 impl EnvironmentBuilder {
     /// Builds the environment with the supplied configurations.
     pub fn build(self) -> Environment {
+        #[cfg(feature = "builtins")]
+        let is_stock = self.built_ins.is_none();
+        let mut built_ins = self
+            .built_ins
+            .unwrap_or_else(|| BUILT_INS.with(Clone::clone));
+        let import_loader: Box<dyn ImportLoader> = if self.hermetic {
+            let is_effectful =
+                |value: &Value| matches!(value, Value::NativePatternMatch(pat) if pat.effectful);
+            if built_ins.values().any(is_effectful) {
+                built_ins = Rc::new(
+                    built_ins
+                        .iter()
+                        .filter(|(_, value)| !is_effectful(value))
+                        .map(|(id, value)| (id.clone(), value.clone()))
+                        .collect(),
+                );
+            }
+
+            Box::new(NoImport)
+        } else {
+            self.import_loader
+        };
+
+        #[cfg(feature = "builtins")]
+        if let Some(max_size) = self.max_range_size {
+            if built_ins.get("range").is_some_and(native::is_range) {
+                let range = native::range(max_size);
+                Rc::make_mut(&mut built_ins).insert(
+                    range.identifier.clone(),
+                    Value::NativePatternMatch(Rc::new(range)),
+                );
+            }
+        }
+
+        // The stock `help` only documents the stock built-ins:
+        #[cfg(feature = "builtins")]
+        if !is_stock && built_ins.get("help").is_some_and(native::is_help) {
+            let help = native::help(&built_ins);
+            Rc::make_mut(&mut built_ins).insert(
+                help.identifier.clone(),
+                Value::NativePatternMatch(Rc::new(help)),
+            );
+        }
+
         Environment {
             import_state: Rc::new(RefCell::new(ImportState {
-                import_loader: self.import_loader,
+                import_loader,
                 loaded: Default::default(),
+                sources: Default::default(),
+                parse_cache_stats: Default::default(),
                 import_stack: Default::default(),
+                import_graph: Default::default(),
+                import_cache: self.import_cache,
+                cache_env_imports: self.cache_env_imports,
+                max_cached_modules: self.max_cached_modules,
+                max_import_bytes: self.max_import_bytes,
+                stdin_imported: false,
+                missing_env: vec![],
             })),
             current_module: self.current_module,
-            built_ins: self
-                .built_ins
-                .unwrap_or_else(|| BUILT_INS.with(Clone::clone)),
+            built_ins,
+            trace_hook: self.event_hook.clone().filter(|_| self.trace),
+            event_hook: self.event_hook,
+            duplicate_keys: self.duplicate_keys,
+            precision_loss: self.precision_loss,
+            max_call_depth: self.max_call_depth,
+            import_args: None,
+            strict: self.strict,
+            hermetic: self.hermetic,
+            warn_missing_env: self.warn_missing_env,
+            required_env: self.required_env.into(),
+            cancel_token: self.cancel_token,
+            provenance: self.track_provenance.then(Rc::default),
+            incremental: None,
+            #[cfg(feature = "metrics")]
+            metrics: Rc::default(),
         }
     }
 
@@ -129,8 +951,236 @@ impl EnvironmentBuilder {
     }
 
     /// Sets the built_ins for the environment.
-    pub fn built_ins(mut self, built_ins: Rc<HashMap<Rc<str>, Value>>) -> Self {
+    pub fn built_ins(mut self, built_ins: Rc<BTreeMap<Rc<str>, Value>>) -> Self {
         self.built_ins = Some(built_ins);
         self
     }
+
+    /// Sets a callback that receives every [`EvalEvent`] emitted while evaluating with
+    /// this environment, e.g., imports being loaded and warnings.
+    pub fn on_event<F>(mut self, hook: F) -> Self
+    where
+        F: 'static + Fn(EvalEvent),
+    {
+        self.event_hook = Some(EventHook(Rc::new(hook)));
+        self
+    }
+
+    /// Sets whether the callback set with [`EnvironmentBuilder::on_event`] also receives
+    /// tracing events, telling step by step how the program was evaluated: each variable
+    /// bound, each rule of a pattern match tried and each branch of an `if` taken. The
+    /// default is `false`, since there are many of these events.
+    ///
+    /// ```rust
+    /// use std::{cell::RefCell, rc::Rc};
+    /// use ryan::environment::{Environment, EvalEvent};
+    ///
+    /// let events = Rc::new(RefCell::new(vec![]));
+    /// let recorded = events.clone();
+    /// let env = Environment::builder()
+    ///     .on_event(move |event| recorded.borrow_mut().push(event.to_string()))
+    ///     .trace(true)
+    ///     .build();
+    ///
+    /// let value: i64 = ryan::from_str_with_env(&env, "let x = 1; if x > 0 then x else 0").unwrap();
+    /// assert_eq!(value, 1);
+    /// assert_eq!(*events.borrow(), ["let x = 1", "if: took `then`"]);
+    /// ```
+    pub fn trace(mut self, trace: bool) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// Sets whether imported modules are cached at all. The default is `true`, which means
+    /// that each module is loaded only once for the whole lifetime of the environment,
+    /// even if imported many times. Set this to `false` if your import loader returns
+    /// data that changes over time. Even then, modules that have a version, as per
+    /// [`ImportLoader::version`], are still parsed only once for each version.
+    pub fn import_cache(mut self, cache: bool) -> Self {
+        self.import_cache = cache;
+        self
+    }
+
+    /// Sets whether modules imported from environment variables (`env:` paths) are
+    /// cached. The default is `false`, which means that an environment variable is read
+    /// again on every import, so that changes made by the host between evaluations are
+    /// always picked up. Set this to `true` to read each variable only once for the whole
+    /// lifetime of the environment, like files. To keep other paths out of the cache,
+    /// see [`ImportLoader::cacheable`].
+    pub fn cache_env_imports(mut self, cache: bool) -> Self {
+        self.cache_env_imports = cache;
+        self
+    }
+
+    /// Sets the maximum number of imported modules kept in the cache. When the cache is
+    /// full, the least recently used module is evicted. By default, the cache is
+    /// unbounded. Evicted modules are simply read again when imported.
+    pub fn max_cached_modules(mut self, max: usize) -> Self {
+        self.max_cached_modules = Some(max);
+        self
+    }
+
+    /// Sets the maximum size of an imported module, in bytes. Bigger modules fail to
+    /// import with [`ImportError::TooLarge`], without being read any further. By default,
+    /// modules can be of any size. Set this if your import loader reads from sources you
+    /// do not fully trust.
+    pub fn max_import_bytes(mut self, max: usize) -> Self {
+        self.max_import_bytes = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of elements the built-in `range` may generate. Bigger
+    /// ranges raise an error instead of exhausting the memory. The default is
+    /// [`native::DEFAULT_MAX_RANGE_SIZE`], around 16 million elements. Has no effect if
+    /// `range` was replaced using [`EnvironmentBuilder::built_ins`].
+    #[cfg(feature = "builtins")]
+    pub fn max_range_size(mut self, max: usize) -> Self {
+        self.max_range_size = Some(max);
+        self
+    }
+
+    /// Sets how many pattern matches may be applied one inside the other, e.g., `f` calling
+    /// `g` calling `h` is 3 deep. Deeper calls raise an error instead of overflowing the
+    /// stack, which is what a pattern match calling itself would do. The default is
+    /// [`DEFAULT_MAX_CALL_DEPTH`], which is safe on the 2 MiB stacks of spawned threads in
+    /// debug builds for most programs. Raise it if you evaluate on a bigger stack.
+    pub fn max_call_depth(mut self, max: usize) -> Self {
+        self.max_call_depth = max;
+        self
+    }
+
+    /// Sets what happens when a dictionary defines the same key twice. The default is
+    /// [`DuplicateKeyPolicy::LastWins`].
+    pub fn duplicate_keys(mut self, policy: DuplicateKeyPolicy) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// Sets what happens when an arithmetic operation mixes a float with an integer too
+    /// big to be turned into a float exactly. The default is
+    /// [`PrecisionLossPolicy::Warn`].
+    pub fn precision_loss(mut self, policy: PrecisionLossPolicy) -> Self {
+        self.precision_loss = policy;
+        self
+    }
+
+    /// Makes the environment hermetic: programs evaluated with it cannot import anything
+    /// (as if the import loader were [`NoImport`]) and the built-ins flagged as
+    /// [`NativePatternMatch::effectful`] are removed. This holds regardless of the order
+    /// in which the other methods of this builder are called. See
+    /// [`Environment::is_hermetic`].
+    ///
+    /// ```rust
+    /// use std::{convert::Infallible, rc::Rc};
+    /// use ryan::environment::{Environment, NativePatternMatch, BUILT_INS};
+    /// use ryan::parser::{Pattern, Value};
+    ///
+    /// let mut built_ins = BUILT_INS.with(|built_ins| (**built_ins).clone());
+    /// let now = NativePatternMatch::new("now", Pattern::Identifier("_".into(), None), |_| {
+    ///     Ok::<_, Infallible>(Value::Integer(1_700_000_000))
+    /// })
+    /// .effectful(true);
+    /// built_ins.insert("now".into(), Value::NativePatternMatch(Rc::new(now)));
+    ///
+    /// let env = Environment::builder()
+    ///     .built_ins(Rc::new(built_ins))
+    ///     .hermetic()
+    ///     .build();
+    /// assert!(env.is_hermetic());
+    /// assert!(env.builtin("now").is_none());
+    /// assert!(env.builtin("len").is_some());
+    /// assert!(ryan::from_str_with_env::<String>(&env, r#"import "env:HOME" as text"#).is_err());
+    /// ```
+    pub fn hermetic(mut self) -> Self {
+        self.hermetic = true;
+        self
+    }
+
+    /// Sets whether to warn about every `env:` import that fell back to its `or` default
+    /// because the variable is not set. The default is `false`. A typo in the name of a
+    /// variable makes its import use the default forever, so this lists such variables,
+    /// with the modules importing them, as [`EvalEvent::Warning`]s at the end of each
+    /// evaluation, where they are easy to spot.
+    ///
+    /// ```rust
+    /// use std::{cell::RefCell, rc::Rc};
+    /// use ryan::environment::{Environment, EvalEvent};
+    ///
+    /// let warnings = Rc::new(RefCell::new(vec![]));
+    /// let recorded = warnings.clone();
+    /// let env = Environment::builder()
+    ///     .warn_missing_env(true)
+    ///     .on_event(move |event| {
+    ///         if let EvalEvent::Warning { message } = event {
+    ///             recorded.borrow_mut().push(message.to_string());
+    ///         }
+    ///     })
+    ///     .build();
+    ///
+    /// let url: String = ryan::from_str_with_env(
+    ///     &env,
+    ///     r#"import "env:__RYAN_DATABSE_URL__" as text or "localhost""#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(url, "localhost");
+    /// assert_eq!(
+    ///     warnings.borrow().last().unwrap(),
+    ///     "Environment variable __RYAN_DATABSE_URL__ is not set, so <main> used the default \
+    ///     of its import. Is the name right?"
+    /// );
+    /// ```
+    pub fn warn_missing_env(mut self, warn: bool) -> Self {
+        self.warn_missing_env = warn;
+        self
+    }
+
+    /// Makes the `env:` imports of these variables fail if the variable is not set, even
+    /// if the import has an `or` default. Use this for the variables that must always be
+    /// set in production, while keeping the defaults for local development.
+    ///
+    /// ```rust
+    /// use ryan::environment::Environment;
+    ///
+    /// let env = Environment::builder()
+    ///     .require_env(["__RYAN_REQUIRED__"])
+    ///     .build();
+    /// let code = r#"import "env:__RYAN_REQUIRED__" as text or "default""#;
+    /// assert!(ryan::from_str_with_env::<String>(&env, code).is_err());
+    /// ```
+    pub fn require_env<I, S>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.required_env.extend(
+            vars.into_iter()
+                .map(|var| rc_world::str_to_rc(var.as_ref())),
+        );
+        self
+    }
+
+    /// Sets a token to stop evaluations with this environment from another thread. See
+    /// [`CancellationToken`] for where the evaluation checks it.
+    pub fn cancel_token(mut self, token: CancellationToken) -> Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    /// Sets whether programs run in strict mode. The default is `false`. In strict mode,
+    /// a program (or an imported module) without a final expression is an error, instead
+    /// of evaluating to `null`.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets whether to track where each part of an evaluated value comes from, e.g., which
+    /// module and binding produced each key of a dictionary. The default is `false`, since
+    /// tracking keeps every list and dictionary created during evaluation alive for as
+    /// long as the environment. See [`Environment::provenance`] and
+    /// [`crate::eval_with_provenance`].
+    pub fn track_provenance(mut self, track: bool) -> Self {
+        self.track_provenance = track;
+        self
+    }
 }
@@ -1,17 +1,29 @@
+#[cfg(feature = "builtins")]
+use indexmap::IndexMap;
+#[cfg(feature = "builtins")]
+use std::cmp;
 use std::{
-    cmp,
-    collections::HashMap,
+    collections::BTreeMap,
     error::Error,
     fmt::{self, Debug, Display},
     rc::Rc,
 };
 use thiserror::Error;
 
+#[cfg(feature = "builtins")]
 use crate::{
-    parser::{NotIterable, Pattern, TypeExpression, Value},
+    diff::DiffEntry,
+    parser::{NotIterable, TypeExpression, MAX_REPETITION_SIZE},
+    utils::QuotedStr,
+};
+use crate::{
+    parser::{Pattern, Value},
     rc_world,
 };
 
+/// The closure called by a [`NativePatternMatch`] with the matched value.
+pub type NativeFunction = Box<dyn Fn(Value) -> Result<Value, Box<dyn Error + 'static>>>;
+
 /// A native pattern match. It matches a Ryan value to a given pattern and, if there is
 /// a match, applies a supplied closure to the value. Use this type to create your own
 /// extensions and built-in functions to Ryan.
@@ -21,7 +33,16 @@ pub struct NativePatternMatch {
     /// The pattern to which input values must comply to.
     pub pattern: Pattern,
     /// The native function mapping the input value to the output value.
-    pub func: Box<dyn Fn(Value) -> Result<Value, Box<dyn Error + 'static>>>,
+    pub func: NativeFunction,
+    /// Whether the function reaches out to the world, e.g., reading the clock or a
+    /// secret store. Effectful pattern matches are removed from hermetic environments.
+    /// See [`crate::environment::EnvironmentBuilder::hermetic`].
+    pub effectful: bool,
+    /// How to call this pattern match, e.g., `len x: [any] | {any} | text | bytes`, as
+    /// shown by the `help` built-in.
+    pub signature: Option<Rc<str>>,
+    /// What this pattern match does, as shown by the `help` built-in.
+    pub doc: Option<Rc<str>>,
 }
 
 impl Display for NativePatternMatch {
@@ -53,8 +74,25 @@ impl NativePatternMatch {
             identifier: rc_world::str_to_rc(name),
             pattern,
             func: Box::new(move |v| f(v).map_err(|e| Box::new(e).into())),
+            effectful: false,
+            signature: None,
+            doc: None,
         }
     }
+
+    /// Sets how to call this pattern match and what it does, as shown by the `help`
+    /// built-in. The signature reads like a pattern, e.g., `split sep: text`.
+    pub fn with_doc(mut self, signature: &str, doc: &str) -> NativePatternMatch {
+        self.signature = Some(rc_world::str_to_rc(signature));
+        self.doc = Some(rc_world::str_to_rc(doc));
+        self
+    }
+
+    /// Sets whether this pattern match reaches out to the world. The default is `false`.
+    pub fn effectful(mut self, effectful: bool) -> NativePatternMatch {
+        self.effectful = effectful;
+        self
+    }
 }
 
 /// A wrapper around a string that implements [`Error`]. Use this type to conveniently
@@ -68,18 +106,286 @@ impl Display for BuiltinErrorMsg {
     }
 }
 
-fn build_built_ins() -> HashMap<Rc<str>, Value> {
-    let mut built_ins = HashMap::new();
+/// The maximum number of items `range` may produce by default, so that a typo does not
+/// exhaust the memory. See [`crate::environment::EnvironmentBuilder::max_range_size`].
+#[cfg(feature = "builtins")]
+pub const DEFAULT_MAX_RANGE_SIZE: usize = 1 << 24;
+
+/// The signature and the documentation of each stock built-in, as shown by `help`.
+#[cfg(feature = "builtins")]
+const BUILT_IN_DOCS: &[(&str, &str, &str)] = &[
+    (
+        "fmt",
+        "fmt x: any",
+        "Transforms any value into a text that represents it, e.g., `\"there are \" + fmt 4 + \" lights\"`.",
+    ),
+    (
+        "len",
+        "len x: [any] | {any} | text | bytes",
+        "Gets the length of a list, a dictionary, a text or some bytes.",
+    ),
+    (
+        "range",
+        "range [start: int, end: int] | [start: int, end: int, step: int]",
+        "Generates the list of integers from `start` up to, but not including, `end`, counting `step` by `step` (one, by default). Count down with a negative step, e.g., `range [3, 0, -1]` = `[3, 2, 1]`. Ranges are capped at around 16 million elements.",
+    ),
+    (
+        "list.zip",
+        "list.zip [left, right]",
+        "Pairs the elements of two iterables in the same position, e.g., `list.zip [[1, 2], [3, 4]]` = `[[1, 3], [2, 4]]`.",
+    ),
+    (
+        "list.enumerate",
+        "list.enumerate x: [any] | {any}",
+        "Pairs each element of an iterable with its index, counting from zero, e.g., `list.enumerate [\"a\", \"b\"]` = `[[0, \"a\"], [1, \"b\"]]`.",
+    ),
+    (
+        "math.sum",
+        "math.sum x: [number]",
+        "Returns the sum of all numbers in a list.",
+    ),
+    (
+        "math.max",
+        "math.max x: [number]",
+        "Returns the maximum of all numbers in a non-empty list.",
+    ),
+    (
+        "math.min",
+        "math.min x: [number]",
+        "Returns the minimum of all numbers in a non-empty list.",
+    ),
+    (
+        "list.all",
+        "list.all x: [bool]",
+        "Returns `true` if there is no `false` in the list.",
+    ),
+    (
+        "list.any",
+        "list.any x: [bool]",
+        "Returns `true` if there is some `true` in the list.",
+    ),
+    (
+        "list.sort",
+        "list.sort x: [number] | [text]",
+        "Returns a sorted copy of a list. Integers and floats can be mixed and `NaN` is sorted last.",
+    ),
+    (
+        "dict.keys",
+        "dict.keys x: {any}",
+        "Returns the list of the keys of a dictionary, in order.",
+    ),
+    (
+        "dict.values",
+        "dict.values x: {any}",
+        "Returns the list of the values of a dictionary, in the order of their keys.",
+    ),
+    (
+        "dict.sort_keys",
+        "dict.sort_keys x: {any}",
+        "Returns a copy of a dictionary with its keys sorted, e.g., `dict.sort_keys { b: 1, a: 2 }` = `{ a: 2, b: 1 }`. Nested dictionaries are left as they are.",
+    ),
+    (
+        "dict.from_entries",
+        "dict.from_entries x: [[text, any]]",
+        "Builds a dictionary out of a list of `[key, value]` entries, e.g., `dict.from_entries [[\"a\", 1]]` = `{ a: 1 }`.",
+    ),
+    (
+        "str.join",
+        "str.join sep: text",
+        "Returns a pattern that joins a list of texts with the separator, e.g., `(str.join \",\") [\"a\", \"b\"]` = `\"a,b\"`.",
+    ),
+    (
+        "str.split",
+        "str.split sep: text",
+        "Returns a pattern that splits a text by the separator, e.g., `(str.split \",\") \"a,b\"` = `[\"a\", \"b\"]`.",
+    ),
+    (
+        "str.trim",
+        "str.trim x: text",
+        "Removes all leading and trailing whitespace from a text.",
+    ),
+    (
+        "str.trim_start",
+        "str.trim_start x: text",
+        "Removes all leading whitespace from a text.",
+    ),
+    (
+        "str.trim_end",
+        "str.trim_end x: text",
+        "Removes all trailing whitespace from a text.",
+    ),
+    (
+        "str.starts_with",
+        "str.starts_with prefix: text",
+        "Returns a pattern that tests whether a text starts with the prefix.",
+    ),
+    (
+        "str.ends_with",
+        "str.ends_with postfix: text",
+        "Returns a pattern that tests whether a text ends with the postfix.",
+    ),
+    (
+        "str.lowercase",
+        "str.lowercase x: text",
+        "Makes all letters of a text lowercase.",
+    ),
+    (
+        "str.uppercase",
+        "str.uppercase x: text",
+        "Makes all letters of a text uppercase.",
+    ),
+    (
+        "str.replace",
+        "str.replace [find: text, subst: text]",
+        "Returns a pattern that substitutes all occurrences of `find` in a text with `subst`.",
+    ),
+    (
+        "str.pad_start",
+        "str.pad_start [width: int, fill: text]",
+        "Returns a pattern that pads a text at the start with the character `fill` until it is `width` characters long.",
+    ),
+    (
+        "str.pad_end",
+        "str.pad_end [width: int, fill: text]",
+        "Returns a pattern that pads a text at the end with the character `fill` until it is `width` characters long.",
+    ),
+    (
+        "str.repeat",
+        "str.repeat n: int",
+        "Returns a pattern that repeats a text `n` times.",
+    ),
+    (
+        "str.contains",
+        "str.contains needle: text",
+        "Returns a pattern that tests whether a text contains `needle`.",
+    ),
+    (
+        "str.index_of",
+        "str.index_of needle: text",
+        "Returns a pattern that finds the position, in characters, of the first occurrence of `needle` in a text, or `null` if there is none.",
+    ),
+    (
+        "str.char_at",
+        "str.char_at i: int",
+        "Returns a pattern that gets the character at position `i` of a text, counting from zero.",
+    ),
+    (
+        "str.shell_quote",
+        "str.shell_quote x: text",
+        "Quotes a text to be used as a single argument in a POSIX shell command.",
+    ),
+    (
+        "str.json_escape",
+        "str.json_escape x: text",
+        "Escapes a text to be used inside a JSON string, without the surrounding quotes.",
+    ),
+    (
+        "str.yaml_quote",
+        "str.yaml_quote x: text",
+        "Quotes a text as a YAML scalar.",
+    ),
+    (
+        "str.base64_encode",
+        "str.base64_encode x: text | bytes",
+        "Encodes text, as UTF-8, or bytes in standard base64.",
+    ),
+    (
+        "str.base64_decode",
+        "str.base64_decode x: text",
+        "Decodes standard base64 into bytes. Padding is optional and whitespace is ignored.",
+    ),
+    (
+        "str.parse_int",
+        "str.parse_int x: text | int",
+        "Parses a text as an integer. Given a radix from 2 to 36 instead, returns a pattern that parses a text in that radix, e.g., `(str.parse_int 16) \"ff\"` = `255`.",
+    ),
+    (
+        "str.parse_float",
+        "str.parse_float x: text",
+        "Parses a text as a float, raising an error if it is not a valid float.",
+    ),
+    (
+        "str.try_parse_float",
+        "str.try_parse_float x: text",
+        "Parses a text as a float, returning `null` if it is not a valid float.",
+    ),
+    (
+        "math.floor",
+        "math.floor x: float",
+        "Calculates the floor of a number.",
+    ),
+    (
+        "math.ceil",
+        "math.ceil x: float",
+        "Calculates the ceiling of a number.",
+    ),
+    (
+        "math.round",
+        "math.round x: float",
+        "Rounds a number to the nearest integer.",
+    ),
+    (
+        "deep_equal",
+        "deep_equal [a, b]",
+        "Tests whether two values are structurally equal. Unlike `==`, integers are never equal to floats and `NaN` is equal to itself.",
+    ),
+    (
+        "is_subset",
+        "is_subset [partial, full]",
+        "Tests whether every key in the dictionaries of `partial` exists in `full` with an equal value. Lists are compared position by position.",
+    ),
+    (
+        "diff",
+        "diff [old, new]",
+        "Lists the differences between two values as dictionaries with a `path`, a `kind` and the values involved.",
+    ),
+    (
+        "intersect",
+        "intersect [left, right]",
+        "Keeps the elements of the list `left` that are in the list `right`, or the entries of the dictionary `left` whose keys are in the dictionary `right`.",
+    ),
+    (
+        "help",
+        "help name: text | null",
+        "Returns the `name`, `signature` and `doc` of a built-in, given its name, or a dictionary of all built-ins to their signatures, given `null`. Built-ins in a namespace go by their full names, e.g., `str.trim`.",
+    ),
+];
+
+#[cfg(feature = "builtins")]
+fn build_built_ins() -> BTreeMap<Rc<str>, Value> {
+    let mut built_ins = BTreeMap::new();
 
     fn t(s: &str) -> Rc<str> {
         rc_world::str_to_rc(s)
     }
 
+    let mut namespaces = BTreeMap::<Rc<str>, IndexMap<Rc<str>, Value>>::new();
+
     let mut insert = |pat: NativePatternMatch| {
-        built_ins.insert(
-            pat.identifier.clone(),
-            Value::NativePatternMatch(pat.into()),
-        )
+        let doc = BUILT_IN_DOCS
+            .iter()
+            .find(|(id, ..)| **id == *pat.identifier);
+        let pat = match doc {
+            Some((_, signature, doc)) => pat.with_doc(signature, doc),
+            None => pat,
+        };
+        let identifier = pat.identifier.clone();
+        let value = Value::NativePatternMatch(pat.into());
+
+        // Built-ins in a namespace, like `str.trim`, are also available by their bare
+        // names, as deprecated aliases from before there were namespaces:
+        match identifier.split_once('.') {
+            Some((namespace, name)) => {
+                namespaces
+                    .entry(t(namespace))
+                    .or_default()
+                    .insert(t(name), value.clone());
+                built_ins.insert(t(name), value);
+            }
+            None => {
+                built_ins.insert(identifier, value);
+            }
+        }
     };
 
     insert(NativePatternMatch::new(
@@ -97,32 +403,21 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
                 Value::List(list) => list.len() as i64,
                 Value::Map(map) => map.len() as i64,
                 Value::Text(text) => text.len() as i64,
-                _ => return Err(BuiltinErrorMsg(format!("Value `{value}` has no length"))),
+                Value::Bytes(bytes) => bytes.len() as i64,
+                _ => {
+                    return Err(BuiltinErrorMsg(format!(
+                        "Value `{}` has no length",
+                        value.brief()
+                    )))
+                }
             };
 
             Ok(Value::Integer(len))
         },
     ));
+    insert(range(DEFAULT_MAX_RANGE_SIZE));
     insert(NativePatternMatch::new(
-        "range",
-        Pattern::MatchList(vec![
-            Pattern::Identifier(t("start"), None),
-            Pattern::Identifier(t("end"), None),
-        ]),
-        move |value| match value {
-            Value::List(range) => match &*range {
-                [Value::Integer(start), Value::Integer(end)] => {
-                    Ok(Value::List((*start..*end).map(Value::Integer).collect()))
-                }
-                bad => Err(BuiltinErrorMsg(format!("List `{bad:?}` cannot be a range"))),
-            },
-            _ => Err(BuiltinErrorMsg(format!(
-                "Value `{value}` cannot be a range"
-            ))),
-        },
-    ));
-    insert(NativePatternMatch::new(
-        "zip",
+        "list.zip",
         Pattern::MatchList(vec![
             Pattern::Identifier(t("left"), None),
             Pattern::Identifier(t("right"), None),
@@ -145,7 +440,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
         },
     ));
     insert(NativePatternMatch::new(
-        "enumerate",
+        "list.enumerate",
         Pattern::Identifier(t("x"), None),
         move |value| {
             let enumerated: Value = value
@@ -157,20 +452,24 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
         },
     ));
     insert(NativePatternMatch::new(
-        "sum",
+        "math.sum",
         Pattern::Identifier(
             t("x"),
-            Some(TypeExpression::List(Box::new(TypeExpression::Or(vec![
-                TypeExpression::Float,
-                TypeExpression::Integer,
-            ])))),
+            Some(TypeExpression::List(Box::new(TypeExpression::Number))),
         ),
         move |value| {
             let mut sum = Value::Integer(0);
 
-            for val in value.iter()? {
+            let Value::List(list) = value else {
+                unreachable!()
+            };
+
+            for val in list.iter().cloned() {
                 sum = match (val, sum) {
-                    (Value::Integer(val), Value::Integer(sum)) => Value::Integer(val + sum),
+                    (Value::Integer(val), Value::Integer(sum)) => match val.checked_add(sum) {
+                        Some(sum) => Value::Integer(sum),
+                        None => return Err(BuiltinErrorMsg("Integer overflow in sum".into())),
+                    },
                     (Value::Float(val), Value::Integer(sum)) => Value::Float(val + sum as f64),
                     (Value::Integer(val), Value::Float(sum)) => Value::Float(val as f64 + sum),
                     (Value::Float(val), Value::Float(sum)) => Value::Float(val + sum),
@@ -178,73 +477,45 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
                 }
             }
 
-            Ok(sum) as Result<_, NotIterable>
+            Ok(sum)
         },
     ));
     insert(NativePatternMatch::new(
-        "max",
+        "math.max",
         Pattern::Identifier(
             t("x"),
-            Some(TypeExpression::List(Box::new(TypeExpression::Or(vec![
-                TypeExpression::Float,
-                TypeExpression::Integer,
-            ])))),
+            Some(TypeExpression::List(Box::new(TypeExpression::Number))),
         ),
         move |value| {
-            let mut max = Value::Integer(0);
-
-            for val in value.iter()? {
-                max = match (val, max) {
-                    (Value::Integer(val), Value::Integer(max)) => {
-                        Value::Integer(i64::max(val, max))
-                    }
-                    (Value::Float(val), Value::Integer(max)) => {
-                        Value::Float(f64::max(val, max as f64))
-                    }
-                    (Value::Integer(val), Value::Float(max)) => {
-                        Value::Float(f64::max(val as f64, max))
-                    }
-                    (Value::Float(val), Value::Float(max)) => Value::Float(f64::max(val, max)),
-                    _ => unreachable!(),
-                }
-            }
+            let Value::List(list) = value else {
+                unreachable!()
+            };
 
-            Ok(max) as Result<_, NotIterable>
+            list.iter()
+                .max_by(|a, b| a.partial_cmp(b).expect("numbers are always comparable"))
+                .cloned()
+                .ok_or_else(|| BuiltinErrorMsg("Cannot take the maximum of an empty list".into()))
         },
     ));
     insert(NativePatternMatch::new(
-        "min",
+        "math.min",
         Pattern::Identifier(
             t("x"),
-            Some(TypeExpression::List(Box::new(TypeExpression::Or(vec![
-                TypeExpression::Float,
-                TypeExpression::Integer,
-            ])))),
+            Some(TypeExpression::List(Box::new(TypeExpression::Number))),
         ),
         move |value| {
-            let mut min = Value::Integer(0);
-
-            for val in value.iter()? {
-                min = match (val, min) {
-                    (Value::Integer(val), Value::Integer(min)) => {
-                        Value::Integer(i64::min(val, min))
-                    }
-                    (Value::Float(val), Value::Integer(min)) => {
-                        Value::Float(f64::min(val, min as f64))
-                    }
-                    (Value::Integer(val), Value::Float(min)) => {
-                        Value::Float(f64::min(val as f64, min))
-                    }
-                    (Value::Float(val), Value::Float(min)) => Value::Float(f64::min(val, min)),
-                    _ => unreachable!(),
-                }
-            }
+            let Value::List(list) = value else {
+                unreachable!()
+            };
 
-            Ok(min) as Result<_, NotIterable>
+            list.iter()
+                .min_by(|a, b| a.partial_cmp(b).expect("numbers are always comparable"))
+                .cloned()
+                .ok_or_else(|| BuiltinErrorMsg("Cannot take the minimum of an empty list".into()))
         },
     ));
     insert(NativePatternMatch::new(
-        "all",
+        "list.all",
         Pattern::Identifier(
             t("x"),
             Some(TypeExpression::List(Box::new(TypeExpression::Or(vec![
@@ -262,7 +533,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
         },
     ));
     insert(NativePatternMatch::new(
-        "any",
+        "list.any",
         Pattern::Identifier(
             t("x"),
             Some(TypeExpression::List(Box::new(TypeExpression::Or(vec![
@@ -288,7 +559,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
     }
 
     insert(NativePatternMatch::new(
-        "sort",
+        "list.sort",
         Pattern::Identifier(
             t("x"),
             Some(TypeExpression::List(Box::new(TypeExpression::Any))),
@@ -312,14 +583,14 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
             });
 
             if let Some(error) = bad_comp {
-                Err(error)
+                Err(Box::new(error))
             } else {
                 Ok(Value::List(list.into()))
             }
         },
     ));
     insert(NativePatternMatch::new(
-        "keys",
+        "dict.keys",
         Pattern::Identifier(
             t("x"),
             Some(TypeExpression::Dictionary(Box::new(TypeExpression::Any))),
@@ -337,7 +608,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
         },
     ));
     insert(NativePatternMatch::new(
-        "values",
+        "dict.values",
         Pattern::Identifier(
             t("x"),
             Some(TypeExpression::Dictionary(Box::new(TypeExpression::Any))),
@@ -352,7 +623,54 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
         },
     ));
     insert(NativePatternMatch::new(
-        "join",
+        "dict.sort_keys",
+        Pattern::Identifier(
+            t("x"),
+            Some(TypeExpression::Dictionary(Box::new(TypeExpression::Any))),
+        ),
+        move |value| {
+            let Value::Map(dict) = value else {
+                unreachable!()
+            };
+            let mut sorted = dict.as_ref().clone();
+            sorted.sort_keys();
+
+            Ok(Value::Map(Rc::new(sorted))) as Result<_, BuiltinErrorMsg>
+        },
+    ));
+    insert(NativePatternMatch::new(
+        "dict.from_entries",
+        Pattern::Identifier(
+            t("x"),
+            Some(TypeExpression::List(Box::new(TypeExpression::Any))),
+        ),
+        move |value| {
+            let Value::List(list) = value else {
+                unreachable!()
+            };
+            let invalid = |item: &Value| {
+                BuiltinErrorMsg(format!(
+                    "Expected an entry `[key, value]` with a text key, got {}",
+                    item.brief()
+                ))
+            };
+            let mut dict = IndexMap::new();
+
+            for item in list.iter() {
+                let Value::List(entry) = item else {
+                    return Err(invalid(item));
+                };
+                let [Value::Text(key), value] = &**entry else {
+                    return Err(invalid(item));
+                };
+                dict.insert(key.clone(), value.clone());
+            }
+
+            Ok(Value::Map(Rc::new(dict)))
+        },
+    ));
+    insert(NativePatternMatch::new(
+        "str.join",
         Pattern::Identifier(t("sep"), Some(TypeExpression::Text)),
         move |value| {
             let Value::Text(separator) = value else {
@@ -360,7 +678,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
             };
 
             Ok(Value::NativePatternMatch(Rc::new(NativePatternMatch::new(
-                "join$ret",
+                "str.join$ret",
                 Pattern::Identifier(
                     t("x"),
                     Some(TypeExpression::List(Box::new(TypeExpression::Text))),
@@ -390,7 +708,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
         },
     ));
     insert(NativePatternMatch::new(
-        "split",
+        "str.split",
         Pattern::Identifier(t("sep"), Some(TypeExpression::Text)),
         move |value| {
             let Value::Text(separator) = value else {
@@ -398,7 +716,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
             };
 
             Ok(Value::NativePatternMatch(Rc::new(NativePatternMatch::new(
-                "split$ret",
+                "str.split$ret",
                 Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
                 move |value| {
                     let Value::Text(text) = value else {
@@ -415,7 +733,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
         },
     ));
     insert(NativePatternMatch::new(
-        "trim",
+        "str.trim",
         Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
         move |value| {
             let Value::Text(text) = value else {
@@ -428,7 +746,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
         },
     ));
     insert(NativePatternMatch::new(
-        "trim_start",
+        "str.trim_start",
         Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
         move |value| {
             let Value::Text(text) = value else {
@@ -439,7 +757,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
         },
     ));
     insert(NativePatternMatch::new(
-        "trim_end",
+        "str.trim_end",
         Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
         move |value| {
             let Value::Text(text) = value else {
@@ -450,7 +768,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
         },
     ));
     insert(NativePatternMatch::new(
-        "starts_with",
+        "str.starts_with",
         Pattern::Identifier(t("prefix"), Some(TypeExpression::Text)),
         move |value| {
             let Value::Text(prefix) = value else {
@@ -458,7 +776,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
             };
 
             Ok(Value::NativePatternMatch(Rc::new(NativePatternMatch::new(
-                "starts_with$ret",
+                "str.starts_with$ret",
                 Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
                 move |value| {
                     let Value::Text(text) = value else {
@@ -472,7 +790,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
         },
     ));
     insert(NativePatternMatch::new(
-        "ends_with",
+        "str.ends_with",
         Pattern::Identifier(t("postfix"), Some(TypeExpression::Text)),
         move |value| {
             let Value::Text(postfix) = value else {
@@ -480,7 +798,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
             };
 
             Ok(Value::NativePatternMatch(Rc::new(NativePatternMatch::new(
-                "ends_with$ret",
+                "str.ends_with$ret",
                 Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
                 move |value| {
                     let Value::Text(text) = value else {
@@ -494,7 +812,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
         },
     ));
     insert(NativePatternMatch::new(
-        "lowercase",
+        "str.lowercase",
         Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
         move |value| {
             let Value::Text(text) = value else {
@@ -506,7 +824,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
         },
     ));
     insert(NativePatternMatch::new(
-        "uppercase",
+        "str.uppercase",
         Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
         move |value| {
             let Value::Text(text) = value else {
@@ -518,7 +836,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
         },
     ));
     insert(NativePatternMatch::new(
-        "replace",
+        "str.replace",
         Pattern::MatchList(vec![
             Pattern::Identifier(t("find"), Some(TypeExpression::Text)),
             Pattern::Identifier(t("subst"), Some(TypeExpression::Text)),
@@ -534,7 +852,7 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
             let subst = subst.clone();
 
             Ok(Value::NativePatternMatch(Rc::new(NativePatternMatch::new(
-                "replace$ret",
+                "str.replace$ret",
                 Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
                 move |value| {
                     let Value::Text(text) = value else {
@@ -547,74 +865,772 @@ fn build_built_ins() -> HashMap<Rc<str>, Value> {
             )))) as Result<_, BuiltinErrorMsg>
         },
     ));
+    for (name, at_start) in [("str.pad_start", true), ("str.pad_end", false)] {
+        insert(NativePatternMatch::new(
+            name,
+            Pattern::MatchList(vec![
+                Pattern::Identifier(t("width"), Some(TypeExpression::Integer)),
+                Pattern::Identifier(t("fill"), Some(TypeExpression::Text)),
+            ]),
+            move |value| {
+                let Value::List(list) = value else {
+                    unreachable!()
+                };
+                let [Value::Integer(width), Value::Text(fill)] = &*list else {
+                    unreachable!()
+                };
+                let width = usize::try_from(*width)
+                    .ok()
+                    .filter(|&width| width <= MAX_REPETITION_SIZE)
+                    .ok_or_else(|| {
+                        BuiltinErrorMsg(format!(
+                            "Width must be between 0 and {MAX_REPETITION_SIZE}, got {width}"
+                        ))
+                    })?;
+                let mut chars = fill.chars();
+                let (Some(fill), None) = (chars.next(), chars.next()) else {
+                    return Err(BuiltinErrorMsg(format!(
+                        "Fill must be exactly one character, got {}",
+                        QuotedStr(fill)
+                    )));
+                };
+
+                Ok(Value::NativePatternMatch(Rc::new(NativePatternMatch::new(
+                    &format!("{name}$ret"),
+                    Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
+                    move |value| {
+                        let Value::Text(text) = value else {
+                            unreachable!()
+                        };
+
+                        let padding = fill
+                            .to_string()
+                            .repeat(width.saturating_sub(text.chars().count()));
+                        let padded = if at_start {
+                            padding + &*text
+                        } else {
+                            text.to_string() + &*padding
+                        };
+                        Ok(Value::Text(rc_world::string_to_rc(padded))) as Result<_, NotIterable>
+                    },
+                )))) as Result<_, BuiltinErrorMsg>
+            },
+        ));
+    }
+    insert(NativePatternMatch::new(
+        "str.repeat",
+        Pattern::Identifier(t("n"), Some(TypeExpression::Integer)),
+        move |value| {
+            let Value::Integer(n) = value else {
+                unreachable!()
+            };
+            let times = usize::try_from(n).map_err(|_| {
+                BuiltinErrorMsg(format!(
+                    "Cannot repeat a text a negative number of times, got {n}"
+                ))
+            })?;
+
+            Ok(Value::NativePatternMatch(Rc::new(NativePatternMatch::new(
+                "str.repeat$ret",
+                Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
+                move |value| {
+                    let Value::Text(text) = value else {
+                        unreachable!()
+                    };
+
+                    // Same limit as the `*` operator:
+                    if text.len().saturating_mul(times) > MAX_REPETITION_SIZE {
+                        return Err(BuiltinErrorMsg(format!(
+                            "Repeating a value of length {} {times} times exceeds the maximum \
+                            size of {MAX_REPETITION_SIZE}",
+                            text.len()
+                        )));
+                    }
+
+                    Ok(Value::Text(rc_world::string_to_rc(text.repeat(times))))
+                },
+            )))) as Result<_, BuiltinErrorMsg>
+        },
+    ));
+    insert(NativePatternMatch::new(
+        "str.contains",
+        Pattern::Identifier(t("needle"), Some(TypeExpression::Text)),
+        move |value| {
+            let Value::Text(needle) = value else {
+                unreachable!()
+            };
+
+            Ok(Value::NativePatternMatch(Rc::new(NativePatternMatch::new(
+                "str.contains$ret",
+                Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
+                move |value| {
+                    let Value::Text(text) = value else {
+                        unreachable!()
+                    };
+
+                    Ok(Value::Bool(text.contains(&*needle))) as Result<_, NotIterable>
+                },
+            )))) as Result<_, BuiltinErrorMsg>
+        },
+    ));
     insert(NativePatternMatch::new(
-        "parse_int",
+        "str.index_of",
+        Pattern::Identifier(t("needle"), Some(TypeExpression::Text)),
+        move |value| {
+            let Value::Text(needle) = value else {
+                unreachable!()
+            };
+
+            Ok(Value::NativePatternMatch(Rc::new(NativePatternMatch::new(
+                "str.index_of$ret",
+                Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
+                move |value| {
+                    let Value::Text(text) = value else {
+                        unreachable!()
+                    };
+
+                    // Counted in characters, not in bytes:
+                    let index = match text.find(&*needle) {
+                        Some(pos) => Value::Integer(text[..pos].chars().count() as i64),
+                        None => Value::Null,
+                    };
+                    Ok(index) as Result<_, NotIterable>
+                },
+            )))) as Result<_, BuiltinErrorMsg>
+        },
+    ));
+    insert(NativePatternMatch::new(
+        "str.char_at",
+        Pattern::Identifier(t("i"), Some(TypeExpression::Integer)),
+        move |value| {
+            let Value::Integer(idx) = value else {
+                unreachable!()
+            };
+            let idx = usize::try_from(idx).map_err(|_| {
+                BuiltinErrorMsg(format!("Character index must not be negative, got {idx}"))
+            })?;
+
+            Ok(Value::NativePatternMatch(Rc::new(NativePatternMatch::new(
+                "str.char_at$ret",
+                Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
+                move |value| {
+                    let Value::Text(text) = value else {
+                        unreachable!()
+                    };
+
+                    match text.chars().nth(idx) {
+                        Some(ch) => Ok(Value::Text(rc_world::string_to_rc(ch.to_string()))),
+                        None => Err(BuiltinErrorMsg(format!(
+                            "Tried to get character {idx} of a text of {} characters",
+                            text.chars().count()
+                        ))),
+                    }
+                },
+            )))) as Result<_, BuiltinErrorMsg>
+        },
+    ));
+    insert(NativePatternMatch::new(
+        "str.shell_quote",
         Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
         move |value| {
-            let Value::Text(int) = value else {
+            let Value::Text(text) = value else {
                 unreachable!()
             };
 
-            Ok(Value::Integer(
-                int.parse::<i64>()
-                    .map_err(|err| BuiltinErrorMsg(err.to_string()))?,
-            )) as Result<_, BuiltinErrorMsg>
+            // Nothing is special inside single quotes, except for the single quote itself,
+            // which has to be closed, escaped and reopened:
+            let quoted = format!("'{}'", text.replace('\'', r"'\''"));
+            Ok(Value::Text(rc_world::string_to_rc(quoted))) as Result<_, BuiltinErrorMsg>
         },
     ));
     insert(NativePatternMatch::new(
-        "parse_float",
+        "str.json_escape",
         Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
         move |value| {
-            let Value::Text(int) = value else {
+            let Value::Text(text) = value else {
                 unreachable!()
             };
 
-            Ok(Value::Float(
-                int.parse::<f64>()
-                    .map_err(|err| BuiltinErrorMsg(err.to_string()))?,
-            )) as Result<_, BuiltinErrorMsg>
+            let quoted = QuotedStr(&text).quote();
+            Ok(Value::Text(rc_world::str_to_rc(
+                &quoted[1..quoted.len() - 1],
+            ))) as Result<_, BuiltinErrorMsg>
         },
     ));
     insert(NativePatternMatch::new(
-        "floor",
-        Pattern::Identifier(t("x"), Some(TypeExpression::Float)),
+        "str.yaml_quote",
+        Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
         move |value| {
-            let Value::Float(float) = value else {
+            let Value::Text(text) = value else {
                 unreachable!()
             };
 
-            Ok(Value::Float(float.floor())) as Result<_, BuiltinErrorMsg>
+            // Line breaks in single-quoted scalars are folded into spaces. Double-quoted
+            // scalars, on the other hand, accept the same escapes as JSON.
+            let quoted = if text.chars().any(char::is_control) {
+                QuotedStr(&text).quote()
+            } else {
+                format!("'{}'", text.replace('\'', "''"))
+            };
+            Ok(Value::Text(rc_world::string_to_rc(quoted))) as Result<_, BuiltinErrorMsg>
+        },
+    ));
+    insert(NativePatternMatch::new(
+        "str.base64_encode",
+        Pattern::Identifier(
+            t("x"),
+            Some(TypeExpression::Or(vec![
+                TypeExpression::Text,
+                TypeExpression::Bytes,
+            ])),
+        ),
+        move |value| {
+            let encoded = match &value {
+                Value::Text(text) => crate::utils::base64_encode(text.as_bytes()),
+                Value::Bytes(bytes) => crate::utils::base64_encode(bytes),
+                _ => unreachable!(),
+            };
+
+            Ok(Value::Text(rc_world::string_to_rc(encoded))) as Result<_, BuiltinErrorMsg>
         },
     ));
     insert(NativePatternMatch::new(
-        "ceil",
-        Pattern::Identifier(t("x"), Some(TypeExpression::Float)),
+        "str.base64_decode",
+        Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
+        move |value| {
+            let Value::Text(text) = value else {
+                unreachable!()
+            };
+
+            crate::utils::base64_decode(&text)
+                .map(|bytes| Value::Bytes(bytes.into()))
+                .map_err(BuiltinErrorMsg)
+        },
+    ));
+    insert(NativePatternMatch::new(
+        "str.parse_int",
+        Pattern::Identifier(t("x"), None),
+        move |value| match value {
+            Value::Text(text) => crate::utils::parse_int(&text, 10)
+                .map(Value::Integer)
+                .ok_or_else(|| {
+                    BuiltinErrorMsg(format!("Cannot parse {} as int", QuotedStr(&text)))
+                }),
+            // A radix, returning the pattern that does the actual parsing:
+            Value::Integer(radix) => {
+                let radix = u32::try_from(radix)
+                    .ok()
+                    .filter(|radix| (2..=36).contains(radix))
+                    .ok_or_else(|| {
+                        BuiltinErrorMsg(format!("Radix must be between 2 and 36, got {radix}"))
+                    })?;
+
+                Ok(Value::NativePatternMatch(Rc::new(NativePatternMatch::new(
+                    "str.parse_int$ret",
+                    Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
+                    move |value| {
+                        let Value::Text(text) = value else {
+                            unreachable!()
+                        };
+
+                        crate::utils::parse_int(&text, radix)
+                            .map(Value::Integer)
+                            .ok_or_else(|| {
+                                BuiltinErrorMsg(format!(
+                                    "Cannot parse {} as int in radix {radix}",
+                                    QuotedStr(&text)
+                                ))
+                            })
+                    },
+                ))))
+            }
+            value => Err(BuiltinErrorMsg(format!(
+                "Expected text or a radix for `str.parse_int`, got {}",
+                value.brief()
+            ))),
+        },
+    ));
+    insert(NativePatternMatch::new(
+        "str.parse_float",
+        Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
+        move |value| {
+            let Value::Text(text) = value else {
+                unreachable!()
+            };
+
+            crate::utils::parse_float(&text)
+                .map(Value::Float)
+                .ok_or_else(|| {
+                    BuiltinErrorMsg(format!("Cannot parse {} as float", QuotedStr(&text)))
+                })
+        },
+    ));
+    insert(NativePatternMatch::new(
+        "str.try_parse_float",
+        Pattern::Identifier(t("x"), Some(TypeExpression::Text)),
         move |value| {
-            let Value::Float(float) = value else {
+            let Value::Text(text) = value else {
                 unreachable!()
             };
 
-            Ok(Value::Float(float.ceil())) as Result<_, BuiltinErrorMsg>
+            let parsed = crate::utils::parse_float(&text);
+            Ok(parsed.map(Value::Float).unwrap_or(Value::Null)) as Result<_, BuiltinErrorMsg>
         },
     ));
     insert(NativePatternMatch::new(
-        "round",
+        "math.floor",
+        Pattern::Identifier(t("x"), Some(TypeExpression::Float)),
+        move |value| Ok(Value::Float(as_float(value).floor())) as Result<_, BuiltinErrorMsg>,
+    ));
+    insert(NativePatternMatch::new(
+        "math.ceil",
         Pattern::Identifier(t("x"), Some(TypeExpression::Float)),
+        move |value| Ok(Value::Float(as_float(value).ceil())) as Result<_, BuiltinErrorMsg>,
+    ));
+    insert(NativePatternMatch::new(
+        "math.round",
+        Pattern::Identifier(t("x"), Some(TypeExpression::Float)),
+        move |value| Ok(Value::Float(as_float(value).round())) as Result<_, BuiltinErrorMsg>,
+    ));
+
+    insert(NativePatternMatch::new(
+        "deep_equal",
+        Pattern::MatchList(vec![
+            Pattern::Identifier(t("a"), None),
+            Pattern::Identifier(t("b"), None),
+        ]),
         move |value| {
-            let Value::Float(float) = value else {
+            let Value::List(list) = value else {
+                unreachable!()
+            };
+            let [a, b] = &*list else {
                 unreachable!()
             };
 
-            Ok(Value::Float(float.round())) as Result<_, BuiltinErrorMsg>
+            Ok(Value::Bool(deep_equal(a, b))) as Result<_, BuiltinErrorMsg>
         },
     ));
+    insert(NativePatternMatch::new(
+        "is_subset",
+        Pattern::MatchList(vec![
+            Pattern::Identifier(t("partial"), None),
+            Pattern::Identifier(t("full"), None),
+        ]),
+        move |value| {
+            let Value::List(list) = value else {
+                unreachable!()
+            };
+            let [partial, full] = &*list else {
+                unreachable!()
+            };
+
+            Ok(Value::Bool(is_subset(partial, full))) as Result<_, BuiltinErrorMsg>
+        },
+    ));
+    insert(NativePatternMatch::new(
+        "diff",
+        Pattern::MatchList(vec![
+            Pattern::Identifier(t("old"), None),
+            Pattern::Identifier(t("new"), None),
+        ]),
+        move |value| {
+            let Value::List(list) = value else {
+                unreachable!()
+            };
+            let [old, new] = &*list else {
+                unreachable!()
+            };
+
+            let entries: Value = crate::diff(old, new)
+                .iter()
+                .map(DiffEntry::to_value)
+                .collect();
+
+            Ok(entries) as Result<_, BuiltinErrorMsg>
+        },
+    ));
+    insert(NativePatternMatch::new(
+        "intersect",
+        Pattern::MatchList(vec![
+            Pattern::Identifier(t("left"), None),
+            Pattern::Identifier(t("right"), None),
+        ]),
+        move |value| {
+            let Value::List(list) = value else {
+                unreachable!()
+            };
+            let [left, right] = &*list else {
+                unreachable!()
+            };
+
+            match (left, right) {
+                (Value::List(left), Value::List(right)) => Ok(Value::List(
+                    left.iter()
+                        .filter(|item| right.contains(item))
+                        .cloned()
+                        .collect(),
+                )),
+                (Value::Map(left), Value::Map(right)) => Ok(Value::Map(Rc::new(
+                    left.iter()
+                        .filter(|(key, _)| right.contains_key(*key))
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect(),
+                ))),
+                _ => Err(BuiltinErrorMsg(format!(
+                    "Cannot intersect `{}` and `{}`: both must be lists or both must be \
+                    dictionaries",
+                    left.brief(),
+                    right.brief()
+                ))),
+            }
+        },
+    ));
+
+    for (namespace, mut members) in namespaces {
+        members.sort_keys();
+        built_ins.insert(namespace, Value::Map(Rc::new(members)));
+    }
+
+    let help = help(&built_ins);
+    built_ins.insert(
+        help.identifier.clone(),
+        Value::NativePatternMatch(help.into()),
+    );
 
     built_ins
 }
 
+/// Builds the `help` built-in, which documents the given built-ins, plus itself. Given
+/// a name, it returns the `name`, `signature` and `doc` of that built-in; given `null`,
+/// a dictionary of all built-ins to their signatures, sorted by name. The members of a
+/// namespace are listed by their full names, e.g., `str.trim`, and not by their aliases.
+#[cfg(feature = "builtins")]
+pub(crate) fn help(built_ins: &BTreeMap<Rc<str>, Value>) -> NativePatternMatch {
+    let (_, signature, doc) = BUILT_IN_DOCS
+        .iter()
+        .find(|(id, ..)| *id == "help")
+        .expect("help is documented");
+
+    let describe_value = |id: &Rc<str>, value: &Value| match value {
+        // Undocumented native pattern matches are still better than nothing:
+        Value::NativePatternMatch(pat) => {
+            let signature = pat
+                .signature
+                .clone()
+                .unwrap_or_else(|| rc_world::string_to_rc(format!("{id} {}", pat.pattern)));
+            describe(id, Some(signature), pat.doc.clone())
+        }
+        _ => describe(id, None, None),
+    };
+
+    let mut described = IndexMap::new();
+    let mut aliases = vec![];
+    for (id, value) in built_ins.iter().filter(|(id, _)| &***id != "help") {
+        match value {
+            Value::Map(members) if is_namespace(members) => {
+                for (name, member) in members.iter() {
+                    let name = rc_world::string_to_rc(format!("{id}.{name}"));
+                    described.insert(name.clone(), describe_value(&name, member));
+                }
+            }
+            Value::NativePatternMatch(pat) if pat.identifier != *id => {
+                aliases.push((id, value, pat.identifier.clone()));
+            }
+            _ => {
+                described.insert(id.clone(), describe_value(id, value));
+            }
+        }
+    }
+
+    // Aliases, like `trim` for `str.trim`, are documented by the name they stand for:
+    let mut canonical_names = IndexMap::new();
+    for (id, value, canonical) in aliases {
+        if described.contains_key(&canonical) {
+            canonical_names.insert(id.clone(), canonical);
+        } else {
+            described.insert(id.clone(), describe_value(id, value));
+        }
+    }
+    let id = rc_world::str_to_rc("help");
+    let help = describe(
+        &id,
+        Some(rc_world::str_to_rc(signature)),
+        Some(rc_world::str_to_rc(doc)),
+    );
+    described.insert(id, help);
+    described.sort_keys();
+
+    NativePatternMatch::new(
+        "help",
+        Pattern::Identifier(
+            rc_world::str_to_rc("name"),
+            Some(TypeExpression::Or(vec![
+                TypeExpression::Text,
+                TypeExpression::Null,
+            ])),
+        ),
+        move |value| match value {
+            Value::Text(name) => match described.get(&*name).or_else(|| {
+                let canonical = canonical_names.get(&*name)?;
+                described.get(canonical)
+            }) {
+                Some(description) => Ok(Value::Map(Rc::new(description.clone()))),
+                None => Err(BuiltinErrorMsg(format!(
+                    "There is no built-in named {}",
+                    QuotedStr(&name)
+                ))),
+            },
+            Value::Null => Ok(Value::Map(Rc::new(
+                described
+                    .iter()
+                    .map(|(id, description)| (id.clone(), description["signature"].clone()))
+                    .collect(),
+            ))),
+            _ => unreachable!(),
+        },
+    )
+    .with_doc(signature, doc)
+}
+
+/// Whether a value is the `help` built-in built by [`help`], as opposed to something else
+/// that happens to be called `help`.
+#[cfg(feature = "builtins")]
+pub(crate) fn is_help(value: &Value) -> bool {
+    is_stock(value, "help")
+}
+
+/// Whether a value is the `range` built-in built by [`range`], as opposed to something
+/// else that happens to be called `range`.
+#[cfg(feature = "builtins")]
+pub(crate) fn is_range(value: &Value) -> bool {
+    is_stock(value, "range")
+}
+
+/// Whether a value is the stock built-in called `name`.
+#[cfg(feature = "builtins")]
+fn is_stock(value: &Value, name: &str) -> bool {
+    matches!(
+        value,
+        Value::NativePatternMatch(pat)
+            if &*pat.identifier == name
+                && BUILT_IN_DOCS.iter().any(|(id, signature, _)| {
+                    *id == name && pat.signature.as_deref() == Some(*signature)
+                })
+    )
+}
+
+/// The `range` built-in, generating at most `max_size` integers.
+#[cfg(feature = "builtins")]
+pub(crate) fn range(max_size: usize) -> NativePatternMatch {
+    let (_, signature, doc) = BUILT_IN_DOCS
+        .iter()
+        .find(|(id, ..)| *id == "range")
+        .expect("range is documented");
+    let bounds = || {
+        vec![
+            Pattern::Identifier(rc_world::str_to_rc("start"), None),
+            Pattern::Identifier(rc_world::str_to_rc("end"), None),
+        ]
+    };
+    let with_step = bounds()
+        .into_iter()
+        .chain([Pattern::Identifier(rc_world::str_to_rc("step"), None)])
+        .collect();
+
+    NativePatternMatch::new(
+        "range",
+        Pattern::Or(vec![
+            Pattern::MatchList(bounds()),
+            Pattern::MatchList(with_step),
+        ]),
+        move |value| {
+            let Value::List(list) = &value else {
+                unreachable!()
+            };
+            let (start, end, step) = match &**list {
+                [Value::Integer(start), Value::Integer(end)] if end < start => {
+                    return Err(BuiltinErrorMsg(format!(
+                        "Range `{value}` counts down, but ranges count up by default. Use a \
+                        negative step to count down, e.g., `range [{start}, {end}, -1]`"
+                    )))
+                }
+                [Value::Integer(start), Value::Integer(end)] => (*start, *end, 1),
+                [Value::Integer(_), Value::Integer(_), Value::Integer(0)] => {
+                    return Err(BuiltinErrorMsg(format!(
+                        "The step of range `{value}` cannot be zero"
+                    )))
+                }
+                [Value::Integer(start), Value::Integer(end), Value::Integer(step)] => {
+                    (*start, *end, *step)
+                }
+                _ => {
+                    return Err(BuiltinErrorMsg(format!(
+                        "Range `{}` must be made of integers",
+                        value.brief()
+                    )))
+                }
+            };
+
+            // Computed in 128 bits, so that nothing overflows:
+            let (start, end, step) = (start as i128, end as i128, step as i128);
+            let distance = if step > 0 { end - start } else { start - end };
+            let len = if distance > 0 {
+                (distance + step.abs() - 1) / step.abs()
+            } else {
+                0
+            };
+
+            if len > max_size as i128 {
+                return Err(BuiltinErrorMsg(format!(
+                    "Range `{value}` has {len} elements, more than the maximum of {max_size}"
+                )));
+            }
+
+            Ok(Value::List(
+                (0..len)
+                    .map(|idx| Value::Integer((start + idx * step) as i64))
+                    .collect(),
+            ))
+        },
+    )
+    .with_doc(signature, doc)
+}
+
+/// Whether a dictionary of built-ins is a namespace, like `str`, rather than a built-in
+/// that happens to be a dictionary.
+#[cfg(feature = "builtins")]
+fn is_namespace(members: &IndexMap<Rc<str>, Value>) -> bool {
+    !members.is_empty()
+        && members
+            .values()
+            .all(|member| matches!(member, Value::NativePatternMatch(_)))
+}
+
+/// The dictionary returned by `help` for a single built-in.
+#[cfg(feature = "builtins")]
+fn describe(
+    id: &Rc<str>,
+    signature: Option<Rc<str>>,
+    doc: Option<Rc<str>>,
+) -> IndexMap<Rc<str>, Value> {
+    let or_null = |text: Option<Rc<str>>| text.map(Value::Text).unwrap_or(Value::Null);
+    let description = [
+        (rc_world::str_to_rc("name"), Value::Text(id.clone())),
+        (rc_world::str_to_rc("signature"), or_null(signature)),
+        (rc_world::str_to_rc("doc"), or_null(doc)),
+    ];
+
+    description.into_iter().collect()
+}
+
+/// The value of an argument matched by a `float` type guard, which lets integers through.
+#[cfg(feature = "builtins")]
+fn as_float(value: Value) -> f64 {
+    match value {
+        Value::Float(float) => float,
+        Value::Integer(int) => int as f64,
+        _ => unreachable!(),
+    }
+}
+
+/// Structural equality between two values. Unlike [`Value`]'s `==`, an integer is never
+/// equal to a float, even if both represent the same number. This uses an explicit stack,
+/// so deeply nested values cannot overflow the call stack. A key mapped to `null` is _not_
+/// the same as a missing key.
+#[cfg(feature = "builtins")]
+fn deep_equal(a: &Value, b: &Value) -> bool {
+    let mut stack = vec![(a, b)];
+
+    while let Some((a, b)) = stack.pop() {
+        match (a, b) {
+            (Value::List(a), Value::List(b)) => {
+                if a.len() != b.len() {
+                    return false;
+                }
+
+                stack.extend(a.iter().zip(b.iter()));
+            }
+            (Value::Map(a), Value::Map(b)) => {
+                if a.len() != b.len() {
+                    return false;
+                }
+
+                for (key, a) in a.iter() {
+                    let Some(b) = b.get(key) else {
+                        return false;
+                    };
+                    stack.push((a, b));
+                }
+            }
+            (a, b) => {
+                if !scalar_equal(a, b) {
+                    return false;
+                }
+            }
+        }
+    }
+
+    true
+}
+
+/// Tests whether `partial` is contained in `full`. Every key in a map of `partial` must
+/// exist in the corresponding map of `full` and lists are compared positionally, with the
+/// list in `partial` allowed to be shorter than the one in `full`. Anything else is
+/// compared like in [`deep_equal`]. This uses an explicit stack, so deeply nested values
+/// cannot overflow the call stack.
+#[cfg(feature = "builtins")]
+fn is_subset(partial: &Value, full: &Value) -> bool {
+    let mut stack = vec![(partial, full)];
+
+    while let Some((partial, full)) = stack.pop() {
+        match (partial, full) {
+            (Value::List(partial), Value::List(full)) => {
+                if partial.len() > full.len() {
+                    return false;
+                }
+
+                stack.extend(partial.iter().zip(full.iter()));
+            }
+            (Value::Map(partial), Value::Map(full)) => {
+                for (key, partial) in partial.iter() {
+                    let Some(full) = full.get(key) else {
+                        return false;
+                    };
+                    stack.push((partial, full));
+                }
+            }
+            (partial, full) => {
+                if !scalar_equal(partial, full) {
+                    return false;
+                }
+            }
+        }
+    }
+
+    true
+}
+
+/// Compares two values that are not both lists or both maps for [`deep_equal`] and
+/// [`is_subset`]. The variants must be the same, so `1` and `1.0` are different values.
+#[cfg(feature = "builtins")]
+fn scalar_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Integer(_), Value::Float(_)) | (Value::Float(_), Value::Integer(_)) => false,
+        (a, b) => a == b,
+    }
+}
+
+/// Without the `builtins` feature, there are no stock built-ins: only the ones set with
+/// [`crate::environment::EnvironmentBuilder::built_ins`].
+#[cfg(not(feature = "builtins"))]
+fn build_built_ins() -> BTreeMap<Rc<str>, Value> {
+    BTreeMap::new()
+}
+
 thread_local! {
     /// The Ryan default built_ins that are supplied as "batteries included". All default
     /// built_ins are guaranteed to finish executing and to not access the outside
-    /// environment, in compliance to Ryan's key principles.
-    pub static BUILT_INS: Rc<HashMap<Rc<str>, Value>> = Rc::new(build_built_ins());
+    /// environment, in compliance to Ryan's key principles. They are sorted by name, so
+    /// that listing them gives the same order everywhere.
+    pub static BUILT_INS: Rc<BTreeMap<Rc<str>, Value>> = Rc::new(build_built_ins());
 }
@@ -0,0 +1,147 @@
+use indexmap::IndexMap;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    rc::Rc,
+};
+
+use crate::parser::{PathSeg, Value};
+
+/// Where a part of an evaluated value was produced.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Origin {
+    /// The module that produced the value: the resolved path of a file, `env:VAR` for an
+    /// environment variable or `<main>` for code without a file name.
+    pub module: Rc<str>,
+    /// The innermost binding being evaluated when the value was produced, e.g., `db` in
+    /// `let db = { ... };`. This is `None` for the final expression of a module.
+    pub binding: Option<Rc<str>>,
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.binding {
+            Some(binding) => write!(f, "{}, in binding `{binding}`", self.module),
+            None => write!(f, "{}", self.module),
+        }
+    }
+}
+
+/// Where each part of an evaluated value came from, as returned by
+/// [`crate::eval_with_provenance`]. The provenance is coarse: it tells which module and
+/// binding produced each list, dictionary and entry, not the exact span of code.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Provenance {
+    origins: IndexMap<Vec<PathSeg>, Origin>,
+}
+
+impl Provenance {
+    /// Returns the origin of the value at the given path from the root, if known.
+    pub fn get(&self, path: &[PathSeg]) -> Option<&Origin> {
+        self.origins.get(path)
+    }
+
+    /// Iterates over the paths with a known origin, parents before children.
+    pub fn iter(&self) -> impl Iterator<Item = (&[PathSeg], &Origin)> {
+        self.origins
+            .iter()
+            .map(|(path, origin)| (path.as_slice(), origin))
+    }
+}
+
+/// Records the origins of values while they are being evaluated.
+#[derive(Debug, Default)]
+pub(crate) struct ProvenanceTracker {
+    /// Where each list, dictionary or bytes value was created, by address. The value is
+    /// kept alive, so that its address is not reused by another value. Text is not
+    /// tracked, since equal text may share the same address.
+    nodes: HashMap<usize, (Value, Origin)>,
+    /// Where each entry of a list or dictionary was put in it.
+    entries: HashMap<(usize, PathSeg), Origin>,
+    /// The origin of the value returned by the last import, if it succeeded.
+    pub(crate) last_import: Option<Origin>,
+}
+
+/// The address of the allocation behind a value, for the values that have a stable one.
+fn address(value: &Value) -> Option<usize> {
+    match value {
+        Value::Bytes(bytes) => Some(Rc::as_ptr(bytes) as *const u8 as usize),
+        Value::List(list) => Some(Rc::as_ptr(list) as *const Value as usize),
+        Value::Map(map) => Some(Rc::as_ptr(map) as usize),
+        _ => None,
+    }
+}
+
+impl ProvenanceTracker {
+    /// Records where a value was created, unless it is known already.
+    pub(crate) fn record(&mut self, value: &Value, origin: Origin) {
+        if let Some(address) = address(value) {
+            self.nodes
+                .entry(address)
+                .or_insert_with(|| (value.clone(), origin));
+        }
+    }
+
+    /// Records where each entry of a list or dictionary was put in it.
+    pub(crate) fn record_entries(
+        &mut self,
+        container: &Value,
+        entries: impl IntoIterator<Item = (PathSeg, Origin)>,
+    ) {
+        if let Some(address) = address(container) {
+            for (seg, origin) in entries {
+                self.entries.insert((address, seg), origin);
+            }
+        }
+    }
+
+    /// Returns where a value was created, if known.
+    pub(crate) fn node(&self, value: &Value) -> Option<Origin> {
+        let (_, origin) = self.nodes.get(&address(value)?)?;
+        Some(origin.clone())
+    }
+
+    /// Returns where an entry of a list or dictionary was put in it, if known.
+    pub(crate) fn entry(&self, container: &Value, seg: PathSeg) -> Option<Origin> {
+        self.entries.get(&(address(container)?, seg)).cloned()
+    }
+
+    /// Works out the origin of every part of a value. A value created somewhere keeps
+    /// the origin of its creation, wherever it was put afterwards. Otherwise, it has the
+    /// origin of the entry holding it or, failing that, of its parent.
+    pub(crate) fn provenance(&self, value: &Value) -> Provenance {
+        let mut origins = IndexMap::new();
+        let mut to_visit = vec![(vec![], value, None)];
+
+        while let Some((path, value, inherited)) = to_visit.pop() {
+            let origin = self.node(value).or(inherited);
+
+            let children = match value {
+                Value::List(list) => list
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, item)| (PathSeg::Index(idx), item))
+                    .collect::<Vec<_>>(),
+                Value::Map(map) => map
+                    .iter()
+                    .map(|(key, value)| (PathSeg::Key(key.clone()), value))
+                    .collect(),
+                _ => vec![],
+            };
+
+            // Reversed, so that children are visited in order:
+            for (seg, child) in children.into_iter().rev() {
+                let inherited = self.entry(value, seg.clone()).or_else(|| origin.clone());
+                let mut child_path = path.clone();
+                child_path.push(seg);
+                to_visit.push((child_path, child, inherited));
+            }
+
+            if let Some(origin) = origin {
+                origins.insert(path, origin);
+            }
+        }
+
+        Provenance { origins }
+    }
+}
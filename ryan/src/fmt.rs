@@ -0,0 +1,250 @@
+use crate::parser::{Comments, ItemComments, Layout, ParseError, Printer};
+
+/// The maximum width of a line in formatted code, if it can be helped.
+const WIDTH: usize = 80;
+
+/// A line comment, starting at `//` and ending before the newline.
+#[derive(Debug, Clone, Copy)]
+struct Comment {
+    start: usize,
+    end: usize,
+}
+
+/// Something to be printed in its own line(s) at the top level of the formatted code.
+struct Entry {
+    /// Where this entry starts and ends in the original source. A blank line is kept
+    /// before the entry if there was one before its start.
+    start: usize,
+    end: usize,
+    code: String,
+}
+
+/// Formats Ryan code in the canonical style: four spaces of indentation, one top-level
+/// binding per line and lists, dictionaries and comprehensions broken into many lines,
+/// with trailing commas, when they don't fit in 80 columns. Formatting twice is the same
+/// as formatting once.
+///
+/// Comments are kept next to the nearest item of the innermost list or dictionary around
+/// them, or else next to the nearest top-level binding. A comment inside an item, but not
+/// inside a list or dictionary of its own, is moved to just before the item. Blank lines
+/// between top-level items are collapsed into a single one.
+pub fn format(source: &str) -> Result<String, ParseError> {
+    let (block, _, layout) = crate::parser::parse_program(source)?;
+    let comments = find_comments(source);
+    let spans = trim_spans(source, &comments, &layout.items);
+    let mut inner = Comments::new();
+
+    let mut leading = vec![vec![]; spans.len()];
+    let mut trailing = vec![None; spans.len()];
+    let mut footer = vec![];
+
+    for &comment in &comments {
+        if attach_inside(source, &layout, &comments, comment, &mut inner) {
+            continue;
+        }
+
+        let inside = spans
+            .iter()
+            .position(|&(start, end)| start <= comment.start && comment.start < end);
+        let before = spans.iter().rposition(|&(_, end)| end <= comment.start);
+
+        if let Some(idx) = inside {
+            leading[idx].push((comment, spans[idx].0));
+        } else if let Some(idx) = before.filter(|&idx| {
+            trailing[idx].is_none() && !source[spans[idx].1..comment.start].contains('\n')
+        }) {
+            trailing[idx] = Some(comment);
+        } else if let Some(idx) = spans.iter().position(|&(start, _)| comment.start < start) {
+            leading[idx].push((comment, comment.start));
+        } else {
+            footer.push(comment);
+        }
+    }
+
+    // Comments moved from inside an item are anchored at the start of the item:
+    let comment_entry = |comment: Comment, anchor: usize| Entry {
+        start: anchor,
+        end: comment.end,
+        code: source[comment.start..comment.end].trim_end().to_string(),
+    };
+
+    let printer = Printer {
+        width: WIDTH,
+        comments: Some(&inner),
+    };
+    let mut entries = vec![];
+
+    for (idx, &(start, end)) in spans.iter().enumerate() {
+        for &(comment, anchor) in &leading[idx] {
+            entries.push(comment_entry(comment, anchor));
+        }
+
+        let mut code = match block.bindings.get(idx) {
+            Some(binding) => printer.binding(binding, 0) + ";",
+            None => printer.expression(&block.expression, 0, 0),
+        };
+
+        if let Some(comment) = trailing[idx] {
+            code += "  ";
+            code += source[comment.start..comment.end].trim_end();
+        }
+
+        entries.push(Entry {
+            start,
+            end: trailing[idx].map(|comment| comment.end).unwrap_or(end),
+            code,
+        });
+    }
+
+    for comment in footer {
+        entries.push(comment_entry(comment, comment.start));
+    }
+
+    let mut formatted = String::new();
+    let mut previous_end = None;
+
+    for entry in entries {
+        if let Some(previous_end) = previous_end {
+            // Empty if the entry was moved before something already printed:
+            let gap = source.get(previous_end..entry.start).unwrap_or_default();
+            if gap.matches('\n').count() >= 2 {
+                formatted += "\n";
+            }
+        }
+
+        formatted += &entry.code;
+        formatted += "\n";
+        previous_end = Some(entry.end);
+    }
+
+    Ok(formatted)
+}
+
+/// Attaches a comment to the nearest item of the innermost list or dictionary around it,
+/// in the same way as top-level comments are attached to bindings. Returns whether there
+/// was any list or dictionary around it.
+fn attach_inside(
+    source: &str,
+    layout: &Layout,
+    comments: &[Comment],
+    comment: Comment,
+    inner: &mut Comments,
+) -> bool {
+    let Some((&key, collection)) = layout
+        .collections
+        .iter()
+        .filter(|(_, collection)| {
+            collection.span.0 < comment.start && comment.start < collection.span.1
+        })
+        .max_by_key(|(_, collection)| collection.span.0)
+    else {
+        return false;
+    };
+
+    let items = trim_spans(source, comments, &collection.items);
+    let comments = inner.entry(key).or_insert_with(|| ItemComments {
+        leading: vec![vec![]; items.len()],
+        trailing: vec![None; items.len()],
+        closing: vec![],
+    });
+    let code = source[comment.start..comment.end].trim_end().to_string();
+
+    let inside = items
+        .iter()
+        .position(|&(start, end)| start <= comment.start && comment.start < end);
+    let before = items.iter().rposition(|&(_, end)| end <= comment.start);
+
+    if let Some(idx) = inside {
+        comments.leading[idx].push(code);
+    } else if let Some(idx) = before.filter(|&idx| {
+        comments.trailing[idx].is_none() && !source[items[idx].1..comment.start].contains('\n')
+    }) {
+        comments.trailing[idx] = Some(code);
+    } else if let Some(idx) = items.iter().position(|&(start, _)| comment.start < start) {
+        comments.leading[idx].push(code);
+    } else {
+        comments.closing.push(code);
+    }
+
+    true
+}
+
+/// Leaves out the whitespace and the comments that the parser sometimes includes at the
+/// end of the spans of the items, when they end in something optional.
+fn trim_spans(source: &str, comments: &[Comment], spans: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let trim = |(start, mut end): (usize, usize)| loop {
+        end = start + source[start..end].trim_end().len();
+        match comments
+            .iter()
+            .find(|comment| start < comment.start && comment.start < end && end <= comment.end)
+        {
+            Some(comment) => end = comment.start,
+            None => return (start, end),
+        }
+    };
+
+    spans.iter().copied().map(trim).collect()
+}
+
+/// Whether some Ryan code is already formatted, i.e., whether [`format`] would leave it
+/// untouched.
+pub fn is_formatted(source: &str) -> Result<bool, ParseError> {
+    Ok(format(source)? == source)
+}
+
+/// What the lexer is inside of while looking for comments, besides the top-level code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Frame {
+    /// Code between braces, either of a dictionary or of a template interpolation.
+    Braces,
+    /// A template string.
+    Template,
+}
+
+/// Finds all comments in the source, skipping over strings and templates.
+fn find_comments(source: &str) -> Vec<Comment> {
+    let bytes = source.as_bytes();
+    let mut stack = vec![];
+    let mut comments = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let next = bytes.get(i + 1).copied();
+
+        match (stack.last().copied(), bytes[i]) {
+            (Some(Frame::Template), b'\\') => i += 1,
+            (Some(Frame::Template), b'`') => {
+                stack.pop();
+            }
+            (Some(Frame::Template), b'$') if next == Some(b'{') => {
+                stack.push(Frame::Braces);
+                i += 1;
+            }
+            (Some(Frame::Template), _) => {}
+            (_, b'/') if next == Some(b'/') => {
+                let end = source[i..].find('\n').map_or(source.len(), |len| i + len);
+                comments.push(Comment { start: i, end });
+                i = end;
+            }
+            (_, b'"') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            (_, b'`') => stack.push(Frame::Template),
+            (_, b'{') => stack.push(Frame::Braces),
+            (_, b'}') => {
+                stack.pop();
+            }
+            _ => {}
+        }
+
+        i += 1;
+    }
+
+    comments
+}
@@ -27,6 +27,21 @@
 //! has the interfaces for native extensions. Finally, everything can be put together
 //! in an environment using the [`environment::EnvironmentBuilder`].
 //!
+//! ## Cargo features
+//!
+//! * `parse` (default): the parser for Ryan code. Without it, this crate can only
+//!   evaluate syntax trees built elsewhere, with [`parser::eval`].
+//! * `builtins` (default): the stock built-in functions, like `fmt` and `range`.
+//! * `ast-serde`: implements `Serialize` and `Deserialize` for the syntax tree, so that
+//!   a program can be parsed in one place and run in another, e.g., on an embedded
+//!   target built with `default-features = false`.
+//! * `introspection`: where each name is bound and used in the code, for editors.
+//! * `metrics`: how long parsing and evaluating took, how many modules were imported and
+//!   so on, with [`Environment::metrics`] and [`from_str_with_env_metrics`].
+//! * `std-thread`: evaluating with a deadline, e.g., with
+//!   [`from_str_with_env_and_deadline`], which spawns a thread to keep time.
+//! * `yaml`: writing values as YAML with [`to_yaml_string`].
+//!
 //! ## Ryan key principles
 //!
 //! It might look at first that adding one more thingamajig to your project might be
@@ -35,17 +50,17 @@
 //! **cannot** code a fully functional Pacman game in Ryan:
 //!
 //! 1. **(Configurable) hermeticity**: there is no `print` statement or any other kind
-//! side-effect to the language itself. The import system is the only way data can get
-//! into Ryan and even that can be easily disabled. Even if Ryan is not completely
-//! hermetic out-of-the-box, it can be made so in a couple of extra lines.
+//!    side-effect to the language itself. The import system is the only way data can get
+//!    into Ryan and even that can be easily disabled. Even if Ryan is not completely
+//!    hermetic out-of-the-box, it can be made so in a couple of extra lines.
 //! 2. **Turing incompleteness**: this has to do mainly with loops. There is no `while`
-//! statement and you cannot recurse in Ryan. While you can iterate through data, you
-//! can do so only in pre-approved ways. This is done in such a way that every Ryan
-//! program is guaranteed to finish executing (eventually).
+//!    statement and you cannot recurse in Ryan. While you can iterate through data, you
+//!    can do so only in pre-approved ways. This is done in such a way that every Ryan
+//!    program is guaranteed to finish executing (eventually).
 //! 3. **Immutability**: everything in Ryan is immutable. Once a value is declared, it
-//! stays that way for the remaining of its existence. Of course, you can _shadow_ a
-//! variable by re-declaring it with another value, but that will be a completely new
-//! variable.
+//!    stays that way for the remaining of its existence. Of course, you can _shadow_ a
+//!    variable by re-declaring it with another value, but that will be a completely new
+//!    variable.
 //!
 //! Of course, one can reconfigure the import system to read from any arbitrary source of
 //! information and can also create _native extensions_ to throw all these guarantees out
@@ -122,27 +137,63 @@
 //! (under construction).
 //!
 
+/// Rewrites Ryan values in a stable form.
+mod canonical;
 /// Deserializes a Ryan value into a Rust struct using `serde`'s data model.
 mod de;
+/// Lists the differences between two Ryan values.
+mod diff;
+/// Decodes the content of files and modules into text.
+mod encoding;
 /// The interface between Ryan and the rest of the world. Contains the import system and
 /// the native extension system.
 pub mod environment;
+/// Formats Ryan code in a canonical style.
+#[cfg(feature = "parse")]
+pub mod fmt;
 /// The Ryan language _per se_, with parsing and evaluating functions and the types
 /// building the Abstract Syntax Tree.
 pub mod parser;
 /// The way Ryan allocates strings in memory.
 mod rc_world;
+/// Runs the tests written in Ryan, in `*.test.ryan` files.
+#[cfg(feature = "parse")]
+mod testing;
+/// Escapes and unescapes the text of strings, which follows the JSON rules. This is
+/// how strings are read from Ryan code and written back into it.
+pub mod text;
 /// Utilities for this crate.
 mod utils;
+/// Writes Ryan values as YAML.
+#[cfg(feature = "yaml")]
+mod yaml;
 
+pub use crate::canonical::{CanonicalizeOptions, Canonicalized};
 pub use crate::de::DecodeError;
+pub use crate::diff::{diff, DiffEntry, DiffKind};
+pub use crate::encoding::{decode_text, EncodingError};
 pub use crate::environment::Environment;
+#[cfg(feature = "parse")]
+pub use crate::testing::{discover_tests, run_tests, TestOutcome, TestReport, TestResult};
+#[cfg(feature = "yaml")]
+pub use crate::yaml::to_yaml_string;
 
+#[cfg(feature = "parse")]
 use serde::Deserialize;
-use std::{io::Read, path::Path};
+#[cfg(feature = "parse")]
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+#[cfg(all(feature = "parse", feature = "std-thread"))]
+use std::{sync::mpsc::RecvTimeoutError, time::Duration};
 use thiserror::Error;
 
-use crate::parser::{EvalError, ParseError};
+#[cfg(all(feature = "parse", feature = "metrics"))]
+use crate::environment::EvalMetrics;
+#[cfg(feature = "parse")]
+use crate::parser::Warning;
+use crate::parser::{EvalError, NotRepresentable, ParseError};
 
 /// The errors that may happen while processing Ryan programs.
 #[derive(Debug, Error)]
@@ -150,6 +201,9 @@ pub enum Error {
     /// An IO error happened (e.g., the file does not exist).
     #[error("Io error: {0}")]
     Io(std::io::Error),
+    /// The code is not valid text, e.g., it is not valid UTF-8.
+    #[error("{0}")]
+    Encoding(EncodingError),
     /// A parse error happened.
     #[error("{0}")]
     Parse(ParseError),
@@ -159,10 +213,44 @@ pub enum Error {
     /// An error happened when transforming the final result to JSON.
     #[error("Decode error: {0}")]
     DecodeError(DecodeError),
+    /// The final result contains a value that has no counterpart in JSON, e.g., a pattern
+    /// match that was never applied.
+    #[error("{0}")]
+    NotRepresentable(NotRepresentable),
+}
+
+/// Builds an instance of type `T` from the outcome of a program. If that fails because
+/// the outcome contains something JSON cannot represent, that is reported instead of the
+/// decode error, since it tells where the culprit is.
+#[cfg(feature = "parse")]
+fn decode<T>(value: &parser::Value) -> Result<T, Error>
+where
+    T: for<'a> Deserialize<'a>,
+{
+    value
+        .decode()
+        .map_err(|err| match value.check_representable() {
+            Err(not_representable) => Error::NotRepresentable(not_representable),
+            Ok(()) => Error::DecodeError(err),
+        })
+}
+
+/// Reads the code of a Ryan file, which must be UTF-8 or, with a byte order mark, UTF-16.
+/// See [`decode_text`].
+#[cfg(feature = "parse")]
+fn read_text<R: Read>(path: Option<&str>, mut reader: R) -> Result<String, Error> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes).map_err(Error::Io)?;
+
+    match decode_text(&bytes) {
+        Ok(text) => Ok(text.into_owned()),
+        Err(err) => Err(Error::Encoding(err.in_module(path))),
+    }
 }
 
 /// Loads a Ryan file from disk and executes it, finally building an instance of type `T`
 /// from the execution outcome.
+#[cfg(feature = "parse")]
 pub fn from_path<P: AsRef<Path>, T>(path: P) -> Result<T, Error>
 where
     T: for<'a> Deserialize<'a>,
@@ -176,6 +264,7 @@ where
 /// Loads a Ryan file from disk and executes it, finally building an instance of type `T`
 /// from the execution outcome. This function takes an [`Environment`] as a parameter,
 /// that lets you have fine-grained control over imports and built-in functions.
+#[cfg(feature = "parse")]
 pub fn from_path_with_env<P: AsRef<Path>, T>(env: &Environment, path: P) -> Result<T, Error>
 where
     T: for<'a> Deserialize<'a>,
@@ -191,12 +280,12 @@ where
 /// Loads a Ryan file from a supplied reader and executes it, finally building an instance
 /// of type `T` from the execution outcome. The `current_module` will be set to `None`
 /// while executing in this mode.
-pub fn from_reader<R: Read, T>(mut reader: R) -> Result<T, Error>
+#[cfg(feature = "parse")]
+pub fn from_reader<R: Read, T>(reader: R) -> Result<T, Error>
 where
     T: for<'a> Deserialize<'a>,
 {
-    let mut string = String::new();
-    reader.read_to_string(&mut string).map_err(Error::Io)?;
+    let string = read_text(None, reader)?;
     let decoded = from_str(&string)?;
 
     Ok(decoded)
@@ -205,12 +294,12 @@ where
 /// Loads a Ryan file from a supplied reader and executes it, finally building an instance
 /// of type `T` from the execution outcome. The `current_module` will be set to `name`
 /// while executing in this mode.
-pub fn from_reader_with_filename<R: Read, T>(name: &str, mut reader: R) -> Result<T, Error>
+#[cfg(feature = "parse")]
+pub fn from_reader_with_filename<R: Read, T>(name: &str, reader: R) -> Result<T, Error>
 where
     T: for<'a> Deserialize<'a>,
 {
-    let mut string = String::new();
-    reader.read_to_string(&mut string).map_err(Error::Io)?;
+    let string = read_text(Some(name), reader)?;
     let decoded = from_str_with_filename(name, &string)?;
 
     Ok(decoded)
@@ -220,12 +309,12 @@ where
 /// of type `T`. from the execution outcome. This function takes an [`Environment`] as a
 /// parameter, that lets you have fine-grained control over imports, built-in functions and
 /// the `current_module` name.
-pub fn from_reader_with_env<R: Read, T>(env: &Environment, mut reader: R) -> Result<T, Error>
+#[cfg(feature = "parse")]
+pub fn from_reader_with_env<R: Read, T>(env: &Environment, reader: R) -> Result<T, Error>
 where
     T: for<'a> Deserialize<'a>,
 {
-    let mut string = String::new();
-    reader.read_to_string(&mut string).map_err(Error::Io)?;
+    let string = read_text(env.current_module.as_deref(), reader)?;
     let decoded = from_str_with_env(env, &string)?;
 
     Ok(decoded)
@@ -234,29 +323,83 @@ where
 /// Loads a Ryan file from a supplied string and executes it, finally building an instance
 /// of type `T` from the execution outcome. The `current_module` will be set to `None`
 /// while executing in this mode.
+#[cfg(feature = "parse")]
 pub fn from_str<T>(s: &str) -> Result<T, Error>
 where
     T: for<'a> Deserialize<'a>,
 {
     let env = Environment::new(None);
-    let parsed = parser::parse(&s).map_err(Error::Parse)?;
+    let parsed = parser::parse(s).map_err(Error::Parse)?;
     let value = parser::eval(env, &parsed).map_err(Error::Eval)?;
-    let decoded = value.decode::<T>().map_err(Error::DecodeError)?;
+    let decoded = decode::<T>(&value)?;
 
     Ok(decoded)
 }
 
+/// Loads a Ryan file from a supplied string and executes it, finally building an instance
+/// of type `T` from the execution outcome. Besides the value, this also returns the
+/// non-fatal issues found in the code, such as unused variables. The `current_module`
+/// will be set to `None` while executing in this mode.
+#[cfg(feature = "parse")]
+pub fn from_str_with_warnings<T>(s: &str) -> Result<(T, Vec<Warning>), Error>
+where
+    T: for<'a> Deserialize<'a>,
+{
+    let env = Environment::new(None);
+    let (parsed, warnings) = parser::parse_with_warnings(s).map_err(Error::Parse)?;
+    let value = parser::eval(env, &parsed).map_err(Error::Eval)?;
+    let decoded = decode::<T>(&value)?;
+
+    Ok((decoded, warnings))
+}
+
+/// Finds the non-fatal issues in a Ryan program without running it, such as variables
+/// and imports that are never used in their scope, variables defined twice in the same
+/// block and dictionaries defining the same key twice. Each warning tells where the issue
+/// is in the source.
+///
+/// ```
+/// use ryan::parser::WarningKind;
+///
+/// let warnings = ryan::lint(r#"
+///     let in_branch = 1;
+///     let in_guard = 2;
+///     let captured = 3;
+///     let add x = x + captured;
+///     let config = import "config.ryan";
+///     let x = 4;
+///     let x = 5;
+///     [if true then in_branch else 0, [y for y in [1] if y != in_guard], add 1, x]
+/// "#).unwrap();
+///
+/// let unused = warnings
+///     .iter()
+///     .filter(|w| matches!(w.kind, WarningKind::UnusedBinding | WarningKind::UnusedImport))
+///     .map(|w| w.message.as_str())
+///     .collect::<Vec<_>>();
+/// assert_eq!(
+///     unused,
+///     ["Imported variable `config` is never used", "Variable `x` is never used"],
+/// );
+/// ```
+#[cfg(feature = "parse")]
+pub fn lint(s: &str) -> Result<Vec<Warning>, Error> {
+    let (_, warnings) = parser::parse_with_warnings(s).map_err(Error::Parse)?;
+    Ok(warnings)
+}
+
 /// Loads a Ryan file from a supplied string and executes it, finally building an instance
 /// of type `T` from the execution outcome. The `current_module` will be set to `name`
 /// while executing in this mode.
+#[cfg(feature = "parse")]
 pub fn from_str_with_filename<T>(name: &str, s: &str) -> Result<T, Error>
 where
     T: for<'a> Deserialize<'a>,
 {
     let env = Environment::new(Some(name));
-    let parsed = parser::parse(&s).map_err(Error::Parse)?;
+    let parsed = parser::parse(s).map_err(Error::Parse)?;
     let value = parser::eval(env, &parsed).map_err(Error::Eval)?;
-    let decoded = value.decode().map_err(Error::DecodeError)?;
+    let decoded = decode(&value)?;
 
     Ok(decoded)
 }
@@ -265,13 +408,323 @@ where
 /// of type `T`. from the execution outcome. This function takes an [`Environment`] as a
 /// parameter, that lets you have fine-grained control over imports, built-in functions and
 /// the `current_module` name.
+#[cfg(feature = "parse")]
 pub fn from_str_with_env<T>(env: &Environment, s: &str) -> Result<T, Error>
 where
     T: for<'a> Deserialize<'a>,
 {
-    let parsed = parser::parse(&s).map_err(Error::Parse)?;
+    let parsed = parser::parse(s).map_err(Error::Parse)?;
     let value = parser::eval(env.clone(), &parsed).map_err(Error::Eval)?;
-    let decoded = value.decode().map_err(Error::DecodeError)?;
+    let decoded = decode(&value)?;
 
     Ok(decoded)
 }
+
+/// Loads a Ryan file from a supplied string and executes it in a hermetic environment,
+/// finally building an instance of type `T` from the execution outcome. Nothing can be
+/// imported and effectful built-ins are not available, so the outcome depends only on
+/// the string. This is meant for settings that should not depend on the machine they
+/// are read in, like the defaults of a tool:
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// let defaults: BTreeMap<String, bool> =
+///     ryan::from_str_hermetic("{ no_color: true, hermetic: 1 > 0 }").unwrap();
+/// assert_eq!(defaults["hermetic"], true);
+/// assert!(ryan::from_str_hermetic::<String>(r#"import "env:HOME""#).is_err());
+/// ```
+#[cfg(feature = "parse")]
+pub fn from_str_hermetic<T>(s: &str) -> Result<T, Error>
+where
+    T: for<'a> Deserialize<'a>,
+{
+    from_str_with_env(&Environment::builder().hermetic().build(), s)
+}
+
+/// Like [`from_path_with_env`], but fails with [`parser::EvalErrorKind::Cancelled`] if
+/// evaluating takes longer than `deadline`. See [`from_str_with_env_and_deadline`].
+#[cfg(all(feature = "parse", feature = "std-thread"))]
+pub fn from_path_with_env_and_deadline<P: AsRef<Path>, T>(
+    env: &Environment,
+    path: P,
+    deadline: Duration,
+) -> Result<T, Error>
+where
+    T: for<'a> Deserialize<'a>,
+{
+    with_deadline(env, deadline, |env| from_path_with_env(env, path))
+}
+
+/// Like [`from_reader_with_env`], but fails with [`parser::EvalErrorKind::Cancelled`] if
+/// evaluating takes longer than `deadline`. See [`from_str_with_env_and_deadline`].
+#[cfg(all(feature = "parse", feature = "std-thread"))]
+pub fn from_reader_with_env_and_deadline<R: Read, T>(
+    env: &Environment,
+    reader: R,
+    deadline: Duration,
+) -> Result<T, Error>
+where
+    T: for<'a> Deserialize<'a>,
+{
+    with_deadline(env, deadline, |env| from_reader_with_env(env, reader))
+}
+
+/// Like [`from_str_with_env`], but fails with [`parser::EvalErrorKind::Cancelled`] if
+/// evaluating takes longer than `deadline`. A watchdog thread cancels the evaluation when
+/// the deadline is due, through a [`environment::CancellationToken`] that replaces the
+/// one the environment may have.
+///
+/// ```
+/// use std::time::Duration;
+/// use ryan::{parser::EvalErrorKind, Environment};
+///
+/// let env = Environment::new(None);
+/// let code = "[[x + y for y in range [0, 100000]] for x in range [0, 100000]]";
+///
+/// let Err(ryan::Error::Eval(err)) = ryan::from_str_with_env_and_deadline::<Vec<Vec<i64>>>(
+///     &env,
+///     code,
+///     Duration::from_millis(50),
+/// ) else {
+///     panic!("ten billion elements take longer than that");
+/// };
+/// assert!(matches!(err.kind(), EvalErrorKind::Cancelled));
+/// ```
+#[cfg(all(feature = "parse", feature = "std-thread"))]
+pub fn from_str_with_env_and_deadline<T>(
+    env: &Environment,
+    s: &str,
+    deadline: Duration,
+) -> Result<T, Error>
+where
+    T: for<'a> Deserialize<'a>,
+{
+    with_deadline(env, deadline, |env| from_str_with_env(env, s))
+}
+
+/// Runs `run` with a clone of `env` that is cancelled by a watchdog thread once `deadline`
+/// is due. The watchdog stops as soon as `run` returns.
+#[cfg(all(feature = "parse", feature = "std-thread"))]
+fn with_deadline<T>(
+    env: &Environment,
+    deadline: Duration,
+    run: impl FnOnce(&Environment) -> T,
+) -> T {
+    let token = environment::CancellationToken::new();
+    let (finished, watched) = std::sync::mpsc::channel::<()>();
+    let watchdog = {
+        let token = token.clone();
+        std::thread::spawn(move || {
+            // Dropping the sender disconnects the channel, which ends the wait early:
+            if let Err(RecvTimeoutError::Timeout) = watched.recv_timeout(deadline) {
+                token.cancel();
+            }
+        })
+    };
+
+    let result = run(&env.with_cancel_token(token));
+    drop(finished);
+    // The watchdog only waits and cancels, so it cannot panic:
+    let _ = watchdog.join();
+
+    result
+}
+
+/// Splits a stream of Ryan documents separated by lines containing only `---`, like in
+/// YAML. Blank documents, e.g., the one before a leading `---`, are skipped.
+///
+/// ```
+/// let stream = "---\n1\n---\nlet x = 2;\n{ x }\n";
+/// assert_eq!(ryan::documents(stream).collect::<Vec<_>>(), ["1\n", "let x = 2;\n{ x }\n"]);
+/// ```
+#[cfg(feature = "parse")]
+pub fn documents(s: &str) -> impl Iterator<Item = &str> {
+    let mut documents = vec![];
+    let mut start = 0;
+    let mut offset = 0;
+
+    for line in s.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            documents.push(&s[start..offset]);
+            start = offset + line.len();
+        }
+        offset += line.len();
+    }
+    documents.push(&s[start..]);
+
+    documents
+        .into_iter()
+        .filter(|document| !document.trim().is_empty())
+}
+
+/// Loads a stream of Ryan documents, as split by [`documents`], and executes each of them
+/// in order, finally building an instance of type `T` from each outcome. All documents
+/// run in the same [`Environment`] and therefore share its cache of imported modules: a
+/// module imported by many documents is only loaded once. The first error stops the
+/// evaluation.
+///
+/// ```
+/// use ryan::environment::{Environment, ImportLoader, NoImport};
+///
+/// let loader = NoImport.r#override("base.ryan".to_owned(), "{ port: 80 }".to_owned());
+/// let env = Environment::builder().import_loader(loader).build();
+/// let stream = r#"
+/// (import "base.ryan").port
+/// ---
+/// (import "base.ryan").port + 1
+/// "#;
+///
+/// assert_eq!(ryan::from_documents_with_env::<u16>(&env, stream).unwrap(), [80, 81]);
+/// ```
+#[cfg(feature = "parse")]
+pub fn from_documents_with_env<T>(env: &Environment, s: &str) -> Result<Vec<T>, Error>
+where
+    T: for<'a> Deserialize<'a>,
+{
+    documents(s)
+        .map(|document| from_str_with_env(env, document))
+        .collect()
+}
+
+/// Loads a Ryan file from a supplied string and executes it, just like
+/// [`from_str_with_env`], also returning the metrics of this evaluation: how long parsing
+/// and evaluating took, how many modules were imported and so on. The metrics are
+/// returned even if the evaluation fails.
+///
+/// ```
+/// use ryan::environment::{Environment, ImportLoader, NoImport};
+///
+/// let loader = NoImport
+///     .r#override("a.ryan".to_owned(), "1".to_owned())
+///     .r#override("b.ryan".to_owned(), "2".to_owned());
+/// let env = Environment::builder().import_loader(loader).build();
+/// let code = r#"import "a.ryan" + import "b.ryan""#;
+///
+/// let (value, metrics) = ryan::from_str_with_env_metrics::<i64>(&env, code);
+/// assert_eq!(value.unwrap(), 3);
+/// assert_eq!((metrics.imports, metrics.cache_hits), (2, 0));
+///
+/// // Only this evaluation counts, even if the environment is reused:
+/// let (_, metrics) = ryan::from_str_with_env_metrics::<i64>(&env, code);
+/// assert_eq!((metrics.imports, metrics.cache_hits), (2, 2));
+/// ```
+#[cfg(all(feature = "parse", feature = "metrics"))]
+pub fn from_str_with_env_metrics<T>(env: &Environment, s: &str) -> (Result<T, Error>, EvalMetrics)
+where
+    T: for<'a> Deserialize<'a>,
+{
+    let before = env.metrics();
+    let decoded = env
+        .counters()
+        .time_parse(|| parser::parse(s))
+        .map_err(Error::Parse)
+        .and_then(|parsed| {
+            let value = parser::eval(env.clone(), &parsed).map_err(Error::Eval)?;
+            decode(&value)
+        });
+
+    (decoded, env.metrics().since(&before))
+}
+
+/// Loads a Ryan file from a supplied string and executes it, returning the resulting value
+/// along with where each part of it came from: which module and binding produced each
+/// list, dictionary and entry. Provenance is tracked even if the supplied [`Environment`]
+/// was not built with [`environment::EnvironmentBuilder::track_provenance`].
+///
+/// ```
+/// use ryan::{environment::Environment, parser::PathSeg};
+///
+/// let env = Environment::new(Some("main.ryan"));
+/// let (value, provenance) =
+///     ryan::eval_with_provenance(&env, "let db = { port: 5432 }; { db }").unwrap();
+///
+/// assert_eq!(value.to_string(), "{db: {port: 5432}}");
+/// let origin = provenance.get(&[PathSeg::Key("db".into()), PathSeg::Key("port".into())]);
+/// assert_eq!(origin.unwrap().to_string(), "main.ryan, in binding `db`");
+/// ```
+#[cfg(feature = "parse")]
+pub fn eval_with_provenance(
+    env: &Environment,
+    s: &str,
+) -> Result<(parser::Value, environment::Provenance), Error> {
+    let env = env.tracking_provenance();
+    let parsed = parser::parse(s).map_err(Error::Parse)?;
+    let value = parser::eval(env.clone(), &parsed).map_err(Error::Eval)?;
+    let provenance = env.provenance(&value);
+
+    Ok((value, provenance))
+}
+
+/// The modules read while evaluating a Ryan file, as returned by [`trace_imports`].
+#[cfg(feature = "parse")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportTrace {
+    /// The files read, starting with the evaluated file itself, in the order they were
+    /// first imported.
+    pub files: Vec<PathBuf>,
+    /// The environment variables read through `env:` imports, without the prefix.
+    pub env_vars: Vec<String>,
+}
+
+/// Loads a Ryan file from disk and executes it, returning every file and environment
+/// variable read in the process, directly or transitively. This is useful for
+/// integrating Ryan with build systems, which need to know the exact inputs of a
+/// configuration.
+#[cfg(feature = "parse")]
+pub fn trace_imports<P: AsRef<Path>>(path: P) -> Result<ImportTrace, Error> {
+    let env = Environment::new(Some(&path.as_ref().display().to_string()));
+    let file = std::fs::File::open(path.as_ref()).map_err(Error::Io)?;
+    let text = read_text(Some(&path.as_ref().display().to_string()), file)?;
+    let parsed = parser::parse(&text).map_err(Error::Parse)?;
+    parser::eval(env.clone(), &parsed).map_err(Error::Eval)?;
+
+    let mut trace = ImportTrace {
+        files: vec![path.as_ref().to_owned()],
+        env_vars: vec![],
+    };
+
+    for edge in env.import_graph() {
+        if let Some(var) = edge.imported.strip_prefix("env:") {
+            if !trace.env_vars.iter().any(|v| v == var) {
+                trace.env_vars.push(var.to_owned());
+            }
+        } else {
+            let file = PathBuf::from(&*edge.imported);
+            if !trace.files.contains(&file) {
+                trace.files.push(file);
+            }
+        }
+    }
+
+    Ok(trace)
+}
+
+/// Finds out what identifier is under the cursor, at a given byte offset of some Ryan
+/// code, and where it was bound. Returns `None` if the code doesn't parse or if there is
+/// nothing to resolve under the cursor. Imports are not loaded: if the cursor is on the
+/// path of an import, only the path is returned.
+///
+/// ```
+/// use ryan::parser::{BindingKind, Definition};
+///
+/// let source = "let x = 1;\nlet y = x + 1;\nfmt y";
+///
+/// let resolved = ryan::resolve_at(source, 19).unwrap();
+/// assert_eq!(&*resolved.name, "x");
+/// match resolved.definition {
+///     Definition::Binding(info) => {
+///         assert_eq!(info.span, (4, 5));
+///         assert_eq!(info.kind, BindingKind::Variable);
+///     }
+///     _ => panic!("`x` is bound in the code"),
+/// }
+///
+/// let resolved = ryan::resolve_at(source, 27).unwrap();
+/// assert_eq!(&*resolved.name, "fmt");
+/// assert_eq!(resolved.definition, Definition::Builtin);
+/// ```
+#[cfg(feature = "introspection")]
+pub fn resolve_at(source: &str, offset: usize) -> Option<parser::ResolvedSymbol> {
+    parser::SourceMap::parse(source).ok()?.resolve_at(offset)
+}
@@ -1,22 +1,30 @@
 use indexmap::IndexMap;
+#[cfg(feature = "parse")]
 use pest::iterators::Pairs;
 use std::fmt::Display;
 use std::rc::Rc;
 
+use crate::environment::{events, EvalEvent};
 use crate::rc_world;
 
 use super::block::Block;
-use super::pattern::Pattern;
+use super::expression::Expression;
+use super::format::Printer;
+use super::literal::Literal;
+use super::pattern::{BindError, Pattern};
 use super::types::TypeExpression;
 use super::value::PatternMatch;
+#[cfg(feature = "parse")]
 use super::ErrorLogger;
+#[cfg(feature = "parse")]
 use super::Rule;
 use super::State;
-use super::{Context, Value};
+use super::{Context, EvalErrorKind, Value};
 
-/// A binding is a `let ... = ...;` or a `type ... = ...;` statement that creates new
-/// variables, types and patterns.
+/// A binding is a `let ... = ...;`, a `type ... = ...;` or a `use "...";` statement that
+/// creates new variables, types and patterns.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Binding {
     /// Defines a new pattern or a new rule for an existing pattern.
     PatternMatchDefinition {
@@ -24,6 +32,10 @@ pub enum Binding {
         identifier: Rc<str>,
         /// The pattern against which to match the input.
         pattern: Pattern,
+        /// An optional condition on the variables bound by the pattern. If it evaluates
+        /// to `false`, the next rule of the pattern is tried, as if the pattern did not
+        /// match.
+        guard: Option<Expression>,
         /// The code to be executed if the pattern is satisfied.
         block: Block,
     },
@@ -35,6 +47,9 @@ pub enum Binding {
         /// The block to be executed to produce the value against which the pattern will
         /// be matched.
         block: Block,
+        /// An optional expression, after `else`, whose value is matched against the
+        /// pattern instead if the value of the block does not match.
+        fallback: Option<Expression>,
     },
     /// A type definition. This binds an identifier to a type value.
     TypeDefinition {
@@ -43,46 +58,23 @@ pub enum Binding {
         /// The expression defining this type.
         type_expression: TypeExpression,
     },
+    /// Brings the bindings of another Ryan module into scope, as if they were written in
+    /// place of this statement. The final expression of the module is ignored. Unlike
+    /// imports, this is a way of sharing code, not values.
+    Use {
+        /// The path of the module, as written.
+        path: Rc<str>,
+    },
 }
 
 impl Display for Binding {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::PatternMatchDefinition {
-                identifier,
-                pattern,
-                block,
-            } => {
-                if block.bindings.is_empty() {
-                    write!(f, "let {identifier} {pattern} = {block}")?;
-                } else {
-                    // Indent:
-                    let blockstr = block.to_string().replace('\n', "\n    ");
-                    write!(f, "let {identifier} {pattern} =\n    {blockstr};")?;
-                }
-            }
-            Self::Destructuring { pattern, block } => {
-                if block.bindings.is_empty() {
-                    write!(f, "let {pattern} = {block}")?;
-                } else {
-                    // Indent:
-                    let blockstr = block.to_string().replace('\n', "\n    ");
-                    write!(f, "let {pattern} =\n    {blockstr};")?;
-                }
-            }
-            Self::TypeDefinition {
-                identifier,
-                type_expression,
-            } => {
-                write!(f, "type {identifier} = {type_expression};")?;
-            }
-        }
-
-        Ok(())
+        f.write_str(&Printer::FLAT.binding(self, 0))
     }
 }
 
 impl Binding {
+    #[cfg(feature = "parse")]
     pub(super) fn parse(logger: &mut ErrorLogger, mut pairs: Pairs<'_, Rule>) -> Self {
         let pair = pairs
             .next()
@@ -92,12 +84,20 @@ impl Binding {
             Rule::patternMatchBinding => {
                 let mut identifier = None;
                 let mut pattern = None;
+                let mut guard = None;
                 let mut block = None;
 
                 for pair in pair.into_inner() {
                     match pair.as_rule() {
                         Rule::identifier => identifier = Some(rc_world::str_to_rc(pair.as_str())),
                         Rule::pattern => pattern = Some(Pattern::parse(logger, pair.into_inner())),
+                        Rule::ifGuard => {
+                            let predicate = pair
+                                .into_inner()
+                                .next()
+                                .expect("there is always a predicate in an if guard");
+                            guard = Some(Expression::parse(logger, predicate.into_inner()));
+                        }
                         Rule::block => block = Some(Block::parse(logger, pair.into_inner())),
                         _ => unreachable!(),
                     }
@@ -107,17 +107,26 @@ impl Binding {
                     identifier: identifier
                         .expect("tere is always an identifier in a pattern match definition"),
                     pattern: pattern.expect("there is always a pattern in a pattern definition"),
+                    guard,
                     block: block.expect("there is always an expression in a pattern definition"),
                 }
             }
             Rule::destructuringBiding => {
                 let mut pattern = None;
                 let mut block = None;
+                let mut fallback = None;
 
                 for pair in pair.into_inner() {
                     match pair.as_rule() {
                         Rule::pattern => pattern = Some(Pattern::parse(logger, pair.into_inner())),
                         Rule::block => block = Some(Block::parse(logger, pair.into_inner())),
+                        Rule::destructuringFallback => {
+                            let expression = pair
+                                .into_inner()
+                                .next()
+                                .expect("there is always an expression in a fallback");
+                            fallback = Some(Expression::parse(logger, expression.into_inner()));
+                        }
                         _ => unreachable!(),
                     }
                 }
@@ -125,6 +134,7 @@ impl Binding {
                 Binding::Destructuring {
                     pattern: pattern.expect("there is always a pattern in a destructuring binding"),
                     block: block.expect("there is always an expression in a destructuring binding"),
+                    fallback,
                 }
             }
             Rule::typeDefinition => {
@@ -148,6 +158,17 @@ impl Binding {
                         .expect("there is always an expression in a type definition"),
                 }
             }
+            Rule::useStatement => {
+                let text = pair
+                    .into_inner()
+                    .next()
+                    .expect("there is always a path in a use statement");
+                let path = logger.absorb(&text, crate::text::unescape(text.as_str()));
+
+                Binding::Use {
+                    path: rc_world::string_to_rc(path),
+                }
+            }
             _ => unreachable!(),
         }
     }
@@ -163,17 +184,28 @@ impl Binding {
             Self::PatternMatchDefinition {
                 identifier,
                 pattern,
+                guard,
                 block,
             } => {
                 pattern.capture(state, provided, values)?;
                 pattern.provided(provided);
                 provided.push(identifier.clone());
+                if let Some(guard) = guard {
+                    guard.capture(state, provided, values)?;
+                }
                 block.capture(state, provided, values)?;
             }
-            Self::Destructuring { pattern, block } => {
+            Self::Destructuring {
+                pattern,
+                block,
+                fallback,
+            } => {
                 pattern.capture(state, provided, values)?;
                 pattern.provided(provided);
                 block.capture(state, provided, values)?;
+                if let Some(fallback) = fallback {
+                    fallback.capture(state, provided, values)?;
+                }
             }
             Self::TypeDefinition {
                 identifier,
@@ -182,16 +214,45 @@ impl Binding {
                 provided.push(identifier.clone());
                 type_expression.capture(state, provided, values)?;
             }
+            Self::Use { path } => {
+                // The variables of the module are only known once it is read:
+                let environment = state.environment.clone();
+                let used = environment.with_used_module(path, |block, _| {
+                    for binding in &block.bindings {
+                        binding.provided(provided);
+                    }
+                });
+                if let Err(source) = used {
+                    state.raise(EvalErrorKind::ImportFailed {
+                        path: path.clone(),
+                        source,
+                    })?;
+                }
+            }
         }
 
         Some(())
     }
 
+    /// The variables defined by this binding. Those of a `use` statement are not known
+    /// before the module is read, so they are left out.
+    pub(super) fn provided(&self, provided: &mut Vec<Rc<str>>) {
+        match self {
+            Self::PatternMatchDefinition { identifier, .. }
+            | Self::TypeDefinition { identifier, .. } => provided.push(identifier.clone()),
+            Self::Destructuring { pattern, .. } => pattern.provided(provided),
+            Self::Use { .. } => {}
+        }
+    }
+
     pub(super) fn eval(&self, state: &mut State<'_>) -> Option<()> {
+        state.check_cancelled()?;
+
         match self {
             Self::PatternMatchDefinition {
                 identifier,
                 pattern,
+                guard,
                 block,
             } => {
                 state.push_ctx(Context::EvaluatingBinding(identifier.clone()));
@@ -200,16 +261,26 @@ impl Binding {
                 pattern.provided(&mut provided);
 
                 let mut captured = IndexMap::default();
+                state.defining = Some(identifier.clone());
+                pattern.capture(state, &provided, &mut captured)?;
+                if let Some(guard) = guard {
+                    guard.capture(state, &mut provided, &mut captured)?;
+                }
                 block.capture(state, &mut provided, &mut captured)?;
+                state.defining = None;
+                state.raise_undefined(Some(identifier))?;
+                warn_if_shadowing(state, identifier);
 
                 if let Some(Value::PatternMatches(_, mut matches)) =
                     state.bindings.remove(identifier)
                 {
                     // Insert new alternative:
                     matches.push(Rc::new(PatternMatch {
-                        captures: captured,
+                        captures: Rc::new(captured),
                         pattern: pattern.clone(),
+                        guard: guard.clone(),
                         block: block.clone(),
+                        module: state.environment.current_module.clone(),
                     }));
                     // Reinsert value into the bindings;
                     state.bindings.insert(
@@ -222,9 +293,11 @@ impl Binding {
                         Value::PatternMatches(
                             identifier.clone(),
                             vec![Rc::new(PatternMatch {
-                                captures: captured,
+                                captures: Rc::new(captured),
                                 pattern: pattern.clone(),
+                                guard: guard.clone(),
                                 block: block.clone(),
+                                module: state.environment.current_module.clone(),
                             })],
                         ),
                     );
@@ -232,7 +305,11 @@ impl Binding {
 
                 state.pop_ctx();
             }
-            Self::Destructuring { pattern, block } => {
+            Self::Destructuring {
+                pattern,
+                block,
+                fallback,
+            } => {
                 state.push_ctx(Context::EvaluatingBinding(rc_world::string_to_rc(
                     pattern.to_string(),
                 )));
@@ -241,8 +318,31 @@ impl Binding {
                 let mut new_bindings = IndexMap::default();
 
                 if let Err(err) = pattern.bind(&evaluated, &mut new_bindings, state)? {
-                    state.raise(format!("{err}"))?;
-                    return None;
+                    let Some(fallback) = fallback else {
+                        let expression = block.bindings.is_empty().then_some(&block.expression);
+                        raise_bind_error(state, err, expression)?;
+                        return None;
+                    };
+
+                    // Whatever was bound before the pattern failed is discarded:
+                    let evaluated = fallback.eval(state)?;
+                    new_bindings = IndexMap::default();
+
+                    if let Err(err) = pattern.bind(&evaluated, &mut new_bindings, state)? {
+                        raise_bind_error(state, err, Some(fallback))?;
+                        return None;
+                    }
+                }
+
+                for (name, value) in &new_bindings {
+                    state.environment.trace(|| EvalEvent::BindingEvaluated {
+                        name: name.clone(),
+                        value_summary: events::summarize(value),
+                    });
+                }
+
+                if let Pattern::Identifier(name, _) = pattern {
+                    warn_if_shadowing(state, name);
                 }
 
                 state.bindings.extend(new_bindings);
@@ -261,8 +361,86 @@ impl Binding {
 
                 state.pop_ctx();
             }
+            Self::Use { path } => {
+                state.push_ctx(Context::LoadingImport {
+                    path: path.clone(),
+                    chain: state.environment.import_chain(),
+                });
+
+                let environment = state.environment.clone();
+                let used = environment.with_used_module(path, |block, environment| {
+                    if block.has_expression
+                        && block.expression != Expression::Literal(Literal::Null)
+                    {
+                        environment.emit(EvalEvent::Warning {
+                            message: rc_world::string_to_rc(format!(
+                                "The final expression of {path:?} is ignored by `use`"
+                            )),
+                        });
+                    }
+
+                    // The module does not see the variables of this one:
+                    let mut module = state.new_module(environment);
+                    for binding in &block.bindings {
+                        binding.eval(&mut module)?;
+                    }
+
+                    Some(module.bindings)
+                });
+
+                match used {
+                    Ok(bindings) => state.bindings.extend(bindings?),
+                    Err(source) => {
+                        state.raise(EvalErrorKind::ImportFailed {
+                            path: path.clone(),
+                            source,
+                        })?;
+                    }
+                }
+                state.pop_ctx();
+            }
         }
 
         Some(())
     }
 }
+
+/// Raises an error binding the value of `expression` to a pattern. If the value comes
+/// straight from an import, e.g., `let { a } = import "lib.ryan";`, the error is about
+/// the imported module, so the import is added to the context.
+fn raise_bind_error(
+    state: &mut State<'_>,
+    err: BindError,
+    expression: Option<&Expression>,
+) -> Option<()> {
+    if let Some(Expression::Import(import)) = expression {
+        state.push_ctx(Context::LoadingImport {
+            path: import.path.clone(),
+            chain: state.environment.import_chain(),
+        });
+    }
+
+    state.raise(err)
+}
+
+/// Warns that a new binding shadows a built-in, unless the program already shadows it.
+/// Built-ins in a namespace, like `str.trim`, cannot be shadowed by a binding called
+/// `trim`, so the warning points to them.
+fn warn_if_shadowing(state: &State<'_>, name: &Rc<str>) {
+    if state.binds(name) {
+        return;
+    }
+
+    let message = match state.environment.builtin(name) {
+        Some(Value::NativePatternMatch(pat)) if pat.identifier != *name => format!(
+            "Binding `{name}` shadows the built-in `{name}`, which is still available as `{}`",
+            pat.identifier
+        ),
+        Some(_) => format!("Binding `{name}` shadows the built-in `{name}`"),
+        None => return,
+    };
+
+    state.environment.emit(EvalEvent::Warning {
+        message: rc_world::string_to_rc(message),
+    });
+}
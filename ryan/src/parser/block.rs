@@ -1,34 +1,40 @@
 use indexmap::IndexMap;
+#[cfg(feature = "parse")]
 use pest::iterators::Pairs;
+#[cfg(feature = "parse")]
+use std::collections::HashMap;
 use std::fmt::Display;
 use std::rc::Rc;
 
 use super::binding::Binding;
 use super::expression::Expression;
+use super::format::Printer;
 use super::literal::Literal;
 use super::value::Value;
+#[cfg(feature = "parse")]
 use super::ErrorLogger;
+#[cfg(feature = "parse")]
 use super::Rule;
 use super::State;
+#[cfg(feature = "parse")]
+use super::WarningKind;
 
 /// A block of Ryan code. This consists of a list of statements and a return expression at the end.
 #[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Block {
     /// The list of bindings to be applied and evaluated before running the final expression.
     pub bindings: Vec<Binding>,
     /// The expression that will build the final outcome of this block.
     pub expression: Expression,
+    /// Whether the final expression was written in the code. If not, the block returns
+    /// `null`.
+    pub has_expression: bool,
 }
 
 impl Display for Block {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for binding in &self.bindings {
-            writeln!(f, "{binding}")?;
-        }
-
-        write!(f, "{}", self.expression)?;
-
-        Ok(())
+        f.write_str(&Printer::FLAT.block(self, 0))
     }
 }
 
@@ -39,15 +45,40 @@ impl Block {
         Block {
             bindings: vec![],
             expression: Expression::Literal(Literal::Null),
+            has_expression: false,
         }
     }
+    #[cfg(feature = "parse")]
     pub(super) fn parse(logger: &mut ErrorLogger, pairs: Pairs<'_, Rule>) -> Self {
         let mut bindings = vec![];
         let mut expression = None;
+        // Whether each variable defined so far was defined by a pattern match definition.
+        let mut defined = HashMap::new();
 
         for pair in pairs {
             match pair.as_rule() {
-                Rule::binding => bindings.push(Binding::parse(logger, pair.into_inner())),
+                Rule::binding => {
+                    let binding = Binding::parse(logger, pair.clone().into_inner());
+                    let is_pattern_match =
+                        matches!(binding, Binding::PatternMatchDefinition { .. });
+                    let mut provided = vec![];
+                    binding.provided(&mut provided);
+
+                    for id in provided {
+                        // Many rules for the same pattern match are fine:
+                        match defined.insert(id.clone(), is_pattern_match) {
+                            Some(true) if is_pattern_match => {}
+                            Some(_) => logger.warn(
+                                &pair,
+                                WarningKind::ShadowedBinding,
+                                format!("Variable `{id}` is already defined in this block"),
+                            ),
+                            None => {}
+                        }
+                    }
+
+                    bindings.push(binding);
+                }
                 Rule::expression => expression = Some(Expression::parse(logger, pair.into_inner())),
                 _ => unreachable!(),
             }
@@ -55,6 +86,7 @@ impl Block {
 
         Block {
             bindings,
+            has_expression: expression.is_some(),
             expression: expression.unwrap_or(Expression::Literal(Literal::Null)),
         }
     }
@@ -2,14 +2,24 @@ use std::fmt::Display;
 use std::rc::Rc;
 
 use indexmap::IndexMap;
-use pest::iterators::Pairs;
+#[cfg(feature = "parse")]
+use pest::iterators::{Pair, Pairs};
 
-use super::{expression::Expression, ErrorLogger};
-use super::{Pattern, Rule, State, Value};
+use crate::environment::Origin;
+
+use super::expression::{
+    iterated_origin, record_provenance, text_key, DictBuilder, Expression, KeyOrigin,
+};
+use super::format::Printer;
+use super::pattern::BindError;
+#[cfg(feature = "parse")]
+use super::{ErrorLogger, Rule};
+use super::{EvalErrorKind, PathSeg, Pattern, State, Value};
 
 /// A Python-style list comprehension. This is the nearest thing to `for` statement that
 /// you will get in Ryan.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ListComprehension {
     /// The expression building each item of the final list.
     pub expression: Expression,
@@ -23,23 +33,12 @@ pub struct ListComprehension {
 
 impl Display for ListComprehension {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "[{} for {} in {}",
-            self.expression, self.for_clauses[0].pattern, self.for_clauses[0].expression
-        )?;
-
-        if let Some(guard) = self.if_guard.as_ref() {
-            write!(f, " if {}", guard.predicate)?;
-        }
-
-        write!(f, "]")?;
-
-        Ok(())
+        f.write_str(&Printer::FLAT.list_comprehension(self, 0, 0))
     }
 }
 
 impl ListComprehension {
+    #[cfg(feature = "parse")]
     pub(super) fn parse(logger: &mut ErrorLogger, pairs: Pairs<'_, Rule>) -> Self {
         let mut expression = None;
         let mut for_clauses = vec![];
@@ -48,7 +47,7 @@ impl ListComprehension {
         for pair in pairs {
             match pair.as_rule() {
                 Rule::expression => expression = Some(Expression::parse(logger, pair.into_inner())),
-                Rule::forClause => for_clauses.push(ForClause::parse(logger, pair.into_inner())),
+                Rule::forClause => for_clauses.push(ForClause::parse(logger, pair)),
                 Rule::ifGuard => if_guard = Some(IfGuard::parse(logger, pair.into_inner())),
                 _ => unreachable!(),
             }
@@ -75,33 +74,38 @@ impl ListComprehension {
         }
 
         if let Some(guard) = &self.if_guard {
-            guard.capture(state, &mut *provided, values)?;
+            guard.capture(state, &mut provided, values)?;
         }
 
-        self.expression.capture(state, &mut *provided, values)?;
+        self.expression.capture(state, &mut provided, values)?;
 
         Some(())
     }
 
     pub(super) fn eval(&self, state: &mut State<'_>) -> Option<Value> {
         let mut bag = vec![];
-        self.run_iter(state, &mut bag, &self.for_clauses)?;
+        let mut provenance = vec![];
+        self.run_iter(state, &mut bag, &mut provenance, &self.for_clauses)?;
 
-        Some(Value::List(bag.into()))
+        let built = Value::List(bag.into());
+        record_provenance(state, &built, provenance);
+
+        Some(built)
     }
 
     fn run_iter(
         &self,
         state: &mut State<'_>,
         bag: &mut Vec<Value>,
+        provenance: &mut Vec<(PathSeg, Origin)>,
         for_clauses: &[ForClause],
     ) -> Option<()> {
         let for_clause = &for_clauses[0];
-        let iterable = for_clause.expression.eval(state)?;
+        let iterable = for_clause.iterable(state)?;
         let iter = match iterable.iter() {
             Ok(iter) => iter,
             Err(err) => {
-                state.raise(err)?;
+                state.raise(EvalErrorKind::NotIterable(err))?;
                 return None;
             }
         };
@@ -109,26 +113,34 @@ impl ListComprehension {
         if for_clauses.len() > 1 {
             // Recurse
             for item in iter {
+                state.check_cancelled()?;
                 let new_bindings = for_clause.bindings(state, &item)?;
                 let mut new_state = state.new_local(new_bindings);
 
-                self.run_iter(&mut new_state, bag, &for_clauses[1..])?;
+                self.run_iter(&mut new_state, bag, provenance, &for_clauses[1..])?;
             }
         } else {
             // Loop
-            for item in iter {
+            for (idx, item) in iter.enumerate() {
+                state.check_cancelled()?;
                 let new_bindings = for_clause.bindings(state, &item)?;
                 let mut new_state = state.new_local(new_bindings);
+                let mut push = |value| {
+                    if let Some(origin) = iterated_origin(state, &iterable, idx) {
+                        provenance.push((PathSeg::Index(bag.len()), origin));
+                    }
+                    bag.push(value);
+                };
 
                 if let Some(guard) = &self.if_guard {
                     guard.maybe_eval(&mut new_state, |s| {
                         let value = self.expression.eval(s)?;
-                        bag.push(value);
+                        push(value);
                         Some(())
                     })?;
                 } else {
                     let value = self.expression.eval(&mut new_state)?;
-                    bag.push(value);
+                    push(value);
                 }
             }
         }
@@ -140,6 +152,7 @@ impl ListComprehension {
 /// A Python-style dictionary comprehension. This is the nearest thing to `for` statement that
 /// you will get in Ryan.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DictComprehension {
     /// The expression building each item of the final dictionary.
     pub key_value_clause: KeyValueClause,
@@ -153,23 +166,12 @@ pub struct DictComprehension {
 
 impl Display for DictComprehension {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{{{} for {} in {}",
-            self.key_value_clause, self.for_clauses[0].pattern, self.for_clauses[0].expression
-        )?;
-
-        if let Some(guard) = self.if_guard.as_ref() {
-            write!(f, " if {}", guard.predicate)?;
-        }
-
-        write!(f, "}}")?;
-
-        Ok(())
+        f.write_str(&Printer::FLAT.dict_comprehension(self, 0, 0))
     }
 }
 
 impl DictComprehension {
+    #[cfg(feature = "parse")]
     pub(super) fn parse(logger: &mut ErrorLogger, pairs: Pairs<'_, Rule>) -> Self {
         let mut key_value_clause = None;
         let mut for_clauses = vec![];
@@ -180,7 +182,7 @@ impl DictComprehension {
                 Rule::keyValueClause => {
                     key_value_clause = Some(KeyValueClause::parse(logger, pair.into_inner()))
                 }
-                Rule::forClause => for_clauses.push(ForClause::parse(logger, pair.into_inner())),
+                Rule::forClause => for_clauses.push(ForClause::parse(logger, pair)),
                 Rule::ifGuard => if_guard = Some(IfGuard::parse(logger, pair.into_inner())),
                 _ => unreachable!(),
             }
@@ -208,34 +210,33 @@ impl DictComprehension {
         }
 
         if let Some(guard) = &self.if_guard {
-            guard.capture(state, &mut *provided, values)?;
+            guard.capture(state, &mut provided, values)?;
         }
 
-        self.key_value_clause
-            .capture(state, &mut *provided, values)?;
+        self.key_value_clause.capture(state, &mut provided, values)?;
 
         Some(())
     }
 
     pub(super) fn eval(&self, state: &mut State<'_>) -> Option<Value> {
-        let mut bag = IndexMap::new();
+        let mut bag = DictBuilder::new(state);
         self.run_iter(state, &mut bag, &self.for_clauses)?;
 
-        Some(Value::Map(bag.into()))
+        Some(bag.build(state))
     }
 
     fn run_iter(
         &self,
         state: &mut State<'_>,
-        bag: &mut IndexMap<Rc<str>, Value>,
+        bag: &mut DictBuilder,
         for_clauses: &[ForClause],
     ) -> Option<()> {
         let for_clause = &for_clauses[0];
-        let iterable = for_clause.expression.eval(state)?;
+        let iterable = for_clause.iterable(state)?;
         let iter = match iterable.iter() {
             Ok(iter) => iter,
             Err(err) => {
-                state.raise(err)?;
+                state.raise(EvalErrorKind::NotIterable(err))?;
                 return None;
             }
         };
@@ -243,25 +244,33 @@ impl DictComprehension {
         if for_clauses.len() > 1 {
             // Recurse
             for item in iter {
+                state.check_cancelled()?;
                 let new_bindings = for_clause.bindings(state, &item)?;
                 let mut new_state = state.new_local(new_bindings);
                 self.run_iter(&mut new_state, bag, &for_clauses[1..])?;
             }
         } else {
             // Loop
-            for item in iter {
+            for (idx, item) in iter.enumerate() {
+                state.check_cancelled()?;
                 let new_bindings = for_clause.bindings(state, &item)?;
                 let mut new_state = state.new_local(new_bindings);
+                let provenance = iterated_origin(state, &iterable, idx);
 
                 if let Some(guard) = &self.if_guard {
                     guard.maybe_eval(&mut new_state, |s| {
                         let (key, value) = self.key_value_clause.eval(s)?;
-                        bag.insert(key, value);
-                        Some(())
+                        bag.insert(s, key, value, KeyOrigin::Comprehension, provenance)
                     })?;
                 } else {
                     let (key, value) = self.key_value_clause.eval(&mut new_state)?;
-                    bag.insert(key, value);
+                    bag.insert(
+                        &mut new_state,
+                        key,
+                        value,
+                        KeyOrigin::Comprehension,
+                        provenance,
+                    )?;
                 }
             }
         }
@@ -271,30 +280,110 @@ impl DictComprehension {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ForClause {
-    pattern: Pattern,
-    expression: Expression,
+    pub(super) pattern: Pattern,
+    pub(super) expression: Expression,
+    /// The other iterables of a parallel for clause, e.g., `ys` in `for a, b in xs, ys`,
+    /// which are zipped with the first one.
+    pub(super) zipped: Vec<Expression>,
 }
 
 impl ForClause {
-    pub(super) fn parse(logger: &mut ErrorLogger, pairs: Pairs<'_, Rule>) -> Self {
-        let mut pattern = None;
-        let mut expression = None;
-
-        for pair in pairs {
-            match pair.as_rule() {
-                Rule::pattern => pattern = Some(Pattern::parse(logger, pair.into_inner())),
-                Rule::expression => expression = Some(Expression::parse(logger, pair.into_inner())),
+    /// Parses a for clause. A parallel for clause, e.g., `for a, b in xs, ys`, iterates
+    /// over the iterables zipped together, as in `for [a, b] in list.zip [xs, ys]`, and
+    /// therefore stops at the end of the shortest iterable. More than two iterables are
+    /// zipped one at a time, e.g., `for a, b, c in xs, ys, zs` matches `[[a, b], c]`.
+    #[cfg(feature = "parse")]
+    pub(super) fn parse(logger: &mut ErrorLogger, pair: Pair<'_, Rule>) -> Self {
+        let mut patterns = vec![];
+        let mut expressions = vec![];
+
+        for inner in pair.clone().into_inner() {
+            match inner.as_rule() {
+                Rule::pattern => patterns.push(Pattern::parse(logger, inner.into_inner())),
+                Rule::expression => expressions.push(Expression::parse(logger, inner.into_inner())),
                 _ => unreachable!(),
             }
         }
 
+        if patterns.len() != expressions.len() {
+            logger.absorb(
+                &pair,
+                Err::<(), _>(format!(
+                    "For clause has {} patterns, but iterates over {} values",
+                    patterns.len(),
+                    expressions.len()
+                )),
+            );
+        }
+
+        let mut patterns = patterns.into_iter();
+        let mut expressions = expressions.into_iter();
+        let mut pattern = patterns
+            .next()
+            .expect("there is always a pattern in a for clause");
+        let expression = expressions
+            .next()
+            .expect("there is always an expression in a for clause");
+        let mut zipped = vec![];
+
+        for (next_pattern, next_expression) in patterns.zip(expressions) {
+            pattern = Pattern::MatchList(vec![pattern, next_pattern]);
+            zipped.push(next_expression);
+        }
+
         ForClause {
-            pattern: pattern.expect("there is always a pattern in a for clause"),
-            expression: expression.expect("there is always an expression in a for clause"),
+            pattern,
+            expression,
+            zipped,
         }
     }
 
+    /// The patterns of the clause as written, e.g., `a` and `b` in `for a, b in xs, ys`.
+    pub(super) fn patterns(&self) -> Vec<&Pattern> {
+        let mut patterns = vec![];
+        let mut pattern = &self.pattern;
+
+        for _ in &self.zipped {
+            let Pattern::MatchList(items) = pattern else {
+                unreachable!("parallel for clauses match lists")
+            };
+            let [init, last] = &items[..] else {
+                unreachable!("parallel for clauses match pairs")
+            };
+            patterns.push(last);
+            pattern = init;
+        }
+
+        patterns.push(pattern);
+        patterns.reverse();
+        patterns
+    }
+
+    /// Evaluates the value iterated over, zipping the iterables of a parallel for clause
+    /// one at a time, in the same way as the patterns are nested.
+    fn iterable(&self, state: &mut State<'_>) -> Option<Value> {
+        let mut iterable = self.expression.eval(state)?;
+
+        for next in &self.zipped {
+            let next = next.eval(state)?;
+            let pairs = match (iterable.iter(), next.iter()) {
+                (Ok(left), Ok(right)) => left
+                    .zip(right)
+                    .map(|(left, right)| Value::List(vec![left, right].into()))
+                    .collect(),
+                (Err(err), _) | (_, Err(err)) => {
+                    state.raise(EvalErrorKind::NotIterable(err))?;
+                    return None;
+                }
+            };
+            iterable = pairs;
+        }
+
+        Some(iterable)
+    }
+
     #[must_use]
     pub(super) fn capture(
         &self,
@@ -303,6 +392,9 @@ impl ForClause {
         values: &mut IndexMap<Rc<str>, Value>,
     ) -> Option<()> {
         self.expression.capture(state, provided, values)?;
+        for next in &self.zipped {
+            next.capture(state, provided, values)?;
+        }
         self.pattern.capture(state, provided, values)?;
         self.pattern.provided(provided);
 
@@ -311,7 +403,18 @@ impl ForClause {
 
     fn bindings(&self, state: &mut State<'_>, value: &Value) -> Option<IndexMap<Rc<str>, Value>> {
         let mut new_bindings = IndexMap::new();
-        let bind = self.pattern.bind(&value, &mut new_bindings, state)?;
+        let bind = self.pattern.bind(value, &mut new_bindings, state)?;
+
+        // Forgetting to `zip` is a common mistake when iterating over many lists at once:
+        if let (Err(BindError::NoMatch { .. }), Pattern::MatchList(items)) = (&bind, &self.pattern)
+        {
+            state.raise(EvalErrorKind::IterationMismatch {
+                pattern: self.pattern.clone(),
+                expected: items.len(),
+                value: value.clone(),
+            })?;
+        }
+
         state.absorb(bind)?;
 
         Some(new_bindings)
@@ -319,18 +422,20 @@ impl ForClause {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct KeyValueClause {
-    key: Expression,
-    value: Expression,
+    pub(super) key: Expression,
+    pub(super) value: Expression,
 }
 
 impl Display for KeyValueClause {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}: {}", self.key, self.value)
+        f.write_str(&Printer::FLAT.key_value_clause(self, 0, 0))
     }
 }
 
 impl KeyValueClause {
+    #[cfg(feature = "parse")]
     pub(super) fn parse(logger: &mut ErrorLogger, mut pairs: Pairs<'_, Rule>) -> Self {
         let key = Expression::parse(
             logger,
@@ -364,21 +469,20 @@ impl KeyValueClause {
 
     pub(super) fn eval(&self, state: &mut State<'_>) -> Option<(Rc<str>, Value)> {
         let key = self.key.eval(state)?;
-        let Value::Text(key) = key else {
-            state.raise(format!("Dict comprehension received non-text value {key}"));
-            return None;
-        };
+        let key = text_key(state, key)?;
         let value = self.value.eval(state)?;
         Some((key, value))
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IfGuard {
-    predicate: Expression,
+    pub(super) predicate: Expression,
 }
 
 impl IfGuard {
+    #[cfg(feature = "parse")]
     pub(super) fn parse(logger: &mut ErrorLogger, mut pairs: Pairs<'_, Rule>) -> Self {
         let predicate = Expression::parse(
             logger,
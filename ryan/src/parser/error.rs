@@ -1,13 +1,18 @@
+#[cfg(feature = "parse")]
 use pest::error::{ErrorVariant, InputLocation};
+#[cfg(feature = "parse")]
 use pest::iterators::Pair;
 use std::fmt::{self, Display};
 use std::str;
 use thiserror::Error;
 
+#[cfg(feature = "parse")]
+use super::format::{Collection, Layout};
+#[cfg(feature = "parse")]
 use super::Rule;
 
 /// An entry of a post-parsing error, logged by [`ErrorLogger`].
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ErrorEntry {
     /// The beginning and end of the offending code.
     pub span: (usize, usize),
@@ -15,6 +20,7 @@ pub struct ErrorEntry {
     pub error: String,
 }
 
+#[cfg(feature = "parse")]
 impl From<pest::error::Error<Rule>> for ErrorEntry {
     fn from(value: pest::error::Error<Rule>) -> Self {
         let span = match value.location {
@@ -29,7 +35,7 @@ impl From<pest::error::Error<Rule>> for ErrorEntry {
                 let mut message = String::new();
                 let or_list = |v: &[Rule]| match v {
                     [] => unreachable!(),
-                    [r0] => format!("{}", r0.name()),
+                    [r0] => r0.name().to_string(),
                     [r0, r1] => format!("{} or {}", r0.name(), r1.name()),
                     [r0, r1, r2] => format!("{}, {} or {}", r0.name(), r1.name(), r2.name()),
                     [r0, r1, r2, tail @ ..] => {
@@ -43,11 +49,11 @@ impl From<pest::error::Error<Rule>> for ErrorEntry {
                     }
                 };
 
-                if negatives.len() > 0 {
+                if !negatives.is_empty() {
                     message.push_str(&format!("Found {}.", or_list(&negatives)));
                 }
 
-                if positives.len() > 0 {
+                if !positives.is_empty() {
                     message.push_str(&format!("Expected {}.", or_list(&positives)));
                 }
 
@@ -62,91 +68,208 @@ impl From<pest::error::Error<Rule>> for ErrorEntry {
 
 impl ErrorEntry {
     /// Creates a human-readable form for this error entry, given the input it was derived from.
-    pub(super) fn to_string_with(&self, input: &str) -> String {
-        let (line_start, col_start) = crate::utils::line_col(input, self.span.0);
-        let (line_end, col_end) = crate::utils::line_col(input, self.span.1);
-
-        // The string buffer for this error message.
-        let mut string = String::new();
-
-        // The size of the margin to be set to fit the line number.
-        let line_display_gap: String = std::iter::repeat(' ')
-            .take((line_end + 1).to_string().len())
-            .collect();
-
-        // The header indicating where the error starts.
-        string.push_str(&format!(
-            " {line_display_gap} \u{21e2} Starting at line {}, col {}:\n",
-            line_start + 1,
-            col_start + 1
-        ));
-
-        // Start with an empty line:
-        string.push_str(&format!(" {line_display_gap} \u{2502}\n"));
-
-        // For each line in which the error appears, do:
-        for (i, line) in input
-            .lines()
-            .enumerate()
-            .skip(line_start)
-            .take(line_end - line_start + 1)
-        {
-            // Print the line:
-            string.push_str(&format!(" {} \u{2502} {line}\n", i + 1));
-
-            // Now, underline the error portion...
-
-            // Get the starting and ending point of the error:
-            let start_point = if line_start != line_end && i != line_start {
-                0
-            } else {
-                col_start
-            };
-            let end_point = if line_start != line_end && i != line_end {
-                line.chars().count()
-            } else {
-                col_end
-            };
-
-            // Print the error line point:
-            string.push_str(&format!(" {line_display_gap} \u{2502} "));
-            for _ in 0..start_point {
-                string.push(' ');
-            }
-            for _ in 0..(end_point - start_point) {
-                string.push('^');
-            }
-            string.push('\n');
-        }
+    pub fn to_string_with(&self, input: &str) -> String {
+        fmt_span(input, self.span, &self.error)
+    }
+
+    /// The line and the column, both starting at one, where this error starts, given the
+    /// input it was derived from. Columns count characters, not bytes.
+    pub fn position(&self, input: &str) -> (usize, usize) {
+        let (line, col) = crate::utils::line_col(input, self.span.0);
+        (line + 1, col + 1)
+    }
+}
+
+/// The kinds of non-fatal issues that Ryan can find in a program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A binding redefines a variable already defined in the same block.
+    ShadowedBinding,
+    /// A dictionary literal defines the same key twice, unconditionally.
+    DuplicateKey,
+    /// A binding is never used in its scope.
+    UnusedBinding,
+    /// A variable bound to the value of an import is never used, e.g., `let x = import
+    /// "x.ryan";` without any mention of `x` afterwards.
+    UnusedImport,
+    /// The code uses syntax that still works, but will be removed in the future.
+    DeprecatedSyntax,
+}
+
+/// A non-fatal issue found while parsing, logged by [`ErrorLogger`]. Warnings never
+/// prevent a program from running.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// What kind of issue this is.
+    pub kind: WarningKind,
+    /// The beginning and end of the offending code.
+    pub span: (usize, usize),
+    /// A human-readable description of the issue.
+    pub message: String,
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Warning {
+    /// Creates a human-readable form for this warning, pointing to where it happens,
+    /// given the input it was derived from. Columns count characters, not bytes:
+    /// ```rust
+    /// let source = "let s = \"ação\"; let x = 1;\ns";
+    /// let warnings = ryan::lint(source).unwrap();
+    /// assert_eq!(
+    ///     warnings[0].to_string_with(source),
+    ///     [
+    ///         "   ⇢ Starting at line 1, col 21:",
+    ///         "   │",
+    ///         " 1 │ let s = \"ação\"; let x = 1;",
+    ///         "   │                     ^",
+    ///         "   │",
+    ///         "   = Variable `x` is never used",
+    ///     ]
+    ///     .join("\n")
+    /// );
+    ///
+    /// // On the last line, without a trailing newline:
+    /// let source = "let s = 1;\nlet t = \"🎉🎉\"; let x = 1; s + t";
+    /// let warnings = ryan::lint(source).unwrap();
+    /// assert_eq!(
+    ///     warnings[0].to_string_with(source),
+    ///     [
+    ///         "   ⇢ Starting at line 2, col 19:",
+    ///         "   │",
+    ///         " 2 │ let t = \"🎉🎉\"; let x = 1; s + t",
+    ///         "   │                   ^",
+    ///         "   │",
+    ///         "   = Variable `x` is never used",
+    ///     ]
+    ///     .join("\n")
+    /// );
+    /// ```
+    pub fn to_string_with(&self, input: &str) -> String {
+        fmt_span(input, self.span, &self.message)
+    }
+}
+
+/// Underlines a span of the input, followed by a message.
+fn fmt_span(input: &str, span: (usize, usize), message: &str) -> String {
+    let (line_start, col_start) = crate::utils::line_col(input, span.0);
+    let (mut line_end, mut col_end) = crate::utils::line_col(input, span.1);
+
+    // A span ending right after a newline ends, in fact, at the end of the previous line:
+    if line_end > line_start && col_end == 0 {
+        (line_end, col_end) = crate::utils::line_col(input, span.1.min(input.len()) - 1);
+    }
+
+    // The string buffer for this error message.
+    let mut string = String::new();
+
+    // The size of the margin to be set to fit the line number.
+    let line_display_gap = " ".repeat((line_end + 1).to_string().len());
 
-        // End with an empty line:
-        string.push_str(&format!(" {line_display_gap} \u{2502}\n"));
+    // The header indicating where the error starts.
+    string.push_str(&format!(
+        " {line_display_gap} \u{21e2} Starting at line {}, col {}:\n",
+        line_start + 1,
+        col_start + 1
+    ));
 
-        // Print the error message itself.
-        string.push_str(&format!(" {line_display_gap} = {}", self.error));
+    // Start with an empty line:
+    string.push_str(&format!(" {line_display_gap} \u{2502}\n"));
 
-        string
+    // For each line in which the error appears, do:
+    for (i, line) in input
+        .lines()
+        .enumerate()
+        .skip(line_start)
+        .take(line_end - line_start + 1)
+    {
+        // Print the line:
+        string.push_str(&format!(" {} \u{2502} {line}\n", i + 1));
+
+        // Now, underline the error portion...
+
+        // Get the starting and ending point of the error, within the line:
+        let line_len = line.chars().count();
+        let start_point = if line_start != line_end && i != line_start {
+            0
+        } else {
+            col_start.min(line_len)
+        };
+        let end_point = if line_start != line_end && i != line_end {
+            line_len
+        } else if line_start == line_end {
+            // Always point at something, even if it is the end of the line:
+            col_end.min(line_len).max(start_point + 1)
+        } else {
+            col_end.min(line_len)
+        };
+
+        // Print the error line point:
+        string.push_str(&format!(" {line_display_gap} \u{2502} "));
+        for _ in 0..start_point {
+            string.push(' ');
+        }
+        for _ in 0..end_point.saturating_sub(start_point) {
+            string.push('^');
+        }
+        string.push('\n');
     }
+
+    // End with an empty line:
+    string.push_str(&format!(" {line_display_gap} \u{2502}\n"));
+
+    // Print the error message itself.
+    string.push_str(&format!(" {line_display_gap} = {message}"));
+
+    string
 }
 
 /// A logger of errors that happen post-parsing. Post parsing always succeeds, even with
 /// a list of errors. It's the whole parsing processing that fails if there are
 /// post-parsing errors.
+#[cfg(feature = "parse")]
 #[derive(Debug)]
 pub struct ErrorLogger<'a> {
     input: &'a str,
     /// The list of errors found during post-parsing, in the orders they were found.
     pub errors: Vec<ErrorEntry>,
+    /// The list of warnings found during post-parsing, in the orders they were found.
+    pub warnings: Vec<Warning>,
+    /// Where the lists and dictionaries are in the input, for the formatter.
+    pub(super) layout: Layout,
 }
 
+#[cfg(feature = "parse")]
 impl ErrorLogger<'_> {
-    pub(super) fn new(input: &str) -> ErrorLogger {
+    pub(super) fn new(input: &str) -> ErrorLogger<'_> {
         ErrorLogger {
             input,
             errors: vec![],
+            warnings: vec![],
+            layout: Layout::default(),
         }
     }
 
+    /// Records where a list or a dictionary and each of its items are.
+    pub(super) fn lay_out<T>(&mut self, pair: &Pair<Rule>, items: &[T]) {
+        let span = pair.as_span();
+        let item_spans = pair.clone().into_inner().map(|item| {
+            let span = item.as_span();
+            (span.start(), span.end())
+        });
+        self.layout.record(
+            items,
+            Collection {
+                span: (span.start(), span.end()),
+                items: item_spans.collect(),
+            },
+        );
+    }
+
     /// "Absorbs" an error.
     pub(super) fn absorb<T, E>(&mut self, pair: &Pair<Rule>, r: Result<T, E>) -> T
     where
@@ -164,24 +287,56 @@ impl ErrorLogger<'_> {
             }
         }
     }
+
+    /// Logs a warning about the code in a given pair.
+    pub(super) fn warn(&mut self, pair: &Pair<Rule>, kind: WarningKind, message: String) {
+        // Rules ending in an optional part take the whitespace before it into their span:
+        let start = pair.as_span().start();
+        let end = start + pair.as_str().trim_end().len();
+        self.warn_at((start, end), kind, message);
+    }
+
+    /// Logs a warning about the code in a given span.
+    pub(super) fn warn_at(&mut self, span: (usize, usize), kind: WarningKind, message: String) {
+        self.warnings.push(Warning {
+            kind,
+            span,
+            message,
+        });
+    }
 }
 
 /// A general parsing error.
 #[derive(Debug, Error)]
 pub struct ParseError {
-    pub(super) errors: Vec<String>,
+    entries: Vec<ErrorEntry>,
+    errors: Vec<String>,
 }
 
-impl From<ErrorLogger<'_>> for ParseError {
-    fn from(value: ErrorLogger<'_>) -> Self {
+impl ParseError {
+    #[cfg_attr(not(feature = "parse"), allow(dead_code))]
+    pub(super) fn new(input: &str, entries: Vec<ErrorEntry>) -> ParseError {
         ParseError {
-            errors: value
-                .errors
-                .into_iter()
-                .map(|entry| entry.to_string_with(value.input))
+            errors: entries
+                .iter()
+                .map(|entry| entry.to_string_with(input))
                 .collect(),
+            entries,
         }
     }
+
+    /// The errors found in the code, in the order they were found, each with the span
+    /// where it happens. Use this to report errors as data, e.g., to an editor.
+    pub fn entries(&self) -> &[ErrorEntry] {
+        &self.entries
+    }
+}
+
+#[cfg(feature = "parse")]
+impl From<ErrorLogger<'_>> for ParseError {
+    fn from(value: ErrorLogger<'_>) -> Self {
+        ParseError::new(value.input, value.errors)
+    }
 }
 
 impl Display for ParseError {
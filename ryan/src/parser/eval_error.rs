@@ -0,0 +1,631 @@
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::rc::Rc;
+use thiserror::Error;
+
+use super::expression::KeyOrigin;
+use super::import::Format;
+use super::operation::MAX_REPETITION_SIZE;
+use super::pattern::{BindError, Pattern};
+use super::types::Type;
+use super::value::{NotIterable, NotRepresentable, Value};
+use crate::utils::QuotedStr;
+
+/// What the interpreter was doing when an error happened. The context of an error is a
+/// stack of these, from the outermost to the innermost.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Context {
+    /// Running a Ryan file (or `<main>`, if the program has no file name).
+    RunningFile(Rc<str>),
+    /// Evaluating the value of a binding, e.g., `let x = ...;`.
+    EvaluatingBinding(Rc<str>),
+    /// Evaluating a type definition, e.g., `type T = ...;`.
+    DefiningType(Rc<str>),
+    /// Calling a pattern match. The name is `None` for anonymous pattern matches.
+    SubstitutingPattern(Option<Rc<str>>),
+    /// Loading an import.
+    LoadingImport {
+        /// The path being imported.
+        path: Rc<str>,
+        /// The modules that led to this import, in import order.
+        chain: Vec<Rc<str>>,
+    },
+    /// Evaluating the default value of an import, given with `or`, because the import
+    /// itself failed. If the default fails as well, both failures are reported:
+    ///
+    /// ```
+    /// let Err(ryan::Error::Eval(err)) =
+    ///     ryan::from_str::<String>(r#"import "env:__RYAN_UNSET__" as text or missing"#)
+    /// else {
+    ///     panic!("both the import and its default fail");
+    /// };
+    ///
+    /// assert_eq!(err.kind().to_string(), "Variable `missing` is undefined");
+    /// assert_eq!(
+    ///     err.context().last().unwrap().to_string(),
+    ///     "Evaluating the default of import \"env:__RYAN_UNSET__\", since it failed: \
+    ///     Environment variable __RYAN_UNSET__ is not set",
+    /// );
+    /// ```
+    ///
+    /// When the default succeeds, the failed import is reported as a warning instead. An
+    /// import that succeeds never warns:
+    ///
+    /// ```
+    /// use std::{cell::RefCell, rc::Rc};
+    /// use ryan::environment::{Environment, EvalEvent, ImportLoader, NoImport};
+    ///
+    /// let warnings = Rc::new(RefCell::new(vec![]));
+    /// let recorded = warnings.clone();
+    /// let loader = NoImport.r#override("/present.ryan".to_owned(), "1".to_owned());
+    /// let env = Environment::builder()
+    ///     .import_loader(loader)
+    ///     .on_event(move |event| {
+    ///         if let EvalEvent::Warning { message } = event {
+    ///             recorded.borrow_mut().push(message.to_string());
+    ///         }
+    ///     })
+    ///     .build();
+    ///
+    /// let code = r#"[import "/present.ryan" or 0, import "/missing.ryan" or 2]"#;
+    /// assert_eq!(ryan::from_str_with_env::<Vec<i64>>(&env, code).unwrap(), [1, 2]);
+    /// assert_eq!(warnings.borrow().len(), 1);
+    /// assert!(warnings.borrow()[0].starts_with("Import \"/missing.ryan\" failed, using default"));
+    /// ```
+    EvaluatingImportDefault {
+        /// The path being imported.
+        path: Rc<str>,
+        /// Why the import failed.
+        reason: Rc<str>,
+    },
+}
+
+impl Display for Context {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RunningFile(filename) => write!(f, "Running {filename}"),
+            Self::EvaluatingBinding(name) => write!(f, "Evaluating binding {name}"),
+            Self::DefiningType(typ) => write!(f, "Defining type {typ}"),
+            Self::SubstitutingPattern(Some(name)) => write!(f, "Substituting pattern {name}"),
+            Self::SubstitutingPattern(None) => write!(f, "Substituting anonymous pattern"),
+            Self::LoadingImport { path, chain } if chain.is_empty() => {
+                write!(f, "Loading import {path:?}")
+            }
+            Self::LoadingImport { path, chain } => write!(
+                f,
+                "Loading import {path:?} from {}",
+                crate::environment::loader::fmt_import_chain(chain)
+            ),
+            Self::EvaluatingImportDefault { path, reason } => {
+                write!(
+                    f,
+                    "Evaluating the default of import {path:?}, since it failed: {reason}"
+                )
+            }
+        }
+    }
+}
+
+/// The kind of error that happened while running a Ryan program. Use this to tell
+/// failures apart, e.g., to fall back to defaults when an import fails.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum EvalErrorKind {
+    /// A variable was used, but it is bound nowhere.
+    #[error("Variable `{name}` is undefined")]
+    UndefinedVariable {
+        /// The name of the variable.
+        name: Rc<str>,
+    },
+    /// Variables used in the definition of a pattern match are bound nowhere. Pattern
+    /// matches capture the variables they use when they are defined, so this is raised
+    /// where the pattern match is defined, not where it is applied. All the undefined
+    /// variables in the definition are reported at once:
+    ///
+    /// ```
+    /// let Err(ryan::Error::Eval(err)) =
+    ///     ryan::from_str::<i64>("let f x = x + offset * scale; f 1")
+    /// else {
+    ///     panic!("`offset` and `scale` are undefined");
+    /// };
+    ///
+    /// assert_eq!(
+    ///     err.kind().to_string(),
+    ///     "Variables `offset` and `scale` are undefined, but they are used in the \
+    ///     definition of pattern `f`. Bind them before defining `f`",
+    /// );
+    /// ```
+    #[error("{}", fmt_undefined_in_pattern(.pattern, .names))]
+    UndefinedInPattern {
+        /// The name of the pattern match, or `None` if it is anonymous.
+        pattern: Option<Rc<str>>,
+        /// The undefined variables, in the order they appear in the definition.
+        names: Vec<Rc<str>>,
+    },
+    /// A pattern match tries to apply itself, which Ryan does not support. Using its own
+    /// name in its definition is raised where the pattern match is defined. Reaching an
+    /// earlier definition of itself through other pattern matches, e.g., when a rule
+    /// added to `a` calls `b`, which captured `a` before that rule was added, is raised
+    /// where it is applied:
+    ///
+    /// ```
+    /// let Err(ryan::Error::Eval(err)) =
+    ///     ryan::from_str::<i64>("let a 0 = 0; let b x = a x; let a x = b (x - 1); a 3")
+    /// else {
+    ///     panic!("`a` reaches itself through `b`");
+    /// };
+    ///
+    /// assert_eq!(
+    ///     err.kind().to_string(),
+    ///     "Ryan does not support recursion: pattern `a` calls `b`, which calls an earlier \
+    ///     definition of `a`. Give the new rules of `a` a pattern of their own instead",
+    /// );
+    /// ```
+    #[error("{}", fmt_recursion(.pattern, .through))]
+    Recursion {
+        /// The name of the pattern match.
+        pattern: Rc<str>,
+        /// The pattern matches through which the pattern match reaches itself, in the
+        /// order they are applied. Empty if it uses its own name.
+        through: Vec<Rc<str>>,
+    },
+    /// A key was accessed in a dictionary that does not have it.
+    #[error("Key `{key}` not present in `{}`", .value.brief())]
+    KeyMissing {
+        /// The key accessed.
+        key: Rc<str>,
+        /// The dictionary accessed.
+        value: Value,
+    },
+    /// A value bound to a variable does not conform to the type the variable declares.
+    #[error("Variable {name} bound to {} is not of type {expected}", .value.brief())]
+    TypeMismatch {
+        /// The name of the variable.
+        name: Rc<str>,
+        /// The value bound to the variable.
+        value: Value,
+        /// The declared type of the variable.
+        expected: Type,
+    },
+    /// A value does not have the shape a pattern expects, for reasons other than a type
+    /// mismatch.
+    #[error("{0}")]
+    PatternMismatch(BindError),
+    /// An import could not be loaded.
+    #[error("{source}")]
+    ImportFailed {
+        /// The path being imported.
+        path: Rc<str>,
+        /// What went wrong when loading the import.
+        source: Box<dyn StdError + 'static>,
+    },
+    /// An operator was applied to values it does not support, e.g., `1 + "a"`.
+    #[error("Operator `{op}` cannot be applied to {}", fmt_operands(.left, .right))]
+    OperatorMisuse {
+        /// The operator, as it is written in Ryan.
+        op: String,
+        /// The left operand. This is `None` for prefix operators.
+        left: Option<Value>,
+        /// The right operand. This is `None` for postfix operators.
+        right: Option<Value>,
+    },
+    /// A value that is not a pattern match is applied to another, e.g., `5 { a: 1 }`.
+    /// Since juxtaposition is how pattern matches are applied, this is usually a missing
+    /// comma between two items, as in `[name value]`.
+    #[error(
+        "`{}` is not a pattern and cannot be applied to `{}`; if you meant two separate \
+        items, check for a missing comma",
+        .callee.brief(),
+        .arg.brief()
+    )]
+    NotAPattern {
+        /// The value in the place of the pattern match.
+        callee: Value,
+        /// The value it is applied to.
+        arg: Value,
+    },
+    /// The right side of `|>` is not a pattern match, e.g., `[1, 2] |> 3`.
+    #[error(
+        "The right side of `|>` must be a pattern to apply to the left side, got {}",
+        .target.brief()
+    )]
+    NotAPipeTarget {
+        /// The value on the right side of `|>`.
+        target: Value,
+    },
+    /// A value that is not a type is used as one, e.g., `let n = 1; let x: n = 2; x`.
+    #[error("The value `{}` is not a type", .value.brief())]
+    NotAType {
+        /// The value used as a type.
+        value: Value,
+    },
+    /// A value that is neither a list nor a dictionary is iterated over, e.g., in
+    /// `[x for x in 5]`.
+    #[error("{0}")]
+    NotIterable(NotIterable),
+    /// A conversion with `as` failed, e.g., `"abc" as int` or `1e100 as int`.
+    #[error("{}", fmt_cast_failed(.value, .target))]
+    CastFailed {
+        /// The value being converted.
+        value: Value,
+        /// The type it is converted to.
+        target: Type,
+    },
+    /// A list or dictionary converted with `as text` contains something JSON cannot
+    /// represent, such as a pattern match.
+    #[error("{0}")]
+    NotRepresentable(NotRepresentable),
+    /// A value other than text or an integer is used as a dictionary key, e.g.,
+    /// `{ [1.5]: "a" }`.
+    #[error(
+        "Dictionary keys must be text or integers, got {} {}; turn it into text with `fmt` \
+        or `as text`",
+        .key.canonical_type(),
+        .key.brief()
+    )]
+    InvalidKey {
+        /// The value used as a key.
+        key: Value,
+    },
+    /// A value other than text is among the keys to be removed from a dictionary, as in
+    /// `{ a: 1 } - [1]`.
+    #[error("Keys to be removed from a dictionary must be text, got `{}`", .key.brief())]
+    InvalidKeyToRemove {
+        /// The value used as a key.
+        key: Value,
+    },
+    /// The same key is set twice in a dictionary. Only raised with
+    /// [`DuplicateKeyPolicy::Error`].
+    ///
+    /// [`DuplicateKeyPolicy::Error`]: crate::environment::DuplicateKeyPolicy::Error
+    #[error(
+        "Key {} defined twice in dictionary, first by {first} and then by {then}",
+        QuotedStr(.key)
+    )]
+    DuplicateKey {
+        /// The key set twice.
+        key: Rc<str>,
+        /// What set the key first.
+        first: KeyOrigin,
+        /// What set the key again.
+        then: KeyOrigin,
+    },
+    /// A flatten expression in a list spreads something other than a list or a
+    /// dictionary, e.g., `[...1]`.
+    #[error(
+        "Flatten expression must be either a map or a list, got {}",
+        .value.canonical_type()
+    )]
+    InvalidListFlatten {
+        /// The value spread.
+        value: Value,
+    },
+    /// A flatten expression in a dictionary spreads something other than a dictionary or
+    /// a list of key-value pairs, e.g., `{ ...1 }`.
+    #[error(
+        "Flatten expression must be either a map or list of key-value pairs, got {}",
+        .value.canonical_type()
+    )]
+    InvalidDictFlatten {
+        /// The value spread.
+        value: Value,
+    },
+    /// An item of a list spread into a dictionary is not a pair of a text and a value,
+    /// e.g., `{ ...[[1, 2]] }`.
+    #[error("{}", fmt_invalid_key_pair(.item))]
+    InvalidKeyPair {
+        /// The item that is not a key-value pair.
+        item: Value,
+    },
+    /// Text or a list is repeated with `*` into something bigger than
+    /// [`MAX_REPETITION_SIZE`] bytes or elements.
+    #[error(
+        "Repeating a value of length {len} {times} times exceeds the maximum size of \
+        {MAX_REPETITION_SIZE}"
+    )]
+    RepetitionTooLarge {
+        /// The length of the value repeated.
+        len: usize,
+        /// The number of repetitions.
+        times: i64,
+    },
+    /// A dictionary is repeated with `*`, which would only repeat its keys.
+    #[error(
+        "Dictionaries cannot be repeated (in `{dict} * {times}`): keys would collide. Use a \
+        comprehension to build the keys instead"
+    )]
+    RepeatedDictionary {
+        /// The dictionary repeated.
+        dict: Value,
+        /// The number of repetitions.
+        times: i64,
+    },
+    /// Pattern matches are applied inside each other too deeply, as in
+    /// `let f x = x x; f f`.
+    #[error(
+        "Pattern matches nested more than {max} levels deep. Is there a pattern match \
+        calling itself?"
+    )]
+    NestingTooDeep {
+        /// How deep pattern matches may be nested.
+        max: usize,
+    },
+    /// The pattern of a comprehension expects lists of a given length, but the items
+    /// iterated over are not such lists. This is usually a missing `zip`.
+    #[error(
+        "Pattern {pattern} expected a list with {expected} elements in each iteration, got \
+        {}. To iterate over many lists in lockstep, use `zip` or `for a, b in xs, ys`",
+        .value.brief()
+    )]
+    IterationMismatch {
+        /// The pattern of the comprehension.
+        pattern: Pattern,
+        /// The number of elements the pattern expects.
+        expected: usize,
+        /// The item iterated over.
+        value: Value,
+    },
+    /// Arguments are passed to an import that is not read as Ryan, as in
+    /// `import "x.txt" as text with { a: 1 }`.
+    #[error("Arguments can only be passed to imports as Ryan, not as {format}")]
+    ImportArgumentsUnsupported {
+        /// The path being imported.
+        path: Rc<str>,
+        /// The format of the import.
+        format: Format,
+    },
+    /// Only some keys are picked from an import that is not read as Ryan.
+    #[error("Only some keys can be imported from imports as Ryan, not as {format}")]
+    ImportKeysUnsupported {
+        /// The path being imported.
+        path: Rc<str>,
+        /// The format of the import.
+        format: Format,
+    },
+    /// A program without a final expression is run in strict mode.
+    #[error("Program has no final expression to evaluate (strict mode)")]
+    NoFinalExpression,
+    /// An integer operation does not fit in 64 bits.
+    #[error("Integer overflow in `{operation}`")]
+    IntegerOverflow {
+        /// The operation, as it is written in Ryan.
+        operation: String,
+    },
+    /// An arithmetic operation mixes a float with an integer too big to be turned into a
+    /// float exactly. Only raised with [`PrecisionLossPolicy::Error`].
+    ///
+    /// [`PrecisionLossPolicy::Error`]: crate::environment::PrecisionLossPolicy::Error
+    #[error(
+        "The integer {int} in `{operation}` is too big to be turned into a float exactly. \
+        Convert it explicitly with `as float` if the loss of precision is fine"
+    )]
+    PrecisionLoss {
+        /// The integer that would be turned into a float.
+        int: i64,
+        /// The operation, as it is written in Ryan.
+        operation: String,
+    },
+    /// The evaluation was stopped through its
+    /// [`CancellationToken`](crate::environment::CancellationToken).
+    #[error("Evaluation was cancelled")]
+    Cancelled,
+    /// Any other error. These are errors raised by native pattern matches, conditions
+    /// that are not booleans, indices and paths that cannot be extracted from a value and
+    /// text templates that cannot be formatted.
+    #[error("{0}")]
+    Other(String),
+}
+
+impl EvalErrorKind {
+    /// A short, stable name for this kind of error in `snake_case`, e.g.,
+    /// `undefined_variable`. Use this to report errors as data.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::UndefinedVariable { .. } | Self::UndefinedInPattern { .. } => {
+                "undefined_variable"
+            }
+            Self::Recursion { .. } => "recursion",
+            Self::KeyMissing { .. } => "key_missing",
+            Self::TypeMismatch { .. } => "type_mismatch",
+            Self::PatternMismatch(_) => "pattern_mismatch",
+            Self::ImportFailed { .. } => "import_failed",
+            Self::OperatorMisuse { .. } => "operator_misuse",
+            Self::NotAPattern { .. } | Self::NotAPipeTarget { .. } => "not_a_pattern",
+            Self::NotAType { .. } => "not_a_type",
+            Self::NotIterable(_) => "not_iterable",
+            Self::CastFailed { .. } => "cast_failed",
+            Self::NotRepresentable(_) => "not_representable",
+            Self::InvalidKey { .. } | Self::InvalidKeyToRemove { .. } => "invalid_key",
+            Self::DuplicateKey { .. } => "duplicate_key",
+            Self::InvalidListFlatten { .. }
+            | Self::InvalidDictFlatten { .. }
+            | Self::InvalidKeyPair { .. } => "invalid_flatten",
+            Self::RepetitionTooLarge { .. } => "repetition_too_large",
+            Self::RepeatedDictionary { .. } => "operator_misuse",
+            Self::NestingTooDeep { .. } => "nesting_too_deep",
+            Self::IterationMismatch { .. } => "pattern_mismatch",
+            Self::ImportArgumentsUnsupported { .. } | Self::ImportKeysUnsupported { .. } => {
+                "unsupported_import"
+            }
+            Self::NoFinalExpression => "no_final_expression",
+            Self::IntegerOverflow { .. } => "integer_overflow",
+            Self::PrecisionLoss { .. } => "precision_loss",
+            Self::Cancelled => "cancelled",
+            Self::Other(_) => "other",
+        }
+    }
+}
+
+fn fmt_recursion(pattern: &Rc<str>, through: &[Rc<str>]) -> String {
+    if through.is_empty() {
+        return format!(
+            "Ryan does not support recursion: pattern `{pattern}` cannot reference itself. \
+            Consider a comprehension over a `range` instead"
+        );
+    }
+
+    let calls = through
+        .iter()
+        .map(|name| format!("`{name}`, which calls "))
+        .collect::<String>();
+    format!(
+        "Ryan does not support recursion: pattern `{pattern}` calls {calls}an earlier \
+        definition of `{pattern}`. Give the new rules of `{pattern}` a pattern of their own \
+        instead"
+    )
+}
+
+fn fmt_undefined_in_pattern(pattern: &Option<Rc<str>>, names: &[Rc<str>]) -> String {
+    let quoted = names
+        .iter()
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<_>>();
+    let (variables, are, they, them) = match quoted.as_slice() {
+        [one] => (format!("Variable {one}"), "is", "it", "it"),
+        [init @ .., last] => (
+            format!("Variables {} and {last}", init.join(", ")),
+            "are",
+            "they",
+            "them",
+        ),
+        [] => ("No variables".to_string(), "are", "they", "them"),
+    };
+
+    match pattern {
+        Some(pattern) => format!(
+            "{variables} {are} undefined, but {they} {are} used in the definition of pattern \
+            `{pattern}`. Bind {them} before defining `{pattern}`"
+        ),
+        None => format!(
+            "{variables} {are} undefined, but {they} {are} used in the definition of an \
+            anonymous pattern"
+        ),
+    }
+}
+
+fn fmt_cast_failed(value: &Value, target: &Type) -> String {
+    match value {
+        Value::Float(float) => {
+            let reason = if float.is_finite() {
+                "it is out of the range of int"
+            } else {
+                "it is not a finite number"
+            };
+            format!(
+                "Cannot convert float {value} to {target}, since {reason}. Note that `as int` \
+                truncates towards zero: use `round`, `floor` or `ceil` to round explicitly"
+            )
+        }
+        Value::Text(text) => format!("Cannot convert text {} to {target}", QuotedStr(text)),
+        Value::Bytes(bytes) => match std::str::from_utf8(bytes) {
+            Err(err) => format!("Cannot convert bytes to {target}: {err}"),
+            Ok(_) => format!("Cannot convert bytes to {target}"),
+        },
+        value => format!("Cannot convert {} to {target}", value.brief()),
+    }
+}
+
+fn fmt_invalid_key_pair(item: &Value) -> String {
+    match item {
+        Value::List(pair) if pair.len() == 2 => format!(
+            "First element of key-pair list must be text, got {}",
+            pair[0].canonical_type()
+        ),
+        item => format!(
+            "Key-pair list must be [text, any], got {}",
+            item.canonical_type()
+        ),
+    }
+}
+
+fn fmt_operands(left: &Option<Value>, right: &Option<Value>) -> String {
+    match (left, right) {
+        (Some(left), Some(right)) => format!("`{}` and `{}`", left.brief(), right.brief()),
+        (Some(operand), None) | (None, Some(operand)) => format!("`{}`", operand.brief()),
+        (None, None) => "nothing".to_string(),
+    }
+}
+
+impl From<String> for EvalErrorKind {
+    fn from(msg: String) -> EvalErrorKind {
+        EvalErrorKind::Other(msg)
+    }
+}
+
+impl From<&str> for EvalErrorKind {
+    fn from(msg: &str) -> EvalErrorKind {
+        EvalErrorKind::Other(msg.to_string())
+    }
+}
+
+impl From<BindError> for EvalErrorKind {
+    fn from(err: BindError) -> EvalErrorKind {
+        match err {
+            BindError::WrongType { id, val, typ } => EvalErrorKind::TypeMismatch {
+                name: id,
+                value: val,
+                expected: typ,
+            },
+            err => EvalErrorKind::PatternMismatch(err),
+        }
+    }
+}
+
+/// An error that happens during the execution of a Ryan program.
+///
+/// ```
+/// use ryan::parser::EvalErrorKind;
+///
+/// let Err(ryan::Error::Eval(err)) = ryan::from_str::<i64>("let x = 1; y + x") else {
+///     panic!("expected an evaluation error");
+/// };
+///
+/// assert!(matches!(err.kind(), EvalErrorKind::UndefinedVariable { name } if &**name == "y"));
+/// ```
+#[derive(Debug, Error)]
+pub struct EvalError {
+    // Boxed, since errors are moved around far more often than they are looked into:
+    kind: Box<EvalErrorKind>,
+    context: Vec<Context>,
+}
+
+impl EvalError {
+    pub(super) fn new(kind: EvalErrorKind, context: Vec<Context>) -> EvalError {
+        EvalError {
+            kind: Box::new(kind),
+            context,
+        }
+    }
+
+    /// What went wrong.
+    pub fn kind(&self) -> &EvalErrorKind {
+        &self.kind
+    }
+
+    /// What the interpreter was doing when the error happened, from the outermost to the
+    /// innermost activity.
+    pub fn context(&self) -> &[Context] {
+        &self.context
+    }
+
+    /// Takes the kind of this error, discarding the context.
+    pub fn into_kind(self) -> EvalErrorKind {
+        *self.kind
+    }
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.kind)?;
+
+        if !self.context.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Context:")?;
+            for line in &self.context {
+                writeln!(f, "    - {line}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
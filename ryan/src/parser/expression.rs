@@ -1,26 +1,41 @@
 use indexmap::IndexMap;
+#[cfg(feature = "parse")]
 use pest::{
     iterators::Pairs,
     pratt_parser::{Op, PrattParser},
 };
-use std::{cell::RefCell, fmt::Display, rc::Rc};
-
-use crate::{rc_world, utils::QuotedStr};
+#[cfg(feature = "parse")]
+use std::{cell::RefCell, collections::HashSet};
+use std::{collections::HashMap, fmt::Display, rc::Rc};
+
+use crate::{
+    environment::{Branch, DuplicateKeyPolicy, EvalEvent, Origin},
+    rc_world,
+    utils::QuotedStr,
+};
 
-use super::State;
-use super::{comprehension::DictComprehension, ErrorLogger};
-use super::{comprehension::ListComprehension, operation::BinaryOperator};
+use super::block::Block;
+use super::comprehension::DictComprehension;
+use super::comprehension::ListComprehension;
+use super::format::Printer;
+use super::lambda::Lambda;
+use super::matching::Match;
+use super::template_string::TemplateString;
+use super::types::TypeExpression;
 use super::{import::Import, operation::BinaryOperation};
+use super::{literal::Literal, operation::PrefixOperation};
 use super::{
-    literal::Literal,
-    operation::{PrefixOperation, PrefixOperator},
+    operation::PostfixOperation,
+    value::{PathSeg, Value},
 };
+#[cfg(feature = "parse")]
 use super::{
-    operation::{PostfixOperation, PostfixOperator},
-    value::Value,
+    operation::{BinaryOperator, PostfixOperator, PrefixOperator},
+    ErrorLogger, Rule, WarningKind,
 };
-use super::{template_string::TemplateString, Rule};
+use super::{EvalErrorKind, State};
 
+#[cfg(feature = "parse")]
 lazy_static::lazy_static! {
     static ref PRATT_PARSER: PrattParser<Rule> = {
         use pest::pratt_parser::Assoc::*;
@@ -29,28 +44,38 @@ lazy_static::lazy_static! {
             .op(Op::infix(Rule::orOp, Left))
             .op(Op::infix(Rule::andOp, Left))
             .op(Op::prefix(Rule::notOp))
+            .op(Op::infix(Rule::pipeOp, Left))
             .op(
                 Op::infix(Rule::equalsOp, Left)
                 | Op::infix(Rule::notEqualsOp, Left)
+                | Op::infix(Rule::isOp, Left)
                 | Op::infix(Rule::typeMatchesOp, Left)
                 | Op::infix(Rule::greaterOp, Left)
                 | Op::infix(Rule::greaterEqualOp, Left)
                 | Op::infix(Rule::lesserOp, Left)
                 | Op::infix(Rule::lesserEqualOp, Left)
                 | Op::infix(Rule::isContainedOp, Left)
+                | Op::infix(Rule::isNotContainedOp, Left)
             )
             .op(Op::infix(Rule::plusOp, Left) | Op::infix(Rule::minusOp, Left))
             .op(Op::infix(Rule::remainderOp, Left))
             .op(Op::infix(Rule::timesOp, Left) | Op::infix(Rule::dividedOp, Left))
+            .op(Op::prefix(Rule::negativeOp) | Op::prefix(Rule::positiveOp))
             .op(Op::infix(Rule::defaultOp, Left))
             .op(Op::infix(Rule::juxtapositionOp, Right))
-            .op(Op::postfix(Rule::accessOp))
+            .op(
+                Op::postfix(Rule::accessOp)
+                | Op::postfix(Rule::pathOp)
+                | Op::postfix(Rule::optionalAccessOp)
+                | Op::postfix(Rule::optionalPathOp)
+            )
             .op(Op::postfix(Rule::castInt) | Op::postfix(Rule::castFloat) | Op::postfix(Rule::castText))
     };
 }
 
 /// Transformations of Ryan values.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expression {
     /// Builds a list of Ryan values.
     List(List),
@@ -75,6 +100,16 @@ pub enum Expression {
     ListComprehension(Box<ListComprehension>),
     /// Creates a Ryan value from a dict comprehension.
     DictComprehension(Box<DictComprehension>),
+    /// Creates an anonymous pattern match.
+    Lambda(Box<Lambda>),
+    /// Evaluates the first arm whose pattern matches a value.
+    Match(Box<Match>),
+    /// Evaluates a block with its own local bindings, which are not visible outside of
+    /// it. This is represented by, e.g., `let x = 1; x + 1` in the middle of an
+    /// expression.
+    Scoped(Box<Block>),
+    /// Builds a Ryan type, as in the right-hand side of `x is int`.
+    Type(TypeExpression),
 }
 
 impl Default for Expression {
@@ -85,46 +120,30 @@ impl Default for Expression {
 
 impl Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::List(list) => {
-                write!(f, "[")?;
-                crate::utils::fmt_list(f, &list.items)?;
-                write!(f, "]")?;
-            }
-            Self::Dict(dict) => {
-                write!(f, "{{")?;
-                crate::utils::fmt_list(f, &dict.items)?;
-                write!(f, "}}")?;
-            }
-            Self::Literal(lit) => write!(f, "{lit}")?,
-            Self::TemplateString(template) => write!(f, "{template}")?,
-            Self::BinaryOperation(op) => write!(f, "{op}")?,
-            Self::PrefixOperation(op) => write!(f, "{op}")?,
-            Self::PostfixOperation(op) => write!(f, "{op}")?,
-            Self::Conditional(r#if, then, r#else) => {
-                write!(f, "if {} then {} else {}", r#if, r#then, r#else)?
-            }
-            Self::Import(import) => write!(f, "{import}")?,
-            Self::ListComprehension(comprehension) => write!(f, "{comprehension}")?,
-            Self::DictComprehension(comprehension) => write!(f, "{comprehension}")?,
-        }
-
-        Ok(())
+        f.write_str(&Printer::FLAT.expression(self, 0, 0))
     }
 }
 
 impl Expression {
+    #[cfg(feature = "parse")]
     pub(super) fn parse(logger: &mut ErrorLogger, pairs: Pairs<'_, Rule>) -> Self {
         let logger_cell = Rc::new(RefCell::new(logger));
+        let logger_cell_infix = logger_cell.clone();
         let logger_cell_postfix = logger_cell.clone();
 
         PRATT_PARSER
             .map_primary(|pair| match pair.as_rule() {
                 Rule::list => {
-                    Expression::List(List::parse(*logger_cell.borrow_mut(), pair.into_inner()))
+                    let mut logger = logger_cell.borrow_mut();
+                    let list = List::parse(*logger, pair.clone().into_inner());
+                    logger.lay_out(&pair, &list.items);
+                    Expression::List(list)
                 }
                 Rule::dict => {
-                    Expression::Dict(Dict::parse(*logger_cell.borrow_mut(), pair.into_inner()))
+                    let mut logger = logger_cell.borrow_mut();
+                    let dict = Dict::parse(*logger, pair.clone().into_inner());
+                    logger.lay_out(&pair, &dict.items);
+                    Expression::Dict(dict)
                 }
                 Rule::conditional => {
                     let mut pairs = pair.into_inner();
@@ -157,9 +176,33 @@ impl Expression {
                 Rule::dictComprehension => Expression::DictComprehension(Box::new(
                     DictComprehension::parse(*logger_cell.borrow_mut(), pair.into_inner()),
                 )),
+                Rule::lambda => Expression::Lambda(Box::new(Lambda::parse(
+                    *logger_cell.borrow_mut(),
+                    pair.into_inner(),
+                ))),
+                Rule::matchExpression => Expression::Match(Box::new(Match::parse(
+                    *logger_cell.borrow_mut(),
+                    pair.into_inner(),
+                ))),
+                Rule::scoped => Expression::Scoped(Box::new(Block::parse(
+                    *logger_cell.borrow_mut(),
+                    pair.into_inner(),
+                ))),
+                Rule::typeExpression => Expression::Type(TypeExpression::parse(
+                    *logger_cell.borrow_mut(),
+                    pair.into_inner(),
+                )),
                 _ => unreachable!(),
             })
-            .map_infix(|left, op, right| {
+            .map_infix(move |left, op, right| {
+                if op.as_rule() == Rule::typeMatchesOp {
+                    logger_cell_infix.borrow_mut().warn(
+                        &op,
+                        WarningKind::DeprecatedSyntax,
+                        "The `#` type test is deprecated, use `is` instead".to_string(),
+                    );
+                }
+
                 Expression::BinaryOperation(Box::new(BinaryOperation {
                     left,
                     op: BinaryOperator::parse(op),
@@ -208,29 +251,39 @@ impl Expression {
             }
             Self::PrefixOperation(op) => op.right.capture(state, provided, values)?,
             Self::PostfixOperation(op) => op.left.capture(state, provided, values)?,
-            Self::Import(_) => {}
+            Self::Import(import) => import.capture(state, provided, values)?,
             Self::ListComprehension(comprehension) => {
                 comprehension.capture(state, provided, values)?
             }
             Self::DictComprehension(comprehension) => {
                 comprehension.capture(state, provided, values)?
             }
+            Self::Lambda(lambda) => lambda.capture(state, provided, values)?,
+            Self::Match(r#match) => r#match.capture(state, provided, values)?,
+            Self::Scoped(block) => block.capture(state, provided, values)?,
+            Self::Type(type_expression) => type_expression.capture(state, provided, values)?,
         };
 
         Some(())
     }
 
     pub(super) fn eval(&self, state: &mut State<'_>) -> Option<Value> {
+        #[cfg(feature = "metrics")]
+        state.environment.counters().count_value();
+
         let returned = match self {
             Self::List(list) => list.eval(state)?,
             Self::Dict(dict) => dict.eval(state)?,
             Self::Conditional(r#if, then, r#else) => {
                 let if_evalued = r#if.eval(state)?;
-                let to_eval = if state.absorb(if_evalued.is_true())? {
-                    then
+                let (to_eval, branch) = if state.absorb(if_evalued.is_true())? {
+                    (then, Branch::Then)
                 } else {
-                    r#else
+                    (r#else, Branch::Else)
                 };
+                state
+                    .environment
+                    .trace(|| EvalEvent::ConditionalTaken { branch });
 
                 to_eval.eval(state)?
             }
@@ -242,6 +295,13 @@ impl Expression {
             Self::Import(import) => import.eval(state)?,
             Self::ListComprehension(comprehension) => comprehension.eval(state)?,
             Self::DictComprehension(comprehension) => comprehension.eval(state)?,
+            Self::Lambda(lambda) => lambda.eval(state)?,
+            Self::Match(r#match) => r#match.eval(state)?,
+            Self::Scoped(block) => {
+                let mut local = state.new_local(IndexMap::new());
+                block.eval(&mut local)?
+            }
+            Self::Type(type_expression) => Value::Type(type_expression.eval(state)?),
         };
 
         Some(returned)
@@ -250,18 +310,40 @@ impl Expression {
 
 /// An association of string values to Ryan values.
 #[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Dict {
     /// The entries of this association.
     pub items: Vec<DictItem>,
 }
 
 impl Dict {
+    #[cfg(feature = "parse")]
     fn parse(logger: &mut ErrorLogger, pairs: Pairs<'_, Rule>) -> Self {
         let mut items = vec![];
+        let mut literal_keys = HashSet::new();
 
         for pair in pairs {
             match pair.as_rule() {
-                Rule::dictItem => items.push(DictItem::parse(logger, pair.into_inner())),
+                Rule::dictItem => {
+                    let item = DictItem::parse(logger, pair.clone().into_inner());
+
+                    if let DictItem::KeyValue(KeyValue {
+                        key: DictKey::Literal(key),
+                        guard: None,
+                        ..
+                    }) = &item
+                    {
+                        if !literal_keys.insert(key.clone()) {
+                            logger.warn(
+                                &pair,
+                                WarningKind::DuplicateKey,
+                                format!("Key {key:?} is defined more than once in this dictionary"),
+                            );
+                        }
+                    }
+
+                    items.push(item);
+                }
                 _ => unreachable!(),
             }
         }
@@ -276,34 +358,62 @@ impl Dict {
         provided: &mut [Rc<str>],
         values: &mut IndexMap<Rc<str>, Value>,
     ) -> Option<()> {
+        let mut provided = provided.to_vec();
+        provided.push(rc_world::str_to_rc("self"));
+
         for item in &self.items {
-            item.capture(state, provided, values)?;
+            item.capture(state, &mut provided, values)?;
         }
 
         Some(())
     }
 
     pub(super) fn eval(&self, state: &mut State<'_>) -> Option<Value> {
-        let mut evald = IndexMap::new();
+        let mut evald = DictBuilder::new(state);
 
         for item in &self.items {
+            state.check_cancelled()?;
+            // The keys defined so far are visible to the next items as `self`:
+            let scope = IndexMap::from([(rc_world::str_to_rc("self"), evald.current())]);
+
             match item {
                 DictItem::KeyValue(kv) => {
+                    let mut local = state.new_local(scope);
+
                     if let Some(g) = &kv.guard {
-                        let tested = g.eval(state)?.is_true();
-                        if !state.absorb(tested)? {
+                        let tested = g.eval(&mut local)?.is_true();
+                        if !local.absorb(tested)? {
                             continue;
                         }
                     }
 
-                    evald.insert(rc_world::str_to_rc(&kv.key), kv.value.eval(state)?);
+                    let (key, origin) = match &kv.key {
+                        DictKey::Literal(key) => (key.clone(), KeyOrigin::Literal),
+                        DictKey::Computed(expr) => {
+                            let key = expr.eval(&mut local)?;
+                            (text_key(&mut local, key)?, KeyOrigin::Computed)
+                        }
+                    };
+                    let value = kv.value.eval(&mut local)?;
+                    let provenance = local.origin_of(&kv.value);
+                    drop(local);
+                    evald.insert(state, key, value, origin, provenance)?;
                 }
                 DictItem::FlattenExpression(expr) => {
-                    let returned = expr.eval(state)?;
+                    let returned = expr.eval(&mut state.new_local(scope))?;
                     match returned {
                         Value::Map(map) => {
+                            let flattened = Value::Map(map.clone());
                             for (key, value) in &*map {
-                                evald.insert(key.clone(), value.clone());
+                                let provenance =
+                                    flattened_origin(state, &flattened, PathSeg::Key(key.clone()));
+                                evald.insert(
+                                    state,
+                                    key.clone(),
+                                    value.clone(),
+                                    KeyOrigin::Flatten,
+                                    provenance,
+                                )?;
                             }
                         }
                         Value::List(list) => {
@@ -311,36 +421,190 @@ impl Dict {
                                 match item {
                                     Value::List(pair) if pair.len() == 2 => {
                                         if let Value::Text(key) = &pair[0] {
-                                            evald.insert(key.clone(), pair[1].clone());
+                                            evald.insert(
+                                                state,
+                                                key.clone(),
+                                                pair[1].clone(),
+                                                KeyOrigin::Flatten,
+                                                None,
+                                            )?;
                                         } else {
-                                            state.raise(format!(
-                                                "First element of key-pair list must be text, got {}",
-                                                pair[0].canonical_type()
-                                            ))?;
+                                            state.raise(EvalErrorKind::InvalidKeyPair {
+                                                item: item.clone(),
+                                            })?;
                                         }
                                     }
-                                    _ => state.raise(format!(
-                                        "Key-pair list must be [text, any], got {}",
-                                        item.canonical_type(),
-                                    ))?,
+                                    _ => state.raise(EvalErrorKind::InvalidKeyPair {
+                                        item: item.clone(),
+                                    })?,
                                 }
                             }
                         }
-                        val => state.raise(format!(
-                            "Flatten expression must be either a map or list of key-value pairs, got {}",
-                            val.canonical_type(),
-                        ))?,
+                        value => state.raise(EvalErrorKind::InvalidDictFlatten { value })?,
+                    }
+                }
+            }
+        }
+
+        Some(evald.build(state))
+    }
+}
+
+/// Records where a list or dictionary just built and each of its entries came from, if
+/// provenance is being tracked.
+pub(super) fn record_provenance(
+    state: &State<'_>,
+    built: &Value,
+    entries: impl IntoIterator<Item = (PathSeg, Origin)>,
+) {
+    if let (Some(tracker), Some(origin)) = (state.environment.provenance_tracker(), state.origin())
+    {
+        let mut tracker = tracker.borrow_mut();
+        tracker.record(built, origin);
+        tracker.record_entries(built, entries);
+    }
+}
+
+/// Returns where the item at a given position of a list or dictionary being iterated over
+/// by a comprehension came from, if provenance is being tracked.
+pub(super) fn iterated_origin(state: &State<'_>, iterable: &Value, idx: usize) -> Option<Origin> {
+    let seg = match iterable {
+        Value::Map(map) => PathSeg::Key(map.get_index(idx)?.0.clone()),
+        _ => PathSeg::Index(idx),
+    };
+    flattened_origin(state, iterable, seg)
+}
+
+/// Returns where an entry of a flattened list or dictionary came from, if provenance is
+/// being tracked: where it was put in the flattened value or else where that value was
+/// created.
+fn flattened_origin(state: &State<'_>, flattened: &Value, seg: PathSeg) -> Option<Origin> {
+    let tracker = state.environment.provenance_tracker()?.borrow();
+    tracker
+        .entry(flattened, seg)
+        .or_else(|| tracker.node(flattened))
+}
+
+/// Where a key in a dictionary under construction came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyOrigin {
+    /// A key-value pair written in the dictionary, e.g., `{ a: 1 }`.
+    Literal,
+    /// A key-value pair with a computed key, e.g., `{ [a]: 1 }`.
+    Computed,
+    /// A flatten expression, e.g., `{ ...other }`.
+    Flatten,
+    /// An iteration of a dictionary comprehension.
+    Comprehension,
+}
+
+impl Display for KeyOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Literal => write!(f, "a key-value pair"),
+            Self::Computed => write!(f, "a computed key"),
+            Self::Flatten => write!(f, "a flatten expression"),
+            Self::Comprehension => write!(f, "a dictionary comprehension"),
+        }
+    }
+}
+
+/// Checks that a value used as a dictionary key is text. Integers are turned into their
+/// decimal text, just like `fmt` does, since there is only one way of writing them.
+pub(super) fn text_key(state: &mut State<'_>, key: Value) -> Option<Rc<str>> {
+    match key {
+        Value::Text(key) => Some(key),
+        Value::Integer(int) => Some(rc_world::string_to_rc(int.to_string())),
+        key => {
+            state.raise(EvalErrorKind::InvalidKey { key })?;
+            None
+        }
+    }
+}
+
+/// Builds the entries of a dictionary, applying the [`DuplicateKeyPolicy`] of the
+/// environment when the same key is inserted twice.
+pub(super) struct DictBuilder {
+    policy: DuplicateKeyPolicy,
+    entries: Rc<IndexMap<Rc<str>, Value>>,
+    origins: HashMap<Rc<str>, KeyOrigin>,
+    /// Where the value of each key came from, if provenance is being tracked.
+    provenance: Option<HashMap<Rc<str>, Origin>>,
+}
+
+impl DictBuilder {
+    pub(super) fn new(state: &State<'_>) -> DictBuilder {
+        DictBuilder {
+            policy: state.environment.duplicate_key_policy(),
+            entries: Rc::default(),
+            origins: HashMap::new(),
+            provenance: state
+                .environment
+                .provenance_tracker()
+                .map(|_| HashMap::new()),
+        }
+    }
+
+    /// Inserts a key in the dictionary. The `provenance` of the value defaults to
+    /// [`State::origin`].
+    #[must_use]
+    pub(super) fn insert(
+        &mut self,
+        state: &mut State<'_>,
+        key: Rc<str>,
+        value: Value,
+        origin: KeyOrigin,
+        provenance: Option<Origin>,
+    ) -> Option<()> {
+        match (self.policy, self.origins.get(&key)) {
+            (_, None) | (DuplicateKeyPolicy::LastWins, Some(_)) => {
+                if let Some(origins) = &mut self.provenance {
+                    if let Some(provenance) = provenance.or_else(|| state.origin()) {
+                        origins.insert(key.clone(), provenance);
                     }
                 }
+                // Only clones the entries if `current` escaped, e.g., as a closure. A key
+                // set again keeps its position, as `insert` does not move existing keys:
+                Rc::make_mut(&mut self.entries).insert(key.clone(), value);
+                self.origins.insert(key, origin);
+            }
+            (DuplicateKeyPolicy::FirstWins, Some(_)) => {}
+            (DuplicateKeyPolicy::Error, Some(first)) => {
+                state.raise(EvalErrorKind::DuplicateKey {
+                    key,
+                    first: *first,
+                    then: origin,
+                })?;
             }
         }
 
-        Some(Value::Map(Rc::new(evald)))
+        Some(())
+    }
+
+    /// The dictionary built so far. This is cheap, as long as the value is dropped
+    /// before the next insertion.
+    pub(super) fn current(&self) -> Value {
+        Value::Map(self.entries.clone())
+    }
+
+    /// Builds the dictionary, recording where it and each of its values came from if
+    /// provenance is being tracked.
+    pub(super) fn build(self, state: &State<'_>) -> Value {
+        let built = Value::Map(self.entries);
+        let entries = self.provenance.into_iter().flatten();
+        record_provenance(
+            state,
+            &built,
+            entries.map(|(key, origin)| (PathSeg::Key(key), origin)),
+        );
+        built
     }
 }
 
-///
+/// An item in a dictionary expression: either a key-value pair or the expression of another
+/// dictionary to be flattened into this one.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DictItem {
     KeyValue(KeyValue),
     FlattenExpression(Expression),
@@ -348,16 +612,12 @@ pub enum DictItem {
 
 impl Display for DictItem {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            DictItem::KeyValue(kv) => write!(f, "{kv}")?,
-            DictItem::FlattenExpression(expr) => write!(f, "...{expr}")?,
-        }
-
-        Ok(())
+        f.write_str(&Printer::FLAT.dict_item(self, 0, 0))
     }
 }
 
 impl DictItem {
+    #[cfg(feature = "parse")]
     fn parse(logger: &mut ErrorLogger, mut pairs: Pairs<'_, Rule>) -> Self {
         let inner = pairs.next().expect("a dict item always has a token");
         match inner.as_rule() {
@@ -385,11 +645,32 @@ impl DictItem {
     }
 }
 
+/// The key of an entry of a dictionary expression.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DictKey {
+    /// A key written literally, e.g., `a` or `"a"` in `{ a: 1, "b": 2 }`.
+    Literal(Rc<str>),
+    /// A key computed from an expression which must evaluate to text, e.g., `[a]` or
+    /// `` `${a}-b` `` in ``{ [a]: 1, `${a}-b`: 2 }``.
+    Computed(Expression),
+}
+
+impl Display for DictKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Literal(key) => write!(f, "{}", QuotedStr(key)),
+            Self::Computed(expr) => write!(f, "[{expr}]"),
+        }
+    }
+}
+
 /// An entry of a dictionary expression.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct KeyValue {
-    /// The string value associated with the Ryan value.
-    pub key: Rc<str>,
+    /// The key associated with the Ryan value.
+    pub key: DictKey,
     /// The expression that evaluates to the value of this association.
     pub value: Expression,
     /// An optional `if` guard. If the supplied expression evaluates to `false`, the
@@ -399,17 +680,15 @@ pub struct KeyValue {
 
 impl Display for KeyValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if let Some(g) = &self.guard {
-            write!(f, "{}: {} if {}", QuotedStr(&self.key), self.value, g)
-        } else {
-            write!(f, "{}: {}", QuotedStr(&self.key), self.value)
-        }
+        f.write_str(&Printer::FLAT.key_value(self, 0, 0))
     }
 }
 
 impl KeyValue {
+    #[cfg(feature = "parse")]
     fn parse(logger: &mut ErrorLogger, pairs: Pairs<'_, Rule>) -> Self {
         let mut key = None;
+        let mut computed_key = None;
         let mut value = None;
         let mut guard = None;
 
@@ -418,7 +697,20 @@ impl KeyValue {
                 Rule::identifier => key = Some(rc_world::str_to_rc(pair.as_str())),
                 Rule::text => {
                     key = Some(rc_world::string_to_rc(
-                        logger.absorb(&pair, crate::utils::unescape(pair.as_str())),
+                        logger.absorb(&pair, crate::text::unescape(pair.as_str())),
+                    ));
+                }
+                Rule::computedKey => {
+                    computed_key =
+                        Some((pair.clone(), Expression::parse(logger, pair.into_inner())));
+                }
+                Rule::templateString => {
+                    computed_key = Some((
+                        pair.clone(),
+                        Expression::TemplateString(TemplateString::parse(
+                            logger,
+                            pair.into_inner(),
+                        )),
                     ));
                 }
                 Rule::expression => value = Some(Expression::parse(logger, pair.into_inner())),
@@ -435,11 +727,26 @@ impl KeyValue {
             }
         }
 
+        if let Some((pair, expr)) = computed_key {
+            if value.is_none() {
+                logger.absorb(
+                    &pair,
+                    Err::<(), _>("A computed key must be followed by a value, like `[key]: value`"),
+                );
+            }
+
+            return KeyValue {
+                key: DictKey::Computed(expr),
+                value: value.unwrap_or_default(),
+                guard,
+            };
+        }
+
         let key = key.expect("there is always a key in dict item");
 
         KeyValue {
             value: value.unwrap_or_else(|| Expression::Literal(Literal::Identifier(key.clone()))),
-            key,
+            key: DictKey::Literal(key),
             guard,
         }
     }
@@ -451,6 +758,9 @@ impl KeyValue {
         provided: &mut [Rc<str>],
         values: &mut IndexMap<Rc<str>, Value>,
     ) -> Option<()> {
+        if let DictKey::Computed(expr) = &self.key {
+            expr.capture(state, provided, values)?;
+        }
         self.value.capture(state, provided, values)?;
         if let Some(g) = &self.guard {
             g.capture(state, provided, values)?;
@@ -461,11 +771,13 @@ impl KeyValue {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct List {
-    items: Vec<ListItem>,
+    pub(super) items: Vec<ListItem>,
 }
 
 impl List {
+    #[cfg(feature = "parse")]
     fn parse(logger: &mut ErrorLogger, pairs: Pairs<'_, Rule>) -> Self {
         let mut items = vec![];
 
@@ -495,16 +807,30 @@ impl List {
 
     pub(super) fn eval(&self, state: &mut State<'_>) -> Option<Value> {
         let mut evald = vec![];
+        let mut provenance = vec![];
 
         for item in &self.items {
+            state.check_cancelled()?;
             match item {
                 ListItem::Item(item) => {
                     evald.push(item.eval(state)?);
+                    if let Some(origin) = state.origin_of(item) {
+                        provenance.push((PathSeg::Index(evald.len() - 1), origin));
+                    }
                 }
                 ListItem::FlattenExpression(expr) => {
                     let returned = expr.eval(state)?;
                     match returned {
-                        Value::List(list) => evald.extend(list.iter().cloned()),
+                        Value::List(list) => {
+                            let flattened = Value::List(list.clone());
+                            for (idx, item) in list.iter().enumerate() {
+                                let seg = PathSeg::Index(idx);
+                                if let Some(origin) = flattened_origin(state, &flattened, seg) {
+                                    provenance.push((PathSeg::Index(evald.len()), origin));
+                                }
+                                evald.push(item.clone());
+                            }
+                        }
                         Value::Map(map) => {
                             for (key, value) in &*map {
                                 evald.push(Value::List(
@@ -512,20 +838,22 @@ impl List {
                                 ));
                             }
                         }
-                        val => state.raise(format!(
-                            "Flatten expression must be either a map or a list, got {}",
-                            val.canonical_type(),
-                        ))?,
+                        value => state.raise(EvalErrorKind::InvalidListFlatten { value })?,
                     }
                 }
             }
         }
 
-        Some(Value::List(evald.into()))
+        let built = Value::List(evald.into());
+        record_provenance(state, &built, provenance);
+
+        Some(built)
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(not(feature = "parse"), allow(dead_code))]
 pub enum ListItem {
     Item(Expression),
     FlattenExpression(Expression),
@@ -533,16 +861,12 @@ pub enum ListItem {
 
 impl Display for ListItem {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ListItem::Item(item) => write!(f, "{item}")?,
-            ListItem::FlattenExpression(expr) => write!(f, "...{expr}")?,
-        }
-
-        Ok(())
+        f.write_str(&Printer::FLAT.list_item(self, 0, 0))
     }
 }
 
 impl ListItem {
+    #[cfg(feature = "parse")]
     fn parse(logger: &mut ErrorLogger, mut pairs: Pairs<'_, Rule>) -> Self {
         let inner = pairs.next().expect("a dict item always has a token");
         match inner.as_rule() {
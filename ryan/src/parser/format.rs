@@ -0,0 +1,829 @@
+use std::collections::HashMap;
+
+use crate::utils::QuotedStr;
+
+use super::binding::Binding;
+use super::block::Block;
+use super::comprehension::{
+    DictComprehension, ForClause, IfGuard, KeyValueClause, ListComprehension,
+};
+use super::expression::{DictItem, DictKey, Expression, KeyValue, ListItem};
+use super::import::{Format, Import};
+use super::lambda::Lambda;
+use super::literal::Literal;
+use super::matching::{Match, MatchArm};
+use super::operation::{
+    BinaryOperation, BinaryOperator, PostfixOperation, PostfixOperator, PrefixOperation,
+    PrefixOperator,
+};
+use super::template_string::{TemplateString, TemplateStringChunk};
+
+/// The number of spaces in each level of indentation.
+const INDENT: usize = 4;
+
+// How tightly each kind of expression binds, from loosest to tightest, as in the Pratt
+// parser. An operand binding less tightly than its operator needs parentheses.
+const LOOSEST: u8 = 0;
+const OR: u8 = 1;
+const AND: u8 = 2;
+const NOT: u8 = 3;
+const PIPE: u8 = 4;
+const COMPARISON: u8 = 5;
+const SUM: u8 = 6;
+const REMAINDER: u8 = 7;
+const PRODUCT: u8 = 8;
+const SIGN: u8 = 9;
+const DEFAULT: u8 = 10;
+const JUXTAPOSITION: u8 = 11;
+const ACCESS: u8 = 12;
+const CAST: u8 = 13;
+const ATOM: u8 = 14;
+
+/// The words that cannot be used as identifiers, and thus as bare dictionary keys.
+const RESERVED: &[&str] = &[
+    "_", "true", "false", "and", "or", "not", "if", "then", "else", "let", "for", "int", "in",
+    "null", "import", "as", "text", "type", "bool", "float", "number", "any",
+];
+
+/// Where things are in the source code, so that the formatter can put the comments back
+/// next to them.
+#[cfg(feature = "parse")]
+#[derive(Debug, Default)]
+pub(crate) struct Layout {
+    /// The top-level bindings and the final expression, in order.
+    pub(crate) items: Vec<(usize, usize)>,
+    /// The non-empty lists and dictionaries, by the [`key`] of their items.
+    pub(crate) collections: HashMap<usize, Collection>,
+}
+
+#[cfg(feature = "parse")]
+impl Layout {
+    pub(super) fn record<T>(&mut self, items: &[T], collection: Collection) {
+        if !items.is_empty() {
+            self.collections.insert(key(items), collection);
+        }
+    }
+}
+
+/// Where a list or a dictionary and each of its items are in the source code.
+#[cfg(feature = "parse")]
+#[derive(Debug)]
+pub(crate) struct Collection {
+    pub(crate) span: (usize, usize),
+    pub(crate) items: Vec<(usize, usize)>,
+}
+
+/// The comments inside lists and dictionaries, by the [`key`] of their items.
+pub(crate) type Comments = HashMap<usize, ItemComments>;
+
+/// The comments inside a list or a dictionary.
+#[derive(Debug, Default)]
+#[cfg_attr(not(feature = "parse"), allow(dead_code))]
+pub(crate) struct ItemComments {
+    /// The comments in their own lines before each item.
+    pub(crate) leading: Vec<Vec<String>>,
+    /// The comment in the same line after each item, if any.
+    pub(crate) trailing: Vec<Option<String>>,
+    /// The comments after the last item.
+    pub(crate) closing: Vec<String>,
+}
+
+/// Tells lists and dictionaries apart by the address of their items, which stays the same
+/// for as long as the syntax tree is not changed. All empty collections have the same
+/// address, so they are never recorded.
+fn key<T>(items: &[T]) -> usize {
+    items.as_ptr() as usize
+}
+
+/// Prints Ryan code out of the abstract syntax tree, breaking lists, dictionaries and
+/// comprehensions across many lines when they don't fit in the maximum width.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Printer<'a> {
+    /// The maximum width of a line, if it can be helped.
+    pub(crate) width: usize,
+    /// The comments to put back inside lists and dictionaries, if any.
+    pub(crate) comments: Option<&'a Comments>,
+}
+
+impl<'a> Printer<'a> {
+    /// A printer that never breaks anything. This is how the syntax tree is displayed.
+    pub(crate) const FLAT: Printer<'static> = Printer {
+        width: usize::MAX,
+        comments: None,
+    };
+
+    /// A printer that never breaks anything, except for the lists and dictionaries with
+    /// comments inside.
+    fn flat(self) -> Printer<'a> {
+        Printer {
+            width: usize::MAX,
+            ..self
+        }
+    }
+
+    /// The comments inside a list or a dictionary, given its items.
+    fn comments<T>(self, items: &[T]) -> Option<&'a ItemComments> {
+        self.comments?.get(&key(items))
+    }
+
+    /// Prints the items of a list or a dictionary each in its own line, with a trailing
+    /// comma and with the comments around them.
+    fn broken(
+        self,
+        (open, close): (&str, &str),
+        items: Vec<String>,
+        comments: Option<&ItemComments>,
+        indent: usize,
+    ) -> String {
+        let mut code = format!("{open}\n");
+        for (i, item) in items.iter().enumerate() {
+            if let Some(comments) = comments {
+                for comment in &comments.leading[i] {
+                    code += &format!("{}{comment}\n", pad(indent + 1));
+                }
+            }
+            code += &pad(indent + 1);
+            code += item;
+            code += ",";
+            if let Some(comment) = comments.and_then(|comments| comments.trailing[i].as_ref()) {
+                code += "  ";
+                code += comment;
+            }
+            code += "\n";
+        }
+        if let Some(comments) = comments {
+            for comment in &comments.closing {
+                code += &format!("{}{comment}\n", pad(indent + 1));
+            }
+        }
+        code += &pad(indent);
+        code + close
+    }
+
+    /// Whether a piece of code fits in a single line, starting at a given column.
+    fn fits(self, column: usize, code: &str) -> bool {
+        !code.contains('\n') && column.saturating_add(code.len()) <= self.width
+    }
+
+    /// Prints a block, whose first line starts at the current indentation. A block whose
+    /// final expression was left out, e.g., a file with only type definitions, is printed
+    /// without one.
+    pub(crate) fn block(self, block: &Block, indent: usize) -> String {
+        let mut code = String::new();
+
+        for (i, binding) in block.bindings.iter().enumerate() {
+            if i > 0 {
+                code += "\n";
+                code += &pad(indent);
+            }
+            code += &self.binding(binding, indent);
+            code += ";";
+        }
+
+        if block.has_expression {
+            if !block.bindings.is_empty() {
+                code += "\n";
+                code += &pad(indent);
+            }
+            code += &self.expression(&block.expression, indent, indent * INDENT);
+        }
+
+        code
+    }
+
+    /// Prints a binding, without the trailing `;`.
+    pub(crate) fn binding(self, binding: &Binding, indent: usize) -> String {
+        match binding {
+            Binding::PatternMatchDefinition {
+                identifier,
+                pattern,
+                guard,
+                block,
+            } => {
+                let mut head = format!("let {identifier} {pattern}");
+                if let Some(guard) = guard {
+                    head += " if ";
+                    let column = column_after(indent * INDENT, &head);
+                    head += &self.expression(guard, indent, column);
+                }
+                self.definition(head, block, indent)
+            }
+            Binding::Destructuring {
+                pattern,
+                block,
+                fallback,
+            } => {
+                let mut code = self.definition(format!("let {pattern}"), block, indent);
+                if let Some(fallback) = fallback {
+                    code += " else ";
+                    let column = column_after(indent * INDENT, &code);
+                    code += &self.expression(fallback, indent, column);
+                }
+                code
+            }
+            Binding::TypeDefinition {
+                identifier,
+                type_expression,
+            } => format!("type {identifier} = {type_expression}"),
+            Binding::Use { path } => format!("use {}", QuotedStr(path)),
+        }
+    }
+
+    fn definition(self, head: String, block: &Block, indent: usize) -> String {
+        if block.bindings.is_empty() {
+            let head = head + " = ";
+            let column = column_after(indent * INDENT, &head);
+            head + &self.expression(&block.expression, indent, column)
+        } else {
+            format!(
+                "{head} =\n{}{}",
+                pad(indent + 1),
+                self.block(block, indent + 1)
+            )
+        }
+    }
+
+    /// Prints an expression starting at a given column.
+    pub(crate) fn expression(
+        self,
+        expression: &Expression,
+        indent: usize,
+        column: usize,
+    ) -> String {
+        match expression {
+            Expression::List(list) => self.list(&list.items, indent, column),
+            Expression::Dict(dict) => self.dict(&dict.items, indent, column),
+            Expression::Literal(literal) => literal.to_string(),
+            Expression::TemplateString(template) => self.template(template),
+            Expression::BinaryOperation(operation) => {
+                self.binary_operation(operation, indent, column)
+            }
+            Expression::PrefixOperation(operation) => {
+                self.prefix_operation(operation, indent, column)
+            }
+            Expression::PostfixOperation(operation) => {
+                self.postfix_operation(operation, indent, column)
+            }
+            Expression::Conditional(r#if, then, r#else) => {
+                let mut code = "if ".to_string();
+                let at = column_after(column, &code);
+                code += &self.expression(r#if, indent, at);
+                code += " then ";
+                let at = column_after(column, &code);
+                code += &self.expression(then, indent, at);
+                code += " else ";
+                let at = column_after(column, &code);
+                code += &self.expression(r#else, indent, at);
+                code
+            }
+            Expression::Import(import) => self.import(import, indent, column),
+            Expression::ListComprehension(comprehension) => {
+                self.list_comprehension(comprehension, indent, column)
+            }
+            Expression::DictComprehension(comprehension) => {
+                self.dict_comprehension(comprehension, indent, column)
+            }
+            Expression::Lambda(lambda) => self.lambda(lambda, indent, column),
+            Expression::Match(r#match) => self.r#match(r#match, indent, column),
+            Expression::Scoped(block) => self.scoped(block, indent, column),
+            Expression::Type(type_expression) => type_expression.to_string(),
+        }
+    }
+
+    pub(crate) fn binary_operation(
+        self,
+        operation: &BinaryOperation,
+        indent: usize,
+        column: usize,
+    ) -> String {
+        let precedence = binary_precedence(operation.op);
+        let (left_min, right_min) = if operation.op == BinaryOperator::Juxtaposition {
+            (precedence + 1, precedence)
+        } else {
+            (precedence, precedence + 1)
+        };
+
+        // `import "x" or y` would read `y` as the default of the import:
+        let left_min = match (&operation.left, operation.op) {
+            (Expression::Import(_), BinaryOperator::Or) => ATOM + 1,
+            _ => left_min,
+        };
+        // `f -1` would read as `f - 1` and `f [1]` as the path `f[1]`:
+        let right_min = match (&operation.right, operation.op) {
+            (Expression::List(_), BinaryOperator::Juxtaposition) => ATOM + 1,
+            (right, BinaryOperator::Juxtaposition) if is_negative(right) => ATOM + 1,
+            _ => right_min,
+        };
+
+        let mut code = self.operand(&operation.left, left_min, indent, column);
+        match operation.op {
+            BinaryOperator::Juxtaposition => code += " ",
+            // The `Display` of this one is not the token in the grammar:
+            BinaryOperator::TypeMatches => code += " is ",
+            op => code += &format!(" {op} "),
+        }
+        let column = column_after(column, &code);
+        code + &self.operand(&operation.right, right_min, indent, column)
+    }
+
+    pub(crate) fn prefix_operation(
+        self,
+        operation: &PrefixOperation,
+        indent: usize,
+        column: usize,
+    ) -> String {
+        if operation.op == PrefixOperator::Not {
+            let code = format!("{} ", operation.op);
+            let column = column_after(column, &code);
+            return code + &self.operand(&operation.right, NOT, indent, column);
+        }
+
+        let right = self.operand(&operation.right, SIGN, indent, column + 1);
+        // `- 1` would read as `-1` and `- -x` as `--x`:
+        if right.starts_with(|ch: char| ch.is_ascii_digit() || ch == '-' || ch == '+') {
+            format!("{} {right}", operation.op)
+        } else {
+            format!("{}{right}", operation.op)
+        }
+    }
+
+    pub(crate) fn postfix_operation(
+        self,
+        operation: &PostfixOperation,
+        indent: usize,
+        column: usize,
+    ) -> String {
+        let code = self.operand(&operation.left, ACCESS, indent, column);
+        let column = column_after(column, &code);
+        let op = self.postfix(&operation.op, indent, column);
+        code + &op
+    }
+
+    pub(crate) fn lambda(self, lambda: &Lambda, indent: usize, column: usize) -> String {
+        let code = format!("\\{} => ", lambda.pattern);
+        let column = column_after(column, &code);
+        code + &self.expression(&lambda.body, indent, column)
+    }
+
+    fn scoped(self, block: &Block, indent: usize, column: usize) -> String {
+        let mut code = "(".to_string();
+        for binding in &block.bindings {
+            code += &self.flat().binding(binding, indent);
+            code += "; ";
+        }
+        let column = column_after(column, &code);
+        code += &self.expression(&block.expression, indent, column);
+        code + ")"
+    }
+
+    /// Prints an expression, wrapping it in parentheses if it binds less tightly than
+    /// `min_precedence`.
+    fn operand(
+        self,
+        expression: &Expression,
+        min_precedence: u8,
+        indent: usize,
+        column: usize,
+    ) -> String {
+        if precedence(expression) < min_precedence {
+            format!("({})", self.expression(expression, indent, column + 1))
+        } else {
+            self.expression(expression, indent, column)
+        }
+    }
+
+    pub(crate) fn template(self, template: &TemplateString) -> String {
+        let mut code = "`".to_string();
+
+        for chunk in &template.chunks {
+            match chunk {
+                TemplateStringChunk::Text(text) => {
+                    for ch in text.chars() {
+                        match ch {
+                            '`' => code += "\\`",
+                            '$' => code += "\\$",
+                            ch => code.push(ch),
+                        }
+                    }
+                }
+                TemplateStringChunk::Interpolation(expression, spec) => {
+                    code += "${";
+                    code += &self.flat().expression(expression, 0, 0);
+                    if let Some(spec) = spec {
+                        code += &format!(":{spec}");
+                    }
+                    code += "}";
+                }
+            }
+        }
+
+        code + "`"
+    }
+
+    pub(crate) fn postfix(self, op: &PostfixOperator, indent: usize, column: usize) -> String {
+        match op {
+            PostfixOperator::Access(field) => format!(".{field}"),
+            PostfixOperator::OptionalAccess(field) => format!("?.{field}"),
+            PostfixOperator::Path(path) => {
+                format!("[{}]", self.expressions(path, indent, column + 1))
+            }
+            PostfixOperator::OptionalPath(path) => {
+                format!("?[{}]", self.expressions(path, indent, column + 2))
+            }
+            PostfixOperator::CastInt => " as int".to_string(),
+            PostfixOperator::CastFloat => " as float".to_string(),
+            PostfixOperator::CastText => " as text".to_string(),
+        }
+    }
+
+    fn expressions(self, expressions: &[Expression], indent: usize, column: usize) -> String {
+        let mut code = String::new();
+
+        for (i, expression) in expressions.iter().enumerate() {
+            if i > 0 {
+                code += ", ";
+            }
+            let column = column_after(column, &code);
+            code += &self.expression(expression, indent, column);
+        }
+
+        code
+    }
+
+    pub(crate) fn import(self, import: &Import, indent: usize, column: usize) -> String {
+        let mut code = format!("import {}", QuotedStr(&import.path));
+
+        match import.format {
+            Format::Ryan => {}
+            Format::Text => code += " as text",
+            Format::Bytes => code += " as bytes",
+        }
+
+        if let Some(args) = &import.args {
+            code += " with ";
+            let at = column_after(column, &code);
+            code += &self.operand(args, ATOM, indent, at);
+        }
+
+        if let Some(integrity) = &import.integrity {
+            code += &format!(" integrity {}", QuotedStr(integrity));
+        }
+
+        if let Some(only) = &import.only {
+            let keys = only.iter().map(|key| QuotedStr(key).quote());
+            code += &format!(" only [{}]", keys.collect::<Vec<_>>().join(", "));
+        }
+
+        if let Some(default) = &import.default {
+            code += " or ";
+            let at = column_after(column, &code);
+            code += &self.expression(default, indent, at);
+        }
+
+        code
+    }
+
+    fn list(self, items: &[ListItem], indent: usize, column: usize) -> String {
+        let flat = format!(
+            "[{}]",
+            items
+                .iter()
+                .map(|item| self.flat().list_item(item, 0, 0))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        let comments = self.comments(items);
+        if items.is_empty() || (comments.is_none() && self.fits(column, &flat)) {
+            return flat;
+        }
+
+        let items = items
+            .iter()
+            .map(|item| self.list_item(item, indent + 1, (indent + 1) * INDENT))
+            .collect();
+        self.broken(("[", "]"), items, comments, indent)
+    }
+
+    pub(crate) fn list_item(self, item: &ListItem, indent: usize, column: usize) -> String {
+        match item {
+            ListItem::Item(expression) => self.expression(expression, indent, column),
+            ListItem::FlattenExpression(expression) => {
+                "...".to_string() + &self.expression(expression, indent, column + 3)
+            }
+        }
+    }
+
+    fn dict(self, items: &[DictItem], indent: usize, column: usize) -> String {
+        if items.is_empty() {
+            return "{}".to_string();
+        }
+
+        let flat = format!(
+            "{{ {} }}",
+            items
+                .iter()
+                .map(|item| self.flat().dict_item(item, 0, 0))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        let comments = self.comments(items);
+        if comments.is_none() && self.fits(column, &flat) {
+            return flat;
+        }
+
+        let items = items
+            .iter()
+            .map(|item| self.dict_item(item, indent + 1, (indent + 1) * INDENT))
+            .collect();
+        self.broken(("{", "}"), items, comments, indent)
+    }
+
+    pub(crate) fn r#match(self, r#match: &Match, indent: usize, column: usize) -> String {
+        let head = "match ".to_string();
+        let at = column_after(column, &head);
+        let head = head + &self.expression(&r#match.scrutinee, indent, at) + " {";
+
+        let flat = format!(
+            "{head} {} }}",
+            r#match
+                .arms
+                .iter()
+                .map(|arm| self.flat().match_arm(arm, 0, 0))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        if self.fits(column, &flat) {
+            return flat;
+        }
+
+        let mut code = head + "\n";
+        for arm in &r#match.arms {
+            code += &pad(indent + 1);
+            code += &self.match_arm(arm, indent + 1, (indent + 1) * INDENT);
+            code += ",\n";
+        }
+        code += &pad(indent);
+        code + "}"
+    }
+
+    pub(crate) fn match_arm(self, arm: &MatchArm, indent: usize, column: usize) -> String {
+        let mut code = arm.pattern.to_string();
+        if let Some(guard) = &arm.guard {
+            code += " if ";
+            let at = column_after(column, &code);
+            code += &self.expression(guard, indent, at);
+        }
+        code += " => ";
+        let at = column_after(column, &code);
+        code + &self.expression(&arm.body, indent, at)
+    }
+
+    pub(crate) fn dict_item(self, item: &DictItem, indent: usize, column: usize) -> String {
+        match item {
+            DictItem::KeyValue(key_value) => self.key_value(key_value, indent, column),
+            DictItem::FlattenExpression(expression) => {
+                "...".to_string() + &self.expression(expression, indent, column + 3)
+            }
+        }
+    }
+
+    pub(crate) fn key_value(self, key_value: &KeyValue, indent: usize, column: usize) -> String {
+        let mut code = match (&key_value.key, &key_value.value) {
+            // Shorthand for `{ x: x }`:
+            (DictKey::Literal(key), Expression::Literal(Literal::Identifier(id)))
+                if key == id && is_identifier(key) =>
+            {
+                key.to_string()
+            }
+            (key, value) => {
+                let mut code = match key {
+                    DictKey::Literal(key) if is_identifier(key) => key.to_string(),
+                    DictKey::Literal(key) => QuotedStr(key).to_string(),
+                    DictKey::Computed(key) => {
+                        format!("[{}]", self.expression(key, indent, column + 1))
+                    }
+                };
+                code += ": ";
+                let column = column_after(column, &code);
+                code += &self.expression(value, indent, column);
+                code
+            }
+        };
+
+        if let Some(guard) = &key_value.guard {
+            code += " if ";
+            let column = column_after(column, &code);
+            code += &self.expression(guard, indent, column);
+        }
+
+        code
+    }
+
+    pub(crate) fn list_comprehension(
+        self,
+        comprehension: &ListComprehension,
+        indent: usize,
+        column: usize,
+    ) -> String {
+        let flat = format!(
+            "[{}{}]",
+            self.flat().expression(&comprehension.expression, 0, 0),
+            self.flat().clauses(
+                &comprehension.for_clauses,
+                comprehension.if_guard.as_ref(),
+                0
+            ),
+        );
+
+        if self.fits(column, &flat) {
+            return flat;
+        }
+
+        format!(
+            "[\n{}{}{}\n{}]",
+            pad(indent + 1),
+            self.expression(&comprehension.expression, indent + 1, (indent + 1) * INDENT),
+            self.clauses(
+                &comprehension.for_clauses,
+                comprehension.if_guard.as_ref(),
+                indent + 1
+            ),
+            pad(indent),
+        )
+    }
+
+    pub(crate) fn dict_comprehension(
+        self,
+        comprehension: &DictComprehension,
+        indent: usize,
+        column: usize,
+    ) -> String {
+        let clause = &comprehension.key_value_clause;
+        let flat = format!(
+            "{{ {}{} }}",
+            self.flat().key_value_clause(clause, 1, INDENT),
+            self.flat().clauses(
+                &comprehension.for_clauses,
+                comprehension.if_guard.as_ref(),
+                0
+            ),
+        );
+
+        if self.fits(column, &flat) {
+            return flat;
+        }
+
+        format!(
+            "{{\n{}{}{}\n{}}}",
+            pad(indent + 1),
+            self.key_value_clause(clause, indent + 1, (indent + 1) * INDENT),
+            self.clauses(
+                &comprehension.for_clauses,
+                comprehension.if_guard.as_ref(),
+                indent + 1
+            ),
+            pad(indent),
+        )
+    }
+
+    pub(crate) fn key_value_clause(
+        self,
+        clause: &KeyValueClause,
+        indent: usize,
+        column: usize,
+    ) -> String {
+        let key = self.expression(&clause.key, indent, column);
+        let column = column_after(column, &key) + 2;
+        format!("{key}: {}", self.expression(&clause.value, indent, column))
+    }
+
+    /// Prints the `for` and `if` clauses of a comprehension, either in the same line (when
+    /// flat) or each in its own line.
+    fn clauses(
+        self,
+        for_clauses: &[ForClause],
+        if_guard: Option<&IfGuard>,
+        indent: usize,
+    ) -> String {
+        let separator = if self.width == usize::MAX {
+            " ".to_string()
+        } else {
+            format!("\n{}", pad(indent))
+        };
+        let mut code = String::new();
+
+        for clause in for_clauses {
+            code += &separator;
+            let patterns = clause.patterns();
+            let mut head = "for ".to_string();
+            for (idx, pattern) in patterns.iter().enumerate() {
+                if idx > 0 {
+                    head += ", ";
+                }
+                head += &pattern.to_string();
+            }
+            head += " in ";
+
+            let mut iterables = String::new();
+            let iterables_column = column_after(indent * INDENT, &head);
+            for (idx, iterable) in std::iter::once(&clause.expression)
+                .chain(&clause.zipped)
+                .enumerate()
+            {
+                if idx > 0 {
+                    iterables += ", ";
+                }
+                let column = column_after(iterables_column, &iterables);
+                iterables += &self.expression(iterable, indent, column);
+            }
+
+            code += &head;
+            code += &iterables;
+        }
+
+        if let Some(guard) = if_guard {
+            code += &separator;
+            code += "if ";
+            code += &self.expression(&guard.predicate, indent, indent * INDENT + 3);
+        }
+
+        code
+    }
+}
+
+/// How tightly an expression binds.
+fn precedence(expression: &Expression) -> u8 {
+    match expression {
+        Expression::BinaryOperation(operation) => binary_precedence(operation.op),
+        Expression::PrefixOperation(operation) => match operation.op {
+            PrefixOperator::Not => NOT,
+            PrefixOperator::Negative | PrefixOperator::Positive => SIGN,
+        },
+        Expression::PostfixOperation(operation) => match operation.op {
+            PostfixOperator::CastInt | PostfixOperator::CastFloat | PostfixOperator::CastText => {
+                CAST
+            }
+            _ => ACCESS,
+        },
+        Expression::Conditional(..) | Expression::Lambda(_) => LOOSEST,
+        Expression::Import(import) if import.default.is_some() => LOOSEST,
+        _ => ATOM,
+    }
+}
+
+fn binary_precedence(op: BinaryOperator) -> u8 {
+    match op {
+        BinaryOperator::Or => OR,
+        BinaryOperator::And => AND,
+        BinaryOperator::Pipe => PIPE,
+        BinaryOperator::Equals
+        | BinaryOperator::NotEquals
+        | BinaryOperator::TypeMatches
+        | BinaryOperator::GreaterThen
+        | BinaryOperator::GreaterEqual
+        | BinaryOperator::LesserThen
+        | BinaryOperator::LesserEqual
+        | BinaryOperator::IsContainedIn
+        | BinaryOperator::IsNotContainedIn => COMPARISON,
+        BinaryOperator::Plus | BinaryOperator::Minus => SUM,
+        BinaryOperator::Remainder => REMAINDER,
+        BinaryOperator::Times | BinaryOperator::Divided => PRODUCT,
+        BinaryOperator::Default => DEFAULT,
+        BinaryOperator::Juxtaposition => JUXTAPOSITION,
+    }
+}
+
+/// Whether an expression is a negative number literal, which starts with a `-`.
+fn is_negative(expression: &Expression) -> bool {
+    match expression {
+        Expression::Literal(Literal::Integer(int)) => *int < 0,
+        Expression::Literal(Literal::Float(float)) => float.is_sign_negative(),
+        _ => false,
+    }
+}
+
+/// Whether a dictionary key can be written without quotes.
+pub(super) fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    let starts_well = matches!(chars.next(), Some(ch) if ch.is_ascii_alphabetic() || ch == '_');
+
+    starts_well
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+        && !RESERVED.contains(&key)
+}
+
+/// The indentation for a given level.
+fn pad(indent: usize) -> String {
+    " ".repeat(indent * INDENT)
+}
+
+/// The column at which the code after `code` starts, given that `code` starts at `column`.
+fn column_after(column: usize, code: &str) -> usize {
+    match code.rfind('\n') {
+        Some(newline) => code.len() - newline - 1,
+        None => column + code.len(),
+    }
+}
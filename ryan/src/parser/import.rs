@@ -1,45 +1,69 @@
 use std::error::Error;
 use std::fmt::Display;
-use std::io::Read;
 use std::rc::Rc;
 
+use indexmap::IndexMap;
+#[cfg(feature = "parse")]
 use pest::iterators::Pairs;
 
-use crate::environment::Environment;
+use crate::environment::loader::ModuleSource;
+use crate::environment::{Environment, EvalEvent, ImportError};
 use crate::rc_world;
-use crate::utils::QuotedStr;
 
+use super::block::Block;
+use super::format::Printer;
 use super::value::Value;
-use super::Context;
+#[cfg(feature = "parse")]
 use super::ErrorLogger;
 use super::Expression;
+#[cfg(feature = "parse")]
 use super::Rule;
 use super::State;
+use super::{Context, EvalErrorKind};
 
 /// The way the imported value should be imported into Ryan.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Format {
     /// Import the content as text, verbatim. No evaluation is done on the imported
     /// content.
     Text,
+    /// Import the content as raw bytes, verbatim. Unlike [`Format::Text`], the content
+    /// need not be valid UTF-8.
+    Bytes,
     /// Import the value as a Ryan. This will execute the provided content as a Ryan
     /// program and will returning its output value.
     Ryan,
 }
 
+impl Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Text => write!(f, "text"),
+            Self::Bytes => write!(f, "bytes"),
+            Self::Ryan => write!(f, "ryan"),
+        }
+    }
+}
+
 impl Format {
+    /// Interprets the content of a module in this format. The code of Ryan modules is
+    /// parsed only once and kept in `source`.
     pub(crate) fn load(
         self,
         env: Environment,
-        mut reader: Box<dyn Read>,
+        source: &mut ModuleSource,
     ) -> Result<Value, Box<dyn Error + 'static>> {
-        let mut text = String::new();
-        reader.read_to_string(&mut text)?;
         match self {
-            Self::Text => Ok(Value::Text(rc_world::string_to_rc(text))),
+            Self::Text => {
+                let text = crate::decode_text(&source.bytes)
+                    .map_err(|err| err.in_module(env.current_module.as_deref()))?;
+                Ok(Value::Text(rc_world::str_to_rc(&text)))
+            }
+            Self::Bytes => Ok(Value::Bytes(source.bytes.clone())),
             Self::Ryan => {
-                let parsed = crate::parser::parse(&text).map_err(Box::new)?;
-                let value = crate::parser::eval(env.clone(), &parsed).map_err(Box::new)?;
+                let block = parse_module(&env, source)?;
+                let value = crate::parser::eval(env, &block).map_err(Box::new)?;
 
                 Ok(value)
             }
@@ -47,46 +71,112 @@ impl Format {
     }
 }
 
+/// Parses the code of a Ryan module, which is kept in `source`, so that it is parsed only
+/// once.
+#[cfg_attr(not(feature = "parse"), allow(unused_variables))]
+pub(crate) fn parse_module(
+    env: &Environment,
+    source: &mut ModuleSource,
+) -> Result<Rc<Block>, Box<dyn Error + 'static>> {
+    match &source.block {
+        Some(block) => Ok(block.clone()),
+        #[cfg(feature = "parse")]
+        None => {
+            let text = crate::decode_text(&source.bytes)
+                .map_err(|err| err.in_module(env.current_module.as_deref()))?;
+            #[cfg(feature = "metrics")]
+            let parsed = env.counters().time_parse(|| crate::parser::parse(&text));
+            #[cfg(not(feature = "metrics"))]
+            let parsed = crate::parser::parse(&text);
+            let parsed = Rc::new(parsed.map_err(Box::new)?);
+            source.block = Some(parsed.clone());
+            Ok(parsed)
+        }
+        #[cfg(not(feature = "parse"))]
+        None => Err("Cannot parse Ryan modules without the `parse` feature".into()),
+    }
+}
+
+/// Checks that a digest in an `integrity` clause is well-formed.
+#[cfg(feature = "parse")]
+fn check_digest(digest: String) -> Result<String, String> {
+    match digest.strip_prefix("sha256-") {
+        Some(hex) if hex.len() == 64 && hex.chars().all(|ch| ch.is_ascii_hexdigit()) => {
+            Ok(digest.to_ascii_lowercase())
+        }
+        _ => Err(format!(
+            "Integrity must be `sha256-` followed by 64 hexadecimal digits, got {digest:?}"
+        )),
+    }
+}
+
 /// An import statement.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Import {
     /// The path from which the content will be imported.
     pub path: Rc<str>,
     /// The way to interpret the imported content.
     pub format: Format,
-    /// A default value in case the value cannot be imported.
+    /// The arguments passed to the imported module, available there as the `args`
+    /// variable.
+    pub args: Option<Box<Expression>>,
+    /// The digest that the raw content of the module must have, as computed by
+    /// [`crate::environment::digest`], e.g., `sha256-ba78...`.
+    pub integrity: Option<Rc<str>>,
+    /// The only top-level keys to import, given with `only`, e.g.,
+    /// `import "lib.ryan" only ["retry"]`. The module must evaluate to a dictionary with
+    /// all of them.
+    pub only: Option<Vec<Rc<str>>>,
+    /// A default value in case the value cannot be found or read. This is not used if
+    /// the module is found but fails to parse or evaluate.
     pub default: Option<Box<Expression>>,
 }
 
 impl Display for Import {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self.format {
-            Format::Ryan => write!(f, "import {}", QuotedStr(&self.path))?,
-            Format::Text => write!(f, "import {} as text", QuotedStr(&self.path))?,
-        }
-
-        if let Some(default) = &self.default {
-            write!(f, " or {default}")?;
-        }
-
-        Ok(())
+        f.write_str(&Printer::FLAT.import(self, 0, 0))
     }
 }
 
 impl Import {
+    #[cfg(feature = "parse")]
     pub(super) fn parse(logger: &mut ErrorLogger, pairs: Pairs<'_, Rule>) -> Self {
         let mut path = None;
         let mut format = None;
+        let mut args = None;
+        let mut integrity = None;
+        let mut only = None;
         let mut default = None;
 
         for pair in pairs {
             match pair.as_rule() {
                 Rule::text => {
                     path = Some(rc_world::string_to_rc(
-                        logger.absorb(&pair, crate::utils::unescape(pair.as_str())),
+                        logger.absorb(&pair, crate::text::unescape(pair.as_str())),
                     ))
                 }
                 Rule::importFormatText => format = Some(Format::Text),
+                Rule::importFormatBytes => format = Some(Format::Bytes),
+                Rule::importArgs => {
+                    let mut pairs = pair.into_inner();
+                    pairs.next().expect("there is always the `with` keyword");
+                    args = Some(Expression::parse(logger, pairs));
+                }
+                Rule::importIntegrity => {
+                    let text = pair.into_inner().next().expect("there is always a digest");
+                    let digest = crate::text::unescape(text.as_str())
+                        .map_err(|err| err.to_string())
+                        .and_then(check_digest);
+                    integrity = Some(rc_world::string_to_rc(logger.absorb(&text, digest)));
+                }
+                Rule::importOnly => {
+                    let keys = pair.into_inner().map(|text| {
+                        let key = crate::text::unescape(text.as_str());
+                        rc_world::string_to_rc(logger.absorb(&text, key))
+                    });
+                    only = Some(keys.collect());
+                }
                 Rule::expression => default = Some(Expression::parse(logger, pair.into_inner())),
                 _ => unreachable!(),
             }
@@ -95,20 +185,117 @@ impl Import {
         Import {
             path: path.expect("there is always a path in an import"),
             format: format.unwrap_or(Format::Ryan),
+            args: args.map(Box::new),
+            integrity,
+            only,
             default: default.map(Box::new),
         }
     }
 
+    #[must_use]
+    pub(super) fn capture(
+        &self,
+        state: &mut State<'_>,
+        provided: &mut [Rc<str>],
+        values: &mut IndexMap<Rc<str>, Value>,
+    ) -> Option<()> {
+        if let Some(args) = &self.args {
+            args.capture(state, provided, values)?;
+        }
+
+        if let Some(default) = &self.default {
+            default.capture(state, provided, values)?;
+        }
+
+        Some(())
+    }
+
     pub(super) fn eval(&self, state: &mut State) -> Option<Value> {
-        state.push_ctx(Context::LoadingImport(self.path.clone()));
+        state.push_ctx(Context::LoadingImport {
+            path: self.path.clone(),
+            chain: state.environment.import_chain(),
+        });
 
-        let value = match state.environment.load(self.format, &self.path) {
+        // Only a successful load sets the origin of this import again:
+        if let Some(tracker) = state.environment.provenance_tracker() {
+            tracker.borrow_mut().last_import = None;
+        }
+
+        let args = if let Some(args) = &self.args {
+            if self.format != Format::Ryan {
+                state.raise(EvalErrorKind::ImportArgumentsUnsupported {
+                    path: self.path.clone(),
+                    format: self.format,
+                })?;
+            }
+
+            Some(args.eval(state)?)
+        } else {
+            None
+        };
+
+        if self.only.is_some() && self.format != Format::Ryan {
+            state.raise(EvalErrorKind::ImportKeysUnsupported {
+                path: self.path.clone(),
+                format: self.format,
+            })?;
+        }
+
+        let loaded = state.environment.load_only(
+            self.format,
+            &self.path,
+            args.as_ref(),
+            self.integrity.as_deref(),
+            self.only.as_deref(),
+        );
+        let value = match loaded {
             Ok(value) => value,
             Err(err) => {
-                if let Some(default) = &self.default {
-                    default.eval(state)?
+                // A cancelled import, or one whose module was cancelled, is not an import
+                // failure, but the end of the evaluation:
+                state.check_cancelled()?;
+
+                // A broken module is a bug, not a missing module. Neither is a missing
+                // environment variable that must be set:
+                let missing_env = match err.downcast_ref::<ImportError>() {
+                    Some(ImportError::EnvVarNotFound { name }) => Some(name.clone()),
+                    _ => None,
+                };
+                let is_broken = matches!(
+                    err.downcast_ref::<ImportError>(),
+                    Some(err) if !err.is_recoverable()
+                ) || matches!(
+                    &missing_env,
+                    Some(name) if state.environment.is_env_required(name)
+                );
+
+                if let (Some(default), false) = (&self.default, is_broken) {
+                    // If the default fails too, the error tells why it was needed at all:
+                    state.push_ctx(Context::EvaluatingImportDefault {
+                        path: self.path.clone(),
+                        reason: rc_world::string_to_rc(err.to_string()),
+                    });
+                    let value = default.eval(state)?;
+                    state.pop_ctx();
+
+                    state.environment.emit(EvalEvent::Warning {
+                        message: rc_world::string_to_rc(format!(
+                            "Import {:?} failed, using default: {err}",
+                            self.path
+                        )),
+                    });
+
+                    if let Some(name) = missing_env {
+                        state.environment.record_missing_env(name);
+                    }
+
+                    value
                 } else {
-                    state.absorb(Err(err))?
+                    state.raise(EvalErrorKind::ImportFailed {
+                        path: self.path.clone(),
+                        source: err,
+                    })?;
+                    return None;
                 }
             }
         };
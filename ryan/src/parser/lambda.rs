@@ -0,0 +1,101 @@
+use std::fmt::Display;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+#[cfg(feature = "parse")]
+use pest::iterators::Pairs;
+
+use crate::rc_world;
+
+use super::block::Block;
+use super::format::Printer;
+use super::pattern::Pattern;
+use super::value::{PatternMatch, Value};
+#[cfg(feature = "parse")]
+use super::ErrorLogger;
+use super::Expression;
+#[cfg(feature = "parse")]
+use super::Rule;
+use super::State;
+
+/// An anonymous pattern match, defined inline in an expression. This is represented by,
+/// e.g., `\x => x + 1` in Ryan.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lambda {
+    /// The pattern against which to match the input.
+    pub pattern: Pattern,
+    /// The code to be executed if the pattern is satisfied.
+    pub body: Expression,
+}
+
+impl Display for Lambda {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&Printer::FLAT.lambda(self, 0, 0))
+    }
+}
+
+impl Lambda {
+    /// The name given to the value of an anonymous pattern match.
+    const IDENTIFIER: &'static str = "anonymous";
+
+    #[cfg(feature = "parse")]
+    pub(super) fn parse(logger: &mut ErrorLogger, pairs: Pairs<'_, Rule>) -> Self {
+        let mut pattern = None;
+        let mut body = None;
+
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::pattern => pattern = Some(Pattern::parse(logger, pair.into_inner())),
+                Rule::expression => body = Some(Expression::parse(logger, pair.into_inner())),
+                _ => unreachable!(),
+            }
+        }
+
+        Lambda {
+            pattern: pattern.expect("there is always a pattern in an anonymous pattern match"),
+            body: body.expect("there is always a body in an anonymous pattern match"),
+        }
+    }
+
+    #[must_use]
+    pub(super) fn capture(
+        &self,
+        state: &mut State<'_>,
+        provided: &mut [Rc<str>],
+        values: &mut IndexMap<Rc<str>, Value>,
+    ) -> Option<()> {
+        let mut provided = provided.to_vec();
+
+        self.pattern.capture(state, &provided, values)?;
+        self.pattern.provided(&mut provided);
+        self.body.capture(state, &mut provided, values)?;
+
+        Some(())
+    }
+
+    pub(super) fn eval(&self, state: &mut State<'_>) -> Option<Value> {
+        let mut provided = vec![];
+        self.pattern.provided(&mut provided);
+
+        let mut captured = IndexMap::default();
+        self.pattern.capture(state, &provided, &mut captured)?;
+        self.body.capture(state, &mut provided, &mut captured)?;
+        state.raise_undefined(None)?;
+
+        Some(Value::PatternMatches(
+            rc_world::str_to_rc(Self::IDENTIFIER),
+            vec![Rc::new(PatternMatch {
+                captures: Rc::new(captured),
+                pattern: self.pattern.clone(),
+                guard: None,
+                block: Block {
+                    bindings: vec![],
+                    expression: self.body.clone(),
+                    has_expression: true,
+                },
+                module: state.environment.current_module.clone(),
+            })],
+        ))
+    }
+}
@@ -1,17 +1,22 @@
 use indexmap::IndexMap;
+#[cfg(feature = "parse")]
 use pest::iterators::Pairs;
 use std::fmt::Display;
 use std::rc::Rc;
 
 use crate::rc_world;
+use crate::utils::{CanonicalFloat, QuotedStr};
 
 use super::value::Value;
+#[cfg(feature = "parse")]
 use super::ErrorLogger;
+#[cfg(feature = "parse")]
 use super::Rule;
 use super::State;
 
 /// A literal Ryan value.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Literal {
     /// The value `null`.
     Null,
@@ -38,40 +43,57 @@ impl Display for Literal {
         match self {
             Self::Null => write!(f, "null"),
             Self::Integer(int) => write!(f, "{int}"),
-            Self::Float(float) => write!(f, "{float}"),
+            Self::Float(float) => write!(f, "{}", CanonicalFloat(*float)),
             Self::Bool(b) => write!(f, "{b}"),
-            Self::Text(text) => write!(f, "{text:?}"),
+            Self::Text(text) => write!(f, "{}", QuotedStr(text)),
             Self::Identifier(id) => write!(f, "{id}"),
         }
     }
 }
 
+/// Parses a number literal. Integers too big for 64 bits become floats, but only if the
+/// float is exactly the integer written.
+#[cfg(feature = "parse")]
+fn parse_number(number: &str) -> Result<Literal, String> {
+    let number = number.replace('_', "");
+    if let Ok(int) = number.parse::<i64>() {
+        return Ok(Literal::Integer(int));
+    }
+
+    let float = number.parse::<f64>().map_err(|err| err.to_string())?;
+    if number.contains(['.', 'e']) {
+        return Ok(Literal::Float(float));
+    }
+
+    let (sign, digits) = match number.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", number.trim_start_matches('+')),
+    };
+    if format!("{float:.0}") == format!("{sign}{}", digits.trim_start_matches('0')) {
+        Ok(Literal::Float(float))
+    } else {
+        Err(format!(
+            "The integer {number} does not fit in 64 bits and is not exactly a float either. \
+            Write it as a float, e.g. `{number}.0`, if the loss of precision is fine"
+        ))
+    }
+}
+
 impl Literal {
+    #[cfg(feature = "parse")]
     pub(super) fn parse(logger: &mut ErrorLogger, mut pairs: Pairs<'_, Rule>) -> Self {
         let pair = pairs.next().expect("there is always a token in a literal");
 
         let literal = match pair.as_rule() {
             Rule::null => Literal::Null,
-            Rule::number => logger.absorb(
-                &pair,
-                pair.as_str()
-                    .replace('_', "")
-                    .parse::<i64>()
-                    .map(|int| Literal::Integer(int))
-                    .or_else(|_| {
-                        pair.as_str()
-                            .replace('_', "")
-                            .parse::<f64>()
-                            .map(|float| Literal::Float(float))
-                    }),
-            ),
+            Rule::number => logger.absorb(&pair, parse_number(pair.as_str())),
             Rule::bool => match pair.as_str() {
                 "true" => Literal::Bool(true),
                 "false" => Literal::Bool(false),
                 _ => unreachable!(),
             },
             Rule::text => {
-                Literal::Text(logger.absorb(&pair, crate::utils::unescape(pair.as_str())))
+                Literal::Text(logger.absorb(&pair, crate::text::unescape(pair.as_str())))
             }
             Rule::identifier => Literal::Identifier(rc_world::str_to_rc(pair.as_str())),
             _ => unreachable!(),
@@ -88,16 +110,7 @@ impl Literal {
         values: &mut IndexMap<Rc<str>, Value>,
     ) -> Option<()> {
         if let Self::Identifier(id) = self {
-            match state.try_get(id) {
-                Ok(cap) => {
-                    values.insert(id.clone(), cap.clone());
-                }
-                Err(err) => {
-                    if !provided.contains(&id) {
-                        state.absorb(Err(err))?;
-                    }
-                }
-            }
+            state.capture_variable(id, provided, values);
         }
 
         Some(())
@@ -109,7 +122,7 @@ impl Literal {
             Self::Bool(b) => Value::Bool(*b),
             Self::Integer(int) => Value::Integer(*int),
             Self::Float(float) => Value::Float(*float),
-            Self::Text(text) => Value::Text(rc_world::str_to_rc(&text)),
+            Self::Text(text) => Value::Text(rc_world::str_to_rc(text)),
             Self::Identifier(id) => state.get(id)?,
         };
 
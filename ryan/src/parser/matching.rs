@@ -0,0 +1,171 @@
+use std::fmt::Display;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+#[cfg(feature = "parse")]
+use pest::iterators::Pairs;
+
+use super::expression::Expression;
+use super::format::Printer;
+use super::pattern::{BindError, Pattern};
+use super::value::Value;
+#[cfg(feature = "parse")]
+use super::ErrorLogger;
+#[cfg(feature = "parse")]
+use super::Rule;
+use super::State;
+
+/// Branches on the value of an expression, evaluating the first arm whose pattern
+/// matches it. This is represented by, e.g., `match env { "prod" => 32, _ => 2 }` in
+/// Ryan.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Match {
+    /// The expression whose value is matched against each arm.
+    pub scrutinee: Expression,
+    /// The arms, in the order they are tried.
+    pub arms: Vec<MatchArm>,
+}
+
+/// An arm of a [`Match`] expression, e.g., `[x, y] if x > y => x`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchArm {
+    /// The pattern against which to match the value.
+    pub pattern: Pattern,
+    /// An optional condition on the variables bound by the pattern.
+    pub guard: Option<Expression>,
+    /// The code to be executed if the pattern is satisfied.
+    pub body: Expression,
+}
+
+impl Display for MatchArm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&Printer::FLAT.match_arm(self, 0, 0))
+    }
+}
+
+impl Display for Match {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&Printer::FLAT.r#match(self, 0, 0))
+    }
+}
+
+impl MatchArm {
+    #[cfg(feature = "parse")]
+    fn parse(logger: &mut ErrorLogger, pairs: Pairs<'_, Rule>) -> Self {
+        let mut pattern = None;
+        let mut guard = None;
+        let mut body = None;
+
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::pattern => pattern = Some(Pattern::parse(logger, pair.into_inner())),
+                Rule::ifGuard => {
+                    let predicate = pair
+                        .into_inner()
+                        .next()
+                        .expect("there is always a predicate in an if guard");
+                    guard = Some(Expression::parse(logger, predicate.into_inner()));
+                }
+                Rule::expression => body = Some(Expression::parse(logger, pair.into_inner())),
+                _ => unreachable!(),
+            }
+        }
+
+        MatchArm {
+            pattern: pattern.expect("there is always a pattern in a match arm"),
+            guard,
+            body: body.expect("there is always a body in a match arm"),
+        }
+    }
+
+    #[must_use]
+    fn capture(
+        &self,
+        state: &mut State<'_>,
+        provided: &mut [Rc<str>],
+        values: &mut IndexMap<Rc<str>, Value>,
+    ) -> Option<()> {
+        let mut provided = provided.to_vec();
+
+        self.pattern.capture(state, &provided, values)?;
+        self.pattern.provided(&mut provided);
+        if let Some(guard) = &self.guard {
+            guard.capture(state, &mut provided, values)?;
+        }
+        self.body.capture(state, &mut provided, values)?;
+
+        Some(())
+    }
+
+    /// Evaluates this arm on `value`, returning `None` in the inner option if the
+    /// pattern or the guard does not match.
+    fn eval(&self, value: &Value, state: &mut State<'_>) -> Option<Option<Value>> {
+        let mut bindings = IndexMap::new();
+        if self.pattern.bind(value, &mut bindings, state)?.is_err() {
+            return Some(None);
+        }
+
+        let mut local = state.new_local(bindings);
+
+        if let Some(guard) = &self.guard {
+            let truthiness = guard.eval(&mut local)?.is_true();
+            if !local.absorb(truthiness)? {
+                return Some(None);
+            }
+        }
+
+        Some(Some(self.body.eval(&mut local)?))
+    }
+}
+
+impl Match {
+    #[cfg(feature = "parse")]
+    pub(super) fn parse(logger: &mut ErrorLogger, mut pairs: Pairs<'_, Rule>) -> Self {
+        pairs.next().expect("there is always the `match` keyword");
+        let scrutinee = pairs
+            .next()
+            .expect("there is always an expression being matched");
+
+        Match {
+            scrutinee: Expression::parse(logger, scrutinee.into_inner()),
+            arms: pairs
+                .map(|pair| MatchArm::parse(logger, pair.into_inner()))
+                .collect(),
+        }
+    }
+
+    #[must_use]
+    pub(super) fn capture(
+        &self,
+        state: &mut State<'_>,
+        provided: &mut [Rc<str>],
+        values: &mut IndexMap<Rc<str>, Value>,
+    ) -> Option<()> {
+        self.scrutinee.capture(state, provided, values)?;
+
+        for arm in &self.arms {
+            arm.capture(state, provided, values)?;
+        }
+
+        Some(())
+    }
+
+    pub(super) fn eval(&self, state: &mut State<'_>) -> Option<Value> {
+        let value = self.scrutinee.eval(state)?;
+
+        for arm in &self.arms {
+            if let Some(found) = arm.eval(&value, state)? {
+                return Some(found);
+            }
+        }
+
+        state.raise(BindError::NoArmMatched {
+            value,
+            patterns: self.arms.iter().map(|arm| arm.pattern.clone()).collect(),
+        })?;
+
+        None
+    }
+}
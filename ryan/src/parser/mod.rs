@@ -2,48 +2,82 @@ mod binding;
 mod block;
 mod comprehension;
 mod error;
+mod eval_error;
 mod expression;
+mod format;
 mod import;
+mod lambda;
 mod literal;
+mod matching;
 mod operation;
 mod pattern;
+#[cfg(feature = "parse")]
+mod program;
+#[cfg(feature = "parse")]
+mod session;
+#[cfg(feature = "parse")]
+#[cfg_attr(not(feature = "introspection"), allow(dead_code))]
+mod source_map;
 mod template_string;
 mod types;
 mod value;
 
 use indexmap::IndexMap;
+#[cfg(feature = "parse")]
 use pest::Parser as _;
+#[cfg(feature = "parse")]
 use pest_derive::Parser;
 use std::cell::RefCell;
-use std::fmt::Display;
 use std::rc::Rc;
 use std::str;
-use thiserror::Error;
 
-use crate::environment::Environment;
+use crate::environment::{Environment, Origin};
 use crate::rc_world;
 
 pub use self::binding::Binding;
 pub use self::block::Block;
 pub use self::comprehension::ListComprehension;
-pub use self::error::{ErrorEntry, ErrorLogger, ParseError};
-pub use self::expression::{Dict, Expression, KeyValue};
+#[cfg(feature = "parse")]
+pub use self::error::ErrorLogger;
+pub use self::error::{ErrorEntry, ParseError, Warning, WarningKind};
+pub use self::eval_error::{Context, EvalError, EvalErrorKind};
+pub use self::expression::{Dict, DictKey, Expression, KeyOrigin, KeyValue};
+#[cfg(feature = "parse")]
+pub(crate) use self::format::{Comments, ItemComments, Layout, Printer};
+pub(crate) use self::import::parse_module;
 pub use self::import::{Format, Import};
+pub use self::lambda::Lambda;
 pub use self::literal::Literal;
+pub use self::matching::{Match, MatchArm};
+#[cfg(feature = "builtins")]
+pub(crate) use self::operation::MAX_REPETITION_SIZE;
 pub use self::operation::{
     BinaryOperation, BinaryOperator, PostfixOperation, PostfixOperator, PrefixOperation,
     PrefixOperator,
 };
-pub use self::pattern::{MatchDictItem, Pattern};
+pub use self::pattern::{BindError, MatchDictItem, Pattern};
+#[cfg(feature = "parse")]
+pub use self::program::Program;
+#[cfg(feature = "parse")]
+pub use self::session::Session;
+#[cfg(feature = "introspection")]
+pub use self::source_map::{
+    BindingInfo, BindingKind, Definition, Reference, ResolvedSymbol, SourceMap,
+};
 pub use self::types::{Type, TypeExpression};
-pub use self::value::{NotIterable, NotRepresentable, PatternMatch, Value};
+pub use self::value::{
+    NotIterable, NotRepresentable, PathSeg, PathedRemoval, PatternMatch, SummarizedValue, Value,
+    ValueStats,
+};
 
 /// The Pest parser for Ryan.
+#[cfg(feature = "parse")]
 #[allow(missing_docs)]
 #[derive(Parser)]
 #[grammar = "ryan.pest"] // relative to src
 struct Parser;
 
+#[cfg(feature = "parse")]
 impl Rule {
     /// A human-readable name for each grammar rule.
     fn name(&self) -> &'static str {
@@ -51,6 +85,7 @@ impl Rule {
             Rule::EOI => "end of input",
             Rule::WHITESPACE => "whitespace",
             Rule::COMMENT => "a comment",
+            Rule::question => "`?`",
             Rule::root => "a Ryan program",
             Rule::main => "a Ryan program",
             Rule::literal => "a literal value",
@@ -68,19 +103,23 @@ impl Rule {
             Rule::templateString => "a template string",
             Rule::templateEscaped => "the interior of a template string",
             Rule::interpolation => "a string interpolation in a template",
+            Rule::formatSpec => "a format specifier",
             Rule::templateControlCode => "a control code in a template string",
             Rule::expression => "an expression",
             Rule::binaryOp => "a binary operation",
             Rule::orOp => "`or`",
             Rule::andOp => "`and`",
+            Rule::pipeOp => "`|>`",
             Rule::equalsOp => "`==`",
             Rule::notEqualsOp => "`!=`",
-            Rule::typeMatchesOp => "`:`",
+            Rule::isOp => "`is`",
+            Rule::typeMatchesOp => "`#`",
             Rule::greaterOp => "`>`",
             Rule::greaterEqualOp => "`>=`",
             Rule::lesserOp => "`<`",
             Rule::lesserEqualOp => "`<=`",
             Rule::isContainedOp => "`in`",
+            Rule::isNotContainedOp => "`not in`",
             Rule::plusOp => "`+`",
             Rule::minusOp => "`-`",
             Rule::timesOp => "`*`",
@@ -90,26 +129,37 @@ impl Rule {
             Rule::juxtapositionOp => "a juxtaposition",
             Rule::prefixOp => "a prefix operator",
             Rule::notOp => "`not`",
+            Rule::negativeOp => "a unary `-`",
+            Rule::positiveOp => "a unary `+`",
             Rule::postfixOp => "a postfix operator",
             Rule::castInt => "a type cast to integer",
             Rule::castFloat => "a type cast to float",
             Rule::castText => "a type cast to text",
             Rule::accessOp => "list or map access",
             Rule::pathOp => "list or map access",
+            Rule::optionalAccessOp => "optional list or map access",
+            Rule::optionalPathOp => "optional list or map access",
             Rule::term => "an expression term",
             Rule::list => "a list",
             Rule::listItem => "an item of a list",
             Rule::flatExpression => "a flatten expression",
             Rule::dictItem => "a dictionary item",
             Rule::keyValue => "a key-value dictionary entry",
+            Rule::computedKey => "a computed dictionary key",
             Rule::dict => "a dictionary",
             Rule::conditional => "`if ... then ... else ...`",
+            Rule::lambda => "an anonymous pattern match",
+            Rule::matchExpression => "a `match` expression",
+            Rule::matchKeyword => "`match`",
+            Rule::matchArm => "an arm of a `match` expression",
+            Rule::scoped => "an expression with local bindings",
             Rule::listComprehension => "a list comprehension",
             Rule::dictComprehension => "a dictionary comprehension",
             Rule::forClause => "a `for` clause",
             Rule::ifGuard => "an `if` guard",
             Rule::keyValueClause => "a key-value clause",
             Rule::pattern => "a pattern match",
+            Rule::patternAlternative => "an alternative of a pattern match",
             Rule::wildcard => "a wildcard pattern patch",
             Rule::matchIdentifier => "an identifier pattern match",
             Rule::matchList => "a full list pattern match",
@@ -118,14 +168,23 @@ impl Rule {
             Rule::matchDict => "a non-strict dictionary pattern match",
             Rule::matchDictStrict => "a strict dictionary pattern match",
             Rule::matchDictItem => "a dictionary item pattern match",
+            Rule::matchDefault => "a default value for a missing key",
+            Rule::matchRest => "a rest binding",
             Rule::binding => "a variable binding",
             Rule::patternMatchBinding => "a pattern match binding",
             Rule::destructuringBiding => "a destructuring binding",
+            Rule::destructuringFallback => "an `else` fallback",
             Rule::typeDefinition => "a type definition",
+            Rule::useStatement => "a use statement",
             Rule::block => "a code block",
             Rule::import => "an import statement",
             Rule::importFormat => "an import format",
             Rule::importFormatText => "import as text",
+            Rule::importFormatBytes => "import as bytes",
+            Rule::importArgs => "import arguments",
+            Rule::withKeyword => "`with`",
+            Rule::importIntegrity => "an import integrity check",
+            Rule::importOnly => "a list of keys to import",
             Rule::primitive => "a primitive type value",
             Rule::typeExpression => "a type expression",
             Rule::typeTerm => "a term in a type expression",
@@ -142,54 +201,100 @@ impl Rule {
 
 /// Parses a Ryan string and returns an abstract syntax tree (AST) object, represented by
 /// its root, a [`Block`].
+#[cfg(feature = "parse")]
 pub fn parse(s: &str) -> Result<Block, ParseError> {
-    let mut parsed = Parser::parse(Rule::root, s).map_err(|e| ParseError {
-        errors: vec![ErrorEntry::from(e).to_string_with(s)],
-    })?;
+    let (block, _) = parse_with_warnings(s)?;
+    Ok(block)
+}
+
+/// Parses a Ryan string and returns an abstract syntax tree (AST) object, represented by
+/// its root, a [`Block`], together with the non-fatal issues found along the way.
+#[cfg(feature = "parse")]
+pub fn parse_with_warnings(s: &str) -> Result<(Block, Vec<Warning>), ParseError> {
+    let (block, warnings, _) = parse_program(s)?;
+    Ok((block, warnings))
+}
+
+/// Parses a Ryan string and returns an abstract syntax tree (AST) object, represented by
+/// its root, a [`Block`], together with a [`SourceMap`] telling where each name is bound
+/// and used.
+#[cfg(feature = "introspection")]
+pub fn parse_with_source_map(s: &str) -> Result<(Block, SourceMap), ParseError> {
+    let block = parse(s)?;
+    let source_map = SourceMap::parse(s)?;
+    Ok((block, source_map))
+}
+
+/// Parses a Ryan string like [`parse_with_warnings`], also returning where things are in
+/// the source: the spans of each top-level binding (without the trailing `;`) and of the
+/// final expression, if any, in the order they appear, and of all lists and dictionaries.
+#[cfg(feature = "parse")]
+pub(crate) fn parse_program(s: &str) -> Result<(Block, Vec<Warning>, Layout), ParseError> {
+    let mut parsed =
+        Parser::parse(Rule::root, s).map_err(|e| ParseError::new(s, vec![ErrorEntry::from(e)]))?;
     let mut error_logger = ErrorLogger::new(s);
     let main = parsed.next().expect("there is always a matching token");
     let block = if !main.as_str().is_empty() {
-        Block::parse(&mut error_logger, main.into_inner())
+        for pair in main.clone().into_inner() {
+            let span = pair.as_span();
+            error_logger.layout.items.push((span.start(), span.end()));
+        }
+        let block = Block::parse(&mut error_logger, main.clone().into_inner());
+        warn_unused(&mut error_logger, &source_map::SourceMap::from_root(main));
+        block
     } else {
         Block::null()
     };
 
     if error_logger.errors.is_empty() {
-        Ok(block)
+        Ok((block, error_logger.warnings, error_logger.layout))
     } else {
         Err(error_logger.into())
     }
 }
 
-#[derive(Debug)]
-enum Context {
-    RunningFile(Rc<str>),
-    EvaluatingBinding(Rc<str>),
-    DefiningType(Rc<str>),
-    SubstitutingPattern(Option<Rc<str>>),
-    LoadingImport(Rc<str>),
-}
-
-impl Display for Context {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::RunningFile(filename) => write!(f, "Running {filename}"),
-            Self::EvaluatingBinding(name) => write!(f, "Evaluating binding {name}"),
-            Self::DefiningType(typ) => write!(f, "Defining type {typ}"),
-            Self::SubstitutingPattern(Some(name)) => write!(f, "Substituting pattern {name}"),
-            Self::SubstitutingPattern(None) => write!(f, "Substituting anonymous pattern"),
-            Self::LoadingImport(import) => write!(f, "Loading import {import:?}"),
-        }
+/// Warns about the bindings that are never used in their scope.
+#[cfg(feature = "parse")]
+fn warn_unused(logger: &mut ErrorLogger, source_map: &source_map::SourceMap) {
+    for (binding, is_import) in source_map.unused_bindings() {
+        let (kind, message) = match (binding.kind, is_import) {
+            (_, true) => (
+                WarningKind::UnusedImport,
+                format!("Imported variable `{}` is never used", binding.name),
+            ),
+            (source_map::BindingKind::Type, _) => (
+                WarningKind::UnusedBinding,
+                format!("Type `{}` is never used", binding.name),
+            ),
+            _ => (
+                WarningKind::UnusedBinding,
+                format!("Variable `{}` is never used", binding.name),
+            ),
+        };
+        logger.warn_at(binding.span, kind, message);
     }
 }
 
+/// A pattern match being applied: its identifier and its rules.
+type Applying = (Rc<str>, Vec<Rc<PatternMatch>>);
+
 #[derive(Debug)]
 struct State<'a> {
     inherited: Option<&'a State<'a>>,
     bindings: IndexMap<Rc<str>, Value>,
-    error: Rc<RefCell<Option<String>>>,
+    /// The variables captured by the pattern match being applied, shared with it instead
+    /// of copied into `bindings`, which shadow them.
+    captures: Option<Rc<IndexMap<Rc<str>, Value>>>,
+    error: Rc<RefCell<Option<EvalErrorKind>>>,
     contexts: Rc<RefCell<Vec<Context>>>,
     environment: Environment,
+    /// The undefined variables found so far while capturing the variables used by a
+    /// pattern match, in the order they appear.
+    undefined: Vec<Rc<str>>,
+    /// The pattern whose variables are being captured, which cannot capture itself.
+    defining: Option<Rc<str>>,
+    /// The pattern matches being applied, from the outermost to the innermost.
+    applying: Rc<RefCell<Vec<Applying>>>,
 }
 
 impl<'a> State<'a> {
@@ -197,11 +302,15 @@ impl<'a> State<'a> {
         State {
             inherited: None,
             bindings: IndexMap::new(),
+            captures: None,
             error: Rc::default(),
             contexts: Rc::new(RefCell::new(vec![Context::RunningFile(
                 rc_world::str_to_rc(environment.current_module.as_deref().unwrap_or("<main>")),
             )])),
             environment,
+            undefined: vec![],
+            defining: None,
+            applying: Rc::default(),
         }
     }
 
@@ -212,20 +321,30 @@ impl<'a> State<'a> {
         match r {
             Ok(t) => Some(t),
             Err(e) => {
-                *self.error.borrow_mut() = Some(e.to_string());
+                *self.error.borrow_mut() = Some(EvalErrorKind::Other(e.to_string()));
                 None
             }
         }
     }
 
-    fn raise<E>(&mut self, msg: E) -> Option<()>
+    fn raise<E>(&mut self, err: E) -> Option<()>
     where
-        E: ToString,
+        E: Into<EvalErrorKind>,
     {
-        *self.error.borrow_mut() = Some(msg.to_string());
+        *self.error.borrow_mut() = Some(err.into());
         None
     }
 
+    /// Raises [`EvalErrorKind::Cancelled`] if the evaluation was cancelled. Call this
+    /// wherever evaluation may go on for long.
+    fn check_cancelled(&mut self) -> Option<()> {
+        if self.environment.is_cancelled() {
+            self.raise(EvalErrorKind::Cancelled)?;
+        }
+
+        Some(())
+    }
+
     fn push_ctx(&mut self, ctx: Context) {
         self.contexts.borrow_mut().push(ctx);
     }
@@ -234,23 +353,86 @@ impl<'a> State<'a> {
         self.contexts.borrow_mut().pop();
     }
 
-    fn try_get(&self, id: &str) -> Result<Value, String> {
-        match self.bindings.get(id) {
-            Some(bound) => Ok(bound.clone()),
+    /// Returns where values produced right now come from, if provenance is being
+    /// tracked: the current module and the innermost binding being evaluated.
+    fn origin(&self) -> Option<Origin> {
+        self.environment.provenance_tracker()?;
+        let binding = self
+            .contexts
+            .borrow()
+            .iter()
+            .rev()
+            .find_map(|ctx| match ctx {
+                Context::EvaluatingBinding(name) => Some(name.clone()),
+                _ => None,
+            });
+
+        let module = self.environment.current_module.as_deref();
+        Some(Origin {
+            module: rc_world::str_to_rc(module.unwrap_or("<main>")),
+            binding,
+        })
+    }
+
+    /// Returns where the value just produced by an expression comes from, if provenance
+    /// is being tracked. This is the imported module for imports and [`State::origin`]
+    /// otherwise.
+    fn origin_of(&self, expr: &Expression) -> Option<Origin> {
+        let tracker = self.environment.provenance_tracker()?;
+        let imported = if let Expression::Import(_) = expr {
+            tracker.borrow_mut().last_import.take()
+        } else {
+            None
+        };
+
+        imported.or_else(|| self.origin())
+    }
+
+    /// Looks a variable up, returning `None` if it is undefined.
+    fn try_get(&self, id: &str) -> Option<Value> {
+        let captured = || self.captures.as_ref().and_then(|captures| captures.get(id));
+        match self.bindings.get(id).or_else(captured) {
+            Some(bound) => Some(bound.clone()),
             _ => {
                 if let Some(inherited) = self.inherited.as_ref() {
                     inherited.try_get(id)
-                } else if let Some(builtin) = self.environment.builtin(id) {
-                    Ok(builtin)
+                } else if id == "args" {
+                    Some(self.environment.import_args())
+                } else if id == "__module__" {
+                    Some(self.environment.module_name())
+                } else if id == "__dir__" {
+                    Some(self.environment.module_dir())
                 } else {
-                    Err(format!("Variable `{id}` is undefined"))
+                    self.environment.builtin(id)
                 }
             }
         }
     }
 
+    /// Whether a variable is bound by the program, as opposed to being a built-in or not
+    /// being defined at all.
+    fn binds(&self, id: &str) -> bool {
+        self.bindings.contains_key(id)
+            || self
+                .captures
+                .as_ref()
+                .is_some_and(|captures| captures.contains_key(id))
+            || self
+                .inherited
+                .as_ref()
+                .is_some_and(|inherited| inherited.binds(id))
+    }
+
     fn get(&mut self, id: &str) -> Option<Value> {
-        self.absorb(self.try_get(id))
+        match self.try_get(id) {
+            Some(value) => Some(value),
+            None => {
+                self.raise(EvalErrorKind::UndefinedVariable {
+                    name: rc_world::str_to_rc(id),
+                })?;
+                None
+            }
+        }
     }
 
     fn new_local(&'a self, new_bindings: IndexMap<Rc<str>, Value>) -> Self {
@@ -260,52 +442,130 @@ impl<'a> State<'a> {
             contexts: self.contexts.clone(),
             inherited: Some(self),
             bindings: new_bindings,
+            captures: None,
+            undefined: vec![],
+            defining: None,
+            applying: self.applying.clone(),
         }
     }
-}
 
-/// An error that happens during the execution of a Ryan program.
-#[derive(Debug, Error)]
-pub struct EvalError {
-    error: String,
-    context: Vec<String>,
-}
+    /// A state for the code of another module, which does not see any of the variables of
+    /// this one, but shares its errors and contexts.
+    fn new_module(&self, environment: Environment) -> State<'static> {
+        State {
+            inherited: None,
+            bindings: IndexMap::new(),
+            captures: None,
+            error: self.error.clone(),
+            contexts: self.contexts.clone(),
+            environment,
+            undefined: vec![],
+            defining: None,
+            applying: self.applying.clone(),
+        }
+    }
+
+    /// Like [`State::new_local`], but also sees the variables captured by a pattern
+    /// match, which the new bindings shadow. The captures are shared, not copied, so
+    /// applying a pattern match costs the same, however many variables it captures.
+    fn new_captured(
+        &'a self,
+        captures: Rc<IndexMap<Rc<str>, Value>>,
+        new_bindings: IndexMap<Rc<str>, Value>,
+    ) -> Self {
+        State {
+            captures: Some(captures),
+            ..self.new_local(new_bindings)
+        }
+    }
 
-impl Display for EvalError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "{}", self.error)?;
+    /// Captures the value of a variable used by a pattern match, unless the pattern match
+    /// provides it itself. Undefined variables are not raised right away, but collected,
+    /// so that [`State::raise_undefined`] reports all of them at once. The pattern being
+    /// defined counts as undefined, so that it cannot capture an earlier definition of
+    /// itself.
+    fn capture_variable(
+        &mut self,
+        id: &Rc<str>,
+        provided: &[Rc<str>],
+        values: &mut IndexMap<Rc<str>, Value>,
+    ) {
+        let found = if self.defining.as_ref() == Some(id) {
+            None
+        } else {
+            self.try_get(id)
+        };
 
-        if !self.context.is_empty() {
-            writeln!(f)?;
-            writeln!(f, "Context:")?;
-            for line in &self.context {
-                writeln!(f, "    - {line}")?;
+        match found {
+            Some(captured) => {
+                values.insert(id.clone(), captured);
             }
+            None if !provided.contains(id) && !self.undefined.contains(id) => {
+                self.undefined.push(id.clone());
+            }
+            None => {}
         }
+    }
 
-        Ok(())
+    /// Raises the undefined variables collected while capturing the variables of a
+    /// pattern match, if there are any. The name is `None` for anonymous pattern matches.
+    fn raise_undefined(&mut self, pattern: Option<&Rc<str>>) -> Option<()> {
+        let names = std::mem::take(&mut self.undefined);
+        if let Some(pattern) = pattern.filter(|pattern| names.contains(pattern)) {
+            self.raise(EvalErrorKind::Recursion {
+                pattern: pattern.clone(),
+                through: vec![],
+            })?;
+        }
+
+        if !names.is_empty() {
+            self.raise(EvalErrorKind::UndefinedInPattern {
+                pattern: pattern.cloned(),
+                names,
+            })?;
+        }
+
+        Some(())
+    }
+}
+
+impl EvalError {
+    /// Collects the error set in a state after backtracking.
+    fn from_state(state: &State<'_>) -> EvalError {
+        EvalError::new(
+            state
+                .error
+                .borrow_mut()
+                .take()
+                .expect("on backtracking, an error must be set"),
+            state.contexts.borrow().clone(),
+        )
     }
 }
 
-/// Executes a block in a given environment, returning the resulting value.
+/// Executes a block in a given environment, returning the resulting value. In strict
+/// mode, a block without a final expression is an error.
 pub fn eval(environment: Environment, block: &Block) -> Result<Value, EvalError> {
+    let strict = environment.is_strict();
     let mut state = State::new(environment);
 
-    if let Some(value) = block.eval(&mut state) {
+    if strict && !block.has_expression {
+        state.raise(EvalErrorKind::NoFinalExpression);
+        return Err(EvalError::from_state(&state));
+    }
+
+    #[cfg(feature = "metrics")]
+    let evaluated = {
+        let counters = state.environment.counters().clone();
+        counters.time_eval(|| block.eval(&mut state))
+    };
+    #[cfg(not(feature = "metrics"))]
+    let evaluated = block.eval(&mut state);
+    state.environment.warn_about_missing_env();
+
+    if let Some(value) = evaluated {
         Ok(value)
     } else {
-        Err(EvalError {
-            error: state
-                .error
-                .borrow()
-                .clone()
-                .expect("on backtracking, an error must be set"),
-            context: state
-                .contexts
-                .borrow()
-                .iter()
-                .map(ToString::to_string)
-                .collect(),
-        })
+        Err(EvalError::from_state(&state))
     }
 }
@@ -1,18 +1,26 @@
+#[cfg(feature = "parse")]
 use pest::iterators::Pair;
+use std::cmp::Ordering;
 use std::fmt::Display;
 use std::rc::Rc;
 
+use crate::environment::{EvalEvent, PrecisionLossPolicy};
 use crate::rc_world;
 
 use super::expression::Expression;
-use super::value::Value;
-use super::Context;
+use super::format::Printer;
+use super::pattern::BindError;
+use super::value::{self, PatternMatch, Value};
+#[cfg(feature = "parse")]
 use super::ErrorLogger;
+#[cfg(feature = "parse")]
 use super::Rule;
 use super::State;
+use super::{Context, EvalErrorKind, Type};
 
 /// An operation involving two Ryan values.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BinaryOperator {
     /// Logical and.
     And,
@@ -22,8 +30,8 @@ pub enum BinaryOperator {
     Equals,
     /// Strict inequality.
     NotEquals,
-    // #[deprecated]
-    /// Whether the value is of a certain type (to be deprecated).
+    /// Whether the value is of a certain type, e.g., `x is int`. The older form `x # int`
+    /// is deprecated.
     TypeMatches,
     /// Greater than comparison.
     GreaterThen,
@@ -35,6 +43,9 @@ pub enum BinaryOperator {
     LesserEqual,
     /// Set inclusion
     IsContainedIn,
+    /// Set exclusion, i.e., the negation of [`BinaryOperator::IsContainedIn`], using the
+    /// `not in` notation.
+    IsNotContainedIn,
     /// Addition or concatenation.
     Plus,
     /// Subtraction.
@@ -49,6 +60,9 @@ pub enum BinaryOperator {
     Default,
     /// Pattern application.
     Juxtaposition,
+    /// Pattern application with the argument first, e.g., `conf |> values |> len` is the
+    /// same as `len (values conf)`.
+    Pipe,
 }
 
 impl Display for BinaryOperator {
@@ -58,12 +72,13 @@ impl Display for BinaryOperator {
             Self::Or => write!(f, "or")?,
             Self::Equals => write!(f, "==")?,
             Self::NotEquals => write!(f, "!=")?,
-            Self::TypeMatches => write!(f, ":")?,
+            Self::TypeMatches => write!(f, "is")?,
             Self::GreaterThen => write!(f, ">")?,
             Self::GreaterEqual => write!(f, ">=")?,
             Self::LesserThen => write!(f, "<")?,
             Self::LesserEqual => write!(f, "<=")?,
             Self::IsContainedIn => write!(f, "in")?,
+            Self::IsNotContainedIn => write!(f, "not in")?,
             Self::Plus => write!(f, "+")?,
             Self::Minus => write!(f, "-")?,
             Self::Times => write!(f, "*")?,
@@ -71,6 +86,7 @@ impl Display for BinaryOperator {
             Self::Remainder => write!(f, "%")?,
             Self::Default => write!(f, "?")?,
             Self::Juxtaposition => {}
+            Self::Pipe => write!(f, "|>")?,
         }
 
         Ok(())
@@ -78,18 +94,20 @@ impl Display for BinaryOperator {
 }
 
 impl BinaryOperator {
+    #[cfg(feature = "parse")]
     pub(super) fn parse(pair: Pair<'_, Rule>) -> Self {
         match pair.as_rule() {
             Rule::andOp => BinaryOperator::And,
             Rule::orOp => BinaryOperator::Or,
             Rule::equalsOp => BinaryOperator::Equals,
             Rule::notEqualsOp => BinaryOperator::NotEquals,
-            Rule::typeMatchesOp => BinaryOperator::TypeMatches,
+            Rule::isOp | Rule::typeMatchesOp => BinaryOperator::TypeMatches,
             Rule::greaterOp => BinaryOperator::GreaterThen,
             Rule::greaterEqualOp => BinaryOperator::GreaterEqual,
             Rule::lesserOp => BinaryOperator::LesserThen,
             Rule::lesserEqualOp => BinaryOperator::LesserEqual,
             Rule::isContainedOp => BinaryOperator::IsContainedIn,
+            Rule::isNotContainedOp => BinaryOperator::IsNotContainedIn,
             Rule::plusOp => BinaryOperator::Plus,
             Rule::minusOp => BinaryOperator::Minus,
             Rule::timesOp => BinaryOperator::Times,
@@ -97,6 +115,7 @@ impl BinaryOperator {
             Rule::remainderOp => BinaryOperator::Remainder,
             Rule::defaultOp => BinaryOperator::Default,
             Rule::juxtapositionOp => BinaryOperator::Juxtaposition,
+            Rule::pipeOp => BinaryOperator::Pipe,
             _ => unreachable!(),
         }
     }
@@ -104,15 +123,22 @@ impl BinaryOperator {
 
 /// An operation involving one Ryan value, where the value follows it.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PrefixOperator {
     /// Logical negation.
     Not,
+    /// Arithmetic negation of a number, e.g., `-x`.
+    Negative,
+    /// Does nothing to a number, e.g., `+x`. Exists for symmetry with `-`.
+    Positive,
 }
 
 impl Display for PrefixOperator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Not => write!(f, "not")?,
+            Self::Negative => write!(f, "-")?,
+            Self::Positive => write!(f, "+")?,
         }
 
         Ok(())
@@ -120,9 +146,12 @@ impl Display for PrefixOperator {
 }
 
 impl PrefixOperator {
+    #[cfg(feature = "parse")]
     pub(super) fn parse(pair: Pair<'_, Rule>) -> Self {
         match pair.as_rule() {
             Rule::notOp => PrefixOperator::Not,
+            Rule::negativeOp => PrefixOperator::Negative,
+            Rule::positiveOp => PrefixOperator::Positive,
             _ => unreachable!(),
         }
     }
@@ -130,12 +159,20 @@ impl PrefixOperator {
 
 /// An operation involving one Ryan value, where the value precedes it.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PostfixOperator {
     /// Get the value associated with a key in a dictionary using the familiar `.` notation.
     Access(Rc<str>),
     /// Access the value in a deeply nested Ryan object using the supplied path.
     Path(Vec<Expression>),
-    /// Cast the value as integer.
+    /// Like [`PostfixOperator::Access`], but yields `null` if the value is `null` or if
+    /// the key is absent, using the `?.` notation.
+    OptionalAccess(Rc<str>),
+    /// Like [`PostfixOperator::Path`], but yields `null` if any step of the path is `null`
+    /// or absent, using the `?[...]` notation.
+    OptionalPath(Vec<Expression>),
+    /// Cast the value as integer. Floats are truncated towards zero, and NaN, the
+    /// infinities and floats out of the range of integers are errors.
     CastInt,
     /// Cast the value as float.
     CastFloat,
@@ -152,6 +189,12 @@ impl Display for PostfixOperator {
                 crate::utils::fmt_list(f, exprs)?;
                 write!(f, "]")?;
             }
+            Self::OptionalAccess(field) => write!(f, "?.{field}")?,
+            Self::OptionalPath(exprs) => {
+                write!(f, "?[")?;
+                crate::utils::fmt_list(f, exprs)?;
+                write!(f, "]")?;
+            }
             Self::CastInt => {
                 write!(f, "as int")?;
             }
@@ -168,9 +211,10 @@ impl Display for PostfixOperator {
 }
 
 impl PostfixOperator {
+    #[cfg(feature = "parse")]
     pub(super) fn parse(logger: &mut ErrorLogger, pair: Pair<'_, Rule>) -> Self {
         match pair.as_rule() {
-            Rule::accessOp => {
+            rule @ (Rule::accessOp | Rule::optionalAccessOp) => {
                 let mut field = None;
                 for pair in pair.into_inner() {
                     match pair.as_rule() {
@@ -179,11 +223,15 @@ impl PostfixOperator {
                     }
                 }
 
-                PostfixOperator::Access(
-                    field.expect("there is always a field in an access operation"),
-                )
+                let field = field.expect("there is always a field in an access operation");
+
+                if rule == Rule::accessOp {
+                    PostfixOperator::Access(field)
+                } else {
+                    PostfixOperator::OptionalAccess(field)
+                }
             }
-            Rule::pathOp => {
+            rule @ (Rule::pathOp | Rule::optionalPathOp) => {
                 let mut exprs = vec![];
                 for pair in pair.into_inner() {
                     match pair.as_rule() {
@@ -194,7 +242,11 @@ impl PostfixOperator {
                     }
                 }
 
-                PostfixOperator::Path(exprs)
+                if rule == Rule::pathOp {
+                    PostfixOperator::Path(exprs)
+                } else {
+                    PostfixOperator::OptionalPath(exprs)
+                }
             }
             Rule::castInt => PostfixOperator::CastInt,
             Rule::castFloat => PostfixOperator::CastFloat,
@@ -206,6 +258,7 @@ impl PostfixOperator {
 
 /// An operation involving two Ryan expressions and a binary operator.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BinaryOperation {
     /// The left side of the operation.
     pub left: Expression,
@@ -217,15 +270,40 @@ pub struct BinaryOperation {
 
 impl Display for BinaryOperation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if let BinaryOperator::Juxtaposition = self.op {
-            write!(f, "{} {}", self.left, self.right)
-        } else {
-            write!(f, "{} {} {}", self.left, self.op, self.right)
-        }
+        f.write_str(&Printer::FLAT.binary_operation(self, 0, 0))
     }
 }
 
+/// Compares an integer and a float exactly, without turning the integer into a float,
+/// which could round it. Returns `None` if the float is `NaN`.
+fn cmp_int_float(int: i64, float: f64) -> Option<Ordering> {
+    (!float.is_nan()).then(|| value::cmp_int_float(int, float))
+}
+
+/// The biggest magnitude below which every integer can be turned into a float exactly.
+pub(super) const MAX_SAFE_INTEGER: u64 = 1 << 53;
+
 impl BinaryOperation {
+    /// Turns an integer operand into a float, for the operations mixing integers and
+    /// floats, applying the [`PrecisionLossPolicy`] if the integer is too big for that.
+    fn promote(&self, int: i64, state: &mut State<'_>) -> Option<f64> {
+        if int.unsigned_abs() > MAX_SAFE_INTEGER {
+            let error = EvalErrorKind::PrecisionLoss {
+                int,
+                operation: self.to_string(),
+            };
+            match state.environment.precision_loss_policy() {
+                PrecisionLossPolicy::Warn => state.environment.emit(EvalEvent::Warning {
+                    message: rc_world::string_to_rc(error.to_string()),
+                }),
+                PrecisionLossPolicy::Error => state.raise(error)?,
+                PrecisionLossPolicy::Allow => {}
+            }
+        }
+
+        Some(int as f64)
+    }
+
     pub(super) fn eval(&self, state: &mut State<'_>) -> Option<Value> {
         let left = self.left.eval(state)?;
 
@@ -239,43 +317,36 @@ impl BinaryOperation {
 
         let right = self.right.eval(state)?;
         let result = match (left, self.op, right) {
-            (Value::PatternMatches(id, pats), BinaryOperator::Juxtaposition, arg) => {
-                state.push_ctx(Context::SubstitutingPattern(Some(id)));
-                let mut evalued = None;
-                let mut last_error = None;
-
-                for pat in pats {
-                    match pat.r#match(&arg, state)? {
-                        Ok(found) => {
-                            evalued = Some(found);
-                            break;
-                        }
-                        Err(err) => last_error = Some(err),
-                    }
-                }
-
-                if let Some(evalued) = evalued {
-                    state.pop_ctx();
-                    evalued
-                } else {
-                    state.raise(format!(
-                        "{}",
-                        last_error.expect("there is at least one patter in a pattern match")
-                    ))?;
-                    return None;
-                }
-            }
-            (Value::NativePatternMatch(pat), BinaryOperator::Juxtaposition, arg) => {
-                pat.r#match(arg, state)?
+            (
+                callee @ (Value::PatternMatches(..) | Value::NativePatternMatch(_)),
+                BinaryOperator::Juxtaposition,
+                arg,
+            ) => apply(callee, arg, state)?,
+            (
+                arg,
+                BinaryOperator::Pipe,
+                callee @ (Value::PatternMatches(..) | Value::NativePatternMatch(_)),
+            ) => apply(callee, arg, state)?,
+            (_, BinaryOperator::Pipe, right) => {
+                state.raise(EvalErrorKind::NotAPipeTarget { target: right })?;
+                return None;
             }
-            (value, BinaryOperator::Juxtaposition, Value::List(list)) => {
-                match value.extract_path(&list) {
-                    Ok(val) => val,
-                    Err(err) => {
-                        state.raise(err);
-                        return None;
-                    }
+            // A collection juxtaposed with a list is indexed by it, as in `x path`, where
+            // `path` is a list of keys and indices:
+            (
+                value @ (Value::Map(_) | Value::List(_)),
+                BinaryOperator::Juxtaposition,
+                Value::List(list),
+            ) => match value.extract_path(&list) {
+                Ok(val) => val,
+                Err(err) => {
+                    state.raise(err);
+                    return None;
                 }
+            },
+            (callee, BinaryOperator::Juxtaposition, arg) => {
+                state.raise(EvalErrorKind::NotAPattern { callee, arg })?;
+                return None;
             }
             (Value::Null, BinaryOperator::Default, val) => val.clone(),
             (first, BinaryOperator::Default, _) => first,
@@ -294,10 +365,10 @@ impl BinaryOperation {
                 Value::Bool(left > right)
             }
             (Value::Integer(left), BinaryOperator::GreaterThen, Value::Float(right)) => {
-                Value::Bool(left as f64 > right)
+                Value::Bool(cmp_int_float(left, right).is_some_and(Ordering::is_gt))
             }
             (Value::Float(left), BinaryOperator::GreaterThen, Value::Integer(right)) => {
-                Value::Bool(left > right as f64)
+                Value::Bool(cmp_int_float(right, left).is_some_and(Ordering::is_lt))
             }
             (Value::Float(left), BinaryOperator::GreaterThen, Value::Float(right)) => {
                 Value::Bool(left > right)
@@ -307,10 +378,10 @@ impl BinaryOperation {
                 Value::Bool(left >= right)
             }
             (Value::Integer(left), BinaryOperator::GreaterEqual, Value::Float(right)) => {
-                Value::Bool(left as f64 >= right)
+                Value::Bool(cmp_int_float(left, right).is_some_and(Ordering::is_ge))
             }
             (Value::Float(left), BinaryOperator::GreaterEqual, Value::Integer(right)) => {
-                Value::Bool(left >= right as f64)
+                Value::Bool(cmp_int_float(right, left).is_some_and(Ordering::is_le))
             }
             (Value::Float(left), BinaryOperator::GreaterEqual, Value::Float(right)) => {
                 Value::Bool(left >= right)
@@ -320,10 +391,10 @@ impl BinaryOperation {
                 Value::Bool(left < right)
             }
             (Value::Integer(left), BinaryOperator::LesserThen, Value::Float(right)) => {
-                Value::Bool((left as f64) < right)
+                Value::Bool(cmp_int_float(left, right).is_some_and(Ordering::is_lt))
             }
             (Value::Float(left), BinaryOperator::LesserThen, Value::Integer(right)) => {
-                Value::Bool(left < right as f64)
+                Value::Bool(cmp_int_float(right, left).is_some_and(Ordering::is_gt))
             }
             (Value::Float(left), BinaryOperator::LesserThen, Value::Float(right)) => {
                 Value::Bool(left < right)
@@ -333,59 +404,97 @@ impl BinaryOperation {
                 Value::Bool(left <= right)
             }
             (Value::Integer(left), BinaryOperator::LesserEqual, Value::Float(right)) => {
-                Value::Bool(left as f64 <= right)
+                Value::Bool(cmp_int_float(left, right).is_some_and(Ordering::is_le))
             }
             (Value::Float(left), BinaryOperator::LesserEqual, Value::Integer(right)) => {
-                Value::Bool(left <= right as f64)
+                Value::Bool(cmp_int_float(right, left).is_some_and(Ordering::is_ge))
             }
             (Value::Float(left), BinaryOperator::LesserEqual, Value::Float(right)) => {
                 Value::Bool(left <= right)
             }
 
-            (val, BinaryOperator::IsContainedIn, Value::List(list)) => {
-                Value::Bool(list.iter().any(|item| *item == val))
-            }
-            (Value::Text(key), BinaryOperator::IsContainedIn, Value::Map(map)) => {
-                Value::Bool(map.contains_key(&*key))
-            }
-            (Value::Text(sub), BinaryOperator::IsContainedIn, Value::Text(text)) => {
-                Value::Bool(text.contains(&*sub))
+            (
+                val,
+                op @ (BinaryOperator::IsContainedIn | BinaryOperator::IsNotContainedIn),
+                collection,
+            ) => {
+                let is_contained = match (&val, &collection) {
+                    (val, Value::List(list)) => list.iter().any(|item| item == val),
+                    (Value::Text(key), Value::Map(map)) => map.contains_key(&**key),
+                    // Keys are always text, so nothing else is ever in a dictionary:
+                    (_, Value::Map(_)) => false,
+                    (Value::Text(sub), Value::Text(text)) => text.contains(&**sub),
+                    _ => {
+                        state.raise(EvalErrorKind::OperatorMisuse {
+                            op: op.to_string(),
+                            left: Some(val),
+                            right: Some(collection),
+                        })?;
+                        return None;
+                    }
+                };
+
+                Value::Bool(is_contained == (op == BinaryOperator::IsContainedIn))
             }
 
             (Value::Integer(left), BinaryOperator::Plus, Value::Integer(right)) => {
-                Value::Integer(left + right)
+                match left.checked_add(right) {
+                    Some(result) => Value::Integer(result),
+                    None => {
+                        state.raise(EvalErrorKind::IntegerOverflow {
+                            operation: format!("{left} + {right}"),
+                        })?;
+                        return None;
+                    }
+                }
             }
             (Value::Integer(left), BinaryOperator::Plus, Value::Float(right)) => {
-                Value::Float(left as f64 + right)
+                Value::Float(self.promote(left, state)? + right)
             }
             (Value::Float(left), BinaryOperator::Plus, Value::Integer(right)) => {
-                Value::Float(left + right as f64)
+                Value::Float(left + self.promote(right, state)?)
             }
             (Value::Float(left), BinaryOperator::Plus, Value::Float(right)) => {
                 Value::Float(left + right)
             }
 
             (Value::Integer(left), BinaryOperator::Minus, Value::Integer(right)) => {
-                Value::Integer(left - right)
+                match left.checked_sub(right) {
+                    Some(result) => Value::Integer(result),
+                    None => {
+                        state.raise(EvalErrorKind::IntegerOverflow {
+                            operation: format!("{left} - {right}"),
+                        })?;
+                        return None;
+                    }
+                }
             }
             (Value::Integer(left), BinaryOperator::Minus, Value::Float(right)) => {
-                Value::Float(left as f64 - right)
+                Value::Float(self.promote(left, state)? - right)
             }
             (Value::Float(left), BinaryOperator::Minus, Value::Integer(right)) => {
-                Value::Float(left - right as f64)
+                Value::Float(left - self.promote(right, state)?)
             }
             (Value::Float(left), BinaryOperator::Minus, Value::Float(right)) => {
                 Value::Float(left - right)
             }
 
             (Value::Integer(left), BinaryOperator::Times, Value::Integer(right)) => {
-                Value::Integer(left * right)
+                match left.checked_mul(right) {
+                    Some(result) => Value::Integer(result),
+                    None => {
+                        state.raise(EvalErrorKind::IntegerOverflow {
+                            operation: format!("{left} * {right}"),
+                        })?;
+                        return None;
+                    }
+                }
             }
             (Value::Integer(left), BinaryOperator::Times, Value::Float(right)) => {
-                Value::Float(left as f64 * right)
+                Value::Float(self.promote(left, state)? * right)
             }
             (Value::Float(left), BinaryOperator::Times, Value::Integer(right)) => {
-                Value::Float(left * right as f64)
+                Value::Float(left * self.promote(right, state)?)
             }
             (Value::Float(left), BinaryOperator::Times, Value::Float(right)) => {
                 Value::Float(left * right)
@@ -395,13 +504,21 @@ impl BinaryOperation {
                 Value::Float(f64::NAN)
             }
             (Value::Integer(left), BinaryOperator::Divided, Value::Integer(right)) => {
-                Value::Integer(left / right)
+                match left.checked_div(right) {
+                    Some(result) => Value::Integer(result),
+                    None => {
+                        state.raise(EvalErrorKind::IntegerOverflow {
+                            operation: format!("{left} / {right}"),
+                        })?;
+                        return None;
+                    }
+                }
             }
             (Value::Integer(left), BinaryOperator::Divided, Value::Float(right)) => {
-                Value::Float(left as f64 / right)
+                Value::Float(self.promote(left, state)? / right)
             }
             (Value::Float(left), BinaryOperator::Divided, Value::Integer(right)) => {
-                Value::Float(left / right as f64)
+                Value::Float(left / self.promote(right, state)?)
             }
             (Value::Float(left), BinaryOperator::Divided, Value::Float(right)) => {
                 Value::Float(left / right)
@@ -411,13 +528,21 @@ impl BinaryOperation {
                 Value::Float(f64::NAN)
             }
             (Value::Integer(left), BinaryOperator::Remainder, Value::Integer(right)) => {
-                Value::Integer(left % right)
+                match left.checked_rem(right) {
+                    Some(result) => Value::Integer(result),
+                    None => {
+                        state.raise(EvalErrorKind::IntegerOverflow {
+                            operation: format!("{left} % {right}"),
+                        })?;
+                        return None;
+                    }
+                }
             }
             (Value::Integer(left), BinaryOperator::Remainder, Value::Float(right)) => {
-                Value::Float(left as f64 % right)
+                Value::Float(self.promote(left, state)? % right)
             }
             (Value::Float(left), BinaryOperator::Remainder, Value::Integer(right)) => {
-                Value::Float(left % right as f64)
+                Value::Float(left % self.promote(right, state)?)
             }
             (Value::Float(left), BinaryOperator::Remainder, Value::Float(right)) => {
                 Value::Float(left % right)
@@ -427,23 +552,75 @@ impl BinaryOperation {
                 let cat = left.as_ref().to_string() + &right;
                 Value::Text(rc_world::string_to_rc(cat))
             }
+            (Value::Bytes(left), BinaryOperator::Plus, Value::Bytes(right)) => {
+                Value::Bytes(left.iter().chain(right.iter()).copied().collect())
+            }
             (Value::List(left), BinaryOperator::Plus, Value::List(right)) => Value::List(Rc::from(
                 left.iter()
                     .chain(right.as_ref())
                     .cloned()
                     .collect::<Vec<_>>(),
             )),
+            // Keys of `left` keep their positions, even if `right` replaces their values:
             (Value::Map(left), BinaryOperator::Plus, Value::Map(right)) => Value::Map(Rc::new(
                 left.iter()
                     .chain(right.as_ref())
                     .map(|(key, value)| (key.clone(), value.clone()))
                     .collect(),
             )),
+            (Value::List(left), BinaryOperator::Minus, Value::List(right)) => Value::List(
+                left.iter()
+                    .filter(|item| !right.contains(item))
+                    .cloned()
+                    .collect(),
+            ),
+            (Value::Map(left), BinaryOperator::Minus, Value::List(keys)) => {
+                let mut to_remove = Vec::with_capacity(keys.len());
+                for key in keys.iter() {
+                    if let Value::Text(key) = key {
+                        to_remove.push(key.clone());
+                    } else {
+                        state.raise(EvalErrorKind::InvalidKeyToRemove { key: key.clone() })?;
+                    }
+                }
+
+                Value::Map(Rc::new(
+                    left.iter()
+                        .filter(|(key, _)| !to_remove.contains(key))
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect(),
+                ))
+            }
+            (Value::Map(left), BinaryOperator::Minus, Value::Map(right)) => Value::Map(Rc::new(
+                left.iter()
+                    .filter(|(key, _)| !right.contains_key(*key))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            )),
+            (Value::Text(text), BinaryOperator::Times, Value::Integer(n))
+            | (Value::Integer(n), BinaryOperator::Times, Value::Text(text)) => {
+                let times = repetitions(state, text.len(), n)?;
+                Value::Text(rc_world::string_to_rc(text.repeat(times)))
+            }
+            (Value::List(list), BinaryOperator::Times, Value::Integer(n))
+            | (Value::Integer(n), BinaryOperator::Times, Value::List(list)) => {
+                let times = repetitions(state, list.len(), n)?;
+                Value::List((0..times).flat_map(|_| list.iter().cloned()).collect())
+            }
+            (map @ Value::Map(_), BinaryOperator::Times, Value::Integer(n))
+            | (Value::Integer(n), BinaryOperator::Times, map @ Value::Map(_)) => {
+                state.raise(EvalErrorKind::RepeatedDictionary {
+                    dict: map,
+                    times: n,
+                })?;
+                return None;
+            }
             (left, op, right) => {
-                state.raise(format!(
-                    "Operator `{}` cannot be applied to `{}` and `{}`",
-                    op, left, right,
-                ))?;
+                state.raise(EvalErrorKind::OperatorMisuse {
+                    op: op.to_string(),
+                    left: Some(left),
+                    right: Some(right),
+                })?;
                 return None;
             }
         };
@@ -452,8 +629,97 @@ impl BinaryOperation {
     }
 }
 
+/// Applies a pattern match, native or not, to an argument.
+fn apply(callee: Value, arg: Value, state: &mut State<'_>) -> Option<Value> {
+    let (id, pats) = match callee {
+        Value::PatternMatches(id, pats) => (id, pats),
+        Value::NativePatternMatch(pat) => return pat.r#match(arg, state),
+        _ => unreachable!("only pattern matches can be applied"),
+    };
+
+    // Only pattern matches count, not the bindings and imports evaluated in between, so
+    // that the limit is the same for any program. This keeps programs such as
+    // `let f x = x x; f f` from overflowing the stack:
+    state.push_ctx(Context::SubstitutingPattern(Some(id.clone())));
+    let max = state.environment.max_call_depth();
+    if state.applying.borrow().len() >= max {
+        state.raise(EvalErrorKind::NestingTooDeep { max })?;
+    }
+
+    check_reentry(&id, &pats, state)?;
+    state.applying.borrow_mut().push((id.clone(), pats.clone()));
+
+    let mut failures = Vec::with_capacity(pats.len());
+
+    for (rule_index, pat) in pats.iter().enumerate() {
+        match pat.r#match(&id, rule_index, &arg, state)? {
+            Ok(found) => {
+                state.applying.borrow_mut().pop();
+                state.pop_ctx();
+                return Some(found);
+            }
+            Err(err) => failures.push(err),
+        }
+    }
+
+    // With a single rule, there is no need to say which rule failed:
+    if failures.len() == 1 {
+        state.raise(failures.pop().expect("there is one failure"))?;
+    } else {
+        state.raise(BindError::NoRuleMatched {
+            identifier: id,
+            failures,
+        })?;
+    }
+
+    None
+}
+
+/// Raises an error if a pattern match is applied while a later definition of itself, one
+/// with more rules, is being applied. This happens when a rule added to a pattern match
+/// calls another pattern match that captured the pattern match before the rule was added.
+fn check_reentry(id: &Rc<str>, pats: &[Rc<PatternMatch>], state: &mut State<'_>) -> Option<()> {
+    let applying = state.applying.borrow();
+    let is_earlier = |rules: &[Rc<PatternMatch>]| {
+        pats.len() < rules.len() && pats.iter().zip(rules).all(|(a, b)| Rc::ptr_eq(a, b))
+    };
+    let Some(start) = applying
+        .iter()
+        .position(|(outer, rules)| outer == id && is_earlier(rules))
+    else {
+        return Some(());
+    };
+
+    let through = applying[start + 1..]
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect();
+    drop(applying);
+    state.raise(EvalErrorKind::Recursion {
+        pattern: id.clone(),
+        through,
+    })
+}
+
+/// The maximum size (in bytes for text and in elements for lists) of the result of
+/// repeating a value with the `*` operator.
+pub(crate) const MAX_REPETITION_SIZE: usize = 1 << 24;
+
+/// Validates the number of times a value of length `len` is to be repeated, returning
+/// zero for non-positive counts and raising an error if the result would be too big.
+fn repetitions(state: &mut State<'_>, len: usize, n: i64) -> Option<usize> {
+    let times = usize::try_from(n).unwrap_or(0);
+
+    if len.saturating_mul(times) > MAX_REPETITION_SIZE {
+        state.raise(EvalErrorKind::RepetitionTooLarge { len, times: n })?;
+    }
+
+    Some(times)
+}
+
 /// An operation involving a Ryan expression and a prefix operator.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PrefixOperation {
     /// The prefix operator.
     pub op: PrefixOperator,
@@ -463,7 +729,7 @@ pub struct PrefixOperation {
 
 impl Display for PrefixOperation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} {}", self.op, self.right)
+        f.write_str(&Printer::FLAT.prefix_operation(self, 0, 0))
     }
 }
 
@@ -473,11 +739,23 @@ impl PrefixOperation {
 
         let result = match (&self.op, &right) {
             (PrefixOperator::Not, Value::Bool(b)) => Value::Bool(!*b),
+            (PrefixOperator::Negative, Value::Integer(int)) => match int.checked_neg() {
+                Some(result) => Value::Integer(result),
+                None => {
+                    state.raise(EvalErrorKind::IntegerOverflow {
+                        operation: format!("- {int}"),
+                    })?;
+                    return None;
+                }
+            },
+            (PrefixOperator::Negative, Value::Float(float)) => Value::Float(-*float),
+            (PrefixOperator::Positive, Value::Integer(_) | Value::Float(_)) => right.clone(),
             _ => {
-                state.raise(format!(
-                    "Operator `{}` cannot be applied to `{}`",
-                    self.op, right,
-                ))?;
+                state.raise(EvalErrorKind::OperatorMisuse {
+                    op: self.op.to_string(),
+                    left: None,
+                    right: Some(right.clone()),
+                })?;
                 return None;
             }
         };
@@ -487,7 +765,26 @@ impl PrefixOperation {
 }
 
 /// An operation involving a Ryan expression and a postfix operator.
+///
+/// Accesses, with `.` or `[...]`, bind tighter than anything else but casts and chain
+/// freely with each other:
+/// ```rust
+/// use ryan::parser::{parse, Expression, PostfixOperator};
+///
+/// let block = parse(r#"a.b[0].c["k"]"#).unwrap();
+/// assert_eq!(block.expression.to_string(), r#"a.b[0].c["k"]"#);
+/// let Expression::PostfixOperation(operation) = &block.expression else { panic!() };
+/// assert!(matches!(operation.op, PostfixOperator::Path(_)));
+/// assert_eq!(operation.left.to_string(), "a.b[0].c");
+///
+/// // Juxtaposition binds looser, so this is `f (a.b[0])`:
+/// let block = parse("f a.b[0]").unwrap();
+/// let Expression::BinaryOperation(operation) = &block.expression else { panic!() };
+/// assert_eq!(operation.left.to_string(), "f");
+/// assert_eq!(operation.right.to_string(), "a.b[0]");
+/// ```
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PostfixOperation {
     /// The expression on which the postfix operator is applied.
     pub left: Expression,
@@ -497,7 +794,7 @@ pub struct PostfixOperation {
 
 impl Display for PostfixOperation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}{}", self.left, self.op)
+        f.write_str(&Printer::FLAT.postfix_operation(self, 0, 0))
     }
 }
 
@@ -510,10 +807,24 @@ impl PostfixOperation {
                 if let Some(value) = dict.get(field) {
                     value.clone()
                 } else {
-                    state.raise(format!("Key `{}` not present in `{}`", field, left,))?;
+                    state.raise(EvalErrorKind::KeyMissing {
+                        key: field.clone(),
+                        value: left.clone(),
+                    })?;
                     return None;
                 }
             }
+            // `f[a, b]` is the same as `f [a, b]`, i.e., the pattern applied to a list:
+            (
+                Value::PatternMatches(..) | Value::NativePatternMatch(_),
+                PostfixOperator::Path(path),
+            ) => {
+                let arg = path
+                    .iter()
+                    .map(|item| item.eval(state))
+                    .collect::<Option<Rc<[_]>>>()?;
+                apply(left.clone(), Value::List(arg), state)?
+            }
             (left, PostfixOperator::Path(path)) => {
                 match left.extract_path(
                     &path
@@ -528,20 +839,95 @@ impl PostfixOperation {
                     }
                 }
             }
+            (Value::Null, PostfixOperator::OptionalAccess(_)) => Value::Null,
+            (Value::Map(dict), PostfixOperator::OptionalAccess(field)) => {
+                dict.get(field).cloned().unwrap_or(Value::Null)
+            }
+            (left, PostfixOperator::OptionalPath(path)) => {
+                match left.extract_optional_path(
+                    &path
+                        .iter()
+                        .map(|item| item.eval(state))
+                        .collect::<Option<Vec<_>>>()?,
+                ) {
+                    Ok(value) => value,
+                    Err(err) => {
+                        state.raise(err);
+                        return None;
+                    }
+                }
+            }
             (Value::Bool(b), PostfixOperator::CastInt) => Value::Integer(*b as i64),
-            (Value::Float(f), PostfixOperator::CastInt) => Value::Integer(*f as i64),
-            (Value::Integer(i), PostfixOperator::CastInt) => Value::Integer(*i as i64),
+            // Truncates towards zero. Note that `i64::MAX as f64` is 2^63, which is already
+            // out of range:
+            (Value::Float(f), PostfixOperator::CastInt)
+                if *f >= i64::MIN as f64 && *f < i64::MAX as f64 =>
+            {
+                Value::Integer(*f as i64)
+            }
+            (Value::Float(_), PostfixOperator::CastInt) => {
+                state.raise(EvalErrorKind::CastFailed {
+                    value: left.clone(),
+                    target: Type::Integer,
+                })?;
+                return None;
+            }
+            (Value::Integer(i), PostfixOperator::CastInt) => Value::Integer(*i),
             (Value::Bool(b), PostfixOperator::CastFloat) => Value::Float(*b as i64 as f64),
-            (Value::Float(f), PostfixOperator::CastFloat) => Value::Float(*f as f64),
+            (Value::Float(f), PostfixOperator::CastFloat) => Value::Float(*f),
             (Value::Integer(i), PostfixOperator::CastFloat) => Value::Float(*i as f64),
+            (Value::Text(text), PostfixOperator::CastInt) => {
+                match crate::utils::parse_int(text, 10) {
+                    Some(int) => Value::Integer(int),
+                    None => {
+                        state.raise(EvalErrorKind::CastFailed {
+                            value: left.clone(),
+                            target: Type::Integer,
+                        })?;
+                        return None;
+                    }
+                }
+            }
+            (Value::Text(text), PostfixOperator::CastFloat) => {
+                match crate::utils::parse_float(text) {
+                    Some(float) => Value::Float(float),
+                    None => {
+                        state.raise(EvalErrorKind::CastFailed {
+                            value: left.clone(),
+                            target: Type::Float,
+                        })?;
+                        return None;
+                    }
+                }
+            }
+            (Value::Bytes(bytes), PostfixOperator::CastText) => match std::str::from_utf8(bytes) {
+                Ok(text) => Value::Text(rc_world::str_to_rc(text)),
+                Err(_) => {
+                    state.raise(EvalErrorKind::CastFailed {
+                        value: left.clone(),
+                        target: Type::Text,
+                    })?;
+                    return None;
+                }
+            },
+            (left @ (Value::List(_) | Value::Map(_)), PostfixOperator::CastText) => {
+                match left.to_json() {
+                    Ok(json) => Value::Text(rc_world::string_to_rc(json)),
+                    Err(err) => {
+                        state.raise(EvalErrorKind::NotRepresentable(err))?;
+                        return None;
+                    }
+                }
+            }
             (left, PostfixOperator::CastText) => {
                 Value::Text(rc_world::string_to_rc(left.to_string()))
             }
             _ => {
-                state.raise(format!(
-                    "Operator `{}` cannot be applied to `{}`",
-                    self.op, left,
-                ))?;
+                state.raise(EvalErrorKind::OperatorMisuse {
+                    op: self.op.to_string(),
+                    left: Some(left.clone()),
+                    right: None,
+                })?;
                 return None;
             }
         };
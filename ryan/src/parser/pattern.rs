@@ -1,39 +1,129 @@
 use indexmap::IndexMap;
-use pest::iterators::Pairs;
+#[cfg(feature = "parse")]
+use pest::iterators::{Pair, Pairs};
 use std::fmt::Display;
 use std::rc::Rc;
 use thiserror::Error;
 
+#[cfg(feature = "parse")]
 use crate::rc_world;
 use crate::utils::QuotedStr;
 
+use super::expression::Expression;
+use super::format::is_identifier;
 use super::literal::Literal;
 use super::types::Type;
 use super::types::TypeExpression;
 use super::value::Value;
+#[cfg(feature = "parse")]
 use super::ErrorLogger;
+#[cfg(feature = "parse")]
 use super::Rule;
 use super::State;
 
+/// The reasons why a value may fail to match a pattern.
+#[allow(missing_docs)]
 #[derive(Debug, Error)]
 pub enum BindError {
-    #[error("Variable {id} bound to {val} is not of type {typ}")]
+    /// The value bound to a variable is not of the type the variable declares.
+    #[error("Variable {id} bound to {} is not of type {typ}", .val.brief())]
     WrongType { id: Rc<str>, val: Value, typ: Type },
+    /// The list has a different length than the pattern.
     #[error("Pattern expected list with {expected} elements, got list with {got}")]
     WrongListLength { expected: usize, got: usize },
+    /// The list is shorter than the head or tail of the pattern.
     #[error("Pattern expected list with at least {expected} elements, got list with {got}")]
     TooFewValuesInList { expected: usize, got: usize },
-    #[error("Pattern expect key {key} in {value}")]
+    /// The dictionary lacks a key the pattern expects.
+    #[error("Pattern expect key {key} in {}", .value.brief())]
     MissingKey { key: Rc<str>, value: Value },
-    #[error("Pattern expected a strict match of {pattern} on {value}")]
+    /// The dictionary has keys other than the ones a strict pattern expects.
+    #[error("Pattern expected a strict match of {pattern} on {}", .value.brief())]
     MatchIsNonStrict { pattern: Pattern, value: Value },
-    #[error("Pattern expected {pattern}, got {value}")]
+    /// The value has a different shape than the pattern.
+    #[error("Pattern expected {pattern}, got {}", .value.brief())]
     NoMatch { pattern: Pattern, value: Value },
+    /// A native pattern match got a list with the wrong number of arguments.
+    #[error(
+        "Pattern match {identifier} takes a list with {expected} elements, {pattern}, \
+        but got a list with {got} elements"
+    )]
+    WrongArity {
+        identifier: Rc<str>,
+        pattern: Pattern,
+        expected: usize,
+        got: usize,
+    },
+    /// The guard of the pattern evaluated to `false`.
+    #[error("Guard of pattern {pattern} is false for {}", .value.brief())]
+    GuardFailed { pattern: Pattern, value: Value },
+    /// None of the rules of a pattern match with more than one rule matched. The
+    /// failures are in the order the rules were defined.
+    #[error("No rule of pattern match {identifier} matches: {}", fmt_failures(.failures))]
+    NoRuleMatched {
+        identifier: Rc<str>,
+        failures: Vec<BindError>,
+    },
+    /// None of the arms of a `match` expression matched the value. The patterns are in
+    /// the order the arms were defined.
+    #[error(
+        "No arm of `match` matches {}, tried: {}",
+        .value.brief(),
+        fmt_patterns(.patterns)
+    )]
+    NoArmMatched {
+        value: Value,
+        patterns: Vec<Pattern>,
+    },
+}
+
+/// The maximum number of rule failures listed in a [`BindError::NoRuleMatched`] error,
+/// or of patterns listed in a [`BindError::NoArmMatched`] error.
+const MAX_REPORTED_FAILURES: usize = 5;
+
+/// Formats the failures of each rule of a pattern match as a numbered list.
+fn fmt_failures(failures: &[BindError]) -> String {
+    let mut formatted = failures
+        .iter()
+        .take(MAX_REPORTED_FAILURES)
+        .enumerate()
+        .map(|(i, failure)| format!("rule {}: {failure}", i + 1))
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    if failures.len() > MAX_REPORTED_FAILURES {
+        formatted += &format!(
+            "; and {} more rules",
+            failures.len() - MAX_REPORTED_FAILURES
+        );
+    }
+
+    formatted
+}
+
+/// Formats the patterns of the arms of a `match` expression as a list.
+fn fmt_patterns(patterns: &[Pattern]) -> String {
+    let mut formatted = patterns
+        .iter()
+        .take(MAX_REPORTED_FAILURES)
+        .map(|pattern| format!("`{pattern}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if patterns.len() > MAX_REPORTED_FAILURES {
+        formatted += &format!(
+            " and {} more patterns",
+            patterns.len() - MAX_REPORTED_FAILURES
+        );
+    }
+
+    formatted
 }
 
 /// An expression expecting a certain structure of a given value and optionally binding
 /// variables to selected bits and pieces of this value.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Pattern {
     /// Matches any value and provides no biding. This is represented by `_` in Ryan.
     Wildcard,
@@ -47,17 +137,26 @@ pub enum Pattern {
     /// patterns. This is represented by, e.g., `[a, b, c]` in Ryan.
     MatchList(Vec<Pattern>),
     /// Expects a list of at least a given size and proceeds to bind the beginning of the
-    /// list to patterns. This is represented by, e.g., `[a, b, c, ..]` in Ryan.
-    MatchHead(Vec<Pattern>),
+    /// list to patterns. This is represented by, e.g., `[a, b, c, ..]` in Ryan. The
+    /// remainder of the list can be bound to a variable, e.g., `[a, b, c, ...rest]`.
+    MatchHead(Vec<Pattern>, Option<Rc<str>>),
     /// Expects a list of at least a given size and proceeds to bind the end of the list
-    /// to patterns. This is represented by, e.g., `[.., a, b, c]` in Ryan.
-    MatchTail(Vec<Pattern>),
+    /// to patterns. This is represented by, e.g., `[.., a, b, c]` in Ryan. The beginning
+    /// of the list can be bound to a variable, e.g., `[...rest, a, b, c]`.
+    MatchTail(Option<Rc<str>>, Vec<Pattern>),
     /// Expects a dictionary with at least the provided keys and proceeds to bind each
-    /// value to a pattern. This is represented by, e.g., `{ a, "b": c, .. }` in Ryan.
-    MatchDict(Vec<MatchDictItem>),
+    /// value to a pattern. This is represented by, e.g., `{ a, "b": c, .. }` in Ryan. The
+    /// entries not matched by any key can be bound to a variable, e.g.,
+    /// `{ a, "b": c, ...rest }`. Keys with a default, e.g., `{ a ? 1, .. }`, may be
+    /// missing.
+    MatchDict(Vec<MatchDictItem>, Option<Rc<str>>),
     /// Expects a dictionary with exactly the provided keys and proceeds to bind each
-    /// value to a pattern. This is represented by, e.g., `{ a, "b": c }` in Ryan.
+    /// value to a pattern. This is represented by, e.g., `{ a, "b": c }` in Ryan. Keys
+    /// with a default, e.g., `{ a ? 1 }`, may be missing.
     MatchDictStrict(Vec<MatchDictItem>),
+    /// Tries each pattern in order, using the first one that matches. All alternatives
+    /// must bind the same variables. This is represented by, e.g., `[x] | x` in Ryan.
+    Or(Vec<Pattern>),
 }
 
 impl Display for Pattern {
@@ -72,45 +171,43 @@ impl Display for Pattern {
                 crate::utils::fmt_list(f, list)?;
                 write!(f, "]")?;
             }
-            Self::MatchHead(list) => {
+            Self::MatchHead(list, rest) => {
                 write!(f, "[")?;
                 crate::utils::fmt_list(f, list)?;
-                if list.is_empty() {
-                    write!(f, " .. ]")?;
-                } else {
-                    write!(f, ", .. ]")?;
+                if !list.is_empty() {
+                    write!(f, ",")?;
                 }
+                write!(f, " {}]", RestDisplay(rest))?;
             }
-            Self::MatchTail(list) => {
-                if list.is_empty() {
-                    write!(f, "[ ..")?;
-                } else {
-                    write!(f, "[ .., ")?;
+            Self::MatchTail(rest, list) => {
+                write!(f, "[{}", RestDisplay(rest))?;
+                if !list.is_empty() {
+                    write!(f, ", ")?;
                 }
                 crate::utils::fmt_list(f, list)?;
                 write!(f, "]")?;
             }
-            Self::MatchDict(dict) => {
+            Self::MatchDict(dict, rest) => {
                 write!(f, "{{ ")?;
-                crate::utils::fmt_map(
-                    f,
-                    dict.iter()
-                        .map(|item| (QuotedStr(&item.key), &item.pattern)),
-                )?;
-                if dict.is_empty() {
-                    write!(f, ".. }}")?;
-                } else {
-                    write!(f, ", .. }}")?;
+                crate::utils::fmt_list(f, dict)?;
+                if !dict.is_empty() {
+                    write!(f, ", ")?;
                 }
+                write!(f, "{} }}", RestDisplay(rest))?;
             }
+            Self::MatchDictStrict(dict) if dict.is_empty() => write!(f, "{{}}")?,
             Self::MatchDictStrict(dict) => {
-                write!(f, "{{")?;
-                crate::utils::fmt_map(
-                    f,
-                    dict.iter()
-                        .map(|item| (QuotedStr(&item.key), &item.pattern)),
-                )?;
-                write!(f, "}}")?;
+                write!(f, "{{ ")?;
+                crate::utils::fmt_list(f, dict)?;
+                write!(f, " }}")?;
+            }
+            Self::Or(alternatives) => {
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " | ")?;
+                    }
+                    write!(f, "{alternative}")?;
+                }
             }
         }
 
@@ -119,9 +216,50 @@ impl Display for Pattern {
 }
 
 impl Pattern {
-    pub(super) fn parse(error_logger: &mut ErrorLogger, mut pairs: Pairs<'_, Rule>) -> Self {
-        let pair = pairs.next().expect("there is always a token in a pattern");
+    #[cfg(feature = "parse")]
+    pub(super) fn parse(error_logger: &mut ErrorLogger, pairs: Pairs<'_, Rule>) -> Self {
+        let pairs = pairs.collect::<Vec<_>>();
+
+        if pairs.len() == 1 {
+            let pair = pairs.into_iter().next().expect("there is one alternative");
+            return Pattern::parse_alternative(error_logger, pair);
+        }
+
+        let mut alternatives = vec![];
+        let mut expected_provided: Option<Vec<Rc<str>>> = None;
+
+        for pair in pairs {
+            let alternative = Pattern::parse_alternative(error_logger, pair.clone());
+
+            let mut provided = vec![];
+            alternative.provided(&mut provided);
+            provided.sort();
+            provided.dedup();
+
+            if let Some(expected) = &expected_provided {
+                if expected != &provided {
+                    error_logger.absorb(
+                        &pair,
+                        Err::<(), _>(format!(
+                            "All alternatives of a pattern must bind the same variables, \
+                            but `{alternative}` binds {} while the first alternative binds {}",
+                            fmt_identifiers(&provided),
+                            fmt_identifiers(expected),
+                        )),
+                    );
+                }
+            } else {
+                expected_provided = Some(provided);
+            }
+
+            alternatives.push(alternative);
+        }
+
+        Pattern::Or(alternatives)
+    }
 
+    #[cfg(feature = "parse")]
+    fn parse_alternative(error_logger: &mut ErrorLogger, pair: Pair<'_, Rule>) -> Self {
         match pair.as_rule() {
             Rule::wildcard => Pattern::Wildcard,
             Rule::matchIdentifier => {
@@ -150,21 +288,24 @@ impl Pattern {
                     .map(|pair| Pattern::parse(error_logger, pair.into_inner()))
                     .collect(),
             ),
-            Rule::matchHead => Pattern::MatchHead(
-                pair.into_inner()
-                    .map(|pair| Pattern::parse(error_logger, pair.into_inner()))
-                    .collect(),
-            ),
-            Rule::matchTail => Pattern::MatchTail(
-                pair.into_inner()
-                    .map(|pair| Pattern::parse(error_logger, pair.into_inner()))
-                    .collect(),
-            ),
-            Rule::matchDict => Pattern::MatchDict(
-                pair.into_inner()
-                    .map(|pair| MatchDictItem::parse(error_logger, pair.into_inner()))
-                    .collect(),
-            ),
+            Rule::matchHead => {
+                let (list, rest) = parse_with_rest(pair.into_inner(), |pair| {
+                    Pattern::parse(error_logger, pair.into_inner())
+                });
+                Pattern::MatchHead(list, rest)
+            }
+            Rule::matchTail => {
+                let (list, rest) = parse_with_rest(pair.into_inner(), |pair| {
+                    Pattern::parse(error_logger, pair.into_inner())
+                });
+                Pattern::MatchTail(rest, list)
+            }
+            Rule::matchDict => {
+                let (dict, rest) = parse_with_rest(pair.into_inner(), |pair| {
+                    MatchDictItem::parse(error_logger, pair.into_inner())
+                });
+                Pattern::MatchDict(dict, rest)
+            }
             Rule::matchDictStrict => Pattern::MatchDictStrict(
                 pair.into_inner()
                     .map(|pair| MatchDictItem::parse(error_logger, pair.into_inner()))
@@ -184,26 +325,29 @@ impl Pattern {
                     item.provided(identifiers);
                 }
             }
-            Self::MatchHead(list) => {
+            Self::MatchHead(list, rest) | Self::MatchTail(rest, list) => {
                 for item in list {
                     item.provided(identifiers);
                 }
+                identifiers.extend(rest.iter().cloned());
             }
-            Self::MatchTail(list) => {
-                for item in list {
-                    item.provided(identifiers);
-                }
-            }
-            Self::MatchDict(dict) => {
+            Self::MatchDict(dict, rest) => {
                 for item in dict {
                     item.pattern.provided(identifiers);
                 }
+                identifiers.extend(rest.iter().cloned());
             }
             Self::MatchDictStrict(dict) => {
                 for item in dict {
                     item.pattern.provided(identifiers);
                 }
             }
+            Self::Or(alternatives) => {
+                // All alternatives provide the same identifiers.
+                if let Some(first) = alternatives.first() {
+                    first.provided(identifiers);
+                }
+            }
         }
     }
 
@@ -214,8 +358,33 @@ impl Pattern {
         provided: &[Rc<str>],
         values: &mut IndexMap<Rc<str>, Value>,
     ) -> Option<()> {
-        if let Self::Identifier(_, Some(type_guard)) = self {
-            type_guard.capture(state, provided, values)?;
+        match self {
+            Self::Identifier(_, Some(type_guard)) => {
+                type_guard.capture(state, provided, values)?;
+            }
+            Self::MatchList(list) | Self::MatchHead(list, _) | Self::MatchTail(_, list) => {
+                for item in list {
+                    item.capture(state, provided, values)?;
+                }
+            }
+            Self::MatchDict(dict, _) | Self::MatchDictStrict(dict) => {
+                // Defaults may refer to the variables bound by the other keys:
+                let mut provided = provided.to_vec();
+                self.provided(&mut provided);
+
+                for item in dict {
+                    item.pattern.capture(state, &provided, values)?;
+                    if let Some(default) = &item.default {
+                        default.capture(state, &mut provided.clone(), values)?;
+                    }
+                }
+            }
+            Self::Or(alternatives) => {
+                for alternative in alternatives {
+                    alternative.capture(state, provided, values)?;
+                }
+            }
+            _ => {}
         }
 
         Some(())
@@ -232,7 +401,7 @@ impl Pattern {
             (Pattern::Identifier(id, type_guard), val) => {
                 if let Some(guard) = type_guard {
                     let typ = guard.eval(state)?;
-                    if !typ.matches(&val) {
+                    if !typ.matches(val) {
                         return Some(Err(BindError::WrongType {
                             id: id.clone(),
                             val: val.clone(),
@@ -258,13 +427,20 @@ impl Pattern {
                     }));
                 }
             }
-            (Pattern::MatchHead(pat_list), Value::List(val_list)) => {
+            (Pattern::MatchHead(pat_list, rest), Value::List(val_list)) => {
                 if pat_list.len() <= val_list.len() {
                     for (pat, val) in pat_list.iter().zip(val_list.iter()) {
                         if let Err(err) = pat.bind(val, bindings, state)? {
                             return Some(Err(err));
                         }
                     }
+
+                    if let Some(rest) = rest {
+                        bindings.insert(
+                            rest.clone(),
+                            Value::List(val_list[pat_list.len()..].into()),
+                        );
+                    }
                 } else {
                     return Some(Err(BindError::TooFewValuesInList {
                         expected: pat_list.len(),
@@ -272,13 +448,20 @@ impl Pattern {
                     }));
                 }
             }
-            (Pattern::MatchTail(pat_list), Value::List(val_list)) => {
+            (Pattern::MatchTail(rest, pat_list), Value::List(val_list)) => {
                 if pat_list.len() <= val_list.len() {
                     for (pat, val) in pat_list.iter().rev().zip(val_list.iter().rev()) {
                         if let Err(err) = pat.bind(val, bindings, state)? {
                             return Some(Err(err));
                         }
                     }
+
+                    if let Some(rest) = rest {
+                        bindings.insert(
+                            rest.clone(),
+                            Value::List(val_list[..val_list.len() - pat_list.len()].into()),
+                        );
+                    }
                 } else {
                     return Some(Err(BindError::TooFewValuesInList {
                         expected: pat_list.len(),
@@ -286,41 +469,61 @@ impl Pattern {
                     }));
                 }
             }
-            (Pattern::MatchDict(list), Value::Map(val_dict)) => {
+            (Pattern::MatchDict(list, rest), Value::Map(val_dict)) => {
                 for item in list {
-                    if let Some(val) = val_dict.get(&item.key) {
-                        if let Err(err) = item.pattern.bind(val, bindings, state)? {
-                            return Some(Err(err));
-                        }
-                    } else {
-                        return Some(Err(BindError::MissingKey {
-                            key: item.key.clone(),
-                            value: Value::Map(val_dict.clone()),
-                        }));
+                    if let Err(err) = item.bind(val_dict, bindings, state)? {
+                        return Some(Err(err));
                     }
                 }
+
+                if let Some(rest) = rest {
+                    let remainder = val_dict
+                        .iter()
+                        .filter(|(key, _)| list.iter().all(|item| &item.key != *key))
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect();
+                    bindings.insert(rest.clone(), Value::Map(Rc::new(remainder)));
+                }
             }
             (Pattern::MatchDictStrict(list), Value::Map(val_dict)) => {
                 for item in list {
-                    if let Some(val) = val_dict.get(&item.key) {
-                        if let Err(err) = item.pattern.bind(val, bindings, state)? {
-                            return Some(Err(err));
-                        }
-                    } else {
-                        return Some(Err(BindError::MissingKey {
-                            key: item.key.clone(),
-                            value: Value::Map(val_dict.clone()),
-                        }));
+                    if let Err(err) = item.bind(val_dict, bindings, state)? {
+                        return Some(Err(err));
                     }
                 }
 
-                if list.len() != val_dict.len() {
+                // Keys filled in by defaults are not in the dictionary:
+                if !val_dict
+                    .keys()
+                    .all(|key| list.iter().any(|item| &item.key == key))
+                {
                     return Some(Err(BindError::MatchIsNonStrict {
                         pattern: self.clone(),
                         value: value.clone(),
                     }));
                 }
             }
+            (Pattern::Or(alternatives), val) => {
+                let mut last_error = None;
+
+                for alternative in alternatives {
+                    // Bind to a scratch space, so that a partial match leaves no garbage
+                    // behind.
+                    let mut alternative_bindings = IndexMap::default();
+                    match alternative.bind(val, &mut alternative_bindings, state)? {
+                        Ok(()) => {
+                            bindings.extend(alternative_bindings);
+                            return Some(Ok(()));
+                        }
+                        Err(err) => last_error = Some(err),
+                    }
+                }
+
+                return Some(Err(last_error.unwrap_or_else(|| BindError::NoMatch {
+                    pattern: self.clone(),
+                    value: value.clone(),
+                })));
+            }
             (_, _) => {
                 return Some(Err(BindError::NoMatch {
                     pattern: self.clone(),
@@ -333,23 +536,105 @@ impl Pattern {
     }
 }
 
+/// Formats a list of identifiers for error messages.
+#[cfg(feature = "parse")]
+fn fmt_identifiers(identifiers: &[Rc<str>]) -> String {
+    if identifiers.is_empty() {
+        "no variables".to_owned()
+    } else {
+        identifiers
+            .iter()
+            .map(|id| format!("`{id}`"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Parses the items of a pattern that may end in a rest binding, like `...rest`.
+#[cfg(feature = "parse")]
+fn parse_with_rest<T>(
+    pairs: Pairs<'_, Rule>,
+    mut parse_item: impl FnMut(Pair<'_, Rule>) -> T,
+) -> (Vec<T>, Option<Rc<str>>) {
+    let mut items = vec![];
+    let mut rest = None;
+
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::matchRest => {
+                let identifier = pair
+                    .into_inner()
+                    .next()
+                    .expect("there is always an identifier in a rest binding");
+                rest = Some(rc_world::str_to_rc(identifier.as_str()));
+            }
+            _ => items.push(parse_item(pair)),
+        }
+    }
+
+    (items, rest)
+}
+
+/// Displays the rest part of a pattern, either `..` or `...rest`.
+struct RestDisplay<'a>(&'a Option<Rc<str>>);
+
+impl Display for RestDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(rest) => write!(f, "...{rest}"),
+            None => write!(f, ".."),
+        }
+    }
+}
+
 /// A pattern matching a dictionary entry. This can take the form of `x`, which binds the
 /// value associated to the key `x` to the variable `x` or `x: pattern` which bind the
 /// value associated with `x`to another pattern. Of note is that, in this position,
-/// `pattern` cannot be an identifier pattern.
+/// `pattern` cannot be an identifier pattern. Either form can be followed by a default,
+/// e.g., `x ? 1`, used when the key is missing.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MatchDictItem {
-    /// The key which must exist in the dictionary.
+    /// The key which must exist in the dictionary, unless there is a default.
     pub key: Rc<str>,
     /// The pattern to which the value associated with the key will be matched against.
     pub pattern: Pattern,
+    /// The value matched against the pattern when the key is missing. This is only
+    /// evaluated if needed and can refer to the variables bound by the other keys.
+    pub default: Option<Expression>,
+}
+
+impl Display for MatchDictItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.pattern {
+            // Shorthand for `{ x: x }`:
+            Pattern::Identifier(id, None) if *id == self.key && is_identifier(id) => {
+                write!(f, "{id}")?
+            }
+            Pattern::Identifier(id, Some(r#type)) if *id == self.key && is_identifier(id) => {
+                write!(f, "{id}: {type}")?
+            }
+            // An identifier after `key:` would read as the shorthand with a type:
+            Pattern::Identifier(..) => write!(f, "{}: {}", QuotedStr(&self.key), self.pattern)?,
+            pattern if is_identifier(&self.key) => write!(f, "{}: {pattern}", self.key)?,
+            pattern => write!(f, "{}: {pattern}", QuotedStr(&self.key))?,
+        }
+
+        if let Some(default) = &self.default {
+            write!(f, " ? {default}")?;
+        }
+
+        Ok(())
+    }
 }
 
 impl MatchDictItem {
+    #[cfg(feature = "parse")]
     fn parse(logger: &mut ErrorLogger, pairs: Pairs<'_, Rule>) -> Self {
         let mut key = None;
         let mut text = None;
         let mut pattern = None;
+        let mut default = None;
 
         for pair in pairs {
             match pair.as_rule() {
@@ -357,7 +642,16 @@ impl MatchDictItem {
                 Rule::pattern => pattern = Some(Pattern::parse(logger, pair.into_inner())),
                 Rule::text => {
                     text = Some(rc_world::string_to_rc(
-                        logger.absorb(&pair, crate::utils::unescape(pair.as_str())),
+                        logger.absorb(&pair, crate::text::unescape(pair.as_str())),
+                    ))
+                }
+                Rule::matchDefault => {
+                    default = Some(Expression::parse(
+                        logger,
+                        pair.into_inner()
+                            .next()
+                            .expect("there is always an expression in a default")
+                            .into_inner(),
                     ))
                 }
                 Rule::matchIdentifier => {
@@ -379,10 +673,8 @@ impl MatchDictItem {
 
                     let identifier = identifier.expect("identifier match has an identifier");
 
-                    return MatchDictItem {
-                        key: identifier.clone(),
-                        pattern: Pattern::Identifier(identifier, type_guard),
-                    };
+                    key = Some(identifier.clone());
+                    pattern = Some(Pattern::Identifier(identifier, type_guard));
                 }
                 _ => unreachable!(),
             }
@@ -397,6 +689,28 @@ impl MatchDictItem {
             pattern: pattern
                 // .or(key.map(Pattern::Identifier))
                 .expect("a match dict always has a pattern"),
+            default,
         }
     }
+
+    /// Binds the value under the key of this item, or the default if the key is missing.
+    fn bind(
+        &self,
+        dict: &Rc<IndexMap<Rc<str>, Value>>,
+        bindings: &mut IndexMap<Rc<str>, Value>,
+        state: &mut State<'_>,
+    ) -> Option<Result<(), BindError>> {
+        let value = match (dict.get(&self.key), &self.default) {
+            (Some(value), _) => value.clone(),
+            (None, Some(default)) => default.eval(&mut state.new_local(bindings.clone()))?,
+            (None, None) => {
+                return Some(Err(BindError::MissingKey {
+                    key: self.key.clone(),
+                    value: Value::Map(dict.clone()),
+                }))
+            }
+        };
+
+        self.pattern.bind(&value, bindings, state)
+    }
 }
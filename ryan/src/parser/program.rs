@@ -0,0 +1,83 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::environment::incremental::Memo;
+use crate::environment::{Environment, IncrementalStats};
+use crate::Error;
+
+use super::{eval, parse, Block, Value};
+
+/// A Ryan program, parsed once to be evaluated many times, e.g., whenever the
+/// configuration it reads changes.
+///
+/// Evaluating incrementally with [`Program::eval_incremental`] also reuses the values of
+/// the modules it imports from the previous evaluations, as long as nothing they read,
+/// directly or through their own imports, changed since:
+///
+/// ```
+/// use ryan::environment::{Environment, ImportLoader, NoImport};
+/// use ryan::parser::{Program, Value};
+///
+/// let loader = NoImport
+///     .r#override("base.ryan".to_owned(), "{ replicas: 3 }".to_owned())
+///     .r#override("env:REGION".to_owned(), "eu".to_owned());
+/// let env = Environment::builder().import_loader(loader).build();
+/// let mut program = Program::parse(r#"
+///     let base = import "base.ryan";
+///     let region = import "env:REGION" as text;
+///     base + { region }
+/// "#).unwrap();
+///
+/// program.eval_incremental(&env).unwrap();
+/// assert_eq!(program.incremental_stats().recomputed, 2);
+///
+/// program.eval_incremental(&env).unwrap();
+/// assert_eq!(program.incremental_stats().reused, 2);
+/// ```
+#[derive(Debug)]
+pub struct Program {
+    block: Block,
+    memo: Rc<RefCell<Memo>>,
+}
+
+impl Program {
+    /// Parses a Ryan program.
+    pub fn parse(code: &str) -> Result<Program, Error> {
+        Ok(Program {
+            block: parse(code).map_err(Error::Parse)?,
+            memo: Rc::default(),
+        })
+    }
+
+    /// The parsed code of this program.
+    pub fn block(&self) -> &Block {
+        &self.block
+    }
+
+    /// Evaluates this program from scratch in the supplied environment.
+    pub fn eval(&self, env: &Environment) -> Result<Value, Error> {
+        eval(env.clone(), &self.block).map_err(Error::Eval)
+    }
+
+    /// Evaluates this program in the supplied environment, reusing the value of every
+    /// imported module whose inputs did not change since the last evaluation. The
+    /// content of every module read by a reused module is read again to tell whether it
+    /// changed, but nothing is parsed or evaluated again. Modules whose inputs changed
+    /// are evaluated again, along with every module importing them.
+    ///
+    /// The cache of imported modules of the environment is bypassed, so that changes are
+    /// always picked up. Modules imported with arguments, with environment variables in
+    /// their paths or calling effectful native pattern matches, as well as the modules
+    /// importing them, are never reused. Always use the same environment (or a clone of
+    /// it) with the same program.
+    pub fn eval_incremental(&mut self, env: &Environment) -> Result<Value, Error> {
+        self.memo.borrow_mut().start();
+        eval(env.incremental(self.memo.clone()), &self.block).map_err(Error::Eval)
+    }
+
+    /// How many imported modules the last call to [`Program::eval_incremental`] reused
+    /// and how many it evaluated again.
+    pub fn incremental_stats(&self) -> IncrementalStats {
+        self.memo.borrow().stats()
+    }
+}
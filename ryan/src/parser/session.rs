@@ -0,0 +1,65 @@
+use indexmap::IndexMap;
+use std::rc::Rc;
+
+use crate::environment::Environment;
+use crate::Error;
+
+use super::{parse, EvalError, State, Value};
+
+/// An interactive Ryan session, where code is evaluated piece by piece and the bindings
+/// defined in each piece remain available to the following ones. This is the same as if
+/// all the pieces were written one after the other in the same file.
+///
+/// ```
+/// use ryan::parser::{Session, Value};
+///
+/// let mut session = Session::new(ryan::Environment::new(None));
+/// session.eval_line("let x = 1;").unwrap();
+/// assert_eq!(session.eval_line("x + 1").unwrap(), Value::Integer(2));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Session {
+    environment: Environment,
+    bindings: IndexMap<Rc<str>, Value>,
+}
+
+impl Session {
+    /// Creates a new session with nothing bound, running in the supplied environment.
+    pub fn new(environment: Environment) -> Session {
+        Session {
+            environment,
+            bindings: IndexMap::new(),
+        }
+    }
+
+    /// Evaluates a piece of code in this session, keeping its bindings for the next
+    /// pieces. If the code has no final expression, e.g. `let x = 1;`, this returns
+    /// `null`. If evaluation fails, nothing from this piece of code is kept.
+    pub fn eval_line(&mut self, line: &str) -> Result<Value, Error> {
+        let (value, bindings) = self.run(line)?;
+        self.bindings = bindings;
+        Ok(value)
+    }
+
+    /// Evaluates a piece of code in this session, but discards its bindings afterwards.
+    pub fn peek(&self, line: &str) -> Result<Value, Error> {
+        let (value, _) = self.run(line)?;
+        Ok(value)
+    }
+
+    /// The names bound so far in this session, in the order they were first bound.
+    pub fn bound(&self) -> impl Iterator<Item = &str> {
+        self.bindings.keys().map(|key| &**key)
+    }
+
+    fn run(&self, line: &str) -> Result<(Value, IndexMap<Rc<str>, Value>), Error> {
+        let block = parse(line).map_err(Error::Parse)?;
+        let mut state = State::new(self.environment.clone());
+        state.bindings = self.bindings.clone();
+
+        match block.eval(&mut state) {
+            Some(value) => Ok((value, state.bindings)),
+            None => Err(Error::Eval(EvalError::from_state(&state))),
+        }
+    }
+}
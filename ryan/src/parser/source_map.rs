@@ -0,0 +1,434 @@
+use pest::iterators::Pair;
+use pest::Parser as _;
+use std::rc::Rc;
+
+use crate::environment::native::BUILT_INS;
+use crate::rc_world;
+
+use super::error::ErrorEntry;
+use super::{ParseError, Parser, Rule};
+
+/// What kind of construct binds a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingKind {
+    /// A variable bound by a destructuring binding, e.g., `let x = 1;`.
+    Variable,
+    /// A pattern match definition, e.g., `let f x = x + 1;`.
+    PatternMatch,
+    /// A type definition, e.g., `type Id = int;`.
+    Type,
+    /// A variable bound by the pattern of a pattern match or of an anonymous pattern
+    /// match, e.g., the `x` in `\x => x + 1`.
+    Argument,
+    /// A variable bound by a `for` clause in a comprehension.
+    LoopVariable,
+}
+
+/// A name bound somewhere in a Ryan program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindingInfo {
+    /// The name being bound.
+    pub name: Rc<str>,
+    /// The beginning and end of the name where it is bound.
+    pub span: (usize, usize),
+    /// What kind of construct binds the name.
+    pub kind: BindingKind,
+}
+
+/// A use of a name somewhere in a Ryan program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// The name being used.
+    pub name: Rc<str>,
+    /// The beginning and end of the name where it is used.
+    pub span: (usize, usize),
+    /// The index in [`SourceMap::bindings`] of the binding in scope for this name, if the
+    /// name is bound in the program.
+    pub binding: Option<usize>,
+}
+
+/// Where each name is bound and used in a Ryan program, for editor integrations such as
+/// go-to-definition and hover. Use [`super::parse_with_source_map`] to get one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// All the names bound in the program, in the order they appear.
+    pub bindings: Vec<BindingInfo>,
+    /// All the names used in the program, in the order they appear.
+    pub references: Vec<Reference>,
+    /// The paths of all imports and `use` statements in the program, together with the
+    /// beginning and end of the quoted path in the source.
+    pub imports: Vec<((usize, usize), Rc<str>)>,
+    /// The indices in `bindings` of the bindings at the top level of the program.
+    root: Vec<usize>,
+    /// The indices in `bindings` of the variables bound directly to the value of an
+    /// import, e.g., `let x = import "x.ryan";`.
+    imported: Vec<usize>,
+    /// Pairs of indices in `bindings` of consecutive rules of the same pattern match, e.g.,
+    /// `let f 0 = 1; let f n = n;`, from the first to the last.
+    rules: Vec<(usize, usize)>,
+}
+
+/// Where a name under the cursor comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Definition {
+    /// The name is bound in the program itself.
+    Binding(BindingInfo),
+    /// The name is one of the default built-ins.
+    Builtin,
+    /// The name is not bound anywhere, which is an error when the program runs.
+    Unbound,
+    /// The cursor is on the path of an import. The imported module is not loaded.
+    Import(Rc<str>),
+}
+
+/// What was found under the cursor by [`SourceMap::resolve_at`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedSymbol {
+    /// The name (or import path) under the cursor.
+    pub name: Rc<str>,
+    /// The beginning and end of the name (or the quoted import path) under the cursor.
+    pub span: (usize, usize),
+    /// Where the name comes from.
+    pub definition: Definition,
+}
+
+impl SourceMap {
+    /// Builds the source map of a Ryan program.
+    pub fn parse(s: &str) -> Result<SourceMap, ParseError> {
+        let mut parsed = Parser::parse(Rule::root, s)
+            .map_err(|e| ParseError::new(s, vec![ErrorEntry::from(e)]))?;
+        let main = parsed.next().expect("there is always a matching token");
+
+        Ok(SourceMap::from_root(main))
+    }
+
+    /// Builds the source map of a Ryan program, given its parse tree.
+    pub(super) fn from_root(main: Pair<'_, Rule>) -> SourceMap {
+        let mut builder = Builder {
+            map: SourceMap::default(),
+            scopes: vec![vec![]],
+        };
+
+        for pair in main.into_inner() {
+            builder.block_item(pair);
+        }
+
+        builder.map.root = builder.scopes.pop().expect("root scope is never popped");
+
+        builder.map
+    }
+
+    /// The variables, pattern matches and types bound by `let` and `type` that are never
+    /// used in their scope, in the order they appear, together with whether each one is
+    /// bound directly to an import. Names starting with `_` are never reported. A pattern
+    /// match is used if any of its rules is.
+    pub(super) fn unused_bindings(&self) -> Vec<(&BindingInfo, bool)> {
+        let mut used = vec![false; self.bindings.len()];
+
+        for reference in &self.references {
+            if let Some(idx) = reference.binding {
+                used[idx] = true;
+            }
+        }
+
+        // References always point to the last rule in scope:
+        for &(previous, next) in self.rules.iter().rev() {
+            used[previous] |= used[next];
+        }
+        for &(previous, next) in &self.rules {
+            used[next] |= used[previous];
+        }
+
+        self.bindings
+            .iter()
+            .enumerate()
+            .filter(|(idx, binding)| {
+                !used[*idx]
+                    && !binding.name.starts_with('_')
+                    && matches!(
+                        binding.kind,
+                        BindingKind::Variable | BindingKind::PatternMatch | BindingKind::Type
+                    )
+            })
+            .map(|(idx, binding)| (binding, self.imported.contains(&idx)))
+            .collect()
+    }
+
+    /// The bindings at the top level of the program, in the order they appear.
+    pub fn root_bindings(&self) -> impl Iterator<Item = &BindingInfo> {
+        self.root.iter().map(|&idx| &self.bindings[idx])
+    }
+
+    /// Finds out what is under the cursor, at a given byte offset of the source, and
+    /// where it comes from. The cursor may be right after the name as well.
+    pub fn resolve_at(&self, offset: usize) -> Option<ResolvedSymbol> {
+        let contains = |(start, end): (usize, usize)| start <= offset && offset <= end;
+
+        if let Some(reference) = self.references.iter().find(|r| contains(r.span)) {
+            let definition = match reference.binding {
+                Some(idx) => Definition::Binding(self.bindings[idx].clone()),
+                None if BUILT_INS.with(|built_ins| built_ins.contains_key(&reference.name)) => {
+                    Definition::Builtin
+                }
+                None => Definition::Unbound,
+            };
+
+            return Some(ResolvedSymbol {
+                name: reference.name.clone(),
+                span: reference.span,
+                definition,
+            });
+        }
+
+        if let Some(binding) = self.bindings.iter().find(|b| contains(b.span)) {
+            return Some(ResolvedSymbol {
+                name: binding.name.clone(),
+                span: binding.span,
+                definition: Definition::Binding(binding.clone()),
+            });
+        }
+
+        self.imports
+            .iter()
+            .find(|(span, _)| contains(*span))
+            .map(|(span, path)| ResolvedSymbol {
+                name: path.clone(),
+                span: *span,
+                definition: Definition::Import(path.clone()),
+            })
+    }
+}
+
+/// Walks the parse tree, keeping track of which names are in scope.
+struct Builder {
+    map: SourceMap,
+    /// The indices in `map.bindings` of the names bound in each nested scope.
+    scopes: Vec<Vec<usize>>,
+}
+
+impl Builder {
+    fn bind(&mut self, pair: &Pair<'_, Rule>, kind: BindingKind) {
+        let span = pair.as_span();
+        self.map.bindings.push(BindingInfo {
+            name: rc_world::str_to_rc(pair.as_str()),
+            span: (span.start(), span.end()),
+            kind,
+        });
+        self.scopes
+            .last_mut()
+            .expect("there is always a scope")
+            .push(self.map.bindings.len() - 1);
+    }
+
+    fn reference(&mut self, pair: &Pair<'_, Rule>) {
+        let name = pair.as_str();
+        let binding = self
+            .scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .copied()
+            .find(|&idx| &*self.map.bindings[idx].name == name);
+        let span = pair.as_span();
+
+        self.map.references.push(Reference {
+            name: rc_world::str_to_rc(name),
+            span: (span.start(), span.end()),
+            binding,
+        });
+    }
+
+    /// Runs `f` in a new scope, which is discarded afterwards.
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(vec![]);
+        f(self);
+        self.scopes.pop();
+    }
+
+    /// Walks a binding or the final expression of a block, in the current scope.
+    fn block_item(&mut self, pair: Pair<'_, Rule>) {
+        match pair.as_rule() {
+            Rule::binding => {
+                let binding = pair.into_inner().next().expect("there is always a binding");
+                self.binding(binding);
+            }
+            _ => self.walk(pair),
+        }
+    }
+
+    fn binding(&mut self, pair: Pair<'_, Rule>) {
+        let rule = pair.as_rule();
+        let mut inner = pair.into_inner();
+
+        match rule {
+            Rule::destructuringBiding => {
+                let pattern = inner.next().expect("there is always a pattern");
+                let block = inner.next().expect("there is always a block");
+                let block_span = block.as_span();
+                let is_import = block
+                    .clone()
+                    .into_inner()
+                    .flatten()
+                    .find(|pair| pair.as_rule() == Rule::import)
+                    .is_some_and(|import| import.as_span() == block_span);
+
+                self.walk(block);
+                // The fallback, if any, does not see the variables of the pattern either:
+                inner.for_each(|fallback| self.walk(fallback));
+                let first = self.map.bindings.len();
+                self.pattern(pattern, BindingKind::Variable);
+
+                if is_import {
+                    self.map.imported.extend(first..self.map.bindings.len());
+                }
+            }
+            Rule::patternMatchBinding => {
+                let identifier = inner.next().expect("there is always an identifier");
+                self.scoped(|this| {
+                    for pair in inner {
+                        match pair.as_rule() {
+                            Rule::pattern => this.pattern(pair, BindingKind::Argument),
+                            _ => this.walk(pair),
+                        }
+                    }
+                });
+                // Consecutive rules of the same pattern match are a single definition:
+                let previous = self
+                    .scopes
+                    .last()
+                    .expect("there is always a scope")
+                    .last()
+                    .copied()
+                    .filter(|&idx| {
+                        let binding = &self.map.bindings[idx];
+                        binding.kind == BindingKind::PatternMatch
+                            && &*binding.name == identifier.as_str()
+                    });
+                self.bind(&identifier, BindingKind::PatternMatch);
+
+                if let Some(previous) = previous {
+                    self.map.rules.push((previous, self.map.bindings.len() - 1));
+                }
+            }
+            Rule::typeDefinition => {
+                let identifier = inner.next().expect("there is always an identifier");
+                inner.for_each(|type_expression| self.walk(type_expression));
+                self.bind(&identifier, BindingKind::Type);
+            }
+            Rule::useStatement => inner.for_each(|path| self.import_path(path)),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Records the quoted path of an import or of a `use` statement.
+    fn import_path(&mut self, pair: Pair<'_, Rule>) {
+        let span = pair.as_span();
+        if let Ok(path) = crate::text::unescape(pair.as_str()) {
+            self.map
+                .imports
+                .push(((span.start(), span.end()), rc_world::string_to_rc(path)));
+        }
+    }
+
+    /// Binds all the variables provided by a pattern. Type names in the pattern are
+    /// references.
+    fn pattern(&mut self, pair: Pair<'_, Rule>, kind: BindingKind) {
+        match pair.as_rule() {
+            Rule::matchIdentifier | Rule::matchRest => {
+                for pair in pair.into_inner() {
+                    match pair.as_rule() {
+                        Rule::identifier => self.bind(&pair, kind),
+                        _ => self.walk(pair),
+                    }
+                }
+            }
+            Rule::matchDictItem => {
+                for pair in pair.into_inner() {
+                    match pair.as_rule() {
+                        // In `key: pattern`, the key is not a variable:
+                        Rule::identifier => {}
+                        Rule::matchDefault => self.walk(pair),
+                        _ => self.pattern(pair, kind),
+                    }
+                }
+            }
+            _ => {
+                for pair in pair.into_inner() {
+                    self.pattern(pair, kind);
+                }
+            }
+        }
+    }
+
+    /// Walks an expression, a type expression or a block.
+    fn walk(&mut self, pair: Pair<'_, Rule>) {
+        match pair.as_rule() {
+            Rule::identifier => self.reference(&pair),
+            // Field names are not variables:
+            Rule::accessOp | Rule::optionalAccessOp => {}
+            Rule::block | Rule::scoped => self.scoped(|this| {
+                for pair in pair.into_inner() {
+                    this.block_item(pair);
+                }
+            }),
+            Rule::keyValue => {
+                let mut inner = pair.into_inner().peekable();
+                let key = inner.next().expect("there is always a key");
+                let is_shorthand = inner.peek().map(Pair::as_rule) != Some(Rule::expression);
+
+                if key.as_rule() != Rule::identifier || is_shorthand {
+                    self.walk(key);
+                }
+
+                inner.for_each(|pair| self.walk(pair));
+            }
+            Rule::typeItem => {
+                // The key is not a variable:
+                pair.into_inner().skip(1).for_each(|pair| self.walk(pair));
+            }
+            Rule::lambda | Rule::matchArm => self.scoped(|this| {
+                for pair in pair.into_inner() {
+                    match pair.as_rule() {
+                        Rule::pattern => this.pattern(pair, BindingKind::Argument),
+                        _ => this.walk(pair),
+                    }
+                }
+            }),
+            Rule::listComprehension | Rule::dictComprehension => self.scoped(|this| {
+                // The first item is evaluated in the scope of all the `for` clauses.
+                let mut inner = pair.into_inner();
+                let item = inner.next().expect("there is always an item");
+
+                for pair in inner {
+                    match pair.as_rule() {
+                        Rule::forClause => {
+                            let (patterns, expressions): (Vec<_>, Vec<_>) = pair
+                                .into_inner()
+                                .partition(|pair| pair.as_rule() == Rule::pattern);
+                            expressions.into_iter().for_each(|pair| this.walk(pair));
+                            patterns
+                                .into_iter()
+                                .for_each(|pair| this.pattern(pair, BindingKind::LoopVariable));
+                        }
+                        _ => this.walk(pair),
+                    }
+                }
+
+                this.walk(item);
+            }),
+            Rule::import => {
+                for pair in pair.into_inner() {
+                    match pair.as_rule() {
+                        Rule::text => self.import_path(pair),
+                        _ => self.walk(pair),
+                    }
+                }
+            }
+            _ => {
+                for pair in pair.into_inner() {
+                    self.walk(pair);
+                }
+            }
+        }
+    }
+}
@@ -1,49 +1,215 @@
 use std::{fmt::Display, rc::Rc};
 
 use indexmap::IndexMap;
+#[cfg(feature = "parse")]
 use pest::iterators::Pairs;
 
 use crate::rc_world;
+use crate::utils::CanonicalFloat;
 
-use super::{value::TemplatedValue, ErrorLogger, Expression, Rule, State, Value};
+use super::format::Printer;
+use super::{value::TemplatedValue, Expression, State, Value};
+#[cfg(feature = "parse")]
+use super::{ErrorLogger, Rule};
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TemplateString {
-    chunks: Vec<TemplateStringChunk>,
+    pub(super) chunks: Vec<TemplateStringChunk>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(not(feature = "parse"), allow(dead_code))]
 pub enum TemplateStringChunk {
     Text(Rc<str>),
-    Interpolation(Expression),
+    Interpolation(Expression, Option<FormatSpec>),
 }
 
-impl Display for TemplateString {
+/// The alignment of an interpolated value within its width.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(not(feature = "parse"), allow(dead_code))]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// A format specifier for an interpolation, like `${value:.2}` or `${value:>8}`. The
+/// syntax is a subset of Rust's: `[[fill]align][+][0][width][.precision]`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FormatSpec {
+    /// The specifier as written, without the leading `:`.
+    raw: Rc<str>,
+    fill: char,
+    align: Option<Align>,
+    sign: bool,
+    zero: bool,
+    width: Option<usize>,
+    precision: Option<usize>,
+}
+
+impl Display for FormatSpec {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "`")?;
+        write!(f, "{}", self.raw)
+    }
+}
 
-        for chunk in &self.chunks {
-            match chunk {
-                TemplateStringChunk::Interpolation(expr) => write!(f, "${{{expr}}}")?,
-                TemplateStringChunk::Text(text) => {
-                    for char in text.chars() {
-                        match char {
-                            '`' => write!(f, "\\`")?,
-                            '$' => write!(f, "\\$")?,
-                            ch => write!(f, "{ch}")?,
-                        }
-                    }
-                }
+impl FormatSpec {
+    #[cfg(feature = "parse")]
+    fn parse(raw: &str) -> Result<FormatSpec, String> {
+        let invalid = || format!("Invalid format specifier `{raw}`");
+        let chars = raw.chars().collect::<Vec<_>>();
+        let mut i = 0;
+
+        let to_align = |ch: Option<&char>| match ch {
+            Some('<') => Some(Align::Left),
+            Some('^') => Some(Align::Center),
+            Some('>') => Some(Align::Right),
+            _ => None,
+        };
+
+        let mut fill = ' ';
+        let mut align = None;
+        if let Some(found) = to_align(chars.get(1)) {
+            fill = chars[0];
+            align = Some(found);
+            i = 2;
+        } else if let Some(found) = to_align(chars.first()) {
+            align = Some(found);
+            i = 1;
+        }
+
+        let sign = chars.get(i) == Some(&'+');
+        if sign {
+            i += 1;
+        }
+
+        let zero = chars.get(i) == Some(&'0');
+        if zero {
+            i += 1;
+        }
+
+        let parse_number = |i: &mut usize| {
+            let start = *i;
+            while chars.get(*i).is_some_and(char::is_ascii_digit) {
+                *i += 1;
+            }
+            if start == *i {
+                None
+            } else {
+                Some(chars[start..*i].iter().collect::<String>().parse::<usize>())
+            }
+        };
+
+        let width = parse_number(&mut i).transpose().map_err(|_| invalid())?;
+
+        let precision = if chars.get(i) == Some(&'.') {
+            i += 1;
+            Some(
+                parse_number(&mut i)
+                    .ok_or_else(invalid)?
+                    .map_err(|_| invalid())?,
+            )
+        } else {
+            None
+        };
+
+        if i != chars.len() {
+            return Err(invalid());
+        }
+
+        Ok(FormatSpec {
+            raw: rc_world::str_to_rc(raw),
+            fill,
+            align,
+            sign,
+            zero,
+            width,
+            precision,
+        })
+    }
+
+    /// Renders a value according to this specifier.
+    fn apply(&self, value: Value) -> Result<String, String> {
+        let (mut rendered, is_number) = match (&value, self.precision) {
+            (Value::Integer(int), None) => (int.to_string(), true),
+            (Value::Integer(int), Some(precision)) => {
+                (format!("{:.precision$}", *int as f64), true)
+            }
+            (Value::Float(float), None) => (CanonicalFloat(*float).to_string(), true),
+            (Value::Float(float), Some(precision)) => (format!("{float:.precision$}"), true),
+            (Value::Text(text), Some(precision)) => (text.chars().take(precision).collect(), false),
+            (_, Some(_)) => {
+                return Err(format!(
+                    "Precision in format specifier `{self}` only applies to numbers and text, \
+                    got {}",
+                    value.brief()
+                ))
+            }
+            (_, None) => (TemplatedValue(value.clone()).to_string(), false),
+        };
+
+        if self.sign {
+            if !is_number {
+                return Err(format!(
+                    "Sign in format specifier `{self}` only applies to numbers, got {}",
+                    value.brief()
+                ));
+            }
+            if !rendered.starts_with('-') {
+                rendered.insert(0, '+');
+            }
+        }
+
+        let width = self.width.unwrap_or(0);
+        let len = rendered.chars().count();
+        if len >= width {
+            return Ok(rendered);
+        }
+        let padding = width - len;
+
+        if self.zero {
+            if !is_number {
+                return Err(format!(
+                    "Zero padding in format specifier `{self}` only applies to numbers, \
+                    got {}",
+                    value.brief()
+                ));
             }
+            let sign_len = if rendered.starts_with(['+', '-']) {
+                1
+            } else {
+                0
+            };
+            rendered.insert_str(sign_len, &"0".repeat(padding));
+            return Ok(rendered);
         }
 
-        write!(f, "`")?;
+        let align = self
+            .align
+            .unwrap_or(if is_number { Align::Right } else { Align::Left });
+        let (before, after) = match align {
+            Align::Left => (0, padding),
+            Align::Center => (padding / 2, padding - padding / 2),
+            Align::Right => (padding, 0),
+        };
+        let fill = |n: usize| self.fill.to_string().repeat(n);
+
+        Ok(format!("{}{rendered}{}", fill(before), fill(after)))
+    }
+}
 
-        Ok(())
+impl Display for TemplateString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&Printer::FLAT.template(self))
     }
 }
 
 impl TemplateString {
+    #[cfg(feature = "parse")]
     pub(super) fn parse(logger: &mut ErrorLogger, pairs: Pairs<'_, Rule>) -> Self {
         let mut chunks = vec![];
         let mut chunk_builder = String::new();
@@ -63,14 +229,29 @@ impl TemplateString {
                                 chunk_builder = String::new();
                                 chunks.push(TemplateStringChunk::Text(chunk));
 
-                                let expression = Expression::parse(
-                                    logger,
-                                    pair.into_inner()
-                                        .next()
-                                        .expect("an interpolation always has an expression")
-                                        .into_inner(),
-                                );
-                                chunks.push(TemplateStringChunk::Interpolation(expression));
+                                let mut expression = None;
+                                let mut spec = None;
+
+                                for pair in escaped.into_inner() {
+                                    match pair.as_rule() {
+                                        Rule::expression => {
+                                            expression =
+                                                Some(Expression::parse(logger, pair.into_inner()))
+                                        }
+                                        Rule::formatSpec => {
+                                            spec = logger.absorb(
+                                                &pair,
+                                                FormatSpec::parse(&pair.as_str()[1..]).map(Some),
+                                            )
+                                        }
+                                        _ => unreachable!(),
+                                    }
+                                }
+
+                                chunks.push(TemplateStringChunk::Interpolation(
+                                    expression.expect("an interpolation always has an expression"),
+                                    spec,
+                                ));
                             }
                             _ => unreachable!(),
                         }
@@ -98,7 +279,7 @@ impl TemplateString {
         values: &mut IndexMap<Rc<str>, Value>,
     ) -> Option<()> {
         for chunk in &self.chunks {
-            if let TemplateStringChunk::Interpolation(expr) = chunk {
+            if let TemplateStringChunk::Interpolation(expr, _) = chunk {
                 expr.capture(state, provided, values)?;
             }
         }
@@ -111,10 +292,17 @@ impl TemplateString {
         for chunk in &self.chunks {
             match chunk {
                 TemplateStringChunk::Text(text) => builder += text,
-                TemplateStringChunk::Interpolation(expr) => {
+                TemplateStringChunk::Interpolation(expr, None) => {
                     let outcome = expr.eval(state)?;
                     builder += &TemplatedValue(outcome).to_string();
                 }
+                TemplateStringChunk::Interpolation(expr, Some(spec)) => {
+                    let outcome = expr.eval(state)?;
+                    let formatted = spec
+                        .apply(outcome)
+                        .map_err(|err| format!("{err} in template string {self}"));
+                    builder += &state.absorb(formatted)?;
+                }
             }
         }
 
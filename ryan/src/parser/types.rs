@@ -2,17 +2,58 @@ use std::fmt::Display;
 use std::rc::Rc;
 
 use indexmap::IndexMap;
+#[cfg(feature = "parse")]
 use pest::iterators::Pairs;
 
+#[cfg(feature = "parse")]
 use crate::rc_world;
 use crate::utils::QuotedStr;
 
+use super::operation::MAX_SAFE_INTEGER;
+#[cfg(feature = "parse")]
 use super::ErrorLogger;
+use super::EvalErrorKind;
+#[cfg(feature = "parse")]
 use super::Rule;
 use super::State;
 use super::Value;
 
-/// The type of a Ryan value.
+/// The type of a Ryan value. Types are displayed just like they are written in Ryan,
+/// so that the types in error messages can be pasted back into a program (except for
+/// [`Type::Opaque`]):
+///
+/// ```
+/// use ryan::environment::Environment;
+/// use ryan::parser::{self, Type, Value};
+///
+/// let types = vec![
+///     Type::Tuple(vec![]),
+///     Type::Tuple(vec![Type::Integer]),
+///     Type::Tuple(vec![
+///         Type::Integer,
+///         Type::Tuple(vec![Type::Text, Type::Tuple(vec![])]),
+///     ]),
+///     Type::List(Box::new(Type::Tuple(vec![
+///         Type::Bool,
+///         Type::Or(vec![Type::Null, Type::Float]),
+///     ]))),
+///     Type::Dictionary(Box::new(Type::Tuple(vec![Type::Bytes]))),
+///     Type::List(Box::new(Type::PreciseInteger)),
+///     Type::List(Box::new(Type::Or(vec![Type::Number, Type::Null]))),
+///     Type::Record([("a b".to_owned(), Type::Tuple(vec![Type::Any]))].into_iter().collect()),
+///     Type::StrictRecord(
+///         [("x".to_owned(), Type::List(Box::new(Type::Integer)))]
+///             .into_iter()
+///             .collect(),
+///     ),
+/// ];
+///
+/// for ty in types {
+///     let block = parser::parse(&format!("type T = {ty};\nT")).unwrap();
+///     let value = parser::eval(Environment::new(None), &block).unwrap();
+///     assert_eq!(value, Value::Type(ty.clone()), "{ty}");
+/// }
+/// ```
 #[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     /// Any type. Matches anything.
@@ -23,10 +64,20 @@ pub enum Type {
     Bool,
     /// An integer.
     Integer,
-    /// A float.
+    /// An integer that a float can represent exactly, i.e., of magnitude at most 2^53.
+    /// These are the integers that survive a trip through JavaScript or any other JSON
+    /// parser reading all numbers as floats.
+    PreciseInteger,
+    /// A float. Just like in arithmetic, integers are accepted where a float is
+    /// expected, so this matches any number.
     Float,
+    /// Any number, i.e., an integer or a float. This is the same as `int | float`, but
+    /// keeps the name it was written with.
+    Number,
     /// Some text.
     Text,
+    /// Raw binary data.
+    Bytes,
     /// A list where all elements are of the same type.
     List(Box<Type>),
     /// A dictionary where all the values are of the same type.
@@ -50,20 +101,23 @@ impl Display for Type {
             Self::Null => write!(f, "null")?,
             Self::Bool => write!(f, "bool")?,
             Self::Integer => write!(f, "int")?,
+            Self::PreciseInteger => write!(f, "precise_int")?,
             Self::Float => write!(f, "float")?,
+            Self::Number => write!(f, "number")?,
             Self::Text => write!(f, "text")?,
+            Self::Bytes => write!(f, "bytes")?,
             Self::List(item) => write!(f, "[{item}]")?,
             Self::Dictionary(item) => write!(f, "{{{item}}}")?,
             Self::Tuple(items) => {
-                write!(f, "[")?;
+                write!(f, "(")?;
                 crate::utils::fmt_list(f, items)?;
-                write!(f, "]")?;
+                write!(f, ")")?;
             }
             Self::Record(dict) => {
                 write!(f, "{{ ")?;
                 crate::utils::fmt_map(
                     f,
-                    dict.iter().map(|(key, r#type)| (QuotedStr(&key), r#type)),
+                    dict.iter().map(|(key, r#type)| (QuotedStr(key), r#type)),
                 )?;
                 if dict.is_empty() {
                     write!(f, ".. }}")?;
@@ -75,7 +129,7 @@ impl Display for Type {
                 write!(f, "{{")?;
                 crate::utils::fmt_map(
                     f,
-                    items.iter().map(|(key, r#type)| (QuotedStr(&key), r#type)),
+                    items.iter().map(|(key, r#type)| (QuotedStr(key), r#type)),
                 )?;
                 write!(f, "}}")?;
             }
@@ -101,8 +155,10 @@ impl Type {
             | (Self::Null, Value::Null)
             | (Self::Bool, Value::Bool(_))
             | (Self::Integer, Value::Integer(_))
-            | (Self::Float, Value::Float(_))
-            | (Self::Text, Value::Text(_)) => true,
+            | (Self::Float | Self::Number, Value::Integer(_) | Value::Float(_))
+            | (Self::Text, Value::Text(_))
+            | (Self::Bytes, Value::Bytes(_)) => true,
+            (Self::PreciseInteger, Value::Integer(int)) => int.unsigned_abs() <= MAX_SAFE_INTEGER,
             (Self::List(r#type), Value::List(list)) => list.iter().all(|item| r#type.matches(item)),
             (Self::Dictionary(r#type), Value::Map(dict)) => {
                 dict.iter().all(|(_, value)| r#type.matches(value))
@@ -132,6 +188,7 @@ impl Type {
 
 /// Ans expression returning a concrete Ryan type.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TypeExpression {
     /// Any type. Matches anything.
     Any,
@@ -141,10 +198,16 @@ pub enum TypeExpression {
     Bool,
     /// An integer.
     Integer,
-    /// A float.
+    /// An integer that a float can represent exactly.
+    PreciseInteger,
+    /// A float, also accepting integers.
     Float,
+    /// Any number, i.e., an integer or a float.
+    Number,
     /// Some text.
     Text,
+    /// Raw binary data.
+    Bytes,
     /// A list where all elements are of the same type.
     List(Box<TypeExpression>),
     /// A dictionary where all the values are of the same type.
@@ -168,8 +231,11 @@ impl Display for TypeExpression {
             Self::Null => write!(f, "null")?,
             Self::Bool => write!(f, "bool")?,
             Self::Integer => write!(f, "int")?,
+            Self::PreciseInteger => write!(f, "precise_int")?,
             Self::Float => write!(f, "float")?,
+            Self::Number => write!(f, "number")?,
             Self::Text => write!(f, "text")?,
+            Self::Bytes => write!(f, "bytes")?,
             Self::List(item) => write!(f, "[{item}]")?,
             Self::Dictionary(item) => write!(f, "{{{item}}}")?,
             Self::Tuple(items) => {
@@ -181,7 +247,7 @@ impl Display for TypeExpression {
                 write!(f, "{{ ")?;
                 crate::utils::fmt_map(
                     f,
-                    dict.iter().map(|(key, r#type)| (QuotedStr(&key), r#type)),
+                    dict.iter().map(|(key, r#type)| (QuotedStr(key), r#type)),
                 )?;
                 if dict.is_empty() {
                     write!(f, ".. }}")?;
@@ -193,10 +259,14 @@ impl Display for TypeExpression {
                 write!(f, "{{")?;
                 crate::utils::fmt_map(
                     f,
-                    items.iter().map(|(key, r#type)| (QuotedStr(&key), r#type)),
+                    items.iter().map(|(key, r#type)| (QuotedStr(key), r#type)),
                 )?;
                 write!(f, "}}")?;
             }
+            // This is how `?x` is parsed:
+            Self::Or(or_list) if matches!(or_list.as_slice(), [_, Self::Null]) => {
+                write!(f, "?{}", or_list[0])?;
+            }
             Self::Or(or_list) => {
                 let first = or_list.first().expect("or type list cannot be empty");
                 write!(f, "{first}")?;
@@ -212,6 +282,7 @@ impl Display for TypeExpression {
 }
 
 impl TypeExpression {
+    #[cfg(feature = "parse")]
     pub(super) fn parse(logger: &mut ErrorLogger, pairs: Pairs<'_, Rule>) -> Self {
         let mut or_list = Vec::with_capacity(1);
 
@@ -249,11 +320,11 @@ impl TypeExpression {
                     "any" => TypeExpression::Any,
                     "bool" => TypeExpression::Bool,
                     "int" => TypeExpression::Integer,
+                    "precise_int" => TypeExpression::PreciseInteger,
                     "float" => TypeExpression::Float,
-                    "number" => {
-                        TypeExpression::Or(vec![TypeExpression::Integer, TypeExpression::Float])
-                    }
+                    "number" => TypeExpression::Number,
                     "text" => TypeExpression::Text,
+                    "bytes" => TypeExpression::Bytes,
                     _ => unreachable!(),
                 },
                 Rule::identifier => TypeExpression::Variable(rc_world::str_to_rc(pair.as_str())),
@@ -281,17 +352,20 @@ impl TypeExpression {
         provided: &[Rc<str>],
         values: &mut IndexMap<Rc<str>, Value>,
     ) -> Option<()> {
-        if let Self::Variable(id) = self {
-            match state.try_get(id) {
-                Ok(cap) => {
-                    values.insert(id.clone(), cap.clone());
+        match self {
+            Self::Variable(id) => state.capture_variable(id, provided, values),
+            Self::List(item) | Self::Dictionary(item) => item.capture(state, provided, values)?,
+            Self::Tuple(items) | Self::Or(items) => {
+                for item in items {
+                    item.capture(state, provided, values)?;
                 }
-                Err(err) => {
-                    if !provided.contains(&id) {
-                        state.absorb(Err(err))?;
-                    }
+            }
+            Self::Record(record) | Self::StrictRecord(record) => {
+                for item in record.values() {
+                    item.capture(state, provided, values)?;
                 }
             }
+            _ => {}
         }
 
         Some(())
@@ -303,8 +377,11 @@ impl TypeExpression {
             Self::Null => Type::Null,
             Self::Bool => Type::Bool,
             Self::Integer => Type::Integer,
+            Self::PreciseInteger => Type::PreciseInteger,
             Self::Float => Type::Float,
+            Self::Number => Type::Number,
             Self::Text => Type::Text,
+            Self::Bytes => Type::Bytes,
             Self::List(item) => Type::List(Box::new(item.eval(state)?)),
             Self::Dictionary(item) => Type::Dictionary(Box::new(item.eval(state)?)),
             Self::Tuple(tuple) => Type::Tuple(
@@ -331,10 +408,10 @@ impl TypeExpression {
                     .map(|item| item.eval(state))
                     .collect::<Option<Vec<_>>>()?,
             ),
-            Self::Variable(identifier) => match state.get(&identifier)? {
+            Self::Variable(identifier) => match state.get(identifier)? {
                 Value::Type(r#type) => r#type,
-                val => {
-                    state.raise(format!("The value `{val}` is not a type"))?;
+                value => {
+                    state.raise(EvalErrorKind::NotAType { value })?;
                     return None;
                 }
             },
@@ -344,11 +421,13 @@ impl TypeExpression {
     }
 }
 
+#[cfg(feature = "parse")]
 struct TypeItem {
     identifier: String,
     r#type: TypeExpression,
 }
 
+#[cfg(feature = "parse")]
 impl TypeItem {
     pub(super) fn parse(logger: &mut ErrorLogger, pairs: Pairs<'_, Rule>) -> Self {
         let mut identifier = None;
@@ -358,7 +437,7 @@ impl TypeItem {
             match pair.as_rule() {
                 Rule::identifier => identifier = Some(pair.as_str().to_owned()),
                 Rule::text => {
-                    identifier = Some(logger.absorb(&pair, crate::utils::unescape(pair.as_str())))
+                    identifier = Some(logger.absorb(&pair, crate::text::unescape(pair.as_str())))
                 }
                 Rule::typeExpression => {
                     r#type = Some(TypeExpression::parse(logger, pair.into_inner()))
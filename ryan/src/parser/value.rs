@@ -5,11 +5,13 @@ use std::rc::Rc;
 use indexmap::IndexMap;
 use thiserror::Error;
 
-use crate::environment::NativePatternMatch;
-use crate::utils::QuotedStr;
+use crate::de::MaterializedType;
+use crate::environment::{EvalEvent, NativePatternMatch};
+use crate::utils::{CanonicalFloat, QuotedStr};
 use crate::DecodeError;
 
 use super::block::Block;
+use super::expression::Expression;
 use super::literal::Literal;
 use super::pattern::{BindError, Pattern};
 use super::types::Type;
@@ -20,23 +22,29 @@ use super::{Context, State};
 pub struct PatternMatch {
     /// The pattern against which the input will be matched.
     pub pattern: Pattern,
+    /// An optional condition that must be true for the match to be successful.
+    pub guard: Option<Expression>,
     /// The block to be executes if the match is successful.
     pub block: Block,
     /// The variable from the program necessary for the block to evaluate correctly.
-    pub captures: IndexMap<Rc<str>, Value>,
+    /// These are taken before the pattern match itself is bound to a variable, so they
+    /// never contain it, directly or not. Since values are immutable, this means that
+    /// pattern matches never form reference cycles and are always freed. They are shared
+    /// by every application of the pattern match.
+    pub captures: Rc<IndexMap<Rc<str>, Value>>,
+    /// The module where this pattern was defined. Imports in the block are resolved
+    /// relative to it, and not to the module applying the pattern.
+    pub module: Option<Rc<str>>,
 }
 
+/// Shows only the pattern and the guard. The captures are left out, since they can be
+/// arbitrarily large.
 impl Display for PatternMatch {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // TODO: make the syntax for this representation:
-
-        // if let Some(id) = &self.identifier {
-        //     write!(f, "@{id} ")?;
-        // }
-
-        write!(f, "{} let {{", self.pattern)?;
-        crate::utils::fmt_map(f, self.captures.iter().map(|(k, v)| (QuotedStr(k), v)))?;
-        write!(f, "}} => {}", self.block)?;
+        write!(f, "{}", self.pattern)?;
+        if let Some(guard) = &self.guard {
+            write!(f, " if {guard}")?;
+        }
 
         Ok(())
     }
@@ -45,16 +53,43 @@ impl Display for PatternMatch {
 impl PatternMatch {
     pub(super) fn r#match(
         &self,
+        name: &Rc<str>,
+        rule_index: usize,
         arg: &Value,
         state: &mut State,
     ) -> Option<Result<Value, BindError>> {
-        let mut new_bindings = self.captures.clone();
+        let trace = |state: &State, matched| {
+            state.environment.trace(|| EvalEvent::PatternRuleTried {
+                name: name.clone(),
+                rule_index,
+                matched,
+            })
+        };
+
+        // Defaults and type guards in the pattern are resolved where it was defined:
+        let mut new_bindings = IndexMap::new();
+        let mut captured = state.new_captured(self.captures.clone(), IndexMap::new());
 
-        if let Err(err) = self.pattern.bind(&arg, &mut new_bindings, state)? {
+        if let Err(err) = self.pattern.bind(arg, &mut new_bindings, &mut captured)? {
+            trace(state, false);
             return Some(Err(err));
         }
 
-        let mut new_state = state.new_local(new_bindings);
+        let mut new_state = state.new_captured(self.captures.clone(), new_bindings);
+        new_state.environment.current_module = self.module.clone();
+
+        if let Some(guard) = &self.guard {
+            let truthiness = guard.eval(&mut new_state)?.is_true();
+            if !new_state.absorb(truthiness)? {
+                trace(state, false);
+                return Some(Err(BindError::GuardFailed {
+                    pattern: self.pattern.clone(),
+                    value: arg.clone(),
+                }));
+            }
+        }
+
+        trace(state, true);
         let outcome = self.block.eval(&mut new_state)?;
 
         Some(Ok(outcome))
@@ -64,6 +99,29 @@ impl PatternMatch {
 impl NativePatternMatch {
     pub(super) fn r#match(&self, arg: Value, state: &mut State<'_>) -> Option<Value> {
         state.push_ctx(Context::SubstitutingPattern(Some(self.identifier.clone())));
+
+        // Native functions rely on the value conforming to the pattern.
+        if let Err(err) = self.pattern.bind(&arg, &mut IndexMap::new(), state)? {
+            let err = match (&self.pattern, &arg) {
+                (Pattern::MatchList(expected), Value::List(got))
+                    if expected.len() != got.len() =>
+                {
+                    BindError::WrongArity {
+                        identifier: self.identifier.clone(),
+                        pattern: self.pattern.clone(),
+                        expected: expected.len(),
+                        got: got.len(),
+                    }
+                }
+                _ => err,
+            };
+            state.raise(err)?;
+        }
+
+        if self.effectful {
+            state.environment.taint_incremental();
+        }
+
         let value = state.absorb((self.func)(arg))?;
         state.pop_ctx();
 
@@ -72,15 +130,189 @@ impl NativePatternMatch {
 }
 
 /// An error raised when a [`Value`] has no counterpart in JSON, e.g., a type or a pattern
-/// match rule.
-#[derive(Debug, Error)]
-#[error("The following value is not JSON-serializable: {value}")]
+/// match rule. It tells where the offending value is, e.g., `.helpers.make_svc`:
+///
+/// ```
+/// use ryan::parser::Value;
+/// use ryan::Environment;
+///
+/// let code = "let make_svc name = { name }; { helpers: { make_svc } }";
+/// let parsed = ryan::parser::parse(code).unwrap();
+/// let value = ryan::parser::eval(Environment::new(None), &parsed).unwrap();
+///
+/// assert_eq!(
+///     value.check_representable().unwrap_err().to_string(),
+///     "The result contains the unapplied pattern `make_svc` at .helpers.make_svc. Did you \
+///     forget to apply it?",
+/// );
+/// ```
+#[derive(Debug)]
 pub struct NotRepresentable {
-    value: String,
+    path: Vec<PathSeg>,
+    value: Value,
+}
+
+impl Display for NotRepresentable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The result contains {} at {}{}",
+            describe(&self.value),
+            fmt_json_path(&self.path),
+            self.hint()
+        )
+    }
+}
+
+impl std::error::Error for NotRepresentable {}
+
+impl NotRepresentable {
+    /// The path from the root of the result to the offending value.
+    pub fn path(&self) -> &[PathSeg] {
+        &self.path
+    }
+
+    /// The offending value.
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    fn hint(&self) -> &'static str {
+        match &self.value {
+            Value::PatternMatches(..) | Value::NativePatternMatch(_) => {
+                ". Did you forget to apply it?"
+            }
+            Value::Bytes(_) => ". Encode them first, e.g., with `base64_encode`",
+            _ => "",
+        }
+    }
+}
+
+/// A value removed by [`Value::strip_unrepresentable`], together with where it was.
+#[derive(Debug, Clone)]
+pub struct PathedRemoval {
+    /// The path from the root of the original value to the removed value.
+    pub path: Vec<PathSeg>,
+    /// The removed value.
+    pub value: Value,
+}
+
+impl Display for PathedRemoval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Removed {} at {}",
+            describe(&self.value),
+            fmt_json_path(&self.path)
+        )
+    }
+}
+
+/// Says what a value with no counterpart in JSON is, e.g., "the unapplied pattern `f`".
+fn describe(value: &Value) -> String {
+    match value {
+        Value::PatternMatches(identifier, _) => format!("the unapplied pattern `{identifier}`"),
+        Value::NativePatternMatch(pat) => format!("the unapplied pattern `{}`", pat.identifier),
+        Value::Type(typ) => format!("the type `{typ}`"),
+        Value::Bytes(_) => "bytes".to_string(),
+        value => format!("the number `{value}`"),
+    }
+}
+
+/// Writes a path like `jq` does, e.g., `.spec.ports[0]`, quoting keys that are not
+/// identifiers.
+fn fmt_json_path(path: &[PathSeg]) -> String {
+    if path.is_empty() {
+        return "the root".to_string();
+    }
+
+    path.iter()
+        .map(|seg| match seg {
+            PathSeg::Key(key) if is_plain_key(key) => format!(".{key}"),
+            PathSeg::Key(key) => format!("[{}]", QuotedStr(key).quote()),
+            PathSeg::Index(idx) => format!("[{idx}]"),
+        })
+        .collect()
+}
+
+/// Parses a path written like [`fmt_json_path`] writes it, e.g., `.spec.ports[0]`, except
+/// that the leading dot is optional. Inside quoted keys, a backslash escapes the
+/// character after it.
+fn parse_json_path(path: &str) -> Option<Vec<PathSeg>> {
+    let mut segs = vec![];
+    let mut chars = path.strip_prefix('.').unwrap_or(path).chars().peekable();
+    let mut first = true;
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '[' if chars.peek() == Some(&'"') => {
+                chars.next();
+                let mut key = String::new();
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => key.push(chars.next()?),
+                        ch => key.push(ch),
+                    }
+                }
+                if chars.next()? != ']' {
+                    return None;
+                }
+                segs.push(PathSeg::Key(key.into()));
+            }
+            '[' => {
+                let mut idx = String::new();
+                loop {
+                    match chars.next()? {
+                        ']' => break,
+                        ch => idx.push(ch),
+                    }
+                }
+                segs.push(PathSeg::Index(idx.parse().ok()?));
+            }
+            _ => {
+                // Keys are separated by dots, but the first one needs none:
+                let mut key = String::new();
+                match (ch, first) {
+                    ('.', false) => {}
+                    (ch, true) if ch != '.' => key.push(ch),
+                    _ => return None,
+                }
+                while let Some(&ch) = chars.peek() {
+                    if ch == '.' || ch == '[' {
+                        break;
+                    }
+                    key.push(ch);
+                    chars.next();
+                }
+                if key.is_empty() {
+                    return None;
+                }
+                segs.push(PathSeg::Key(key.into()));
+            }
+        }
+
+        first = false;
+    }
+
+    Some(segs)
+}
+
+fn is_plain_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(ch) if ch.is_ascii_alphabetic() || ch == '_')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
 }
 
 /// A Ryan value.
-#[derive(Debug, Clone, PartialEq)]
+///
+/// # Equality and ordering
+///
+/// Integers and floats are compared by their numeric value, so that `1 == 1.0`. Unlike
+/// IEEE 754, `NaN` is equal to itself (so that `x == x` always holds) and is greater
+/// than every other number when ordering (so that sorting never fails on numbers).
+/// Lists and maps are compared element by element, maps disregarding key order.
+#[derive(Debug, Clone)]
 pub enum Value {
     /// The value `null`.
     Null,
@@ -92,9 +324,19 @@ pub enum Value {
     Float(f64),
     /// An utf-8 encoded string.
     Text(Rc<str>),
+    /// Raw binary data, e.g., from `import "logo.png" as bytes`. Bytes have no
+    /// counterpart in JSON, but can be deserialized as `Vec<u8>` or with `serde_bytes`:
+    ///
+    /// ```
+    /// let bytes: Vec<u8> = ryan::from_str(r#"base64_decode "AP+ABw==""#).unwrap();
+    /// assert_eq!(bytes, [0, 255, 128, 7]);
+    /// ```
+    Bytes(Rc<[u8]>),
     /// A list of other Ryan values.
     List(Rc<[Value]>),
-    /// An association of strings to other Ryan values.
+    /// An association of strings to other Ryan values. The keys keep the order in which
+    /// they were first set: setting a key again, e.g., in a dictionary comprehension or
+    /// with `+`, replaces its value but keeps its position.
     Map(Rc<IndexMap<Rc<str>, Value>>),
     /// A list of pattern match rules for a given identifier.
     PatternMatches(Rc<str>, Vec<Rc<PatternMatch>>),
@@ -107,32 +349,77 @@ pub enum Value {
 
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Lists and maps still open, with whether their first item is still to come. An
+        // explicit stack keeps deeply nested values from overflowing the call stack.
+        enum Open<'a> {
+            List(std::slice::Iter<'a, Value>, bool),
+            Map(indexmap::map::Iter<'a, Rc<str>, Value>, bool),
+        }
+
+        let mut open = vec![];
+        let mut next = Some(self);
+
+        loop {
+            match next.take() {
+                Some(Self::List(list)) => {
+                    write!(f, "[")?;
+                    open.push(Open::List(list.iter(), true));
+                }
+                Some(Self::Map(map)) => {
+                    write!(f, "{{")?;
+                    open.push(Open::Map(map.iter(), true));
+                }
+                Some(value) => value.fmt_leaf(f)?,
+                None => {}
+            }
+
+            match open.last_mut() {
+                Some(Open::List(items, first)) => match items.next() {
+                    Some(item) => {
+                        if !std::mem::replace(first, false) {
+                            write!(f, ", ")?;
+                        }
+                        next = Some(item);
+                    }
+                    None => {
+                        write!(f, "]")?;
+                        open.pop();
+                    }
+                },
+                Some(Open::Map(entries, first)) => match entries.next() {
+                    Some((key, value)) => {
+                        if !std::mem::replace(first, false) {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{key}: ")?;
+                        next = Some(value);
+                    }
+                    None => {
+                        write!(f, "}}")?;
+                        open.pop();
+                    }
+                },
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Value {
+    /// Displays a value that is neither a list nor a map.
+    fn fmt_leaf(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Null => write!(f, "null")?,
             Self::Bool(b) => write!(f, "{b}")?,
             Self::Integer(int) => write!(f, "{int}")?,
-            Self::Float(float) => write!(f, "{float}")?,
+            Self::Float(float) => write!(f, "{}", CanonicalFloat(*float))?,
             Self::Text(text) => write!(f, "{text:?}")?,
-            Self::List(list) => {
-                write!(f, "[")?;
-                crate::utils::fmt_list(f, list.iter())?;
-                write!(f, "]")?;
-            }
-            Self::Map(map) => {
-                write!(f, "{{")?;
-                crate::utils::fmt_map(f, map.iter())?;
-                write!(f, "}}")?;
-            }
+            Self::Bytes(bytes) => write!(f, "{}", BytesPreview(bytes))?,
+            Self::List(_) | Self::Map(_) => unreachable!("lists and maps are not leaves"),
             Self::PatternMatches(name, pattern_matches) => {
-                write!(
-                    f,
-                    "![pattern {name} {}]",
-                    pattern_matches
-                        .iter()
-                        .map(|p| p.to_string())
-                        .collect::<Vec<_>>()
-                        .join(" | ")
-                )?;
+                write!(f, "{}", PatternsPreview(name, pattern_matches))?;
             }
             Self::NativePatternMatch(pattern_match) => {
                 write!(f, "{pattern_match}")?;
@@ -142,6 +429,174 @@ impl Display for Value {
 
         Ok(())
     }
+
+    /// Displays this value like [`Display`] does, but within bounds, so that huge values
+    /// do not drown what is being said about them, e.g., in error messages. Lists and
+    /// dictionaries nested deeper than `max_depth` are shown as `[...]` and `{...}`, only
+    /// the first `max_items` items of each are shown and text is cut after `max_text`
+    /// characters. Values within bounds are shown exactly as [`Display`] shows them:
+    ///
+    /// ```
+    /// use ryan::Environment;
+    ///
+    /// let code = r#"{ a: 1, b: [[2]], c: "long text", d: 4, e: 5, f: 6 }"#;
+    /// let parsed = ryan::parser::parse(code).unwrap();
+    /// let value = ryan::parser::eval(Environment::new(None), &parsed).unwrap();
+    ///
+    /// assert_eq!(
+    ///     value.summary(2, 3, 7).to_string(),
+    ///     r#"{a: 1, b: [[...]], c: "long te…", …3 more}"#
+    /// );
+    /// assert_eq!(value.summary(3, 6, 9).to_string(), value.to_string());
+    /// ```
+    pub fn summary(
+        &self,
+        max_depth: usize,
+        max_items: usize,
+        max_text: usize,
+    ) -> SummarizedValue<'_> {
+        SummarizedValue {
+            value: self,
+            max_depth,
+            max_items,
+            max_text,
+        }
+    }
+
+    /// The summary of this value used in error messages. The value itself is still
+    /// available in the structured errors, e.g., [`EvalErrorKind`](super::EvalErrorKind).
+    pub(crate) fn brief(&self) -> SummarizedValue<'_> {
+        self.summary(3, 8, 64)
+    }
+}
+
+/// A value displayed within bounds. See [`Value::summary`].
+#[derive(Debug, Clone, Copy)]
+pub struct SummarizedValue<'a> {
+    value: &'a Value,
+    max_depth: usize,
+    max_items: usize,
+    max_text: usize,
+}
+
+impl Display for SummarizedValue<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Lists and maps still open, with how many items were shown so far. Like in the
+        // `Display` of `Value`, an explicit stack keeps deep values from overflowing.
+        enum Open<'a> {
+            List(std::slice::Iter<'a, Value>, usize),
+            Map(indexmap::map::Iter<'a, Rc<str>, Value>, usize),
+        }
+
+        let mut open = vec![];
+        let mut next = Some(self.value);
+
+        loop {
+            match next.take() {
+                Some(Value::List(list)) if list.is_empty() => write!(f, "[]")?,
+                Some(Value::Map(map)) if map.is_empty() => write!(f, "{{}}")?,
+                Some(Value::List(_)) if open.len() >= self.max_depth => write!(f, "[...]")?,
+                Some(Value::Map(_)) if open.len() >= self.max_depth => write!(f, "{{...}}")?,
+                Some(Value::List(list)) => {
+                    write!(f, "[")?;
+                    open.push(Open::List(list.iter(), 0));
+                }
+                Some(Value::Map(map)) => {
+                    write!(f, "{{")?;
+                    open.push(Open::Map(map.iter(), 0));
+                }
+                Some(Value::Text(text)) => self.fmt_text(text, f)?,
+                Some(value) => value.fmt_leaf(f)?,
+                None => {}
+            }
+
+            let (remaining, shown, close) = match open.last_mut() {
+                Some(Open::List(items, shown)) => (items.len(), shown, "]"),
+                Some(Open::Map(entries, shown)) => (entries.len(), shown, "}"),
+                None => break,
+            };
+            let separator = if *shown > 0 { ", " } else { "" };
+
+            if remaining == 0 {
+                write!(f, "{close}")?;
+                open.pop();
+            } else if *shown >= self.max_items {
+                write!(f, "{separator}…{remaining} more{close}")?;
+                open.pop();
+            } else {
+                *shown += 1;
+                write!(f, "{separator}")?;
+                match open.last_mut() {
+                    Some(Open::List(items, _)) => next = items.next(),
+                    Some(Open::Map(entries, _)) => {
+                        let (key, value) = entries.next().expect("there are entries remaining");
+                        write!(f, "{key}: ")?;
+                        next = Some(value);
+                    }
+                    None => unreachable!("an item was just found"),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl SummarizedValue<'_> {
+    /// Displays text quoted, like [`Value`] does, but cut after `max_text` characters.
+    fn fmt_text(&self, text: &str, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match text.char_indices().nth(self.max_text) {
+            Some((end, _)) => {
+                let quoted = format!("{:?}", &text[..end]);
+                write!(f, "{}…\"", &quoted[..quoted.len() - 1])
+            }
+            None => write!(f, "{text:?}"),
+        }
+    }
+}
+
+/// Shows the length of some bytes and the first few of them in hexadecimal, since bytes
+/// can be arbitrarily large.
+struct BytesPreview<'a>(&'a [u8]);
+
+impl Display for BytesPreview<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const PREVIEW_LEN: usize = 16;
+
+        write!(f, "![bytes ({}) ", self.0.len())?;
+        for byte in self.0.iter().take(PREVIEW_LEN) {
+            write!(f, "{byte:02x}")?;
+        }
+        if self.0.len() > PREVIEW_LEN {
+            write!(f, "...")?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// Shows the rules of a pattern match and how many values they capture, but not the
+/// captured values themselves, since they can be arbitrarily large.
+struct PatternsPreview<'a>(&'a str, &'a [Rc<PatternMatch>]);
+
+impl Display for PatternsPreview<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let PatternsPreview(name, pattern_matches) = self;
+
+        write!(f, "![pattern {name} ")?;
+        for (i, pattern_match) in pattern_matches.iter().enumerate() {
+            if i > 0 {
+                write!(f, " | ")?;
+            }
+            write!(f, "{pattern_match}")?;
+        }
+
+        let captures: usize = pattern_matches.iter().map(|p| p.captures.len()).sum();
+        match captures {
+            0 => write!(f, "]"),
+            1 => write!(f, " with 1 capture]"),
+            _ => write!(f, " with {captures} captures]"),
+        }
+    }
 }
 
 impl FromIterator<Value> for Value {
@@ -151,14 +606,67 @@ impl FromIterator<Value> for Value {
     }
 }
 
+/// Compares two floats, considering `NaN` equal to itself and greater than any other
+/// number.
+fn cmp_floats(a: f64, b: f64) -> cmp::Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => cmp::Ordering::Equal,
+        (true, false) => cmp::Ordering::Greater,
+        (false, true) => cmp::Ordering::Less,
+        (false, false) => a.partial_cmp(&b).expect("neither value is NaN"),
+    }
+}
+
+/// Compares an integer and a float by their exact numeric values, considering `NaN`
+/// greater than any other number.
+pub(super) fn cmp_int_float(int: i64, float: f64) -> cmp::Ordering {
+    if float.is_nan() {
+        return cmp::Ordering::Less;
+    }
+
+    match (int as f64).partial_cmp(&float).expect("neither value is NaN") {
+        // `int as f64` rounds, so break ties exactly. Note that `i64::MAX as f64` is
+        // 2^63, which is strictly greater than any `i64`.
+        cmp::Ordering::Equal if float >= i64::MAX as f64 => cmp::Ordering::Less,
+        cmp::Ordering::Equal => int.cmp(&(float as i64)),
+        order => order,
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Null, Self::Null) => true,
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::Integer(a), Self::Integer(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => cmp_floats(*a, *b).is_eq(),
+            (Self::Integer(a), Self::Float(b)) => cmp_int_float(*a, *b).is_eq(),
+            (Self::Float(a), Self::Integer(b)) => cmp_int_float(*b, *a).is_eq(),
+            (Self::Text(a), Self::Text(b)) => a == b,
+            (Self::Bytes(a), Self::Bytes(b)) => a == b,
+            (Self::List(a), Self::List(b)) => a == b,
+            (Self::Map(a), Self::Map(b)) => a == b,
+            (Self::PatternMatches(a_id, a), Self::PatternMatches(b_id, b)) => {
+                a_id == b_id && a == b
+            }
+            (Self::NativePatternMatch(a), Self::NativePatternMatch(b)) => a == b,
+            (Self::Type(a), Self::Type(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
 impl PartialOrd for Value {
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
         let order = match (self, other) {
             (Self::Null, Self::Null) => cmp::Ordering::Equal,
             (Self::Bool(a), Self::Bool(b)) => a.cmp(b),
             (Self::Integer(a), Self::Integer(b)) => a.cmp(b),
-            (Self::Float(a), Self::Float(b)) => a.partial_cmp(b)?,
+            (Self::Float(a), Self::Float(b)) => cmp_floats(*a, *b),
+            (Self::Integer(a), Self::Float(b)) => cmp_int_float(*a, *b),
+            (Self::Float(a), Self::Integer(b)) => cmp_int_float(*b, *a).reverse(),
             (Self::Text(a), Self::Text(b)) => a.cmp(b),
+            (Self::Bytes(a), Self::Bytes(b)) => a.cmp(b),
             _ => return None,
         };
 
@@ -172,7 +680,10 @@ impl Value {
     pub fn is_true(&self) -> Result<bool, String> {
         match self {
             Self::Bool(b) => Ok(*b),
-            anything_else => Err(format!("Value `{anything_else}` is not a boolean")),
+            anything_else => Err(format!(
+                "Value `{}` is not a boolean",
+                anything_else.brief()
+            )),
         }
     }
 
@@ -180,6 +691,7 @@ impl Value {
     /// syntax tree, while values are not.
     pub fn matches(&self, lit: &Literal) -> bool {
         match (self, lit) {
+            (Value::Null, Literal::Null) => true,
             (Value::Integer(val), Literal::Integer(lit)) if val == lit => true,
             (Value::Float(val), Literal::Float(lit)) if val == lit => true,
             (Value::Bool(val), Literal::Bool(lit)) if val == lit => true,
@@ -199,9 +711,8 @@ impl Value {
                 }
             }
             (Value::List(list), Value::Integer(idx)) => {
-                let idx = *idx as usize;
-                if idx < list.len() {
-                    Ok(list[idx].clone())
+                if let Some(value) = usize::try_from(*idx).ok().and_then(|i| list.get(i)) {
+                    Ok(value.clone())
                 } else {
                     Err(format!(
                         "Tried to access index {idx} of list of length {}",
@@ -209,12 +720,29 @@ impl Value {
                     ))
                 }
             }
-            (val, item) => Err(format!("Cannot index {val} by {item}")),
+            (val, item) => Err(format!("Cannot index {} by {}", val.brief(), item.brief())),
+        }
+    }
+
+    /// Does the indexing of a given value by another, returning `null` if the value is
+    /// `null` or if the key or index is absent.
+    fn extract_optional_item(&self, item: &Value) -> Result<Value, String> {
+        match (self, item) {
+            (Value::Null, _) => Ok(Value::Null),
+            (Value::Map(map), Value::Text(key)) => {
+                Ok(map.get(key).cloned().unwrap_or(Value::Null))
+            }
+            (Value::List(list), Value::Integer(idx)) => Ok(usize::try_from(*idx)
+                .ok()
+                .and_then(|idx| list.get(idx))
+                .cloned()
+                .unwrap_or(Value::Null)),
+            (val, item) => Err(format!("Cannot index {} by {}", val.brief(), item.brief())),
         }
     }
 
     /// Tries to return an iterator, if the value is iterable
-    pub fn iter(&self) -> Result<ValueIter, NotIterable> {
+    pub fn iter(&self) -> Result<ValueIter<'_>, NotIterable> {
         match self {
             Self::List(list) => Ok(ValueIter::List(list.iter())),
             Self::Map(dict) => Ok(ValueIter::Map(dict.iter())),
@@ -232,6 +760,182 @@ impl Value {
         }
     }
 
+    /// Extracts the value lying at the end of a path in a nested Ryan value, returning
+    /// `null` if any step of the path is `null` or absent.
+    pub fn extract_optional_path(&self, path: &[Value]) -> Result<Value, String> {
+        match (self, path) {
+            (val, []) => Ok(val.clone()),
+            (val, [item, tail @ ..]) => val
+                .extract_optional_item(item)
+                .and_then(|extracted| extracted.extract_optional_path(tail)),
+        }
+    }
+
+    /// Returns the value under a key, if this is a [`Value::Map`] holding the key.
+    ///
+    /// ```
+    /// use ryan::{environment::Environment, parser::{self, Value}};
+    ///
+    /// let block = parser::parse(r#"{ name: "api", port: 8080 }"#).unwrap();
+    /// let value = parser::eval(Environment::new(None), &block).unwrap();
+    ///
+    /// assert_eq!(value.get("port"), Some(&Value::Integer(8080)));
+    /// assert_eq!(value.get("host"), None);
+    /// ```
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    /// Returns the value at the end of a path of keys and indices, such as
+    /// `services[0].port`. The path is written the same way as in the errors about
+    /// values that cannot be represented: keys are separated by dots, which may also start
+    /// the path, indices are in brackets and keys that are not identifiers are quoted in
+    /// brackets, e.g., `["weird.key"]`. Returns `None` if any step is missing or if the
+    /// path is malformed. The empty path is the value itself.
+    ///
+    /// ```
+    /// use ryan::{environment::Environment, parser::{self, Value}};
+    ///
+    /// let block = parser::parse(r#"{ services: [{ port: 8080 }], "weird.key": 1 }"#).unwrap();
+    /// let value = parser::eval(Environment::new(None), &block).unwrap();
+    ///
+    /// assert_eq!(value.get_path("services[0].port"), Some(&Value::Integer(8080)));
+    /// assert_eq!(value.get_path(r#"["weird.key"]"#), Some(&Value::Integer(1)));
+    /// assert_eq!(value.get_path("services[1].port"), None);
+    /// ```
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        parse_json_path(path)?
+            .iter()
+            .try_fold(self, |value, seg| match (value, seg) {
+                (Value::Map(map), PathSeg::Key(key)) => map.get(key),
+                (Value::List(list), PathSeg::Index(idx)) => list.get(*idx),
+                _ => None,
+            })
+    }
+
+    /// Returns the text, if this is a [`Value::Text`].
+    ///
+    /// ```
+    /// use ryan::parser::Value;
+    ///
+    /// assert_eq!(Value::Text("api".into()).as_str(), Some("api"));
+    /// assert_eq!(Value::Null.as_str(), None);
+    /// ```
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer, if this is a [`Value::Integer`].
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(int) => Some(*int),
+            _ => None,
+        }
+    }
+
+    /// Returns the number as a float, if this is a [`Value::Float`] or a
+    /// [`Value::Integer`], as in `serde_json`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Float(float) => Some(*float),
+            Value::Integer(int) => Some(*int as f64),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean, if this is a [`Value::Bool`].
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements, if this is a [`Value::List`].
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Returns the keys and values, if this is a [`Value::Map`].
+    pub fn as_map(&self) -> Option<&IndexMap<Rc<str>, Value>> {
+        match self {
+            Value::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Like [`Value::as_str`], but with an error telling what was found instead.
+    ///
+    /// ```
+    /// use ryan::parser::Value;
+    ///
+    /// assert_eq!(
+    ///     Value::Integer(1).try_as_str().unwrap_err().to_string(),
+    ///     "expected text but got value of type int",
+    /// );
+    /// ```
+    pub fn try_as_str(&self) -> Result<&str, DecodeError> {
+        self.as_str()
+            .ok_or_else(|| self.type_error(MaterializedType::String))
+    }
+
+    /// Like [`Value::as_i64`], but with an error telling what was found instead.
+    pub fn try_as_i64(&self) -> Result<i64, DecodeError> {
+        self.as_i64()
+            .ok_or_else(|| self.type_error(MaterializedType::I64))
+    }
+
+    /// Like [`Value::as_f64`], but with an error telling what was found instead.
+    pub fn try_as_f64(&self) -> Result<f64, DecodeError> {
+        self.as_f64()
+            .ok_or_else(|| self.type_error(MaterializedType::F64))
+    }
+
+    /// Like [`Value::as_bool`], but with an error telling what was found instead.
+    pub fn try_as_bool(&self) -> Result<bool, DecodeError> {
+        self.as_bool()
+            .ok_or_else(|| self.type_error(MaterializedType::Bool))
+    }
+
+    /// Like [`Value::as_list`], but with an error telling what was found instead.
+    pub fn try_as_list(&self) -> Result<&[Value], DecodeError> {
+        self.as_list()
+            .ok_or_else(|| self.type_error(MaterializedType::List))
+    }
+
+    /// Like [`Value::as_map`], but with an error telling what was found instead.
+    pub fn try_as_map(&self) -> Result<&IndexMap<Rc<str>, Value>, DecodeError> {
+        self.as_map()
+            .ok_or_else(|| self.type_error(MaterializedType::Map))
+    }
+
+    fn type_error(&self, expected: MaterializedType) -> DecodeError {
+        DecodeError::TypeError {
+            expected,
+            got: self.canonical_type(),
+        }
+    }
+
+    /// The most natural type describing this value. Lists whose elements all have the
+    /// same type are lists of that type, while other lists are tuples:
+    /// ```
+    /// use ryan::parser::{Type, Value};
+    ///
+    /// let numbers = Value::List(vec![Value::Integer(1), Value::Float(2.5)].into());
+    /// assert_eq!(numbers.canonical_type(), Type::List(Box::new(Type::Number)));
+    ///
+    /// let mixed = Value::List(vec![Value::Integer(1), Value::Bool(true)].into());
+    /// assert_eq!(mixed.canonical_type().to_string(), "(int, bool)");
+    /// ```
     pub fn canonical_type(&self) -> Type {
         match self {
             Value::Null => Type::Null,
@@ -239,6 +943,7 @@ impl Value {
             Value::Integer(_) => Type::Integer,
             Value::Float(_) => Type::Float,
             Value::Text(_) => Type::Text,
+            Value::Bytes(_) => Type::Bytes,
             Value::List(list) => {
                 let types = list.iter().map(Value::canonical_type).collect::<Vec<_>>();
 
@@ -261,6 +966,12 @@ impl Value {
                     } else {
                         Type::Tuple(vec![])
                     }
+                } else if types
+                    .iter()
+                    .all(|typ| matches!(typ, Type::Integer | Type::Float))
+                {
+                    // Integers and floats mix freely, e.g., in `[1, 2.5]`:
+                    Type::List(Box::new(Type::Number))
                 } else {
                     Type::Tuple(types)
                 }
@@ -306,9 +1017,453 @@ impl Value {
     {
         let deserializer = crate::de::RyanDeserializer {
             value: std::borrow::Cow::Borrowed(self),
+            lenient: false,
+        };
+        T::deserialize(deserializer)
+    }
+
+    /// Like [`Value::decode`], but also decodes floats with no fractional part, such as
+    /// the `4.0` in `8 / 2.0`, into integer types, as long as they fit. Any other float
+    /// is still an error when an integer is expected.
+    ///
+    /// ```
+    /// use ryan::{environment::Environment, parser};
+    ///
+    /// let block = parser::parse("12 / 3.0").unwrap();
+    /// let value = parser::eval(Environment::new(None), &block).unwrap();
+    ///
+    /// assert!(value.decode::<u32>().is_err());
+    /// assert_eq!(value.decode_lenient::<u32>().unwrap(), 4);
+    /// ```
+    pub fn decode_lenient<T>(&self) -> Result<T, DecodeError>
+    where
+        T: for<'a> serde::Deserialize<'a>,
+    {
+        let deserializer = crate::de::RyanDeserializer {
+            value: std::borrow::Cow::Borrowed(self),
+            lenient: true,
         };
         T::deserialize(deserializer)
     }
+
+    /// Serializes this value as compact JSON, with the keys of every dictionary sorted byte
+    /// by byte, so that the same value always gives the same text. As in `serde_json`,
+    /// floats that are not finite become `null`.
+    pub fn to_json(&self) -> Result<String, NotRepresentable> {
+        let mut json = String::new();
+        self.write_json(&mut json, &mut vec![])?;
+        Ok(json)
+    }
+
+    fn write_json(
+        &self,
+        json: &mut String,
+        path: &mut Vec<PathSeg>,
+    ) -> Result<(), NotRepresentable> {
+        match self {
+            Value::Null => *json += "null",
+            Value::Bool(b) => *json += &b.to_string(),
+            Value::Integer(int) => *json += &int.to_string(),
+            Value::Float(float) if float.is_finite() => {
+                *json += &CanonicalFloat(*float).to_string()
+            }
+            Value::Float(_) => *json += "null",
+            Value::Text(text) => *json += &QuotedStr(text).quote(),
+            Value::List(list) => {
+                *json += "[";
+                for (idx, item) in list.iter().enumerate() {
+                    if idx > 0 {
+                        *json += ",";
+                    }
+                    path.push(PathSeg::Index(idx));
+                    item.write_json(json, path)?;
+                    path.pop();
+                }
+                *json += "]";
+            }
+            Value::Map(map) => {
+                let mut entries = map.iter().collect::<Vec<_>>();
+                entries.sort_by_key(|&(key, _)| key);
+
+                *json += "{";
+                for (idx, (key, value)) in entries.into_iter().enumerate() {
+                    if idx > 0 {
+                        *json += ",";
+                    }
+                    *json += &QuotedStr(key).quote();
+                    *json += ":";
+                    path.push(PathSeg::Key(key.clone()));
+                    value.write_json(json, path)?;
+                    path.pop();
+                }
+                *json += "}";
+            }
+            value => {
+                return Err(NotRepresentable {
+                    path: path.clone(),
+                    value: value.clone(),
+                })
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that this value can be written as JSON without losing anything, i.e., that
+    /// it contains no pattern matches, types, bytes, `NaN`s or infinities. Otherwise, the
+    /// error tells where the first offending value is, in the order the value would be
+    /// printed. Run this before turning the result of a program into something else, so
+    /// that a missing argument to a pattern match does not become a confusing decode
+    /// error:
+    ///
+    /// ```
+    /// use ryan::parser::{PathSeg, Value};
+    ///
+    /// let value = Value::List(vec![Value::Integer(1), Value::Float(f64::NAN)].into());
+    /// let err = value.check_representable().unwrap_err();
+    ///
+    /// assert_eq!(err.path(), [PathSeg::Index(1)]);
+    /// assert_eq!(err.to_string(), "The result contains the number `NaN` at [1]");
+    /// ```
+    pub fn check_representable(&self) -> Result<(), NotRepresentable> {
+        self.find_unrepresentable(Value::is_json_scalar)
+    }
+
+    /// Checks that this value is only data, i.e., that it contains no pattern matches or
+    /// types, which usually means that a pattern match was not applied to its argument.
+    /// Unlike [`Value::check_representable`], bytes and non-finite floats are accepted,
+    /// for hosts that have a counterpart for them, like Python and JavaScript:
+    ///
+    /// ```
+    /// use ryan::Environment;
+    ///
+    /// let code = "let make_service name = { name }; { services: { api: make_service } }";
+    /// let parsed = ryan::parser::parse(code).unwrap();
+    /// let value = ryan::parser::eval(Environment::new(None), &parsed).unwrap();
+    ///
+    /// assert_eq!(
+    ///     value.check_data().unwrap_err().to_string(),
+    ///     "The result contains the unapplied pattern `make_service` at .services.api. Did \
+    ///     you forget to apply it?",
+    /// );
+    /// ```
+    pub fn check_data(&self) -> Result<(), NotRepresentable> {
+        self.find_unrepresentable(|value| {
+            !matches!(
+                value,
+                Value::PatternMatches(..) | Value::NativePatternMatch(_) | Value::Type(_)
+            )
+        })
+    }
+
+    /// Whether this value is a number, text, boolean or `null` that JSON can represent.
+    fn is_json_scalar(&self) -> bool {
+        match self {
+            Value::Null | Value::Bool(_) | Value::Integer(_) | Value::Text(_) => true,
+            Value::Float(float) => float.is_finite(),
+            _ => false,
+        }
+    }
+
+    /// Finds the first value, in the order the value would be printed, that is neither a
+    /// list, nor a dictionary nor `is_scalar`.
+    fn find_unrepresentable(
+        &self,
+        is_scalar: impl Fn(&Value) -> bool,
+    ) -> Result<(), NotRepresentable> {
+        let mut to_visit = vec![(self, vec![])];
+
+        while let Some((value, path)) = to_visit.pop() {
+            let child = |seg| [path.as_slice(), &[seg]].concat();
+
+            match value {
+                Value::List(list) => to_visit.extend(
+                    list.iter()
+                        .enumerate()
+                        .rev()
+                        .map(|(idx, item)| (item, child(PathSeg::Index(idx)))),
+                ),
+                Value::Map(map) => to_visit.extend(
+                    map.iter()
+                        .rev()
+                        .map(|(key, value)| (value, child(PathSeg::Key(key.clone())))),
+                ),
+                value if is_scalar(value) => {}
+                value => {
+                    return Err(NotRepresentable {
+                        path,
+                        value: value.clone(),
+                    })
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes from this value everything [`Value::check_representable`] would reject,
+    /// e.g., helper pattern matches exposed alongside the data. Offending entries of
+    /// dictionaries and items of lists are dropped altogether, so the items after a
+    /// dropped one move back a position. If this value itself is not representable, the
+    /// result is `null`. Returns the stripped value and what was removed, in the order
+    /// the value would be printed:
+    ///
+    /// ```
+    /// use ryan::parser::PathSeg;
+    /// use ryan::Environment;
+    ///
+    /// let code = "let port x = x + 8000; { port, ports: [port 80, port] }";
+    /// let parsed = ryan::parser::parse(code).unwrap();
+    /// let value = ryan::parser::eval(Environment::new(None), &parsed).unwrap();
+    /// let (stripped, removed) = value.strip_unrepresentable();
+    ///
+    /// assert_eq!(stripped.to_string(), "{ports: [8080]}");
+    /// assert_eq!(removed[1].path, [PathSeg::Key("ports".into()), PathSeg::Index(1)]);
+    /// assert_eq!(removed[1].to_string(), "Removed the unapplied pattern `port` at .ports[1]");
+    /// ```
+    pub fn strip_unrepresentable(&self) -> (Value, Vec<PathedRemoval>) {
+        let mut removed = vec![];
+        let stripped = self
+            .strip_at(&mut vec![], &mut removed)
+            .unwrap_or(Value::Null);
+
+        (stripped, removed)
+    }
+
+    fn strip_at(&self, path: &mut Vec<PathSeg>, removed: &mut Vec<PathedRemoval>) -> Option<Value> {
+        let mut strip_child = |seg, value: &Value| {
+            path.push(seg);
+            let stripped = value.strip_at(path, removed);
+            path.pop();
+            stripped
+        };
+
+        match self {
+            Value::List(list) => Some(Value::List(
+                list.iter()
+                    .enumerate()
+                    .filter_map(|(idx, item)| strip_child(PathSeg::Index(idx), item))
+                    .collect(),
+            )),
+            Value::Map(map) => Some(Value::Map(Rc::new(
+                map.iter()
+                    .filter_map(|(key, value)| {
+                        let stripped = strip_child(PathSeg::Key(key.clone()), value)?;
+                        Some((key.clone(), stripped))
+                    })
+                    .collect(),
+            ))),
+            value if value.is_json_scalar() => Some(value.clone()),
+            value => {
+                removed.push(PathedRemoval {
+                    path: path.clone(),
+                    value: value.clone(),
+                });
+                None
+            }
+        }
+    }
+
+    /// Measures the size and shape of this value. This works on values of any depth,
+    /// without risk of overflowing the stack.
+    ///
+    /// ```
+    /// use ryan::parser::ValueStats;
+    /// use ryan::Environment;
+    ///
+    /// let parsed = ryan::parser::parse(r#"{ a: [1, 2], b: "xyz" }"#).unwrap();
+    /// let value = ryan::parser::eval(Environment::new(None), &parsed).unwrap();
+    /// assert_eq!(
+    ///     value.stats(),
+    ///     ValueStats { nodes: 5, max_depth: 2, text_bytes: 5, list_items: 2, map_entries: 2 }
+    /// );
+    /// ```
+    pub fn stats(&self) -> ValueStats {
+        let mut stats = ValueStats::default();
+        let mut to_visit = vec![(self, 0)];
+
+        while let Some((value, depth)) = to_visit.pop() {
+            stats.nodes += 1;
+            stats.max_depth = stats.max_depth.max(depth);
+
+            match value {
+                Value::Text(text) => stats.text_bytes += text.len(),
+                Value::List(list) => {
+                    stats.list_items += list.len();
+                    to_visit.extend(list.iter().map(|item| (item, depth + 1)));
+                }
+                Value::Map(map) => {
+                    stats.map_entries += map.len();
+                    for (key, value) in map.iter() {
+                        stats.text_bytes += key.len();
+                        to_visit.push((value, depth + 1));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        stats
+    }
+
+    /// Visits this value and all values nested in it, parents before children. Each value
+    /// is passed to `f` together with its path from the root, which is empty for `self`.
+    pub fn walk(&self, f: &mut impl FnMut(&[PathSeg], &Value)) {
+        self.walk_at(&mut vec![], f);
+    }
+
+    fn walk_at(&self, path: &mut Vec<PathSeg>, f: &mut impl FnMut(&[PathSeg], &Value)) {
+        f(path, self);
+
+        match self {
+            Value::List(list) => {
+                for (idx, item) in list.iter().enumerate() {
+                    path.push(PathSeg::Index(idx));
+                    item.walk_at(path, f);
+                    path.pop();
+                }
+            }
+            Value::Map(map) => {
+                for (key, value) in map.iter() {
+                    path.push(PathSeg::Key(key.clone()));
+                    value.walk_at(path, f);
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Transforms this value and all values nested in it, children before parents. Each
+    /// value is passed to `f` together with its path from the root, which is empty for
+    /// `self`, and is replaced by whatever `f` returns. Lists and maps whose items are
+    /// all left untouched are not rebuilt: the original allocation is reused instead.
+    ///
+    /// For example, this redacts every key containing `"secret"`:
+    /// ```rust
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use ryan::parser::{PathSeg, Value};
+    /// use ryan::Environment;
+    ///
+    /// let parsed = ryan::parser::parse(r#"{ db: { user: "ryan", secret_key: "hunter2" } }"#)?;
+    /// let value = ryan::parser::eval(Environment::new(None), &parsed)?;
+    ///
+    /// let redacted = value.map(|path, value| match path.last() {
+    ///     Some(PathSeg::Key(key)) if key.contains("secret") => Value::Text("<redacted>".into()),
+    ///     _ => value,
+    /// });
+    ///
+    /// let path = [Value::Text("db".into()), Value::Text("secret_key".into())];
+    /// assert_eq!(redacted.extract_path(&path)?, Value::Text("<redacted>".into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn map(self, f: impl Fn(&[PathSeg], Value) -> Value) -> Value {
+        self.map_at(&mut vec![], &f)
+    }
+
+    fn map_at(self, path: &mut Vec<PathSeg>, f: &impl Fn(&[PathSeg], Value) -> Value) -> Value {
+        let mapped = match self {
+            Value::List(list) => {
+                let mut rebuilt: Option<Vec<Value>> = None;
+
+                for (idx, item) in list.iter().enumerate() {
+                    path.push(PathSeg::Index(idx));
+                    let new_item = item.clone().map_at(path, f);
+                    path.pop();
+
+                    if let Some(rebuilt) = &mut rebuilt {
+                        rebuilt.push(new_item);
+                    } else if !new_item.is_same(item) {
+                        let mut items = list[..idx].to_vec();
+                        items.push(new_item);
+                        rebuilt = Some(items);
+                    }
+                }
+
+                rebuilt.map_or(Value::List(list), |items| Value::List(items.into()))
+            }
+            Value::Map(map) => {
+                let mut rebuilt: Option<IndexMap<Rc<str>, Value>> = None;
+
+                for (idx, (key, value)) in map.iter().enumerate() {
+                    path.push(PathSeg::Key(key.clone()));
+                    let new_value = value.clone().map_at(path, f);
+                    path.pop();
+
+                    if let Some(rebuilt) = &mut rebuilt {
+                        rebuilt.insert(key.clone(), new_value);
+                    } else if !new_value.is_same(value) {
+                        let mut entries = map
+                            .iter()
+                            .take(idx)
+                            .map(|(key, value)| (key.clone(), value.clone()))
+                            .collect::<IndexMap<_, _>>();
+                        entries.insert(key.clone(), new_value);
+                        rebuilt = Some(entries);
+                    }
+                }
+
+                rebuilt.map_or(Value::Map(map), |entries| Value::Map(Rc::new(entries)))
+            }
+            value => value,
+        };
+
+        f(path, mapped)
+    }
+
+    /// Whether two values are known to be identical without traversing them: lists and
+    /// maps must share the same allocation and scalars must be equal and of the same type.
+    fn is_same(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+            (Value::Text(a), Value::Text(b)) => Rc::ptr_eq(a, b) || a == b,
+            (Value::Bytes(a), Value::Bytes(b)) => Rc::ptr_eq(a, b),
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b),
+            (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b),
+            (Value::NativePatternMatch(a), Value::NativePatternMatch(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// The size and shape of a [`Value`], as measured by [`Value::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ValueStats {
+    /// The number of values, counting the value itself and everything nested in it.
+    pub nodes: usize,
+    /// How deeply values are nested. This is `0` for anything that is not a list or a
+    /// map and `1` for a list or map of such values.
+    pub max_depth: usize,
+    /// The total length in bytes of all text, including the keys of maps.
+    pub text_bytes: usize,
+    /// The total number of items in all lists.
+    pub list_items: usize,
+    /// The total number of entries in all maps.
+    pub map_entries: usize,
+}
+
+impl Display for ValueStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "nodes: {}, max depth: {}, text bytes: {}, list items: {}, map entries: {}",
+            self.nodes, self.max_depth, self.text_bytes, self.list_items, self.map_entries
+        )
+    }
+}
+
+/// A step in the path from the root of a [`Value`] to one of the values nested in it,
+/// as used by [`Value::walk`] and [`Value::map`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSeg {
+    /// The value under a given key of a [`Value::Map`].
+    Key(Rc<str>),
+    /// The value at a given index of a [`Value::List`].
+    Index(usize),
 }
 
 /// An iterator over a [`Value`], only in the cases that makes sense.
@@ -333,41 +1488,63 @@ impl<'a> Iterator for ValueIter<'a> {
 
 /// Error when the user tries to iterate over non-iterable values.
 #[derive(Debug, Error)]
-#[error("Value {val} is not iterable")]
+#[error("Value {} is not iterable{}", .val.brief(), range_hint(.val))]
 pub struct NotIterable {
     val: Value,
 }
 
+/// Suggests the use of `range` when trying to iterate over an integer.
+fn range_hint(val: &Value) -> String {
+    match val {
+        Value::Integer(n) => format!(". Did you mean `range [0, {n}]`?"),
+        _ => String::new(),
+    }
+}
+
+/// Renders a value as it is interpolated in a template string: text at the top level is
+/// rendered bare, while everything else is rendered much like JSON.
 pub struct TemplatedValue(pub Value);
 
 impl Display for TemplatedValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.0 {
+            // Text is rendered bare only at the top level:
+            Value::Text(text) => write!(f, "{text}"),
+            value => write!(f, "{}", NestedTemplatedValue(value)),
+        }
+    }
+}
+
+/// Renders a value nested inside a list or a map in a template string. Text (including
+/// map keys) is quoted as in JSON and floats use the shortest representation that reads
+/// back as the same float.
+struct NestedTemplatedValue<'a>(&'a Value);
+
+impl Display for NestedTemplatedValue<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
             Value::Null => write!(f, "null")?,
             Value::Bool(b) => write!(f, "{b}")?,
             Value::Integer(int) => write!(f, "{int}")?,
-            Value::Float(float) => write!(f, "{float}")?,
-            Value::Text(text) => write!(f, "{text}")?,
+            Value::Float(float) => write!(f, "{}", CanonicalFloat(*float))?,
+            Value::Text(text) => write!(f, "{}", QuotedStr(text))?,
+            Value::Bytes(bytes) => write!(f, "{}", BytesPreview(bytes))?,
             Value::List(list) => {
                 write!(f, "[")?;
-                crate::utils::fmt_list(f, list.iter())?;
+                crate::utils::fmt_list(f, list.iter().map(NestedTemplatedValue))?;
                 write!(f, "]")?;
             }
             Value::Map(map) => {
                 write!(f, "{{")?;
-                crate::utils::fmt_map(f, map.iter())?;
+                crate::utils::fmt_map(
+                    f,
+                    map.iter()
+                        .map(|(key, value)| (QuotedStr(key), NestedTemplatedValue(value))),
+                )?;
                 write!(f, "}}")?;
             }
             Value::PatternMatches(name, pattern_matches) => {
-                write!(
-                    f,
-                    "![pattern {name} {}]",
-                    pattern_matches
-                        .iter()
-                        .map(|p| p.to_string())
-                        .collect::<Vec<_>>()
-                        .join(" | ")
-                )?;
+                write!(f, "{}", PatternsPreview(name, pattern_matches))?;
             }
             Value::NativePatternMatch(pattern_match) => {
                 write!(f, "{pattern_match}")?;
@@ -44,3 +44,9 @@ pub fn str_to_rc(s: &str) -> Rc<str> {
 pub fn string_to_rc(s: String) -> Rc<str> {
     RC_WORLD.with(|world| world.string_to_rc(s))
 }
+
+/// The number of distinct strings interned so far in this thread.
+#[cfg(feature = "metrics")]
+pub fn interned_count() -> usize {
+    RC_WORLD.with(|world| world.strings.borrow().len())
+}
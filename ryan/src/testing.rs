@@ -0,0 +1,236 @@
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+
+use crate::environment::Environment;
+use crate::parser::{self, Value};
+use crate::{rc_world, Error};
+
+/// The suffix of the names of the files holding tests.
+const TEST_SUFFIX: &str = ".test.ryan";
+
+/// How a single test went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestOutcome {
+    /// The test evaluated to `true`.
+    Passed,
+    /// The test evaluated to `false`.
+    Failed,
+    /// The test could not be run, e.g., because its file does not parse, its evaluation
+    /// failed or it evaluated to something other than a `bool`.
+    Error(String),
+}
+
+/// The outcome of a test in a test file, as returned by [`run_tests`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    /// The file where the test is.
+    pub file: PathBuf,
+    /// The key of the test in the `tests` dictionary of the file, or `None` if the file
+    /// itself could not be run.
+    pub name: Option<String>,
+    /// How the test went.
+    pub outcome: TestOutcome,
+}
+
+impl Display for TestResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some(name) = &self.name {
+            write!(f, "::{name}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The outcome of all the tests in a list of test files, as returned by [`run_tests`]. It
+/// is displayed in the same way as the summary of `cargo test`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestReport {
+    /// The outcome of each test, in the order of the files and then in the order of the
+    /// tests in each file.
+    pub results: Vec<TestResult>,
+}
+
+impl TestReport {
+    /// The number of tests that passed.
+    pub fn passed(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|result| result.outcome == TestOutcome::Passed)
+            .count()
+    }
+
+    /// The number of tests that failed or could not be run.
+    pub fn failed(&self) -> usize {
+        self.results.len() - self.passed()
+    }
+
+    /// Whether all tests passed.
+    pub fn is_success(&self) -> bool {
+        self.failed() == 0
+    }
+}
+
+impl Display for TestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "running {} tests", self.results.len())?;
+        for result in &self.results {
+            let status = match result.outcome {
+                TestOutcome::Passed => "ok",
+                TestOutcome::Failed | TestOutcome::Error(_) => "FAILED",
+            };
+            writeln!(f, "test {result} ... {status}")?;
+        }
+
+        let errors = self
+            .results
+            .iter()
+            .filter_map(|result| match &result.outcome {
+                TestOutcome::Error(message) => Some((result, message)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            writeln!(f, "\nerrors:")?;
+            for (result, message) in errors {
+                writeln!(f, "\n---- {result} ----\n{}", message.trim())?;
+            }
+        }
+
+        write!(
+            f,
+            "\ntest result: {}. {} passed; {} failed",
+            if self.is_success() { "ok" } else { "FAILED" },
+            self.passed(),
+            self.failed(),
+        )
+    }
+}
+
+/// Lists the test files, named `*.test.ryan`, under a directory and all of its
+/// subdirectories, sorted by path. If `path` is a file, it is the only test file, whatever
+/// its name.
+pub fn discover_tests<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    if !path.is_dir() {
+        // Fails right away if the file does not exist:
+        std::fs::metadata(path)?;
+        return Ok(vec![path.to_owned()]);
+    }
+
+    let mut found = vec![];
+    let mut pending = vec![path.to_owned()];
+
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                pending.push(path);
+            } else if entry.file_name().to_string_lossy().ends_with(TEST_SUFFIX) {
+                found.push(path);
+            }
+        }
+    }
+
+    found.sort();
+    Ok(found)
+}
+
+/// Runs the tests in each of the supplied files. A test file evaluates to a dictionary
+/// with the key `tests`, holding a dictionary of named `bool`s, each one a test that passes
+/// if it is `true`:
+/// ```ryan
+/// let networking = import "networking.ryan";
+///
+/// {
+///     tests: {
+///         uses_https: networking.port == 443,
+///         has_a_timeout: networking.timeout_secs > 0,
+///     }
+/// }
+/// ```
+/// A file that cannot be read, parsed or evaluated counts as a single failed test. The
+/// files are evaluated in the supplied environment, sharing its import cache, with each
+/// file as the current module.
+///
+/// ```
+/// let dir = std::env::temp_dir().join("ryan-run-tests-doctest");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// let file = dir.join("math.test.ryan");
+/// std::fs::write(&file, "{ tests: { sums: 1 + 1 == 2, lies: 1 == 2 } }").unwrap();
+///
+/// let report = ryan::run_tests([&file], &ryan::Environment::new(None));
+/// assert_eq!((report.passed(), report.failed()), (1, 1));
+/// assert!(!report.is_success());
+/// ```
+pub fn run_tests<I, P>(paths: I, env: &Environment) -> TestReport
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let mut report = TestReport::default();
+
+    for path in paths {
+        let file = path.as_ref().to_owned();
+        match run_file(&file, env) {
+            Ok(tests) => report
+                .results
+                .extend(tests.into_iter().map(|(name, outcome)| TestResult {
+                    file: file.clone(),
+                    name: Some(name),
+                    outcome,
+                })),
+            Err(message) => report.results.push(TestResult {
+                file,
+                name: None,
+                outcome: TestOutcome::Error(message),
+            }),
+        }
+    }
+
+    report
+}
+
+/// Evaluates a test file, returning the outcome of each of its tests.
+fn run_file(path: &Path, env: &Environment) -> Result<Vec<(String, TestOutcome)>, String> {
+    let mut env = env.clone();
+    env.current_module = Some(rc_world::str_to_rc(&path.display().to_string()));
+
+    let bytes = std::fs::read(path).map_err(|err| Error::Io(err).to_string())?;
+    let code = crate::decode_text(&bytes).map_err(|err| Error::Encoding(err).to_string())?;
+    let parsed = parser::parse(&code).map_err(|err| Error::Parse(err).to_string())?;
+    let value = parser::eval(env, &parsed).map_err(|err| Error::Eval(err).to_string())?;
+
+    let tests = match &value {
+        Value::Map(map) => match map.get("tests") {
+            Some(Value::Map(tests)) => tests,
+            _ => return Err(not_a_test_file(&value)),
+        },
+        _ => return Err(not_a_test_file(&value)),
+    };
+
+    Ok(tests
+        .iter()
+        .map(|(name, test)| {
+            let outcome = match test {
+                Value::Bool(true) => TestOutcome::Passed,
+                Value::Bool(false) => TestOutcome::Failed,
+                other => TestOutcome::Error(format!(
+                    "Test `{name}` should evaluate to a bool, got a value of type {}",
+                    other.canonical_type()
+                )),
+            };
+            (name.to_string(), outcome)
+        })
+        .collect())
+}
+
+fn not_a_test_file(value: &Value) -> String {
+    format!(
+        "A test file should evaluate to `{{ tests: {{ ... }} }}`, with a bool for each test, \
+        got a value of type {}",
+        value.canonical_type()
+    )
+}
@@ -0,0 +1,138 @@
+use std::str::Chars;
+
+use thiserror::Error;
+
+/// The reason why a string cannot be unescaped.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum UnescapeError {
+    /// The string does not start with a double quote.
+    #[error("Missing starting double quote in escaped string")]
+    NoStartingQuote,
+    /// A backslash is followed by something other than the JSON escapes, e.g., `\x41`.
+    #[error("No such escape sequence \\{0}")]
+    UnknownEscape(char),
+    /// A `\u` escape is not followed by four hexadecimal digits.
+    #[error("Expected hexadecimal digit, got {0:?}")]
+    NotADigit(char),
+    /// A `\u` escape is half of a UTF-16 surrogate pair, but the other half is missing.
+    #[error("The escape \\u{0:04x} is half of a surrogate pair, but the other half is missing")]
+    LoneSurrogate(u32),
+    /// There is something after the ending double quote.
+    #[error("Quoted string ended before the end of the input")]
+    SpuriousTail,
+    /// The string ends before its ending double quote.
+    #[error("Input ended before the ending double quote in escaped string")]
+    NoEndingQuote,
+}
+
+/// Quotes a string, as per the official JSON rules. The result reads back as the same
+/// string with [`unescape`], both in Rust and in Ryan code.
+///
+/// ```
+/// assert_eq!(ryan::text::escape("say \"hi\"\n"), r#""say \"hi\"\n""#);
+/// assert_eq!(ryan::text::escape("\u{7}😀"), r#""\u0007😀""#);
+/// ```
+pub fn escape(s: &str) -> String {
+    let mut string = String::with_capacity(s.len() + 2);
+    string.push('"');
+
+    for ch in s.chars() {
+        match ch {
+            '"' => string.push_str(r#"\""#),
+            '\\' => string.push_str(r"\\"),
+            '\u{0008}' => string.push_str(r"\b"),
+            '\u{000c}' => string.push_str(r"\f"),
+            '\n' => string.push_str(r"\n"),
+            '\r' => string.push_str(r"\r"),
+            '\t' => string.push_str(r"\t"),
+            ch if ch.is_control() => string.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => string.push(ch),
+        }
+    }
+
+    string.push('"');
+
+    string
+}
+
+/// Unquotes a string, as per the official JSON rules. This is how the strings in Ryan
+/// code are read. Characters outside the Basic Multilingual Plane, like emoji, can be
+/// escaped as a UTF-16 surrogate pair.
+///
+/// ```
+/// assert_eq!(ryan::text::unescape(r#""a\tb""#).unwrap(), "a\tb");
+/// assert_eq!(ryan::text::unescape(r#""\ud83d\ude00""#).unwrap(), "😀");
+/// assert!(ryan::text::unescape(r#""\x41""#).is_err());
+/// ```
+///
+/// See https://stackoverflow.com/questions/19176024/ for implementation.
+pub fn unescape(s: &str) -> Result<String, UnescapeError> {
+    let mut chars = s.chars();
+    let mut string = String::with_capacity(s.len());
+
+    if chars.next() != Some('"') {
+        return Err(UnescapeError::NoStartingQuote);
+    }
+
+    loop {
+        match next(&mut chars)? {
+            '"' => break,
+            '\\' => match next(&mut chars)? {
+                '"' => string.push('"'),
+                '\\' => string.push('\\'),
+                '/' => string.push('/'),
+                'b' => string.push('\u{0008}'),
+                'f' => string.push('\u{000c}'),
+                'n' => string.push('\n'),
+                'r' => string.push('\r'),
+                't' => string.push('\t'),
+                'u' => string.push(unicode_escape(&mut chars)?),
+                unknown => return Err(UnescapeError::UnknownEscape(unknown)),
+            },
+            ch => string.push(ch),
+        }
+    }
+
+    if chars.next().is_none() {
+        Ok(string)
+    } else {
+        Err(UnescapeError::SpuriousTail)
+    }
+}
+
+/// The next character of a string that has not ended yet.
+fn next(chars: &mut Chars) -> Result<char, UnescapeError> {
+    chars.next().ok_or(UnescapeError::NoEndingQuote)
+}
+
+/// Reads the four hexadecimal digits of a `\u` escape.
+fn hex_code(chars: &mut Chars) -> Result<u32, UnescapeError> {
+    (0..4).try_fold(0, |code, _| {
+        let ch = next(chars)?;
+        let digit = ch.to_digit(16).ok_or(UnescapeError::NotADigit(ch))?;
+        Ok(code << 4 | digit)
+    })
+}
+
+/// Reads what comes after `\u`: either a character in the Basic Multilingual Plane or a
+/// high surrogate followed by `\u` and a low surrogate.
+fn unicode_escape(chars: &mut Chars) -> Result<char, UnescapeError> {
+    let high = hex_code(chars)?;
+    if !(0xd800..0xdc00).contains(&high) {
+        // Only surrogates are not characters here:
+        return char::from_u32(high).ok_or(UnescapeError::LoneSurrogate(high));
+    }
+
+    if !chars.as_str().starts_with("\\u") {
+        return Err(UnescapeError::LoneSurrogate(high));
+    }
+    chars.nth(1);
+
+    let low = hex_code(chars)?;
+    if !(0xdc00..0xe000).contains(&low) {
+        return Err(UnescapeError::LoneSurrogate(high));
+    }
+
+    let code = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+    Ok(char::from_u32(code).expect("surrogate pairs are always valid unicode"))
+}
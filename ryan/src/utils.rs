@@ -2,8 +2,6 @@ use std::fmt::Display;
 
 use std::fmt;
 
-use thiserror::Error;
-
 pub fn fmt_list<I>(f: &mut fmt::Formatter<'_>, it: I) -> fmt::Result
 where
     I: IntoIterator,
@@ -15,7 +13,7 @@ where
         write!(f, "{item}")?;
     }
 
-    while let Some(item) = it.next() {
+    for item in it {
         write!(f, ", {item}")?;
     }
 
@@ -34,7 +32,7 @@ where
         write!(f, "{key}: {value}")?;
     }
 
-    while let Some((key, value)) = it.next() {
+    for (key, value) in it {
         write!(f, ", {key}: {value}")?;
     }
 
@@ -46,122 +44,271 @@ pub(crate) struct QuotedStr<'a>(pub &'a str);
 
 impl QuotedStr<'_> {
     pub fn quote(&self) -> String {
-        let mut string = String::with_capacity(self.0.len() + 2);
-        string.push('"');
-
-        for ch in self.0.chars() {
-            match ch {
-                '"' => string.push_str(r#"\""#),
-                '\\' => string.push_str(r"\\"),
-                '\u{0008}' => string.push_str(r"\b"),
-                '\u{000c}' => string.push_str(r"\f"),
-                '\n' => string.push_str(r"\n"),
-                '\r' => string.push_str(r"\r"),
-                '\t' => string.push_str(r"\t"),
-                ch => string.push(ch),
+        crate::text::escape(self.0)
+    }
+}
+
+impl Display for QuotedStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.quote())
+    }
+}
+
+/// A float displayed as the shortest text that reads back as the same float. The text
+/// always has a decimal point or an exponent, e.g., `1.0` or `1e300`, so that it does not
+/// read back as an integer.
+pub(crate) struct CanonicalFloat(pub f64);
+
+impl Display for CanonicalFloat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let float = self.0;
+        if !float.is_finite() {
+            return write!(f, "{float}");
+        }
+
+        // Both `Display` and `LowerExp` give the shortest digits that read back as the same
+        // float. Very large and very small floats are written with an exponent, like in
+        // JavaScript, instead of with hundreds of zeros:
+        let scientific = format!("{float:e}");
+        let (_, exponent) = scientific
+            .split_once('e')
+            .expect("floats formatted with `e` have an exponent");
+        let exponent = exponent.parse::<i32>().expect("exponent is an integer");
+
+        if (-7..21).contains(&exponent) {
+            let plain = float.to_string();
+            if plain.contains('.') {
+                write!(f, "{plain}")
+            } else {
+                write!(f, "{plain}.0")
             }
+        } else {
+            write!(f, "{scientific}")
         }
+    }
+}
+
+/// Finds the line and the column, both starting at zero, of a byte offset in the input.
+/// Columns count characters, not bytes. Offsets past the end of the input or in the
+/// middle of a character are moved back to the closest character boundary.
+pub(crate) fn line_col(input: &str, idx: usize) -> (usize, usize) {
+    let mut idx = idx.min(input.len());
+    while !input.is_char_boundary(idx) {
+        idx -= 1;
+    }
+
+    let before = &input[..idx];
+    let line_start = before.rfind('\n').map(|pos| pos + 1).unwrap_or(0);
+    let line = before.matches('\n').count();
+
+    (line, before[line_start..].chars().count())
+}
 
-        string.push('"');
+/// Parses an integer written in some text, in a given radix, as leniently as Ryan number
+/// literals: surrounding whitespace and underscores between digits are ignored. A prefix
+/// such as `0x` is also accepted, if it matches the radix.
+pub(crate) fn parse_int(text: &str, radix: u32) -> Option<i64> {
+    let text = text.trim().replace('_', "");
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", text.strip_prefix('+').unwrap_or(&text)),
+    };
+    let prefix = match radix {
+        2 => Some("0b"),
+        8 => Some("0o"),
+        16 => Some("0x"),
+        _ => None,
+    };
+    let digits = prefix
+        .and_then(|prefix| {
+            digits
+                .strip_prefix(prefix)
+                .or_else(|| digits.strip_prefix(&*prefix.to_uppercase()))
+        })
+        .unwrap_or(digits);
 
-        string
+    // `from_str_radix` accepts signs of its own:
+    if digits.starts_with(['+', '-']) {
+        return None;
     }
+
+    i64::from_str_radix(&format!("{sign}{digits}"), radix).ok()
 }
 
-impl Display for QuotedStr<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.quote())
+/// Parses a float written in some text as leniently as Ryan number literals: surrounding
+/// whitespace and underscores between digits are ignored.
+pub(crate) fn parse_float(text: &str) -> Option<f64> {
+    text.trim().replace('_', "").parse().ok()
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes bytes in standard base64, with padding.
+#[cfg(feature = "builtins")]
+pub(crate) fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | (byte as u32) << (16 - 8 * i)
+        });
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let sextet = (group >> (18 - 6 * i)) & 0x3f;
+                encoded.push(BASE64_ALPHABET[sextet as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
     }
+
+    encoded
 }
 
-#[derive(Debug, Error)]
-pub enum UnescapeError {
-    #[error("Missing starting double quote in escaped string")]
-    NoStartingQuote,
-    #[error("No such escape sequence \\{0}")]
-    UnknownEscape(char),
-    #[error("Expected hexadecimal digit, got {0:?}")]
-    NotADigit(char),
-    #[error("The character \\u{0:x} is not valid unicode")]
-    NotUnicode(u32),
-    #[error("Quoted string ended before the end of the input")]
-    SpuriousTail,
-    #[error("Input ended before the ending double quote in escaped string")]
-    NoEndingQuote,
+/// Decodes standard base64. Padding is optional and whitespace is ignored.
+pub(crate) fn base64_decode(text: &str) -> Result<Vec<u8>, String> {
+    let mut decoded = Vec::with_capacity(text.len() / 4 * 3);
+    let mut group = 0u32;
+    let mut sextets = 0;
+
+    for ch in text
+        .trim_end_matches(|ch: char| ch == '=' || ch.is_whitespace())
+        .chars()
+    {
+        if ch.is_whitespace() {
+            continue;
+        }
+
+        let sextet = BASE64_ALPHABET
+            .iter()
+            .position(|&digit| digit as char == ch)
+            .ok_or_else(|| format!("Invalid base64 character {ch:?}"))?;
+        group = group << 6 | sextet as u32;
+        sextets += 1;
+
+        if sextets == 4 {
+            decoded.extend_from_slice(&group.to_be_bytes()[1..]);
+            group = 0;
+            sextets = 0;
+        }
+    }
+
+    match sextets {
+        0 => {}
+        2 => decoded.push((group >> 4) as u8),
+        3 => decoded.extend_from_slice(&((group >> 2) as u16).to_be_bytes()),
+        _ => return Err("Invalid base64 length".to_string()),
+    }
+
+    Ok(decoded)
 }
 
-/// Unquotes a string, as per the official JSON rules.
-///
-/// See https://stackoverflow.com/questions/19176024/ for implementation.
-pub(crate) fn unescape(s: &str) -> Result<String, UnescapeError> {
-    let mut chars = s.chars();
-    let mut next = move || chars.next().ok_or(UnescapeError::NoEndingQuote);
-    let mut string = String::with_capacity(s.len());
-
-    if next()? != '"' {
-        return Err(UnescapeError::NoStartingQuote);
-    }
-
-    loop {
-        match next()? {
-            '"' => break,
-            '\\' => match next()? {
-                '"' => string.push('"'),
-                '\\' => string.push('\\'),
-                '/' => string.push('/'),
-                'b' => string.push('\u{0008}'),
-                'f' => string.push('\u{000c}'),
-                'n' => string.push('\n'),
-                'r' => string.push('\r'),
-                't' => string.push('\t'),
-                'u' => {
-                    // This could be a closure, but debugging got the best of me...
-                    macro_rules! next_digit {
-                        () => {
-                            next().and_then(|ch| {
-                                ch.to_digit(16).ok_or(UnescapeError::NotADigit(ch))
-                            })?
-                        };
-                    }
-                    // Descending order...
-                    let code = (next_digit!() << 12)
-                        + (next_digit!() << 8)
-                        + (next_digit!() << 4)
-                        + (next_digit!() << 0);
-                    let ch = char::from_u32(code).ok_or(UnescapeError::NotUnicode(code))?;
-                    string.push(ch);
-                }
-                unknown => return Err(UnescapeError::UnknownEscape(unknown)),
-            },
-            ch => string.push(ch),
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Computes the SHA-256 digest of some bytes.
+pub(crate) fn sha256(bytes: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    // Pad with a single 1 bit, then zeros up to 56 bytes mod 64, then the bit length:
+    let mut padded = bytes.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&((bytes.len() as u64).wrapping_mul(8)).to_be_bytes());
+
+    for block in padded.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(SHA256_K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (word, added) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(added);
         }
     }
 
-    // If error, the whole input has been consumed and everything is ok.
-    if next().is_err() {
-        Ok(string)
-    } else {
-        Err(UnescapeError::SpuriousTail)
+    let mut digest = [0; 32];
+    for (chunk, word) in digest.chunks_mut(4).zip(state) {
+        chunk.copy_from_slice(&word.to_be_bytes());
     }
+
+    digest
 }
 
-pub(crate) fn line_col(input: &str, idx: usize) -> (usize, usize) {
-    let mut lines = 0;
-    let mut pos = 0;
-    let mut count = 0;
-
-    for ch in input.chars().take(idx) {
-        count += 1;
-        if ch == '\n' {
-            lines += 1;
-            pos = 0;
-        } else {
-            pos += 1;
+/// Decodes the `%XX` escapes of a URL into the bytes they stand for. Everything else is
+/// kept as is, including `+`.
+pub(crate) fn percent_decode(text: &str) -> Result<Vec<u8>, String> {
+    let mut decoded = Vec::with_capacity(text.len());
+    let mut bytes = text.bytes();
+
+    while let Some(byte) = bytes.next() {
+        if byte != b'%' {
+            decoded.push(byte);
+            continue;
         }
+
+        let escape = [bytes.next(), bytes.next()];
+        let hex = escape
+            .iter()
+            .flatten()
+            .map(|&digit| digit as char)
+            .collect::<String>();
+        if hex.len() != 2 || !hex.chars().all(|digit| digit.is_ascii_hexdigit()) {
+            return Err(format!("Invalid percent escape `%{hex}`"));
+        }
+        decoded.push(u8::from_str_radix(&hex, 16).expect("two hexadecimal digits are a byte"));
     }
 
-    // Extra offset if `idx` points outside the string:
-    let extra_offset = idx - count;
+    Ok(decoded)
+}
 
-    (lines, pos + extra_offset)
+/// Encodes bytes as lowercase hexadecimal.
+pub(crate) fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
 }
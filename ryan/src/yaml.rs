@@ -0,0 +1,285 @@
+use std::fmt::Write;
+
+use crate::parser::{NotRepresentable, Value};
+use crate::utils::CanonicalFloat;
+
+/// The number of spaces each nesting level is indented by.
+const INDENT: usize = 2;
+
+/// Words that some YAML parser reads as a boolean or as `null` when unquoted. YAML 1.1
+/// parsers take `no` for `false`, which is how Norway goes missing from lists of country
+/// codes. These are compared ignoring case.
+const RESERVED_WORDS: &[&str] = &["y", "n", "yes", "no", "true", "false", "on", "off", "null"];
+
+/// Writes a Ryan value as YAML, in block style, for the tools that want YAML instead of
+/// JSON. Dictionaries keep the order of their keys and values shared in many places are
+/// written out again every time, without anchors or aliases. Multi-line text is written
+/// as a literal block and text that a YAML parser could take for something else, like
+/// `no`, `1.10` or `2023-01-01`, is quoted:
+///
+/// ```
+/// use ryan::Environment;
+///
+/// let code = r#"
+///     let labels = { app: "web", country: "no" };
+///     {
+///         version: "1.10",
+///         replicas: 3,
+///         labels,
+///         selector: { matchLabels: labels },
+///         script: "set -e\nmake\n",
+///         ports: [{ port: 80 }, { port: 443 }],
+///         args: [],
+///     }
+/// "#;
+/// let parsed = ryan::parser::parse(code).unwrap();
+/// let value = ryan::parser::eval(Environment::new(None), &parsed).unwrap();
+///
+/// assert_eq!(
+///     ryan::to_yaml_string(&value).unwrap(),
+///     r#"version: "1.10"
+/// replicas: 3
+/// labels:
+///   app: web
+///   country: "no"
+/// selector:
+///   matchLabels:
+///     app: web
+///     country: "no"
+/// script: |
+///   set -e
+///   make
+/// ports:
+///   - port: 80
+///   - port: 443
+/// args: []
+/// "#,
+/// );
+/// ```
+///
+/// Like JSON, YAML has no counterpart for patterns, types and bytes, nor for infinite
+/// floats and `NaN` in Ryan's output, so these are reported as [`NotRepresentable`].
+pub fn to_yaml_string(value: &Value) -> Result<String, NotRepresentable> {
+    value.check_representable()?;
+
+    let mut yaml = String::new();
+    write_node(&mut yaml, value, 0);
+
+    Ok(yaml)
+}
+
+/// Writes a value whose first line starts at the current position, which is column
+/// `indent`, e.g., right after the `- ` of a list item. Every other line is indented
+/// by `indent` spaces.
+fn write_node(yaml: &mut String, value: &Value, indent: usize) {
+    match value {
+        Value::Map(map) if !map.is_empty() => {
+            for (idx, (key, value)) in map.iter().enumerate() {
+                if idx > 0 {
+                    push_indent(yaml, indent);
+                }
+                yaml.push_str(&quote_key(key));
+                yaml.push(':');
+                write_entry_value(yaml, value, indent);
+            }
+        }
+        Value::List(list) if !list.is_empty() => {
+            for (idx, item) in list.iter().enumerate() {
+                if idx > 0 {
+                    push_indent(yaml, indent);
+                }
+                yaml.push_str("- ");
+                write_node(yaml, item, indent + INDENT);
+            }
+        }
+        Value::Text(text) if is_literal_block(text) => {
+            // At the top level, content in the first column could be taken for a
+            // document marker, like `---`:
+            write_literal_block(yaml, text, indent.max(INDENT));
+        }
+        scalar => {
+            yaml.push_str(&fmt_scalar(scalar));
+            yaml.push('\n');
+        }
+    }
+}
+
+/// Writes the value of a dictionary entry, right after the colon following the key. The
+/// key is at column `indent`.
+fn write_entry_value(yaml: &mut String, value: &Value, indent: usize) {
+    match value {
+        Value::Map(_) | Value::List(_) if !is_empty_collection(value) => {
+            yaml.push('\n');
+            push_indent(yaml, indent + INDENT);
+            write_node(yaml, value, indent + INDENT);
+        }
+        Value::Text(text) if is_literal_block(text) => {
+            yaml.push(' ');
+            write_literal_block(yaml, text, indent + INDENT);
+        }
+        scalar => {
+            yaml.push(' ');
+            yaml.push_str(&fmt_scalar(scalar));
+            yaml.push('\n');
+        }
+    }
+}
+
+fn is_empty_collection(value: &Value) -> bool {
+    match value {
+        Value::Map(map) => map.is_empty(),
+        Value::List(list) => list.is_empty(),
+        _ => false,
+    }
+}
+
+fn push_indent(yaml: &mut String, indent: usize) {
+    yaml.push_str(&" ".repeat(indent));
+}
+
+/// Writes multi-line text as a literal block scalar, like `|`, with every line of the
+/// text indented by `indent` spaces. The chomping indicator keeps the trailing newlines
+/// exactly as they are: `|-` strips the last newline, `|` keeps one and `|+` keeps all.
+fn write_literal_block(yaml: &mut String, text: &str, indent: usize) {
+    let body = match text.strip_suffix('\n') {
+        Some(body) if body.ends_with('\n') => {
+            yaml.push_str("|+\n");
+            body
+        }
+        Some(body) => {
+            yaml.push_str("|\n");
+            body
+        }
+        None => {
+            yaml.push_str("|-\n");
+            text
+        }
+    };
+
+    for line in body.split('\n') {
+        if !line.is_empty() {
+            push_indent(yaml, indent);
+            yaml.push_str(line);
+        }
+        yaml.push('\n');
+    }
+}
+
+/// Whether text is best written as a literal block: it spans many lines and every line
+/// reads back verbatim. Lines only made of whitespace and a first line starting with
+/// whitespace would need explicit indentation, so these are quoted instead.
+fn is_literal_block(text: &str) -> bool {
+    text.contains('\n')
+        && text.chars().all(|ch| ch == '\n' || is_printable(ch))
+        && text
+            .split('\n')
+            .all(|line| line.is_empty() || !line.trim().is_empty())
+        && !text
+            .trim_start_matches('\n')
+            .starts_with(|ch: char| ch.is_whitespace())
+}
+
+/// Whether a character can appear in YAML as it is, without an escape.
+fn is_printable(ch: char) -> bool {
+    (ch == '\t' || !ch.is_control()) && !matches!(ch, '\u{feff}' | '\u{2028}' | '\u{2029}')
+}
+
+fn quote_key(key: &str) -> String {
+    if is_plain(key) {
+        key.to_owned()
+    } else {
+        quote(key)
+    }
+}
+
+/// Formats a scalar, or an empty list or dictionary, in a single line. Floats always have
+/// a decimal point, so that they read back as floats, and an exponent with a sign, which
+/// is what YAML 1.1 expects.
+fn fmt_scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_owned(),
+        Value::Bool(true) => "true".to_owned(),
+        Value::Bool(false) => "false".to_owned(),
+        Value::Integer(int) => int.to_string(),
+        Value::Float(float) => {
+            let formatted = CanonicalFloat(*float).to_string();
+            match formatted.split_once('e') {
+                Some((mantissa, exponent)) => {
+                    let mantissa = if mantissa.contains('.') {
+                        mantissa.to_owned()
+                    } else {
+                        format!("{mantissa}.0")
+                    };
+                    let sign = if exponent.starts_with('-') { "" } else { "+" };
+                    format!("{mantissa}e{sign}{exponent}")
+                }
+                None => formatted,
+            }
+        }
+        Value::Text(text) if is_plain(text) => text.to_string(),
+        Value::Text(text) => quote(text),
+        Value::List(_) => "[]".to_owned(),
+        Value::Map(_) => "{}".to_owned(),
+        _ => unreachable!("only representable scalars are written as YAML"),
+    }
+}
+
+/// Whether text reads back as the same text when written without quotes, in every YAML
+/// version. This is on the safe side: when in doubt, the text is quoted.
+fn is_plain(text: &str) -> bool {
+    let Some(first) = text.chars().next() else {
+        return false;
+    };
+
+    if !(first.is_alphanumeric() || matches!(first, '_' | '/' | '.')) || text.ends_with(' ') {
+        return false;
+    }
+
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let is_safe = match ch {
+            // `: ` starts a value and a final `:` is a key with no value:
+            ':' => !matches!(chars.peek(), None | Some(' ')),
+            ' ' | '_' | '-' | '.' | '/' | '+' | '=' | '@' | '$' | '(' | ')' | '~' | '%' => true,
+            ch => ch.is_alphanumeric(),
+        };
+
+        if !is_safe {
+            return false;
+        }
+    }
+
+    // Numbers, versions, dates and times, in any base or notation, start with a digit.
+    // So do sexagesimal numbers, like `1:20`, in YAML 1.1:
+    let looks_numeric = first.is_ascii_digit()
+        || (first == '.' && text[1..].starts_with(|ch: char| ch.is_ascii_digit()));
+    let is_special_float = first == '.'
+        && [".inf", ".nan"].contains(&text.to_ascii_lowercase().as_str());
+    let is_reserved = RESERVED_WORDS
+        .iter()
+        .any(|word| word.eq_ignore_ascii_case(text));
+
+    !looks_numeric && !is_special_float && !is_reserved
+}
+
+/// Quotes text in a double-quoted scalar, the only YAML style that can escape any
+/// character.
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+
+    for ch in text.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            ch if is_printable(ch) => quoted.push(ch),
+            ch => write!(quoted, "\\u{:04x}", ch as u32).expect("writing to string never fails"),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
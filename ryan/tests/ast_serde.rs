@@ -0,0 +1,73 @@
+#![cfg(feature = "ast-serde")]
+
+use ryan::environment::Environment;
+use ryan::parser::{self, Block, Value};
+
+/// A program written directly as a syntax tree, as a host without the parser would get
+/// it: `let x = 1; x + 2`.
+const HAND_WRITTEN: &str = r#"{
+    "bindings": [
+        {
+            "Destructuring": {
+                "pattern": { "Identifier": ["x", null] },
+                "block": {
+                    "bindings": [],
+                    "expression": { "Literal": { "Integer": 1 } },
+                    "has_expression": true
+                }
+            }
+        }
+    ],
+    "expression": {
+        "BinaryOperation": {
+            "left": { "Literal": { "Identifier": "x" } },
+            "op": "Plus",
+            "right": { "Literal": { "Integer": 2 } }
+        }
+    },
+    "has_expression": true
+}"#;
+
+#[test]
+fn evaluates_a_deserialized_syntax_tree() {
+    let block: Block = serde_json::from_str(HAND_WRITTEN).unwrap();
+    let value = parser::eval(Environment::new(None), &block).unwrap();
+
+    assert_eq!(value, Value::Integer(3));
+}
+
+#[cfg(feature = "parse")]
+#[test]
+fn round_trips_a_parsed_syntax_tree() {
+    let source = r#"
+        let base = { port: 8080, "host name": "localhost" };
+        let double x = x * 2;
+        let { a, b ? 0, .. } = { a: 1 };
+        type Port = int;
+        let square = \x => x * x;
+        {
+            ...base,
+            [`key-${a + b}`]: [double x for x in [1, 2, 3] if x > 1],
+            squares: { k: square v for [k, v] in [["a", 1], ["b", 2]] },
+            pi: `${3.14159:.2}`,
+            fallback: import "env:__RYAN_AST_SERDE_MISSING__" as text or "none",
+            scoped: (let y = 3; y + 1),
+            port_text: base.port as text,
+            missing: base?.missing,
+            typed: [1 is int, base is { port: Port, .. }],
+            not_in: 4 not in [1, 2],
+            cond: if not false then -1 else +1,
+            replicas: match "prod" { "prod" => 32, n if n != "" => 8, _ => 2 },
+            checked: (\x: Port => x) 1,
+        }
+    "#;
+
+    let parsed = parser::parse(source).unwrap();
+    let serialized = serde_json::to_string(&parsed).unwrap();
+    let deserialized: Block = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized, parsed);
+
+    let expected = parser::eval(Environment::new(None), &parsed).unwrap();
+    let value = parser::eval(Environment::new(None), &deserialized).unwrap();
+    assert_eq!(value, expected);
+}
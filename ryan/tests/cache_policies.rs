@@ -0,0 +1,118 @@
+//! Which imported modules are kept in the cache of an environment across evaluations.
+#![cfg(feature = "parse")]
+
+mod common;
+
+use std::{cell::RefCell, collections::HashMap, error::Error, io::Read, rc::Rc};
+
+use ryan::environment::{Environment, EnvironmentBuilder, EvalEvent, ImportLoader};
+
+use common::{eval_with, fixture};
+
+/// Serves modules from a map that can be changed between evaluations.
+#[derive(Debug, Clone, Default)]
+struct Modules(Rc<RefCell<HashMap<String, String>>>);
+
+impl Modules {
+    fn set(&self, path: &str, code: &str) {
+        self.0.borrow_mut().insert(path.to_owned(), code.to_owned());
+    }
+}
+
+impl ImportLoader for Modules {
+    fn resolve(&self, _: Option<&str>, path: &str) -> Result<String, Box<dyn Error>> {
+        Ok(path.to_owned())
+    }
+
+    fn load(&self, path: &str) -> Result<Box<dyn Read>, Box<dyn Error>> {
+        let code = self.0.borrow().get(path).cloned().ok_or("no such module")?;
+        Ok(Box::new(std::io::Cursor::new(code)))
+    }
+
+    fn cacheable(&self, path: &str) -> bool {
+        !path.starts_with("live/")
+    }
+}
+
+/// Builds an environment that records, for each import, whether it hit the cache.
+fn recording(builder: EnvironmentBuilder) -> (Environment, Rc<RefCell<Vec<bool>>>) {
+    let cache_hits = Rc::new(RefCell::new(vec![]));
+    let recorded = cache_hits.clone();
+    let env = builder
+        .on_event(move |event| {
+            if let EvalEvent::ImportLoaded { cache_hit, .. } = event {
+                recorded.borrow_mut().push(cache_hit);
+            }
+        })
+        .build();
+
+    (env, cache_hits)
+}
+
+#[test]
+fn environment_variables_are_read_again_by_default() {
+    const VAR: &str = "RYAN_CACHE_POLICIES_FRESH";
+    let env = Environment::new(None);
+    let code = format!(r#"import "env:{VAR}" as text"#);
+
+    std::env::set_var(VAR, "before");
+    assert_eq!(eval_with(&env, &code), "before");
+
+    std::env::set_var(VAR, "after");
+    assert_eq!(eval_with(&env, &code), "after");
+}
+
+#[test]
+fn environment_variables_can_still_be_cached() {
+    const VAR: &str = "RYAN_CACHE_POLICIES_CACHED";
+    let env = Environment::builder().cache_env_imports(true).build();
+    let code = format!(r#"import "env:{VAR}" as text"#);
+
+    std::env::set_var(VAR, "before");
+    assert_eq!(eval_with(&env, &code), "before");
+
+    std::env::set_var(VAR, "after");
+    assert_eq!(eval_with(&env, &code), "before");
+}
+
+#[test]
+fn files_remain_cached() {
+    let dir = fixture("files", &[("cached.ryan", r#""before""#)]);
+    let file = dir.join("cached.ryan");
+
+    let (env, cache_hits) = recording(Environment::builder());
+    let code = format!("import {:?}", file.display().to_string());
+    assert_eq!(eval_with(&env, &code), "before");
+
+    std::fs::write(&file, r#""after""#).unwrap();
+    assert_eq!(eval_with(&env, &code), "before");
+    assert_eq!(*cache_hits.borrow(), [false, true]);
+}
+
+#[test]
+fn loaders_can_keep_paths_out_of_the_cache() {
+    let modules = Modules::default();
+    modules.set("live/status", "up");
+    modules.set("static/name", "api");
+    let (env, cache_hits) = recording(Environment::builder().import_loader(modules.clone()));
+    let code = r#"`${import "static/name" as text} is ${import "live/status" as text}`"#;
+
+    assert_eq!(eval_with(&env, code), "api is up");
+
+    modules.set("live/status", "down");
+    modules.set("static/name", "web");
+    assert_eq!(eval_with(&env, code), "api is down");
+    assert_eq!(*cache_hits.borrow(), [false, false, true, false]);
+}
+
+#[test]
+fn overridden_paths_are_cached_whatever_the_loader_says() {
+    let modules = Modules::default();
+    let loader = modules.r#override("live/status".to_owned(), "pinned".to_owned());
+    let (env, cache_hits) = recording(Environment::builder().import_loader(loader));
+
+    for _ in 0..2 {
+        assert_eq!(eval_with(&env, r#"import "live/status" as text"#), "pinned");
+    }
+    assert_eq!(*cache_hits.borrow(), [false, true]);
+}
@@ -0,0 +1,97 @@
+//! Stopping evaluations with a `CancellationToken`, from another thread or on a deadline.
+#![cfg(feature = "parse")]
+
+use std::time::{Duration, Instant};
+
+use ryan::environment::{CancellationToken, Environment, ImportLoader, NoImport};
+use ryan::parser::{EvalError, EvalErrorKind};
+
+/// Takes way too long to finish: ten billion sums.
+const ENDLESS: &str = "[[x + y for y in range [0, 100000]] for x in range [0, 100000]]";
+
+fn eval_error(env: &Environment, source: &str) -> EvalError {
+    match ryan::from_str_with_env::<serde_json::Value>(env, source) {
+        Err(ryan::Error::Eval(err)) => err,
+        other => panic!("expected an evaluation error, got {other:?}"),
+    }
+}
+
+#[test]
+fn stops_a_long_comprehension_cancelled_from_another_thread() {
+    let token = CancellationToken::new();
+    let env = Environment::builder().cancel_token(token.clone()).build();
+
+    let canceller = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        token.cancel();
+    });
+
+    let started = Instant::now();
+    let err = eval_error(&env, ENDLESS);
+    canceller.join().unwrap();
+
+    assert!(matches!(err.kind(), EvalErrorKind::Cancelled));
+    assert_eq!(err.kind().to_string(), "Evaluation was cancelled");
+    assert_eq!(err.kind().name(), "cancelled");
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn does_not_interfere_until_cancelled() {
+    let token = CancellationToken::new();
+    let env = Environment::builder().cancel_token(token.clone()).build();
+
+    let value: Vec<i64> = ryan::from_str_with_env(&env, "[x * 2 for x in range [0, 3]]").unwrap();
+    assert_eq!(value, [0, 2, 4]);
+    assert!(!env.is_cancelled());
+
+    token.cancel();
+    assert!(env.is_cancelled());
+    assert!(matches!(
+        eval_error(&env, "let x = 1; x").kind(),
+        EvalErrorKind::Cancelled
+    ));
+}
+
+#[test]
+fn cancelled_imports_do_not_fall_back_to_defaults() {
+    let token = CancellationToken::new();
+    let env = Environment::builder()
+        .import_loader(NoImport.r#override("/slow.ryan".to_owned(), ENDLESS.to_owned()))
+        .cancel_token(token.clone())
+        .build();
+
+    let canceller = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        token.cancel();
+    });
+
+    let err = eval_error(&env, r#"import "/slow.ryan" or []"#);
+    canceller.join().unwrap();
+
+    assert!(matches!(err.kind(), EvalErrorKind::Cancelled));
+}
+
+#[cfg(feature = "std-thread")]
+#[test]
+fn stops_evaluating_on_a_deadline() {
+    let env = Environment::new(None);
+
+    let started = Instant::now();
+    let result = ryan::from_str_with_env_and_deadline::<serde_json::Value>(
+        &env,
+        ENDLESS,
+        Duration::from_millis(50),
+    );
+
+    assert!(matches!(
+        result,
+        Err(ryan::Error::Eval(err)) if matches!(err.kind(), EvalErrorKind::Cancelled)
+    ));
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    // Fast programs finish well within the deadline:
+    let value: i64 =
+        ryan::from_str_with_env_and_deadline(&env, "1 + 1", Duration::from_secs(60)).unwrap();
+    assert_eq!(value, 2);
+}
@@ -0,0 +1,76 @@
+//! Circular imports are reported with the whole chain of modules, and a failed import does
+//! not leave the import stack in a state that looks like a cycle later on.
+#![cfg(feature = "parse")]
+
+use ryan::environment::{Environment, ImportLoader, NoImport};
+
+fn env(modules: &[(&str, &str)]) -> Environment {
+    let loader = NoImport.override_many(
+        modules
+            .iter()
+            .map(|(path, code)| (path.to_string(), Some(code.to_string()))),
+    );
+    Environment::builder().import_loader(loader).build()
+}
+
+fn eval_error(env: &Environment, code: &str) -> String {
+    ryan::from_str_with_env::<serde_json::Value>(env, code)
+        .unwrap_err()
+        .to_string()
+}
+
+#[test]
+fn cycles_are_reported_with_the_whole_chain() {
+    let env = env(&[
+        ("a.ryan", r#"import "b.ryan""#),
+        ("b.ryan", r#"import "c.ryan""#),
+        ("c.ryan", r#"import "a.ryan""#),
+    ]);
+    let err = eval_error(&env, r#"import "a.ryan""#);
+
+    assert!(
+        err.starts_with("Circular import detected: a.ryan -> b.ryan -> c.ryan -> a.ryan"),
+        "{err}"
+    );
+    assert!(
+        err.contains(r#"Loading import "a.ryan" from a.ryan -> b.ryan -> c.ryan"#),
+        "{err}"
+    );
+}
+
+#[test]
+fn modules_importing_themselves_are_cycles() {
+    let env = env(&[("a.ryan", r#"import "a.ryan""#)]);
+    let err = eval_error(&env, r#"import "a.ryan""#);
+
+    assert!(
+        err.starts_with("Circular import detected: a.ryan -> a.ryan"),
+        "{err}"
+    );
+}
+
+#[test]
+fn failed_imports_are_not_cycles_when_retried() {
+    let env = env(&[
+        ("broken.ryan", "let x = ;"),
+        ("sibling.ryan", r#"import "broken.ryan""#),
+    ]);
+
+    let first = eval_error(&env, r#"import "broken.ryan""#);
+    assert!(first.contains("Expected a code block"), "{first}");
+
+    let second = eval_error(&env, r#"import "sibling.ryan""#);
+    assert!(second.contains("Expected a code block"), "{second}");
+    assert!(!second.contains("Circular import"), "{second}");
+}
+
+#[test]
+fn the_same_module_can_be_imported_twice_in_a_row() {
+    let env = env(&[
+        ("a.ryan", "1"),
+        ("b.ryan", r#"[import "a.ryan", import "a.ryan"]"#),
+    ]);
+    let value: serde_json::Value = ryan::from_str_with_env(&env, r#"import "b.ryan""#).unwrap();
+
+    assert_eq!(value, serde_json::json!([1, 1]));
+}
@@ -0,0 +1,43 @@
+//! Helpers shared by the integration tests.
+
+// Each test crate compiles its own copy of this module and uses only some of the helpers.
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+use ryan::parser::{self, Value};
+use ryan::Environment;
+
+/// Parses and evaluates `source` in the default environment, panicking on any error.
+pub fn eval(source: &str) -> Value {
+    let block = parser::parse(source).unwrap();
+    parser::eval(Environment::new(None), &block).unwrap()
+}
+
+/// Evaluates `source` in the default environment into JSON, panicking on any error.
+pub fn eval_json(source: &str) -> serde_json::Value {
+    ryan::from_str(source).unwrap()
+}
+
+/// Evaluates `source` in the default environment into JSON, rendering any error as text.
+pub fn try_eval_json(source: &str) -> Result<serde_json::Value, String> {
+    ryan::from_str(source).map_err(|err| err.to_string())
+}
+
+/// Evaluates `source` in `env` into JSON, panicking on any error.
+pub fn eval_with(env: &Environment, source: &str) -> serde_json::Value {
+    ryan::from_str_with_env(env, source).unwrap()
+}
+
+/// Writes files under a fresh directory in the temporary directory, named after the test
+/// crate and `name`, so that tests running in parallel do not step on each other.
+pub fn fixture(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ryan-{}-{name}", env!("CARGO_CRATE_NAME")));
+    let _ = std::fs::remove_dir_all(&dir);
+    for (path, content) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    dir
+}
@@ -0,0 +1,92 @@
+//! How numbers compare: integers and floats by their numeric values, `NaN` equal to itself
+//! and sorted last, but never less or greater than anything with `<`, `<=`, `>` and `>=`.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+mod common;
+
+use ryan::environment::Environment;
+use ryan::parser::{self, Value};
+
+use common::eval;
+
+/// The operands of the comparison matrix, as written in Ryan and as `f64`, which holds all
+/// of them exactly.
+const OPERANDS: &[(&str, f64)] = &[
+    ("1", 1.0),
+    ("1.0", 1.0),
+    ("1.5", 1.5),
+    ("2", 2.0),
+    ("(0.0 / 0.0)", f64::NAN),
+];
+
+/// The expected result of an operator, with `NaN` equal to itself and unordered.
+fn expected(op: &str, a: f64, b: f64) -> bool {
+    let equal = a == b || (a.is_nan() && b.is_nan());
+    match op {
+        "==" => equal,
+        "!=" => !equal,
+        "<" => a < b,
+        "<=" => a <= b,
+        ">" => a > b,
+        ">=" => a >= b,
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn comparison_matrix() {
+    for op in ["==", "!=", "<", "<=", ">", ">="] {
+        for (left, a) in OPERANDS {
+            for (right, b) in OPERANDS {
+                let code = format!("{left} {op} {right}");
+                assert_eq!(eval(&code), Value::Bool(expected(op, *a, *b)), "{code}");
+            }
+        }
+    }
+}
+
+#[test]
+fn membership_uses_equality() {
+    assert_eq!(eval("1.0 in [1, 2]"), Value::Bool(true));
+    assert_eq!(eval("2 in [1.0, 2.0]"), Value::Bool(true));
+    assert_eq!(eval("(0.0 / 0.0) in [1, 0.0 / 0.0]"), Value::Bool(true));
+    assert_eq!(eval("1.5 not in [1, 2]"), Value::Bool(true));
+}
+
+#[test]
+fn mixed_lists_sort_with_nan_last() {
+    assert_eq!(
+        eval("sort [2, 0.0 / 0.0, 1.5, 1, -1.0]").to_string(),
+        "[-1.0, 1, 1.5, 2, NaN]"
+    );
+    assert_eq!(eval("min [2, 1.5, 3]").to_string(), "1.5");
+    assert_eq!(eval("max [1, 2.5, 2]").to_string(), "2.5");
+}
+
+#[test]
+fn big_integers_are_compared_exactly() {
+    // 2^53 + 1 is not a float, so it must not be rounded to 2^53 to be compared:
+    assert_eq!(
+        eval("9007199254740993 == 9007199254740992.0"),
+        Value::Bool(false)
+    );
+    assert_eq!(
+        eval("9007199254740993 > 9007199254740992.0"),
+        Value::Bool(true)
+    );
+    assert_eq!(
+        eval("9007199254740992.0 < 9007199254740993"),
+        Value::Bool(true)
+    );
+}
+
+#[test]
+fn other_types_are_not_numbers() {
+    assert_eq!(eval(r#"1 == "1""#), Value::Bool(false));
+    assert_eq!(eval("1 == true"), Value::Bool(false));
+    assert!(parser::eval(
+        Environment::new(None),
+        &parser::parse(r#""a" < 1"#).unwrap()
+    )
+    .is_err());
+}
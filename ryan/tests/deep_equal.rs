@@ -0,0 +1,45 @@
+//! How `deep_equal` and `is_subset` compare values: structurally, with integers and floats
+//! always being different values.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+mod common;
+
+use ryan::parser::Value;
+
+use common::eval;
+
+#[test]
+fn integers_are_not_floats() {
+    assert_eq!(eval("deep_equal [1, 1.0]"), Value::Bool(false));
+    assert_eq!(eval("deep_equal [[1], [1.0]]"), Value::Bool(false));
+    assert_eq!(eval("deep_equal [{a: 1.0}, {a: 1}]"), Value::Bool(false));
+    assert_eq!(eval("is_subset [{a: 1}, {a: 1.0}]"), Value::Bool(false));
+}
+
+#[test]
+fn large_integers_are_not_rounded() {
+    assert_eq!(
+        eval("deep_equal [9007199254740993, 9007199254740992.0]"),
+        Value::Bool(false)
+    );
+    assert_eq!(
+        eval("deep_equal [9007199254740993, 9007199254740992]"),
+        Value::Bool(false)
+    );
+}
+
+#[test]
+fn same_variants_are_compared_by_value() {
+    assert_eq!(eval("deep_equal [1, 1]"), Value::Bool(true));
+    assert_eq!(eval("deep_equal [1.5, 1.5]"), Value::Bool(true));
+    assert_eq!(eval("deep_equal [0 / 0, 0 / 0]"), Value::Bool(true));
+    assert_eq!(
+        eval("is_subset [{a: [1.0]}, {a: [1.0, 2], b: 3}]"),
+        Value::Bool(true)
+    );
+}
+
+#[test]
+fn equality_operator_still_compares_numbers() {
+    assert_eq!(eval("1 == 1.0"), Value::Bool(true));
+}
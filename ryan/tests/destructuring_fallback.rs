@@ -0,0 +1,68 @@
+//! Destructuring bindings with an `else` fallback, used when the value does not match.
+#![cfg(feature = "parse")]
+
+mod common;
+
+use common::try_eval_json;
+
+#[test]
+fn uses_the_fallback_when_the_pattern_does_not_match() {
+    let source = r#"
+        let conf = { host: "db" };
+        let { port, .. } = conf else { port: 5432 };
+        let [first, ..] = [] else [null];
+        { port, first }
+    "#;
+
+    assert_eq!(
+        try_eval_json(source).unwrap(),
+        serde_json::json!({ "port": 5432, "first": null })
+    );
+}
+
+#[test]
+fn ignores_the_fallback_when_the_pattern_matches() {
+    let source = r#"
+        let { port, .. } = { port: 80, host: "web" } else { port: 5432 };
+        let { a } = { a: 1 } else 1 / 0;
+        [port, a]
+    "#;
+
+    assert_eq!(try_eval_json(source).unwrap(), serde_json::json!([80, 1]));
+}
+
+#[test]
+fn discards_what_was_bound_before_the_pattern_failed() {
+    let source = r#"
+        let [a, b, 3] = [1, 2, 4] else [10, 20, 3];
+        a + b
+    "#;
+
+    assert_eq!(try_eval_json(source).unwrap(), serde_json::json!(30));
+}
+
+#[test]
+fn errors_when_the_fallback_does_not_match_either() {
+    let err = try_eval_json("let { port } = {} else { host: \"db\" }; port").unwrap_err();
+
+    assert!(err.contains("port"), "{err}");
+}
+
+#[test]
+fn errors_without_a_fallback() {
+    let err = try_eval_json("let { port, .. } = { host: \"db\" }; port").unwrap_err();
+
+    assert!(err.contains("port"), "{err}");
+}
+
+#[test]
+fn prints_the_fallback() {
+    let block = ryan::parser::parse("let { port, .. } = conf else { port: 5432 }; port").unwrap();
+    let printed = block.to_string();
+
+    assert_eq!(
+        printed,
+        "let { port, .. } = conf else { port: 5432 };\nport"
+    );
+    assert_eq!(ryan::parser::parse(&printed).unwrap(), block);
+}
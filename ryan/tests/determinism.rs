@@ -0,0 +1,85 @@
+//! The output of every program in `tests/determinism` must be byte for byte the same on
+//! every platform, since it is used for caching. Each `*.ryan` program is checked against
+//! the `*.out` file next to it. Set `UPDATE_SNAPSHOTS=1` to write the `*.out` files
+//! instead, and review the differences before committing them.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+use std::path::{Path, PathBuf};
+
+use ryan::{environment::Environment, parser, CanonicalizeOptions};
+
+fn programs() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/determinism");
+    let mut programs = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ryan"))
+        .collect::<Vec<_>>();
+    programs.sort();
+    programs
+}
+
+/// The value as written by Ryan and as written by `serde_json`, both before and after
+/// sorting the keys.
+fn output(path: &Path) -> String {
+    let code = std::fs::read_to_string(path).unwrap();
+    let block = parser::parse(&code).unwrap();
+    let value = parser::eval(Environment::new(None), &block).unwrap();
+    let sorted = value
+        .canonicalize(CanonicalizeOptions {
+            sort_keys: true,
+            sort_lists_by: None,
+        })
+        .value;
+    let json: serde_json::Value = value.decode().unwrap();
+
+    format!(
+        "{value}\n\n{sorted}\n\n{}\n",
+        serde_json::to_string_pretty(&json).unwrap()
+    )
+}
+
+#[test]
+fn outputs_match_the_snapshots() {
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let programs = programs();
+    assert!(!programs.is_empty(), "no programs were found");
+
+    for program in programs {
+        let snapshot = program.with_extension("out");
+        let output = output(&program);
+
+        if update {
+            std::fs::write(&snapshot, output).unwrap();
+            continue;
+        }
+
+        // Git may check out the snapshots with `\r\n` on Windows:
+        let expected = std::fs::read_to_string(&snapshot)
+            .unwrap_or_else(|err| panic!("cannot read {}: {err}", snapshot.display()))
+            .replace("\r\n", "\n");
+        assert_eq!(
+            output,
+            expected,
+            "the output of {} changed",
+            program.display()
+        );
+    }
+}
+
+#[test]
+fn evaluating_twice_gives_the_same_output() {
+    for program in programs() {
+        assert_eq!(output(&program), output(&program));
+    }
+}
+
+#[test]
+fn built_ins_are_listed_in_order() {
+    let env = Environment::new(None);
+    let names = env.builtins().map(|(id, _)| id).collect::<Vec<_>>();
+    let mut sorted = names.clone();
+    sorted.sort_unstable();
+
+    assert_eq!(names, sorted);
+}
@@ -0,0 +1,87 @@
+//! Printing a syntax tree with `Display` and parsing it again gives back the same tree.
+#![cfg(feature = "parse")]
+
+use std::path::Path;
+
+use ryan::parser;
+
+fn assert_round_trips(source: &str) -> String {
+    let block = parser::parse(source).unwrap();
+    let printed = block.to_string();
+    let reparsed = parser::parse(&printed)
+        .unwrap_or_else(|err| panic!("printed code does not parse:\n{printed}\n{err}"));
+
+    assert_eq!(reparsed, block, "printed code was:\n{printed}");
+
+    printed
+}
+
+/// Also checks that the printed code evaluates to the same value as the original.
+fn assert_evaluates_the_same(source: &str) -> String {
+    let printed = assert_round_trips(source);
+    let expected: serde_json::Value = ryan::from_str(source).unwrap();
+    let evaluated: serde_json::Value = ryan::from_str(&printed).unwrap();
+
+    assert_eq!(evaluated, expected, "printed code was:\n{printed}");
+
+    printed
+}
+
+#[test]
+fn round_trips_every_example() {
+    let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples");
+    let mut checked = 0;
+
+    for entry in std::fs::read_dir(examples).unwrap() {
+        let path = entry.unwrap().path();
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+
+        for document in ryan::documents(&text) {
+            if parser::parse(document).is_ok() {
+                assert_round_trips(document);
+                checked += 1;
+            }
+        }
+    }
+
+    assert!(checked > 0, "no examples were found");
+}
+
+#[test]
+fn keeps_negative_arguments_apart_from_subtraction() {
+    let printed =
+        assert_evaluates_the_same("let sign x = x; let one = 1; [sign (-1), (sign) [1], one - 1]");
+    assert!(printed.contains("sign (-1)"), "{printed}");
+}
+
+#[test]
+fn prints_blocks_without_a_final_expression() {
+    assert_round_trips("type Port = int; let x = 1;");
+    assert_evaluates_the_same("type Port = int; let x: Port = 1; x");
+}
+
+#[test]
+fn prints_optional_types_and_alternatives() {
+    let printed = assert_evaluates_the_same("type T = ?number | text; let x: T = 1; x");
+    assert!(printed.contains("?number | text"), "{printed}");
+}
+
+#[test]
+fn prints_text_with_escapes() {
+    assert_evaluates_the_same(
+        r#"["quote \" and backslash \\", "line\nbreak", "tab\t", "bell\u0007"]"#,
+    );
+}
+
+#[test]
+fn prints_dictionary_patterns_in_shorthand() {
+    let printed = assert_evaluates_the_same(
+        r#"let f { a, "b c": b, d: int, e: [x, y] ? [1, 2], .. } = a; f { a: 1, "b c": 2, d: 3 }"#,
+    );
+    assert!(
+        printed.contains(r#"{ a, "b c": b, d: int, e: [x, y] ? [1, 2], .. }"#),
+        "{printed}"
+    );
+}
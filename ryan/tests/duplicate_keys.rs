@@ -0,0 +1,77 @@
+//! What happens when a dictionary sets the same key twice, under each
+//! `DuplicateKeyPolicy`.
+#![cfg(feature = "parse")]
+
+use ryan::environment::{DuplicateKeyPolicy, Environment};
+
+fn eval(policy: DuplicateKeyPolicy, source: &str) -> Result<serde_json::Value, String> {
+    let env = Environment::builder().duplicate_keys(policy).build();
+    ryan::from_str_with_env(&env, source).map_err(|err| err.to_string())
+}
+
+#[test]
+fn last_wins_by_default() {
+    let value: serde_json::Value = ryan::from_str("{ a: 1, a: 2 }").unwrap();
+    assert_eq!(value, serde_json::json!({ "a": 2 }));
+
+    let value = eval(DuplicateKeyPolicy::LastWins, "{ a: 1, ...{ a: 2, b: 3 } }");
+    assert_eq!(value.unwrap(), serde_json::json!({ "a": 2, "b": 3 }));
+}
+
+#[test]
+fn first_wins_keeps_the_first_value() {
+    let value = eval(DuplicateKeyPolicy::FirstWins, "{ a: 1, a: 2 }");
+    assert_eq!(value.unwrap(), serde_json::json!({ "a": 1 }));
+
+    let value = eval(DuplicateKeyPolicy::FirstWins, "{ ...{ a: 2, b: 3 }, a: 1 }");
+    assert_eq!(value.unwrap(), serde_json::json!({ "a": 2, "b": 3 }));
+
+    let value = eval(
+        DuplicateKeyPolicy::FirstWins,
+        "{ k: v for [k, v] in [[\"a\", 1], [\"a\", 2]] }",
+    );
+    assert_eq!(value.unwrap(), serde_json::json!({ "a": 1 }));
+}
+
+#[test]
+fn errors_name_the_key_and_both_occurrences() {
+    let err = eval(DuplicateKeyPolicy::Error, "{ a: 1, a: 2 }").unwrap_err();
+    assert!(
+        err.contains(
+            "Key \"a\" defined twice in dictionary, first by a key-value pair and then by a \
+            key-value pair"
+        ),
+        "{err}"
+    );
+
+    let err = eval(DuplicateKeyPolicy::Error, "{ a: 1, ...{ a: 2 } }").unwrap_err();
+    assert!(
+        err.contains("first by a key-value pair and then by a flatten expression"),
+        "{err}"
+    );
+
+    let err = eval(DuplicateKeyPolicy::Error, "{ ...{ a: 2 }, a: 1 }").unwrap_err();
+    assert!(
+        err.contains("first by a flatten expression and then by a key-value pair"),
+        "{err}"
+    );
+
+    let err = eval(
+        DuplicateKeyPolicy::Error,
+        "{ k: 1 for k in [\"a\", \"a\"] }",
+    )
+    .unwrap_err();
+    assert!(
+        err.contains("first by a dictionary comprehension and then by a dictionary comprehension"),
+        "{err}"
+    );
+}
+
+#[test]
+fn distinct_keys_are_never_errors() {
+    let value = eval(DuplicateKeyPolicy::Error, "{ a: 1, ...{ b: 2 }, c: 3 }");
+    assert_eq!(
+        value.unwrap(),
+        serde_json::json!({ "a": 1, "b": 2, "c": 3 })
+    );
+}
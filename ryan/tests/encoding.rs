@@ -0,0 +1,96 @@
+#![cfg(feature = "parse")]
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Config {
+    name: String,
+    port: u16,
+}
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/encoding")
+        .join(name)
+}
+
+fn expected() -> Config {
+    Config {
+        name: "café".to_owned(),
+        port: 8080,
+    }
+}
+
+#[test]
+fn byte_order_marks_are_decoded() {
+    for name in ["utf16le.ryan", "utf16be.ryan", "bom.ryan"] {
+        let config: Config = ryan::from_path(fixture(name)).unwrap();
+        assert_eq!(config, expected(), "{name}");
+    }
+}
+
+#[test]
+fn invalid_utf8_is_located() {
+    let path = fixture("latin1.ryan");
+    let err = ryan::from_path::<_, Config>(&path).unwrap_err();
+
+    let ryan::Error::Encoding(err) = err else {
+        panic!("expected an encoding error, got {err}");
+    };
+    assert_eq!((err.offset, err.line, err.column), (16, 2, 15));
+    assert_eq!(err.path, Some(path.display().to_string()));
+    assert!(err.to_string().contains("Latin-1"), "{err}");
+}
+
+#[test]
+fn utf16_without_byte_order_mark_is_detected() {
+    let bytes = "{ name: \"café\" }".encode_utf16().collect::<Vec<_>>();
+    let bytes = bytes
+        .iter()
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect::<Vec<_>>();
+    let err = ryan::from_reader::<_, Config>(bytes.as_slice()).unwrap_err();
+
+    assert!(
+        err.to_string().contains("UTF-16LE without a byte order mark"),
+        "{err}"
+    );
+}
+
+#[test]
+fn broken_utf16_is_located() {
+    // A high surrogate followed by a line feed instead of a low surrogate:
+    let bytes = b"\xff\xfe1\x00\n\x00\x00\xd8\n\x00";
+    let err = ryan::decode_text(bytes).unwrap_err();
+
+    assert_eq!(err.encoding, "UTF-16LE");
+    assert_eq!((err.offset, err.line, err.column), (6, 2, 1));
+
+    let err = ryan::decode_text(b"\xfe\xff\x001\x00").unwrap_err();
+    assert_eq!((err.offset, err.line, err.column), (4, 1, 2));
+}
+
+#[test]
+fn imported_modules_are_decoded() {
+    #[derive(Debug, Deserialize)]
+    struct Main {
+        utf16: Config,
+        raw: String,
+    }
+
+    let main: Main = ryan::from_path(fixture("main.ryan")).unwrap();
+
+    assert_eq!(main.utf16, expected());
+    assert!(main.raw.starts_with('{'), "{:?}", main.raw);
+}
+
+#[test]
+fn invalid_imported_modules_are_named() {
+    let err = ryan::from_path::<_, String>(fixture("imports_latin1.ryan")).unwrap_err();
+    let message = err.to_string();
+
+    assert!(message.contains("Invalid UTF-8 at byte 16"), "{message}");
+    assert!(message.contains("latin1.ryan"), "{message}");
+}
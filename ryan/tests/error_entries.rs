@@ -0,0 +1,27 @@
+//! Parse errors keep every error found, with where in the code it is.
+#![cfg(feature = "parse")]
+
+use ryan::parser;
+
+#[test]
+fn entries_have_spans_and_positions() {
+    let code = "let x = 1;\nlet y = ;\nx";
+    let err = parser::parse(code).unwrap_err();
+    let entries = err.entries();
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].error, "Expected a code block.");
+    assert_eq!(entries[0].span, (19, 20));
+    assert_eq!(entries[0].position(code), (2, 9));
+}
+
+#[test]
+fn positions_count_characters() {
+    let code = "let x = 1;\nlet y = \"ü\" + ;\ny";
+    let err = parser::parse(code).unwrap_err();
+    let entries = err.entries();
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].span.0, 26);
+    assert_eq!(entries[0].position(code), (2, 15));
+}
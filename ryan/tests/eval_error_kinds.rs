@@ -0,0 +1,200 @@
+//! Every error raised by the interpreter itself has a kind of its own, so that callers can
+//! tell failures apart without looking at the messages.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+use ryan::environment::{DuplicateKeyPolicy, Environment};
+use ryan::parser::{EvalErrorKind, KeyOrigin, Type, Value};
+
+fn fail_with_env(env: &Environment, source: &str) -> EvalErrorKind {
+    match ryan::from_str_with_env::<serde_json::Value>(env, source) {
+        Err(ryan::Error::Eval(err)) => err.into_kind(),
+        other => panic!("expected an evaluation error from {source:?}, got {other:?}"),
+    }
+}
+
+fn fail(source: &str) -> EvalErrorKind {
+    fail_with_env(&Environment::new(None), source)
+}
+
+#[test]
+fn failed_casts() {
+    for (source, target) in [
+        (r#""abc" as int"#, Type::Integer),
+        (r#""abc" as float"#, Type::Float),
+        ("1e100 as int", Type::Integer),
+        ("(0.0 / 0.0) as int", Type::Integer),
+    ] {
+        let kind = fail(source);
+        assert_eq!(kind.name(), "cast_failed");
+        assert!(
+            matches!(&kind, EvalErrorKind::CastFailed { target: t, .. } if *t == target),
+            "{source}: {kind:?}"
+        );
+    }
+
+    assert_eq!(
+        fail(r#""abc" as int"#).to_string(),
+        r#"Cannot convert text "abc" to int"#
+    );
+    assert!(fail("1e100 as int")
+        .to_string()
+        .starts_with("Cannot convert float 1e100 to int, since it is out of the range of int"));
+}
+
+#[test]
+fn values_that_are_not_types() {
+    let kind = fail("let n = 1; let x: n = 2; x");
+    assert!(matches!(
+        &kind,
+        EvalErrorKind::NotAType {
+            value: Value::Integer(1)
+        }
+    ));
+    assert_eq!(kind.to_string(), "The value `1` is not a type");
+}
+
+#[test]
+fn values_that_are_not_iterable() {
+    let kind = fail("[x for x in 5]");
+    assert!(matches!(kind, EvalErrorKind::NotIterable(_)));
+    assert_eq!(kind.name(), "not_iterable");
+
+    assert!(matches!(
+        fail("{ [x]: 1 for x in true }"),
+        EvalErrorKind::NotIterable(_)
+    ));
+}
+
+#[test]
+fn values_that_are_not_pipe_targets() {
+    let kind = fail("[1, 2] |> 3");
+    assert!(matches!(
+        kind,
+        EvalErrorKind::NotAPipeTarget {
+            target: Value::Integer(3)
+        }
+    ));
+    assert_eq!(kind.name(), "not_a_pattern");
+}
+
+#[test]
+fn invalid_keys() {
+    let kind = fail("{ [1.5]: 1 }");
+    assert!(matches!(
+        kind,
+        EvalErrorKind::InvalidKey {
+            key: Value::Float(_)
+        }
+    ));
+    assert_eq!(kind.name(), "invalid_key");
+
+    let kind = fail("{ a: 1 } - [1]");
+    assert!(matches!(
+        kind,
+        EvalErrorKind::InvalidKeyToRemove {
+            key: Value::Integer(1)
+        }
+    ));
+    assert_eq!(kind.name(), "invalid_key");
+}
+
+#[test]
+fn duplicate_keys() {
+    let env = Environment::builder()
+        .duplicate_keys(DuplicateKeyPolicy::Error)
+        .build();
+    let kind = fail_with_env(&env, "{ a: 1, ...{ a: 2 } }");
+    assert!(
+        matches!(
+            &kind,
+            EvalErrorKind::DuplicateKey {
+                key,
+                first: KeyOrigin::Literal,
+                then: KeyOrigin::Flatten,
+            } if &**key == "a"
+        ),
+        "{kind:?}"
+    );
+    assert_eq!(kind.name(), "duplicate_key");
+}
+
+#[test]
+fn invalid_flatten_expressions() {
+    for source in ["[...1]", "{ ...1 }", "{ ...[[1, 2]] }", "{ ...[1] }"] {
+        assert_eq!(fail(source).name(), "invalid_flatten", "{source}");
+    }
+
+    assert!(matches!(
+        fail("[...1]"),
+        EvalErrorKind::InvalidListFlatten { .. }
+    ));
+    assert!(matches!(
+        fail("{ ...1 }"),
+        EvalErrorKind::InvalidDictFlatten { .. }
+    ));
+    assert_eq!(
+        fail("{ ...[[1, 2]] }").to_string(),
+        "First element of key-pair list must be text, got int"
+    );
+    assert_eq!(
+        fail("{ ...[1] }").to_string(),
+        "Key-pair list must be [text, any], got int"
+    );
+}
+
+#[test]
+fn repetitions() {
+    assert!(matches!(
+        fail(r#""ab" * 100000000"#),
+        EvalErrorKind::RepetitionTooLarge {
+            len: 2,
+            times: 100000000
+        }
+    ));
+
+    let kind = fail("{ a: 1 } * 2");
+    assert!(matches!(
+        kind,
+        EvalErrorKind::RepeatedDictionary { times: 2, .. }
+    ));
+    assert_eq!(kind.name(), "operator_misuse");
+}
+
+#[test]
+fn iterating_without_zip() {
+    let kind = fail("[a + b for [a, b] in [1, 2]]");
+    assert!(matches!(
+        kind,
+        EvalErrorKind::IterationMismatch { expected: 2, .. }
+    ));
+    assert_eq!(kind.name(), "pattern_mismatch");
+}
+
+#[test]
+fn unsupported_imports() {
+    let kind = fail(r#"import "env:HOME" as text with { a: 1 }"#);
+    assert!(matches!(
+        kind,
+        EvalErrorKind::ImportArgumentsUnsupported { .. }
+    ));
+    assert_eq!(kind.name(), "unsupported_import");
+
+    let kind = fail(r#"import "env:HOME" as text only ["a"]"#);
+    assert!(matches!(kind, EvalErrorKind::ImportKeysUnsupported { .. }));
+    assert_eq!(kind.name(), "unsupported_import");
+}
+
+#[test]
+fn unrepresentable_text_conversions() {
+    let kind = fail("let f x = x; [f] as text");
+    assert!(matches!(kind, EvalErrorKind::NotRepresentable(_)));
+    assert_eq!(kind.name(), "not_representable");
+}
+
+#[test]
+fn strict_programs_without_a_final_expression() {
+    let env = Environment::builder().strict(true).build();
+    let kind = fail_with_env(&env, "let x = 1;");
+    assert!(matches!(kind, EvalErrorKind::NoFinalExpression));
+    assert_eq!(kind.name(), "no_final_expression");
+}
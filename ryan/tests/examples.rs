@@ -0,0 +1,90 @@
+//! Every snippet in the `examples/*.ryans` files of the repository, separated by `---`
+//! lines, is evaluated and its value or error is checked against the `*.out` file with the
+//! same name in `tests/examples`. Environment variables are not available to the snippets,
+//! so that they give the same results everywhere. Set `UPDATE_SNAPSHOTS=1` to write the
+//! `*.out` files instead, and review the differences before committing them.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+use std::path::{Path, PathBuf};
+
+use ryan::environment::{DefaultImporter, Environment, ImportLoader};
+use ryan::parser;
+
+fn examples_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples")
+}
+
+fn examples() -> Vec<PathBuf> {
+    let mut examples = std::fs::read_dir(examples_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ryans"))
+        .collect::<Vec<_>>();
+    examples.sort();
+    examples
+}
+
+/// Evaluates one snippet as if it were a module in the `examples` directory.
+fn run(example: &Path, snippet: &str) -> String {
+    let env = Environment::builder()
+        .module(example.to_string_lossy())
+        .import_loader(DefaultImporter.filter(|path| !path.starts_with("env:")))
+        .build();
+    let result = parser::parse(snippet)
+        .map_err(|err| err.to_string())
+        .and_then(|block| parser::eval(env, &block).map_err(|err| err.to_string()));
+
+    match result {
+        Ok(value) => value.to_string(),
+        Err(err) => format!("error: {err}"),
+    }
+}
+
+/// The results of all snippets of an example, with the paths in error messages made
+/// relative to the `examples` directory.
+fn output(example: &Path) -> String {
+    let code = std::fs::read_to_string(example)
+        .unwrap()
+        .replace("\r\n", "\n");
+    let dir = examples_dir().to_string_lossy().into_owned();
+    let mut output = String::new();
+
+    for snippet in code.split("\n---\n") {
+        let result = run(example, snippet).replace(&dir, "<examples>");
+        output += &format!("{}\n=> {result}\n---\n", snippet.trim());
+    }
+
+    output
+}
+
+#[test]
+fn examples_match_the_snapshots() {
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let examples = examples();
+    assert!(!examples.is_empty(), "no examples were found");
+    let snapshots = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/examples");
+
+    for example in examples {
+        let snapshot = snapshots
+            .join(example.file_name().unwrap())
+            .with_extension("out");
+        let output = output(&example);
+
+        if update {
+            std::fs::create_dir_all(&snapshots).unwrap();
+            std::fs::write(&snapshot, output).unwrap();
+            continue;
+        }
+
+        // Git may check out the snapshots with `\r\n` on Windows:
+        let expected = std::fs::read_to_string(&snapshot)
+            .unwrap_or_else(|err| panic!("cannot read {}: {err}", snapshot.display()))
+            .replace("\r\n", "\n");
+        assert_eq!(
+            output,
+            expected,
+            "the results of {} changed",
+            example.display()
+        );
+    }
+}
@@ -0,0 +1,70 @@
+//! How floats are written: as the shortest text that reads back as the same float, always
+//! with a decimal point or an exponent, so that they never read back as integers.
+#![cfg(feature = "parse")]
+
+mod common;
+
+use ryan::parser::Value;
+
+use common::eval;
+
+fn float(source: &str) -> f64 {
+    match eval(source) {
+        Value::Float(float) => float,
+        value => panic!("expected a float, got {value}"),
+    }
+}
+
+#[test]
+fn display() {
+    assert_eq!(eval("0.1 + 0.2").to_string(), "0.30000000000000004");
+    assert_eq!(eval("1.0").to_string(), "1.0");
+    assert_eq!(eval("1e300").to_string(), "1e300");
+    assert_eq!(eval("-0.0").to_string(), "-0.0");
+    assert_eq!(eval("0.0000001").to_string(), "0.0000001");
+    assert_eq!(eval("1e-8").to_string(), "1e-8");
+    assert_eq!(eval("[1.0, { a: 2.0 }]").to_string(), "[1.0, {a: 2.0}]");
+}
+
+#[test]
+fn template_strings() {
+    assert_eq!(
+        eval("`${0.1 + 0.2}`").to_string(),
+        r#""0.30000000000000004""#
+    );
+    assert_eq!(eval("`${1.0}`").to_string(), r#""1.0""#);
+    assert_eq!(eval("`${[1.0, 1e300]}`").to_string(), r#""[1.0, 1e300]""#);
+    assert_eq!(eval("`${0.1 + 0.2:.1}`").to_string(), r#""0.3""#);
+    assert_eq!(eval("`${1.0:>5}`").to_string(), r#""  1.0""#);
+    assert_eq!(eval("1.0 as text").to_string(), r#""1.0""#);
+}
+
+#[test]
+fn json() {
+    assert_eq!(
+        eval("[1.0, -0.0, 1e300]").to_json().unwrap(),
+        "[1.0,-0.0,1e300]"
+    );
+}
+
+#[test]
+fn round_trip() {
+    for source in [
+        "0.1 + 0.2",
+        "1.0",
+        "1e300",
+        "-0.0",
+        "1e-8",
+        "5e-324",
+        "1e21",
+        "-2.5",
+    ] {
+        let value = float(source);
+        let written = Value::Float(value).to_string();
+        assert_eq!(
+            float(&written).to_bits(),
+            value.to_bits(),
+            "{source} was written as {written}"
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! The code formatter: its style, where it keeps comments and that formatting twice is
+//! the same as formatting once.
+#![cfg(feature = "parse")]
+
+use std::path::Path;
+
+use ryan::fmt::format;
+
+/// Formats some code, checking that formatting it again changes nothing and that it still
+/// evaluates to the same value.
+fn assert_formats(source: &str) -> String {
+    let formatted = format(source).unwrap();
+    assert_eq!(
+        format(&formatted).unwrap(),
+        formatted,
+        "formatting is not idempotent for:\n{source}"
+    );
+
+    let expected = ryan::from_str::<serde_json::Value>(source).ok();
+    let evaluated = ryan::from_str::<serde_json::Value>(&formatted).ok();
+    assert_eq!(evaluated, expected, "formatted code was:\n{formatted}");
+
+    formatted
+}
+
+#[test]
+fn comments_stay_next_to_list_items() {
+    let source = "let x = [\n  1, // one\n  // before two\n  2,\n  3 + // inside\n  4,\n  // closing\n];\nx\n";
+    assert_eq!(
+        assert_formats(source),
+        [
+            "let x = [",
+            "    1,  // one",
+            "    // before two",
+            "    2,",
+            "    // inside",
+            "    3 + 4,",
+            "    // closing",
+            "];",
+            "x",
+            "",
+        ]
+        .join("\n")
+    );
+}
+
+#[test]
+fn comments_stay_next_to_dictionary_items() {
+    let source = "{ a: 1, // the a\n  b: [ // first\n  5 ], c: { inner: 1 // deep\n } }";
+    assert_eq!(
+        assert_formats(source),
+        [
+            "{",
+            "    a: 1,  // the a",
+            "    b: [",
+            "        // first",
+            "        5,",
+            "    ],",
+            "    c: {",
+            "        inner: 1,  // deep",
+            "    },",
+            "}",
+            "",
+        ]
+        .join("\n")
+    );
+}
+
+#[test]
+fn comments_outside_collections_stay_at_the_top_level() {
+    assert_eq!(
+        assert_formats("// header\nlet x = 1 + // inside\n 2; // after\n\nx"),
+        "// header\n// inside\nlet x = 1 + 2;  // after\n\nx\n"
+    );
+}
+
+#[test]
+fn short_collections_without_comments_stay_in_one_line() {
+    assert_eq!(
+        assert_formats("let x = [ 1,2 ];\n{ a: x,\n b: [\n 3 ] }"),
+        "let x = [1, 2];\n{ a: x, b: [3] }\n"
+    );
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    for source in [
+        "[1, // a\n 2, // b\n]",
+        "[// only\n]",
+        "{ // only\n a: 1 }",
+        "[[1, // inner\n 2], 3]",
+        "{ a: [1, 2, 3] // after the list\n, b: 2 }",
+        "let f x = [x, // x\n x]; f 1",
+        "[x for x in [1, // one\n 2]]",
+        "`${[1, // in a template\n 2]}`",
+        "match 1 { 1 => [1, // one\n], _ => [] }",
+        "{ [\"computed\" // key\n]: 1, ...{ b: 2 }, // flattened\n }",
+        "let x = import \"env:NOPE\" as text or [1, // fallback\n 2];\nx",
+    ] {
+        assert_formats(source);
+    }
+}
+
+#[test]
+fn examples_format_idempotently() {
+    let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples");
+    let examples = std::fs::read_dir(examples)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ryans"));
+
+    for path in examples {
+        let text = std::fs::read_to_string(&path)
+            .unwrap()
+            .replace("\r\n", "\n");
+        for snippet in text.split("\n---\n") {
+            if let Ok(formatted) = format(snippet) {
+                assert_eq!(
+                    format(&formatted).unwrap(),
+                    formatted,
+                    "{}:\n{snippet}",
+                    path.display()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Passing arguments to imported modules with `import "x" with {…}`. The `with` is only a
+//! keyword right after an import, so it can still be used as a name elsewhere.
+#![cfg(feature = "parse")]
+
+use ryan::environment::{ImportLoader, NoImport};
+use ryan::Environment;
+
+fn eval(code: &str) -> Result<serde_json::Value, String> {
+    let loader = NoImport.r#override(
+        "scaled.ryan".to_owned(),
+        "{ replicas: args.factor * 2 }".to_owned(),
+    );
+    let env = Environment::builder().import_loader(loader).build();
+    ryan::from_str_with_env(&env, code).map_err(|err| err.to_string())
+}
+
+#[test]
+fn parses_the_arguments() {
+    let block = ryan::parser::parse(r#"import "scaled.ryan" with { factor: 2 }"#).unwrap();
+    assert_eq!(
+        block.to_string(),
+        r#"import "scaled.ryan" with { factor: 2 }"#
+    );
+}
+
+#[test]
+fn passes_the_arguments_to_the_module() {
+    assert_eq!(
+        eval(r#"import "scaled.ryan" with { factor: 2 }"#).unwrap(),
+        serde_json::json!({ "replicas": 4 })
+    );
+    assert_eq!(
+        eval(r#"let with = { factor: 3 }; import "scaled.ryan" with with"#).unwrap(),
+        serde_json::json!({ "replicas": 6 })
+    );
+}
+
+#[test]
+fn with_is_not_reserved() {
+    assert_eq!(
+        eval("let with = 1; { with: with + 1, within: 3 }").unwrap(),
+        serde_json::json!({ "with": 2, "within": 3 })
+    );
+    assert_eq!(
+        eval("let f { with } = with; f { with: 1 }").unwrap(),
+        serde_json::json!(1)
+    );
+}
+
+#[test]
+fn with_needs_to_be_a_whole_word() {
+    // This applies the import to `withargs`, so the module gets no arguments:
+    let err = eval(r#"let withargs = { factor: 2 }; import "scaled.ryan" withargs"#).unwrap_err();
+    assert!(err.starts_with("Key `factor` not present"), "{err}");
+}
@@ -0,0 +1,287 @@
+//! How imported modules are cached: by their resolved paths, never by the paths as
+//! written in the importing modules.
+#![cfg(feature = "parse")]
+
+mod common;
+
+use std::{cell::RefCell, collections::HashMap, error::Error, io::Read, rc::Rc};
+
+use ryan::environment::{Environment, EvalEvent, ImportEdge, ImportLoader};
+
+use common::eval_with;
+
+/// Serves modules from a map, resolving paths starting with `./` relative to the directory
+/// of the importing module, and records every path it loads. One resolved path can be
+/// kept out of the cache.
+#[derive(Debug, Clone, Default)]
+struct Tree {
+    modules: Rc<HashMap<String, String>>,
+    loads: Rc<RefCell<Vec<String>>>,
+    uncacheable: Option<&'static str>,
+}
+
+impl Tree {
+    fn new(modules: &[(&str, &str)]) -> Tree {
+        Tree {
+            modules: Rc::new(
+                modules
+                    .iter()
+                    .map(|(path, code)| (path.to_string(), code.to_string()))
+                    .collect(),
+            ),
+            loads: Rc::default(),
+            uncacheable: None,
+        }
+    }
+
+    fn refusing_to_cache(self, path: &'static str) -> Tree {
+        Tree {
+            uncacheable: Some(path),
+            ..self
+        }
+    }
+}
+
+impl ImportLoader for Tree {
+    fn resolve(&self, current: Option<&str>, path: &str) -> Result<String, Box<dyn Error>> {
+        match (current, path.strip_prefix("./")) {
+            (Some(current), Some(relative)) => {
+                let dir = current.rsplit_once('/').map_or("", |(dir, _)| dir);
+                Ok(format!("{dir}/{relative}"))
+            }
+            _ => Ok(path.to_owned()),
+        }
+    }
+
+    fn load(&self, path: &str) -> Result<Box<dyn Read>, Box<dyn Error>> {
+        self.loads.borrow_mut().push(path.to_owned());
+        let code = self.modules.get(path).cloned().ok_or("no such module")?;
+        Ok(Box::new(std::io::Cursor::new(code)))
+    }
+
+    fn cacheable(&self, path: &str) -> bool {
+        self.uncacheable != Some(path)
+    }
+}
+
+#[test]
+fn same_relative_path_from_different_directories() {
+    let tree = Tree::new(&[
+        ("a/main.ryan", r#"import "./data.ryan""#),
+        ("a/data.ryan", "1"),
+        ("b/main.ryan", r#"import "./data.ryan""#),
+        ("b/data.ryan", "2"),
+    ]);
+    let env = Environment::builder().import_loader(tree.clone()).build();
+
+    assert_eq!(
+        eval_with(&env, r#"[import "a/main.ryan", import "b/main.ryan"]"#),
+        serde_json::json!([1, 2])
+    );
+    assert_eq!(
+        *tree.loads.borrow(),
+        ["a/main.ryan", "a/data.ryan", "b/main.ryan", "b/data.ryan"]
+    );
+}
+
+#[test]
+fn same_resolved_path_is_loaded_once() {
+    let tree = Tree::new(&[
+        (
+            "a/main.ryan",
+            r#"[import "./data.ryan", import "a/data.ryan"]"#,
+        ),
+        ("a/data.ryan", "1"),
+    ]);
+    let env = Environment::builder().import_loader(tree.clone()).build();
+
+    assert_eq!(
+        eval_with(&env, r#"import "a/main.ryan""#),
+        serde_json::json!([1, 1])
+    );
+    assert_eq!(*tree.loads.borrow(), ["a/main.ryan", "a/data.ryan"]);
+}
+
+#[test]
+fn diamond_imports_list_only_modules_that_were_read() {
+    let tree = Tree::new(&[
+        (
+            "app/main.ryan",
+            r#"[import "./left.ryan", import "./right.ryan"]"#,
+        ),
+        ("app/left.ryan", r#"import "./shared.ryan""#),
+        ("app/right.ryan", r#"import "./shared.ryan""#),
+        ("app/shared.ryan", "1"),
+    ]);
+    let env = Environment::builder().import_loader(tree.clone()).build();
+
+    assert_eq!(
+        eval_with(&env, r#"import "app/main.ryan""#),
+        serde_json::json!([1, 1])
+    );
+
+    let edges = env
+        .import_graph()
+        .into_iter()
+        .map(
+            |ImportEdge {
+                 importer, imported, ..
+             }| {
+                (
+                    importer.map(|importer| importer.to_string()),
+                    imported.to_string(),
+                )
+            },
+        )
+        .collect::<Vec<_>>();
+    let edge =
+        |importer: Option<&str>, imported: &str| (importer.map(str::to_owned), imported.to_owned());
+    assert_eq!(
+        edges,
+        [
+            edge(None, "app/main.ryan"),
+            edge(Some("app/main.ryan"), "app/left.ryan"),
+            edge(Some("app/left.ryan"), "app/shared.ryan"),
+            edge(Some("app/main.ryan"), "app/right.ryan"),
+            edge(Some("app/right.ryan"), "app/shared.ryan"),
+        ]
+    );
+    assert_eq!(
+        *tree.loads.borrow(),
+        [
+            "app/main.ryan",
+            "app/left.ryan",
+            "app/shared.ryan",
+            "app/right.ryan"
+        ]
+    );
+}
+
+#[test]
+fn loaders_refuse_to_cache_resolved_paths() {
+    let tree = Tree::new(&[
+        (
+            "app/main.ryan",
+            r#"[import "./live.ryan", import "./live.ryan", import "./stable.ryan"]"#,
+        ),
+        ("app/live.ryan", "1"),
+        ("app/stable.ryan", "2"),
+    ])
+    .refusing_to_cache("app/live.ryan");
+    let env = Environment::builder().import_loader(tree.clone()).build();
+
+    assert_eq!(
+        eval_with(&env, r#"import "app/main.ryan""#),
+        serde_json::json!([1, 1, 2])
+    );
+    assert_eq!(
+        eval_with(&env, r#"import "app/stable.ryan""#),
+        serde_json::json!(2)
+    );
+    assert_eq!(
+        *tree.loads.borrow(),
+        [
+            "app/main.ryan",
+            "app/live.ryan",
+            "app/live.ryan",
+            "app/stable.ryan"
+        ]
+    );
+}
+
+#[test]
+fn cache_hits_report_resolved_paths() {
+    let tree = Tree::new(&[
+        (
+            "app/main.ryan",
+            r#"[import "./data.ryan", import "./data.ryan"]"#,
+        ),
+        ("app/data.ryan", "1"),
+    ]);
+    let events = Rc::new(RefCell::new(vec![]));
+    let recorded = events.clone();
+    let env = Environment::builder()
+        .import_loader(tree)
+        .on_event(move |event| {
+            if let EvalEvent::ImportLoaded {
+                path, cache_hit, ..
+            } = event
+            {
+                recorded.borrow_mut().push((path.to_string(), cache_hit));
+            }
+        })
+        .build();
+
+    eval_with(&env, r#"import "app/main.ryan""#);
+    assert_eq!(
+        *events.borrow(),
+        [
+            ("app/data.ryan".to_owned(), false),
+            ("app/data.ryan".to_owned(), true),
+            ("app/main.ryan".to_owned(), false),
+        ]
+    );
+}
+
+#[test]
+fn least_recently_used_modules_are_evicted() {
+    let tree = Tree::new(&[("a.ryan", "1"), ("b.ryan", "2"), ("c.ryan", "3")]);
+    let env = Environment::builder()
+        .import_loader(tree.clone())
+        .max_cached_modules(2)
+        .build();
+
+    let code =
+        r#"[import "a.ryan", import "b.ryan", import "a.ryan", import "c.ryan", import "a.ryan"]"#;
+    assert_eq!(eval_with(&env, code), serde_json::json!([1, 2, 1, 3, 1]));
+    assert_eq!(*tree.loads.borrow(), ["a.ryan", "b.ryan", "c.ryan"]);
+
+    // `b.ryan` was the least recently used when `c.ryan` came in:
+    assert_eq!(eval_with(&env, r#"import "b.ryan""#), serde_json::json!(2));
+    assert_eq!(
+        *tree.loads.borrow(),
+        ["a.ryan", "b.ryan", "c.ryan", "b.ryan"]
+    );
+}
+
+#[test]
+fn eviction_does_not_hide_circular_imports() {
+    let tree = Tree::new(&[
+        ("a.ryan", "1"),
+        ("b.ryan", "2"),
+        (
+            "x.ryan",
+            r#"[import "a.ryan", import "b.ryan", import "y.ryan"]"#,
+        ),
+        (
+            "y.ryan",
+            r#"[import "a.ryan", import "b.ryan", import "x.ryan"]"#,
+        ),
+    ]);
+    let env = Environment::builder()
+        .import_loader(tree)
+        .max_cached_modules(1)
+        .build();
+
+    let err = ryan::from_str_with_env::<serde_json::Value>(&env, r#"import "x.ryan""#)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("Circular import detected: x.ryan -> y.ryan -> x.ryan"),
+        "{err}"
+    );
+}
+
+#[test]
+fn clearing_the_cache_reads_modules_again() {
+    let tree = Tree::new(&[("a.ryan", "1")]);
+    let env = Environment::builder().import_loader(tree.clone()).build();
+
+    eval_with(&env, r#"import "a.ryan""#);
+    eval_with(&env, r#"import "a.ryan""#);
+    assert_eq!(*tree.loads.borrow(), ["a.ryan"]);
+
+    env.clear_import_cache();
+    eval_with(&env, r#"import "a.ryan""#);
+    assert_eq!(*tree.loads.borrow(), ["a.ryan", "a.ryan"]);
+}
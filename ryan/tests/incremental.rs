@@ -0,0 +1,167 @@
+//! Incremental evaluation of programs with `Program::eval_incremental`.
+#![cfg(feature = "parse")]
+
+use std::{cell::RefCell, collections::HashMap, error::Error, io::Read, rc::Rc};
+
+use ryan::environment::{Environment, ImportLoader, IncrementalStats, NativePatternMatch};
+use ryan::parser::{Pattern, Program, Value};
+
+/// A loader reading modules from a map that can be changed between evaluations.
+#[derive(Debug, Clone, Default)]
+struct Modules(Rc<RefCell<HashMap<String, String>>>);
+
+impl Modules {
+    fn set(&self, path: &str, content: &str) {
+        self.0
+            .borrow_mut()
+            .insert(path.to_owned(), content.to_owned());
+    }
+}
+
+impl ImportLoader for Modules {
+    fn resolve(&self, _current: Option<&str>, path: &str) -> Result<String, Box<dyn Error>> {
+        Ok(path.to_owned())
+    }
+
+    fn load(&self, path: &str) -> Result<Box<dyn Read>, Box<dyn Error>> {
+        match self.0.borrow().get(path) {
+            Some(content) => Ok(Box::new(std::io::Cursor::new(content.clone()))),
+            None => Err(format!("module {path:?} not found").into()),
+        }
+    }
+}
+
+const MAIN: &str = r#"
+    let base = import "base.ryan";
+    let service = import "service.ryan";
+    let region = import "region.ryan";
+    { base, service, region }
+"#;
+
+fn setup() -> (Modules, Environment) {
+    let modules = Modules::default();
+    modules.set("base.ryan", "{ replicas: 3 }");
+    modules.set("service.ryan", r#"(import "base.ryan") + { name: "api" }"#);
+    modules.set(
+        "region.ryan",
+        r#"{ region: import "env:REGION" as text or "us" }"#,
+    );
+    modules.set("env:REGION", "eu");
+    let env = Environment::builder()
+        .import_loader(modules.clone())
+        .build();
+
+    (modules, env)
+}
+
+fn eval(program: &mut Program, env: &Environment) -> serde_json::Value {
+    program.eval_incremental(env).unwrap().decode().unwrap()
+}
+
+#[test]
+fn unchanged_modules_are_reused() {
+    let (modules, env) = setup();
+    let mut program = Program::parse(MAIN).unwrap();
+
+    let first = eval(&mut program, &env);
+    assert_eq!(first["region"]["region"], "eu");
+    assert_eq!(
+        program.incremental_stats(),
+        IncrementalStats {
+            reused: 1,
+            recomputed: 4
+        }
+    );
+
+    modules.set("env:REGION", "sa");
+    let second = eval(&mut program, &env);
+    assert_eq!(second["region"]["region"], "sa");
+    assert_eq!(second["service"], first["service"]);
+    // `base.ryan` and `service.ryan` are reused, while `region.ryan` and the variable
+    // it reads are evaluated again:
+    assert_eq!(
+        program.incremental_stats(),
+        IncrementalStats {
+            reused: 2,
+            recomputed: 2
+        }
+    );
+}
+
+#[test]
+fn changes_are_propagated_to_importers() {
+    let (modules, env) = setup();
+    let mut program = Program::parse(MAIN).unwrap();
+    eval(&mut program, &env);
+
+    modules.set("base.ryan", "{ replicas: 5 }");
+    let value = eval(&mut program, &env);
+    assert_eq!(value["base"]["replicas"], 5);
+    assert_eq!(value["service"]["replicas"], 5);
+    // `service.ryan` is evaluated again, but gets the new `base.ryan` already evaluated:
+    assert_eq!(
+        program.incremental_stats(),
+        IncrementalStats {
+            reused: 2,
+            recomputed: 2
+        }
+    );
+}
+
+#[test]
+fn failed_imports_are_inputs_too() {
+    let (modules, env) = setup();
+    modules.0.borrow_mut().remove("env:REGION");
+    let mut program = Program::parse(MAIN).unwrap();
+    assert_eq!(eval(&mut program, &env)["region"]["region"], "us");
+
+    modules.set("env:REGION", "ap");
+    assert_eq!(eval(&mut program, &env)["region"]["region"], "ap");
+}
+
+#[test]
+fn parameterized_imports_are_never_reused() {
+    let (modules, env) = setup();
+    modules.set("scaled.ryan", "{ replicas: args.factor * 2 }");
+    let mut program = Program::parse(r#"import "scaled.ryan" with { factor: 2 }"#).unwrap();
+
+    for _ in 0..2 {
+        assert_eq!(eval(&mut program, &env)["replicas"], 4);
+        assert_eq!(program.incremental_stats().reused, 0);
+    }
+}
+
+#[test]
+fn modules_calling_effectful_patterns_are_never_reused() {
+    let (modules, _) = setup();
+    let ticks = Rc::new(RefCell::new(0));
+    let tick = NativePatternMatch::new("tick", Pattern::Identifier("_".into(), None), move |_| {
+        *ticks.borrow_mut() += 1;
+        Ok::<_, std::convert::Infallible>(Value::Integer(*ticks.borrow()))
+    })
+    .effectful(true);
+    let mut built_ins = ryan::environment::BUILT_INS.with(|built_ins| (**built_ins).clone());
+    built_ins.insert("tick".into(), Value::NativePatternMatch(Rc::new(tick)));
+    let env = Environment::builder()
+        .import_loader(modules.clone())
+        .built_ins(Rc::new(built_ins))
+        .build();
+
+    modules.set("clock.ryan", "tick null");
+    modules.set("wrapper.ryan", r#"{ now: import "clock.ryan" }"#);
+    let mut program = Program::parse(r#"import "wrapper.ryan""#).unwrap();
+
+    assert_eq!(eval(&mut program, &env)["now"], 1);
+    assert_eq!(eval(&mut program, &env)["now"], 2);
+    assert_eq!(program.incremental_stats().reused, 0);
+}
+
+#[test]
+fn plain_evaluation_is_not_incremental() {
+    let (_, env) = setup();
+    let program = Program::parse(MAIN).unwrap();
+
+    let value: serde_json::Value = program.eval(&env).unwrap().decode().unwrap();
+    assert_eq!(value["service"]["replicas"], 3);
+    assert_eq!(program.incremental_stats(), IncrementalStats::default());
+}
@@ -0,0 +1,55 @@
+//! Integers used as computed keys or as keys of dictionary comprehensions are turned
+//! into their decimal text. Any other value must be turned into text explicitly.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+mod common;
+
+use ryan::environment::{DuplicateKeyPolicy, Environment};
+
+use common::try_eval_json;
+
+#[test]
+fn integer_keys_are_decimal_text() {
+    let value = try_eval_json("{ i: i * i for i in [1, -2, 10] }").unwrap();
+    assert_eq!(value, serde_json::json!({ "1": 1, "-2": 4, "10": 100 }));
+
+    let value = try_eval_json("let port = 8080; { [port]: \"http\" }").unwrap();
+    assert_eq!(value, serde_json::json!({ "8080": "http" }));
+}
+
+#[test]
+fn integer_keys_agree_with_fmt() {
+    let implicit = try_eval_json("{ i: true for i in range [-3, 30] }").unwrap();
+    let explicit = try_eval_json("{ fmt i: true for i in range [-3, 30] }").unwrap();
+
+    assert_eq!(implicit, explicit);
+}
+
+#[test]
+fn other_keys_must_be_turned_into_text_explicitly() {
+    let err = try_eval_json("{ x: x for x in [1.5] }").unwrap_err();
+    assert!(
+        err.contains("Dictionary keys must be text or integers, got float 1.5"),
+        "{err}"
+    );
+    assert!(err.contains("`fmt` or `as text`"), "{err}");
+
+    let err = try_eval_json("{ [true]: 1 }").unwrap_err();
+    assert!(err.contains("got bool true"), "{err}");
+
+    let value = try_eval_json("{ fmt x: x for x in [1.5] }").unwrap();
+    assert_eq!(value, serde_json::json!({ "1.5": 1.5 }));
+}
+
+#[test]
+fn integer_keys_collide_with_text_keys() {
+    let value = try_eval_json(r#"{ "1": "text", ...{ i: "int" for i in [1] } }"#).unwrap();
+    assert_eq!(value, serde_json::json!({ "1": "int" }));
+
+    let env = Environment::builder()
+        .duplicate_keys(DuplicateKeyPolicy::Error)
+        .build();
+    let err = ryan::from_str_with_env::<serde_json::Value>(&env, r#"{ "1": "text", [1]: "int" }"#)
+        .unwrap_err();
+    assert!(err.to_string().contains("\"1\""), "{err}");
+}
@@ -0,0 +1,77 @@
+//! Testing values against types with `is`, and with the deprecated `#`.
+#![cfg(feature = "parse")]
+
+mod common;
+
+use ryan::parser::WarningKind;
+
+use common::eval_json;
+
+#[test]
+fn primitive_types() {
+    assert_eq!(
+        eval_json(r#"[1 is int, 1 is float, 1.5 is int, "a" is text, null is ?int, 1 is any]"#),
+        serde_json::json!([true, true, false, true, true, true])
+    );
+    assert_eq!(
+        eval_json(r#"["a" is int | text, [1, 2] is [int], [1, "a"] is (int, text)]"#),
+        serde_json::json!([true, true, true])
+    );
+}
+
+#[test]
+fn record_types() {
+    assert_eq!(
+        eval_json(
+            r#"
+            let v = { name: "a", port: 80 };
+            [v is { name: text, .. }, v is { name: int, .. }, v is { missing: text, .. }]
+            "#
+        ),
+        serde_json::json!([true, false, false])
+    );
+}
+
+#[test]
+fn types_bound_to_names() {
+    assert_eq!(
+        eval_json("type Port = int; let check x = x is [Port]; [1 is Port, check [1, 2]]"),
+        serde_json::json!([true, true])
+    );
+}
+
+#[test]
+fn binds_like_a_comparison() {
+    assert_eq!(
+        eval_json(
+            "let x = [1]; [x is [int] and not (x is text), 1 + 1 is int, [1] is [int] == true]"
+        ),
+        serde_json::json!([true, true, true])
+    );
+}
+
+#[test]
+fn is_can_still_be_a_name() {
+    assert_eq!(
+        eval_json("let is = 3; { is: is + 1 }"),
+        serde_json::json!({ "is": 4 })
+    );
+}
+
+#[test]
+fn only_the_hash_is_deprecated() {
+    let deprecated = |source| {
+        ryan::lint(source)
+            .unwrap()
+            .into_iter()
+            .filter(|warning| warning.kind == WarningKind::DeprecatedSyntax)
+            .count()
+    };
+
+    assert_eq!(deprecated("1 is int"), 0);
+    assert_eq!(deprecated("1 # int"), 1);
+    assert_eq!(
+        eval_json("[1 # int, 1 # text]"),
+        serde_json::json!([true, false])
+    );
+}
@@ -0,0 +1,71 @@
+//! Juxtaposition applies pattern matches, e.g., `f x`, and indexes a dictionary or a list
+//! by a list of keys and indices, e.g., `x path`. Anything else is most likely a missing
+//! comma and says so.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+mod common;
+
+use ryan::parser::{EvalError, EvalErrorKind, Expression, PostfixOperator};
+
+use common::eval;
+
+fn eval_error(code: &str) -> EvalError {
+    let block = ryan::parser::parse(code).unwrap();
+    ryan::parser::eval(ryan::Environment::new(None), &block).unwrap_err()
+}
+
+#[test]
+fn brackets_after_a_space_are_still_an_access() {
+    for code in ["x[0]", "x [0]"] {
+        let block = ryan::parser::parse(code).unwrap();
+        let Expression::PostfixOperation(operation) = &block.expression else {
+            panic!("{code} should be an access, got {}", block.expression);
+        };
+        assert!(matches!(operation.op, PostfixOperator::Path(_)), "{code}");
+    }
+
+    assert_eq!(eval("let x = [1, 2]; [x[0], x [0]]").to_string(), "[1, 1]");
+    assert_eq!(eval("let x = [[1, 2]]; [x [0]]").to_string(), "[[1, 2]]");
+}
+
+#[test]
+fn collections_are_indexed_by_juxtaposed_lists() {
+    assert_eq!(
+        eval(r#"let path = ["a", 1]; let x = { a: [1, 2] }; x path"#).to_string(),
+        "2"
+    );
+    assert_eq!(
+        eval("let x = [[1, 2]]; x [i for i in [0, 1]]").to_string(),
+        "2"
+    );
+}
+
+#[test]
+fn applying_something_else_hints_at_a_missing_comma() {
+    for (code, callee, arg) in [
+        ("5 { a: 1 }", "5", "{a: 1}"),
+        (
+            r#"let name = "a"; let value = 1; [name value]"#,
+            r#""a""#,
+            "1",
+        ),
+        ("let path = [0]; [1 path]", "1", "[0]"),
+        (r#"null "a""#, "null", r#""a""#),
+    ] {
+        let err = eval_error(code);
+
+        assert!(
+            matches!(err.kind(), EvalErrorKind::NotAPattern { .. }),
+            "{code}: {err}"
+        );
+        assert_eq!(
+            err.kind().to_string(),
+            format!(
+                "`{callee}` is not a pattern and cannot be applied to `{arg}`; if you meant \
+                two separate items, check for a missing comma"
+            ),
+            "{code}"
+        );
+        assert_eq!(err.kind().name(), "not_a_pattern");
+    }
+}
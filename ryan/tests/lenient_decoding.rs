@@ -0,0 +1,86 @@
+//! Decoding floats with no fractional part into integers with `Value::decode_lenient`.
+use std::{collections::HashMap, rc::Rc};
+
+use ryan::parser::Value;
+
+fn lenient_error(float: f64) -> String {
+    Value::Float(float)
+        .decode_lenient::<u32>()
+        .unwrap_err()
+        .to_string()
+}
+
+#[test]
+fn decodes_whole_floats_into_integers() {
+    assert_eq!(Value::Float(4.0).decode_lenient::<u32>().unwrap(), 4);
+    assert_eq!(Value::Float(-4.0).decode_lenient::<i8>().unwrap(), -4);
+    assert_eq!(Value::Integer(4).decode_lenient::<u32>().unwrap(), 4);
+}
+
+#[test]
+fn strict_decoding_is_unchanged() {
+    assert_eq!(
+        Value::Float(4.0).decode::<u32>().unwrap_err().to_string(),
+        "expected a 32-bit positive integer but got value of type float"
+    );
+}
+
+#[test]
+fn rejects_floats_with_a_fractional_part() {
+    assert_eq!(
+        lenient_error(4.5),
+        "expected a 32-bit positive integer but got the number 4.5, which is not a whole number"
+    );
+}
+
+#[test]
+fn rejects_floats_out_of_range() {
+    assert_eq!(
+        lenient_error(1e20),
+        "cannot fit the number 100000000000000000000 into a 32-bit positive integer"
+    );
+    assert_eq!(
+        lenient_error(-1.0),
+        "cannot fit the number -1 into a 32-bit positive integer"
+    );
+    assert_eq!(
+        lenient_error(4294967296.0),
+        "cannot fit the number 4294967296 into a 32-bit positive integer"
+    );
+    assert_eq!(
+        Value::Float(4294967295.0).decode_lenient::<u32>().unwrap(),
+        u32::MAX
+    );
+    assert!(Value::Float(9223372036854775808.0)
+        .decode_lenient::<i64>()
+        .is_err());
+}
+
+#[test]
+fn rejects_floats_that_are_not_numbers() {
+    assert_eq!(
+        lenient_error(f64::NAN),
+        "expected a 32-bit positive integer but got the number NaN, which is not a whole number"
+    );
+    assert_eq!(
+        lenient_error(f64::INFINITY),
+        "expected a 32-bit positive integer but got the number inf, which is not a whole number"
+    );
+}
+
+#[test]
+fn applies_to_nested_values() {
+    let value = Value::List(
+        vec![
+            Value::Float(2.0),
+            Value::Map(Rc::new(
+                [("parts".into(), Value::Float(8.0))].into_iter().collect(),
+            )),
+        ]
+        .into(),
+    );
+
+    let (total, parts): (Option<u16>, HashMap<String, u64>) = value.decode_lenient().unwrap();
+    assert_eq!(total, Some(2));
+    assert_eq!(parts["parts"], 8);
+}
@@ -0,0 +1,86 @@
+//! The keys of a Ryan dictionary are always text, but they can be decoded into maps keyed
+//! by other types, e.g., integers, which are then parsed from the text.
+#![cfg(feature = "parse")]
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Deserialize;
+
+#[test]
+fn integer_keys_are_parsed() {
+    let map: HashMap<u16, String> = ryan::from_str(r#"{ "80": "http", "443": "https" }"#).unwrap();
+
+    assert_eq!(
+        map,
+        HashMap::from([(80, "http".to_owned()), (443, "https".to_owned())])
+    );
+}
+
+#[test]
+fn char_keys_are_parsed() {
+    let map: BTreeMap<char, i32> = ryan::from_str(r#"{ a: 1, "é": -2 }"#).unwrap();
+    assert_eq!(map, BTreeMap::from([('a', 1), ('é', -2)]));
+
+    let err = ryan::from_str::<BTreeMap<char, i32>>("{ ab: 1 }").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        r#"Decode error: expected a single character as the key of a map but got the key "ab""#
+    );
+}
+
+#[test]
+fn enum_and_newtype_keys_are_decoded() {
+    #[derive(Debug, PartialEq, Eq, Hash, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Env {
+        Dev,
+        Prod,
+    }
+
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+    struct Port(u16);
+
+    let map: HashMap<Env, Port> = ryan::from_str("{ dev: 8080, prod: 80 }").unwrap();
+    assert_eq!(
+        map,
+        HashMap::from([(Env::Dev, Port(8080)), (Env::Prod, Port(80))])
+    );
+
+    let map: BTreeMap<Port, bool> = ryan::from_str("{ i: true for i in [80, 443] }").unwrap();
+    assert_eq!(map, BTreeMap::from([(Port(80), true), (Port(443), true)]));
+
+    assert!(ryan::from_str::<HashMap<Env, u16>>("{ staging: 1 }").is_err());
+}
+
+#[test]
+fn keys_that_do_not_parse_are_errors() {
+    let err = ryan::from_str::<HashMap<u16, String>>(r#"{ "80": "http", www: "?" }"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        r#"Decode error: expected a 16-bit positive integer as the key of a map but got the key "www""#
+    );
+
+    let err = ryan::from_str::<HashMap<u8, bool>>("{ i: true for i in [256] }").unwrap_err();
+    assert!(err.to_string().contains(r#"the key "256""#), "{err}");
+}
+
+#[test]
+fn text_keys_and_structs_are_unchanged() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    let server: Server = ryan::from_str(r#"{ host: "localhost", port: 80 }"#).unwrap();
+    assert_eq!(
+        server,
+        Server {
+            host: "localhost".to_owned(),
+            port: 80
+        }
+    );
+
+    let map: HashMap<String, u16> = ryan::from_str(r#"{ "80": 1 }"#).unwrap();
+    assert_eq!(map["80"], 1);
+}
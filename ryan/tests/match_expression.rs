@@ -0,0 +1,104 @@
+//! The `match` expression, which evaluates the first arm whose pattern matches a value.
+#![cfg(feature = "parse")]
+
+mod common;
+
+use common::eval_json;
+
+#[test]
+fn picks_the_first_matching_literal_arm() {
+    let source = r#"
+        let replicas env = match env { "prod" => 32, "staging" => 8, _ => 2 };
+        [replicas "prod", replicas "staging", replicas "dev"]
+    "#;
+
+    assert_eq!(eval_json(source), serde_json::json!([32, 8, 2]));
+}
+
+#[test]
+fn binds_destructured_values_in_the_arm_only() {
+    let source = r#"
+        let x = "outer";
+        let sum p = match p {
+            [x, y] => x + y,
+            { x, y, .. } => x * y,
+            _ => x,
+        };
+        [sum [1, 2], sum { x: 3, y: 4, z: 5 }, sum null]
+    "#;
+
+    assert_eq!(eval_json(source), serde_json::json!([3, 12, "outer"]));
+}
+
+#[test]
+fn falls_through_arms_whose_guard_is_false() {
+    let source = r#"
+        let classify n = match n {
+            x: int if x < 0 => "negative",
+            0 => "zero",
+            x: int if x % 2 == 0 => "even",
+            x: int => "odd",
+        };
+        [classify (-3), classify 0, classify 4, classify 7]
+    "#;
+
+    assert_eq!(
+        eval_json(source),
+        serde_json::json!(["negative", "zero", "even", "odd"])
+    );
+}
+
+#[test]
+fn captures_variables_from_the_enclosing_scope() {
+    let source = r#"
+        let limit = 10;
+        let clamp = \n => match n { x if x > limit => limit, x => x };
+        [clamp 3, clamp 30]
+    "#;
+
+    assert_eq!(eval_json(source), serde_json::json!([3, 10]));
+}
+
+#[test]
+fn lists_the_tried_patterns_when_no_arm_matches() {
+    let Err(ryan::Error::Eval(err)) =
+        ryan::from_str::<i64>(r#"match "dev" { "prod" => 32, "staging" => 8 }"#)
+    else {
+        panic!("no arm matches `\"dev\"`");
+    };
+
+    assert_eq!(
+        err.kind().to_string(),
+        r#"No arm of `match` matches "dev", tried: `"prod"`, `"staging"`"#
+    );
+}
+
+#[test]
+fn still_allows_variables_named_match() {
+    assert_eq!(eval_json("let match = 1; match + 1"), serde_json::json!(2));
+}
+
+#[test]
+fn formats_back_to_the_same_code() {
+    for source in [
+        "match env { \"prod\" => 32, \"staging\" => 8, _ => 2 }\n",
+        "match [1, 2] { [x, y] if x > y => x, [_, y] => y }\n",
+        "match x { _ => f } 1\n",
+        concat!(
+            "match [environment, replicas] {\n",
+            "    [\"production\", n] if n > 0 => n * 10,\n",
+            "    [_, n] => n + minimum_replicas,\n",
+            "}\n",
+        ),
+    ] {
+        assert_eq!(ryan::fmt::format(source).unwrap(), source);
+    }
+}
+
+#[test]
+fn displays_as_code_that_parses_to_the_same_tree() {
+    let source = r#"match [1, 2] { [x, y] if x > y => x, "a" | "b" => 0, _ => -1 }"#;
+    let block = ryan::parser::parse(source).unwrap();
+
+    assert_eq!(ryan::parser::parse(&block.to_string()).unwrap(), block);
+}
@@ -0,0 +1,48 @@
+//! Membership tests with `in` and `not in`, on lists, dictionaries and text.
+#![cfg(feature = "parse")]
+
+mod common;
+
+use common::eval_json;
+
+#[test]
+fn not_in_negates_in() {
+    assert_eq!(
+        eval_json(r#"[1 in [1, 2], 3 not in [1, 2], "a" in { a: 1 }, "b" not in { a: 1 }]"#),
+        serde_json::json!([true, true, true, true])
+    );
+    assert_eq!(
+        eval_json(r#"["bc" in "abc", "d" not in "abc", "" in "abc"]"#),
+        serde_json::json!([true, true, true])
+    );
+}
+
+#[test]
+fn integers_are_never_keys_of_a_dictionary() {
+    assert_eq!(
+        eval_json("[1 in { a: 1 }, 1 not in { a: 1 }]"),
+        serde_json::json!([false, true])
+    );
+}
+
+#[test]
+fn not_in_binds_like_in() {
+    assert_eq!(
+        eval_json("[1 + 1 not in [2], not 1 in [1], 1 not in [1] == false, 1 not   in [2]]"),
+        serde_json::json!([false, false, true, true])
+    );
+}
+
+#[test]
+fn names_starting_with_keywords_are_not_operators() {
+    assert_eq!(
+        eval_json(
+            "let f x = x; let index = 1; let order = 2; let andy = 3; [f index, f order, f andy]"
+        ),
+        serde_json::json!([1, 2, 3])
+    );
+    assert_eq!(
+        eval_json("let notin = [1]; let nota = 2; [2 not in notin, nota in notin]"),
+        serde_json::json!([true, false])
+    );
+}
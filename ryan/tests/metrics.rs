@@ -0,0 +1,48 @@
+#![cfg(all(feature = "parse", feature = "metrics"))]
+
+use std::time::Duration;
+
+use ryan::environment::{Environment, ImportLoader, NoImport};
+
+fn environment() -> Environment {
+    let loader = NoImport
+        .r#override(
+            "main.ryan".to_owned(),
+            r#"import "lib.ryan" + 1"#.to_owned(),
+        )
+        .r#override("lib.ryan".to_owned(), "let x = 2; x * 3".to_owned());
+    Environment::builder().import_loader(loader).build()
+}
+
+#[test]
+fn counts_nested_imports() {
+    let env = environment();
+    let code = r#"[import "main.ryan", import "lib.ryan"]"#;
+    let (value, metrics) = ryan::from_str_with_env_metrics::<Vec<i64>>(&env, code);
+
+    assert_eq!(value.unwrap(), [7, 6]);
+    assert_eq!(metrics.imports, 3);
+    assert_eq!(metrics.cache_hits, 1);
+    assert!(metrics.parse_time > Duration::ZERO);
+    assert!(metrics.eval_time > Duration::ZERO);
+    assert!(metrics.interned_strings > 0);
+    // At least the list, its two elements and the expressions in both modules:
+    assert!(metrics.values >= 3 + 3 + 3);
+
+    // The environment keeps the total of all evaluations:
+    let (_, again) = ryan::from_str_with_env_metrics::<Vec<i64>>(&env, code);
+    assert_eq!((again.imports, again.cache_hits), (2, 2));
+    assert_eq!(env.metrics().imports, metrics.imports + again.imports);
+    assert_eq!(env.metrics().values, metrics.values + again.values);
+}
+
+#[test]
+fn reports_metrics_of_failed_evaluations() {
+    let env = environment();
+    let (value, metrics) =
+        ryan::from_str_with_env_metrics::<i64>(&env, r#"import "lib.ryan" + missing"#);
+
+    assert!(value.is_err());
+    assert_eq!(metrics.imports, 1);
+    assert!(metrics.parse_time > Duration::ZERO);
+}
@@ -0,0 +1,107 @@
+//! Warning about and requiring environment variables whose `env:` imports have defaults.
+#![cfg(feature = "parse")]
+
+use std::{cell::RefCell, rc::Rc};
+
+use ryan::environment::{DefaultImporter, EnvironmentBuilder, EvalEvent, ImportLoader};
+
+/// Evaluates a program, returning its value and the warnings about missing variables.
+fn eval(builder: EnvironmentBuilder, source: &str) -> (serde_json::Value, Vec<String>) {
+    let warnings = Rc::new(RefCell::new(vec![]));
+    let recorded = warnings.clone();
+    let env = builder
+        .on_event(move |event| {
+            if let EvalEvent::Warning { message } = event {
+                if message.starts_with("Environment variable") {
+                    recorded.borrow_mut().push(message.to_string());
+                }
+            }
+        })
+        .build();
+
+    let value = ryan::from_str_with_env(&env, source).unwrap();
+    let warnings = warnings.borrow().clone();
+    (value, warnings)
+}
+
+#[test]
+fn does_not_warn_unless_asked() {
+    let (value, warnings) = eval(
+        ryan::Environment::builder(),
+        r#"import "env:__RYAN_MISSING_ENV_UNSET__" as text or "default""#,
+    );
+
+    assert_eq!(value, "default");
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn warns_once_per_variable_and_module() {
+    let (value, warnings) = eval(
+        ryan::Environment::builder().warn_missing_env(true),
+        r#"[
+            import "env:__RYAN_MISSING_ENV_UNSET__" as text or "a",
+            import "env:__RYAN_MISSING_ENV_UNSET__" as text or "b",
+            import "env:PATH" as text or "c",
+        ]"#,
+    );
+
+    assert_eq!(value[0], "a");
+    assert_eq!(value[1], "b");
+    assert_eq!(
+        warnings,
+        [
+            "Environment variable __RYAN_MISSING_ENV_UNSET__ is not set, so <main> used the \
+            default of its import. Is the name right?"
+        ]
+    );
+}
+
+#[test]
+fn names_the_module_that_imported_the_variable() {
+    let loader = DefaultImporter.r#override(
+        "settings.ryan".to_string(),
+        r#"{ port: import "env:__RYAN_MISSING_ENV_PORT__" or 8080 }"#.to_string(),
+    );
+    let (value, warnings) = eval(
+        ryan::Environment::builder()
+            .import_loader(loader)
+            .warn_missing_env(true),
+        r#"(import "settings.ryan").port"#,
+    );
+
+    assert_eq!(value, 8080);
+    assert_eq!(
+        warnings,
+        [
+            "Environment variable __RYAN_MISSING_ENV_PORT__ is not set, so settings.ryan used \
+            the default of its import. Is the name right?"
+        ]
+    );
+}
+
+#[test]
+fn required_variables_fail_despite_defaults() {
+    let env = ryan::Environment::builder()
+        .require_env(["__RYAN_MISSING_ENV_REQUIRED__"])
+        .build();
+    let error = ryan::from_str_with_env::<String>(
+        &env,
+        r#"import "env:__RYAN_MISSING_ENV_REQUIRED__" as text or "default""#,
+    )
+    .unwrap_err();
+
+    assert!(error
+        .to_string()
+        .contains("Environment variable __RYAN_MISSING_ENV_REQUIRED__ is not set"));
+
+    // Variables that are not required still fall back to their defaults:
+    assert_eq!(
+        ryan::from_str_with_env::<String>(
+            &env,
+            r#"import "env:__RYAN_MISSING_ENV_UNSET__" as text or "default""#,
+        )
+        .unwrap(),
+        "default"
+    );
+}
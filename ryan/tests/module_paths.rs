@@ -0,0 +1,105 @@
+//! The pseudo-variables `__module__` and `__dir__`, telling each module where it is.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+mod common;
+
+use std::path::Path;
+
+use ryan::Environment;
+use serde_json::json;
+
+use common::{eval_with, fixture};
+
+fn text(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+#[test]
+fn each_imported_module_sees_its_own_path() {
+    let dir = fixture(
+        "nested",
+        &[
+            (
+                "main.ryan",
+                r#"{ dir: __dir__, api: import "services/api.ryan" }"#,
+            ),
+            (
+                "services/api.ryan",
+                r#"{ dir: __dir__, module: __module__, db: import "db/conn.ryan" }"#,
+            ),
+            ("services/db/conn.ryan", "{ dir: __dir__ }"),
+        ],
+    );
+    let value: serde_json::Value = ryan::from_path(dir.join("main.ryan")).unwrap();
+
+    assert_eq!(
+        value,
+        json!({
+            "dir": text(&dir),
+            "api": {
+                "dir": text(&dir.join("services")),
+                "module": text(&dir.join("services/api.ryan")),
+                "db": { "dir": text(&dir.join("services/db")) },
+            },
+        })
+    );
+}
+
+#[test]
+fn pattern_matches_see_the_module_defining_them() {
+    let dir = fixture(
+        "patterns",
+        &[
+            (
+                "main.ryan",
+                r#"let { asset } = import "lib/assets.ryan"; asset "logo.png""#,
+            ),
+            (
+                "lib/assets.ryan",
+                "let asset name = `${__dir__}/static/${name}`; { asset }",
+            ),
+        ],
+    );
+    let value: String = ryan::from_path(dir.join("main.ryan")).unwrap();
+
+    assert_eq!(value, format!("{}/static/logo.png", text(&dir.join("lib"))));
+}
+
+#[test]
+fn paths_follow_the_current_module() {
+    let env = Environment::new(None);
+    assert_eq!(
+        eval_with(&env, "[__module__, __dir__]"),
+        json!([null, null])
+    );
+
+    let env = Environment::new(Some("conf/app.ryan"));
+    assert_eq!(
+        eval_with(&env, "[__module__, __dir__]"),
+        json!(["conf/app.ryan", "conf"])
+    );
+
+    let env = Environment::new(Some("app.ryan"));
+    assert_eq!(eval_with(&env, "__dir__"), json!("."));
+}
+
+#[test]
+fn hermetic_environments_still_have_paths() {
+    let env = Environment::builder()
+        .module("conf/app.ryan")
+        .hermetic()
+        .build();
+
+    assert_eq!(
+        eval_with(&env, "[__module__, __dir__]"),
+        json!(["conf/app.ryan", "conf"])
+    );
+}
+
+#[test]
+fn modules_that_are_not_files_have_no_directory() {
+    let env = Environment::new(None);
+    let value = eval_with(&env, r#"import "data:,[__module__, __dir__]""#);
+
+    assert_eq!(value, json!(["data:,[__module__, __dir__]", null]));
+}
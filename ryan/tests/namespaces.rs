@@ -0,0 +1,82 @@
+//! Built-ins are organized in namespaces, like `str.trim` or `list.sort`, while their bare
+//! names are kept as aliases. Bindings shadowing a built-in are warned about.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+use std::{cell::RefCell, rc::Rc};
+
+use ryan::environment::{Environment, EvalEvent};
+
+/// Evaluates a program, returning its value and the warnings it emitted.
+fn eval(code: &str) -> (String, Vec<String>) {
+    let warnings = Rc::new(RefCell::new(vec![]));
+    let recorded = warnings.clone();
+    let env = Environment::builder()
+        .on_event(move |event| {
+            if let EvalEvent::Warning { message } = event {
+                recorded.borrow_mut().push(message.to_string());
+            }
+        })
+        .build();
+    let block = ryan::parser::parse(code).unwrap();
+    let value = ryan::parser::eval(env, &block).unwrap();
+
+    (value.to_string(), warnings.take())
+}
+
+#[test]
+fn namespaced_built_ins_are_accessed_with_a_dot() {
+    for (code, expected) in [
+        (r#"str.trim " a ""#, r#""a""#),
+        (r#"(str.split ",") "a,b""#, r#"["a", "b"]"#),
+        ("list.sort [3, 1, 2]", "[1, 2, 3]"),
+        ("dict.keys { a: 1, b: 2 }", r#"["a", "b"]"#),
+        ("math.floor 1.5", "1.0"),
+        ("[1, 2] |> math.sum", "3"),
+    ] {
+        assert_eq!(eval(code), (expected.to_owned(), vec![]), "{code}");
+    }
+}
+
+#[test]
+fn bare_names_are_aliases() {
+    assert_eq!(eval(r#"trim " a ""#).0, r#""a""#);
+    assert_eq!(eval("trim == str.trim").0, "true");
+    assert_eq!(eval("sort [2, 1]").0, "[1, 2]");
+}
+
+#[test]
+fn shadowing_a_built_in_warns_at_the_definition() {
+    let (value, warnings) = eval("let len = 3; 1");
+
+    assert_eq!(value, "1");
+    assert_eq!(warnings, ["Binding `len` shadows the built-in `len`"]);
+}
+
+#[test]
+fn shadowing_an_alias_points_to_the_namespace() {
+    let (value, warnings) = eval(r#"let trim x = x; let trim 0 = 0; [trim " a ", str.trim " a "]"#);
+
+    assert_eq!(value, r#"[" a ", "a"]"#);
+    assert_eq!(
+        warnings,
+        ["Binding `trim` shadows the built-in `trim`, which is still available as `str.trim`"]
+    );
+}
+
+#[test]
+fn shadowing_a_binding_does_not_warn() {
+    let (_, warnings) = eval("let f len = [len, let len = 2; len]; f 1");
+    assert!(warnings.is_empty(), "{warnings:?}");
+
+    let (_, warnings) = eval("let x = 1; let x = 2; let keys = 3; [x, keys]");
+    assert_eq!(warnings.len(), 1, "{warnings:?}");
+}
+
+#[test]
+fn help_lists_the_full_names() {
+    let (value, _) = eval(r#"let all = help null; ["str.trim" in all, "trim" in all]"#);
+    assert_eq!(value, "[true, false]");
+
+    let (value, _) = eval(r#"[(help "trim").name, (help "str.trim").name, (help "len").name]"#);
+    assert_eq!(value, r#"["str.trim", "str.trim", "len"]"#);
+}
@@ -0,0 +1,71 @@
+//! Turning text into numbers, with `as int`, `as float` and the `parse_int` and
+//! `try_parse_float` built-ins.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+mod common;
+
+use common::try_eval_json;
+
+#[test]
+fn casts_trim_whitespace_and_ignore_underscores() {
+    assert_eq!(
+        try_eval_json(r#"[" 42 " as int, "1_000" as int, "\t2.5\n" as float, "-7" as float]"#)
+            .unwrap(),
+        serde_json::json!([42, 1000, 2.5, -7.0])
+    );
+}
+
+#[test]
+fn failed_casts_quote_the_text() {
+    for (source, message) in [
+        (r#""" as int"#, r#"Cannot convert text "" to int"#),
+        (r#""  " as float"#, r#"Cannot convert text "  " to float"#),
+        (r#""12abc" as int"#, r#"Cannot convert text "12abc" to int"#),
+    ] {
+        let err = try_eval_json(source).unwrap_err();
+        assert!(err.starts_with(message), "{source}: {err}");
+    }
+}
+
+#[test]
+fn parses_in_a_radix() {
+    assert_eq!(
+        try_eval_json(
+            r#"[
+                (parse_int 16) "ff",
+                (parse_int 16) "-0xFF",
+                (parse_int 2) "1010_1010",
+                "777" |> parse_int 8,
+            ]"#
+        )
+        .unwrap(),
+        serde_json::json!([255, -255, 170, 511])
+    );
+    assert!(try_eval_json(r#"(parse_int 16) "fg""#)
+        .unwrap_err()
+        .starts_with(r#"Cannot parse "fg" as int in radix 16"#));
+    assert!(try_eval_json(r#"(parse_int 37) "1""#)
+        .unwrap_err()
+        .starts_with("Radix must be between 2 and 36, got 37"));
+}
+
+#[test]
+fn application_needs_parentheses_for_the_radix() {
+    // Application is right-associative, so this applies `16` to `"ff"`:
+    let err = try_eval_json(r#"parse_int 16 "ff""#).unwrap_err();
+    assert!(err.starts_with("`16` is not a pattern"), "{err}");
+}
+
+#[test]
+fn empty_text_is_not_a_number() {
+    assert!(try_eval_json(r#"parse_int """#)
+        .unwrap_err()
+        .starts_with(r#"Cannot parse "" as int"#));
+    assert_eq!(
+        try_eval_json(
+            r#"[try_parse_float "", try_parse_float "x" ? 1.0, try_parse_float " 2.5 "]"#
+        )
+        .unwrap(),
+        serde_json::json!([null, 1.0, 2.5])
+    );
+}
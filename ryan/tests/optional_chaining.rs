@@ -0,0 +1,81 @@
+//! The optional access operators `?.` and `?[...]`, and how they bind next to `?` and to
+//! application.
+#![cfg(feature = "parse")]
+
+mod common;
+
+use common::try_eval_json;
+
+#[test]
+fn missing_values_become_null() {
+    assert_eq!(
+        try_eval_json(
+            r#"
+            let conf = { db: { host: "localhost" }, replicas: [] };
+            [conf?.db?.port ? 5432, conf?.cache?.size, conf?["replicas", 0, "host"], null?.a?[0]]
+            "#
+        )
+        .unwrap(),
+        serde_json::json!([5432, null, null, null])
+    );
+}
+
+#[test]
+fn only_null_and_dictionaries_can_be_accessed() {
+    for source in ["let a = 1; a?.b", "let a = [1]; a?.b"] {
+        let err = try_eval_json(source).unwrap_err();
+        assert!(err.starts_with("Operator `?.b` cannot be applied"), "{err}");
+    }
+}
+
+#[test]
+fn a_space_before_the_bracket_is_a_default() {
+    assert_eq!(
+        try_eval_json("let x = null; [x ?[1], x ? [1], x?[1], x  ?[1]]").unwrap(),
+        serde_json::json!([[1], [1], null, [1]])
+    );
+    assert_eq!(
+        try_eval_json("let x = [5]; [x ?[1], x?[0]]").unwrap(),
+        serde_json::json!([[5], 5])
+    );
+    assert_eq!(
+        try_eval_json("let x = null;\nx\n?[2]").unwrap(),
+        serde_json::json!([2])
+    );
+}
+
+#[test]
+fn optional_types_and_pattern_defaults_allow_a_space() {
+    assert_eq!(
+        try_eval_json(
+            "let x: ?[int] = null; type T = ?[int]; let f { a ?[1] } = a; [x, [1] is T, f {}]"
+        )
+        .unwrap(),
+        serde_json::json!([null, true, [1]])
+    );
+}
+
+#[test]
+fn binds_tighter_than_defaults_and_application() {
+    assert_eq!(
+        try_eval_json("let f x = x; let a = { b: 3 }; [f a?.b, a?.c ? 1 + 1, a?.b ? 0 * 2]")
+            .unwrap(),
+        serde_json::json!([3, 2, 6])
+    );
+    assert_eq!(
+        try_eval_json("let a = { b: [1, 2] }; a?.b?[1] + 1").unwrap(),
+        serde_json::json!(3)
+    );
+}
+
+#[test]
+fn round_trips() {
+    for source in ["x?.a?[0] ? 1", "x ?[1]", "f x?.a", "(x ? y)?.a"] {
+        let block = ryan::parser::parse(source).unwrap();
+        assert_eq!(
+            ryan::parser::parse(&block.to_string()).unwrap(),
+            block,
+            "{source}"
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! The order of the keys of dictionaries. Keys are kept in the order in which they were
+//! first set: setting a key again replaces its value, but keeps its position.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+mod common;
+
+use ryan::parser::Value;
+
+use common::eval;
+
+/// The entries of a dictionary, in order. Comparing dictionaries with `==` ignores order.
+fn entries(source: &str) -> Vec<(String, Value)> {
+    match eval(source) {
+        Value::Map(map) => map
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect(),
+        value => panic!("expected a dictionary, got {value}"),
+    }
+}
+
+fn keys(source: &str) -> Vec<String> {
+    entries(source).into_iter().map(|(key, _)| key).collect()
+}
+
+#[test]
+fn repeated_keys_keep_their_first_position() {
+    assert_eq!(
+        entries("{ a: 1, b: 2, a: 3 }"),
+        [
+            ("a".to_owned(), Value::Integer(3)),
+            ("b".to_owned(), Value::Integer(2))
+        ]
+    );
+}
+
+#[test]
+fn concatenation_appends_new_keys() {
+    assert_eq!(
+        entries("{ a: 1, b: 2, c: 3 } + { d: 4, b: 5 }"),
+        [
+            ("a".to_owned(), Value::Integer(1)),
+            ("b".to_owned(), Value::Integer(5)),
+            ("c".to_owned(), Value::Integer(3)),
+            ("d".to_owned(), Value::Integer(4)),
+        ]
+    );
+}
+
+#[test]
+fn subtraction_keeps_the_remaining_keys_in_place() {
+    assert_eq!(keys("{ c: 1, b: 2, a: 3 } - [\"b\"]"), ["c", "a"]);
+    assert_eq!(keys("{ c: 1, b: 2, a: 3 } - { c: null }"), ["b", "a"]);
+}
+
+#[test]
+fn flatten_expressions_insert_keys_where_they_are() {
+    assert_eq!(
+        keys("let base = { a: 1, d: 2 }; { d: 4, ...base, b: 2 }"),
+        ["d", "a", "b"]
+    );
+}
+
+#[test]
+fn comprehension_overwrites_keep_the_first_position() {
+    assert_eq!(
+        entries(r#"{ k: v for [k, v] in [["b", 1], ["a", 2], ["b", 3]] }"#),
+        [
+            ("b".to_owned(), Value::Integer(3)),
+            ("a".to_owned(), Value::Integer(2))
+        ]
+    );
+    assert_eq!(
+        keys(r#"from_entries [["b", 1], ["a", 2], ["b", 3]]"#),
+        ["b", "a"]
+    );
+}
+
+#[test]
+fn dictionaries_become_lists_in_order() {
+    let expected = eval(r#"[["c", 1], ["a", 2], ["b", 3]]"#);
+    let dict = "let x = { c: 1, a: 2, b: 3 };";
+
+    assert_eq!(eval(&format!("{dict} [...x]")), expected);
+    assert_eq!(eval(&format!("{dict} [[k, v] for [k, v] in x]")), expected);
+    assert_eq!(eval(&format!("{dict} keys x")), eval(r#"["c", "a", "b"]"#));
+    assert_eq!(eval(&format!("{dict} values x")), eval("[1, 2, 3]"));
+}
+
+#[test]
+fn sort_keys_sorts_only_the_outer_dictionary() {
+    let sorted = entries("sort_keys { b: 1, c: { z: 1, y: 2 }, a: 3 }");
+    assert_eq!(
+        sorted.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+        ["a", "b", "c"]
+    );
+
+    let Value::Map(nested) = &sorted[2].1 else {
+        panic!("expected a dictionary, got {}", sorted[2].1);
+    };
+    assert_eq!(
+        nested.keys().map(|key| &**key).collect::<Vec<_>>(),
+        ["z", "y"]
+    );
+}
@@ -0,0 +1,242 @@
+//! Whatever the input, parsing and evaluating must fail with an error, never with a panic.
+//! This runs the same steps as the `parse_eval` fuzz target on arbitrary text and bytes,
+//! and on arbitrary programs built from the grammar, which get much further than random
+//! text does.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+use proptest::prelude::*;
+use ryan::environment::NoImport;
+use ryan::Environment;
+
+fn hermetic() -> Environment {
+    Environment::builder().import_loader(NoImport).build()
+}
+
+/// Parses, prints and evaluates some code, as the fuzz target does.
+fn run(source: &str) {
+    let block = match ryan::parser::parse(source) {
+        Ok(block) => block,
+        Err(err) => {
+            let _ = err.to_string();
+            return;
+        }
+    };
+
+    let _ = block.to_string();
+    let _ = ryan::fmt::format(source);
+
+    match ryan::parser::eval(hermetic(), &block) {
+        Ok(value) => {
+            let _ = value.to_string();
+        }
+        Err(err) => {
+            let _ = err.to_string();
+        }
+    }
+}
+
+/// Inputs that used to panic or overflow the stack.
+#[test]
+fn regressions() {
+    for source in [
+        "1e999999999999",
+        "-1e999999999999",
+        "9223372036854775807 + 1",
+        "-9223372036854775807 - 2",
+        "4611686018427387904 * 2",
+        "(-9223372036854775807 - 1) / -1",
+        "(-9223372036854775807 - 1) % -1",
+        "-(-9223372036854775807 - 1)",
+        "let x = [1, 2, 3]; x [-1]",
+        "[1, 2, 3][-9223372036854775807 - 1]",
+        "sum [9223372036854775807, 1]",
+        "range [0, 100000000000]",
+        "\"ab\" * 9223372036854775807",
+        "let f x = x x; f f",
+        "(\\x => x x) (\\x => x x)",
+        "let f [a, ...b] = a; f",
+        "1 +",
+        "\"unterminated",
+        "`${`",
+        "\n\n\u{1F600} +",
+    ] {
+        run(source);
+    }
+}
+
+#[test]
+fn self_application_is_an_error() {
+    let err = ryan::from_str_with_env::<serde_json::Value>(&hermetic(), "let f x = x x; f f")
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("Pattern matches nested more than"),
+        "{err}"
+    );
+}
+
+/// A chain of `depth` pattern matches, each calling the one before it from inside a
+/// binding, which evaluates to 1.
+fn call_chain(depth: usize) -> String {
+    let mut source = "let f0 x = x + 1;\n".to_owned();
+    for i in 1..depth {
+        source += &format!("let f{i} x = let y = f{} x; y;\n", i - 1);
+    }
+    source + &format!("f{} 0", depth - 1)
+}
+
+#[test]
+fn deep_calls_are_not_self_application() {
+    let value = ryan::from_str_with_env::<i64>(&hermetic(), &call_chain(40)).unwrap();
+    assert_eq!(value, 1);
+    let value = ryan::from_str_with_env::<i64>(&hermetic(), &call_chain(64)).unwrap();
+    assert_eq!(value, 1);
+
+    let err = ryan::from_str_with_env::<i64>(&hermetic(), &call_chain(65)).unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("Pattern matches nested more than 64 levels deep"),
+        "{err}"
+    );
+}
+
+#[test]
+fn call_depth_is_configurable() {
+    let env = Environment::builder()
+        .import_loader(NoImport)
+        .max_call_depth(10)
+        .build();
+    assert_eq!(env.max_call_depth(), 10);
+    assert_eq!(
+        ryan::from_str_with_env::<i64>(&env, &call_chain(10)).unwrap(),
+        1
+    );
+
+    let err = ryan::from_str_with_env::<i64>(&env, &call_chain(11)).unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("Pattern matches nested more than 10 levels deep"),
+        "{err}"
+    );
+}
+
+/// Literals, including numbers too big for their types.
+fn literal() -> impl Strategy<Value = String> {
+    prop_oneof![
+        any::<i64>().prop_map(|int| int.to_string()),
+        any::<f64>().prop_map(|float| format!("{float:?}")),
+        "[0-9]{1,20}(\\.[0-9]{0,3})?(e[+-]?[0-9]{1,4})?",
+        "\"([a-z ]|\\\\[nt\"\\\\]|\\\\u[0-9a-f]{4}){0,6}\"",
+        prop::sample::select(vec!["null", "true", "false", "x", "f", "xs"]).prop_map(str::to_owned),
+    ]
+}
+
+/// Built-ins that take all sorts of arguments.
+fn builtin() -> impl Strategy<Value = String> {
+    prop::sample::select(vec![
+        "len",
+        "range",
+        "fmt",
+        "sum",
+        "max",
+        "min",
+        "sort",
+        "keys",
+        "values",
+        "zip",
+        "enumerate",
+        "join",
+        "split",
+        "replace",
+        "trim",
+        "lowercase",
+        "round",
+        "floor",
+        "parse_int",
+        "repeat",
+    ])
+    .prop_map(str::to_owned)
+}
+
+fn binary_operator() -> impl Strategy<Value = &'static str> {
+    prop::sample::select(vec![
+        "+", "-", "*", "/", "%", "==", "!=", "<", "<=", ">", ">=", "and", "or", "in", "?", "|>", "",
+    ])
+}
+
+fn expression() -> impl Strategy<Value = String> {
+    let leaf = prop_oneof![3 => literal(), 1 => builtin()];
+    leaf.prop_recursive(4, 48, 4, |inner| {
+        prop_oneof![
+            (inner.clone(), binary_operator(), inner.clone())
+                .prop_map(|(left, op, right)| format!("({left} {op} {right})")),
+            (prop::sample::select(vec!["not ", "-", "+"]), inner.clone())
+                .prop_map(|(op, operand)| format!("({op}{operand})")),
+            (
+                inner.clone(),
+                prop::sample::select(vec!["as int", "as float", "as text", ".a", "?.a"])
+            )
+                .prop_map(|(operand, op)| format!("({operand} {op})")),
+            (inner.clone(), inner.clone()).prop_map(|(value, index)| format!("{value}[{index}]")),
+            prop::collection::vec(inner.clone(), 0..4)
+                .prop_map(|items| format!("[{}]", items.join(", "))),
+            prop::collection::vec(("[a-c]", inner.clone()), 0..4).prop_map(|entries| {
+                let entries = entries
+                    .into_iter()
+                    .map(|(key, value)| format!("{key}: {value}"))
+                    .collect::<Vec<_>>();
+                format!("{{ {} }}", entries.join(", "))
+            }),
+            inner.clone().prop_map(|items| format!("[...{items}]")),
+            inner.clone().prop_map(|items| format!("{{ ...{items} }}")),
+            (inner.clone(), inner.clone())
+                .prop_map(|(item, iterable)| format!("[{item} for x in {iterable}]")),
+            (inner.clone(), inner.clone(), inner.clone()).prop_map(|(cond, then, other)| {
+                format!("(if {cond} then {then} else {other})")
+            }),
+            inner.clone().prop_map(|body| format!("(\\x => {body})")),
+            (inner.clone(), inner.clone())
+                .prop_map(|(value, body)| format!("(let x = {value}; {body})")),
+            inner.clone().prop_map(|value| format!("`a${{{value}}}b`")),
+        ]
+    })
+}
+
+/// Programs with some bindings and a final expression, all of them sharing a few names.
+fn program() -> impl Strategy<Value = String> {
+    let binding = prop_oneof![
+        expression().prop_map(|value| format!("let x = {value};")),
+        expression().prop_map(|value| format!("let xs = {value};")),
+        expression().prop_map(|body| format!("let f x = {body};")),
+        expression().prop_map(|body| format!("let f [x, ...xs] = {body};")),
+        expression().prop_map(|body| format!("let f {{ a: x }} = {body};")),
+    ];
+    (prop::collection::vec(binding, 0..4), expression()).prop_map(|(bindings, last)| {
+        let mut program = bindings.join("\n");
+        program.push('\n');
+        program += &last;
+        program
+    })
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_text(source in any::<String>()) {
+        run(&source);
+    }
+
+    #[test]
+    fn arbitrary_tokens(source in "([0-9a-z_ \n.,:;=+*/%<>!?|&#$`\"\\\\(){}\\[\\]-]|let |for |in |if |then |else |as |\\.\\.\\.|=>|//){0,40}") {
+        run(&source);
+    }
+
+    #[test]
+    fn arbitrary_bytes(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+        let _ = ryan::from_reader_with_env::<_, serde_json::Value>(&hermetic(), &bytes[..]);
+    }
+
+    #[test]
+    fn arbitrary_programs(source in program()) {
+        run(&source);
+    }
+}
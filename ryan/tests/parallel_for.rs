@@ -0,0 +1,65 @@
+//! Parallel for clauses, e.g., `for a, b in xs, ys`, which iterate over many iterables in
+//! lockstep.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+mod common;
+
+use std::{collections::BTreeMap, rc::Rc};
+
+use ryan::Environment;
+
+use common::eval_json;
+
+#[test]
+fn iterates_in_lockstep_until_the_shortest_iterable_ends() {
+    assert_eq!(
+        eval_json("[x * y for x, y in [1, 2, 3], [4, 5]]"),
+        serde_json::json!([4, 10])
+    );
+    assert_eq!(
+        eval_json("[[a, b, c] for a, b, c in [1, 2], [3, 4], [5, 6]]"),
+        serde_json::json!([[1, 3, 5], [2, 4, 6]])
+    );
+    assert_eq!(
+        eval_json(r#"{ k: v for k, v in ["a", "b"], [1, 2] }"#),
+        serde_json::json!({ "a": 1, "b": 2 })
+    );
+}
+
+#[test]
+fn does_not_depend_on_the_variable_zip() {
+    assert_eq!(
+        eval_json("let zip x = [[0, 0]]; [x + y for x, y in [1, 2], [3, 4]]"),
+        serde_json::json!([4, 6])
+    );
+    assert_eq!(
+        eval_json("let f zip = [zip + y for zip, y in [1, 2], [3, 4]]; f 0"),
+        serde_json::json!([4, 6])
+    );
+}
+
+#[test]
+fn does_not_need_the_zip_built_in() {
+    let env = Environment::builder()
+        .built_ins(Rc::new(BTreeMap::new()))
+        .build();
+    let value: Vec<i64> =
+        ryan::from_str_with_env(&env, "[x + y for x, y in [1, 2], [3, 4]]").unwrap();
+    assert_eq!(value, [4, 6]);
+}
+
+#[test]
+fn iterables_must_be_iterable() {
+    let err = ryan::from_str::<serde_json::Value>("[x for x, y in [1], 2]").unwrap_err();
+    assert!(err.to_string().contains("not iterable"), "{err}");
+}
+
+#[test]
+fn is_printed_as_written() {
+    let block = ryan::parser::parse("[x for x, [y, z] in xs, ys]").unwrap();
+    assert_eq!(block.to_string(), "[x for x, [y, z] in xs, ys]");
+    assert_eq!(
+        ryan::fmt::format("[ x for x,y,z in xs,ys,zs ]").unwrap(),
+        "[x for x, y, z in xs, ys, zs]\n"
+    );
+}
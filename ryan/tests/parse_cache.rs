@@ -0,0 +1,121 @@
+//! The parsed code of modules is kept across imports and evaluations, until the module
+//! changes, as told by `ImportLoader::version` or `Environment::invalidate`.
+#![cfg(feature = "parse")]
+
+mod common;
+
+use std::{cell::RefCell, collections::HashMap, error::Error, io::Read, rc::Rc};
+
+use ryan::environment::{Environment, ImportLoader, ParseCacheStats};
+
+use common::eval_with;
+
+/// The code of each module, with its version.
+type Modules = HashMap<String, (String, Option<String>)>;
+
+/// Serves modules from a map that can be changed between evaluations, counting how many
+/// times each one is read.
+#[derive(Debug, Clone, Default)]
+struct Files {
+    code: Rc<RefCell<Modules>>,
+    reads: Rc<RefCell<HashMap<String, usize>>>,
+}
+
+impl Files {
+    fn set(&self, path: &str, code: &str, version: Option<&str>) {
+        self.code.borrow_mut().insert(
+            path.to_owned(),
+            (code.to_owned(), version.map(str::to_owned)),
+        );
+    }
+
+    fn reads(&self, path: &str) -> usize {
+        self.reads.borrow().get(path).copied().unwrap_or(0)
+    }
+}
+
+impl ImportLoader for Files {
+    fn resolve(&self, _: Option<&str>, path: &str) -> Result<String, Box<dyn Error>> {
+        Ok(path.to_owned())
+    }
+
+    fn load(&self, path: &str) -> Result<Box<dyn Read>, Box<dyn Error>> {
+        *self.reads.borrow_mut().entry(path.to_owned()).or_default() += 1;
+        let (code, _) = self
+            .code
+            .borrow()
+            .get(path)
+            .cloned()
+            .ok_or("no such module")?;
+        Ok(Box::new(std::io::Cursor::new(code)))
+    }
+
+    fn version(&self, path: &str) -> Option<String> {
+        self.code.borrow().get(path)?.1.clone()
+    }
+}
+
+#[test]
+fn modules_imported_with_different_arguments_are_parsed_once() {
+    let files = Files::default();
+    files.set("greet.ryan", r#""Hello, " + args.name"#, None);
+    let env = Environment::builder().import_loader(files.clone()).build();
+
+    let code =
+        r#"[import "greet.ryan" with { name: "a" }, import "greet.ryan" with { name: "b" }]"#;
+    assert_eq!(
+        eval_with(&env, code),
+        serde_json::json!(["Hello, a", "Hello, b"])
+    );
+    assert_eq!(files.reads("greet.ryan"), 1);
+    assert_eq!(
+        env.parse_cache_stats(),
+        ParseCacheStats { hits: 1, misses: 1 }
+    );
+}
+
+#[test]
+fn invalidated_modules_are_read_again() {
+    let files = Files::default();
+    files.set("a.ryan", "1", None);
+    let env = Environment::builder().import_loader(files.clone()).build();
+    assert_eq!(eval_with(&env, r#"import "a.ryan""#), serde_json::json!(1));
+
+    // Without a version, a change goes unnoticed until the module is invalidated:
+    files.set("a.ryan", "2", None);
+    assert_eq!(eval_with(&env, r#"import "a.ryan""#), serde_json::json!(1));
+    env.invalidate("a.ryan");
+    assert_eq!(eval_with(&env, r#"import "a.ryan""#), serde_json::json!(2));
+    assert_eq!(files.reads("a.ryan"), 2);
+}
+
+#[test]
+fn modules_are_read_again_when_their_version_changes() {
+    let files = Files::default();
+    files.set("a.ryan", "1", Some("v1"));
+    let env = Environment::builder().import_loader(files.clone()).build();
+    assert_eq!(eval_with(&env, r#"import "a.ryan""#), serde_json::json!(1));
+    assert_eq!(eval_with(&env, r#"import "a.ryan""#), serde_json::json!(1));
+    assert_eq!(files.reads("a.ryan"), 1);
+
+    files.set("a.ryan", "2", Some("v2"));
+    assert_eq!(eval_with(&env, r#"import "a.ryan""#), serde_json::json!(2));
+    assert_eq!(files.reads("a.ryan"), 2);
+}
+
+#[test]
+fn versioned_modules_are_reused_without_the_import_cache() {
+    let files = Files::default();
+    files.set("versioned.ryan", "1", Some("v1"));
+    files.set("unversioned.ryan", "2", None);
+    let env = Environment::builder()
+        .import_loader(files.clone())
+        .import_cache(false)
+        .build();
+
+    let code = r#"[import "versioned.ryan", import "unversioned.ryan"]"#;
+    assert_eq!(eval_with(&env, code), serde_json::json!([1, 2]));
+    assert_eq!(eval_with(&env, code), serde_json::json!([1, 2]));
+    assert_eq!(files.reads("versioned.ryan"), 1);
+    assert_eq!(files.reads("unversioned.ryan"), 2);
+}
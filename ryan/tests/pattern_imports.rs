@@ -0,0 +1,13 @@
+#![cfg(feature = "parse")]
+
+use std::path::Path;
+
+/// `examples/lib/helpers.ryan` defines a pattern that imports a file next to itself. The
+/// pattern is applied in `examples/greeter.ryan`, in another directory.
+#[test]
+fn imports_in_patterns_resolve_relative_to_the_defining_module() {
+    let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples");
+    let greeting: String = ryan::from_path(examples.join("greeter.ryan")).unwrap();
+
+    assert_eq!(greeting, "Hello, Ryan!");
+}
@@ -0,0 +1,86 @@
+#![cfg(feature = "parse")]
+
+mod common;
+
+use std::rc::Rc;
+
+use ryan::parser::Value;
+
+use common::eval;
+
+/// Pattern matches stored alongside what they capture, or capturing their own earlier
+/// rules, are freed as soon as the value holding them is dropped.
+#[test]
+fn pattern_matches_are_freed() {
+    let value = eval(
+        r#"
+        let big = { items: [1, 2, 3] };
+        let count 0 = big;
+        let count_zero = count;
+        let count n = count_zero 0;
+        let with_count = { count, big, nested: { count } };
+        { with_count, count }
+        "#,
+    );
+
+    let Value::Map(map) = &value else {
+        panic!("expected a map, got {value}");
+    };
+    let Value::PatternMatches(_, rules) = &map["count"] else {
+        panic!("expected a pattern match, got {}", map["count"]);
+    };
+    let rule = Rc::clone(&rules[1]);
+    let Value::Map(big) = &rules[0].captures["big"] else {
+        panic!("expected `big` to be captured");
+    };
+    let big = Rc::clone(big);
+
+    drop(value);
+    assert_eq!(Rc::strong_count(&rule), 1);
+
+    drop(rule);
+    assert_eq!(Rc::strong_count(&big), 1);
+}
+
+/// Interpolating a pattern match shows how many values it captures, but not the values.
+#[test]
+fn interpolated_pattern_matches_are_bounded() {
+    let value = eval(
+        r#"
+        let big = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let first = 0;
+        let pick i = [big, first];
+        let pick i if i > 1 = big;
+        `${pick}`
+        "#,
+    );
+
+    assert_eq!(
+        value,
+        Value::Text("![pattern pick i | i if i > 1 with 3 captures]".into())
+    );
+}
+
+/// Variables bound by the pattern shadow the captured ones and the captures shadow the
+/// variables where the pattern is applied, including in defaults and type guards.
+#[test]
+fn captures_are_shadowed_by_the_pattern_only() {
+    let value = eval(
+        r#"
+        let x = 1;
+        let y = 2;
+        let f = (
+            type P = int;
+            let d = 5;
+            let g { x: P, z ? d } = [x, y, z];
+            g
+        );
+        type P = text;
+        let d = 7;
+        let y = 3;
+        f { x: 10 }
+        "#,
+    );
+
+    assert_eq!(value.to_string(), "[10, 2, 5]");
+}
@@ -0,0 +1,70 @@
+//! The pipeline operator, `|>`, which applies the pattern on its right to the value on its
+//! left.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+mod common;
+
+use common::eval_json;
+
+#[test]
+fn chains_stages_left_to_right() {
+    assert_eq!(
+        eval_json(r#"{ a: 1, b: 2, c: 3 } |> values |> sum |> fmt"#),
+        serde_json::json!("6")
+    );
+}
+
+#[test]
+fn chains_user_defined_alternatives() {
+    let source = r#"
+        let describe [x] = `just ${x}`;
+        let describe xs: [any] = `${len xs} values`;
+        let shout x = `${x}!`;
+
+        [
+            { a: 1 } |> values |> describe |> shout,
+            { a: 1, b: 2 } |> values |> describe |> shout,
+            ["a", "b"] |> (join ", ") |> shout,
+            [1, 2] |> \[a, b] => a + b,
+        ]
+    "#;
+
+    assert_eq!(
+        eval_json(source),
+        serde_json::json!(["just 1!", "2 values!", "a, b!", 3])
+    );
+}
+
+#[test]
+fn binds_looser_than_application_and_arithmetic() {
+    assert_eq!(eval_json("1 + 2 |> fmt"), serde_json::json!("3"));
+    assert_eq!(eval_json(r#"["a"] |> join "-""#), serde_json::json!("a"));
+    assert_eq!(
+        eval_json(r#"(3 |> fmt) == "3" and true"#),
+        serde_json::json!(true)
+    );
+}
+
+#[test]
+fn needs_a_pattern_on_the_right() {
+    let Err(ryan::Error::Eval(err)) = ryan::from_str::<i64>("1 |> 2") else {
+        panic!("`2` is not a pattern");
+    };
+
+    assert_eq!(
+        err.kind().to_string(),
+        "The right side of `|>` must be a pattern to apply to the left side, got 2"
+    );
+}
+
+#[test]
+fn formats_back_to_the_same_code() {
+    for source in [
+        "[1, 2] |> sum |> fmt\n",
+        "(1 |> fmt) == \"1\"\n",
+        "x |> (f |> g)\n",
+        "1 + 2 |> f x\n",
+    ] {
+        assert_eq!(ryan::fmt::format(source).unwrap(), source);
+    }
+}
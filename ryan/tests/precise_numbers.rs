@@ -0,0 +1,153 @@
+//! Integers bigger than 2^53 do not survive being turned into floats. Mixing them with
+//! floats is flagged as per the `PrecisionLossPolicy`, integer literals too big for 64
+//! bits must be exact floats and `precise_int` checks that an integer is safe for JSON.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+use std::{cell::RefCell, rc::Rc};
+
+use ryan::environment::{Environment, EvalEvent, PrecisionLossPolicy};
+use ryan::parser::{self, Value};
+
+/// 2^60, which is exactly a float, but far from the integers floats represent densely.
+const BIG: &str = "1_152_921_504_606_846_976";
+
+/// Evaluates a program, returning its value and the warnings it emitted.
+fn eval(policy: PrecisionLossPolicy, code: &str) -> (Result<Value, String>, Vec<String>) {
+    let warnings = Rc::new(RefCell::new(vec![]));
+    let recorded = warnings.clone();
+    let env = Environment::builder()
+        .precision_loss(policy)
+        .on_event(move |event| {
+            if let EvalEvent::Warning { message } = event {
+                recorded.borrow_mut().push(message.to_string());
+            }
+        })
+        .build();
+    let code = code.replace("BIG", BIG);
+    let block = parser::parse(&code).unwrap();
+    let value = parser::eval(env, &block).map_err(|err| err.to_string());
+
+    (value, warnings.take())
+}
+
+#[test]
+fn mixing_big_integers_with_floats_warns_by_default() {
+    for code in [
+        "BIG + 0.5",
+        "0.5 + BIG",
+        "BIG * 1.5",
+        "BIG - 1.0",
+        "BIG / 2.0",
+    ] {
+        let (value, warnings) = eval(PrecisionLossPolicy::default(), code);
+
+        assert!(matches!(value, Ok(Value::Float(_))), "{code}: {value:?}");
+        assert_eq!(warnings.len(), 1, "{code}: {warnings:?}");
+        assert!(
+            warnings[0].contains("The integer 1152921504606846976 in `"),
+            "{code}: {warnings:?}"
+        );
+    }
+}
+
+#[test]
+fn mixing_big_integers_with_floats_may_be_an_error() {
+    let (value, _) = eval(PrecisionLossPolicy::Error, "let x = BIG; x * 2.0");
+
+    assert_eq!(
+        value.unwrap_err().lines().next().unwrap(),
+        "The integer 1152921504606846976 in `x * 2.0` is too big to be turned into a float \
+        exactly. Convert it explicitly with `as float` if the loss of precision is fine"
+    );
+}
+
+#[test]
+fn mixing_big_integers_with_floats_may_be_allowed() {
+    let (value, warnings) = eval(PrecisionLossPolicy::Allow, "BIG + 0.5");
+
+    assert_eq!(value.unwrap(), Value::Float(2f64.powi(60)));
+    assert!(warnings.is_empty(), "{warnings:?}");
+}
+
+#[test]
+fn small_integers_explicit_casts_and_integer_arithmetic_are_fine() {
+    for code in [
+        "9_007_199_254_740_992 + 0.5",
+        "-9_007_199_254_740_992 * 1.5",
+        "BIG as float + 0.5",
+        "BIG + 1",
+        "BIG * 4",
+    ] {
+        let (value, _) = eval(PrecisionLossPolicy::Error, code);
+        assert!(value.is_ok(), "{code}: {value:?}");
+    }
+
+    let (value, _) = eval(PrecisionLossPolicy::Error, "BIG + 1");
+    assert_eq!(value.unwrap(), Value::Integer((1 << 60) + 1));
+}
+
+#[test]
+fn comparisons_with_big_integers_are_exact() {
+    for code in [
+        "BIG + 1 > BIG as float",
+        "BIG as float < BIG + 1",
+        "BIG - 1 <= BIG as float",
+        "BIG as float >= BIG - 1",
+        "not (BIG + 1 <= BIG as float)",
+        "BIG == BIG as float",
+        "BIG + 1 != BIG as float",
+        "not (BIG > 0.0 / 0.0) and not (0.0 / 0.0 <= BIG)",
+    ] {
+        let (value, warnings) = eval(PrecisionLossPolicy::Error, code);
+
+        assert_eq!(value, Ok(Value::Bool(true)), "{code}");
+        assert!(warnings.is_empty(), "{code}: {warnings:?}");
+    }
+}
+
+#[test]
+fn integer_literals_too_big_for_64_bits_must_be_exact_floats() {
+    let (value, _) = eval(PrecisionLossPolicy::Error, "18_446_744_073_709_551_616");
+    assert_eq!(value.unwrap(), Value::Float(2f64.powi(64)));
+
+    let (value, _) = eval(PrecisionLossPolicy::Error, "-9223372036854775808");
+    assert_eq!(value.unwrap(), Value::Integer(i64::MIN));
+
+    for code in ["18446744073709551615", "-9223372036854775809"] {
+        let err = parser::parse(code).unwrap_err().to_string();
+        assert!(err.contains("does not fit in 64 bits"), "{code}: {err}");
+    }
+
+    let (value, _) = eval(PrecisionLossPolicy::Error, "18446744073709551615.0");
+    assert_eq!(value.unwrap(), Value::Float(2f64.powi(64)));
+}
+
+#[test]
+fn precise_int_accepts_only_integers_floats_represent_exactly() {
+    let (value, _) = eval(
+        PrecisionLossPolicy::default(),
+        "type Id = precise_int; [x is Id for x in \
+        [9_007_199_254_740_992, -9_007_199_254_740_992, 9_007_199_254_740_993, BIG, 1.0]]",
+    );
+    assert_eq!(
+        value.unwrap().to_string(),
+        "[true, true, false, false, false]"
+    );
+
+    let (value, _) = eval(
+        PrecisionLossPolicy::default(),
+        "let id: precise_int = BIG; id",
+    );
+    assert!(value.unwrap_err().contains("is not of type precise_int"));
+}
+
+#[test]
+fn deserializing_big_integers_into_floats_is_exact() {
+    assert_eq!(ryan::from_str::<f64>(BIG).unwrap(), 2f64.powi(60));
+    assert!(ryan::from_str::<f64>("9223372036854775807").is_err());
+    assert!(ryan::from_str::<f32>("16777217").is_err());
+    assert_eq!(
+        ryan::from_str::<i64>("9223372036854775807").unwrap(),
+        i64::MAX
+    );
+}
@@ -0,0 +1,71 @@
+//! The prefix operators `not`, `-` and `+`, and how tightly they bind.
+#![cfg(feature = "parse")]
+
+mod common;
+
+use common::try_eval_json;
+
+#[test]
+fn negation_binds_tighter_than_products() {
+    assert_eq!(
+        try_eval_json("let x = 3; let y = 2; [-x * y, -x + y, - x, +x, -(-x)]").unwrap(),
+        serde_json::json!([-6, -1, -3, 3, 3])
+    );
+    assert_eq!(
+        try_eval_json("[-1.5, +1.5, -(2.5)]").unwrap(),
+        serde_json::json!([-1.5, 1.5, -2.5])
+    );
+}
+
+#[test]
+fn negation_applies_to_the_whole_application() {
+    assert_eq!(
+        try_eval_json("let f x = x * 2; - f 3").unwrap(),
+        serde_json::json!(-6)
+    );
+}
+
+#[test]
+fn negation_checks_for_overflow() {
+    let err = try_eval_json("let x = -9223372036854775807 - 1; -x").unwrap_err();
+    assert!(err.starts_with("Integer overflow"), "{err}");
+    assert!(try_eval_json(r#"-"a""#)
+        .unwrap_err()
+        .starts_with("Operator `-` cannot be applied"));
+}
+
+#[test]
+fn negative_literals_in_patterns() {
+    assert_eq!(
+        try_eval_json(
+            r#"let f -1 = "minus one"; let f -1.5 = "minus one and a half"; [f (-1), f (-1.5)]"#
+        )
+        .unwrap(),
+        serde_json::json!(["minus one", "minus one and a half"])
+    );
+}
+
+#[test]
+fn negation_round_trips() {
+    for source in ["-x * y", "- f x", "-(-x)", "f (-1)", "not -x", "+x - -1"] {
+        let block = ryan::parser::parse(source).unwrap();
+        assert_eq!(
+            ryan::parser::parse(&block.to_string()).unwrap(),
+            block,
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn names_starting_with_not_are_not_negated() {
+    assert_eq!(
+        try_eval_json("let notable = true; let not_yet = 1; [notable, not notable, not_yet]")
+            .unwrap(),
+        serde_json::json!([true, false, 1])
+    );
+    assert_eq!(
+        try_eval_json("let f x = x; let notes = 3; f notes").unwrap(),
+        serde_json::json!(3)
+    );
+}
@@ -0,0 +1,94 @@
+//! The `data:` and `stdin:` modules of the default importer.
+#![cfg(feature = "parse")]
+
+use std::{cell::RefCell, rc::Rc};
+
+use ryan::environment::{Environment, EvalEvent, ImportLoader, NoImport};
+
+fn error(source: &str) -> String {
+    ryan::from_str::<serde_json::Value>(source)
+        .unwrap_err()
+        .to_string()
+}
+
+#[test]
+fn data_urls_are_percent_decoded() {
+    let imported = ryan::from_str::<Vec<String>>(
+        r#"[
+            import "data:text,hello%20world%21" as text,
+            import "data:,a+b%2Bc" as text,
+            import "data:text/plain;charset=utf-8,caf%C3%A9" as text,
+            import "data:text,%25%2c" as text,
+        ]"#,
+    )
+    .unwrap();
+
+    assert_eq!(imported, ["hello world!", "a+b+c", "café", "%,"]);
+}
+
+#[test]
+fn data_urls_can_hold_ryan_programs() {
+    let imported = ryan::from_str::<Vec<i64>>(
+        r#"[
+            import "data:ryan,let%20x%20=%202;%20x%20*%20x",
+            import "data:ryan;base64,MSArIDE=",
+        ]"#,
+    )
+    .unwrap();
+
+    assert_eq!(imported, [4, 2]);
+}
+
+#[test]
+fn invalid_data_urls_are_errors() {
+    assert!(error(r#"import "data:text" as text"#)
+        .contains("Invalid data URL: expected a `,` before the content"));
+    assert!(error(r#"import "data:text,%G1" as text"#)
+        .contains("Invalid data URL: Invalid percent escape `%G1`"));
+    assert!(error(r#"import "data:text,abc%4" as text"#)
+        .contains("Invalid data URL: Invalid percent escape `%4`"));
+    assert!(error(r#"import "data:text;base64,!!" as text"#).contains("Invalid data URL"));
+
+    // A malformed URL is a bug, not a missing module:
+    assert!(error(r#"import "data:text,%" as text or "default""#).contains("Invalid data URL"));
+}
+
+#[test]
+fn data_urls_cannot_import_files() {
+    assert!(error(r#"import "data:ryan,import%20%22lib.ryan%22""#)
+        .contains("Cannot access the filesystem from a `data:` or `stdin:` module"));
+}
+
+#[test]
+fn data_urls_are_cached() {
+    let cache_hits = Rc::new(RefCell::new(vec![]));
+    let recorded = cache_hits.clone();
+    let env = Environment::builder()
+        .on_event(move |event| {
+            if let EvalEvent::ImportLoaded { cache_hit, .. } = event {
+                recorded.borrow_mut().push(cache_hit);
+            }
+        })
+        .build();
+
+    let code = r#"[import "data:ryan,1", import "data:ryan,1"]"#;
+    assert_eq!(
+        ryan::from_str_with_env::<Vec<i64>>(&env, code).unwrap(),
+        [1, 1]
+    );
+    assert_eq!(*cache_hits.borrow(), [false, true]);
+}
+
+#[test]
+fn stdin_can_only_be_imported_once() {
+    let loader = NoImport.r#override("stdin:".to_owned(), "piped".to_owned());
+    let env = Environment::builder().import_loader(loader).build();
+
+    let imported = ryan::from_str_with_env::<String>(&env, r#"import "stdin:" as text"#);
+    assert_eq!(imported.unwrap(), "piped");
+
+    let err = ryan::from_str_with_env::<String>(&env, r#"import "stdin:" as text or "x""#)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("The standard input can only be imported once"));
+}
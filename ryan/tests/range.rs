@@ -0,0 +1,111 @@
+//! The built-in `range`, with and without a step, and its cap on the number of elements.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+use ryan::Environment;
+
+fn eval(env: &Environment, code: &str) -> Result<Vec<i64>, String> {
+    ryan::from_str_with_env(env, code).map_err(|err| err.to_string())
+}
+
+fn range(code: &str) -> Result<Vec<i64>, String> {
+    eval(&Environment::new(None), code)
+}
+
+#[test]
+fn ranges_count_up_by_one_by_default() {
+    assert_eq!(range("range [0, 4]").unwrap(), [0, 1, 2, 3]);
+    assert_eq!(range("range [-2, 1]").unwrap(), [-2, -1, 0]);
+    assert_eq!(range("range [3, 3]").unwrap(), [0; 0]);
+}
+
+#[test]
+fn ranges_count_by_the_step() {
+    assert_eq!(range("range [0, 10, 3]").unwrap(), [0, 3, 6, 9]);
+    assert_eq!(range("range [0, 9, 3]").unwrap(), [0, 3, 6]);
+    assert_eq!(range("range [0, 4, 1]").unwrap(), [0, 1, 2, 3]);
+    assert_eq!(range("range [0, 1, 100]").unwrap(), [0]);
+}
+
+#[test]
+fn negative_steps_count_down() {
+    assert_eq!(range("range [3, 0, -1]").unwrap(), [3, 2, 1]);
+    assert_eq!(range("range [10, -1, -5]").unwrap(), [10, 5, 0]);
+    assert_eq!(range("range [0, 3, -1]").unwrap(), [0; 0]);
+    assert_eq!(range("range [3, 0, 1]").unwrap(), [0; 0]);
+}
+
+#[test]
+fn ranges_near_the_integer_limits_do_not_overflow() {
+    let max = i64::MAX;
+    let min = i64::MIN;
+
+    assert_eq!(
+        range(&format!("range [{}, {max}, 2]", max - 3)).unwrap(),
+        [max - 3, max - 1]
+    );
+    assert_eq!(
+        range(&format!("range [{}, {min}, -2]", min + 3)).unwrap(),
+        [min + 3, min + 1]
+    );
+    assert_eq!(
+        range(&format!("range [{max}, {min}, {min}]")).unwrap(),
+        [max, -1]
+    );
+}
+
+#[test]
+fn zero_steps_are_errors() {
+    let err = range("range [0, 10, 0]").unwrap_err();
+    assert!(err.contains("cannot be zero"), "{err}");
+}
+
+#[test]
+fn counting_down_needs_a_negative_step() {
+    let err = range("range [10, 0]").unwrap_err();
+    assert!(err.contains("counts down"), "{err}");
+    assert!(err.contains("`range [10, 0, -1]`"), "{err}");
+}
+
+#[test]
+fn ranges_must_be_made_of_integers() {
+    let err = range("range [0, 1.5]").unwrap_err();
+    assert!(err.contains("must be made of integers"), "{err}");
+
+    let err = range("range [0, 10, 2, 1]").unwrap_err();
+    assert!(err.contains("expected list with 3 elements"), "{err}");
+}
+
+#[test]
+fn ranges_are_capped() {
+    let err = range("range [0, 100000000]").unwrap_err();
+    assert!(
+        err.contains("has 100000000 elements, more than the maximum of 16777216"),
+        "{err}"
+    );
+
+    let err = range(&format!("range [{}, {}]", i64::MIN, i64::MAX)).unwrap_err();
+    assert!(err.contains("has 18446744073709551615 elements"), "{err}");
+}
+
+#[test]
+fn the_cap_is_configurable() {
+    let env = Environment::builder().max_range_size(5).build();
+
+    assert_eq!(eval(&env, "range [0, 5]").unwrap(), [0, 1, 2, 3, 4]);
+    assert_eq!(
+        eval(&env, "range [0, 100, 20]").unwrap(),
+        [0, 20, 40, 60, 80]
+    );
+
+    let err = eval(&env, "range [0, 6]").unwrap_err();
+    assert!(
+        err.contains("Range `[0, 6]` has 6 elements, more than the maximum of 5"),
+        "{err}"
+    );
+
+    let env = Environment::builder().max_range_size(1 << 30).build();
+    assert_eq!(
+        eval(&env, "[len (range [0, 20000000])]").unwrap(),
+        [20000000]
+    );
+}
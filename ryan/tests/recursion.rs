@@ -0,0 +1,76 @@
+//! Ryan does not support recursion. A pattern match referencing itself is an error where
+//! it is defined and a pattern match calling an earlier definition of itself through other
+//! pattern matches is an error when it is applied.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+mod common;
+
+use ryan::parser::{EvalError, EvalErrorKind};
+
+use common::eval;
+
+fn eval_error(code: &str) -> EvalError {
+    let block = ryan::parser::parse(code).unwrap();
+    ryan::parser::eval(ryan::Environment::new(None), &block).unwrap_err()
+}
+
+#[test]
+fn direct_self_reference_fails_at_the_definition() {
+    for code in [
+        "let foo x = [foo x]; 1",
+        "let foo [1] = 1; let foo x = [foo [x]]; 1",
+        "let foo x if foo == 1 = x; 1",
+        r"let foo x = (\y => foo y) x; 1",
+    ] {
+        let err = eval_error(code);
+
+        assert!(
+            matches!(err.kind(), EvalErrorKind::Recursion { pattern, through }
+                if &**pattern == "foo" && through.is_empty()),
+            "{code}: {err}"
+        );
+        assert_eq!(
+            err.kind().to_string(),
+            "Ryan does not support recursion: pattern `foo` cannot reference itself. \
+            Consider a comprehension over a `range` instead",
+            "{code}"
+        );
+        assert_eq!(err.kind().name(), "recursion");
+    }
+}
+
+#[test]
+fn other_bindings_with_the_same_name_are_not_recursion() {
+    for (code, expected) in [
+        ("let f f = f + 1; f 1", "2"),
+        ("let f x = let f = x * 2; f; f 3", "6"),
+        ("let f x = [y for f in [x] for y in [f]]; f 3", "[3]"),
+        ("let f [1] = 1; let g x = [f [x]]; g 1", "[1]"),
+    ] {
+        assert_eq!(eval(code).to_string(), expected, "{code}");
+    }
+}
+
+#[test]
+fn higher_order_pattern_matches_are_not_recursion() {
+    let code = r"let twice f = \x => f (f x); let succ x = x + 1; (twice (twice succ)) 0";
+    assert_eq!(eval(code).to_string(), "4");
+
+    let code = r"let a 0 = 0; let b x = a x; let a x = b (x - 1); [a 0, b 0]";
+    assert_eq!(eval(code).to_string(), "[0, 0]");
+}
+
+#[test]
+fn calling_an_earlier_definition_through_others_fails_when_applied() {
+    let err = eval_error("let a 0 = 0; let b x = a x; let c x = b x; let a x = c (x - 1); a 3");
+
+    assert!(
+        matches!(err.kind(), EvalErrorKind::Recursion { pattern, through }
+        if &**pattern == "a" && through.iter().map(|name| &**name).eq(["c", "b"]))
+    );
+    assert_eq!(
+        err.kind().to_string(),
+        "Ryan does not support recursion: pattern `a` calls `c`, which calls `b`, which calls \
+        an earlier definition of `a`. Give the new rules of `a` a pattern of their own instead"
+    );
+}
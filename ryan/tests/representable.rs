@@ -0,0 +1,167 @@
+#![cfg(feature = "parse")]
+
+mod common;
+
+use ryan::parser::{PathSeg, Value};
+
+use common::eval;
+
+fn check(source: &str) -> String {
+    eval(source).check_representable().unwrap_err().to_string()
+}
+
+#[test]
+fn json_values_are_representable() {
+    let value = eval(r#"{ a: [1, 2.5, "x", null, true], b: { c: [] } }"#);
+    assert!(value.check_representable().is_ok());
+}
+
+#[test]
+fn pattern_matches_are_not_representable() {
+    assert_eq!(
+        check("let make_svc name = { name }; { helpers: { make_svc } }"),
+        "The result contains the unapplied pattern `make_svc` at .helpers.make_svc. Did you forget to apply it?"
+    );
+}
+
+#[test]
+fn native_pattern_matches_are_not_representable() {
+    assert_eq!(
+        check("{ count: len }"),
+        "The result contains the unapplied pattern `len` at .count. Did you forget to apply it?"
+    );
+}
+
+#[test]
+fn types_are_not_representable() {
+    assert_eq!(
+        check("type Port = int; { types: [Port] }"),
+        "The result contains the type `int` at .types[0]"
+    );
+}
+
+#[test]
+fn bytes_are_not_representable() {
+    assert_eq!(
+        check(r#"{ logo: import "data:text,abc" as bytes }"#),
+        "The result contains bytes at .logo. Encode them first, e.g., with `base64_encode`"
+    );
+}
+
+#[test]
+fn non_finite_floats_are_not_representable() {
+    assert_eq!(
+        check("[1.0 / 0.0]"),
+        "The result contains the number `inf` at [0]"
+    );
+    assert_eq!(
+        check("{ ratio: 0.0 / 0.0 }"),
+        "The result contains the number `NaN` at .ratio"
+    );
+}
+
+#[test]
+fn the_first_offending_value_is_reported() {
+    let err = eval(r#"{ "a b": [0, { c: len }], d: len }"#)
+        .check_representable()
+        .unwrap_err();
+
+    assert_eq!(
+        err.path(),
+        [
+            PathSeg::Key("a b".into()),
+            PathSeg::Index(1),
+            PathSeg::Key("c".into())
+        ]
+    );
+    assert_eq!(
+        err.to_string(),
+        r#"The result contains the unapplied pattern `len` at ["a b"][1].c. Did you forget to apply it?"#
+    );
+}
+
+#[test]
+fn the_root_is_reported_by_name() {
+    assert_eq!(
+        check("len"),
+        "The result contains the unapplied pattern `len` at the root. Did you forget to apply it?"
+    );
+}
+
+#[test]
+fn decoding_reports_unrepresentable_values() {
+    let err = ryan::from_str::<serde_json::Value>("let f x = x; { f }").unwrap_err();
+    assert!(matches!(err, ryan::Error::NotRepresentable(_)), "{err:?}");
+
+    // Values that decode fine are not affected, even if JSON cannot represent them:
+    let ratio = ryan::from_str::<f64>("1.0 / 0.0").unwrap();
+    assert_eq!(ratio, f64::INFINITY);
+    let bytes = ryan::from_str::<Vec<u8>>(r#"import "data:text,abc" as bytes"#).unwrap();
+    assert_eq!(bytes, b"abc");
+}
+
+#[test]
+fn data_may_have_bytes_and_non_finite_floats() {
+    let value = eval(r#"{ logo: import "data:text,abc" as bytes, ratio: 1.0 / 0.0 }"#);
+    assert!(value.check_data().is_ok());
+
+    let err = eval("let make_service name = { name }; { services: { api: make_service } }")
+        .check_data()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "The result contains the unapplied pattern `make_service` at .services.api. Did you \
+        forget to apply it?"
+    );
+
+    let err = eval("type Port = int; { bytes: import \"data:text,abc\" as bytes, port: Port }")
+        .check_data()
+        .unwrap_err();
+    assert_eq!(err.path(), [PathSeg::Key("port".into())]);
+}
+
+#[test]
+fn stripping_removes_what_is_not_representable() {
+    let value = eval(
+        r#"
+        let make_svc name = { name };
+        type Port = int;
+        {
+            services: [make_svc "api", make_svc, { port: Port, ratio: 0.0 / 0.0 }],
+            make_svc,
+            logo: import "data:text,abc" as bytes,
+            replicas: 3,
+        }
+        "#,
+    );
+    let (stripped, removed) = value.strip_unrepresentable();
+
+    assert_eq!(
+        stripped.to_string(),
+        r#"{services: [{name: "api"}, {}], replicas: 3}"#
+    );
+    assert!(stripped.check_representable().is_ok());
+    assert_eq!(
+        removed.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        [
+            "Removed the unapplied pattern `make_svc` at .services[1]",
+            "Removed the type `int` at .services[2].port",
+            "Removed the number `NaN` at .services[2].ratio",
+            "Removed the unapplied pattern `make_svc` at .make_svc",
+            "Removed bytes at .logo",
+        ]
+    );
+}
+
+#[test]
+fn stripping_representable_values_changes_nothing() {
+    let value = eval(r#"{ a: [1, 2.5, "x", null, true], b: { c: [] } }"#);
+    let (stripped, removed) = value.strip_unrepresentable();
+
+    assert_eq!(stripped, value);
+    assert!(removed.is_empty());
+
+    let (stripped, removed) = eval("len").strip_unrepresentable();
+    assert_eq!(stripped, Value::Null);
+    assert_eq!(removed[0].path, []);
+}
@@ -0,0 +1,90 @@
+//! Importing only some of the keys of a module, with `only` or by destructuring.
+#![cfg(feature = "parse")]
+
+use ryan::environment::{ImportLoader, NoImport};
+use ryan::parser::Context;
+use ryan::Environment;
+
+const LIB: &str = "{ retry: 3, backoff: 1.5, jitter: true }";
+
+fn env() -> Environment {
+    let loader = NoImport
+        .r#override("lib.ryan".to_owned(), LIB.to_owned())
+        .r#override("list.ryan".to_owned(), "[1, 2]".to_owned());
+    Environment::builder().import_loader(loader).build()
+}
+
+fn eval(code: &str) -> Result<serde_json::Value, String> {
+    ryan::from_str_with_env(&env(), code).map_err(|err| err.to_string())
+}
+
+#[test]
+fn only_keeps_the_listed_keys_in_order() {
+    let value = eval(r#"import "lib.ryan" only ["jitter", "retry"]"#).unwrap();
+
+    assert_eq!(value, serde_json::json!({ "jitter": true, "retry": 3 }));
+    assert_eq!(value.as_object().unwrap().keys().count(), 2);
+
+    let value = eval(
+        r#"let { retry, backoff } = import "lib.ryan" only ["retry", "backoff"]; retry * backoff"#,
+    )
+    .unwrap();
+    assert_eq!(value, serde_json::json!(4.5));
+}
+
+#[test]
+fn missing_keys_list_the_keys_of_the_module() {
+    let err = eval(r#"import "lib.ryan" only ["retry", "timeout", "limit"]"#).unwrap_err();
+
+    assert!(
+        err.contains(
+            "Module \"lib.ryan\" has no keys `timeout`, `limit`; \
+            its keys are `retry`, `backoff`, `jitter`"
+        ),
+        "{err}"
+    );
+}
+
+#[test]
+fn missing_keys_are_not_recovered_by_defaults() {
+    let err = eval(r#"import "lib.ryan" only ["timeout"] or {}"#).unwrap_err();
+
+    assert!(err.contains("has no key `timeout`"), "{err}");
+}
+
+#[test]
+fn only_needs_a_dictionary() {
+    let err = eval(r#"import "list.ryan" only ["a"]"#).unwrap_err();
+
+    assert!(
+        err.contains("Module \"list.ryan\" must be a dictionary to import only some of its keys"),
+        "{err}"
+    );
+
+    let err = eval(r#"import "lib.ryan" as text only ["a"]"#).unwrap_err();
+    assert!(err.contains("not as text"), "{err}");
+}
+
+#[test]
+fn destructuring_an_import_names_the_module() {
+    let code = r#"let { retry, timeout } = import "lib.ryan"; retry"#;
+    let Err(ryan::Error::Eval(err)) = ryan::from_str_with_env::<i64>(&env(), code) else {
+        panic!("the binding must fail");
+    };
+
+    assert!(err.kind().to_string().contains("key timeout"), "{err}");
+    assert!(
+        err.context().iter().any(
+            |context| matches!(context, Context::LoadingImport { path, .. } if &**path == "lib.ryan")
+        ),
+        "{err}"
+    );
+}
+
+#[test]
+fn only_is_printed_back() {
+    let code = r#"import "lib.ryan" only ["retry", "backoff"] or {}"#;
+    let block = ryan::parser::parse(code).unwrap();
+
+    assert_eq!(block.to_string(), code);
+}
@@ -0,0 +1,100 @@
+//! Values summarized within bounds with `Value::summary`, as in error messages.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+mod common;
+
+use ryan::environment::Environment;
+use ryan::parser::{self, EvalErrorKind};
+
+use common::eval;
+
+fn eval_err(source: &str) -> ryan::parser::EvalError {
+    let block = parser::parse(source).unwrap();
+    parser::eval(Environment::new(None), &block).unwrap_err()
+}
+
+#[test]
+fn small_values_are_unchanged() {
+    for code in [
+        "null",
+        "1.5",
+        r#""text""#,
+        "[]",
+        "{}",
+        r#"{ a: 1, b: [2, { c: "x" }], d: {} }"#,
+        "[[[]]]",
+    ] {
+        let value = eval(code);
+        assert_eq!(value.summary(3, 8, 64).to_string(), value.to_string());
+    }
+}
+
+#[test]
+fn deep_values_are_cut() {
+    let value = eval("{ a: [[1]], b: [], c: { d: {} } }");
+
+    assert_eq!(value.summary(0, 8, 64).to_string(), "{...}");
+    assert_eq!(
+        value.summary(1, 8, 64).to_string(),
+        "{a: [...], b: [], c: {...}}"
+    );
+    assert_eq!(
+        value.summary(2, 8, 64).to_string(),
+        "{a: [[...]], b: [], c: {d: {}}}"
+    );
+}
+
+#[test]
+fn long_collections_are_cut() {
+    let value = eval("range [0, 10]");
+    assert_eq!(value.summary(3, 3, 64).to_string(), "[0, 1, 2, …7 more]");
+    assert_eq!(value.summary(3, 0, 64).to_string(), "[…10 more]");
+    assert_eq!(value.summary(3, 10, 64).to_string(), value.to_string());
+
+    let value = eval("{ x: x * x for x in range [0, 5] }");
+    assert_eq!(value.summary(3, 2, 64).to_string(), "{0: 0, 1: 1, …3 more}");
+}
+
+#[test]
+fn long_text_is_cut() {
+    let value = eval(r#"["café au lait", "tea"]"#);
+    assert_eq!(value.summary(3, 8, 4).to_string(), r#"["café…", "tea"]"#);
+
+    let value = eval(r#""a\nb\"c""#);
+    assert_eq!(value.summary(3, 8, 4).to_string(), r#""a\nb\"…""#);
+    assert_eq!(value.summary(3, 8, 5).to_string(), value.to_string());
+}
+
+#[test]
+fn errors_summarize_the_values_in_them() {
+    let err = eval_err("let config = { a: range [0, 1000], b: 2 }; config.c");
+    let message = err.kind().to_string();
+
+    assert!(
+        message
+            .starts_with("Key `c` not present in `{a: [0, 1, 2, 3, 4, 5, 6, 7, …992 more], b: 2}`"),
+        "{message}"
+    );
+
+    // The whole value is still there for tools that want it:
+    let EvalErrorKind::KeyMissing { value, .. } = err.kind() else {
+        panic!("expected a missing key, got {err}");
+    };
+    assert_eq!(
+        value.to_string(),
+        eval("{ a: range [0, 1000], b: 2 }").to_string()
+    );
+}
+
+#[test]
+fn pattern_errors_summarize_the_values_in_them() {
+    let long = "x".repeat(1000);
+    let err = eval_err(&format!(r#"let f {{ a: int }} = a; f {{ a: "{long}" }}"#));
+    let message = err.kind().to_string();
+
+    assert!(
+        message.contains(&format!(r#""{}…""#, "x".repeat(64))),
+        "{message}"
+    );
+    assert!(message.len() < 200, "{message}");
+}
@@ -0,0 +1,126 @@
+//! Escaping and unescaping strings with `ryan::text`.
+
+use ryan::text::{escape, unescape, UnescapeError};
+
+/// A small xorshift generator, so that the property tests are the same on every run.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A character biased towards the ones that are escaped or are close to the
+    /// surrogate range.
+    fn char(&mut self) -> char {
+        let code = match self.next() % 6 {
+            0 => self.next() % 0x80,
+            1 => *[0x22, 0x5c, 0x2f, 0x08, 0x0c, 0x7f, 0x85, 0x2028]
+                .get((self.next() % 8) as usize)
+                .unwrap(),
+            2 => 0xd7f0 + self.next() % 0x10,
+            3 => 0xe000 + self.next() % 0x10,
+            4 => 0x10000 + self.next() % 0x100000,
+            _ => self.next() % 0x10000,
+        };
+
+        char::from_u32(code as u32).unwrap_or('\u{fffd}')
+    }
+
+    fn string(&mut self) -> String {
+        let len = self.next() % 24;
+        (0..len).map(|_| self.char()).collect()
+    }
+}
+
+#[test]
+fn escaped_strings_unescape_to_themselves() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+
+    for _ in 0..20_000 {
+        let string = rng.string();
+        assert_eq!(
+            unescape(&escape(&string)).as_ref(),
+            Ok(&string),
+            "{string:?}"
+        );
+    }
+}
+
+#[test]
+fn unescaping_arbitrary_input_does_not_panic() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    let pieces = ["\"", "\\", "\\u", "\\ud83d", "\\ude00", "d8", "x", "\\n"];
+
+    for _ in 0..20_000 {
+        let mut input = String::new();
+        for _ in 0..rng.next() % 8 {
+            if rng.next() & 1 == 0 {
+                input.push_str(pieces[(rng.next() % pieces.len() as u64) as usize]);
+            } else {
+                input.push(rng.char());
+            }
+        }
+
+        let _ = unescape(&input);
+    }
+}
+
+#[test]
+fn surrogate_pairs_are_characters_outside_the_basic_plane() {
+    assert_eq!(unescape(r#""\ud83d\ude00""#).unwrap(), "😀");
+    assert_eq!(unescape(r#""\uD834\uDD1E!""#).unwrap(), "𝄞!");
+    assert_eq!(unescape(r#""\u00e9""#).unwrap(), "é");
+
+    assert_eq!(
+        unescape(r#""\ud83d""#),
+        Err(UnescapeError::LoneSurrogate(0xd83d))
+    );
+    assert_eq!(
+        unescape(r#""\ud83dx""#),
+        Err(UnescapeError::LoneSurrogate(0xd83d))
+    );
+    assert_eq!(
+        unescape(r#""\ud83d\u0041""#),
+        Err(UnescapeError::LoneSurrogate(0xd83d))
+    );
+    assert_eq!(
+        unescape(r#""\ude00""#),
+        Err(UnescapeError::LoneSurrogate(0xde00))
+    );
+}
+
+#[test]
+fn only_json_escapes_are_accepted() {
+    assert_eq!(
+        unescape(r#""\x41""#),
+        Err(UnescapeError::UnknownEscape('x'))
+    );
+    assert_eq!(unescape("'a'"), Err(UnescapeError::NoStartingQuote));
+    assert_eq!(unescape(r#""a"b"#), Err(UnescapeError::SpuriousTail));
+    assert_eq!(unescape(r#""a\""#), Err(UnescapeError::NoEndingQuote));
+    assert_eq!(unescape(r#""\u12g4""#), Err(UnescapeError::NotADigit('g')));
+}
+
+#[cfg(feature = "parse")]
+#[test]
+fn ryan_code_reads_surrogate_pairs_everywhere() {
+    let value: serde_json::Value = ryan::from_str(
+        r#"
+            let smile = "\ud83d\ude00";
+            { "key \ud83d\ude00": smile, "\u00e9": 1 }
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(value, serde_json::json!({ "key 😀": "😀", "é": 1 }));
+
+    let err = ryan::from_str::<String>(r#""\ud83d""#).unwrap_err();
+    assert!(
+        err.to_string().contains("half of a surrogate pair"),
+        "{err}"
+    );
+}
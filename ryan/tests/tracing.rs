@@ -0,0 +1,108 @@
+//! Tracing the evaluation of a program step by step with `EnvironmentBuilder::trace`.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+use std::{cell::RefCell, rc::Rc};
+
+use ryan::environment::{Branch, EvalEvent, ImportLoader, NoImport};
+use ryan::Environment;
+
+/// Evaluates a program, returning every event sent to the hook.
+fn record(trace: bool, code: &str) -> Vec<EvalEvent> {
+    let events = Rc::new(RefCell::new(vec![]));
+    let recorded = events.clone();
+    let loader = NoImport.r#override("limits.ryan".to_owned(), "{ max: 3 }".to_owned());
+    let env = Environment::builder()
+        .import_loader(loader)
+        .on_event(move |event| recorded.borrow_mut().push(event))
+        .trace(trace)
+        .build();
+
+    ryan::from_str_with_env::<serde_json::Value>(&env, code).unwrap();
+    events.take()
+}
+
+fn binding(name: &str, value_summary: &str) -> EvalEvent {
+    EvalEvent::BindingEvaluated {
+        name: name.into(),
+        value_summary: value_summary.into(),
+    }
+}
+
+fn rule(name: &str, rule_index: usize, matched: bool) -> EvalEvent {
+    EvalEvent::PatternRuleTried {
+        name: name.into(),
+        rule_index,
+        matched,
+    }
+}
+
+const PROGRAM: &str = r#"
+    let { max } = import "limits.ryan";
+    let clamp 0 = 0;
+    let clamp x = if x > max then max else x;
+    let [a, b] = [clamp 5, clamp 2];
+    a + b
+"#;
+
+#[test]
+fn events_follow_the_evaluation_in_order() {
+    let events = record(true, PROGRAM);
+
+    assert_eq!(
+        events,
+        [
+            EvalEvent::ImportStarted {
+                path: "limits.ryan".into(),
+                format: ryan::parser::Format::Ryan,
+            },
+            EvalEvent::ImportLoaded {
+                path: "limits.ryan".into(),
+                format: ryan::parser::Format::Ryan,
+                cache_hit: false,
+            },
+            binding("max", "3"),
+            rule("clamp", 0, false),
+            rule("clamp", 1, true),
+            EvalEvent::ConditionalTaken {
+                branch: Branch::Then,
+            },
+            rule("clamp", 0, false),
+            rule("clamp", 1, true),
+            EvalEvent::ConditionalTaken {
+                branch: Branch::Else,
+            },
+            binding("a", "3"),
+            binding("b", "2"),
+        ]
+    );
+}
+
+#[test]
+fn tracing_is_off_by_default() {
+    let events = record(false, PROGRAM);
+
+    assert_eq!(events.len(), 2, "{events:?}");
+    assert!(events.iter().all(|event| matches!(
+        event,
+        EvalEvent::ImportStarted { .. } | EvalEvent::ImportLoaded { .. }
+    )));
+}
+
+#[test]
+fn failed_guards_are_rules_that_did_not_match() {
+    let events = record(true, "let f x if x > 0 = x; let f x = 0; f (-1)");
+
+    assert_eq!(events, [rule("f", 0, false), rule("f", 1, true)]);
+}
+
+#[test]
+fn long_values_are_summarized() {
+    let events = record(true, "let xs = range [0, 1000]; len xs");
+    let [EvalEvent::BindingEvaluated { value_summary, .. }] = &events[..] else {
+        panic!("expected a single binding, got {events:?}");
+    };
+
+    assert!(value_summary.starts_with("[0, 1, 2, 3"), "{value_summary}");
+    assert!(value_summary.ends_with("..."), "{value_summary}");
+    assert_eq!(value_summary.chars().count(), 83);
+}
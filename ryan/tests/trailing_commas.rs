@@ -0,0 +1,158 @@
+//! Every comma-separated construct accepts a trailing comma, and comments may go
+//! anywhere whitespace may. Each case is written with `{,}` where a trailing comma may
+//! go and `{c}` where a comment may go, and every combination must give the same value.
+#![cfg(all(feature = "parse", feature = "builtins"))]
+
+use ryan::environment::{ImportLoader, NoImport};
+use ryan::Environment;
+
+const CASES: &[(&str, &str, &str)] = &[
+    ("list", "[{c}1,{c} 2{,}{c}]", "[1, 2]"),
+    ("dictionary", "{{c} a: 1,{c} b: 2{,}{c} }", "{a: 1, b: 2}"),
+    ("path", r#"let d = { a: [5] }; d[{c}"a",{c} 0{,}{c}]"#, "5"),
+    (
+        "optional path",
+        r#"let d = { a: [5] }; d?[{c}"a", 0{,}{c}]"#,
+        "5",
+    ),
+    (
+        "list pattern",
+        "let [{c}a,{c} b{,}{c}] = [1, 2]; a + b",
+        "3",
+    ),
+    (
+        "head pattern",
+        "let [{c}a,{c} ...rest{,}{c}] = [1, 2, 3]; rest",
+        "[2, 3]",
+    ),
+    (
+        "anonymous head pattern",
+        "let [{c}a,{c} ..{,}{c}] = [1, 2, 3]; a",
+        "1",
+    ),
+    (
+        "tail pattern",
+        "let [{c}...init,{c} z{,}{c}] = [1, 2, 3]; init",
+        "[1, 2]",
+    ),
+    (
+        "dictionary pattern",
+        "let {{c} a,{c} b{,}{c} } = { a: 1, b: 2 }; a + b",
+        "3",
+    ),
+    (
+        "open dictionary pattern",
+        "let {{c} a,{c} ..{,}{c} } = { a: 1, b: 2 }; a",
+        "1",
+    ),
+    (
+        "dictionary pattern with rest",
+        "let {{c} a,{c} ...others{,}{c} } = { a: 1, b: 2 }; others",
+        "{b: 2}",
+    ),
+    (
+        "dictionary pattern with defaults",
+        "let {{c} a ? 1,{c} b: [x, y{,}]{,}{c} } = { b: [2, 3] }; a + x + y",
+        "6",
+    ),
+    (
+        "pattern match rule",
+        "let f [{c}a,{c} b{,}{c}] {c}= a * b; f [2, 3]",
+        "6",
+    ),
+    (
+        "alternative patterns",
+        "let f [{c}1{,}] |{c} [{c}2{,}] = true; let f _ = false; [f [1], f [2], f [3]]",
+        "[true, true, false]",
+    ),
+    ("lambda", r"(\[{c}a,{c} b{,}{c}] => a + b) [1, 2]", "3"),
+    (
+        "match arms",
+        r#"match 2 {{c} 1 => "one",{c} _ => "other"{,}{c} }"#,
+        r#""other""#,
+    ),
+    (
+        "tuple type",
+        r#"type Pair = ({c}int,{c} text{,}{c}); [1, "a"] is Pair"#,
+        "true",
+    ),
+    (
+        "record type",
+        r#"type R = {{c} a: int,{c} b: text{,}{c} }; { a: 1, b: "x" } is R"#,
+        "true",
+    ),
+    (
+        "open record type",
+        "type R = {{c} a: int,{c} ..{,}{c} }; { a: 1, b: 2 } is R",
+        "true",
+    ),
+    (
+        "typed pattern",
+        "let x: ({c}int,{c} [{c}int{c}]{,}{c}) = [1, [2]]; x",
+        "[1, [2]]",
+    ),
+    (
+        "list comprehension",
+        "[{c}x * y {c}for x,{c} y{,} in {c}[1, 2],{c} [3, 4]{,}{c}]",
+        "[3, 8]",
+    ),
+    (
+        "dictionary comprehension",
+        "{{c} x: y for x,{c} y{,} in [1, 2],{c} [3, 4]{,}{c} }",
+        "{1: 3, 2: 4}",
+    ),
+    (
+        "import only",
+        r#"import "lib.ryan" only [{c}"a",{c} "b"{,}{c}]"#,
+        "{a: 1, b: 2}",
+    ),
+    (
+        "type definition",
+        "type Port = {c}int{c}; let p: {c}Port = 8080; p",
+        "8080",
+    ),
+];
+
+fn eval(code: &str) -> Result<String, String> {
+    let loader = NoImport.r#override("lib.ryan".to_owned(), "{ a: 1, b: 2, c: 3 }".to_owned());
+    let env = Environment::builder().import_loader(loader).build();
+    let block = ryan::parser::parse(code).map_err(|err| err.to_string())?;
+    let value = ryan::parser::eval(env, &block).map_err(|err| err.to_string())?;
+
+    Ok(value.to_string())
+}
+
+#[test]
+fn trailing_commas_and_comments_are_accepted_everywhere() {
+    let mut failures = vec![];
+
+    for (name, template, expected) in CASES {
+        for trailing_comma in ["", ","] {
+            for comment in [" ", " // a comment, with a comma\n"] {
+                let code = template
+                    .replace("{,}", trailing_comma)
+                    .replace("{c}", comment);
+                match eval(&code) {
+                    Ok(value) if value == *expected => {}
+                    Ok(value) => failures.push(format!("{name}: {code:?} gave {value}")),
+                    Err(err) => failures.push(format!("{name}: {code:?} failed: {err}")),
+                }
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn comments_may_end_the_input() {
+    assert_eq!(eval("1 // no new line after this").unwrap(), "1");
+    assert_eq!(eval("let x = 1;\nx //").unwrap(), "1");
+}
+
+#[test]
+fn lone_commas_are_still_errors() {
+    for code in ["[,]", "{,}", "let [,] = []; 1", "type T = (,); 1"] {
+        assert!(ryan::parser::parse(code).is_err(), "{code:?}");
+    }
+}
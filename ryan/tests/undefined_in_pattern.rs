@@ -0,0 +1,81 @@
+//! Undefined variables in the definition of a pattern match, which are found when the
+//! pattern match captures the variables it uses.
+#![cfg(feature = "parse")]
+
+use ryan::parser::{Context, EvalError, EvalErrorKind};
+
+fn eval_error(source: &str) -> EvalError {
+    match ryan::from_str::<serde_json::Value>(source) {
+        Err(ryan::Error::Eval(err)) => err,
+        other => panic!("expected an evaluation error, got {other:?}"),
+    }
+}
+
+#[test]
+fn reports_every_undefined_variable_in_the_body() {
+    let err = eval_error("let f x = { a: x + offset, b: scale * offset }; f 1");
+
+    assert!(matches!(
+        err.kind(),
+        EvalErrorKind::UndefinedInPattern { pattern: Some(pattern), names }
+            if &**pattern == "f" && names.iter().map(|name| &**name).eq(["offset", "scale"])
+    ));
+    assert_eq!(
+        err.kind().to_string(),
+        "Variables `offset` and `scale` are undefined, but they are used in the definition \
+        of pattern `f`. Bind them before defining `f`"
+    );
+    assert_eq!(err.kind().name(), "undefined_variable");
+}
+
+#[test]
+fn fails_where_the_pattern_is_defined() {
+    let err = eval_error("let f x = x + offset; let y = 1; [y]");
+
+    assert_eq!(
+        err.kind().to_string(),
+        "Variable `offset` is undefined, but it is used in the definition of pattern `f`. \
+        Bind it before defining `f`"
+    );
+    assert_eq!(
+        err.context().last(),
+        Some(&Context::EvaluatingBinding("f".into()))
+    );
+}
+
+#[test]
+fn reports_undefined_variables_in_guards_and_types() {
+    let err = eval_error("let f x: Port if x > minimum = x; f 1");
+
+    assert_eq!(
+        err.kind().to_string(),
+        "Variables `Port` and `minimum` are undefined, but they are used in the definition \
+        of pattern `f`. Bind them before defining `f`"
+    );
+}
+
+#[test]
+fn reports_undefined_variables_in_anonymous_patterns() {
+    let err = eval_error(r"let double = \x => x * factor; double 1");
+
+    assert_eq!(
+        err.kind().to_string(),
+        "Variable `factor` is undefined, but it is used in the definition of an anonymous \
+        pattern"
+    );
+}
+
+#[test]
+fn variables_provided_by_the_pattern_are_not_undefined() {
+    let value: serde_json::Value = ryan::from_str(
+        r#"
+        let f [x, { y, "z": w }] = x + y + w;
+        let g { a ? 1, .. } = let b = a * 2; let add = \c => c + b; [a, b, add 3];
+        let h x = [y + x for y in [1, 2]];
+        [f [1, { y: 2, z: 3 }], g {}, h 10]
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(value, serde_json::json!([6, [1, 2, 5], [11, 12]]));
+}
@@ -0,0 +1,141 @@
+//! Sharing bindings between modules with `use "...";`.
+#![cfg(feature = "parse")]
+
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+
+use ryan::environment::{EvalEvent, ImportLoader, NoImport};
+use ryan::Environment;
+
+use common::fixture;
+
+/// Evaluates a program whose modules are all in memory.
+fn eval(modules: &[(&str, &str)], code: &str) -> Result<serde_json::Value, String> {
+    let loader = NoImport.override_many(
+        modules
+            .iter()
+            .map(|(path, code)| (path.to_string(), Some(code.to_string()))),
+    );
+    let env = Environment::builder().import_loader(loader).build();
+    ryan::from_str_with_env(&env, code).map_err(|err| err.to_string())
+}
+
+#[test]
+fn a_prelude_is_shared_by_two_files() {
+    let dir = fixture(
+        "shared",
+        &[
+            (
+                "lib/prelude.ryan",
+                r#"
+                    let double x = x * 2;
+                    let greeting = import "greeting.txt" as text;
+                    type Port = int;
+                "#,
+            ),
+            ("lib/greeting.txt", "hello"),
+            ("a.ryan", r#"use "lib/prelude.ryan"; double 21"#),
+            (
+                "b.ryan",
+                r#"use "lib/prelude.ryan"; let port: Port = 80; [greeting, double port]"#,
+            ),
+        ],
+    );
+
+    let a: i64 = ryan::from_path(dir.join("a.ryan")).unwrap();
+    let b: (String, i64) = ryan::from_path(dir.join("b.ryan")).unwrap();
+
+    assert_eq!(a, 42);
+    // The import in the prelude is relative to the prelude:
+    assert_eq!(b, ("hello".to_owned(), 160));
+}
+
+#[test]
+fn using_a_module_in_a_cycle_fails() {
+    let dir = fixture(
+        "cycle",
+        &[
+            ("a.ryan", r#"use "b.ryan"; let a = 1;"#),
+            ("b.ryan", r#"use "a.ryan"; let b = 2;"#),
+        ],
+    );
+
+    let err = ryan::from_path::<_, serde_json::Value>(dir.join("a.ryan")).unwrap_err();
+
+    assert!(
+        err.to_string().contains("Circular import detected"),
+        "{err}"
+    );
+}
+
+#[test]
+fn used_bindings_are_shadowed_as_usual() {
+    let modules = [("prelude.ryan", "let x = 1; let y = 2;")];
+
+    let value = eval(&modules, r#"use "prelude.ryan"; let x = 10; [x, y]"#).unwrap();
+    assert_eq!(value, serde_json::json!([10, 2]));
+
+    let value = eval(&modules, r#"let x = 10; use "prelude.ryan"; x"#).unwrap();
+    assert_eq!(value, serde_json::json!(1));
+}
+
+#[test]
+fn used_modules_do_not_see_the_variables_of_the_user() {
+    let modules = [("prelude.ryan", "let y = x + 1;")];
+
+    let err = eval(&modules, r#"let x = 1; use "prelude.ryan"; y"#).unwrap_err();
+
+    assert!(err.to_string().contains("`x`"), "{err}");
+}
+
+#[test]
+fn used_modules_work_inside_pattern_matches() {
+    let modules = [("prelude.ryan", "let double x = x * 2;")];
+
+    let value = eval(
+        &modules,
+        r#"let quadruple x = (use "prelude.ryan"; double (double x)); quadruple 3"#,
+    )
+    .unwrap();
+
+    assert_eq!(value, serde_json::json!(12));
+}
+
+#[test]
+fn the_final_expression_is_ignored_with_a_warning() {
+    let warnings = Rc::new(RefCell::new(vec![]));
+    let recorded = warnings.clone();
+    let loader = NoImport
+        .r#override("with_value.ryan".to_owned(), "let x = 1; x + 1".to_owned())
+        .r#override("with_null.ryan".to_owned(), "let y = 2; null".to_owned());
+    let env = Environment::builder()
+        .import_loader(loader)
+        .on_event(move |event| {
+            if let EvalEvent::Warning { message } = event {
+                recorded.borrow_mut().push(message.to_string());
+            }
+        })
+        .build();
+
+    let value: (i64, i64) = ryan::from_str_with_env(
+        &env,
+        r#"use "with_value.ryan"; use "with_null.ryan"; [x, y]"#,
+    )
+    .unwrap();
+
+    assert_eq!(value, (1, 2));
+    assert_eq!(
+        *warnings.borrow(),
+        ["The final expression of \"with_value.ryan\" is ignored by `use`"]
+    );
+}
+
+#[test]
+fn use_statements_are_printed_back() {
+    let block = ryan::parser::parse(r#"use "prelude.ryan"; 1"#).unwrap();
+    let printed = block.to_string();
+
+    assert_eq!(printed, "use \"prelude.ryan\";\n1");
+    assert_eq!(ryan::parser::parse(&printed).unwrap(), block);
+}
@@ -0,0 +1,157 @@
+//! Looking up keys, paths and typed values in a `Value` from Rust.
+#![cfg(feature = "parse")]
+
+mod common;
+
+use ryan::parser::Value;
+
+use common::eval;
+
+fn config() -> Value {
+    eval(
+        r#"{
+            name: "api",
+            replicas: 3,
+            ratio: 0.5,
+            enabled: true,
+            services: [{ port: 8080 }, { port: 9090, "tls.enabled": false }],
+            "weird.key": { "with \"quotes\"": 1, "[0]": 2 },
+        }"#,
+    )
+}
+
+#[test]
+fn gets_keys_of_maps_only() {
+    let config = config();
+
+    assert_eq!(config.get("name"), Some(&Value::Text("api".into())));
+    assert_eq!(config.get("missing"), None);
+    assert_eq!(config.get("services").unwrap().get("port"), None);
+    assert_eq!(Value::Null.get("name"), None);
+}
+
+#[test]
+fn follows_paths_of_keys_and_indices() {
+    let config = config();
+
+    assert_eq!(
+        config.get_path("services[1].port"),
+        Some(&Value::Integer(9090))
+    );
+    assert_eq!(
+        config.get_path(".services[0].port"),
+        Some(&Value::Integer(8080))
+    );
+    assert_eq!(config.get_path("services[2].port"), None);
+    assert_eq!(config.get_path("services.port"), None);
+    assert_eq!(config.get_path("name[0]"), None);
+    assert_eq!(config.get_path(""), Some(&config));
+    assert_eq!(config.get_path("."), Some(&config));
+}
+
+#[test]
+fn follows_quoted_keys_holding_dots_and_brackets() {
+    let config = config();
+
+    assert_eq!(
+        config.get_path(r#"services[1]["tls.enabled"]"#),
+        Some(&Value::Bool(false))
+    );
+    assert_eq!(
+        config.get_path(r#"["weird.key"]["with \"quotes\""]"#),
+        Some(&Value::Integer(1))
+    );
+    assert_eq!(
+        config.get_path(r#"["weird.key"]["[0]"]"#),
+        Some(&Value::Integer(2))
+    );
+    // Unquoted, the dot separates two keys:
+    assert_eq!(config.get_path("weird.key"), None);
+}
+
+#[test]
+fn rejects_malformed_paths() {
+    let config = config();
+
+    for path in [
+        "services[",
+        "services[x]",
+        "services[-1]",
+        "services..port",
+        "services.",
+        "..name",
+        r#"["name"#,
+        r#"["name"x]"#,
+        "services[0]port",
+    ] {
+        assert_eq!(config.get_path(path), None, "{path}");
+    }
+}
+
+#[test]
+fn typed_getters_return_values_of_their_type_only() {
+    let config = config();
+
+    assert_eq!(config.get("name").and_then(Value::as_str), Some("api"));
+    assert_eq!(config.get("replicas").and_then(Value::as_i64), Some(3));
+    assert_eq!(config.get("ratio").and_then(Value::as_f64), Some(0.5));
+    assert_eq!(config.get("replicas").and_then(Value::as_f64), Some(3.0));
+    assert_eq!(config.get("enabled").and_then(Value::as_bool), Some(true));
+    assert_eq!(
+        config
+            .get("services")
+            .and_then(Value::as_list)
+            .map(<[_]>::len),
+        Some(2)
+    );
+    assert_eq!(config.as_map().map(|map| map.len()), Some(6));
+
+    assert_eq!(config.get("ratio").and_then(Value::as_i64), None);
+    assert_eq!(config.get("name").and_then(Value::as_bool), None);
+    assert_eq!(config.as_list(), None);
+}
+
+#[test]
+fn fallible_getters_tell_what_was_found() {
+    let config = config();
+
+    assert_eq!(
+        config
+            .get_path("services[0].port")
+            .unwrap()
+            .try_as_i64()
+            .unwrap(),
+        8080
+    );
+    assert_eq!(
+        config
+            .get("name")
+            .unwrap()
+            .try_as_i64()
+            .unwrap_err()
+            .to_string(),
+        "expected a 64-bit signed integer but got value of type text"
+    );
+    assert_eq!(
+        config
+            .get("enabled")
+            .unwrap()
+            .try_as_str()
+            .unwrap_err()
+            .to_string(),
+        "expected text but got value of type bool"
+    );
+    assert_eq!(
+        config
+            .get("replicas")
+            .unwrap()
+            .try_as_list()
+            .unwrap_err()
+            .to_string(),
+        "expected a list of values but got value of type int"
+    );
+    assert_eq!(
+        Value::Null.try_as_map().unwrap_err().to_string(),
+        "expected a key-value map but got value of type null"
+    );
+}
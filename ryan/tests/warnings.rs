@@ -0,0 +1,63 @@
+//! Warnings about code that runs, but is probably not what was meant.
+#![cfg(feature = "parse")]
+
+use ryan::parser::WarningKind;
+
+/// The kind of each warning and the code it points to.
+fn warnings(source: &str) -> Vec<(WarningKind, &str)> {
+    ryan::lint(source)
+        .unwrap()
+        .into_iter()
+        .map(|warning| (warning.kind, &source[warning.span.0..warning.span.1]))
+        .collect()
+}
+
+#[test]
+fn shadowed_bindings_point_to_the_second_definition() {
+    assert_eq!(
+        warnings("let x = 1;\nlet x = 2;\nx"),
+        [
+            (WarningKind::ShadowedBinding, "let x = 2"),
+            (WarningKind::UnusedBinding, "x"),
+        ]
+    );
+}
+
+#[test]
+fn duplicate_keys_point_to_the_second_key() {
+    assert_eq!(
+        warnings("{ a: 1, b: 2, a: 3 }"),
+        [(WarningKind::DuplicateKey, "a: 3")]
+    );
+    assert_eq!(warnings("{ a: 1 if true, a: 2 if false }"), []);
+}
+
+#[test]
+fn unused_bindings_point_to_their_names() {
+    assert_eq!(
+        warnings("let unused = 1;\nlet used = 2;\nused"),
+        [(WarningKind::UnusedBinding, "unused")]
+    );
+}
+
+#[test]
+fn names_starting_with_an_underscore_are_never_unused() {
+    assert_eq!(warnings("let _ignored = 1;\nlet _ = 2;\n3"), []);
+    assert_eq!(
+        ryan::from_str::<i64>("let _x = 1; let f _ = 2; f _x + _x").unwrap(),
+        3
+    );
+}
+
+#[test]
+fn warnings_come_with_the_value() {
+    let (value, warnings) = ryan::from_str_with_warnings::<i64>("let x = 1; let x = 2; x").unwrap();
+    assert_eq!(value, 2);
+    assert_eq!(
+        warnings
+            .iter()
+            .map(|warning| warning.kind)
+            .collect::<Vec<_>>(),
+        [WarningKind::ShadowedBinding, WarningKind::UnusedBinding]
+    );
+}
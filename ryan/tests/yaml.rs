@@ -0,0 +1,191 @@
+//! Writing values as YAML, checked against expected snippets and read back by a YAML
+//! parser.
+#![cfg(all(feature = "parse", feature = "yaml"))]
+
+mod common;
+
+use common::eval;
+
+fn yaml(source: &str) -> String {
+    ryan::to_yaml_string(&eval(source)).unwrap()
+}
+
+/// Reads the YAML back and compares it with the JSON Ryan produces for the same value.
+fn assert_round_trips(source: &str) {
+    let yaml = yaml(source);
+    let from_yaml: serde_json::Value = serde_yaml::from_str(&yaml).unwrap();
+    let from_ryan: serde_json::Value = ryan::from_str(source).unwrap();
+
+    assert_eq!(from_yaml, from_ryan, "YAML was:\n{yaml}");
+}
+
+#[test]
+fn writes_nested_blocks_in_key_order() {
+    let source = r#"{
+        zeta: 1,
+        alpha: { nested: [1, [2, 3], { a: null, b: true }] },
+        empty: { list: [], dict: {} },
+    }"#;
+
+    assert_eq!(
+        yaml(source),
+        "zeta: 1
+alpha:
+  nested:
+    - 1
+    - - 2
+      - 3
+    - a: null
+      b: true
+empty:
+  list: []
+  dict: {}
+"
+    );
+    assert_round_trips(source);
+}
+
+#[test]
+fn writes_top_level_scalars_and_lists() {
+    assert_eq!(yaml("42"), "42\n");
+    assert_eq!(yaml(r#""yes""#), "\"yes\"\n");
+    assert_eq!(yaml("[]"), "[]\n");
+    assert_eq!(yaml("[1, { a: 2 }]"), "- 1\n- a: 2\n");
+}
+
+#[test]
+fn quotes_text_that_would_read_back_as_something_else() {
+    let source = r##"[
+        "no", "No", "off", "y", "null", "~", "true",
+        "1.10", "1.2.3", "0o17", "0x1F", "1_000", "1:20", "2023-01-01", ".5", ".inf", ".NaN",
+        "", " padded", "trailing ", "key: value", "# comment", "a #b", "- item", "*alias",
+        "&anchor", "!tag", "@at", "`tick`", "'single'", "\"double\"", "{a}", "[a]", "a,b",
+        "<<", "?", "ends with:", "tab\there", "back\\slash", "bell\u0007",
+    ]"##;
+
+    let yaml = yaml(source);
+    for line in yaml.lines() {
+        assert!(line.starts_with("- \""), "unquoted: {line}");
+    }
+    assert!(yaml.contains(r#"- "bell\u0007""#));
+    assert_round_trips(source);
+}
+
+#[test]
+fn leaves_plain_text_unquoted() {
+    let source = r#"[
+        "web", "nginx:1.25", "http://example.com/a?b", "v1.2.3", "hello world",
+        "a-b_c.d/e", "Norway", "nope", "ünïcödé", "$HOME",
+    ]"#;
+
+    assert_eq!(
+        yaml(source),
+        "- web
+- nginx:1.25
+- \"http://example.com/a?b\"
+- v1.2.3
+- hello world
+- a-b_c.d/e
+- Norway
+- nope
+- ünïcödé
+- \"$HOME\"
+"
+    );
+    assert_round_trips(source);
+}
+
+#[test]
+fn writes_multi_line_text_as_literal_blocks() {
+    let source = r#"{
+        clip: "a\nb\n",
+        strip: "a\nb",
+        keep: "a\n\n",
+        blank_lines: "a\n\nb\n",
+        in_list: ["x\ny\n"],
+        indented: " a\nb",
+        "multi\nline key": 1,
+    }"#;
+
+    assert_eq!(
+        yaml(source),
+        r#"clip: |
+  a
+  b
+strip: |-
+  a
+  b
+keep: |+
+  a
+
+blank_lines: |
+  a
+
+  b
+in_list:
+  - |
+    x
+    y
+indented: " a\nb"
+"multi\nline key": 1
+"#
+    );
+    assert_round_trips(source);
+}
+
+#[test]
+fn writes_top_level_literal_blocks_indented() {
+    assert_eq!(yaml(r#""---\n...\n""#), "|\n  ---\n  ...\n");
+    assert_round_trips(r#""---\n...\n""#);
+}
+
+#[test]
+fn writes_floats_that_read_back_as_floats() {
+    let source = "[1.0, -0.5, 1e21, 1.5e-8, 123456.789]";
+
+    assert_eq!(
+        yaml(source),
+        "- 1.0\n- -0.5\n- 1.0e+21\n- 1.5e-8\n- 123456.789\n"
+    );
+    assert_round_trips(source);
+}
+
+#[test]
+fn never_writes_anchors_for_shared_values() {
+    let source = r#"
+        let labels = { app: "web" };
+        let ports = [80, 443];
+        { a: { labels, ports }, b: { labels, ports } }
+    "#;
+
+    let yaml = yaml(source);
+    assert!(!yaml.contains('&') && !yaml.contains('*'));
+    assert_eq!(
+        yaml,
+        "a:
+  labels:
+    app: web
+  ports:
+    - 80
+    - 443
+b:
+  labels:
+    app: web
+  ports:
+    - 80
+    - 443
+"
+    );
+    assert_round_trips(source);
+}
+
+#[test]
+fn reports_values_yaml_cannot_represent() {
+    let value = eval("let f x = x; { helpers: [f] }");
+    let err = ryan::to_yaml_string(&value).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "The result contains the unapplied pattern `f` at .helpers[0]. Did you forget to apply it?"
+    );
+}